@@ -0,0 +1,104 @@
+//! Merge cost of Bybit order book deltas: the previous `Vec` levels, scanned and
+//! re-sorted after every delta, against the `BTreeMap` levels of `market::OrderBook`.
+//!
+//! Run with `cargo bench --bench orderbook_merge`.
+
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use rust_decimal::Decimal;
+use zero_r::models::market::{OrderBook, OrderBookItem};
+
+const BOOK_DEPTH: usize = 50;
+const DELTAS: usize = 1000;
+
+/// Bid levels of a delta: updates inside the book, new levels and removals
+fn deltas() -> Vec<Vec<(String, String)>> {
+    (0..DELTAS)
+        .map(|i| {
+            let level = (i * 7) % (BOOK_DEPTH + 10);
+            let price = format!("{}.{:02}", 100 - level / 100, 99 - level % 100);
+            let volume = if i % 5 == 0 {
+                "0".to_string()
+            } else {
+                format!("{}.5", i % 13)
+            };
+            vec![(price, volume)]
+        })
+        .collect()
+}
+
+fn snapshot() -> Vec<(String, String)> {
+    (0..BOOK_DEPTH)
+        .map(|level| (format!("100.{:02}", 99 - level), "1.0".to_string()))
+        .collect()
+}
+
+/// The `Vec` merge `OrderBook` used before: linear lookup, then a full sort
+fn merge_vec(items: &mut Vec<OrderBookItem>, price: &str, volume: &str) {
+    let price_dec = price.parse::<Decimal>().unwrap();
+    if volume == "0" {
+        items.retain(|item| item.price != price_dec);
+    } else {
+        let volume_dec = volume.parse::<Decimal>().unwrap();
+        if let Some(item) = items.iter_mut().find(|item| item.price == price_dec) {
+            item.volume = volume_dec;
+        } else {
+            items.push(OrderBookItem {
+                price: price_dec,
+                volume: volume_dec,
+            });
+        }
+    }
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let snapshot = snapshot();
+    let deltas = deltas();
+    let mut group = c.benchmark_group("orderbook_merge_50_levels_1000_deltas");
+
+    group.bench_function("vec", |b| {
+        b.iter_batched(
+            || {
+                snapshot
+                    .iter()
+                    .map(|(price, volume)| OrderBookItem::new(price, volume))
+                    .collect::<Vec<_>>()
+            },
+            |mut bids| {
+                for delta in &deltas {
+                    for (price, volume) in delta {
+                        merge_vec(&mut bids, price, volume);
+                    }
+                    bids.sort_by(|a, b| b.price.cmp(&a.price));
+                }
+                black_box(bids)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("btree_map", |b| {
+        b.iter_batched(
+            || {
+                let mut book = OrderBook::new("bybit", "BENCH");
+                for (price, volume) in &snapshot {
+                    OrderBook::merge_item(&mut book.bids, price, volume);
+                }
+                book
+            },
+            |mut book| {
+                for delta in &deltas {
+                    for (price, volume) in delta {
+                        OrderBook::merge_item(&mut book.bids, price, volume);
+                    }
+                }
+                black_box(book)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_merge);
+criterion_main!(benches);
@@ -1,3 +1,10 @@
+-- Initial schema, src/store/init.sql as of the first release, statement for statement.
+-- Every statement is idempotent so that databases created by that init.sql apply it as their
+-- baseline: their tables are left untouched and the migration is then recorded in
+-- `_sqlx_migrations` like on a fresh database, after which the later migrations add what the
+-- schema gained since. Schema changes go into new migration files; editing this one changes
+-- its checksum and fails startup on every database that already applied it.
+
 CREATE TABLE IF NOT EXISTS `cex_markets` (
   `id` BIGINT NOT NULL AUTO_INCREMENT,
   `trade_id` VARCHAR(128) NOT NULL,
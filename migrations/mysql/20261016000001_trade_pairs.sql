@@ -0,0 +1,17 @@
+-- Pairs quoted per venue, seeded with the Meteora TRUMP/USDC pool
+CREATE TABLE `trade_pairs` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `symbol` VARCHAR(64) NOT NULL,
+  `venue` VARCHAR(64) NOT NULL,
+  `pool_pubkey` VARCHAR(64) NOT NULL,
+  `precision` INT UNSIGNED NOT NULL,
+  `base_is_x` BOOLEAN NOT NULL DEFAULT FALSE,
+  `enabled` BOOLEAN NOT NULL DEFAULT TRUE,
+  `created_at` DATETIME(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `idx_trade_pairs_venue_symbol_pool` (`venue`, `symbol`, `pool_pubkey`),
+  KEY `idx_trade_pairs_venue_enabled` (`venue`, `enabled`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+INSERT IGNORE INTO `trade_pairs` (`symbol`, `venue`, `pool_pubkey`, `precision`, `base_is_x`)
+VALUES ('TRUMPUSDC', 'meteora', '9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2', 6, FALSE);
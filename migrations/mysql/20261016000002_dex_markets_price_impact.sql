@@ -0,0 +1,3 @@
+-- Price impact in bps of a DEX quote against the spot price of the active bin, NULL when
+-- not computed
+ALTER TABLE `dex_markets` ADD COLUMN `price_impact_bps` DECIMAL(16,4) NULL;
@@ -0,0 +1,2 @@
+-- Pool a DEX quote was read from, NULL for quotes not tied to one pool
+ALTER TABLE `dex_markets` ADD COLUMN `pool_address` VARCHAR(64) NULL;
@@ -0,0 +1,7 @@
+-- Pairs whose pools are discovered through the Meteora API, by their mints, have no pool
+-- pubkey of their own
+ALTER TABLE `trade_pairs`
+  MODIFY COLUMN `pool_pubkey` VARCHAR(64) NOT NULL DEFAULT '',
+  ADD COLUMN `base_mint` VARCHAR(64) NULL,
+  ADD COLUMN `quote_mint` VARCHAR(64) NULL,
+  ADD COLUMN `auto_discover` BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,15 @@
+-- Sampled DEX quotes cross-checked against simulated swaps
+CREATE TABLE `dex_quote_checks` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `pool_address` VARCHAR(64) NOT NULL,
+  `block_number` BIGINT UNSIGNED NOT NULL,
+  `amount_in` BIGINT UNSIGNED NOT NULL,
+  `local_amount_out` BIGINT UNSIGNED NOT NULL,
+  `simulated_amount_out` BIGINT UNSIGNED NOT NULL,
+  `deviation_bps` DECIMAL(16,4) NOT NULL,
+  `check_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_quote_checks_exchange_pair_ts` (`exchange`, `trade_pair`, `check_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
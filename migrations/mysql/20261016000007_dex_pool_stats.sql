@@ -0,0 +1,15 @@
+-- Liquidity of the DEX pools quoted
+CREATE TABLE `dex_pool_stats` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `pool_address` VARCHAR(64) NOT NULL,
+  `block_number` BIGINT UNSIGNED NOT NULL,
+  `base_amount` DECIMAL(32,16) NOT NULL,
+  `quote_amount` DECIMAL(32,16) NOT NULL,
+  `liquidity` DECIMAL(32,16) NULL,
+  `degraded` BOOLEAN NOT NULL DEFAULT FALSE,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_pool_stats_exchange_pair_ts` (`exchange`, `trade_pair`, `fetch_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,2 @@
+-- Time taken to fetch the accounts a DEX quote was computed from, NULL when not measured
+ALTER TABLE `dex_markets` ADD COLUMN `fetch_latency_ms` BIGINT UNSIGNED NULL;
@@ -0,0 +1,8 @@
+-- Multi-hop routes: the route a DEX quote went through, and the second pool of a pair quoted
+-- through an intermediate token
+ALTER TABLE `dex_markets` ADD COLUMN `route` VARCHAR(64) NULL;
+
+ALTER TABLE `trade_pairs`
+  ADD COLUMN `route` VARCHAR(64) NULL,
+  ADD COLUMN `route_pool_pubkey` VARCHAR(64) NULL,
+  ADD COLUMN `route_base_is_x` BOOLEAN NOT NULL DEFAULT FALSE;
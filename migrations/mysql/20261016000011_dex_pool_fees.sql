@@ -0,0 +1,14 @@
+-- Fee rates of the DLMM pools quoted
+CREATE TABLE `dex_pool_fees` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `pool_address` VARCHAR(64) NOT NULL,
+  `block_number` BIGINT UNSIGNED NOT NULL,
+  `base_fee_bps` DECIMAL(16,6) NOT NULL,
+  `variable_fee_bps` DECIMAL(16,6) NOT NULL,
+  `total_fee_bps` DECIMAL(16,6) NOT NULL,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_pool_fees_pool_ts` (`pool_address`, `fetch_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,18 @@
+-- 24h ticker statistics of CEX pairs
+CREATE TABLE `cex_tickers` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `last_price` DECIMAL(32,16) NOT NULL,
+  `high_price_24h` DECIMAL(32,16) NOT NULL,
+  `low_price_24h` DECIMAL(32,16) NOT NULL,
+  `prev_price_24h` DECIMAL(32,16) NOT NULL,
+  `volume_24h` DECIMAL(32,16) NOT NULL,
+  `turnover_24h` DECIMAL(32,16) NOT NULL,
+  `price_change_24h` DECIMAL(16,8) NOT NULL,
+  `usd_index_price` DECIMAL(32,16) NULL,
+  `trade_timestamp` DATETIME(6) NOT NULL,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_tickers_exchange_symbol_ts` (`exchange`, `trade_pair`, `trade_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
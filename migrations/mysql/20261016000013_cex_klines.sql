@@ -0,0 +1,17 @@
+-- CEX candles, upserted until confirmed
+CREATE TABLE `cex_klines` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `interval` VARCHAR(16) NOT NULL,
+  `open_time` DATETIME(6) NOT NULL,
+  `open` DECIMAL(32,16) NOT NULL,
+  `high` DECIMAL(32,16) NOT NULL,
+  `low` DECIMAL(32,16) NOT NULL,
+  `close` DECIMAL(32,16) NOT NULL,
+  `volume` DECIMAL(32,16) NOT NULL,
+  `confirmed` BOOLEAN NOT NULL DEFAULT FALSE,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `uniq_klines_exchange_symbol_interval_open` (`exchange`, `trade_pair`, `interval`, `open_time`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,3 @@
+-- Delay between the exchange timestamp of a CEX state and local receipt of the message,
+-- NULL when the exchange does not send one
+ALTER TABLE `cex_markets` ADD COLUMN `feed_latency_ms` BIGINT NULL;
@@ -0,0 +1,9 @@
+-- Volume within 5, 10 and 25 bps of the top of each side of the book, NULL for venues
+-- without a full book
+ALTER TABLE `cex_markets`
+  ADD COLUMN `bid_depth_5bps` DECIMAL(32,16) NULL,
+  ADD COLUMN `ask_depth_5bps` DECIMAL(32,16) NULL,
+  ADD COLUMN `bid_depth_10bps` DECIMAL(32,16) NULL,
+  ADD COLUMN `ask_depth_10bps` DECIMAL(32,16) NULL,
+  ADD COLUMN `bid_depth_25bps` DECIMAL(32,16) NULL,
+  ADD COLUMN `ask_depth_25bps` DECIMAL(32,16) NULL;
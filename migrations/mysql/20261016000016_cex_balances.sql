@@ -0,0 +1,12 @@
+-- Balances of our CEX accounts
+CREATE TABLE `cex_balances` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `coin` VARCHAR(32) NOT NULL,
+  `free` DECIMAL(32,16) NOT NULL,
+  `locked` DECIMAL(32,16) NOT NULL,
+  `update_timestamp` DATETIME(6) NOT NULL,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_balances_exchange_coin_ts` (`exchange`, `coin`, `update_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
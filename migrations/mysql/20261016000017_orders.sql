@@ -0,0 +1,21 @@
+-- Orders placed on CEXs, keyed by their client order id
+CREATE TABLE `orders` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `order_link_id` VARCHAR(64) NOT NULL,
+  `order_id` VARCHAR(64) NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `side` VARCHAR(8) NOT NULL,
+  `order_type` VARCHAR(16) NOT NULL,
+  `price` DECIMAL(32,16) NULL,
+  `qty` DECIMAL(32,16) NOT NULL,
+  `status` VARCHAR(32) NOT NULL,
+  `cum_exec_qty` DECIMAL(32,16) NOT NULL,
+  `avg_price` DECIMAL(32,16) NULL,
+  `reject_reason` VARCHAR(255) NULL,
+  `create_timestamp` DATETIME(6) NOT NULL,
+  `update_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `uniq_orders_exchange_link_id` (`exchange`, `order_link_id`),
+  KEY `idx_orders_status` (`status`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,11 @@
+-- Maker and taker fee snapshots of CEX pairs
+CREATE TABLE `cex_fees` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `maker_fee_bps` DECIMAL(16,6) NOT NULL,
+  `taker_fee_bps` DECIMAL(16,6) NOT NULL,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_cex_fees_pair_ts` (`exchange`, `trade_pair`, `fetch_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,11 @@
+-- Truncated CEX order books, stored as JSON levels
+CREATE TABLE `cex_orderbook_snapshots` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `exchange` VARCHAR(64) NOT NULL,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `snapshot_time` DATETIME(6) NOT NULL,
+  `bids_json` MEDIUMTEXT NOT NULL,
+  `asks_json` MEDIUMTEXT NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_orderbook_snapshots_exchange_symbol_time` (`exchange`, `trade_pair`, `snapshot_time`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,2 @@
+-- Venues of the route an aggregator quote went through, NULL for direct pool quotes
+ALTER TABLE `dex_markets` ADD COLUMN `route_plan` VARCHAR(512) NULL;
@@ -0,0 +1,12 @@
+-- Token balances of our Solana wallets per mint and slot
+CREATE TABLE `onchain_balances` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `wallet` VARCHAR(44) NOT NULL,
+  `mint` VARCHAR(44) NOT NULL,
+  `symbol` VARCHAR(32) NULL,
+  `amount` DECIMAL(38,18) NOT NULL,
+  `slot` BIGINT UNSIGNED NOT NULL,
+  `fetch_timestamp` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_onchain_balances_wallet_mint_ts` (`wallet`, `mint`, `fetch_timestamp`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
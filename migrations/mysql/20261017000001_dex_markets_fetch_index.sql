@@ -0,0 +1,2 @@
+-- Latest-row reads of a pair and direction go through its rows ordered by fetch time
+CREATE INDEX `idx_dex_markets_pair_fetch_ts` ON `dex_markets` (`exchange`, `trade_pair`, `direction`, `fetch_timestamp`);
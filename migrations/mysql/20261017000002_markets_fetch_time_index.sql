@@ -0,0 +1,4 @@
+-- Pages over every pair walk the rows in (fetch_timestamp, id) order, the primary key being
+-- the implicit last column of a secondary index
+CREATE INDEX `idx_cex_markets_fetch_ts` ON `cex_markets` (`fetch_timestamp`);
+CREATE INDEX `idx_dex_markets_fetch_ts` ON `dex_markets` (`fetch_timestamp`);
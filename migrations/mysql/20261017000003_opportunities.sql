@@ -0,0 +1,22 @@
+-- Arbitrage opportunities seen by the engine. Deleting a market row they were priced from
+-- only clears the reference.
+CREATE TABLE `opportunities` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `buy_venue` VARCHAR(64) NOT NULL,
+  `sell_venue` VARCHAR(64) NOT NULL,
+  `buy_price` DECIMAL(32,16) NOT NULL,
+  `sell_price` DECIMAL(32,16) NOT NULL,
+  `max_size` DECIMAL(32,16) NOT NULL,
+  `gross_spread_bps` DECIMAL(16,4) NOT NULL,
+  `net_spread_bps` DECIMAL(16,4) NOT NULL,
+  `cex_state_id` BIGINT NULL,
+  `dex_state_id` BIGINT NULL,
+  `detected_at` DATETIME(6) NOT NULL,
+  `status` ENUM('detected', 'expired', 'acted') NOT NULL DEFAULT 'detected',
+  PRIMARY KEY (`id`),
+  KEY `idx_opportunities_detected_at` (`detected_at`),
+  KEY `idx_opportunities_status_detected_at` (`status`, `detected_at`),
+  CONSTRAINT `fk_opportunities_cex_state` FOREIGN KEY (`cex_state_id`) REFERENCES `cex_markets` (`id`) ON DELETE SET NULL,
+  CONSTRAINT `fk_opportunities_dex_state` FOREIGN KEY (`dex_state_id`) REFERENCES `dex_markets` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
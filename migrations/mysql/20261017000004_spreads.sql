@@ -0,0 +1,19 @@
+-- Directional spreads between the latest CEX and DEX states of a pair, recorded every tick
+-- by the spread recorder. Ages are those of the states at computation time.
+CREATE TABLE `spreads` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT,
+  `trade_pair` VARCHAR(64) NOT NULL,
+  `cex_exchange` VARCHAR(64) NOT NULL,
+  `dex_exchange` VARCHAR(64) NOT NULL,
+  `cex_bid` DECIMAL(32,16) NOT NULL,
+  `cex_ask` DECIMAL(32,16) NOT NULL,
+  `dex_bid` DECIMAL(32,16) NOT NULL,
+  `dex_ask` DECIMAL(32,16) NOT NULL,
+  `spread_buy_dex_sell_cex_bps` DECIMAL(16,4) NOT NULL,
+  `spread_buy_cex_sell_dex_bps` DECIMAL(16,4) NOT NULL,
+  `cex_age_ms` BIGINT UNSIGNED NOT NULL,
+  `dex_age_ms` BIGINT UNSIGNED NOT NULL,
+  `computed_at` DATETIME(6) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_spreads_pair_computed_at` (`trade_pair`, `computed_at`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
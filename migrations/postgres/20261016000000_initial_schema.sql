@@ -0,0 +1,36 @@
+-- Initial schema, the Postgres counterpart of mysql/20261016000000_initial_schema.sql.
+-- Postgres has no unsigned integers: unsigned columns are BIGINT, which the store binds and
+-- reads as i64. Index names are unique per schema here, not per table, so the ones MySQL
+-- shares between tables are prefixed with their table.
+-- Schema changes go into new migration files in both directories, with the same version.
+
+CREATE TABLE IF NOT EXISTS cex_markets (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  trade_id VARCHAR(128) NOT NULL,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  bid_price NUMERIC(32,16) NOT NULL,
+  bid_volume NUMERIC(32,16) NOT NULL,
+  ask_price NUMERIC(32,16) NOT NULL,
+  ask_volume NUMERIC(32,16) NOT NULL,
+  trade_timestamp TIMESTAMPTZ NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL,
+  CONSTRAINT uniq_cex_markets_trade_id_exchange UNIQUE (trade_id, exchange)
+);
+CREATE INDEX IF NOT EXISTS idx_cex_markets_exchange_symbol_ts ON cex_markets (exchange, trade_pair, trade_timestamp);
+
+CREATE TABLE IF NOT EXISTS dex_markets (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  trade_id VARCHAR(128) NOT NULL,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  direction VARCHAR(16) NOT NULL,
+  volume NUMERIC(32,16) NOT NULL,
+  price NUMERIC(32,16) NOT NULL,
+  trade_timestamp TIMESTAMPTZ NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL,
+  block_number BIGINT NOT NULL,
+  CONSTRAINT uniq_dex_markets_trade_id_exchange UNIQUE (trade_id, exchange)
+);
+CREATE INDEX IF NOT EXISTS idx_dex_markets_exchange_symbol_ts ON dex_markets (exchange, trade_pair, trade_timestamp);
+CREATE INDEX IF NOT EXISTS idx_dex_markets_direction ON dex_markets (direction);
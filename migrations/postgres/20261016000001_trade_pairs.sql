@@ -0,0 +1,17 @@
+-- Pairs quoted per venue, seeded with the Meteora TRUMP/USDC pool
+CREATE TABLE trade_pairs (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  symbol VARCHAR(64) NOT NULL,
+  venue VARCHAR(64) NOT NULL,
+  pool_pubkey VARCHAR(64) NOT NULL,
+  "precision" BIGINT NOT NULL,
+  base_is_x BOOLEAN NOT NULL DEFAULT FALSE,
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT uniq_trade_pairs_venue_symbol_pool UNIQUE (venue, symbol, pool_pubkey)
+);
+CREATE INDEX idx_trade_pairs_venue_enabled ON trade_pairs (venue, enabled);
+
+INSERT INTO trade_pairs (symbol, venue, pool_pubkey, "precision", base_is_x)
+VALUES ('TRUMPUSDC', 'meteora', '9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2', 6, FALSE)
+ON CONFLICT DO NOTHING;
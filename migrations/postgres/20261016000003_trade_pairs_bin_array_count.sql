@@ -0,0 +1,2 @@
+-- Bin arrays read on each side of the active one when quoting a DLMM pool
+ALTER TABLE trade_pairs ADD COLUMN bin_array_count BIGINT NOT NULL DEFAULT 4;
@@ -0,0 +1,7 @@
+-- Pairs whose pools are discovered through the Meteora API, by their mints, have no pool
+-- pubkey of their own
+ALTER TABLE trade_pairs
+  ALTER COLUMN pool_pubkey SET DEFAULT '',
+  ADD COLUMN base_mint VARCHAR(64) NULL,
+  ADD COLUMN quote_mint VARCHAR(64) NULL,
+  ADD COLUMN auto_discover BOOLEAN NOT NULL DEFAULT FALSE;
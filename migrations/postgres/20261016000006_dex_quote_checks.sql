@@ -0,0 +1,14 @@
+-- Sampled DEX quotes cross-checked against simulated swaps
+CREATE TABLE dex_quote_checks (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  pool_address VARCHAR(64) NOT NULL,
+  block_number BIGINT NOT NULL,
+  amount_in BIGINT NOT NULL,
+  local_amount_out BIGINT NOT NULL,
+  simulated_amount_out BIGINT NOT NULL,
+  deviation_bps NUMERIC(16,4) NOT NULL,
+  check_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_quote_checks_exchange_pair_ts ON dex_quote_checks (exchange, trade_pair, check_timestamp);
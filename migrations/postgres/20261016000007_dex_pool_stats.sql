@@ -0,0 +1,14 @@
+-- Liquidity of the DEX pools quoted
+CREATE TABLE dex_pool_stats (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  pool_address VARCHAR(64) NOT NULL,
+  block_number BIGINT NOT NULL,
+  base_amount NUMERIC(32,16) NOT NULL,
+  quote_amount NUMERIC(32,16) NOT NULL,
+  liquidity NUMERIC(32,16) NULL,
+  degraded BOOLEAN NOT NULL DEFAULT FALSE,
+  fetch_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_pool_stats_exchange_pair_ts ON dex_pool_stats (exchange, trade_pair, fetch_timestamp);
@@ -0,0 +1,2 @@
+-- DEX quotes read while the Clock sysvar drifted from wall time
+ALTER TABLE dex_markets ADD COLUMN stale BOOLEAN NOT NULL DEFAULT FALSE;
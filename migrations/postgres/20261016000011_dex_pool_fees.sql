@@ -0,0 +1,13 @@
+-- Fee rates of the DLMM pools quoted
+CREATE TABLE dex_pool_fees (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  pool_address VARCHAR(64) NOT NULL,
+  block_number BIGINT NOT NULL,
+  base_fee_bps NUMERIC(16,6) NOT NULL,
+  variable_fee_bps NUMERIC(16,6) NOT NULL,
+  total_fee_bps NUMERIC(16,6) NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_pool_fees_pool_ts ON dex_pool_fees (pool_address, fetch_timestamp);
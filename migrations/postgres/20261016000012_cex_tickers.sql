@@ -0,0 +1,17 @@
+-- 24h ticker statistics of CEX pairs
+CREATE TABLE cex_tickers (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  last_price NUMERIC(32,16) NOT NULL,
+  high_price_24h NUMERIC(32,16) NOT NULL,
+  low_price_24h NUMERIC(32,16) NOT NULL,
+  prev_price_24h NUMERIC(32,16) NOT NULL,
+  volume_24h NUMERIC(32,16) NOT NULL,
+  turnover_24h NUMERIC(32,16) NOT NULL,
+  price_change_24h NUMERIC(16,8) NOT NULL,
+  usd_index_price NUMERIC(32,16) NULL,
+  trade_timestamp TIMESTAMPTZ NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_tickers_exchange_symbol_ts ON cex_tickers (exchange, trade_pair, trade_timestamp);
@@ -0,0 +1,16 @@
+-- CEX candles, upserted until confirmed
+CREATE TABLE cex_klines (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  "interval" VARCHAR(16) NOT NULL,
+  open_time TIMESTAMPTZ NOT NULL,
+  "open" NUMERIC(32,16) NOT NULL,
+  high NUMERIC(32,16) NOT NULL,
+  low NUMERIC(32,16) NOT NULL,
+  "close" NUMERIC(32,16) NOT NULL,
+  volume NUMERIC(32,16) NOT NULL,
+  confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+  fetch_timestamp TIMESTAMPTZ NOT NULL,
+  CONSTRAINT uniq_klines_exchange_symbol_interval_open UNIQUE (exchange, trade_pair, "interval", open_time)
+);
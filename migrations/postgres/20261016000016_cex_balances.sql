@@ -0,0 +1,11 @@
+-- Balances of our CEX accounts
+CREATE TABLE cex_balances (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  coin VARCHAR(32) NOT NULL,
+  free NUMERIC(32,16) NOT NULL,
+  locked NUMERIC(32,16) NOT NULL,
+  update_timestamp TIMESTAMPTZ NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_balances_exchange_coin_ts ON cex_balances (exchange, coin, update_timestamp);
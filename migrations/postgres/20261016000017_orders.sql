@@ -0,0 +1,20 @@
+-- Orders placed on CEXs, keyed by their client order id
+CREATE TABLE orders (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  order_link_id VARCHAR(64) NOT NULL,
+  order_id VARCHAR(64) NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  side VARCHAR(8) NOT NULL,
+  order_type VARCHAR(16) NOT NULL,
+  price NUMERIC(32,16) NULL,
+  qty NUMERIC(32,16) NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  cum_exec_qty NUMERIC(32,16) NOT NULL,
+  avg_price NUMERIC(32,16) NULL,
+  reject_reason VARCHAR(255) NULL,
+  create_timestamp TIMESTAMPTZ NOT NULL,
+  update_timestamp TIMESTAMPTZ NOT NULL,
+  CONSTRAINT uniq_orders_exchange_link_id UNIQUE (exchange, order_link_id)
+);
+CREATE INDEX idx_orders_status ON orders (status);
@@ -0,0 +1,10 @@
+-- Maker and taker fee snapshots of CEX pairs
+CREATE TABLE cex_fees (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  maker_fee_bps NUMERIC(16,6) NOT NULL,
+  taker_fee_bps NUMERIC(16,6) NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_cex_fees_pair_ts ON cex_fees (exchange, trade_pair, fetch_timestamp);
@@ -0,0 +1,10 @@
+-- Truncated CEX order books, stored as JSON levels
+CREATE TABLE cex_orderbook_snapshots (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  exchange VARCHAR(64) NOT NULL,
+  trade_pair VARCHAR(64) NOT NULL,
+  snapshot_time TIMESTAMPTZ NOT NULL,
+  bids_json TEXT NOT NULL,
+  asks_json TEXT NOT NULL
+);
+CREATE INDEX idx_orderbook_snapshots_exchange_symbol_time ON cex_orderbook_snapshots (exchange, trade_pair, snapshot_time);
@@ -0,0 +1,11 @@
+-- Token balances of our Solana wallets per mint and slot
+CREATE TABLE onchain_balances (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  wallet VARCHAR(44) NOT NULL,
+  mint VARCHAR(44) NOT NULL,
+  symbol VARCHAR(32) NULL,
+  amount NUMERIC(38,18) NOT NULL,
+  slot BIGINT NOT NULL,
+  fetch_timestamp TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_onchain_balances_wallet_mint_ts ON onchain_balances (wallet, mint, fetch_timestamp);
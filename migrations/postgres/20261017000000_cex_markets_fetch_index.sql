@@ -0,0 +1,2 @@
+-- Filtered and latest-row reads of a pair go through its rows ordered by fetch time
+CREATE INDEX idx_cex_markets_pair_fetch_ts ON cex_markets (exchange, trade_pair, fetch_timestamp);
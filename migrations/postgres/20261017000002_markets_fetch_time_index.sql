@@ -0,0 +1,3 @@
+-- Pages over every pair walk the rows in (fetch_timestamp, id) order
+CREATE INDEX idx_cex_markets_fetch_ts ON cex_markets (fetch_timestamp, id);
+CREATE INDEX idx_dex_markets_fetch_ts ON dex_markets (fetch_timestamp, id);
@@ -0,0 +1,20 @@
+-- Arbitrage opportunities seen by the engine. Deleting a market row they were priced from
+-- only clears the reference.
+CREATE TABLE opportunities (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  trade_pair VARCHAR(64) NOT NULL,
+  buy_venue VARCHAR(64) NOT NULL,
+  sell_venue VARCHAR(64) NOT NULL,
+  buy_price NUMERIC(32,16) NOT NULL,
+  sell_price NUMERIC(32,16) NOT NULL,
+  max_size NUMERIC(32,16) NOT NULL,
+  gross_spread_bps NUMERIC(16,4) NOT NULL,
+  net_spread_bps NUMERIC(16,4) NOT NULL,
+  cex_state_id BIGINT NULL REFERENCES cex_markets (id) ON DELETE SET NULL,
+  dex_state_id BIGINT NULL REFERENCES dex_markets (id) ON DELETE SET NULL,
+  detected_at TIMESTAMPTZ NOT NULL,
+  -- A check rather than an enum type, so the status binds as plain text like on MySQL
+  status VARCHAR(16) NOT NULL DEFAULT 'detected' CHECK (status IN ('detected', 'expired', 'acted'))
+);
+CREATE INDEX idx_opportunities_detected_at ON opportunities (detected_at);
+CREATE INDEX idx_opportunities_status_detected_at ON opportunities (status, detected_at);
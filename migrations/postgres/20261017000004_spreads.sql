@@ -0,0 +1,18 @@
+-- Directional spreads between the latest CEX and DEX states of a pair, recorded every tick
+-- by the spread recorder. Ages are those of the states at computation time.
+CREATE TABLE spreads (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  trade_pair VARCHAR(64) NOT NULL,
+  cex_exchange VARCHAR(64) NOT NULL,
+  dex_exchange VARCHAR(64) NOT NULL,
+  cex_bid NUMERIC(32,16) NOT NULL,
+  cex_ask NUMERIC(32,16) NOT NULL,
+  dex_bid NUMERIC(32,16) NOT NULL,
+  dex_ask NUMERIC(32,16) NOT NULL,
+  spread_buy_dex_sell_cex_bps NUMERIC(16,4) NOT NULL,
+  spread_buy_cex_sell_dex_bps NUMERIC(16,4) NOT NULL,
+  cex_age_ms BIGINT NOT NULL,
+  dex_age_ms BIGINT NOT NULL,
+  computed_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_spreads_pair_computed_at ON spreads (trade_pair, computed_at);
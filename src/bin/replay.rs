@@ -0,0 +1,45 @@
+//! Replay a Bybit raw capture through the order book handling, offline.
+//!
+//! Usage: `cargo run --bin replay -- logs/capture/bybit-raw.2026-01-01-00.jsonl`
+
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
+use zero_r::screeners::bybit::replay_capture;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .init();
+
+    let path = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .ok_or("usage: replay <capture file>")?;
+    let outcome = replay_capture(&path).await?;
+
+    for (symbol, book) in &outcome.books {
+        let best = |item: Option<zero_r::models::market::OrderBookItem>| {
+            item.map_or_else(
+                || "-".to_string(),
+                |item| format!("{} x {}", item.price, item.volume),
+            )
+        };
+        println!(
+            "{}: {} bids, {} asks, best bid {}, best ask {}",
+            symbol,
+            book.bids.len(),
+            book.asks.len(),
+            best(book.best_bid()),
+            best(book.best_ask())
+        );
+    }
+    println!(
+        "{} order book frames applied, {} other frames skipped, {} resyncs",
+        outcome.applied, outcome.skipped, outcome.resyncs
+    );
+    println!("digest {}", outcome.digest());
+    Ok(())
+}
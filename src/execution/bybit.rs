@@ -0,0 +1,443 @@
+use chrono::Utc;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::models::order::Order;
+use crate::screeners::bybit::BybitEnv;
+use crate::screeners::bybit_instruments::{BybitInstruments, InstrumentInfo};
+use crate::screeners::bybit_private::{OrderEvent, PrivateCredentials, order_event};
+use crate::screeners::bybit_rest::{
+    BybitRestClient, BybitRestError, DEFAULT_RECV_WINDOW_MS, RestResult,
+};
+use crate::solana::retry::RetryPolicy;
+use crate::store::db::DatabasePool;
+use crate::store::orders::upsert_order;
+
+/// Default timeout of a single request
+const DEFAULT_ORDER_TIMEOUT_MS: u64 = 5_000;
+/// Default attempts per request, including the first one
+const DEFAULT_ORDER_MAX_ATTEMPTS: u32 = 3;
+/// Longest client order id Bybit accepts
+const MAX_ORDER_LINK_ID_LEN: usize = 36;
+/// `retCode`s of an order whose `orderLinkId` is already taken, for unified and classic accounts
+const DUPLICATE_ORDER_CODES: [i64; 2] = [110072, 170141];
+/// Status of an order whose placement is in flight
+const PENDING_STATUS: &str = "Pending";
+
+/// Side of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Buy => "Buy",
+            Self::Sell => "Sell",
+        }
+    }
+}
+
+/// Spot limit order, good till cancelled
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitOrder {
+    pub symbol: String,
+    pub side: Side,
+    /// Base quantity, rounded down to the lot step
+    pub qty: Decimal,
+    /// Limit price, rounded onto the tick grid in the caller's favour
+    pub price: Decimal,
+    /// Client order id; retrying with the same id never places a second order
+    pub order_link_id: String,
+}
+
+/// Spot market order, sized in the base coin
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketOrder {
+    pub symbol: String,
+    pub side: Side,
+    /// Base quantity, rounded down to the lot step
+    pub qty: Decimal,
+    /// Client order id; retrying with the same id never places a second order
+    pub order_link_id: String,
+}
+
+/// Order accepted by Bybit, with the quantity and price actually sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedOrder {
+    pub order_id: String,
+    pub order_link_id: String,
+    pub qty: Decimal,
+    pub price: Option<Decimal>,
+}
+
+impl InstrumentInfo {
+    /// Round a limit price onto the tick grid without paying more or receiving less than asked:
+    /// buys round down, sells round up
+    pub fn round_price(&self, side: Side, price: Decimal) -> Decimal {
+        let strategy = match side {
+            Side::Buy => RoundingStrategy::ToZero,
+            Side::Sell => RoundingStrategy::AwayFromZero,
+        };
+        self.round_price_to_tick(price, strategy)
+    }
+
+    /// Rounded quantity, or why it cannot be ordered
+    fn checked_qty(&self, qty: Decimal) -> Result<Decimal, String> {
+        let rounded = self.round_qty_to_step(qty);
+        if rounded < self.min_qty || rounded.is_zero() {
+            return Err(format!(
+                "quantity {} rounds to {}, below the minimum {}",
+                qty, rounded, self.min_qty
+            ));
+        }
+        if rounded > self.max_qty {
+            return Err(format!(
+                "quantity {} is above the maximum {}",
+                rounded, self.max_qty
+            ));
+        }
+        Ok(rounded)
+    }
+
+    /// Rounded quantity and price of a limit order, or why it cannot be placed
+    pub fn limit_order(
+        &self,
+        side: Side,
+        qty: Decimal,
+        price: Decimal,
+    ) -> Result<(Decimal, Decimal), String> {
+        let price = self.round_price(side, price);
+        if price <= Decimal::ZERO {
+            return Err(format!("price {} is not positive", price));
+        }
+        let qty = self.checked_qty(qty)?;
+        if !self.meets_min_notional(qty, price) {
+            return Err(format!(
+                "order value {} is below the minimum {}",
+                qty * price,
+                self.min_notional
+            ));
+        }
+        Ok((qty, price))
+    }
+
+    /// Rounded quantity of a market order, or why it cannot be placed.
+    /// The order value is only known once filled, so the minimum notional is left to Bybit.
+    pub fn market_order(&self, qty: Decimal) -> Result<Decimal, String> {
+        self.checked_qty(qty)
+    }
+}
+
+/// Whether `order_link_id` is a client order id Bybit accepts
+fn validate_order_link_id(order_link_id: &str) -> Result<(), String> {
+    let valid = !order_link_id.is_empty()
+        && order_link_id.len() <= MAX_ORDER_LINK_ID_LEN
+        && order_link_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "orderLinkId `{}` must be 1 to {} letters, digits, `-` or `_`",
+            order_link_id, MAX_ORDER_LINK_ID_LEN
+        ))
+    }
+}
+
+/// JSON body of a `/v5/order/create` request; a market order is sized in the base coin
+fn order_body(
+    symbol: &str,
+    side: Side,
+    qty: Decimal,
+    price: Option<Decimal>,
+    order_link_id: &str,
+) -> String {
+    let mut body = json!({
+        "category": "spot",
+        "symbol": symbol,
+        "side": side.as_str(),
+        "qty": qty.normalize().to_string(),
+        "orderLinkId": order_link_id,
+    });
+    match price {
+        Some(price) => {
+            body["orderType"] = json!("Limit");
+            body["price"] = json!(price.normalize().to_string());
+            body["timeInForce"] = json!("GTC");
+        }
+        None => {
+            body["orderType"] = json!("Market");
+            body["marketUnit"] = json!("baseCoin");
+        }
+    }
+    body.to_string()
+}
+
+/// Query string selecting an order by its client order id
+fn order_query(symbol: &str, order_link_id: &str) -> String {
+    format!(
+        "category=spot&orderLinkId={}&symbol={}",
+        order_link_id, symbol
+    )
+}
+
+/// Settings of `BybitOrderClient`
+#[derive(Debug, Clone)]
+pub struct OrderClientConfig {
+    pub base_url: String,
+    /// Time Bybit accepts a signed request after its timestamp
+    pub recv_window_ms: u64,
+    pub timeout: Duration,
+    pub retry_policy: RetryPolicy,
+}
+
+impl OrderClientConfig {
+    /// Settings on the REST API of `env`, with `BYBIT_RECV_WINDOW_MS`, `BYBIT_ORDER_TIMEOUT_MS`
+    /// and `BYBIT_ORDER_MAX_ATTEMPTS`, falling back to the defaults
+    pub fn from_env(env: BybitEnv) -> Self {
+        let read = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|&value| value > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            base_url: env.rest_url().to_string(),
+            recv_window_ms: read("BYBIT_RECV_WINDOW_MS", DEFAULT_RECV_WINDOW_MS),
+            timeout: Duration::from_millis(read(
+                "BYBIT_ORDER_TIMEOUT_MS",
+                DEFAULT_ORDER_TIMEOUT_MS,
+            )),
+            retry_policy: RetryPolicy {
+                max_attempts: read(
+                    "BYBIT_ORDER_MAX_ATTEMPTS",
+                    DEFAULT_ORDER_MAX_ATTEMPTS as u64,
+                ) as u32,
+                base_delay: Duration::from_millis(200),
+                max_delay: Duration::from_secs(2),
+            },
+        }
+    }
+}
+
+/// Places and tracks Bybit spot orders over signed v5 REST requests.
+/// Every order carries a caller-supplied `orderLinkId`: a request retried after a timeout is
+/// either accepted once or reported as a duplicate, in which case the existing order is read
+/// back instead of placing a second one.
+pub struct BybitOrderClient {
+    rest: Arc<BybitRestClient>,
+    db_pool: DatabasePool,
+    env: BybitEnv,
+    /// Price and quantity filters per symbol, fetched once
+    instruments: BybitInstruments,
+}
+
+impl BybitOrderClient {
+    pub fn new(
+        db_pool: DatabasePool,
+        env: BybitEnv,
+        credentials: PrivateCredentials,
+        config: OrderClientConfig,
+    ) -> RestResult<Self> {
+        let rest = BybitRestClient::new(&config.base_url, config.timeout, config.retry_policy)?
+            .with_credentials(credentials, config.recv_window_ms);
+        Ok(Self {
+            rest: Arc::new(rest),
+            db_pool,
+            env,
+            instruments: BybitInstruments::new(),
+        })
+    }
+
+    /// Signed REST client of the account, to share its rate limit budget with other modules
+    pub fn rest_client(&self) -> Arc<BybitRestClient> {
+        self.rest.clone()
+    }
+
+    /// Place a good-till-cancelled limit order
+    pub async fn place_limit_order(&self, order: &LimitOrder) -> RestResult<PlacedOrder> {
+        validate_order_link_id(&order.order_link_id)?;
+        let rules = self.instrument_info(&order.symbol).await?;
+        let (qty, price) = rules.limit_order(order.side, order.qty, order.price)?;
+        self.submit(
+            &order.symbol,
+            order.side,
+            qty,
+            Some(price),
+            &order.order_link_id,
+        )
+        .await
+    }
+
+    /// Place a market order sized in the base coin
+    pub async fn place_market_order(&self, order: &MarketOrder) -> RestResult<PlacedOrder> {
+        validate_order_link_id(&order.order_link_id)?;
+        let rules = self.instrument_info(&order.symbol).await?;
+        let qty = rules.market_order(order.qty)?;
+        self.submit(&order.symbol, order.side, qty, None, &order.order_link_id)
+            .await
+    }
+
+    /// Cancel an open order and return its resulting state
+    pub async fn cancel_order(&self, symbol: &str, order_link_id: &str) -> RestResult<OrderEvent> {
+        validate_order_link_id(order_link_id)?;
+        let body = json!({
+            "category": "spot",
+            "symbol": symbol,
+            "orderLinkId": order_link_id,
+        })
+        .to_string();
+        self.rest.signed_post("/v5/order/cancel", &body).await?;
+        info!("Bybit order {} cancel requested", order_link_id);
+        self.get_order_status(symbol, order_link_id).await
+    }
+
+    /// Current state of an order, open or recently closed; the state is recorded in `orders`
+    pub async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_link_id: &str,
+    ) -> RestResult<OrderEvent> {
+        validate_order_link_id(order_link_id)?;
+        let query = order_query(symbol, order_link_id);
+        let mut found = None;
+        // Open orders are served by `realtime`, closed ones move to `history`
+        for path in ["/v5/order/realtime", "/v5/order/history"] {
+            let result = self.rest.signed_get(path, &query).await?;
+            if let Some(order) = result["list"].get(0) {
+                found = Some(order_event(order)?);
+                break;
+            }
+        }
+        let event =
+            found.ok_or_else(|| format!("no Bybit order with orderLinkId {}", order_link_id))?;
+        self.record(&Order {
+            exchange: self.env.exchange().to_string(),
+            order_link_id: event.order_link_id.clone(),
+            order_id: Some(event.order_id.clone()),
+            trade_pair: event.symbol.clone(),
+            side: event.side.clone(),
+            order_type: event.order_type.clone(),
+            price: Some(event.price).filter(|price| !price.is_zero()),
+            qty: event.qty,
+            status: event.status.as_str().to_string(),
+            cum_exec_qty: event.cum_exec_qty,
+            avg_price: event.avg_price,
+            reject_reason: event.reject_reason.clone(),
+            create_time: event.update_time,
+            update_time: event.update_time,
+        })
+        .await;
+        Ok(event)
+    }
+
+    /// Price and quantity filters of `symbol`, fetched on first use
+    pub async fn instrument_info(&self, symbol: &str) -> RestResult<InstrumentInfo> {
+        self.instruments.get_or_fetch(&self.rest, symbol).await
+    }
+
+    /// Record the order as pending, send it and record the outcome.
+    /// A duplicate `orderLinkId` means an earlier attempt went through, so that order is
+    /// returned instead.
+    async fn submit(
+        &self,
+        symbol: &str,
+        side: Side,
+        qty: Decimal,
+        price: Option<Decimal>,
+        order_link_id: &str,
+    ) -> RestResult<PlacedOrder> {
+        let now = Utc::now();
+        let mut order = Order {
+            exchange: self.env.exchange().to_string(),
+            order_link_id: order_link_id.to_string(),
+            order_id: None,
+            trade_pair: symbol.to_string(),
+            side: side.as_str().to_string(),
+            order_type: if price.is_some() { "Limit" } else { "Market" }.to_string(),
+            price,
+            qty,
+            status: PENDING_STATUS.to_string(),
+            cum_exec_qty: Decimal::ZERO,
+            avg_price: None,
+            reject_reason: None,
+            create_time: now,
+            update_time: now,
+        };
+        self.record(&order).await;
+
+        let body = order_body(symbol, side, qty, price, order_link_id);
+        match self.rest.signed_post("/v5/order/create", &body).await {
+            Ok(result) => {
+                let order_id = result["orderId"]
+                    .as_str()
+                    .ok_or("Bybit order reply without an orderId")?
+                    .to_string();
+                info!(
+                    "Bybit {} {} order {} placed: {} at {:?}",
+                    side.as_str(),
+                    symbol,
+                    order_link_id,
+                    qty,
+                    price
+                );
+                order.order_id = Some(order_id.clone());
+                order.status = "New".to_string();
+                order.update_time = Utc::now();
+                self.record(&order).await;
+                Ok(PlacedOrder {
+                    order_id,
+                    order_link_id: order_link_id.to_string(),
+                    qty,
+                    price,
+                })
+            }
+            Err(BybitRestError::InvalidRequest { code, .. })
+                if DUPLICATE_ORDER_CODES.contains(&code) =>
+            {
+                warn!(
+                    "Bybit order {} already placed by an earlier attempt, reading it back",
+                    order_link_id
+                );
+                let existing = self.get_order_status(symbol, order_link_id).await?;
+                Ok(PlacedOrder {
+                    order_id: existing.order_id,
+                    order_link_id: existing.order_link_id,
+                    qty: existing.qty,
+                    price: Some(existing.price).filter(|price| !price.is_zero()),
+                })
+            }
+            // The order may or may not have reached Bybit, so it stays pending
+            Err(e @ BybitRestError::Transport { .. }) => Err(e.into()),
+            Err(e) => {
+                order.status = "Rejected".to_string();
+                order.reject_reason = Some(e.to_string());
+                order.update_time = Utc::now();
+                self.record(&order).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Store the order's state; a failed write is logged, never failing the order itself
+    async fn record(&self, order: &Order) {
+        if let Err(e) = upsert_order(&self.db_pool, order).await {
+            error!(
+                "Failed to record Bybit order {} as {}: {}",
+                order.order_link_id, order.status, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "bybit_tests.rs"]
+mod bybit_tests;
@@ -0,0 +1,125 @@
+use super::*;
+use std::str::FromStr;
+
+use crate::screeners::bybit_rest::parse_envelope;
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Filters of the `/v5/market/instruments-info` reply recorded for TRUMPUSDC
+fn rules() -> InstrumentInfo {
+    InstrumentInfo {
+        tick_size: decimal("0.001"),
+        qty_step: decimal("0.01"),
+        min_qty: decimal("0.1"),
+        max_qty: decimal("50000"),
+        min_notional: decimal("5"),
+    }
+}
+
+#[test]
+fn order_body_serializes_limit_and_market_orders() {
+    assert_eq!(
+        order_body(
+            "TRUMPUSDC",
+            Side::Buy,
+            decimal("1.50"),
+            Some(decimal("10.250")),
+            "arb-1"
+        ),
+        r#"{"category":"spot","orderLinkId":"arb-1","orderType":"Limit","price":"10.25","qty":"1.5","side":"Buy","symbol":"TRUMPUSDC","timeInForce":"GTC"}"#
+    );
+    assert_eq!(
+        order_body("TRUMPUSDC", Side::Sell, decimal("2"), None, "arb-2"),
+        r#"{"category":"spot","marketUnit":"baseCoin","orderLinkId":"arb-2","orderType":"Market","qty":"2","side":"Sell","symbol":"TRUMPUSDC"}"#
+    );
+    assert_eq!(
+        order_query("TRUMPUSDC", "arb-1"),
+        "category=spot&orderLinkId=arb-1&symbol=TRUMPUSDC"
+    );
+}
+
+#[test]
+fn quantities_round_down_to_the_lot_step() {
+    let rules = rules();
+
+    assert_eq!(rules.round_qty_to_step(decimal("1.239")), decimal("1.23"));
+    assert_eq!(rules.round_qty_to_step(decimal("1.23")), decimal("1.23"));
+    assert_eq!(rules.round_qty_to_step(decimal("0.009")), Decimal::ZERO);
+    assert_eq!(
+        rules.market_order(decimal("2.999")).unwrap(),
+        decimal("2.99")
+    );
+}
+
+#[test]
+fn prices_round_in_the_callers_favour() {
+    let rules = rules();
+
+    assert_eq!(
+        rules.round_price(Side::Buy, decimal("10.2509")),
+        decimal("10.25")
+    );
+    assert_eq!(
+        rules.round_price(Side::Sell, decimal("10.2501")),
+        decimal("10.251")
+    );
+    assert_eq!(
+        rules.round_price(Side::Sell, decimal("10.250")),
+        decimal("10.25")
+    );
+}
+
+#[test]
+fn orders_outside_the_lot_rules_are_refused() {
+    let rules = rules();
+
+    assert_eq!(
+        rules
+            .limit_order(Side::Buy, decimal("1.009"), decimal("10.0009"))
+            .unwrap(),
+        (decimal("1"), decimal("10"))
+    );
+    let below_min_qty = rules.market_order(decimal("0.099")).unwrap_err();
+    assert!(below_min_qty.contains("minimum 0.1"), "{}", below_min_qty);
+    let above_max_qty = rules.market_order(decimal("50000.01")).unwrap_err();
+    assert!(above_max_qty.contains("maximum"), "{}", above_max_qty);
+    let below_min_notional = rules
+        .limit_order(Side::Buy, decimal("0.4"), decimal("10"))
+        .unwrap_err();
+    assert!(
+        below_min_notional.contains("minimum 5"),
+        "{}",
+        below_min_notional
+    );
+    assert!(
+        rules
+            .limit_order(Side::Buy, decimal("1"), decimal("0.0001"))
+            .is_err()
+    );
+}
+
+#[test]
+fn order_link_ids_are_validated() {
+    assert!(validate_order_link_id("arb_2024-01").is_ok());
+    assert!(validate_order_link_id(&"a".repeat(36)).is_ok());
+    for invalid in ["", "arb 1", "arb/1", &"a".repeat(37)] {
+        assert!(validate_order_link_id(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn order_status_replies_decode_into_events() {
+    // `/v5/order/realtime` reply recorded for a partially filled order
+    let body = r#"{"retCode":0,"retMsg":"OK","result":{"nextPageCursor":"","category":"spot","list":[{"orderId":"1321052653536515584","orderLinkId":"arb-1","symbol":"TRUMPUSDC","price":"10.25","qty":"1.5","side":"Buy","orderStatus":"PartiallyFilled","avgPrice":"10.25","leavesQty":"0.5","cumExecQty":"1","orderType":"Limit","timeInForce":"GTC","rejectReason":"EC_NoError","createdTime":"1672217577242","updatedTime":"1672217577302"}]},"retExtInfo":{},"time":1672219526294}"#;
+
+    let result = parse_envelope(body).unwrap();
+    let event = order_event(&result["list"][0]).unwrap();
+
+    assert_eq!(event.order_id, "1321052653536515584");
+    assert_eq!(event.order_type, "Limit");
+    assert_eq!(event.status.as_str(), "PartiallyFilled");
+    assert_eq!(event.cum_exec_qty, decimal("1"));
+    assert_eq!(event.avg_price, Some(decimal("10.25")));
+}
@@ -0,0 +1,149 @@
+use commons::dlmm::accounts::LbPair;
+use commons::{derive_event_authority_pda, dlmm};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+/// Anchor discriminator (`sha256("global:swap")[..8]`) of the DLMM swap instruction
+const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+/// SPL Token program, used by mints whose `token_mint_*_program_flag` is 0
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// Associated Token Account program
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xqLmHkvLxVWUMvZbH");
+
+/// Wallet swapping on the pool and its token accounts for both pool tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UserTokenAccounts {
+    pub owner: Pubkey,
+    pub token_x: Pubkey,
+    pub token_y: Pubkey,
+}
+
+impl UserTokenAccounts {
+    /// Associated token accounts of `owner` for the tokens of `lb_pair_state`
+    pub fn associated(owner: Pubkey, lb_pair_state: &LbPair) -> Self {
+        Self {
+            owner,
+            token_x: associated_token_address(
+                &owner,
+                &lb_pair_state.token_x_mint,
+                &token_program_id(lb_pair_state.token_mint_x_program_flag),
+            ),
+            token_y: associated_token_address(
+                &owner,
+                &lb_pair_state.token_y_mint,
+                &token_program_id(lb_pair_state.token_mint_y_program_flag),
+            ),
+        }
+    }
+}
+
+/// Unsigned DLMM swap instruction and the accounts worth keeping in an address lookup table
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapInstruction {
+    pub instruction: Instruction,
+    /// Every non-signer account of the instruction, once, in instruction order
+    pub lookup_table_candidates: Vec<Pubkey>,
+}
+
+/// Token program of a pool mint from its `token_mint_*_program_flag`
+pub fn token_program_id(program_flag: u8) -> Pubkey {
+    if program_flag == 1 {
+        spl_token_2022::id()
+    } else {
+        TOKEN_PROGRAM_ID
+    }
+}
+
+/// Associated token account of `owner` for `mint` under `token_program`
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Build the DLMM `swap` instruction selling `amount_in` of token X (`swap_for_y`) or token Y.
+/// `bin_arrays` are passed as remaining accounts in swap order, as returned by
+/// `get_bin_array_pubkeys_for_swap`. Absent optional accounts (bitmap extension, host fee)
+/// are replaced by the program id, following the Anchor convention.
+/// Nothing is signed: the owner is only marked as the signer of the instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn build_swap_ix(
+    lb_pair: Pubkey,
+    lb_pair_state: &LbPair,
+    amount_in: u64,
+    min_amount_out: u64,
+    swap_for_y: bool,
+    user_token_accounts: &UserTokenAccounts,
+    bin_arrays: &[Pubkey],
+    bitmap_extension: Option<Pubkey>,
+) -> SwapInstruction {
+    let (user_token_in, user_token_out) = if swap_for_y {
+        (user_token_accounts.token_x, user_token_accounts.token_y)
+    } else {
+        (user_token_accounts.token_y, user_token_accounts.token_x)
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new(lb_pair, false),
+        AccountMeta::new_readonly(bitmap_extension.unwrap_or(dlmm::ID), false),
+        AccountMeta::new(lb_pair_state.reserve_x, false),
+        AccountMeta::new(lb_pair_state.reserve_y, false),
+        AccountMeta::new(user_token_in, false),
+        AccountMeta::new(user_token_out, false),
+        AccountMeta::new_readonly(lb_pair_state.token_x_mint, false),
+        AccountMeta::new_readonly(lb_pair_state.token_y_mint, false),
+        AccountMeta::new(lb_pair_state.oracle, false),
+        // No host fee account
+        AccountMeta::new_readonly(dlmm::ID, false),
+        AccountMeta::new_readonly(user_token_accounts.owner, true),
+        AccountMeta::new_readonly(
+            token_program_id(lb_pair_state.token_mint_x_program_flag),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            token_program_id(lb_pair_state.token_mint_y_program_flag),
+            false,
+        ),
+        AccountMeta::new_readonly(derive_event_authority_pda().0, false),
+        AccountMeta::new_readonly(dlmm::ID, false),
+    ];
+    accounts.extend(
+        bin_arrays
+            .iter()
+            .map(|bin_array| AccountMeta::new(*bin_array, false)),
+    );
+
+    let mut data = Vec::with_capacity(24);
+    data.extend_from_slice(&SWAP_DISCRIMINATOR);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: dlmm::ID,
+        accounts,
+        data,
+    };
+    SwapInstruction {
+        lookup_table_candidates: lookup_table_candidates(&instruction),
+        instruction,
+    }
+}
+
+/// Non-signer accounts of an instruction, deduplicated, that a lookup table can hold
+pub fn lookup_table_candidates(instruction: &Instruction) -> Vec<Pubkey> {
+    let mut candidates: Vec<Pubkey> = Vec::with_capacity(instruction.accounts.len());
+    for meta in &instruction.accounts {
+        if !meta.is_signer && !candidates.contains(&meta.pubkey) {
+            candidates.push(meta.pubkey);
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+#[path = "meteora_tests.rs"]
+mod meteora_tests;
@@ -0,0 +1,219 @@
+use super::*;
+
+fn fixture_lb_pair() -> LbPair {
+    let mut state: LbPair = bytemuck::Zeroable::zeroed();
+    state.token_x_mint = Pubkey::new_unique();
+    state.token_y_mint = Pubkey::new_unique();
+    state.reserve_x = Pubkey::new_unique();
+    state.reserve_y = Pubkey::new_unique();
+    state.oracle = Pubkey::new_unique();
+    state.token_mint_x_program_flag = 1;
+    state
+}
+
+fn fixture_user() -> UserTokenAccounts {
+    UserTokenAccounts {
+        owner: Pubkey::new_unique(),
+        token_x: Pubkey::new_unique(),
+        token_y: Pubkey::new_unique(),
+    }
+}
+
+#[test]
+fn build_swap_ix_orders_accounts_like_the_program() {
+    let lb_pair = Pubkey::new_unique();
+    let state = fixture_lb_pair();
+    let user = fixture_user();
+    let bitmap_extension = Pubkey::new_unique();
+    let bin_arrays = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let ix = build_swap_ix(
+        lb_pair,
+        &state,
+        1_000,
+        990,
+        true,
+        &user,
+        &bin_arrays,
+        Some(bitmap_extension),
+    )
+    .instruction;
+
+    let expected = vec![
+        AccountMeta::new(lb_pair, false),
+        AccountMeta::new_readonly(bitmap_extension, false),
+        AccountMeta::new(state.reserve_x, false),
+        AccountMeta::new(state.reserve_y, false),
+        AccountMeta::new(user.token_x, false),
+        AccountMeta::new(user.token_y, false),
+        AccountMeta::new_readonly(state.token_x_mint, false),
+        AccountMeta::new_readonly(state.token_y_mint, false),
+        AccountMeta::new(state.oracle, false),
+        AccountMeta::new_readonly(dlmm::ID, false),
+        AccountMeta::new_readonly(user.owner, true),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(derive_event_authority_pda().0, false),
+        AccountMeta::new_readonly(dlmm::ID, false),
+        AccountMeta::new(bin_arrays[0], false),
+        AccountMeta::new(bin_arrays[1], false),
+    ];
+    assert_eq!(ix.program_id, dlmm::ID);
+    assert_eq!(ix.accounts, expected);
+}
+
+#[test]
+fn build_swap_ix_encodes_amounts_after_discriminator() {
+    let ix = build_swap_ix(
+        Pubkey::new_unique(),
+        &fixture_lb_pair(),
+        1_000,
+        990,
+        true,
+        &fixture_user(),
+        &[],
+        None,
+    )
+    .instruction;
+
+    assert_eq!(ix.data[..8], SWAP_DISCRIMINATOR);
+    assert_eq!(ix.data[8..16], 1_000u64.to_le_bytes());
+    assert_eq!(ix.data[16..], 990u64.to_le_bytes());
+}
+
+#[test]
+fn build_swap_ix_swaps_user_accounts_when_selling_y() {
+    let user = fixture_user();
+
+    let ix = build_swap_ix(
+        Pubkey::new_unique(),
+        &fixture_lb_pair(),
+        1_000,
+        0,
+        false,
+        &user,
+        &[],
+        None,
+    )
+    .instruction;
+
+    assert_eq!(ix.accounts[1], AccountMeta::new_readonly(dlmm::ID, false));
+    assert_eq!(ix.accounts[4].pubkey, user.token_y);
+    assert_eq!(ix.accounts[5].pubkey, user.token_x);
+    assert_eq!(ix.accounts.len(), 15);
+}
+
+#[test]
+fn associated_accounts_use_the_mint_token_program() {
+    let state = fixture_lb_pair();
+    let owner = Pubkey::new_unique();
+
+    let user = UserTokenAccounts::associated(owner, &state);
+
+    assert_eq!(
+        user.token_x,
+        associated_token_address(&owner, &state.token_x_mint, &spl_token_2022::id())
+    );
+    assert_eq!(
+        user.token_y,
+        associated_token_address(&owner, &state.token_y_mint, &TOKEN_PROGRAM_ID)
+    );
+    assert_ne!(
+        user.token_x,
+        associated_token_address(&owner, &state.token_x_mint, &TOKEN_PROGRAM_ID)
+    );
+}
+
+/// Fixed 32-byte key, so golden values do not depend on test order
+fn key(index: u8) -> Pubkey {
+    let mut bytes = [0xa5; 32];
+    bytes[0] = index;
+    Pubkey::new_from_array(bytes)
+}
+
+/// Pool and wallet of the golden swap: a Token-2022 base (X) against an SPL Token quote (Y),
+/// selling 2.5 X for at least 0.99 Y through three bin arrays, with a bitmap extension
+fn golden_swap() -> SwapInstruction {
+    let mut state: LbPair = bytemuck::Zeroable::zeroed();
+    state.token_x_mint = key(1);
+    state.token_y_mint = key(2);
+    state.reserve_x = key(3);
+    state.reserve_y = key(4);
+    state.oracle = key(5);
+    state.token_mint_x_program_flag = 1;
+    let user = UserTokenAccounts {
+        owner: key(6),
+        token_x: key(7),
+        token_y: key(8),
+    };
+
+    build_swap_ix(
+        key(9),
+        &state,
+        2_500_000_000,
+        990_000,
+        true,
+        &user,
+        &[key(10), key(11), key(12)],
+        Some(key(13)),
+    )
+}
+
+#[test]
+fn golden_swap_matches_the_program_account_layout() {
+    let swap = golden_swap();
+
+    // (account, writable, signer) in the order of the program's `swap` accounts
+    let expected: Vec<(Pubkey, bool, bool)> = vec![
+        (key(9), true, false),                          // lb_pair
+        (key(13), false, false),                        // bin_array_bitmap_extension
+        (key(3), true, false),                          // reserve_x
+        (key(4), true, false),                          // reserve_y
+        (key(7), true, false),                          // user_token_in
+        (key(8), true, false),                          // user_token_out
+        (key(1), false, false),                         // token_x_mint
+        (key(2), false, false),                         // token_y_mint
+        (key(5), true, false),                          // oracle
+        (dlmm::ID, false, false),                       // host_fee_in (none)
+        (key(6), false, true),                          // user
+        (spl_token_2022::id(), false, false),           // token_x_program
+        (TOKEN_PROGRAM_ID, false, false),               // token_y_program
+        (derive_event_authority_pda().0, false, false), // event_authority
+        (dlmm::ID, false, false),                       // program
+        (key(10), true, false),                         // bin arrays
+        (key(11), true, false),
+        (key(12), true, false),
+    ];
+    let actual: Vec<(Pubkey, bool, bool)> = swap
+        .instruction
+        .accounts
+        .iter()
+        .map(|meta| (meta.pubkey, meta.is_writable, meta.is_signer))
+        .collect();
+    assert_eq!(actual, expected);
+
+    assert_eq!(
+        swap.instruction.data,
+        [
+            248, 198, 158, 145, 225, 117, 135, 200, // swap discriminator
+            0, 249, 2, 149, 0, 0, 0, 0, // amount_in 2_500_000_000
+            48, 27, 15, 0, 0, 0, 0, 0, // min_amount_out 990_000
+        ]
+    );
+}
+
+#[test]
+fn lookup_table_candidates_skip_the_signer_and_duplicates() {
+    let swap = golden_swap();
+
+    assert!(!swap.lookup_table_candidates.contains(&key(6)));
+    assert_eq!(
+        swap.lookup_table_candidates
+            .iter()
+            .filter(|&&pubkey| pubkey == dlmm::ID)
+            .count(),
+        1
+    );
+    assert_eq!(swap.lookup_table_candidates.len(), 16);
+    assert_eq!(swap.lookup_table_candidates[..3], [key(9), key(13), key(3)]);
+}
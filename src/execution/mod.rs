@@ -0,0 +1,2 @@
+pub mod bybit;
+pub mod meteora;
@@ -0,0 +1,196 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::models::cex_fee::CEXFee;
+use crate::screeners::bybit::BybitEnv;
+use crate::screeners::bybit_rest::{BybitRestClient, RestResult};
+use crate::store::cex_fees::insert_cex_fee;
+use crate::store::db::DatabasePool;
+
+/// Default maker fee used without an API key, in bps
+const DEFAULT_MAKER_FEE_BPS: u32 = 10;
+/// Default taker fee used without an API key, in bps
+const DEFAULT_TAKER_FEE_BPS: u32 = 10;
+/// Default delay before the fee rate of a pair is fetched again
+const DEFAULT_FEE_RATE_REFRESH_SECS: u64 = 86_400;
+const BPS_PER_UNIT: u32 = 10_000;
+
+/// How Bybit trading fee rates are fetched and what is used when they are not
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeRateConfig {
+    /// Maker fee in bps used when the rate cannot be fetched
+    pub fallback_maker_bps: Decimal,
+    /// Taker fee in bps used when the rate cannot be fetched
+    pub fallback_taker_bps: Decimal,
+    /// Delay before a cached fee rate is fetched again
+    pub refresh_interval: Duration,
+}
+
+impl FeeRateConfig {
+    /// Read `BYBIT_DEFAULT_MAKER_FEE_BPS`, `BYBIT_DEFAULT_TAKER_FEE_BPS` and
+    /// `BYBIT_FEE_RATE_REFRESH_SECS`, falling back to the defaults
+    pub fn from_env() -> Self {
+        fn parse_env<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+        }
+        Self {
+            fallback_maker_bps: parse_env("BYBIT_DEFAULT_MAKER_FEE_BPS")
+                .unwrap_or(Decimal::from(DEFAULT_MAKER_FEE_BPS)),
+            fallback_taker_bps: parse_env("BYBIT_DEFAULT_TAKER_FEE_BPS")
+                .unwrap_or(Decimal::from(DEFAULT_TAKER_FEE_BPS)),
+            refresh_interval: Duration::from_secs(
+                parse_env("BYBIT_FEE_RATE_REFRESH_SECS")
+                    .filter(|&secs| secs > 0)
+                    .unwrap_or(DEFAULT_FEE_RATE_REFRESH_SECS),
+            ),
+        }
+    }
+}
+
+/// Maker and taker fee of a pair, in bps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRate {
+    pub maker_bps: Decimal,
+    pub taker_bps: Decimal,
+}
+
+/// Read the fee rate of `symbol` from a `/v5/account/fee-rate` result; rates are fractions
+/// such as `"0.001"` and are converted to bps
+fn parse_fee_rate(result: &Value, symbol: &str) -> RestResult<FeeRate> {
+    let entry = result["list"]
+        .as_array()
+        .and_then(|list| list.iter().find(|entry| entry["symbol"] == symbol))
+        .ok_or_else(|| format!("no Bybit fee rate for {}", symbol))?;
+    let rate_bps = |field: &str| -> RestResult<Decimal> {
+        let value = entry[field]
+            .as_str()
+            .ok_or_else(|| format!("missing {} in Bybit fee rate of {}", field, symbol))?;
+        let rate = Decimal::from_str(value)
+            .map_err(|e| format!("malformed {} `{}` of {}: {}", field, value, symbol, e))?;
+        Ok((rate * Decimal::from(BPS_PER_UNIT)).normalize())
+    };
+    Ok(FeeRate {
+        maker_bps: rate_bps("makerFeeRate")?,
+        taker_bps: rate_bps("takerFeeRate")?,
+    })
+}
+
+/// Caches the account's Bybit spot fee rates per pair, fetching each one again once it is
+/// older than the refresh interval. Without a signed REST client, i.e. without an API key,
+/// the configured default rates are used.
+pub struct BybitFeeRates {
+    pub config: FeeRateConfig,
+    db_pool: DatabasePool,
+    env: BybitEnv,
+    client: Option<Arc<BybitRestClient>>,
+    /// Fee rate per symbol, with the time it was fetched
+    cache: Mutex<HashMap<String, (FeeRate, Instant)>>,
+    /// Whether the missing API key was already reported
+    fallback_warned: AtomicBool,
+}
+
+impl BybitFeeRates {
+    pub fn new(
+        db_pool: DatabasePool,
+        env: BybitEnv,
+        client: Option<Arc<BybitRestClient>>,
+        config: FeeRateConfig,
+    ) -> Self {
+        Self {
+            config,
+            db_pool,
+            env,
+            client,
+            cache: Mutex::new(HashMap::new()),
+            fallback_warned: AtomicBool::new(false),
+        }
+    }
+
+    fn fallback(&self) -> FeeRate {
+        FeeRate {
+            maker_bps: self.config.fallback_maker_bps,
+            taker_bps: self.config.fallback_taker_bps,
+        }
+    }
+
+    /// Maker and taker fee of `symbol` in bps. A failed refresh keeps the last fetched rate,
+    /// or the default rates if there is none.
+    pub async fn get_fee_rate(&self, symbol: &str) -> (Decimal, Decimal) {
+        let rate = self.fee_rate(symbol).await;
+        (rate.maker_bps, rate.taker_bps)
+    }
+
+    async fn fee_rate(&self, symbol: &str) -> FeeRate {
+        let Some(client) = &self.client else {
+            if !self.fallback_warned.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "BYBIT_API_KEY not set, using default Bybit fees of {} bps maker and {} bps taker",
+                    self.config.fallback_maker_bps, self.config.fallback_taker_bps
+                );
+            }
+            return self.fallback();
+        };
+
+        let cached = self.cache.lock().unwrap().get(symbol).copied();
+        if let Some((rate, fetched_at)) = cached
+            && fetched_at.elapsed() < self.config.refresh_interval
+        {
+            return rate;
+        }
+
+        let query = format!("category=spot&symbol={}", symbol);
+        let fetched = match client.signed_get("/v5/account/fee-rate", &query).await {
+            Ok(result) => parse_fee_rate(&result, symbol),
+            Err(e) => Err(e.into()),
+        };
+        match fetched {
+            Ok(rate) => {
+                info!(
+                    "Bybit fee rate of {}: {} bps maker, {} bps taker",
+                    symbol, rate.maker_bps, rate.taker_bps
+                );
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert(symbol.to_string(), (rate, Instant::now()));
+                self.record(symbol, rate).await;
+                rate
+            }
+            Err(e) => {
+                let rate = cached.map_or_else(|| self.fallback(), |(rate, _)| rate);
+                warn!(
+                    "Failed to fetch Bybit fee rate of {}: {}, using {} bps maker and {} bps taker",
+                    symbol, e, rate.maker_bps, rate.taker_bps
+                );
+                rate
+            }
+        }
+    }
+
+    /// Store a fetched fee rate in `cex_fees`
+    async fn record(&self, symbol: &str, rate: FeeRate) {
+        let fee = CEXFee {
+            exchange: self.env.exchange().to_string(),
+            trade_pair: symbol.to_string(),
+            maker_fee_bps: rate.maker_bps,
+            taker_fee_bps: rate.taker_bps,
+            fetch_time: Utc::now(),
+        };
+        if let Err(e) = insert_cex_fee(&self.db_pool, &fee).await {
+            error!("Failed to store Bybit fee rate of {}: {}", symbol, e);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "bybit_tests.rs"]
+mod bybit_tests;
@@ -0,0 +1,129 @@
+use super::*;
+use serde_json::json;
+
+use crate::screeners::bybit_private::PrivateCredentials;
+use crate::solana::retry::RetryPolicy;
+use crate::store::db::lazy_pool;
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn config() -> FeeRateConfig {
+    FeeRateConfig {
+        fallback_maker_bps: decimal("10"),
+        fallback_taker_bps: decimal("12.5"),
+        refresh_interval: Duration::from_secs(86_400),
+    }
+}
+
+#[test]
+fn parse_fee_rate_converts_rates_to_bps() {
+    // `/v5/account/fee-rate` result recorded for a spot symbol
+    let result = json!({
+        "list": [
+            {"symbol": "SOLUSDC", "takerFeeRate": "0.001", "makerFeeRate": "0.0008"},
+            {"symbol": "TRUMPUSDC", "takerFeeRate": "0.0006", "makerFeeRate": "0"}
+        ]
+    });
+
+    assert_eq!(
+        parse_fee_rate(&result, "TRUMPUSDC").unwrap(),
+        FeeRate {
+            maker_bps: decimal("0"),
+            taker_bps: decimal("6"),
+        }
+    );
+    assert_eq!(
+        parse_fee_rate(&result, "SOLUSDC").unwrap(),
+        FeeRate {
+            maker_bps: decimal("8"),
+            taker_bps: decimal("10"),
+        }
+    );
+}
+
+#[test]
+fn parse_fee_rate_rejects_missing_and_malformed_rates() {
+    let result = json!({
+        "list": [{"symbol": "TRUMPUSDC", "takerFeeRate": "n/a", "makerFeeRate": "0.001"}]
+    });
+
+    assert!(parse_fee_rate(&result, "SOLUSDC").is_err());
+    assert!(parse_fee_rate(&result, "TRUMPUSDC").is_err());
+    assert!(parse_fee_rate(&json!({}), "TRUMPUSDC").is_err());
+    let missing = json!({"list": [{"symbol": "TRUMPUSDC", "makerFeeRate": "0.001"}]});
+    assert!(parse_fee_rate(&missing, "TRUMPUSDC").is_err());
+}
+
+#[tokio::test]
+async fn get_fee_rate_uses_defaults_without_api_key() {
+    let fees = BybitFeeRates::new(lazy_pool(), BybitEnv::Mainnet, None, config());
+
+    assert_eq!(
+        fees.get_fee_rate("TRUMPUSDC").await,
+        (decimal("10"), decimal("12.5"))
+    );
+    assert_eq!(
+        fees.get_fee_rate("SOLUSDC").await,
+        (decimal("10"), decimal("12.5"))
+    );
+    assert!(fees.fallback_warned.load(Ordering::Relaxed));
+}
+
+#[tokio::test]
+async fn get_fee_rate_falls_back_when_the_request_fails() {
+    let client = BybitRestClient::new(
+        "http://127.0.0.1:1",
+        Duration::from_millis(200),
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        },
+    )
+    .unwrap()
+    .with_credentials(PrivateCredentials::new("test_key", "test_secret"), 5_000);
+    let fees = BybitFeeRates::new(
+        lazy_pool(),
+        BybitEnv::Testnet,
+        Some(Arc::new(client)),
+        config(),
+    );
+
+    assert_eq!(
+        fees.get_fee_rate("TRUMPUSDC").await,
+        (decimal("10"), decimal("12.5"))
+    );
+    assert!(fees.cache.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn get_fee_rate_serves_fresh_cache_entries() {
+    let client = BybitRestClient::new(
+        "http://127.0.0.1:1",
+        Duration::from_millis(200),
+        RetryPolicy::default(),
+    )
+    .unwrap()
+    .with_credentials(PrivateCredentials::new("test_key", "test_secret"), 5_000);
+    let fees = BybitFeeRates::new(
+        lazy_pool(),
+        BybitEnv::Testnet,
+        Some(Arc::new(client)),
+        config(),
+    );
+    let cached = FeeRate {
+        maker_bps: decimal("1"),
+        taker_bps: decimal("2"),
+    };
+    fees.cache
+        .lock()
+        .unwrap()
+        .insert("TRUMPUSDC".to_string(), (cached, Instant::now()));
+
+    assert_eq!(
+        fees.get_fee_rate("TRUMPUSDC").await,
+        (decimal("1"), decimal("2"))
+    );
+}
@@ -0,0 +1,202 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::solana::rpc::FailoverRpcClient;
+
+/// Fee paid for each transaction signature, in lamports
+pub const BASE_FEE_LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+/// Default compute unit budget of a DLMM swap transaction
+const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 150_000;
+/// Default percentile of recent prioritization fees paid
+const DEFAULT_PRIORITY_FEE_PERCENTILE: u8 = 75;
+/// Default priority fee, in micro-lamports per compute unit, used until fees could be fetched
+const DEFAULT_FALLBACK_PRIORITY_FEE: u64 = 10_000;
+/// Default delay between two priority fee estimates
+const DEFAULT_FEE_REFRESH_SECS: u64 = 10;
+/// Default pair whose quotes price SOL in quote token
+const DEFAULT_SOL_PRICE_SYMBOL: &str = "SOLUSDC";
+
+/// How the landing cost of a DEX transaction is estimated
+#[derive(Debug, Clone, PartialEq)]
+pub struct LandingCostConfig {
+    /// Compute units requested by the swap transaction
+    pub compute_unit_limit: u32,
+    /// Percentile of recent prioritization fees to pay, between 0 and 100
+    pub priority_fee_percentile: u8,
+    /// Priority fee in micro-lamports per compute unit used when no estimate is available
+    pub fallback_priority_fee: u64,
+    /// Jito tip added to every transaction, in lamports
+    pub jito_tip_lamports: u64,
+    /// Delay between two priority fee estimates
+    pub refresh_interval: Duration,
+    /// Static SOL price in quote token used until a live price is set
+    pub fallback_sol_price: Option<Decimal>,
+    /// Pair whose quotes update the SOL price
+    pub sol_price_symbol: String,
+}
+
+impl LandingCostConfig {
+    /// Read `SOLANA_COMPUTE_UNIT_LIMIT`, `SOLANA_PRIORITY_FEE_PERCENTILE`,
+    /// `SOLANA_FALLBACK_PRIORITY_FEE`, `JITO_TIP_LAMPORTS`, `SOLANA_FEE_REFRESH_SECS`,
+    /// `SOLANA_FALLBACK_SOL_PRICE` and `SOL_PRICE_SYMBOL`, falling back to the defaults
+    pub fn from_env() -> Self {
+        fn parse_env<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+        }
+        Self {
+            compute_unit_limit: parse_env("SOLANA_COMPUTE_UNIT_LIMIT")
+                .unwrap_or(DEFAULT_COMPUTE_UNIT_LIMIT),
+            priority_fee_percentile: parse_env("SOLANA_PRIORITY_FEE_PERCENTILE")
+                .filter(|percentile| *percentile <= 100)
+                .unwrap_or(DEFAULT_PRIORITY_FEE_PERCENTILE),
+            fallback_priority_fee: parse_env("SOLANA_FALLBACK_PRIORITY_FEE")
+                .unwrap_or(DEFAULT_FALLBACK_PRIORITY_FEE),
+            jito_tip_lamports: parse_env("JITO_TIP_LAMPORTS").unwrap_or(0),
+            refresh_interval: Duration::from_secs(
+                parse_env("SOLANA_FEE_REFRESH_SECS").unwrap_or(DEFAULT_FEE_REFRESH_SECS),
+            ),
+            fallback_sol_price: parse_env("SOLANA_FALLBACK_SOL_PRICE"),
+            sol_price_symbol: std::env::var("SOL_PRICE_SYMBOL")
+                .unwrap_or_else(|_| DEFAULT_SOL_PRICE_SYMBOL.to_string()),
+        }
+    }
+}
+
+/// Keeps the latest priority fee estimate and SOL price to price the landing of a transaction
+pub struct SolanaFeeEstimator {
+    pub config: LandingCostConfig,
+    /// Latest priority fee estimate in micro-lamports per compute unit
+    priority_fee: AtomicU64,
+    /// Latest SOL price in quote token
+    sol_price: RwLock<Option<Decimal>>,
+}
+
+impl SolanaFeeEstimator {
+    pub fn new(config: LandingCostConfig) -> Self {
+        Self {
+            priority_fee: AtomicU64::new(config.fallback_priority_fee),
+            sol_price: RwLock::new(config.fallback_sol_price),
+            config,
+        }
+    }
+
+    /// Lamports paid to land one swap transaction at the current priority fee
+    pub fn current_landing_cost_lamports(&self) -> u64 {
+        landing_cost_lamports(
+            self.priority_fee.load(Ordering::Relaxed),
+            self.config.compute_unit_limit,
+            self.config.jito_tip_lamports,
+        )
+    }
+
+    /// Latest SOL price in quote token, if any is known
+    pub fn sol_price(&self) -> Option<Decimal> {
+        *self.sol_price.read().unwrap()
+    }
+
+    /// Update the cached SOL price, ignoring non-positive prices
+    pub fn set_sol_price(&self, price: Decimal) {
+        if price > Decimal::ZERO {
+            *self.sol_price.write().unwrap() = Some(price);
+        }
+    }
+
+    /// Estimate the priority fee from recent fees paid on `accounts`.
+    /// The previous estimate is kept when the RPC call fails or returns no fees.
+    pub async fn refresh(&self, rpc_client: &FailoverRpcClient, accounts: &[Pubkey]) {
+        let fees = match rpc_client.get_recent_prioritization_fees(accounts).await {
+            Ok(fees) => fees,
+            Err(e) => {
+                warn!(
+                    "Failed to fetch prioritization fees, keeping {} micro-lamports/CU: {}",
+                    self.priority_fee.load(Ordering::Relaxed),
+                    e
+                );
+                return;
+            }
+        };
+        let fees: Vec<u64> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+        if let Some(fee) = fee_percentile(&fees, self.config.priority_fee_percentile) {
+            self.priority_fee.store(fee, Ordering::Relaxed);
+        }
+    }
+
+    /// Refresh the priority fee estimate every `refresh_interval` until `shutdown` is cancelled.
+    /// `accounts` returns the accounts the landed transaction would lock.
+    pub async fn run<A>(
+        &self,
+        rpc_client: &FailoverRpcClient,
+        accounts: A,
+        shutdown: &CancellationToken,
+    ) where
+        A: Fn() -> Vec<Pubkey>,
+    {
+        info!(
+            "🚀 Estimating Solana priority fees every {:?} (CU limit {}, p{})",
+            self.config.refresh_interval,
+            self.config.compute_unit_limit,
+            self.config.priority_fee_percentile
+        );
+        loop {
+            self.refresh(rpc_client, &accounts()).await;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(self.config.refresh_interval) => {}
+            }
+        }
+    }
+}
+
+/// Nearest-rank percentile of fees, `None` without fees
+fn fee_percentile(fees: &[u64], percentile: u8) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    let mut sorted = fees.to_vec();
+    sorted.sort_unstable();
+    let rank = (sorted.len() * percentile as usize).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+/// Priority fee in lamports of `compute_unit_limit` units at `priority_fee` micro-lamports each,
+/// rounded up like the runtime does
+pub fn priority_fee_lamports(priority_fee: u64, compute_unit_limit: u32) -> u64 {
+    let lamports =
+        (priority_fee as u128 * compute_unit_limit as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+    lamports.min(u64::MAX as u128) as u64
+}
+
+/// Lamports paid to land a single-signature transaction: base fee, priority fee and tip
+pub fn landing_cost_lamports(priority_fee: u64, compute_unit_limit: u32, tip: u64) -> u64 {
+    BASE_FEE_LAMPORTS_PER_SIGNATURE
+        .saturating_add(priority_fee_lamports(priority_fee, compute_unit_limit))
+        .saturating_add(tip)
+}
+
+/// Landing cost in raw quote token units at `sol_price` quote tokens per SOL, rounded up
+pub fn lamports_to_quote_amount(
+    lamports: u64,
+    sol_price: Decimal,
+    quote_decimals: u32,
+) -> Option<u64> {
+    let sol = Decimal::from(lamports) / Decimal::from(LAMPORTS_PER_SOL);
+    let scale = Decimal::from_i128_with_scale(10i128.checked_pow(quote_decimals)?, 0);
+    sol.checked_mul(sol_price)?
+        .checked_mul(scale)?
+        .ceil()
+        .to_u64()
+}
+
+#[cfg(test)]
+#[path = "solana_tests.rs"]
+mod solana_tests;
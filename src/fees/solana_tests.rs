@@ -0,0 +1,64 @@
+use super::*;
+use std::str::FromStr;
+
+#[test]
+fn priority_fee_lamports_rounds_micro_lamports_up() {
+    // 10_000 micro-lamports/CU over 150k CU = 1_500 lamports
+    assert_eq!(priority_fee_lamports(10_000, 150_000), 1_500);
+    // 1 micro-lamport over 1 CU still costs a lamport
+    assert_eq!(priority_fee_lamports(1, 1), 1);
+    assert_eq!(priority_fee_lamports(0, 150_000), 0);
+    assert_eq!(priority_fee_lamports(u64::MAX, u32::MAX), u64::MAX);
+}
+
+#[test]
+fn landing_cost_adds_base_fee_priority_fee_and_tip() {
+    assert_eq!(landing_cost_lamports(10_000, 150_000, 0), 6_500);
+    assert_eq!(landing_cost_lamports(10_000, 150_000, 1_000_000), 1_006_500);
+}
+
+#[test]
+fn lamports_to_quote_amount_uses_the_sol_price() {
+    let sol_price = Decimal::from_str("150.25").unwrap();
+
+    // 6_500 lamports at $150.25 = $0.000976625, rounded up to 977 micro-USDC
+    assert_eq!(lamports_to_quote_amount(6_500, sol_price, 6), Some(977));
+    assert_eq!(
+        lamports_to_quote_amount(1_000_000_000, sol_price, 6),
+        Some(150_250_000)
+    );
+    assert_eq!(lamports_to_quote_amount(0, sol_price, 6), Some(0));
+}
+
+#[test]
+fn fee_percentile_picks_the_nearest_rank() {
+    let fees = [0, 500, 100, 10_000, 2_000, 0, 0, 50_000];
+
+    assert_eq!(fee_percentile(&fees, 50), Some(100));
+    assert_eq!(fee_percentile(&fees, 75), Some(2_000));
+    assert_eq!(fee_percentile(&fees, 100), Some(50_000));
+    assert_eq!(fee_percentile(&fees, 0), Some(0));
+    assert_eq!(fee_percentile(&[], 75), None);
+}
+
+#[test]
+fn estimator_starts_from_the_fallback_values() {
+    let config = LandingCostConfig {
+        compute_unit_limit: 150_000,
+        priority_fee_percentile: 75,
+        fallback_priority_fee: 10_000,
+        jito_tip_lamports: 1_000,
+        refresh_interval: Duration::from_secs(10),
+        fallback_sol_price: Some(Decimal::from(150)),
+        sol_price_symbol: "SOLUSDC".to_string(),
+    };
+    let estimator = SolanaFeeEstimator::new(config);
+
+    assert_eq!(estimator.current_landing_cost_lamports(), 7_500);
+    assert_eq!(estimator.sol_price(), Some(Decimal::from(150)));
+
+    estimator.set_sol_price(Decimal::ZERO);
+    assert_eq!(estimator.sol_price(), Some(Decimal::from(150)));
+    estimator.set_sol_price(Decimal::from(160));
+    assert_eq!(estimator.sol_price(), Some(Decimal::from(160)));
+}
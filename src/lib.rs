@@ -1,3 +1,9 @@
+pub mod execution;
+pub mod fees;
 pub mod models;
 pub mod screeners;
+pub mod solana;
+pub mod spread_recorder;
 pub mod store;
+pub mod telemetry;
+pub mod wallet;
@@ -1,9 +1,36 @@
 mod logger;
 
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
-use zero_r::screeners::bybit::BybitScreener;
-use zero_r::screeners::meteora::MeteoraScreener;
+use zero_r::screeners::backpack::{BackpackConfig, BackpackScreener};
+use zero_r::screeners::binance::{BinanceConfig, BinanceScreener};
+use zero_r::screeners::bitget::{BitgetConfig, BitgetScreener};
+use zero_r::screeners::bybit::{BybitConfig, BybitScreener};
+use zero_r::screeners::bybit_private::{BybitPrivateClient, PrivateCredentials};
+use zero_r::screeners::coinbase::{CoinbaseConfig, CoinbaseScreener};
+use zero_r::screeners::gate::{GateConfig, GateScreener};
+use zero_r::screeners::htx::{HtxConfig, HtxScreener};
+use zero_r::screeners::hyperliquid::{HyperliquidConfig, HyperliquidScreener};
+use zero_r::screeners::jupiter::{JupiterConfig, JupiterScreener};
+use zero_r::screeners::kraken::{KrakenConfig, KrakenScreener};
+use zero_r::screeners::kucoin::{KuCoinConfig, KuCoinScreener};
+use zero_r::screeners::meteora::{MeteoraConfig, MeteoraScreener};
+use zero_r::screeners::meteora_damm::DammScreener;
+use zero_r::screeners::mexc::{MexcConfig, MexcScreener};
+use zero_r::screeners::okx::{OKXConfig, OKXScreener};
+use zero_r::screeners::phoenix::{PhoenixConfig, PhoenixScreener};
+use zero_r::screeners::pumpfun::PumpFunScreener;
+use zero_r::screeners::raydium_amm::RaydiumAmmScreener;
+use zero_r::screeners::raydium_clmm::RaydiumClmmScreener;
+use zero_r::screeners::screener::{Screener, ScreenerTasks};
+use zero_r::screeners::symbols::{self, SymbolOverrides};
+use zero_r::spread_recorder::{SpreadRecorder, SpreadRecorderConfig};
 use zero_r::store::db::init_database;
+use zero_r::store::write_metrics::write_metrics;
+use zero_r::telemetry::DEFAULT_SUMMARY_INTERVAL_SECS;
+use zero_r::wallet::solana::{SolanaBalanceTracker, WalletConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -12,33 +39,202 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     logger::init_logging();
     info!("🚀 Starting Zero-R arbitrage service...");
 
+    // Resolve the RPC settings first so a missing endpoint fails startup with a readable error
+    let meteora_config =
+        MeteoraConfig::from_env().map_err(|e| format!("Invalid Meteora configuration: {}", e))?;
+
+    // Installed before any CEX configuration maps its symbols to venue symbols
+    let symbol_overrides =
+        SymbolOverrides::from_env().map_err(|e| format!("Invalid symbol overrides: {}", e))?;
+    symbols::install_overrides(symbol_overrides)?;
+
+    let jupiter_config =
+        JupiterConfig::from_env().map_err(|e| format!("Invalid Jupiter configuration: {}", e))?;
+    let phoenix_config =
+        PhoenixConfig::from_env().map_err(|e| format!("Invalid Phoenix configuration: {}", e))?;
+    let bybit_config =
+        BybitConfig::from_env().map_err(|e| format!("Invalid Bybit configuration: {}", e))?;
+    let bybit_credentials =
+        PrivateCredentials::from_env().map_err(|e| format!("Invalid Bybit credentials: {}", e))?;
+    let binance_config =
+        BinanceConfig::from_env().map_err(|e| format!("Invalid Binance configuration: {}", e))?;
+    let okx_config =
+        OKXConfig::from_env().map_err(|e| format!("Invalid OKX configuration: {}", e))?;
+    let coinbase_config =
+        CoinbaseConfig::from_env().map_err(|e| format!("Invalid Coinbase configuration: {}", e))?;
+    let kraken_config =
+        KrakenConfig::from_env().map_err(|e| format!("Invalid Kraken configuration: {}", e))?;
+    let gate_config =
+        GateConfig::from_env().map_err(|e| format!("Invalid Gate configuration: {}", e))?;
+    let kucoin_config =
+        KuCoinConfig::from_env().map_err(|e| format!("Invalid KuCoin configuration: {}", e))?;
+    let mexc_config =
+        MexcConfig::from_env().map_err(|e| format!("Invalid MEXC configuration: {}", e))?;
+    let bitget_config =
+        BitgetConfig::from_env().map_err(|e| format!("Invalid Bitget configuration: {}", e))?;
+    let htx_config =
+        HtxConfig::from_env().map_err(|e| format!("Invalid HTX configuration: {}", e))?;
+    let hyperliquid_config = HyperliquidConfig::from_env()
+        .map_err(|e| format!("Invalid Hyperliquid configuration: {}", e))?;
+    let backpack_config =
+        BackpackConfig::from_env().map_err(|e| format!("Invalid Backpack configuration: {}", e))?;
+    let spread_recorder_config = SpreadRecorderConfig::from_env();
+
     let _pool = init_database().await?;
 
-    let meteora_screener = std::sync::Arc::new(MeteoraScreener::new(_pool.clone()));
-    info!("Starting Meteora screener...");
-    let meteora_screener_clone = meteora_screener.clone();
-    let meteora_screener_handle = tokio::spawn(async move {
-        if let Err(e) = meteora_screener_clone.start().await {
-            error!("Meteora screener failed: {}", e);
-        }
+    // Build every screener before spawning any of them
+    let bybit_env = bybit_config.env;
+    let screeners: Vec<Arc<dyn Screener>> = vec![
+        Arc::new(MeteoraScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+        )?),
+        Arc::new(DammScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+        )),
+        Arc::new(RaydiumClmmScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+        )),
+        Arc::new(RaydiumAmmScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+        )),
+        Arc::new(PumpFunScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+        )),
+        Arc::new(PhoenixScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+            phoenix_config,
+        )),
+        Arc::new(JupiterScreener::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+            jupiter_config,
+        )?),
+        Arc::new(BybitScreener::with_config(_pool.clone(), bybit_config)),
+        Arc::new(BinanceScreener::with_config(_pool.clone(), binance_config)?),
+        Arc::new(OKXScreener::with_config(_pool.clone(), okx_config)),
+        Arc::new(CoinbaseScreener::with_config(
+            _pool.clone(),
+            coinbase_config,
+        )),
+        Arc::new(KrakenScreener::with_config(_pool.clone(), kraken_config)),
+        Arc::new(GateScreener::with_config(_pool.clone(), gate_config)?),
+        Arc::new(KuCoinScreener::with_config(_pool.clone(), kucoin_config)?),
+        Arc::new(MexcScreener::with_config(_pool.clone(), mexc_config)?),
+        Arc::new(BitgetScreener::with_config(_pool.clone(), bitget_config)),
+        Arc::new(HtxScreener::with_config(_pool.clone(), htx_config)?),
+        Arc::new(HyperliquidScreener::with_config(
+            _pool.clone(),
+            hyperliquid_config,
+        )),
+        Arc::new(BackpackScreener::with_config(
+            _pool.clone(),
+            backpack_config,
+        )?),
+    ];
+    let wallet_tracker = wallet_config.map(|config| {
+        Arc::new(SolanaBalanceTracker::with_config(
+            _pool.clone(),
+            meteora_config.clone(),
+            config,
+        ))
     });
+    let bybit_private = bybit_credentials.map(|credentials| {
+        let (client, order_events) = BybitPrivateClient::new(_pool.clone(), bybit_env, credentials);
+        (Arc::new(client), order_events)
+    });
+
+    let screener_tasks = ScreenerTasks::spawn(screeners);
 
-    let bybit_screener = std::sync::Arc::new(BybitScreener::new(_pool.clone()));
-    info!("Starting Bybit screener...");
-    let bybit_screener_clone = bybit_screener.clone();
-    let bybit_screener_handle = tokio::spawn(async move {
-        if let Err(e) = bybit_screener_clone.start().await {
-            error!("Bybit screener failed: {}", e);
+    let bybit_private_handle = match bybit_private {
+        Some((client, mut order_events)) => {
+            info!("Starting Bybit private stream...");
+            // No order execution yet, so order events are only logged
+            tokio::spawn(async move {
+                while let Some(event) = order_events.recv().await {
+                    info!("Bybit order event: {:?}", event);
+                }
+            });
+            let client_clone = client.clone();
+            let handle = tokio::spawn(async move {
+                if let Err(e) = client_clone.start().await {
+                    error!("Bybit private stream failed: {}", e);
+                }
+            });
+            Some((client, handle))
+        }
+        None => {
+            info!("BYBIT_API_KEY not set, Bybit private stream disabled");
+            None
+        }
+    };
+
+    // Logs the p95 latency and errors of the store writes of every table
+    let write_metrics_shutdown = CancellationToken::new();
+    let write_metrics_handle = {
+        let shutdown = write_metrics_shutdown.clone();
+        tokio::spawn(async move {
+            write_metrics()
+                .report_every(
+                    Duration::from_secs(DEFAULT_SUMMARY_INTERVAL_SECS),
+                    &shutdown,
+                )
+                .await
+        })
+    };
+
+    let spread_recorder = Arc::new(SpreadRecorder::with_config(
+        _pool.clone(),
+        spread_recorder_config,
+    ));
+    let spread_recorder_clone = spread_recorder.clone();
+    let spread_recorder_handle = tokio::spawn(async move {
+        if let Err(e) = spread_recorder_clone.start().await {
+            error!("Spread recorder failed: {}", e);
         }
     });
 
+    let wallet_tracker_handle = match wallet_tracker {
+        Some(tracker) => {
+            let tracker_clone = tracker.clone();
+            let handle = tokio::spawn(async move {
+                if let Err(e) = tracker_clone.start().await {
+                    error!("Solana balance tracker failed: {}", e);
+                }
+            });
+            Some((tracker, handle))
+        }
+        None => {
+            info!("WALLET_PUBKEYS not set, Solana balance tracking disabled");
+            None
+        }
+    };
+
     // Wait for shutdown signal
     tokio::signal::ctrl_c().await?;
     // Stop screener gracefully
-    meteora_screener.stop().await?;
-    meteora_screener_handle.await?;
-    bybit_screener.stop().await?;
-    bybit_screener_handle.await?;
+    let failures = screener_tasks.stop_all().await;
+    if !failures.is_empty() {
+        let names: Vec<&str> = failures.iter().map(|f| f.name.as_str()).collect();
+        error!("Screeners failed during the run: {}", names.join(", "));
+    }
+    if let Some((client, handle)) = bybit_private_handle {
+        client.stop().await?;
+        handle.await?;
+    }
+    if let Some((tracker, handle)) = wallet_tracker_handle {
+        tracker.stop().await?;
+        handle.await?;
+    }
+    spread_recorder.stop().await?;
+    spread_recorder_handle.await?;
+    write_metrics_shutdown.cancel();
+    write_metrics_handle.await?;
 
     Ok(())
 }
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Balance of a coin on a CEX account, stored in the `cex_balances` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CEXBalance {
+    pub exchange: String,
+    pub coin: String,
+    /// Amount available for new orders
+    pub free: Decimal,
+    /// Amount held by open orders
+    pub locked: Decimal,
+    /// When the exchange reported the balance
+    pub update_time: DateTime<Utc>,
+    pub fetch_time: DateTime<Utc>,
+}
+
+/// Balance of a mint held by one of our Solana wallets, stored in the `onchain_balances` table.
+/// Every token account of the mint owned by the wallet is summed into one balance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OnchainBalance {
+    pub wallet: String,
+    /// Mint of the token, or the system program for native SOL
+    pub mint: String,
+    /// Symbol of the mint when it is configured, e.g. `USDC`
+    pub symbol: Option<String>,
+    /// Amount normalized by the mint decimals
+    pub amount: Decimal,
+    /// Slot the balance was read at
+    pub slot: u64,
+    pub fetch_time: DateTime<Utc>,
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Trading fee rate of the account on a CEX pair, stored in the `cex_fees` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CEXFee {
+    pub exchange: String,
+    pub trade_pair: String,
+    pub maker_fee_bps: Decimal,
+    pub taker_fee_bps: Decimal,
+    pub fetch_time: DateTime<Utc>,
+}
@@ -1,15 +1,21 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use tracing::info;
 
+/// Price levels of one side of a book, volume keyed by price
+pub type OrderBookLevels = BTreeMap<Decimal, Decimal>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderBook {
     pub exchange: String,
     pub symbol: String,
     pub last_update_ts: DateTime<Utc>,
-    pub bids: Vec<OrderBookItem>,
-    pub asks: Vec<OrderBookItem>,
+    /// Bids in ascending price order, iterate with `bid_levels` for best-first
+    pub bids: OrderBookLevels,
+    pub asks: OrderBookLevels,
 }
 
 impl OrderBook {
@@ -18,42 +24,151 @@ impl OrderBook {
             exchange: exchange.to_string(),
             symbol: symbol.to_string(),
             last_update_ts: Utc::now(),
-            bids: vec![],
-            asks: vec![],
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
         }
     }
 
+    /// Highest bid
+    pub fn best_bid(&self) -> Option<OrderBookItem> {
+        self.bid_levels().next()
+    }
+
+    /// Lowest ask
+    pub fn best_ask(&self) -> Option<OrderBookItem> {
+        self.ask_levels().next()
+    }
+
+    /// Bids from the highest price down
+    pub fn bid_levels(&self) -> impl Iterator<Item = OrderBookItem> + '_ {
+        self.bids.iter().rev().map(OrderBookItem::from)
+    }
+
+    /// Asks from the lowest price up
+    pub fn ask_levels(&self) -> impl Iterator<Item = OrderBookItem> + '_ {
+        self.asks.iter().map(OrderBookItem::from)
+    }
+
+    /// Bid volume priced within `bps` basis points below the best bid, zero for an empty side
+    pub fn bid_depth_within_bps(&self, bps: u32) -> Decimal {
+        let Some((&best, _)) = self.bids.last_key_value() else {
+            return Decimal::ZERO;
+        };
+        let floor = best - best * Decimal::from(bps) / Decimal::from(10_000);
+        self.bids.range(floor..).map(|(_, volume)| volume).sum()
+    }
+
+    /// Ask volume priced within `bps` basis points above the best ask, zero for an empty side
+    pub fn ask_depth_within_bps(&self, bps: u32) -> Decimal {
+        let Some((&best, _)) = self.asks.first_key_value() else {
+            return Decimal::ZERO;
+        };
+        let ceiling = best + best * Decimal::from(bps) / Decimal::from(10_000);
+        self.asks.range(..=ceiling).map(|(_, volume)| volume).sum()
+    }
+
+    /// Check that the book describes a possible market: every level has a positive volume
+    /// and the best bid is below the best ask. Levels are kept sorted by the maps.
+    pub fn validate(&self) -> Result<(), BookViolation> {
+        let mut levels = self.bids.iter().chain(self.asks.iter());
+        if let Some((&price, _)) = levels.find(|(_, volume)| **volume <= Decimal::ZERO) {
+            return Err(BookViolation::NonPositiveVolume { price });
+        }
+        match (self.bids.last_key_value(), self.asks.first_key_value()) {
+            (Some((&bid, _)), Some((&ask, _))) if bid == ask => {
+                Err(BookViolation::Locked { price: bid })
+            }
+            (Some((&bid, _)), Some((&ask, _))) if bid > ask => {
+                Err(BookViolation::Crossed { bid, ask })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Best `levels` levels of each side as `price x volume`, for logs
+    pub fn describe_top(&self, levels: usize) -> String {
+        let side = |items: Vec<OrderBookItem>| {
+            items
+                .iter()
+                .map(|item| format!("{} x {}", item.price, item.volume))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "bids [{}], asks [{}]",
+            side(self.bid_levels().take(levels).collect()),
+            side(self.ask_levels().take(levels).collect())
+        )
+    }
+
     pub fn log(&self) {
         info!("[{}] {}", self.exchange, self.symbol);
         info!(" bids:");
-        for bid in &self.bids {
+        for bid in self.bid_levels() {
             info!("     price={} volume={}", bid.price, bid.volume);
         }
         info!(" asks:");
-        for ask in &self.asks {
+        for ask in self.ask_levels() {
             info!("     price={} volume={}", ask.price, ask.volume);
         }
     }
 
-    pub fn merge_item(items: &mut Vec<OrderBookItem>, price: &str, volume: &str) {
+    /// Replace both sides with the levels of a full snapshot, as `(price, volume)`
+    pub fn replace_levels(&mut self, bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) {
+        self.bids = bids.iter().copied().collect();
+        self.asks = asks.iter().copied().collect();
+        self.last_update_ts = Utc::now();
+    }
+
+    /// Set the volume of a price level, removing the level when the volume is zero
+    pub fn merge_item(levels: &mut OrderBookLevels, price: &str, volume: &str) {
         let price_dec = price.parse::<Decimal>().unwrap();
-        if volume == "0" {
-            items.retain(|item| item.price != price_dec);
+        let volume_dec = volume.parse::<Decimal>().unwrap();
+        if volume_dec.is_zero() {
+            levels.remove(&price_dec);
         } else {
-            let volume_dec = volume.parse::<Decimal>().unwrap();
-            if let Some(item) = items.iter_mut().find(|item| item.price == price_dec) {
-                item.volume = volume_dec;
-            } else {
-                items.push(OrderBookItem {
-                    price: price_dec,
-                    volume: volume_dec,
-                });
+            levels.insert(price_dec, volume_dec);
+        }
+    }
+}
+
+/// Why a book cannot describe a real market
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookViolation {
+    /// Best bid above the best ask
+    Crossed { bid: Decimal, ask: Decimal },
+    /// Best bid equal to the best ask
+    Locked { price: Decimal },
+    /// A level with a zero or negative volume
+    NonPositiveVolume { price: Decimal },
+}
+
+impl BookViolation {
+    /// Short name used as a metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Crossed { .. } => "crossed",
+            Self::Locked { .. } => "locked",
+            Self::NonPositiveVolume { .. } => "non_positive_volume",
+        }
+    }
+}
+
+impl fmt::Display for BookViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Crossed { bid, ask } => {
+                write!(f, "crossed book, best bid {} > best ask {}", bid, ask)
+            }
+            Self::Locked { price } => write!(f, "locked book, best bid = best ask = {}", price),
+            Self::NonPositiveVolume { price } => {
+                write!(f, "level {} has a non-positive volume", price)
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderBookItem {
     pub price: Decimal,
     pub volume: Decimal,
@@ -68,7 +183,17 @@ impl OrderBookItem {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl From<(&Decimal, &Decimal)> for OrderBookItem {
+    fn from((price, volume): (&Decimal, &Decimal)) -> Self {
+        Self {
+            price: *price,
+            volume: *volume,
+        }
+    }
+}
+
+/// Market state of a CEX pair, stored in the `cex_markets` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CEXState {
     pub trade_id: String,
     pub exchange: String,
@@ -79,9 +204,103 @@ pub struct CEXState {
     pub ask_volume: Decimal,
     pub trade_time: DateTime<Utc>,
     pub fetch_time: DateTime<Utc>,
+    /// Delay between the exchange timestamp and local receipt of the message, when known
+    pub feed_latency_ms: Option<u64>,
+    /// Volume near the top of the book, when the exchange provides a full book
+    pub depth: Option<CEXDepth>,
+}
+
+/// Base volume within 5, 10 and 25 bps of the best bid and ask
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CEXDepth {
+    pub bid_5bps: Decimal,
+    pub ask_5bps: Decimal,
+    pub bid_10bps: Decimal,
+    pub ask_10bps: Decimal,
+    pub bid_25bps: Decimal,
+    pub ask_25bps: Decimal,
+}
+
+impl CEXDepth {
+    pub fn from_book(orderbook: &OrderBook) -> Self {
+        Self {
+            bid_5bps: orderbook.bid_depth_within_bps(5),
+            ask_5bps: orderbook.ask_depth_within_bps(5),
+            bid_10bps: orderbook.bid_depth_within_bps(10),
+            ask_10bps: orderbook.ask_depth_within_bps(10),
+            bid_25bps: orderbook.bid_depth_within_bps(25),
+            ask_25bps: orderbook.ask_depth_within_bps(25),
+        }
+    }
+}
+
+/// Best levels of both sides of a book, stored in the `cex_orderbook_snapshots` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CEXOrderBookSnapshot {
+    pub exchange: String,
+    pub trade_pair: String,
+    pub snapshot_time: DateTime<Utc>,
+    /// Best bid first
+    pub bids: Vec<OrderBookItem>,
+    /// Best ask first
+    pub asks: Vec<OrderBookItem>,
 }
 
+impl CEXOrderBookSnapshot {
+    /// Snapshot of at most `levels` levels per side of `orderbook`
+    pub fn from_book(orderbook: &OrderBook, levels: usize, snapshot_time: DateTime<Utc>) -> Self {
+        Self {
+            exchange: orderbook.exchange.clone(),
+            trade_pair: orderbook.symbol.clone(),
+            snapshot_time,
+            bids: orderbook.bid_levels().take(levels).collect(),
+            asks: orderbook.ask_levels().take(levels).collect(),
+        }
+    }
+}
+
+/// 24h ticker of a CEX symbol, stored in the `cex_tickers` table
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CEXTicker {
+    pub exchange: String,
+    pub trade_pair: String,
+    pub last_price: Decimal,
+    pub high_price_24h: Decimal,
+    pub low_price_24h: Decimal,
+    pub prev_price_24h: Decimal,
+    /// Traded base volume over the last 24h
+    pub volume_24h: Decimal,
+    /// Traded quote volume over the last 24h
+    pub turnover_24h: Decimal,
+    /// Relative price change over the last 24h, e.g. `0.0215` for +2.15%
+    pub price_change_24h: Decimal,
+    /// USD index price of the base token, when the exchange publishes one
+    pub usd_index_price: Option<Decimal>,
+    pub trade_time: DateTime<Utc>,
+    pub fetch_time: DateTime<Utc>,
+}
+
+/// Candle of a CEX symbol, stored in the `cex_klines` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CEXKline {
+    pub exchange: String,
+    pub trade_pair: String,
+    /// Candle length as named by the exchange, e.g. `1` for one minute
+    pub interval: String,
+    pub open_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Traded base volume within the candle
+    pub volume: Decimal,
+    /// Whether the candle is closed; a still-forming candle keeps changing
+    pub confirmed: bool,
+    pub fetch_time: DateTime<Utc>,
+}
+
+/// Quote of a DEX pair in one direction, stored in the `dex_markets` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DEXState {
     pub trade_id: String,
     pub exchange: String,
@@ -92,6 +311,18 @@ pub struct DEXState {
     pub trade_time: DateTime<Utc>,
     pub fetch_time: DateTime<Utc>,
     pub block_number: u64,
+    /// Deviation of `price` from the pool spot price, when known
+    pub price_impact_bps: Option<Decimal>,
+    /// Pool or market the price was quoted on, when the venue has several per pair
+    pub pool_address: Option<String>,
+    /// Whether the price was computed with a Clock sysvar that drifted from wall time
+    pub stale: bool,
+    /// Wall-clock duration of the account fetch behind the price, when measured
+    pub fetch_latency_ms: Option<u64>,
+    /// Multi-hop route the price was quoted through, e.g. `TRUMP-SOL-USDC`
+    pub route: Option<String>,
+    /// Venues an aggregator split the swap across, e.g. `Meteora DLMM 70% + Whirlpool 30% > Raydium CLMM 100%`
+    pub route_plan: Option<String>,
 }
 
 impl CEXState {
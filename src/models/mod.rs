@@ -1 +1,11 @@
+pub mod balance;
+pub mod candle;
+pub mod cex_fee;
 pub mod market;
+pub mod opportunity;
+pub mod order;
+pub mod pool_fee;
+pub mod pool_stats;
+pub mod quote_check;
+pub mod spread;
+pub mod trade_pair;
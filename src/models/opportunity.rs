@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle state of an arbitrage opportunity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpportunityStatus {
+    /// Seen by the engine and not acted on yet
+    Detected,
+    /// Gone before it was acted on
+    Expired,
+    /// Orders were placed for it
+    Acted,
+}
+
+impl OpportunityStatus {
+    /// Value of the `status` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Detected => "detected",
+            Self::Expired => "expired",
+            Self::Acted => "acted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "detected" => Ok(Self::Detected),
+            "expired" => Ok(Self::Expired),
+            "acted" => Ok(Self::Acted),
+            other => Err(format!("Unknown opportunity status {}", other)),
+        }
+    }
+}
+
+impl TryFrom<String> for OpportunityStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+/// Price difference of a pair between two venues, stored in the `opportunities` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Opportunity {
+    pub trade_pair: String,
+    /// Venue the base is bought on, e.g. `bybit` or `meteora`
+    pub buy_venue: String,
+    /// Venue the base is sold on
+    pub sell_venue: String,
+    pub buy_price: Decimal,
+    pub sell_price: Decimal,
+    /// Largest base size both sides can fill at these prices
+    pub max_size: Decimal,
+    /// Spread between the sell and buy price, in bps of the buy price
+    pub gross_spread_bps: Decimal,
+    /// Gross spread less fees, in bps of the buy price
+    pub net_spread_bps: Decimal,
+    /// `cex_markets` row the CEX side was priced from, if stored
+    pub cex_state_id: Option<u64>,
+    /// `dex_markets` row the DEX side was priced from, if stored
+    pub dex_state_id: Option<u64>,
+    pub detected_at: DateTime<Utc>,
+    pub status: OpportunityStatus,
+}
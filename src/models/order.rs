@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Order placed on a CEX and its latest known state, stored in the `orders` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    pub exchange: String,
+    /// Client order id chosen by the caller, unique per exchange
+    pub order_link_id: String,
+    /// Exchange order id, `None` until the exchange acknowledged the order
+    pub order_id: Option<String>,
+    pub trade_pair: String,
+    /// `Buy` or `Sell`
+    pub side: String,
+    /// `Limit` or `Market`
+    pub order_type: String,
+    /// Limit price, `None` for market orders
+    pub price: Option<Decimal>,
+    pub qty: Decimal,
+    /// Exchange status, or `Pending` while the placement is in flight
+    pub status: String,
+    pub cum_exec_qty: Decimal,
+    pub avg_price: Option<Decimal>,
+    pub reject_reason: Option<String>,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Fee rate of a DEX pool at the time of a quote, stored in the `dex_pool_fees` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolFee {
+    pub exchange: String,
+    pub trade_pair: String,
+    pub pool_address: String,
+    pub block_number: u64,
+    /// Static part of the fee, from the pool's base factor and bin step
+    pub base_fee_bps: Decimal,
+    /// Volatility-driven part of the fee
+    pub variable_fee_bps: Decimal,
+    /// Fee charged on a swap, capped at the program's maximum fee rate
+    pub total_fee_bps: Decimal,
+    pub fetch_time: DateTime<Utc>,
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Liquidity of a DEX pool at the time of a quote, stored in the `dex_pool_stats` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub exchange: String,
+    pub trade_pair: String,
+    pub pool_address: String,
+    pub block_number: u64,
+    /// Base token held by the pool, in UI units
+    pub base_amount: Decimal,
+    /// Quote token held by the pool, in UI units
+    pub quote_amount: Decimal,
+    /// Both amounts valued in quote token, when a price was available
+    pub liquidity: Option<Decimal>,
+    /// Whether the liquidity is below the screener's floor
+    pub degraded: bool,
+    pub fetch_time: DateTime<Utc>,
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Locally computed DEX quote cross-checked against a simulated swap,
+/// stored in the `dex_quote_checks` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteCheck {
+    pub exchange: String,
+    pub trade_pair: String,
+    pub pool_address: String,
+    pub block_number: u64,
+    pub amount_in: u64,
+    pub local_amount_out: u64,
+    pub simulated_amount_out: u64,
+    /// Deviation of the simulated output from the local one
+    pub deviation_bps: Decimal,
+    pub check_time: DateTime<Utc>,
+}
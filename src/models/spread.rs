@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Spreads between the latest CEX and DEX states of a pair, stored in the `spreads` table.
+/// Both directions are in bps of the price paid on the buying venue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spread {
+    pub trade_pair: String,
+    pub cex_exchange: String,
+    pub dex_exchange: String,
+    pub cex_bid: Decimal,
+    pub cex_ask: Decimal,
+    /// Price of the DEX `sell` quote
+    pub dex_bid: Decimal,
+    /// Price of the DEX `buy` quote
+    pub dex_ask: Decimal,
+    /// Buying at the DEX ask and selling at the CEX bid
+    pub spread_buy_dex_sell_cex_bps: Decimal,
+    /// Buying at the CEX ask and selling at the DEX bid
+    pub spread_buy_cex_sell_dex_bps: Decimal,
+    /// Age of the CEX state at `computed_at`
+    pub cex_age_ms: u64,
+    /// Age of the older of the two DEX quotes at `computed_at`
+    pub dex_age_ms: u64,
+    pub computed_at: DateTime<Utc>,
+}
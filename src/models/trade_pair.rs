@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Trade pair configured for a venue, stored in the `trade_pairs` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradePair {
+    pub id: i64,
+    pub symbol: String,
+    pub venue: String,
+    pub pool_pubkey: String,
+    /// Decimals of the base token
+    pub precision: u32,
+    /// Whether the base token is token X of the pool
+    pub base_is_x: bool,
+    /// Bin arrays fetched on each side of the active bin when quoting
+    pub bin_array_count: u8,
+    /// Mint of the base token, required when `auto_discover` is set
+    pub base_mint: Option<String>,
+    /// Mint of the quote token, required when `auto_discover` is set
+    pub quote_mint: Option<String>,
+    /// Whether pools are discovered from the mints instead of `pool_pubkey`
+    pub auto_discover: bool,
+    /// Name of the two-hop route quoted for the pair, e.g. `TRUMP-SOL-USDC`.
+    /// `pool_pubkey` and `base_is_x` then describe the first hop.
+    pub route: Option<String>,
+    /// Pool of the second hop, from the intermediate token to the quote token
+    pub route_pool_pubkey: Option<String>,
+    /// Whether the intermediate token is token X of the second hop pool
+    pub route_base_is_x: bool,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,456 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::depth_sync::{
+    BookSync, DepthSnapshot, DepthUpdate, SnapshotOutcome, SymbolBook, UpdateOutcome, parse_levels,
+    parse_update_id,
+};
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "backpack";
+/// Symbols streamed when `BACKPACK_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDC";
+/// Public websocket URL when `BACKPACK_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://ws.backpack.exchange";
+/// REST base URL when `BACKPACK_REST_URL` is unset
+const DEFAULT_REST_URL: &str = "https://api.backpack.exchange";
+/// Timeout of a REST depth snapshot request
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a snapshot older than the buffered updates is fetched again
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Silence after which the connection is considered dead. Backpack pings every 60s, so a
+/// quiet book still receives a frame within this window.
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Symbols and endpoints of the Backpack screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackpackConfig {
+    /// Internal symbols such as `TRUMPUSDC`, streamed as `TRUMP_USDC`
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+    pub rest_url: String,
+}
+
+impl BackpackConfig {
+    /// Read `BACKPACK_SYMBOLS` (comma-separated), `BACKPACK_WS_URL` and `BACKPACK_REST_URL`,
+    /// falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("BACKPACK_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("BACKPACK_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            rest_url: std::env::var("BACKPACK_REST_URL")
+                .unwrap_or_else(|_| DEFAULT_REST_URL.to_string()),
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to a Backpack symbol
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "BACKPACK_SYMBOLS entry `{}` has no Backpack symbol (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("BACKPACK_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// Depth stream of an internal symbol, `TRUMPUSDC` → `depth.TRUMP_USDC`
+fn depth_stream(symbol: &str) -> String {
+    let pair = to_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.to_string());
+    format!("depth.{}", pair)
+}
+
+/// `SUBSCRIBE` request of the depth streams of every symbol
+fn subscribe_request(symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols.iter().map(|s| depth_stream(s)).collect();
+    json!({"method": "SUBSCRIBE", "params": streams}).to_string()
+}
+
+/// Decoded websocket message
+#[derive(Debug, PartialEq)]
+enum BackpackFrame {
+    Update(DepthUpdate),
+    /// Error reply to a request
+    Error(String),
+    /// Subscription replies and other streams
+    Other,
+}
+
+/// Decode a websocket message, with the symbol of updates mapped to its internal symbol
+fn parse_frame(text: &str) -> Result<BackpackFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if !frame["error"].is_null() {
+        return Ok(BackpackFrame::Error(frame["error"].to_string()));
+    }
+    let data = &frame["data"];
+    if data["e"] != "depth" {
+        return Ok(BackpackFrame::Other);
+    }
+    let pair = data["s"].as_str().ok_or("missing symbol `s`")?;
+    // Backpack timestamps are in microseconds
+    let event_us = data["E"].as_i64().ok_or("missing event time `E`")?;
+    Ok(BackpackFrame::Update(DepthUpdate {
+        symbol: from_venue_symbol(EXCHANGE, pair)
+            .ok_or_else(|| format!("unexpected symbol `{}`", pair))?,
+        event_ms: event_us / 1_000,
+        first_update_id: parse_update_id(data, "U")?,
+        last_update_id: parse_update_id(data, "u")?,
+        bids: parse_levels(&data["b"])?,
+        asks: parse_levels(&data["a"])?,
+    }))
+}
+
+/// Decode a `/api/v1/depth` reply, whose `lastUpdateId` is a string
+fn parse_depth_snapshot(body: &Value) -> Result<DepthSnapshot, String> {
+    let last_update_id = body["lastUpdateId"]
+        .as_str()
+        .and_then(|id| id.parse().ok())
+        .ok_or("missing update id `lastUpdateId`")?;
+    Ok(DepthSnapshot {
+        last_update_id,
+        bids: parse_levels(&body["bids"])?,
+        asks: parse_levels(&body["asks"])?,
+    })
+}
+
+/// Fetch the depth snapshot of `symbol`
+async fn fetch_depth_snapshot(
+    http: &reqwest::Client,
+    rest_url: &str,
+    symbol: &str,
+) -> Result<DepthSnapshot, String> {
+    let pair = to_venue_symbol(EXCHANGE, symbol)
+        .ok_or_else(|| format!("no Backpack symbol for {}", symbol))?;
+    let url = format!(
+        "{}/api/v1/depth?symbol={}",
+        rest_url.trim_end_matches('/'),
+        pair
+    );
+    let response = http.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {}", status, body));
+    }
+    parse_depth_snapshot(&serde_json::from_str(&body).map_err(|e| e.to_string())?)
+}
+
+/// Fetches of REST depth snapshots in flight, with their symbol
+type SnapshotFetches = JoinSet<(String, Result<DepthSnapshot, String>)>;
+
+/// Backpack spot screener keeping a local book per symbol from the depth stream and REST
+/// snapshots, persisted as CEX market states
+pub struct BackpackScreener {
+    config: BackpackConfig,
+    shutdown: CancellationToken,
+    http: reqwest::Client,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl BackpackScreener {
+    /// Create a new BackpackScreener instance on the symbols of `BACKPACK_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, BackpackConfig::from_env()?)
+    }
+
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: BackpackConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder().timeout(REST_TIMEOUT).build()?;
+        let cex_writer = CexMarketWriter::for_database(db_pool, "backpack");
+        Ok(Self {
+            config,
+            shutdown: CancellationToken::new(),
+            http,
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Backpack", EXCHANGE, STALE_FEED_TIMEOUT),
+        })
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from a new snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Backpack screener for {:?} ({})...",
+            self.config.symbols, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Backpack screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(EXCHANGE, symbol)))
+            .collect();
+    }
+
+    /// Fetch the snapshot of `symbol` after `delay`
+    fn spawn_snapshot_fetch(&self, fetches: &mut SnapshotFetches, symbol: &str, delay: Duration) {
+        let http = self.http.clone();
+        let rest_url = self.config.rest_url.clone();
+        let symbol = symbol.to_string();
+        fetches.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let snapshot = fetch_depth_snapshot(&http, &rest_url, &symbol).await;
+            (symbol, snapshot)
+        });
+    }
+
+    /// Apply one text frame; returns whether it was a depth update
+    fn handle_frame(&self, fetches: &mut SnapshotFetches, text: &str) -> bool {
+        let update = match parse_frame(text) {
+            Ok(BackpackFrame::Update(update)) => update,
+            Ok(BackpackFrame::Error(detail)) => {
+                error!("Backpack websocket error: {}", detail);
+                return false;
+            }
+            Ok(BackpackFrame::Other) => {
+                debug!("Skipping Backpack frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed Backpack frame: {}", e);
+                return false;
+            }
+        };
+        let (symbol, update_id, event_ms) = (
+            update.symbol.clone(),
+            update.last_update_id,
+            update.event_ms,
+        );
+
+        let mut books = self.books.lock().unwrap();
+        // Updates of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&symbol) else {
+            return true;
+        };
+        match book.on_update(update) {
+            UpdateOutcome::Applied => self.persist(book, update_id, event_ms, fetches),
+            UpdateOutcome::Resync => {
+                warn!(
+                    "Backpack {} order book missed updates before {}, fetching a new snapshot",
+                    symbol, update_id
+                );
+                metrics::counter!("backpack_orderbook_gaps_total", "symbol" => symbol.clone())
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            }
+            UpdateOutcome::Buffered | UpdateOutcome::Skipped => {}
+        }
+        true
+    }
+
+    /// Rebuild a book from its snapshot, fetching it again when it is too old or failed
+    fn handle_snapshot(
+        &self,
+        fetches: &mut SnapshotFetches,
+        symbol: &str,
+        snapshot: Result<DepthSnapshot, String>,
+    ) {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("Backpack {} depth snapshot failed, retrying: {}", symbol, e);
+                metrics::counter!("backpack_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+                return;
+            }
+        };
+        let update_id = snapshot.last_update_id;
+
+        let mut books = self.books.lock().unwrap();
+        let Some(book) = books.get_mut(symbol) else {
+            return;
+        };
+        match book.on_snapshot(snapshot) {
+            SnapshotOutcome::Synced { replayed } => {
+                info!(
+                    "Backpack {} order book synced from a snapshot at {}, replayed {} updates",
+                    symbol, update_id, replayed
+                );
+                metrics::counter!("backpack_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "ok")
+                    .increment(1);
+                let BookSync::Synced(last) = book.sync else {
+                    return;
+                };
+                self.persist(book, last, Utc::now().timestamp_millis(), fetches);
+            }
+            SnapshotOutcome::Stale => {
+                debug!(
+                    "Backpack {} depth snapshot at {} is older than the buffered updates, retrying",
+                    symbol, update_id
+                );
+                metrics::counter!("backpack_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "stale")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+            }
+            SnapshotOutcome::Ignored => {}
+        }
+    }
+
+    /// Persist the top of book when it changed. A book that cannot describe a real market is
+    /// dropped and rebuilt from a new snapshot instead.
+    fn persist(
+        &self,
+        book: &mut SymbolBook,
+        update_id: u64,
+        event_ms: i64,
+        fetches: &mut SnapshotFetches,
+    ) {
+        let symbol = book.orderbook.symbol.clone();
+        if let Err(violation) = book.orderbook.validate() {
+            error!(
+                "Backpack {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "backpack_invalid_books_total",
+                "symbol" => symbol.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            book.resync_from(Vec::new());
+            self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            return;
+        }
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: update_id.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol,
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(event_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - event_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for BackpackScreener {
+    /// Empty the books, connect, subscribe every symbol, fetch their snapshots and apply the
+    /// updates until the connection drops or the screener stops. `feed` counts the updates
+    /// delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        ws.send(Message::Text(subscribe_request(&self.config.symbols)))
+            .await
+            .map_err(|e| format!("subscribe failed: {}", e))?;
+        info!(
+            "Backpack websocket connected, subscribed to {:?}",
+            self.config.symbols
+        );
+
+        // Updates are buffered from the subscription on, so the snapshots are fetched after it
+        let mut fetches = SnapshotFetches::new();
+        for symbol in &self.config.symbols {
+            self.spawn_snapshot_fetch(&mut fetches, symbol, Duration::ZERO);
+        }
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                Some(fetched) = fetches.join_next(), if !fetches.is_empty() => {
+                    if let Ok((symbol, snapshot)) = fetched {
+                        self.handle_snapshot(&mut fetches, &symbol, snapshot);
+                    }
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            if self.handle_frame(&mut fetches, &text) {
+                                feed.delivered();
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "backpack_tests.rs"]
+mod backpack_tests;
@@ -0,0 +1,217 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// `depth.TRUMP_USDC` messages recorded in order: one already in the snapshot, one
+/// straddling it, two following it, then a gap
+const UPDATE_FIXTURES: [&str; 5] = [
+    r#"{"stream":"depth.TRUMP_USDC","data":{"e":"depth","E":1716863719050000,"s":"TRUMP_USDC","a":[],"b":[["10.21","4"]],"U":2211010,"u":2211019,"T":1716863719049000}}"#,
+    r#"{"stream":"depth.TRUMP_USDC","data":{"e":"depth","E":1716863719100000,"s":"TRUMP_USDC","a":[["10.27","3"]],"b":[["10.24","0"],["10.25","1.5"]],"U":2211020,"u":2211025,"T":1716863719099000}}"#,
+    r#"{"stream":"depth.TRUMP_USDC","data":{"e":"depth","E":1716863719200000,"s":"TRUMP_USDC","a":[],"b":[["10.25","2"]],"U":2211026,"u":2211028,"T":1716863719199000}}"#,
+    r#"{"stream":"depth.TRUMP_USDC","data":{"e":"depth","E":1716863719300000,"s":"TRUMP_USDC","a":[["10.28","0"],["10.26","1"]],"b":[],"U":2211029,"u":2211029,"T":1716863719299000}}"#,
+    r#"{"stream":"depth.TRUMP_USDC","data":{"e":"depth","E":1716863719400000,"s":"TRUMP_USDC","a":[],"b":[["10.2","9"]],"U":2211035,"u":2211036,"T":1716863719399000}}"#,
+];
+
+/// `/api/v1/depth` reply recorded for TRUMP_USDC
+const SNAPSHOT_FIXTURE: &str = r#"{"asks":[["10.27","4"],["10.28","5"]],"bids":[["10.23","3"],["10.24","2"]],"lastUpdateId":"2211024","timestamp":1716863719110000}"#;
+
+fn update(index: usize) -> DepthUpdate {
+    match parse_frame(UPDATE_FIXTURES[index]).unwrap() {
+        BackpackFrame::Update(update) => update,
+        other => panic!("not a depth update: {:?}", other),
+    }
+}
+
+fn snapshot_at(last_update_id: u64) -> DepthSnapshot {
+    DepthSnapshot {
+        last_update_id,
+        ..parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap()).unwrap()
+    }
+}
+
+fn prices(levels: &market::OrderBookLevels) -> Vec<Decimal> {
+    levels.keys().copied().collect()
+}
+
+impl TestScreener for BackpackScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = BackpackScreener {
+            config: BackpackConfig {
+                symbols: vec!["TRUMPUSDC".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+                rest_url: "http://127.0.0.1:1".to_string(),
+            },
+            shutdown: CancellationToken::new(),
+            http: reqwest::Client::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Backpack", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn depth_messages_are_decoded_with_internal_symbols() {
+    assert_eq!(
+        update(1),
+        DepthUpdate {
+            symbol: "TRUMPUSDC".to_string(),
+            event_ms: 1716863719100,
+            first_update_id: 2211020,
+            last_update_id: 2211025,
+            bids: vec![
+                (decimal("10.24"), Decimal::ZERO),
+                (decimal("10.25"), decimal("1.5"))
+            ],
+            asks: vec![(decimal("10.27"), decimal("3"))],
+        }
+    );
+    assert_eq!(
+        parse_frame(r#"{"stream":"trade.TRUMP_USDC","data":{"e":"trade","s":"TRUMP_USDC"}}"#),
+        Ok(BackpackFrame::Other)
+    );
+    assert!(matches!(
+        parse_frame(r#"{"id":null,"error":{"code":4006,"message":"Invalid stream"}}"#),
+        Ok(BackpackFrame::Error(detail)) if detail.contains("Invalid stream")
+    ));
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace("TRUMP_USDC", "TRUMP_USDC_PERP")).is_err());
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace(r#""U":2211026,"#, "")).is_err());
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace(r#""2""#, r#""two""#)).is_err());
+}
+
+#[test]
+fn requests_and_symbols_follow_backpack_conventions() {
+    let symbols = vec!["TRUMPUSDC".to_string(), "SOLUSDC".to_string()];
+    let subscribe: Value = serde_json::from_str(&subscribe_request(&symbols)).unwrap();
+    assert_eq!(
+        subscribe,
+        json!({"method": "SUBSCRIBE", "params": ["depth.TRUMP_USDC", "depth.SOL_USDC"]})
+    );
+    assert_eq!(
+        parse_symbols(" trumpusdc,SOLUSDC,,TRUMPUSDC ").unwrap(),
+        ["TRUMPUSDC", "SOLUSDC"]
+    );
+    assert!(parse_symbols("TRUMP").is_err());
+    assert!(parse_symbols(" , ").is_err());
+    // Snapshot ids are strings, unlike the update ids of the stream
+    assert_eq!(
+        parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap())
+            .unwrap()
+            .last_update_id,
+        2211024
+    );
+    assert!(
+        parse_depth_snapshot(
+            &serde_json::from_str(r#"{"asks":[],"bids":[],"lastUpdateId":2211024}"#).unwrap()
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn snapshot_syncs_the_buffered_sequence() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    for index in 0..3 {
+        assert_eq!(book.on_update(update(index)), UpdateOutcome::Buffered);
+    }
+
+    // The first update is older than the snapshot, the second one straddles its id
+    assert_eq!(
+        book.on_snapshot(snapshot_at(2211024)),
+        SnapshotOutcome::Synced { replayed: 2 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(2211028));
+    assert_eq!(
+        prices(&book.orderbook.bids),
+        [decimal("10.23"), decimal("10.25")]
+    );
+
+    assert_eq!(book.on_update(update(3)), UpdateOutcome::Applied);
+    assert_eq!(
+        prices(&book.orderbook.asks),
+        [decimal("10.26"), decimal("10.27")]
+    );
+    assert_eq!(book.on_update(update(4)), UpdateOutcome::Resync);
+    assert_eq!(
+        book.on_snapshot(snapshot_at(2211029)),
+        SnapshotOutcome::Stale
+    );
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_backpack_states() {
+    let (screener, sink) = build_screener_with_sink::<BackpackScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    assert!(screener.handle_frame(&mut fetches, UPDATE_FIXTURES[1]));
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDC", Ok(snapshot_at(2211024)));
+    screener.cex_writer.flush().await;
+    for fixture in &UPDATE_FIXTURES[2..4] {
+        assert!(screener.handle_frame(&mut fetches, fixture));
+        screener.cex_writer.flush().await;
+    }
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "backpack" && state.trade_pair == "TRUMPUSDC")
+    );
+    assert_eq!(states[0].trade_id, "2211025");
+    assert_eq!(
+        (states[0].bid_price, states[0].ask_price),
+        (decimal("10.25"), decimal("10.27"))
+    );
+    assert_eq!(
+        (states[2].trade_id.as_str(), states[2].ask_price),
+        ("2211029", decimal("10.26"))
+    );
+    assert_eq!(states[2].trade_time.timestamp_millis(), 1716863719300);
+    assert!(fetches.is_empty());
+
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[4]);
+    assert_eq!(fetches.len(), 1);
+    fetches.abort_all();
+}
+
+#[tokio::test]
+async fn depth_snapshots_are_fetched_by_backpack_symbol() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/depth"))
+        .and(query_param("symbol", "TRUMP_USDC"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SNAPSHOT_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/depth"))
+        .and(query_param("symbol", "NOPE_USDC"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_string(
+                r#"{"code":"INVALID_CLIENT_REQUEST","message":"Market not found"}"#,
+            ),
+        )
+        .mount(&server)
+        .await;
+    let http = reqwest::Client::new();
+
+    assert_eq!(
+        fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDC").await,
+        Ok(snapshot_at(2211024))
+    );
+    let error = fetch_depth_snapshot(&http, &server.uri(), "NOPEUSDC")
+        .await
+        .unwrap_err();
+    assert!(error.contains("Market not found"), "{}", error);
+}
@@ -0,0 +1,451 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::depth_sync::{
+    BookSync, DepthSnapshot, DepthUpdate, SnapshotOutcome, SymbolBook, UpdateOutcome, parse_levels,
+    parse_update_id,
+};
+use super::symbols::{from_venue_symbol, is_valid_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "binance";
+/// Symbols streamed when `BINANCE_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDC,TRUMPUSDT";
+/// Spot websocket base URL when `BINANCE_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://stream.binance.com:9443";
+/// Spot REST base URL when `BINANCE_REST_URL` is unset
+const DEFAULT_REST_URL: &str = "https://api.binance.com";
+/// Levels per side of the REST depth snapshot when `BINANCE_SNAPSHOT_LIMIT` is unset
+const DEFAULT_SNAPSHOT_LIMIT: u32 = 1_000;
+/// Largest depth snapshot Binance serves
+const MAX_SNAPSHOT_LIMIT: u32 = 5_000;
+/// Timeout of a REST depth snapshot request
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a snapshot older than the buffered updates is fetched again
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Silence after which the connection is considered dead; Binance pings every 20 seconds
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Symbols and endpoints of the Binance screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinanceConfig {
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+    pub rest_url: String,
+    /// Levels per side of the REST depth snapshots
+    pub snapshot_limit: u32,
+}
+
+impl BinanceConfig {
+    /// Read `BINANCE_SYMBOLS` (comma-separated), `BINANCE_WS_URL`, `BINANCE_REST_URL` and
+    /// `BINANCE_SNAPSHOT_LIMIT`, falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("BINANCE_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        let snapshot_limit = match std::env::var("BINANCE_SNAPSHOT_LIMIT") {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|limit| (1..=MAX_SNAPSHOT_LIMIT).contains(limit))
+                .ok_or_else(|| {
+                    format!(
+                        "BINANCE_SNAPSHOT_LIMIT `{}` is not between 1 and {}",
+                        value, MAX_SNAPSHOT_LIMIT
+                    )
+                })?,
+            Err(_) => DEFAULT_SNAPSHOT_LIMIT,
+        };
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("BINANCE_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            rest_url: std::env::var("BINANCE_REST_URL")
+                .unwrap_or_else(|_| DEFAULT_REST_URL.to_string()),
+            snapshot_limit,
+        })
+    }
+
+    /// Combined stream URL of the 100ms diff depth stream of every symbol
+    fn stream_url(&self) -> String {
+        let streams: Vec<String> = self
+            .symbols
+            .iter()
+            .map(|symbol| format!("{}@depth@100ms", binance_symbol(symbol).to_lowercase()))
+            .collect();
+        format!(
+            "{}/stream?streams={}",
+            self.ws_url.trim_end_matches('/'),
+            streams.join("/")
+        )
+    }
+}
+
+/// Parse comma-separated symbols such as `TRUMPUSDC,TRUMPUSDT`
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if !is_valid_symbol(&symbol) {
+            return Err(format!("BINANCE_SYMBOLS entry `{}` is not a valid symbol", symbol).into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("BINANCE_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// Binance symbol of an internal symbol. Symbols quoted in an asset the symbol registry does
+/// not know are passed through, as Binance spells them like internal symbols.
+fn binance_symbol(symbol: &str) -> String {
+    to_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.to_string())
+}
+
+/// Decode a combined stream frame; frames other than depth updates, such as replies to
+/// requests, are `None`
+fn parse_depth_update(text: &str) -> Result<Option<DepthUpdate>, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let data = &frame["data"];
+    if data["e"] != "depthUpdate" {
+        return Ok(None);
+    }
+    let symbol = data["s"].as_str().ok_or("missing symbol `s`")?;
+    Ok(Some(DepthUpdate {
+        symbol: from_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.to_string()),
+        event_ms: data["E"].as_i64().ok_or("missing event time `E`")?,
+        first_update_id: parse_update_id(data, "U")?,
+        last_update_id: parse_update_id(data, "u")?,
+        bids: parse_levels(&data["b"])?,
+        asks: parse_levels(&data["a"])?,
+    }))
+}
+
+/// Decode a `/api/v3/depth` reply
+fn parse_depth_snapshot(body: &Value) -> Result<DepthSnapshot, String> {
+    Ok(DepthSnapshot {
+        last_update_id: parse_update_id(body, "lastUpdateId")?,
+        bids: parse_levels(&body["bids"])?,
+        asks: parse_levels(&body["asks"])?,
+    })
+}
+
+/// Fetch the depth snapshot of `symbol`
+async fn fetch_depth_snapshot(
+    http: &reqwest::Client,
+    rest_url: &str,
+    symbol: &str,
+    limit: u32,
+) -> Result<DepthSnapshot, String> {
+    let url = format!(
+        "{}/api/v3/depth?symbol={}&limit={}",
+        rest_url.trim_end_matches('/'),
+        binance_symbol(symbol),
+        limit
+    );
+    let response = http.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {} {}", status, body["code"], body["msg"]));
+    }
+    parse_depth_snapshot(&body)
+}
+
+/// Fetches of REST depth snapshots in flight, with their symbol
+type SnapshotFetches = JoinSet<(String, Result<DepthSnapshot, String>)>;
+
+/// Binance spot screener keeping a local book per symbol from the diff depth stream and
+/// REST snapshots, persisted as CEX market states
+pub struct BinanceScreener {
+    config: BinanceConfig,
+    shutdown: CancellationToken,
+    http: reqwest::Client,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl BinanceScreener {
+    /// Create a new BinanceScreener instance on the symbols of `BINANCE_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, BinanceConfig::from_env()?)
+    }
+
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: BinanceConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder().timeout(REST_TIMEOUT).build()?;
+        let cex_writer = CexMarketWriter::for_database(db_pool, "binance");
+        Ok(Self {
+            config,
+            shutdown: CancellationToken::new(),
+            http,
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Binance", EXCHANGE, STALE_FEED_TIMEOUT),
+        })
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from a new snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Binance screener for {:?} ({})...",
+            self.config.symbols, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Binance screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(EXCHANGE, symbol)))
+            .collect();
+    }
+
+    /// Fetch the snapshot of `symbol` after `delay`
+    fn spawn_snapshot_fetch(&self, fetches: &mut SnapshotFetches, symbol: &str, delay: Duration) {
+        let http = self.http.clone();
+        let rest_url = self.config.rest_url.clone();
+        let limit = self.config.snapshot_limit;
+        let symbol = symbol.to_string();
+        fetches.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let snapshot = fetch_depth_snapshot(&http, &rest_url, &symbol, limit).await;
+            (symbol, snapshot)
+        });
+    }
+
+    /// Apply one text frame; returns whether it was a depth update
+    fn handle_frame(&self, fetches: &mut SnapshotFetches, text: &str) -> bool {
+        let update = match parse_depth_update(text) {
+            Ok(Some(update)) => update,
+            Ok(None) => {
+                debug!("Skipping Binance frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed Binance frame: {}", e);
+                return false;
+            }
+        };
+        let (symbol, update_id, event_ms) = (
+            update.symbol.clone(),
+            update.last_update_id,
+            update.event_ms,
+        );
+
+        let mut books = self.books.lock().unwrap();
+        // Updates of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&symbol) else {
+            return true;
+        };
+        match book.on_update(update) {
+            UpdateOutcome::Applied => self.persist(book, update_id, event_ms, fetches),
+            UpdateOutcome::Resync => {
+                warn!(
+                    "Binance {} order book missed updates before {}, fetching a new snapshot",
+                    symbol, update_id
+                );
+                metrics::counter!("binance_orderbook_gaps_total", "symbol" => symbol.clone())
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            }
+            UpdateOutcome::Buffered | UpdateOutcome::Skipped => {}
+        }
+        true
+    }
+
+    /// Rebuild a book from its snapshot, fetching it again when it is too old or failed
+    fn handle_snapshot(
+        &self,
+        fetches: &mut SnapshotFetches,
+        symbol: &str,
+        snapshot: Result<DepthSnapshot, String>,
+    ) {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("Binance {} depth snapshot failed, retrying: {}", symbol, e);
+                metrics::counter!("binance_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+                return;
+            }
+        };
+        let update_id = snapshot.last_update_id;
+
+        let mut books = self.books.lock().unwrap();
+        let Some(book) = books.get_mut(symbol) else {
+            return;
+        };
+        match book.on_snapshot(snapshot) {
+            SnapshotOutcome::Synced { replayed } => {
+                info!(
+                    "Binance {} order book synced from a snapshot at {}, replayed {} updates",
+                    symbol, update_id, replayed
+                );
+                metrics::counter!("binance_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "ok")
+                    .increment(1);
+                let BookSync::Synced(last) = book.sync else {
+                    return;
+                };
+                self.persist(book, last, Utc::now().timestamp_millis(), fetches);
+            }
+            SnapshotOutcome::Stale => {
+                debug!(
+                    "Binance {} depth snapshot at {} is older than the buffered updates, retrying",
+                    symbol, update_id
+                );
+                metrics::counter!("binance_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "stale")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+            }
+            SnapshotOutcome::Ignored => {}
+        }
+    }
+
+    /// Persist the top of book when it changed. A book that cannot describe a real market is
+    /// dropped and rebuilt from a new snapshot instead.
+    fn persist(
+        &self,
+        book: &mut SymbolBook,
+        update_id: u64,
+        event_ms: i64,
+        fetches: &mut SnapshotFetches,
+    ) {
+        let symbol = book.orderbook.symbol.clone();
+        if let Err(violation) = book.orderbook.validate() {
+            error!(
+                "Binance {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "binance_invalid_books_total",
+                "symbol" => symbol.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            book.resync_from(Vec::new());
+            self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            return;
+        }
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: update_id.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol,
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(event_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - event_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for BinanceScreener {
+    /// Empty the books, connect, fetch a snapshot of every book and apply the stream until the
+    /// connection drops or the screener stops. `feed` counts the depth updates delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.stream_url())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        info!(
+            "Binance websocket connected, streaming {:?}",
+            self.config.symbols
+        );
+
+        let mut fetches = SnapshotFetches::new();
+        for symbol in &self.config.symbols {
+            self.spawn_snapshot_fetch(&mut fetches, symbol, Duration::ZERO);
+        }
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                Some(fetched) = fetches.join_next(), if !fetches.is_empty() => {
+                    if let Ok((symbol, snapshot)) = fetched {
+                        self.handle_snapshot(&mut fetches, &symbol, snapshot);
+                    }
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            if self.handle_frame(&mut fetches, &text) {
+                                feed.delivered();
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "binance_tests.rs"]
+mod binance_tests;
@@ -0,0 +1,301 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::screeners::depth_sync::{UpdateCheck, check_update};
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Diff depth frames of the combined stream, recorded in order for TRUMPUSDC
+const UPDATE_FIXTURES: [&str; 4] = [
+    r#"{"stream":"trumpusdc@depth@100ms","data":{"e":"depthUpdate","E":1716863719100,"s":"TRUMPUSDC","U":157,"u":160,"b":[["10.240","0.00"],["10.250","1.50"]],"a":[["10.270","3.00"]]}}"#,
+    r#"{"stream":"trumpusdc@depth@100ms","data":{"e":"depthUpdate","E":1716863719200,"s":"TRUMPUSDC","U":161,"u":163,"b":[["10.250","2.00"]],"a":[]}}"#,
+    r#"{"stream":"trumpusdc@depth@100ms","data":{"e":"depthUpdate","E":1716863719300,"s":"TRUMPUSDC","U":164,"u":164,"b":[],"a":[["10.280","0"],["10.260","1.00"]]}}"#,
+    r#"{"stream":"trumpusdc@depth@100ms","data":{"e":"depthUpdate","E":1716863719400,"s":"TRUMPUSDC","U":170,"u":172,"b":[["10.200","9.00"]],"a":[]}}"#,
+];
+
+/// `/api/v3/depth` reply recorded for TRUMPUSDC
+const SNAPSHOT_FIXTURE: &str = r#"{"lastUpdateId":158,"bids":[["10.240","2.00"],["10.230","3.00"]],"asks":[["10.270","4.00"],["10.280","5.00"]]}"#;
+
+fn update(index: usize) -> DepthUpdate {
+    parse_depth_update(UPDATE_FIXTURES[index]).unwrap().unwrap()
+}
+
+fn snapshot() -> DepthSnapshot {
+    parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap()).unwrap()
+}
+
+fn snapshot_at(last_update_id: u64) -> DepthSnapshot {
+    DepthSnapshot {
+        last_update_id,
+        ..snapshot()
+    }
+}
+
+fn prices(levels: &market::OrderBookLevels) -> Vec<Decimal> {
+    levels.keys().copied().collect()
+}
+
+fn config(rest_url: &str) -> BinanceConfig {
+    BinanceConfig {
+        symbols: vec!["TRUMPUSDC".to_string()],
+        ws_url: DEFAULT_WS_URL.to_string(),
+        rest_url: rest_url.to_string(),
+        snapshot_limit: 100,
+    }
+}
+
+impl TestScreener for BinanceScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = BinanceScreener {
+            config: config("http://127.0.0.1:1"),
+            shutdown: CancellationToken::new(),
+            http: reqwest::Client::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Binance", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn depth_updates_are_decoded_from_the_combined_stream() {
+    assert_eq!(
+        update(0),
+        DepthUpdate {
+            symbol: "TRUMPUSDC".to_string(),
+            event_ms: 1716863719100,
+            first_update_id: 157,
+            last_update_id: 160,
+            bids: vec![
+                (decimal("10.24"), Decimal::ZERO),
+                (decimal("10.25"), decimal("1.5"))
+            ],
+            asks: vec![(decimal("10.27"), decimal("3"))],
+        }
+    );
+    assert_eq!(parse_depth_update(r#"{"result":null,"id":1}"#), Ok(None));
+    let malformed = UPDATE_FIXTURES[1].replace(r#""2.00""#, r#""two""#);
+    assert!(parse_depth_update(&malformed).is_err());
+    let missing_id = UPDATE_FIXTURES[1].replace(r#""U":161,"#, "");
+    assert!(parse_depth_update(&missing_id).is_err());
+}
+
+#[test]
+fn stream_url_and_symbols_follow_binance_conventions() {
+    let config = BinanceConfig {
+        symbols: vec!["TRUMPUSDC".to_string(), "TRUMPUSDT".to_string()],
+        ..config(DEFAULT_REST_URL)
+    };
+
+    assert_eq!(
+        config.stream_url(),
+        "wss://stream.binance.com:9443/stream?streams=trumpusdc@depth@100ms/trumpusdt@depth@100ms"
+    );
+    assert_eq!(
+        parse_symbols(" trumpusdc,TRUMPUSDT,,TRUMPUSDC ").unwrap(),
+        ["TRUMPUSDC", "TRUMPUSDT"]
+    );
+    assert!(parse_symbols("TRUMP-USDC").is_err());
+    assert!(parse_symbols(" , ").is_err());
+}
+
+#[test]
+fn check_update_follows_the_last_update_id() {
+    // Snapshot at 158: the first event may start before it, as long as it reaches past it
+    assert_eq!(check_update(158, &update(0)), UpdateCheck::Apply);
+    assert_eq!(check_update(160, &update(0)), UpdateCheck::Skip);
+    assert_eq!(check_update(160, &update(1)), UpdateCheck::Apply);
+    assert_eq!(check_update(163, &update(2)), UpdateCheck::Apply);
+    assert_eq!(check_update(164, &update(3)), UpdateCheck::Gap);
+    assert_eq!(check_update(155, &update(0)), UpdateCheck::Gap);
+}
+
+#[test]
+fn snapshot_replays_the_buffered_updates_it_does_not_contain() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    for index in 0..3 {
+        assert_eq!(book.on_update(update(index)), UpdateOutcome::Buffered);
+    }
+
+    assert_eq!(
+        book.on_snapshot(snapshot()),
+        SnapshotOutcome::Synced { replayed: 3 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(164));
+    // 10.24 was removed by the first update, 10.28 by the third
+    assert_eq!(
+        prices(&book.orderbook.bids),
+        [decimal("10.23"), decimal("10.25")]
+    );
+    assert_eq!(
+        prices(&book.orderbook.asks),
+        [decimal("10.26"), decimal("10.27")]
+    );
+    assert_eq!(book.orderbook.bids[&decimal("10.25")], decimal("2"));
+    assert_eq!(book.on_snapshot(snapshot()), SnapshotOutcome::Ignored);
+}
+
+#[test]
+fn snapshot_drops_the_updates_it_already_contains() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    book.on_update(update(0));
+    book.on_update(update(1));
+
+    assert_eq!(
+        book.on_snapshot(snapshot_at(161)),
+        SnapshotOutcome::Synced { replayed: 1 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(163));
+
+    // Without buffered updates the snapshot is applied as is
+    let mut quiet = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    assert_eq!(
+        quiet.on_snapshot(snapshot()),
+        SnapshotOutcome::Synced { replayed: 0 }
+    );
+    assert_eq!(quiet.on_update(update(0)), UpdateOutcome::Applied);
+}
+
+#[test]
+fn snapshot_older_than_the_buffer_is_fetched_again() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    book.on_update(update(1));
+    book.on_update(update(2));
+
+    assert_eq!(book.on_snapshot(snapshot_at(158)), SnapshotOutcome::Stale);
+    assert_eq!(
+        book.sync,
+        BookSync::AwaitingSnapshot(vec![update(1), update(2)])
+    );
+    assert!(book.orderbook.bids.is_empty());
+
+    assert_eq!(
+        book.on_snapshot(snapshot_at(160)),
+        SnapshotOutcome::Synced { replayed: 2 }
+    );
+}
+
+#[test]
+fn gap_in_the_buffer_waits_for_a_newer_snapshot() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    book.on_update(update(0));
+    book.on_update(update(1));
+    book.on_update(update(3));
+
+    assert_eq!(book.on_snapshot(snapshot()), SnapshotOutcome::Stale);
+    assert_eq!(book.sync, BookSync::AwaitingSnapshot(vec![update(3)]));
+    assert!(book.orderbook.bids.is_empty());
+}
+
+#[test]
+fn gap_on_a_synced_book_drops_it_until_the_next_snapshot() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    book.on_snapshot(snapshot());
+    assert_eq!(book.on_update(update(0)), UpdateOutcome::Applied);
+    assert_eq!(book.on_update(update(0)), UpdateOutcome::Skipped);
+
+    assert_eq!(book.on_update(update(3)), UpdateOutcome::Resync);
+    assert_eq!(book.sync, BookSync::AwaitingSnapshot(vec![update(3)]));
+    assert!(book.orderbook.bids.is_empty() && book.orderbook.asks.is_empty());
+    assert_eq!(
+        book.on_snapshot(snapshot_at(171)),
+        SnapshotOutcome::Synced { replayed: 1 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(172));
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_binance_states() {
+    let (screener, sink) = build_screener_with_sink::<BinanceScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    assert!(screener.handle_frame(&mut fetches, UPDATE_FIXTURES[0]));
+    assert!(!screener.handle_frame(&mut fetches, r#"{"result":null,"id":1}"#));
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDC", Ok(snapshot()));
+    screener.cex_writer.flush().await;
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[1]);
+    screener.cex_writer.flush().await;
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[2]);
+    screener.cex_writer.flush().await;
+    // Only a level behind the top changed: nothing new to persist
+    let behind_top = UPDATE_FIXTURES[2].replace(
+        r#""U":164,"u":164,"b":[]"#,
+        r#""U":165,"u":165,"b":[["10.230","7.00"]]"#,
+    );
+    assert!(screener.handle_frame(&mut fetches, &behind_top));
+    screener.cex_writer.flush().await;
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(states.iter().all(|state| state.exchange == "binance"));
+    assert_eq!(states[0].trade_id, "160");
+    assert_eq!(
+        (states[0].bid_price, states[0].ask_price),
+        (decimal("10.25"), decimal("10.27"))
+    );
+    assert_eq!(states[1].bid_volume, decimal("2"));
+    assert_eq!(states[2].trade_id, "164");
+    assert_eq!(states[2].ask_price, decimal("10.26"));
+    assert!(fetches.is_empty());
+}
+
+#[tokio::test]
+async fn failed_and_crossed_snapshots_are_fetched_again() {
+    let (screener, sink) = build_screener_with_sink::<BinanceScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDC", Err("HTTP 503".to_string()));
+    assert_eq!(fetches.len(), 1);
+
+    let crossed = DepthSnapshot {
+        bids: vec![(decimal("10.30"), decimal("1"))],
+        ..snapshot()
+    };
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDC", Ok(crossed));
+    assert_eq!(fetches.len(), 2);
+    assert_eq!(
+        screener.books.lock().unwrap()["TRUMPUSDC"].sync,
+        BookSync::AwaitingSnapshot(Vec::new())
+    );
+    screener.cex_writer.flush().await;
+    assert!(sink.states.lock().unwrap().is_empty());
+    fetches.abort_all();
+}
+
+#[tokio::test]
+async fn depth_snapshots_are_fetched_over_rest() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .and(query_param("symbol", "TRUMPUSDC"))
+        .and(query_param("limit", "100"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SNAPSHOT_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .and(query_param("symbol", "NOSUCHPAIR"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_string(r#"{"code":-1121,"msg":"Invalid symbol."}"#),
+        )
+        .mount(&server)
+        .await;
+    let http = reqwest::Client::new();
+
+    assert_eq!(
+        fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDC", 100).await,
+        Ok(snapshot())
+    );
+    let error = fetch_depth_snapshot(&http, &server.uri(), "NOSUCHPAIR", 100)
+        .await
+        .unwrap_err();
+    assert!(error.contains("-1121"), "{}", error);
+}
@@ -0,0 +1,509 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::okx::{Levels, apply_levels, checksum, parse_levels};
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "bitget";
+/// Symbols streamed when `BITGET_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDT";
+/// Public websocket URL when `BITGET_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://ws.bitget.com/v2/ws/public";
+/// Instrument type of the spot books
+const INST_TYPE: &str = "SPOT";
+/// Full-depth order book channel with checksums
+const BOOKS_CHANNEL: &str = "books";
+/// Bitget closes connections that do not send a `ping` every 30 seconds
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Silence after which the connection is considered dead
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Symbols and endpoint of the Bitget screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitgetConfig {
+    /// Internal symbols such as `TRUMPUSDT`, which are also Bitget's spot instrument ids
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+}
+
+impl BitgetConfig {
+    /// Read `BITGET_SYMBOLS` (comma-separated internal symbols) and `BITGET_WS_URL`, falling
+    /// back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("BITGET_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("BITGET_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must have a known quote asset
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "BITGET_SYMBOLS entry `{}` is not a spot symbol (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("BITGET_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// `subscribe` or `unsubscribe` request of the books channel of `symbols`
+fn books_request(op: &str, symbols: &[String]) -> String {
+    let args: Vec<Value> = symbols
+        .iter()
+        .map(|symbol| {
+            let inst_id = to_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.clone());
+            json!({"instType": INST_TYPE, "channel": BOOKS_CHANNEL, "instId": inst_id})
+        })
+        .collect();
+    json!({"op": op, "args": args}).to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookAction {
+    Snapshot,
+    Update,
+}
+
+/// Message of the books channel
+#[derive(Debug, Clone, PartialEq)]
+struct BookMessage {
+    symbol: String,
+    action: BookAction,
+    /// Levels as sent, since the checksum covers the original strings
+    bids: Levels,
+    asks: Levels,
+    /// Exchange timestamp, in milliseconds
+    ts_ms: i64,
+    /// CRC32 of the top levels after the message is applied, as a signed 32-bit integer
+    checksum: i32,
+    seq: i64,
+}
+
+/// Decoded text frame of the public websocket
+#[derive(Debug, Clone, PartialEq)]
+enum BitgetFrame {
+    Book(BookMessage),
+    /// Reply to a request, such as `subscribe` or `error`, with its details
+    Event {
+        event: String,
+        detail: String,
+    },
+    Pong,
+    Other,
+}
+
+fn parse_frame(text: &str) -> Result<BitgetFrame, String> {
+    if text == "pong" {
+        return Ok(BitgetFrame::Pong);
+    }
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if let Some(event) = frame["event"].as_str() {
+        let detail = if frame["code"].is_null() {
+            frame["arg"].to_string()
+        } else {
+            format!(
+                "{} {}",
+                frame["code"],
+                frame["msg"].as_str().unwrap_or_default()
+            )
+        };
+        return Ok(BitgetFrame::Event {
+            event: event.to_string(),
+            detail,
+        });
+    }
+    if frame["arg"]["channel"] != BOOKS_CHANNEL {
+        return Ok(BitgetFrame::Other);
+    }
+    let inst_id = frame["arg"]["instId"]
+        .as_str()
+        .ok_or("missing instrument id")?;
+    let symbol = from_venue_symbol(EXCHANGE, inst_id)
+        .ok_or_else(|| format!("unexpected instrument {}", inst_id))?;
+    let action = match frame["action"].as_str() {
+        Some("snapshot") => BookAction::Snapshot,
+        Some("update") => BookAction::Update,
+        other => return Err(format!("unknown book action {:?}", other)),
+    };
+    let data = frame["data"].get(0).ok_or("missing book data")?;
+    let number = |field: &str| -> Result<i64, String> {
+        data[field]
+            .as_i64()
+            .ok_or_else(|| format!("missing `{}`", field))
+    };
+    Ok(BitgetFrame::Book(BookMessage {
+        symbol,
+        action,
+        bids: parse_levels(&data["bids"])?,
+        asks: parse_levels(&data["asks"])?,
+        ts_ms: data["ts"]
+            .as_str()
+            .and_then(|ts| ts.parse().ok())
+            .ok_or("missing `ts`")?,
+        checksum: number("checksum")? as i32,
+        seq: number("seq")?,
+    }))
+}
+
+/// Result of feeding a message to a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BookOutcome {
+    Applied,
+    /// Update of a book waiting for its snapshot
+    Ignored,
+    /// The checksum did not match, so the book was dropped and needs a new snapshot
+    ChecksumMismatch {
+        expected: i32,
+        actual: i32,
+    },
+}
+
+/// Local book of a symbol with the sequence number of its last message
+#[derive(Debug, Clone)]
+struct SymbolBook {
+    orderbook: market::OrderBook,
+    /// `None` until a snapshot arrives
+    seq: Option<i64>,
+    /// Best bid and ask of the last persisted state
+    last_top: Option<(market::OrderBookItem, market::OrderBookItem)>,
+}
+
+impl SymbolBook {
+    fn new(symbol: &str) -> Self {
+        Self {
+            orderbook: market::OrderBook::new(EXCHANGE, symbol),
+            seq: None,
+            last_top: None,
+        }
+    }
+
+    /// Drop the book until the next snapshot
+    fn reset(&mut self) {
+        self.orderbook.bids.clear();
+        self.orderbook.asks.clear();
+        self.seq = None;
+        self.last_top = None;
+    }
+
+    fn on_message(&mut self, message: &BookMessage) -> BookOutcome {
+        match message.action {
+            BookAction::Snapshot => {
+                self.orderbook.replace_levels(&message.bids, &message.asks);
+                self.last_top = None;
+            }
+            BookAction::Update => {
+                if self.seq.is_none() {
+                    return BookOutcome::Ignored;
+                }
+                apply_levels(&mut self.orderbook.bids, &message.bids);
+                apply_levels(&mut self.orderbook.asks, &message.asks);
+            }
+        }
+        let actual = checksum(&self.orderbook);
+        if actual != message.checksum {
+            self.reset();
+            return BookOutcome::ChecksumMismatch {
+                expected: message.checksum,
+                actual,
+            };
+        }
+        self.orderbook.last_update_ts = Utc::now();
+        self.seq = Some(message.seq);
+        BookOutcome::Applied
+    }
+}
+
+/// Ping schedule of a connection: a `ping` every `PING_INTERVAL`, each of which must be
+/// answered with a `pong` before the next one is due
+struct Keepalive {
+    interval: Interval,
+    /// When the unanswered ping went out
+    awaiting_pong_since: Option<Instant>,
+}
+
+impl Keepalive {
+    fn new(start: Instant) -> Self {
+        let mut interval = tokio::time::interval_at(start + PING_INTERVAL, PING_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            interval,
+            awaiting_pong_since: None,
+        }
+    }
+
+    /// Wait until the next ping is due; fails when the previous one was never answered
+    async fn next_ping(&mut self) -> Result<(), String> {
+        self.interval.tick().await;
+        let now = Instant::now();
+        if let Some(sent) = self.awaiting_pong_since {
+            return Err(format!("no pong for {:?}", now - sent));
+        }
+        self.awaiting_pong_since = Some(now);
+        Ok(())
+    }
+
+    fn on_pong(&mut self) {
+        self.awaiting_pong_since = None;
+    }
+}
+
+/// Bitget spot screener keeping a checksum-verified local book per symbol from the `books`
+/// channel, persisted as CEX market states
+pub struct BitgetScreener {
+    config: BitgetConfig,
+    shutdown: CancellationToken,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl BitgetScreener {
+    /// Create a new BitgetScreener instance on the symbols of `BITGET_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, BitgetConfig::from_env()?))
+    }
+
+    pub fn with_config(db_pool: DatabasePool, config: BitgetConfig) -> Self {
+        let cex_writer = CexMarketWriter::for_database(db_pool, "bitget");
+        Self {
+            config,
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Bitget", EXCHANGE, STALE_FEED_TIMEOUT),
+        }
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from the new subscription's snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Bitget screener for {:?} ({})...",
+            self.config.symbols, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Bitget screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(symbol)))
+            .collect();
+    }
+
+    /// Apply one text frame, adding the symbols whose book must be resubscribed to
+    /// `resubscribe`; returns whether it was a book message
+    fn handle_frame(&self, text: &str, resubscribe: &mut Vec<String>) -> bool {
+        let message = match parse_frame(text) {
+            Ok(BitgetFrame::Book(message)) => message,
+            Ok(BitgetFrame::Event { event, detail }) => {
+                if event == "error" {
+                    error!("Bitget websocket error: {}", detail);
+                } else {
+                    info!("Bitget websocket {}: {}", event, detail);
+                }
+                return false;
+            }
+            Ok(BitgetFrame::Pong | BitgetFrame::Other) => {
+                debug!("Skipping Bitget frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed Bitget frame: {}", e);
+                return false;
+            }
+        };
+
+        let mut books = self.books.lock().unwrap();
+        // Messages of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&message.symbol) else {
+            return true;
+        };
+        match book.on_message(&message) {
+            BookOutcome::Applied => {
+                if let Err(violation) = book.orderbook.validate() {
+                    error!(
+                        "Bitget {} order book rejected, {}: {}",
+                        message.symbol,
+                        violation,
+                        book.orderbook.describe_top(5)
+                    );
+                    metrics::counter!(
+                        "bitget_orderbook_resyncs_total",
+                        "symbol" => message.symbol.clone(),
+                        "reason" => violation.kind()
+                    )
+                    .increment(1);
+                    book.reset();
+                    resubscribe.push(message.symbol);
+                } else {
+                    self.persist(book, message.seq, message.ts_ms);
+                }
+            }
+            BookOutcome::ChecksumMismatch { expected, actual } => {
+                warn!(
+                    "Bitget {} order book checksum {} instead of {} at sequence {}, resubscribing",
+                    message.symbol, actual, expected, message.seq
+                );
+                metrics::counter!(
+                    "bitget_orderbook_resyncs_total",
+                    "symbol" => message.symbol.clone(),
+                    "reason" => "checksum"
+                )
+                .increment(1);
+                resubscribe.push(message.symbol);
+            }
+            BookOutcome::Ignored => {}
+        }
+        true
+    }
+
+    /// Persist the top of book when it changed
+    fn persist(&self, book: &mut SymbolBook, seq: i64, ts_ms: i64) {
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: seq.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: book.orderbook.symbol.clone(),
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(ts_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - ts_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for BitgetScreener {
+    /// Empty the books, connect, subscribe to every book and apply the stream until the connection
+    /// drops or the screener stops. `feed` counts the book messages delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        ws.send(Message::Text(books_request(
+            "subscribe",
+            &self.config.symbols,
+        )))
+        .await
+        .map_err(|e| format!("subscribe failed: {}", e))?;
+        info!(
+            "Bitget websocket connected, subscribed to {:?}",
+            self.config.symbols
+        );
+
+        let mut keepalive = Keepalive::new(Instant::now());
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                due = keepalive.next_ping() => {
+                    due?;
+                    ws.send(Message::Text("ping".to_string()))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            if text == "pong" {
+                                keepalive.on_pong();
+                                continue;
+                            }
+                            let mut resubscribe = Vec::new();
+                            if self.handle_frame(&text, &mut resubscribe) {
+                                feed.delivered();
+                            }
+                            if !resubscribe.is_empty() {
+                                // The new subscription starts with a snapshot
+                                for op in ["unsubscribe", "subscribe"] {
+                                    ws.send(Message::Text(books_request(op, &resubscribe)))
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "bitget_tests.rs"]
+mod bitget_tests;
@@ -0,0 +1,247 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Books channel frames recorded in order for TRUMPUSDT, with their checksums
+const BOOK_FIXTURES: [&str; 3] = [
+    r#"{"action":"snapshot","arg":{"instType":"SPOT","channel":"books","instId":"TRUMPUSDT"},"data":[{"asks":[["10.27","80"],["10.28","150.25"]],"bids":[["10.25","120.5"],["10.24","300"]],"checksum":-1251578382,"seq":5000,"ts":"1716863719100"}],"ts":1716863719102}"#,
+    r#"{"action":"update","arg":{"instType":"SPOT","channel":"books","instId":"TRUMPUSDT"},"data":[{"asks":[["10.26","5"]],"bids":[["10.25","100"]],"checksum":-1526121530,"seq":5001,"ts":"1716863719200"}],"ts":1716863719203}"#,
+    r#"{"action":"update","arg":{"instType":"SPOT","channel":"books","instId":"TRUMPUSDT"},"data":[{"asks":[["10.26","0"]],"bids":[["10.23","50"]],"checksum":-1984531463,"seq":5002,"ts":"1716863719300"}],"ts":1716863719301}"#,
+];
+
+fn message(index: usize) -> BookMessage {
+    match parse_frame(BOOK_FIXTURES[index]).unwrap() {
+        BitgetFrame::Book(message) => message,
+        other => panic!("not a book message: {:?}", other),
+    }
+}
+
+fn levels(levels: &[(&str, &str)]) -> market::OrderBookLevels {
+    levels
+        .iter()
+        .map(|(price, size)| (decimal(price), decimal(size)))
+        .collect()
+}
+
+impl TestScreener for BitgetScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = BitgetScreener {
+            config: BitgetConfig {
+                symbols: vec!["TRUMPUSDT".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+            },
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Bitget", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn book_frames_are_decoded() {
+    assert_eq!(
+        message(1),
+        BookMessage {
+            symbol: "TRUMPUSDT".to_string(),
+            action: BookAction::Update,
+            bids: vec![(decimal("10.25"), decimal("100"))],
+            asks: vec![(decimal("10.26"), decimal("5"))],
+            ts_ms: 1716863719200,
+            checksum: -1526121530,
+            seq: 5001,
+        }
+    );
+    assert_eq!(message(0).action, BookAction::Snapshot);
+
+    assert_eq!(parse_frame("pong"), Ok(BitgetFrame::Pong));
+    assert_eq!(
+        parse_frame(
+            r#"{"event":"error","arg":{"instType":"SPOT","channel":"books","instId":"NOPEUSDT"},"code":30001,"msg":"instType:SPOT,channel:books,instId:NOPEUSDT doesn't exist"}"#
+        ),
+        Ok(BitgetFrame::Event {
+            event: "error".to_string(),
+            detail: "30001 instType:SPOT,channel:books,instId:NOPEUSDT doesn't exist".to_string()
+        })
+    );
+    assert!(matches!(
+        parse_frame(r#"{"event":"subscribe","arg":{"instType":"SPOT","channel":"books","instId":"TRUMPUSDT"}}"#),
+        Ok(BitgetFrame::Event { event, .. }) if event == "subscribe"
+    ));
+    assert_eq!(
+        parse_frame(
+            r#"{"action":"snapshot","arg":{"instType":"SPOT","channel":"ticker","instId":"TRUMPUSDT"},"data":[]}"#
+        ),
+        Ok(BitgetFrame::Other)
+    );
+    assert!(parse_frame(&BOOK_FIXTURES[1].replace(r#""seq":5001"#, r#""seq":"5001""#)).is_err());
+    assert!(parse_frame(&BOOK_FIXTURES[1].replace(r#""update""#, r#""partial""#)).is_err());
+}
+
+#[test]
+fn symbols_and_requests_use_spot_instrument_ids() {
+    assert_eq!(
+        parse_symbols(" trumpusdt,TRUMPUSDC,TRUMPUSDT ").unwrap(),
+        ["TRUMPUSDT", "TRUMPUSDC"]
+    );
+    assert!(parse_symbols("TRUMPXYZ").is_err());
+    assert!(parse_symbols("TRUMP-USDT").is_err());
+    assert!(parse_symbols(",").is_err());
+
+    let request: Value =
+        serde_json::from_str(&books_request("subscribe", &["TRUMPUSDT".to_string()])).unwrap();
+    assert_eq!(
+        request,
+        json!({"op": "subscribe", "args": [
+            {"instType": "SPOT", "channel": "books", "instId": "TRUMPUSDT"}
+        ]})
+    );
+}
+
+#[test]
+fn updates_are_applied_after_the_snapshot_when_checksums_match() {
+    let mut book = SymbolBook::new("TRUMPUSDT");
+    assert_eq!(book.on_message(&message(1)), BookOutcome::Ignored);
+
+    for index in 0..3 {
+        assert_eq!(book.on_message(&message(index)), BookOutcome::Applied);
+    }
+    assert_eq!(book.seq, Some(5002));
+    assert_eq!(checksum(&book.orderbook), -1984531463);
+    assert_eq!(
+        book.orderbook.bids,
+        levels(&[("10.23", "50"), ("10.24", "300"), ("10.25", "100")])
+    );
+    assert_eq!(
+        book.orderbook.asks,
+        levels(&[("10.27", "80"), ("10.28", "150.25")])
+    );
+}
+
+#[test]
+fn checksum_mismatch_drops_the_book() {
+    let mut book = SymbolBook::new("TRUMPUSDT");
+    book.on_message(&message(0));
+
+    // A missed update leaves the book behind the exchange's one
+    assert!(matches!(
+        book.on_message(&message(2)),
+        BookOutcome::ChecksumMismatch {
+            expected: -1984531463,
+            ..
+        }
+    ));
+    assert_eq!(book.seq, None);
+    assert!(book.orderbook.bids.is_empty() && book.orderbook.asks.is_empty());
+    assert_eq!(book.on_message(&message(1)), BookOutcome::Ignored);
+
+    let mut bad_snapshot = message(0);
+    bad_snapshot.checksum = 0;
+    assert_eq!(
+        book.on_message(&bad_snapshot),
+        BookOutcome::ChecksumMismatch {
+            expected: 0,
+            actual: -1251578382
+        }
+    );
+    assert_eq!(book.on_message(&message(0)), BookOutcome::Applied);
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_bitget_states() {
+    let (screener, sink) = build_screener_with_sink::<BitgetScreener>();
+    let mut resubscribe = Vec::new();
+
+    for fixture in BOOK_FIXTURES {
+        assert!(screener.handle_frame(fixture, &mut resubscribe));
+        screener.cex_writer.flush().await;
+    }
+    assert!(!screener.handle_frame("pong", &mut resubscribe));
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "bitget" && state.trade_pair == "TRUMPUSDT")
+    );
+    assert_eq!(
+        states
+            .iter()
+            .map(|state| state.trade_id.as_str())
+            .collect::<Vec<_>>(),
+        ["5000", "5001", "5002"]
+    );
+    assert_eq!(
+        (states[1].bid_volume, states[1].ask_price),
+        (decimal("100"), decimal("10.26"))
+    );
+    assert_eq!(states[2].trade_time.timestamp_millis(), 1716863719300);
+    assert!(resubscribe.is_empty());
+}
+
+#[tokio::test]
+async fn checksum_mismatch_resubscribes_the_symbol() {
+    let (screener, sink) = build_screener_with_sink::<BitgetScreener>();
+    let mut resubscribe = Vec::new();
+
+    screener.handle_frame(BOOK_FIXTURES[0], &mut resubscribe);
+    let corrupted = BOOK_FIXTURES[1].replace("-1526121530", "123");
+    screener.handle_frame(&corrupted, &mut resubscribe);
+    assert_eq!(resubscribe, ["TRUMPUSDT"]);
+
+    // Updates are dropped until the new subscription's snapshot
+    resubscribe.clear();
+    screener.handle_frame(BOOK_FIXTURES[2], &mut resubscribe);
+    assert!(resubscribe.is_empty());
+    assert_eq!(screener.books.lock().unwrap()["TRUMPUSDT"].seq, None);
+
+    screener.cex_writer.flush().await;
+    assert_eq!(sink.states.lock().unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn keepalive_pings_every_30_seconds() {
+    let start = Instant::now();
+    let mut keepalive = Keepalive::new(start);
+
+    for round in 1..=3 {
+        assert_eq!(keepalive.next_ping().await, Ok(()));
+        assert_eq!(Instant::now() - start, PING_INTERVAL * round);
+        tokio::time::advance(Duration::from_secs(1)).await;
+        keepalive.on_pong();
+    }
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn keepalive_fails_when_a_ping_is_not_answered() {
+    let start = Instant::now();
+    let mut keepalive = Keepalive::new(start);
+
+    assert_eq!(keepalive.next_ping().await, Ok(()));
+    let error = keepalive.next_ping().await.unwrap_err();
+    assert!(error.contains("no pong for 30s"), "{}", error);
+    assert_eq!(Instant::now() - start, PING_INTERVAL * 2);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn keepalive_is_not_bunched_after_a_stall() {
+    let start = Instant::now();
+    let mut keepalive = Keepalive::new(start);
+
+    // A loop busy past several pings sends one, then resumes the 30s spacing
+    tokio::time::advance(Duration::from_secs(95)).await;
+    assert_eq!(keepalive.next_ping().await, Ok(()));
+    assert_eq!(Instant::now() - start, Duration::from_secs(95));
+    keepalive.on_pong();
+    assert_eq!(keepalive.next_ping().await, Ok(()));
+    assert_eq!(Instant::now() - start, Duration::from_secs(125));
+}
@@ -1,114 +1,2019 @@
 use chrono::{DateTime, Utc};
-use sqlx::{MySql, Pool};
-use std::collections::HashMap;
-use std::sync::{
-    Arc, Mutex,
-    atomic::{AtomicBool, Ordering},
-};
-use tracing::info;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
 use bybit::WebSocketApiClient;
-use bybit::ws::response::{BasePublicResponse, Orderbook, OrderbookItem, SpotPublicResponse};
+use bybit::ws::response::{
+    BasePublicResponse, Kline, Orderbook, OrderbookItem, SpotPublicResponse, SpotTicker,
+};
 use bybit::ws::spot;
 
 use crate::models::market;
-use crate::store::markets::insert_cex_market;
+use crate::solana::retry::RetryPolicy;
+use crate::store::db::{DatabasePool, lazy_pool};
+use crate::store::markets::{insert_cex_ticker, insert_orderbook_snapshot, upsert_cex_kline};
+use crate::telemetry::{DEFAULT_SUMMARY_INTERVAL_SECS, RollingPercentiles};
+
+use super::bybit_instruments::{BybitInstruments, InstrumentInfo};
+use super::bybit_rest::{BybitRestClient, OrderBookSnapshot, RestResult};
+use super::cex_writer::{CexMarketSink, CexMarketWriter, CexWriterConfig};
+use super::raw_capture::{CapturedFrame, RawCapture, read_capture};
+use super::symbols::is_valid_symbol;
 
 use anyhow::Result;
 
-struct TradeConfig {
-    pub depth: spot::OrderbookDepth,
-    pub _bid_precision: u32,
-    pub _ask_precision: u32,
+/// Messages buffered between the websocket thread and the screener
+const MESSAGE_CHANNEL_CAPACITY: usize = 1024;
+/// How long a stopping screener waits for the websocket thread to notice the stop
+const WEBSOCKET_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Deviation of the ticker last price from the book spread that counts as a broken book
+const DEFAULT_TICKER_MAX_DEVIATION_BPS: u32 = 50;
+/// How long the book may deviate from the ticker before a warning is logged
+const DEFAULT_TICKER_DEVIATION_GRACE_SECS: u64 = 5;
+/// Interval between two `cex_tickers` snapshots
+const DEFAULT_TICKER_PERSIST_INTERVAL_SECS: u64 = 60;
+/// Interval between two `cex_orderbook_snapshots` rows of a book
+const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 10;
+/// Levels per side kept in a `cex_orderbook_snapshots` row
+const DEFAULT_SNAPSHOT_DEPTH: usize = 50;
+/// Longest time an unchanged top of book goes without being persisted
+const DEFAULT_HEARTBEAT_SECS: u64 = 5;
+/// Default interval between two fetches of the instrument filters
+const DEFAULT_INSTRUMENT_REFRESH_SECS: u64 = 86_400;
+/// Silence after which the websocket feed is considered stale
+const DEFAULT_STALE_FEED_SECS: u64 = 10;
+/// Latency samples kept per symbol for the rolling feed latency percentiles
+const FEED_LATENCY_WINDOW: usize = 1_000;
+/// Interval between two checks of the stale feed watchdog
+const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Symbols streamed when `BYBIT_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDC:50,TRUMPUSDT:50";
+/// Deltas kept per book while its REST snapshot is fetched; more means the fetch is stuck
+const MAX_BUFFERED_DELTAS: usize = 1_000;
+/// Candle interval subscribed for every symbol, as named in Bybit kline topics
+const KLINE_INTERVAL: &str = "1";
+/// Order book depths the Bybit spot stream supports
+const SUPPORTED_DEPTHS: [u16; 3] = [1, 50, 200];
+/// File name prefix of the raw message captures
+const CAPTURE_PREFIX: &str = "bybit-raw";
+
+/// Message copied out of a websocket frame so it can cross to the async side
+#[derive(Debug, Clone)]
+enum StreamMessage {
+    Orderbook(OrderbookMessage),
+    Ticker(market::CEXTicker),
+    Klines(Vec<market::CEXKline>),
+    /// The symbol set changed, the session must be rebuilt to subscribe to it
+    Resubscribe,
+}
+
+impl StreamMessage {
+    /// The message as a capture file line; order book levels keep Bybit's field names
+    fn to_capture(&self) -> Option<CapturedFrame> {
+        match self {
+            Self::Orderbook(msg) => Some(CapturedFrame {
+                received_ms: msg.received_ms,
+                topic: msg.topic.clone(),
+                msg_type: msg.msg_type.clone(),
+                ts: msg.ts,
+                data: json!({
+                    "s": msg.symbol,
+                    "u": msg.update_id,
+                    "b": msg.bids,
+                    "a": msg.asks,
+                }),
+            }),
+            Self::Ticker(ticker) => Some(CapturedFrame {
+                received_ms: ticker.fetch_time.timestamp_millis(),
+                topic: format!("tickers.{}", ticker.trade_pair),
+                msg_type: "snapshot".to_string(),
+                ts: ticker.trade_time.timestamp_millis() as u64,
+                data: serde_json::to_value(ticker).ok()?,
+            }),
+            Self::Klines(klines) => {
+                let first = klines.first()?;
+                Some(CapturedFrame {
+                    received_ms: first.fetch_time.timestamp_millis(),
+                    topic: format!("kline.{}.{}", first.interval, first.trade_pair),
+                    msg_type: "snapshot".to_string(),
+                    ts: first.fetch_time.timestamp_millis() as u64,
+                    data: serde_json::to_value(klines).ok()?,
+                })
+            }
+            Self::Resubscribe => None,
+        }
+    }
+}
+
+/// Order book message copied out of the websocket frame so it can cross to the async side
+#[derive(Debug, Clone, PartialEq)]
+struct OrderbookMessage {
+    /// `orderbook.{depth}.{symbol}`
+    topic: String,
+    symbol: String,
+    /// `snapshot` or `delta`
+    msg_type: String,
+    /// Bybit timestamp of the message, in milliseconds
+    ts: u64,
+    /// Update id `u` of the book
+    update_id: u64,
+    asks: Vec<(String, String)>,
+    bids: Vec<(String, String)>,
+    /// Local time the frame was received, in milliseconds
+    received_ms: i64,
+}
+
+impl From<&BasePublicResponse<'_, Orderbook<'_>>> for OrderbookMessage {
+    fn from(msg: &BasePublicResponse<'_, Orderbook<'_>>) -> Self {
+        let levels = |items: &[OrderbookItem]| {
+            items
+                .iter()
+                .map(|item| (item.0.to_string(), item.1.to_string()))
+                .collect()
+        };
+        Self {
+            topic: msg.topic.to_string(),
+            symbol: msg.data.s.to_string(),
+            msg_type: msg.type_.to_string(),
+            ts: msg.ts,
+            update_id: msg.data.u,
+            asks: levels(&msg.data.a),
+            bids: levels(&msg.data.b),
+            received_ms: Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+impl OrderbookMessage {
+    /// Order book message of a captured frame, `None` for frames of other topics
+    fn from_capture(frame: &CapturedFrame) -> Option<Self> {
+        if !frame.topic.starts_with("orderbook.") {
+            return None;
+        }
+        let levels = |side: &Value| serde_json::from_value(side.clone()).ok();
+        Some(Self {
+            topic: frame.topic.clone(),
+            symbol: frame.data["s"].as_str()?.to_string(),
+            msg_type: frame.msg_type.clone(),
+            ts: frame.ts,
+            update_id: frame.data["u"].as_u64()?,
+            asks: levels(&frame.data["a"])?,
+            bids: levels(&frame.data["b"])?,
+            received_ms: frame.received_ms,
+        })
+    }
+}
+
+/// Copy a spot ticker out of its websocket frame.
+/// Returns `None` when a price or volume is not a decimal.
+fn ticker_from_ws(msg: &BasePublicResponse<'_, SpotTicker<'_>>) -> Option<market::CEXTicker> {
+    let ticker = &msg.data;
+    Some(market::CEXTicker {
+        exchange: String::from("bybit"),
+        trade_pair: ticker.symbol.to_string(),
+        last_price: ticker.last_price.parse().ok()?,
+        high_price_24h: ticker.high_price24h.parse().ok()?,
+        low_price_24h: ticker.low_price24h.parse().ok()?,
+        prev_price_24h: ticker.prev_price24h.parse().ok()?,
+        volume_24h: ticker.volume24h.parse().ok()?,
+        turnover_24h: ticker.turnover24h.parse().ok()?,
+        price_change_24h: ticker.price24h_pcnt.parse().ok()?,
+        usd_index_price: ticker.usd_index_price.parse().ok(),
+        trade_time: DateTime::from_timestamp_millis(msg.ts as i64).unwrap_or_else(Utc::now),
+        fetch_time: Utc::now(),
+    })
+}
+
+/// Copy the candles of a kline frame out of the websocket frame.
+/// The symbol is the last part of the `kline.{interval}.{symbol}` topic.
+/// Returns `None` when a price or volume is not a decimal.
+fn klines_from_ws(msg: &BasePublicResponse<'_, Vec<Kline<'_>>>) -> Option<Vec<market::CEXKline>> {
+    let symbol = msg.topic.rsplit('.').next()?;
+    msg.data
+        .iter()
+        .map(|kline| {
+            Some(market::CEXKline {
+                exchange: String::from("bybit"),
+                trade_pair: symbol.to_string(),
+                interval: kline.interval.to_string(),
+                open_time: DateTime::from_timestamp_millis(kline.start as i64)?,
+                open: kline.open.parse().ok()?,
+                high: kline.high.parse().ok()?,
+                low: kline.low.parse().ok()?,
+                close: kline.close.parse().ok()?,
+                volume: kline.volume.parse().ok()?,
+                confirmed: kline.confirm,
+                fetch_time: Utc::now(),
+            })
+        })
+        .collect()
+}
+
+/// How a candle relates to the latest one seen for its symbol
+#[derive(Debug, PartialEq, Eq)]
+enum KlineUpdate {
+    /// A new or still-forming candle, written in place
+    Forming,
+    /// The candle closed, its final values are written
+    Confirmed,
+    /// An older candle, a repeat, or an update of a candle that already closed
+    Ignored,
+}
+
+/// Compare a candle with the latest one seen for its symbol
+fn kline_update(last: Option<&market::CEXKline>, kline: &market::CEXKline) -> KlineUpdate {
+    let values = |k: &market::CEXKline| (k.open, k.high, k.low, k.close, k.volume, k.confirmed);
+    let fresh = match last {
+        None => true,
+        Some(last) if kline.open_time > last.open_time => true,
+        Some(last) if kline.open_time < last.open_time => false,
+        Some(last) => !last.confirmed && values(last) != values(kline),
+    };
+    match (fresh, kline.confirmed) {
+        (false, _) => KlineUpdate::Ignored,
+        (true, true) => KlineUpdate::Confirmed,
+        (true, false) => KlineUpdate::Forming,
+    }
+}
+
+/// Borrow owned price levels as websocket order book items
+fn ws_levels(levels: &[(String, String)]) -> Vec<OrderbookItem<'_>> {
+    levels
+        .iter()
+        .map(|(price, volume)| OrderbookItem(price, volume))
+        .collect()
+}
+
+/// Whether the websocket loop keeps reading after a message
+#[derive(Debug, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Stop,
+}
+
+/// Unwind payload breaking out of the websocket client's read loop
+struct StopSignal;
+
+/// Continuity of a book's update ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookSequence {
+    /// Book follows the stream up to update id `u`
+    Synced(u64),
+    /// An update was missed; the book is not persisted until a snapshot rebuilds it
+    Gapped,
+    /// A REST snapshot is being fetched; deltas are buffered until it is spliced in
+    Recovering,
+    /// Book was rebuilt from a REST snapshot up to update id `u`; deltas it already
+    /// contains may still arrive
+    Rebuilt(u64),
+}
+
+impl BookSequence {
+    /// Whether the book reflects the stream and may be persisted
+    fn is_synced(self) -> bool {
+        matches!(self, BookSequence::Synced(_) | BookSequence::Rebuilt(_))
+    }
+}
+
+/// How an order book message is applied
+#[derive(Debug, PartialEq, Eq)]
+enum SequenceCheck {
+    /// Replace the book with the message levels
+    Snapshot,
+    /// Merge the message levels into the book
+    Delta,
+    /// Drop the message, the book waits for a snapshot
+    Skip,
+    /// Drop the message and rebuild the book from a fresh snapshot
+    Resync,
+    /// Keep the message until the book's REST snapshot arrives
+    Buffer,
+}
+
+/// Check an order book message against the last update id applied to its book.
+/// Deltas must carry the next update id; anything else means updates were lost.
+fn check_sequence(last: Option<BookSequence>, msg_type: &str, update_id: u64) -> SequenceCheck {
+    match last {
+        _ if msg_type == "snapshot" => SequenceCheck::Snapshot,
+        // Bybit restarts update ids at 1 after a service restart, with the full book
+        _ if update_id == 1 => SequenceCheck::Snapshot,
+        Some(BookSequence::Synced(last) | BookSequence::Rebuilt(last)) if update_id == last + 1 => {
+            SequenceCheck::Delta
+        }
+        Some(BookSequence::Rebuilt(last)) if update_id <= last => SequenceCheck::Skip,
+        Some(BookSequence::Synced(_) | BookSequence::Rebuilt(_)) => SequenceCheck::Resync,
+        Some(BookSequence::Recovering) => SequenceCheck::Buffer,
+        Some(BookSequence::Gapped) | None => SequenceCheck::Skip,
+    }
+}
+
+/// Deltas of `buffered` to replay on top of a snapshot taken at update id `snapshot_id`.
+/// Deltas the snapshot already contains are dropped; the rest must continue it without a
+/// hole, otherwise `None` is returned and the snapshot cannot be used.
+fn splice_snapshot(
+    snapshot_id: u64,
+    buffered: Vec<OrderbookMessage>,
+) -> Option<Vec<OrderbookMessage>> {
+    let deltas: Vec<OrderbookMessage> = buffered
+        .into_iter()
+        .filter(|delta| delta.update_id > snapshot_id)
+        .collect();
+    let mut last = snapshot_id;
+    for delta in &deltas {
+        if delta.update_id != last + 1 {
+            return None;
+        }
+        last = delta.update_id;
+    }
+    Some(deltas)
+}
+
+/// Distance of the ticker last price outside the book spread, in bps of the last price.
+/// Spot tickers carry no best bid/ask, but the last trade should not print outside the book.
+fn ticker_deviation_bps(best_bid: Decimal, best_ask: Decimal, last_price: Decimal) -> Decimal {
+    let outside = if last_price < best_bid {
+        best_bid - last_price
+    } else if last_price > best_ask {
+        last_price - best_ask
+    } else {
+        return Decimal::ZERO;
+    };
+    if last_price.is_zero() {
+        return Decimal::ZERO;
+    }
+    outside / last_price * Decimal::from(10_000)
+}
+
+/// Limits of the book against ticker cross-check
+#[derive(Debug, Clone)]
+struct TickerCheck {
+    max_deviation_bps: Decimal,
+    /// How long a deviation may last before it is reported
+    grace: Duration,
+}
+
+impl TickerCheck {
+    /// Read the limits from `BYBIT_TICKER_MAX_DEVIATION_BPS` and
+    /// `BYBIT_TICKER_DEVIATION_GRACE_SECS`, falling back to the defaults
+    fn from_env() -> Self {
+        let max_deviation_bps = std::env::var("BYBIT_TICKER_MAX_DEVIATION_BPS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(Decimal::from(DEFAULT_TICKER_MAX_DEVIATION_BPS));
+        let grace_secs = std::env::var("BYBIT_TICKER_DEVIATION_GRACE_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_TICKER_DEVIATION_GRACE_SECS);
+        Self {
+            max_deviation_bps,
+            grace: Duration::from_secs(grace_secs),
+        }
+    }
+}
+
+/// Interval and depth of the full order book snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BookSnapshots {
+    interval: Duration,
+    /// Levels kept per side, bounding the size of a row
+    depth: usize,
+}
+
+impl BookSnapshots {
+    /// Read `BYBIT_SNAPSHOT_INTERVAL_SECS` and `BYBIT_SNAPSHOT_DEPTH`, falling back to the
+    /// defaults; an interval of 0 disables the snapshots
+    fn from_env() -> Option<Self> {
+        let secs = std::env::var("BYBIT_SNAPSHOT_INTERVAL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SNAPSHOT_INTERVAL_SECS);
+        let depth = std::env::var("BYBIT_SNAPSHOT_DEPTH")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|depth| *depth > 0)
+            .unwrap_or(DEFAULT_SNAPSHOT_DEPTH);
+        (secs > 0).then(|| Self {
+            interval: Duration::from_secs(secs),
+            depth,
+        })
+    }
+}
+
+/// Read the ticker snapshot interval from `BYBIT_TICKER_PERSIST_INTERVAL_SECS`, falling back to the default
+fn ticker_persist_interval_from_env() -> Duration {
+    let secs = std::env::var("BYBIT_TICKER_PERSIST_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_TICKER_PERSIST_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Read the instrument filters refresh interval from `BYBIT_INSTRUMENT_REFRESH_SECS`,
+/// falling back to the default
+fn instrument_refresh_interval_from_env() -> Duration {
+    let secs = std::env::var("BYBIT_INSTRUMENT_REFRESH_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_INSTRUMENT_REFRESH_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Read the stale feed timeout from `BYBIT_STALE_FEED_SECS`, falling back to the default
+fn stale_feed_timeout_from_env() -> Duration {
+    let secs = std::env::var("BYBIT_STALE_FEED_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_STALE_FEED_SECS);
+    Duration::from_secs(secs)
+}
+
+/// How long the feed has been silent, when that exceeds `timeout`.
+/// A session that has not received any message yet is silent since it started.
+fn feed_silence(
+    last_seen: &HashMap<String, tokio::time::Instant>,
+    session_start: tokio::time::Instant,
+    now: tokio::time::Instant,
+    timeout: Duration,
+) -> Option<Duration> {
+    let last_message = last_seen
+        .values()
+        .copied()
+        .fold(session_start, tokio::time::Instant::max);
+    let silence = now.saturating_duration_since(last_message);
+    (silence >= timeout).then_some(silence)
+}
+
+/// Age of the last message of every symbol, e.g. `SOLUSDC=never TRUMPUSDC=1.2s`
+fn format_message_ages<'a>(
+    symbols: impl Iterator<Item = &'a String>,
+    last_seen: &HashMap<String, tokio::time::Instant>,
+    now: tokio::time::Instant,
+) -> String {
+    symbols
+        .map(|symbol| match last_seen.get(symbol) {
+            Some(seen) => format!(
+                "{}={:.1}s",
+                symbol,
+                now.saturating_duration_since(*seen).as_secs_f64()
+            ),
+            None => format!("{}=never", symbol),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Delay between the exchange timestamp of a message and its local receipt, in
+/// milliseconds. A receipt before the exchange timestamp means the clocks are skewed:
+/// the latency is clamped to zero and `true` is returned.
+fn feed_latency_ms(received_ms: i64, exchange_ms: i64) -> (u64, bool) {
+    let latency = received_ms - exchange_ms;
+    (latency.max(0) as u64, latency < 0)
+}
+
+/// Rolling feed latency of a symbol
+#[derive(Debug, Clone)]
+struct FeedLatency {
+    window: RollingPercentiles,
+    /// Messages received before their exchange timestamp since the last stats log
+    skewed: u64,
+}
+
+impl Default for FeedLatency {
+    fn default() -> Self {
+        Self {
+            window: RollingPercentiles::new(FEED_LATENCY_WINDOW),
+            skewed: 0,
+        }
+    }
+}
+
+/// Rolling p50/p95 of every symbol, e.g. `TRUMPUSDC p50=12ms p95=40ms skewed=0`.
+/// Symbols without samples are left out.
+fn format_feed_latency(latencies: &BTreeMap<&String, &FeedLatency>) -> String {
+    latencies
+        .iter()
+        .filter_map(|(symbol, latency)| {
+            let percentiles = latency.window.percentiles(&[50, 95])?;
+            Some(format!(
+                "{} p50={}ms p95={}ms skewed={}",
+                symbol, percentiles[0], percentiles[1], latency.skewed
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Read the unchanged top of book heartbeat from `BYBIT_HEARTBEAT_SECS`, falling back to the default
+fn heartbeat_interval_from_env() -> Duration {
+    let secs = std::env::var("BYBIT_HEARTBEAT_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_HEARTBEAT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Best bid and ask of a book, as persisted in `cex_markets`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TopOfBook {
+    bid_price: Decimal,
+    bid_volume: Decimal,
+    ask_price: Decimal,
+    ask_volume: Decimal,
+}
+
+/// Whether an order book state is persisted
+#[derive(Debug, PartialEq, Eq)]
+enum PersistDecision {
+    /// The top of book differs from the last persisted one
+    Changed,
+    /// The top of book is unchanged but was last persisted a heartbeat ago
+    Heartbeat,
+    Unchanged,
+}
+
+/// Compare a top of book with the last persisted one of its symbol and when it was written
+fn persist_decision(
+    last: Option<&(TopOfBook, Instant)>,
+    top: &TopOfBook,
+    now: Instant,
+    heartbeat: Duration,
+) -> PersistDecision {
+    match last {
+        Some((last_top, _)) if last_top != top => PersistDecision::Changed,
+        Some((_, persisted_at)) if now.duration_since(*persisted_at) < heartbeat => {
+            PersistDecision::Unchanged
+        }
+        Some(_) => PersistDecision::Heartbeat,
+        None => PersistDecision::Changed,
+    }
+}
+
+/// Order book states persisted and skipped within a stats window
+#[derive(Debug, Default)]
+struct PersistStats {
+    written: AtomicU64,
+    /// Written states whose top of book was unchanged
+    heartbeats: AtomicU64,
+    /// States skipped because their top of book was unchanged
+    unchanged: AtomicU64,
+}
+
+impl PersistStats {
+    fn record(&self, decision: &PersistDecision) {
+        match decision {
+            PersistDecision::Changed => self.written.fetch_add(1, Ordering::Relaxed),
+            PersistDecision::Heartbeat => {
+                self.heartbeats.fetch_add(1, Ordering::Relaxed);
+                self.written.fetch_add(1, Ordering::Relaxed)
+            }
+            PersistDecision::Unchanged => self.unchanged.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// `(written, heartbeats, unchanged)` since the previous call
+    fn take(&self) -> (u64, u64, u64) {
+        (
+            self.written.swap(0, Ordering::Relaxed),
+            self.heartbeats.swap(0, Ordering::Relaxed),
+            self.unchanged.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Change in a symbol's book against ticker agreement worth reporting
+#[derive(Debug, PartialEq, Eq)]
+enum DeviationAlert {
+    Quiet,
+    /// The book has deviated for longer than the grace period
+    Exceeded,
+    /// A reported deviation is over
+    Recovered,
 }
 
-fn get_trade_pairs() -> HashMap<String, TradeConfig> {
-    let mut map = HashMap::new();
-    map.insert(
-        "TRUMPUSDC".to_string(),
-        TradeConfig {
-            depth: spot::OrderbookDepth::Level50,
-            _bid_precision: 6,
-            _ask_precision: 6,
+/// Ongoing deviation of a symbol's book from its ticker
+#[derive(Debug, Default)]
+struct DeviationTracker {
+    /// When the current deviation started
+    since: Option<Instant>,
+    /// Whether the current deviation was reported
+    reported: bool,
+}
+
+impl DeviationTracker {
+    /// Record whether the book deviates at `now`; a deviation is reported once,
+    /// when it outlasts `grace`, and again when it ends
+    fn observe(&mut self, deviating: bool, now: Instant, grace: Duration) -> DeviationAlert {
+        if !deviating {
+            let reported = self.reported;
+            *self = Self::default();
+            return if reported {
+                DeviationAlert::Recovered
+            } else {
+                DeviationAlert::Quiet
+            };
+        }
+        let since = *self.since.get_or_insert(now);
+        if !self.reported && now.duration_since(since) >= grace {
+            self.reported = true;
+            return DeviationAlert::Exceeded;
+        }
+        DeviationAlert::Quiet
+    }
+}
+
+/// Forward an order book, ticker or kline message from the websocket thread to the screener.
+/// Stops once the screener is shut down or no longer listening.
+fn forward_message(
+    msg: SpotPublicResponse,
+    shutdown: &CancellationToken,
+    tx: &mpsc::Sender<StreamMessage>,
+) -> Flow {
+    if shutdown.is_cancelled() {
+        return Flow::Stop;
+    }
+    let msg = match msg {
+        SpotPublicResponse::Orderbook(ob) => StreamMessage::Orderbook(OrderbookMessage::from(&ob)),
+        SpotPublicResponse::Ticker(ticker) => match ticker_from_ws(&ticker) {
+            Some(ticker) => StreamMessage::Ticker(ticker),
+            None => {
+                warn!("Skipping malformed Bybit ticker on {}", ticker.topic);
+                return Flow::Continue;
+            }
         },
-    );
-    map.insert(
-        "TRUMPUSDT".to_string(),
-        TradeConfig {
-            depth: spot::OrderbookDepth::Level50,
-            _bid_precision: 6,
-            _ask_precision: 6,
+        SpotPublicResponse::Kline(klines) => match klines_from_ws(&klines) {
+            Some(klines) => StreamMessage::Klines(klines),
+            None => {
+                warn!("Skipping malformed Bybit kline on {}", klines.topic);
+                return Flow::Continue;
+            }
         },
-    );
-    map
+        _ => return Flow::Continue,
+    };
+    match tx.blocking_send(msg) {
+        Ok(()) => Flow::Continue,
+        Err(_) => Flow::Stop,
+    }
+}
+
+/// Run a blocking read loop, forwarding its messages into `tx` until the screener stops.
+/// The Bybit client has no way to leave its loop from the callback, so a stop unwinds it
+/// with a `StopSignal` payload; `resume_unwind` skips the panic hook, so nothing is reported.
+fn run_until_stopped<R>(
+    run: R,
+    shutdown: &CancellationToken,
+    tx: &mpsc::Sender<StreamMessage>,
+) -> Result<(), String>
+where
+    R: FnOnce(&mut dyn FnMut(SpotPublicResponse)) -> Result<(), String>,
+{
+    let mut callback = |msg: SpotPublicResponse| {
+        if forward_message(msg, shutdown, tx) == Flow::Stop {
+            std::panic::resume_unwind(Box::new(StopSignal));
+        }
+    };
+    match std::panic::catch_unwind(AssertUnwindSafe(|| run(&mut callback))) {
+        Ok(result) => result,
+        Err(payload) if payload.is::<StopSignal>() => Ok(()),
+        Err(payload) => std::panic::resume_unwind(payload),
+    }
+}
+
+/// Run the blocking websocket client on the order books, tickers and 1m klines of `trade_pairs`,
+/// forwarding their messages into `tx`.
+/// Meant for a blocking thread: `client.run` never yields to the async runtime.
+fn run_websocket(
+    env: BybitEnv,
+    trade_pairs: &BTreeMap<String, TradeConfig>,
+    shutdown: CancellationToken,
+    tx: mpsc::Sender<StreamMessage>,
+) -> Result<(), String> {
+    let mut client = WebSocketApiClient::spot().uri(env.ws_url()).build();
+
+    for (symbol, conf) in trade_pairs {
+        if let Some(depth) = orderbook_depth(conf.depth) {
+            client.subscribe_orderbook(symbol, depth);
+        }
+        client.subscribe_ticker(symbol);
+        client.subscribe_kline(symbol, spot::KlineInterval::Min1);
+    }
+
+    run_until_stopped(
+        |callback| client.run(callback).map_err(|e| e.to_string()),
+        &shutdown,
+        &tx,
+    )
+}
+
+/// Stream settings of a Bybit symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TradeConfig {
+    /// Order book levels per side, one of `SUPPORTED_DEPTHS`
+    pub depth: u16,
+}
+
+/// Websocket depth subscription of `levels` per side, if Bybit streams it
+fn orderbook_depth(levels: u16) -> Option<spot::OrderbookDepth> {
+    match levels {
+        1 => Some(spot::OrderbookDepth::Level1),
+        50 => Some(spot::OrderbookDepth::Level50),
+        200 => Some(spot::OrderbookDepth::Level200),
+        _ => None,
+    }
+}
+
+/// Bybit deployment streamed from, selected by `BYBIT_ENV`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BybitEnv {
+    Mainnet,
+    Testnet,
+}
+
+impl BybitEnv {
+    /// Parse `mainnet` or `testnet`; an empty value is mainnet
+    fn parse(value: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "mainnet" => Ok(Self::Mainnet),
+            "testnet" => Ok(Self::Testnet),
+            _ => Err(format!("BYBIT_ENV `{}` is neither mainnet nor testnet", value).into()),
+        }
+    }
+
+    /// Public spot websocket of the environment
+    pub fn ws_url(self) -> &'static str {
+        match self {
+            Self::Mainnet => "wss://stream.bybit.com/v5/public/spot",
+            Self::Testnet => "wss://stream-testnet.bybit.com/v5/public/spot",
+        }
+    }
+
+    /// Authenticated websocket of the environment, streaming account updates
+    pub fn private_ws_url(self) -> &'static str {
+        match self {
+            Self::Mainnet => "wss://stream.bybit.com/v5/private",
+            Self::Testnet => "wss://stream-testnet.bybit.com/v5/private",
+        }
+    }
+
+    /// Base URL of the environment's v5 REST API
+    pub fn rest_url(self) -> &'static str {
+        match self {
+            Self::Mainnet => "https://api.bybit.com",
+            Self::Testnet => "https://api-testnet.bybit.com",
+        }
+    }
+
+    /// Exchange name of the rows persisted from the environment
+    pub fn exchange(self) -> &'static str {
+        match self {
+            Self::Mainnet => "bybit",
+            Self::Testnet => "bybit-testnet",
+        }
+    }
+
+    /// Environment served by `url`, `None` when it is not a Bybit host (e.g. a local proxy)
+    fn of_url(url: &str) -> Option<Self> {
+        let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let host = host.split(['/', ':']).next()?.to_ascii_lowercase();
+        if host.ends_with("-testnet.bybit.com") {
+            Some(Self::Testnet)
+        } else if host == "bybit.com" || host.ends_with(".bybit.com") {
+            Some(Self::Mainnet)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for BybitEnv {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mainnet => write!(f, "mainnet"),
+            Self::Testnet => write!(f, "testnet"),
+        }
+    }
+}
+
+/// Bybit environment of the first configuration resolved in this process
+static PROCESS_ENV: OnceLock<BybitEnv> = OnceLock::new();
+
+/// Fail when `source` runs on `other` rather than `env`, unless mixing environments is allowed
+fn check_same_env(
+    env: BybitEnv,
+    other: Option<BybitEnv>,
+    source: &str,
+    allow_mixed: bool,
+) -> Result<(), String> {
+    match other {
+        Some(other) if other != env && !allow_mixed => Err(format!(
+            "{} is on Bybit {} while BYBIT_ENV is {}; set BYBIT_ALLOW_MIXED_ENV=true to mix them",
+            source, other, env
+        )),
+        Some(other) if other != env => {
+            warn!(
+                "{} is on Bybit {} while BYBIT_ENV is {}, mixing allowed by BYBIT_ALLOW_MIXED_ENV",
+                source, other, env
+            );
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Symbols streamed by the Bybit screener
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BybitConfig {
+    pub trade_pairs: BTreeMap<String, TradeConfig>,
+    /// Environment of the websocket and of the persisted rows
+    pub env: BybitEnv,
+    /// Base URL of the REST API rebuilding gapped books
+    pub rest_url: String,
+}
+
+impl BybitConfig {
+    /// Read `BYBIT_SYMBOLS`, `BYBIT_ENV` and `BYBIT_REST_URL`, falling back to the default
+    /// symbols on mainnet. A REST URL or an earlier configuration of this process on the other
+    /// environment is refused unless `BYBIT_ALLOW_MIXED_ENV` is set.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("BYBIT_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        let env = BybitEnv::parse(&std::env::var("BYBIT_ENV").unwrap_or_default())?;
+        let allow_mixed = std::env::var("BYBIT_ALLOW_MIXED_ENV")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(false);
+        let rest_url = std::env::var("BYBIT_REST_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| env.rest_url().to_string());
+        check_same_env(
+            env,
+            BybitEnv::of_url(&rest_url),
+            &format!("BYBIT_REST_URL {}", rest_url),
+            allow_mixed,
+        )?;
+        check_same_env(
+            env,
+            Some(*PROCESS_ENV.get_or_init(|| env)),
+            "An earlier Bybit configuration of this process",
+            allow_mixed,
+        )?;
+        Ok(Self {
+            trade_pairs: parse_symbols(&symbols)?,
+            env,
+            rest_url,
+        })
+    }
+}
+
+/// Parse comma-separated `SYMBOL:DEPTH` entries, e.g. `TRUMPUSDC:50,TRUMPUSDT:200`
+fn parse_symbols(value: &str) -> Result<BTreeMap<String, TradeConfig>, Box<dyn std::error::Error>> {
+    let mut trade_pairs = BTreeMap::new();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let Some((symbol, depth)) = entry.split_once(':') else {
+            return Err(format!("BYBIT_SYMBOLS entry `{}` is not SYMBOL:DEPTH", entry).into());
+        };
+        let symbol = symbol.trim();
+        if !is_valid_symbol(symbol) {
+            return Err(format!("BYBIT_SYMBOLS entry `{}` has an invalid symbol", entry).into());
+        }
+        let depth = depth
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|depth| orderbook_depth(*depth).is_some())
+            .ok_or_else(|| {
+                format!(
+                    "BYBIT_SYMBOLS entry `{}` has an unsupported depth, accepted depths: {:?}",
+                    entry, SUPPORTED_DEPTHS
+                )
+            })?;
+        if trade_pairs
+            .insert(symbol.to_string(), TradeConfig { depth })
+            .is_some()
+        {
+            return Err(format!("BYBIT_SYMBOLS lists {} more than once", symbol).into());
+        }
+    }
+    if trade_pairs.is_empty() {
+        return Err("BYBIT_SYMBOLS lists no symbols".into());
+    }
+    Ok(trade_pairs)
+}
+
+/// Order book of one symbol, locked on its own so symbols never contend
+type SharedOrderBook = Arc<RwLock<market::OrderBook>>;
+
+/// What persisting a merged book needs, read while the book is locked
+struct BookView {
+    symbol: String,
+    best_bid: Option<market::OrderBookItem>,
+    best_ask: Option<market::OrderBookItem>,
+    bid_levels: usize,
+    ask_levels: usize,
+    depth: market::CEXDepth,
+}
+
+impl BookView {
+    fn of(orderbook: &market::OrderBook) -> Self {
+        Self {
+            symbol: orderbook.symbol.clone(),
+            best_bid: orderbook.best_bid(),
+            best_ask: orderbook.best_ask(),
+            bid_levels: orderbook.bids.len(),
+            ask_levels: orderbook.asks.len(),
+            depth: market::CEXDepth::from_book(orderbook),
+        }
+    }
 }
 
 /// Bybit exchange screener for real-time market data
 pub struct BybitScreener {
     /// Database connection pool for storing market data
-    db_pool: Pool<MySql>,
-    /// Shutdown flag
-    shutdown: Arc<AtomicBool>,
-    /// Map of order books with symbol as key
-    order_book_map: Arc<Mutex<HashMap<String, market::OrderBook>>>,
+    db_pool: DatabasePool,
+    /// Shutdown signal, also checked by the websocket thread
+    shutdown: CancellationToken,
+    /// Mainnet or testnet, also the exchange name of the persisted rows
+    env: BybitEnv,
+    /// Streamed symbols with their order book depth, read again on every connect
+    trade_pairs: Arc<Mutex<BTreeMap<String, TradeConfig>>>,
+    /// Order book per symbol; the map is only written when a symbol is added or removed,
+    /// every message locks just the book of its symbol
+    order_book_map: Arc<RwLock<HashMap<String, SharedOrderBook>>>,
+    /// Last update id applied per symbol, missing until the first snapshot
+    book_sequences: Mutex<HashMap<String, BookSequence>>,
+    /// Latest ticker per symbol
+    tickers: Mutex<HashMap<String, market::CEXTicker>>,
+    /// Deviation of each symbol's book from its ticker
+    ticker_deviations: Mutex<HashMap<String, DeviationTracker>>,
+    ticker_check: TickerCheck,
+    /// Latest candle per symbol
+    klines: Mutex<HashMap<String, market::CEXKline>>,
+    /// Interval between two snapshots of the latest tickers
+    ticker_persist_interval: Duration,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    /// Last persisted top of book per symbol and when it was written
+    last_persisted: Mutex<HashMap<String, (TopOfBook, Instant)>>,
+    /// Longest time an unchanged top of book goes without being persisted
+    heartbeat_interval: Duration,
+    persist_stats: PersistStats,
+    /// Full order book snapshots, `None` when disabled
+    book_snapshots: Option<BookSnapshots>,
+    /// Database writes of ticker and order book snapshots, flushed on shutdown
+    pending_writes: Mutex<JoinSet<()>>,
+    /// Backoff between reconnects of a dropped websocket
+    reconnect_policy: RetryPolicy,
+    /// Source of REST snapshots rebuilding gapped books; without it a gap resubscribes
+    rest_client: Option<Arc<BybitRestClient>>,
+    /// Fetch a REST snapshot of every book when a session starts
+    rest_snapshot_on_connect: bool,
+    /// Deltas received per book while its REST snapshot is fetched
+    recovery_buffers: Mutex<HashMap<String, Vec<OrderbookMessage>>>,
+    /// Symbols whose REST snapshot is still to be fetched
+    snapshot_requests: Mutex<Vec<String>>,
+    /// Channel of the live session, used to ask it to resubscribe; weak so the channel
+    /// still closes when the websocket thread exits
+    session_tx: Mutex<Option<mpsc::WeakSender<StreamMessage>>>,
+    /// Set when the current session ends to pick up a changed symbol set
+    resubscribing: AtomicBool,
+    /// When the last message of each symbol arrived
+    last_message_at: Mutex<HashMap<String, tokio::time::Instant>>,
+    /// Silence after which the feed is considered stale and reconnected
+    stale_feed_timeout: Duration,
+    /// Set when the current session ends because its feed went stale
+    feed_stalled: AtomicBool,
+    /// Rolling exchange to local receipt latency per symbol
+    feed_latency: Mutex<HashMap<String, FeedLatency>>,
+    /// Tick size and lot filters per symbol, fetched at start and refreshed daily
+    instruments: BybitInstruments,
+    /// Interval between two fetches of the instrument filters
+    instrument_refresh_interval: Duration,
+    /// Symbols whose misaligned prices were already reported
+    misaligned_symbols: Mutex<HashSet<String>>,
+    /// Capture of every handled message, with `BYBIT_CAPTURE_RAW`
+    raw_capture: Option<RawCapture>,
+}
+
+/// How a websocket session ended
+struct SessionEnd {
+    /// Order book messages the session delivered
+    messages: usize,
+    /// Error the websocket thread exited with, `None` when it closed cleanly
+    error: Option<String>,
 }
 
 impl BybitScreener {
-    /// Create a new BybitScreener instance
-    pub fn new(db_pool: Pool<MySql>) -> Self {
-        let order_book_map = Arc::new(Mutex::new(HashMap::new()));
+    /// Create a new BybitScreener instance on the symbols of `BYBIT_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, BybitConfig::from_env()?))
+    }
 
-        {
-            let mut map = order_book_map.lock().unwrap();
-            for symbol in get_trade_pairs().keys() {
-                map.insert(symbol.to_string(), market::OrderBook::new("bybit", symbol));
-            }
+    /// Build the screener on already resolved symbols; the other settings are read from the env
+    pub fn with_config(db_pool: DatabasePool, config: BybitConfig) -> Self {
+        let cex_writer = CexMarketWriter::for_database(db_pool.clone(), "bybit");
+        let rest_client = BybitRestClient::from_env(&config.rest_url)
+            .inspect_err(|e| {
+                warn!(
+                    "Bybit REST client unavailable, gapped books will be resubscribed: {}",
+                    e
+                )
+            })
+            .ok()
+            .map(Arc::new);
+        Self {
+            rest_client,
+            rest_snapshot_on_connect: std::env::var("BYBIT_REST_SNAPSHOT_ON_CONNECT")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(false),
+            raw_capture: RawCapture::from_env(CAPTURE_PREFIX),
+            ..Self::build(db_pool, config, cex_writer)
         }
+    }
+
+    /// Screener on `config` persisting through `cex_writer`, without REST client or capture
+    fn build(db_pool: DatabasePool, config: BybitConfig, cex_writer: CexMarketWriter) -> Self {
+        let order_book_map = config
+            .trade_pairs
+            .keys()
+            .map(|symbol| {
+                let orderbook = market::OrderBook::new(config.env.exchange(), symbol);
+                (symbol.clone(), Arc::new(RwLock::new(orderbook)))
+            })
+            .collect();
 
         Self {
             db_pool,
-            shutdown: Arc::new(AtomicBool::new(false)),
-            order_book_map,
+            shutdown: CancellationToken::new(),
+            env: config.env,
+            trade_pairs: Arc::new(Mutex::new(config.trade_pairs)),
+            order_book_map: Arc::new(RwLock::new(order_book_map)),
+            book_sequences: Mutex::new(HashMap::new()),
+            tickers: Mutex::new(HashMap::new()),
+            ticker_deviations: Mutex::new(HashMap::new()),
+            ticker_check: TickerCheck::from_env(),
+            klines: Mutex::new(HashMap::new()),
+            ticker_persist_interval: ticker_persist_interval_from_env(),
+            cex_writer,
+            last_persisted: Mutex::new(HashMap::new()),
+            heartbeat_interval: heartbeat_interval_from_env(),
+            persist_stats: PersistStats::default(),
+            book_snapshots: BookSnapshots::from_env(),
+            pending_writes: Mutex::new(JoinSet::new()),
+            reconnect_policy: RetryPolicy {
+                max_attempts: u32::MAX,
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(30),
+            },
+            rest_client: None,
+            rest_snapshot_on_connect: false,
+            recovery_buffers: Mutex::new(HashMap::new()),
+            snapshot_requests: Mutex::new(Vec::new()),
+            session_tx: Mutex::new(None),
+            resubscribing: AtomicBool::new(false),
+            last_message_at: Mutex::new(HashMap::new()),
+            stale_feed_timeout: stale_feed_timeout_from_env(),
+            feed_stalled: AtomicBool::new(false),
+            feed_latency: Mutex::new(HashMap::new()),
+            instruments: BybitInstruments::new(),
+            instrument_refresh_interval: instrument_refresh_interval_from_env(),
+            misaligned_symbols: Mutex::new(HashSet::new()),
+            raw_capture: None,
         }
     }
 
-    /// Start the screener to read from WebSocket and process market data
+    /// Start the screener to read from WebSocket and process market data.
+    /// The websocket client blocks, so it runs on a blocking thread and hands its
+    /// messages over through a channel consumed here.
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
-        info!("🚀 Starting Bybit screener...");
+        info!(
+            "🚀 Starting Bybit screener on {} ({}), rows stored as exchange `{}`...",
+            self.env.to_string().to_uppercase(),
+            self.env.ws_url(),
+            self.env.exchange()
+        );
 
-        let mut client = WebSocketApiClient::spot().build();
+        let env = self.env;
+        let trade_pairs = self.trade_pairs.clone();
+        let supervise = self.supervise(move |shutdown, tx| {
+            let trade_pairs = trade_pairs.lock().unwrap().clone();
+            run_websocket(env, &trade_pairs, shutdown, tx)
+        });
+        tokio::join!(supervise, self.refresh_instruments());
+        Ok(())
+    }
 
-        for (symbol, conf) in get_trade_pairs() {
-            client.subscribe_orderbook(symbol, conf.depth);
-        }
+    /// Tick size and lot filters of `symbol`, once fetched
+    pub fn instrument_info(&self, symbol: &str) -> Option<InstrumentInfo> {
+        self.instruments.get(symbol)
+    }
 
-        client.run(|msg: SpotPublicResponse| {
-            if self.shutdown.load(Ordering::Relaxed) {
-                panic!("Stop signal received!");
+    /// Fetch the instrument filters of the streamed symbols now and then every
+    /// `instrument_refresh_interval`, until shutdown
+    async fn refresh_instruments(&self) {
+        let Some(rest) = &self.rest_client else {
+            warn!("Bybit REST client unavailable, prices are not checked against tick sizes");
+            return;
+        };
+        let mut refresh = tokio::time::interval(self.instrument_refresh_interval);
+        refresh.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return,
+                _ = refresh.tick() => {
+                    let symbols: Vec<String> =
+                        self.trade_pairs.lock().unwrap().keys().cloned().collect();
+                    self.instruments.refresh(rest, &symbols).await;
+                }
             }
+        }
+    }
 
-            match msg {
-                SpotPublicResponse::Orderbook(ob) => self.handle_orderbook(ob),
-                _ => (),
+    /// Start streaming `symbol` with the given order book depth.
+    /// The Bybit client cannot subscribe on a live connection, so the websocket session is
+    /// rebuilt with the new symbol set; the other symbols keep their persisted state.
+    pub async fn add_symbol(
+        &self,
+        symbol: &str,
+        depth: u16,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !is_valid_symbol(symbol) {
+            return Err(format!("`{}` is not a valid Bybit symbol", symbol).into());
+        }
+        if orderbook_depth(depth).is_none() {
+            return Err(format!(
+                "Unsupported Bybit order book depth {}, accepted depths: {:?}",
+                depth, SUPPORTED_DEPTHS
+            )
+            .into());
+        }
+        {
+            let mut trade_pairs = self.trade_pairs.lock().unwrap();
+            if trade_pairs.contains_key(symbol) {
+                return Err(format!("Bybit symbol {} is already streamed", symbol).into());
             }
-        })?;
+            trade_pairs.insert(symbol.to_string(), TradeConfig { depth });
+            self.order_book_map.write().unwrap().insert(
+                symbol.to_string(),
+                Arc::new(RwLock::new(market::OrderBook::new(
+                    self.env.exchange(),
+                    symbol,
+                ))),
+            );
+        }
+        info!("Bybit symbol {} added with depth {}", symbol, depth);
+        self.request_resubscribe().await;
         Ok(())
     }
 
+    /// Stop streaming `symbol` and forget its order book, ticker and candle.
+    /// The websocket session is rebuilt without it.
+    pub async fn remove_symbol(&self, symbol: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.trade_pairs.lock().unwrap().remove(symbol).is_none() {
+            return Err(format!("Bybit symbol {} is not streamed", symbol).into());
+        }
+        self.order_book_map.write().unwrap().remove(symbol);
+        self.book_sequences.lock().unwrap().remove(symbol);
+        self.recovery_buffers.lock().unwrap().remove(symbol);
+        self.tickers.lock().unwrap().remove(symbol);
+        self.ticker_deviations.lock().unwrap().remove(symbol);
+        self.klines.lock().unwrap().remove(symbol);
+        self.last_persisted.lock().unwrap().remove(symbol);
+        self.last_message_at.lock().unwrap().remove(symbol);
+        self.feed_latency.lock().unwrap().remove(symbol);
+        info!("Bybit symbol {} removed", symbol);
+        self.request_resubscribe().await;
+        Ok(())
+    }
+
+    /// Ask the live session, if any, to end so the next one subscribes to the current
+    /// symbol set. Without a live session the next connect picks it up anyway.
+    async fn request_resubscribe(&self) {
+        let tx = self
+            .session_tx
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|tx| tx.upgrade());
+        if let Some(tx) = tx {
+            let _ = tx.send(StreamMessage::Resubscribe).await;
+        }
+    }
+
+    /// Run websocket sessions opened by `connect` on a blocking thread until the screener
+    /// stops, reconnecting with backoff whenever a session ends on its own or a book falls
+    /// out of sequence. Order books are reset in between so the snapshots of the next
+    /// session rebuild them from scratch.
+    async fn supervise<C>(&self, connect: C)
+    where
+        C: Fn(CancellationToken, mpsc::Sender<StreamMessage>) -> Result<(), String>
+            + Clone
+            + Send
+            + 'static,
+    {
+        let mut reconnects = 0;
+        loop {
+            let (tx, rx) = mpsc::channel(MESSAGE_CHANNEL_CAPACITY);
+            *self.session_tx.lock().unwrap() = Some(tx.downgrade());
+            let session = self.shutdown.child_token();
+            let websocket = {
+                let session = session.clone();
+                let connect = connect.clone();
+                tokio::task::spawn_blocking(move || connect(session, tx))
+            };
+
+            let end = self.run_session(rx, websocket, &session, reconnects).await;
+            if self.shutdown.is_cancelled() {
+                break;
+            }
+
+            if self.resubscribing.swap(false, Ordering::Relaxed) {
+                info!("Bybit symbols changed, resubscribing the websocket");
+                reconnects = 0;
+                self.reset_order_books();
+                continue;
+            }
+            if end.messages > 0 {
+                reconnects = 0;
+            }
+            reconnects += 1;
+            self.reset_market_state();
+            let delay = self.reconnect_policy.backoff(reconnects);
+            let reason = match end.error {
+                _ if self.feed_stalled.swap(false, Ordering::Relaxed) => {
+                    format!("no message for {:?}", self.stale_feed_timeout)
+                }
+                _ if session.is_cancelled() => "order book out of sequence".to_string(),
+                Some(e) => e,
+                None => "closed by server".to_string(),
+            };
+            warn!(
+                "Bybit websocket disconnected ({}), reconnect attempt {} in {:?}",
+                reason, reconnects, delay
+            );
+            metrics::counter!("bybit_websocket_reconnects_total", "status" => "attempt")
+                .increment(1);
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        info!("Bybit screener stopped");
+    }
+
+    /// Apply the messages of one websocket session until the session is cancelled or
+    /// ends, join the websocket thread, then flush the pending database writes. No message
+    /// of the session is applied after the flush.
+    async fn run_session(
+        &self,
+        rx: mpsc::Receiver<StreamMessage>,
+        websocket: JoinHandle<Result<(), String>>,
+        session: &CancellationToken,
+        reconnects: u32,
+    ) -> SessionEnd {
+        // Closes and drops `rx`: from here on the thread's sends fail and it stops
+        let messages = self.consume_messages(rx, session, reconnects).await;
+
+        let joined = if session.is_cancelled() {
+            // The websocket thread notices the stop when its next frame arrives
+            match tokio::time::timeout(WEBSOCKET_STOP_TIMEOUT, websocket).await {
+                Ok(joined) => joined,
+                Err(_) => {
+                    // Leaked until its next frame, which it drops on the closed channel
+                    warn!(
+                        "Bybit websocket thread still waiting for a frame after {:?}, leaving it behind and dropping its late messages",
+                        WEBSOCKET_STOP_TIMEOUT
+                    );
+                    metrics::counter!("bybit_websocket_threads_leaked_total").increment(1);
+                    Ok(Ok(()))
+                }
+            }
+        } else {
+            websocket.await
+        };
+        self.flush_pending_writes().await;
+        let error = match joined {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e),
+            Err(e) => Some(e.to_string()),
+        };
+        SessionEnd { messages, error }
+    }
+
+    /// Apply stream messages until the session is cancelled or the channel closes, and
+    /// return how many were received. Messages already buffered when the stop arrives are
+    /// still applied. The first message after a reconnect marks the reconnect successful,
+    /// a book falling out of sequence or a feed silent for `stale_feed_timeout` cancels
+    /// the session. The latest tickers are persisted every `ticker_persist_interval` meanwhile.
+    async fn consume_messages(
+        &self,
+        mut rx: mpsc::Receiver<StreamMessage>,
+        session: &CancellationToken,
+        reconnects: u32,
+    ) -> usize {
+        let start = tokio::time::Instant::now() + self.ticker_persist_interval;
+        let mut persist_tickers = tokio::time::interval_at(start, self.ticker_persist_interval);
+        persist_tickers.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        // Never ticks when the snapshots are disabled
+        let snapshot_interval = self.book_snapshots.map_or(
+            Duration::from_secs(DEFAULT_SNAPSHOT_INTERVAL_SECS),
+            |snapshots| snapshots.interval,
+        );
+        let mut persist_books = tokio::time::interval_at(
+            tokio::time::Instant::now() + snapshot_interval,
+            snapshot_interval,
+        );
+        persist_books.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let stats_interval = Duration::from_secs(DEFAULT_SUMMARY_INTERVAL_SECS);
+        let mut log_stats =
+            tokio::time::interval_at(tokio::time::Instant::now() + stats_interval, stats_interval);
+        let session_start = tokio::time::Instant::now();
+        let mut watchdog = tokio::time::interval_at(
+            session_start + WATCHDOG_CHECK_INTERVAL,
+            WATCHDOG_CHECK_INTERVAL,
+        );
+
+        let mut snapshots = JoinSet::new();
+        if self.rest_snapshot_on_connect {
+            self.request_rest_snapshots();
+        }
+        self.spawn_snapshot_fetches(&mut snapshots);
+
+        let mut messages = 0;
+        loop {
+            tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(msg) => {
+                        if messages == 0 && reconnects > 0 {
+                            info!("Bybit websocket reconnected after {} attempts", reconnects);
+                            metrics::counter!("bybit_websocket_reconnects_total", "status" => "ok")
+                                .increment(1);
+                        }
+                        messages += 1;
+                        if self.handle_message(msg) == Flow::Stop {
+                            session.cancel();
+                        }
+                        self.spawn_snapshot_fetches(&mut snapshots);
+                    }
+                    None => {
+                        warn!("Bybit websocket message channel closed");
+                        return messages;
+                    }
+                },
+                Some(fetched) = snapshots.join_next(), if !snapshots.is_empty() => {
+                    if let Ok((symbol, snapshot)) = fetched
+                        && self.apply_rest_snapshot(&symbol, snapshot) == Flow::Stop
+                    {
+                        session.cancel();
+                    }
+                }
+                _ = persist_tickers.tick() => self.save_tickers(),
+                _ = persist_books.tick(), if self.book_snapshots.is_some() => {
+                    self.save_orderbook_snapshots()
+                }
+                _ = log_stats.tick() => self.log_stats(stats_interval),
+                _ = watchdog.tick() => {
+                    if self.check_feed(session_start) == Flow::Stop {
+                        session.cancel();
+                    }
+                }
+                _ = session.cancelled() => {
+                    rx.close();
+                    while let Ok(msg) = rx.try_recv() {
+                        messages += 1;
+                        self.handle_message(msg);
+                    }
+                    return messages;
+                }
+            }
+        }
+    }
+
+    /// Empty every order book so only the next snapshot repopulates it, and forget the
+    /// tickers they were checked against
+    fn reset_market_state(&self) {
+        self.reset_order_books();
+        self.tickers.lock().unwrap().clear();
+        self.ticker_deviations.lock().unwrap().clear();
+        self.klines.lock().unwrap().clear();
+        self.last_persisted.lock().unwrap().clear();
+    }
+
+    /// Empty every order book so only the next snapshot repopulates it
+    fn reset_order_books(&self) {
+        for (symbol, orderbook) in self.order_book_map.read().unwrap().iter() {
+            *orderbook.write().unwrap() = market::OrderBook::new(self.env.exchange(), symbol);
+        }
+        self.book_sequences.lock().unwrap().clear();
+        self.recovery_buffers.lock().unwrap().clear();
+        self.snapshot_requests.lock().unwrap().clear();
+    }
+
+    /// Mark every book as recovering so a REST snapshot seeds it, unless the websocket
+    /// snapshot arrives first
+    fn request_rest_snapshots(&self) {
+        if self.rest_client.is_none() {
+            return;
+        }
+        let mut sequences = self.book_sequences.lock().unwrap();
+        let mut requests = self.snapshot_requests.lock().unwrap();
+        for symbol in self.trade_pairs.lock().unwrap().keys() {
+            sequences.insert(symbol.clone(), BookSequence::Recovering);
+            requests.push(symbol.clone());
+        }
+    }
+
+    /// Start fetching the requested REST snapshots
+    fn spawn_snapshot_fetches(
+        &self,
+        snapshots: &mut JoinSet<(String, RestResult<OrderBookSnapshot>)>,
+    ) {
+        let Some(client) = &self.rest_client else {
+            return;
+        };
+        for symbol in self.snapshot_requests.lock().unwrap().drain(..) {
+            let client = client.clone();
+            let depth = self
+                .trade_pairs
+                .lock()
+                .unwrap()
+                .get(&symbol)
+                .map_or(SUPPORTED_DEPTHS[2], |conf| conf.depth);
+            snapshots.spawn(async move {
+                let snapshot = client.get_orderbook(&symbol, depth).await;
+                (symbol, snapshot)
+            });
+        }
+    }
+
+    /// Replace a recovering book with its REST snapshot and replay the deltas buffered
+    /// meanwhile. Returns `Flow::Stop` when the snapshot failed or does not line up with
+    /// the buffered deltas, so the book must be resubscribed.
+    fn apply_rest_snapshot(&self, symbol: &str, snapshot: RestResult<OrderBookSnapshot>) -> Flow {
+        let book = self.order_book(symbol);
+        let mut guard = book.as_ref().map(|book| book.write().unwrap());
+        let mut sequences = self.book_sequences.lock().unwrap();
+        if sequences.get(symbol) != Some(&BookSequence::Recovering) {
+            // A websocket snapshot rebuilt the book first
+            return Flow::Continue;
+        }
+        let buffered = self
+            .recovery_buffers
+            .lock()
+            .unwrap()
+            .remove(symbol)
+            .unwrap_or_default();
+
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!(
+                    "Bybit {} REST order book snapshot failed, resubscribing: {}",
+                    symbol, e
+                );
+                metrics::counter!("bybit_orderbook_rest_snapshots_total", "symbol" => symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                sequences.insert(symbol.to_string(), BookSequence::Gapped);
+                return Flow::Stop;
+            }
+        };
+        let Some(deltas) = splice_snapshot(snapshot.update_id, buffered) else {
+            warn!(
+                "Bybit {} REST order book snapshot at {} does not reach the buffered deltas, resubscribing",
+                symbol, snapshot.update_id
+            );
+            metrics::counter!("bybit_orderbook_rest_snapshots_total", "symbol" => symbol.to_string(), "status" => "stale")
+                .increment(1);
+            sequences.insert(symbol.to_string(), BookSequence::Gapped);
+            return Flow::Stop;
+        };
+        let Some(orderbook) = guard.as_deref_mut() else {
+            return Flow::Continue;
+        };
+
+        orderbook.bids = snapshot.orderbook.bids;
+        orderbook.asks = snapshot.orderbook.asks;
+        let (mut update_id, mut ts) = (snapshot.update_id, snapshot.ts);
+        for delta in &deltas {
+            self.merge_orderbook(
+                orderbook,
+                "delta",
+                &ws_levels(&delta.asks),
+                &ws_levels(&delta.bids),
+            );
+            (update_id, ts) = (delta.update_id, delta.ts);
+        }
+        info!(
+            "Bybit {} order book rebuilt from a REST snapshot at {}, replayed {} deltas",
+            symbol,
+            snapshot.update_id,
+            deltas.len()
+        );
+        metrics::counter!("bybit_orderbook_rest_snapshots_total", "symbol" => symbol.to_string(), "status" => "ok")
+            .increment(1);
+        sequences.insert(symbol.to_string(), BookSequence::Rebuilt(update_id));
+        drop(sequences);
+        if let Err(violation) = orderbook.validate() {
+            // Another REST snapshot would likely be just as broken
+            return self.resync_invalid_book(orderbook, violation, false);
+        }
+        let view = BookView::of(orderbook);
+        drop(guard);
+
+        self.save_order_book_state(update_id.to_string(), &view, ts, None);
+        Flow::Continue
+    }
+
+    /// Log how many order book states were written and skipped over the last window, and
+    /// how long ago each symbol received a message. Windows without any state only log
+    /// the message ages.
+    fn log_stats(&self, window: Duration) {
+        let (written, heartbeats, unchanged) = self.persist_stats.take();
+        if written + unchanged > 0 {
+            info!(
+                "[bybit] order book states over {:?}: written={} (heartbeats={}) skipped_unchanged={}",
+                window, written, heartbeats, unchanged
+            );
+        }
+        let ages = {
+            let map = self.order_book_map.read().unwrap();
+            let mut symbols: Vec<&String> = map.keys().collect();
+            symbols.sort();
+            format_message_ages(
+                symbols.into_iter(),
+                &self.last_message_at.lock().unwrap(),
+                tokio::time::Instant::now(),
+            )
+        };
+        if !ages.is_empty() {
+            info!("[bybit] last message age: {}", ages);
+        }
+
+        let mut feed_latency = self.feed_latency.lock().unwrap();
+        let latency = format_feed_latency(&feed_latency.iter().collect());
+        if !latency.is_empty() {
+            info!("[bybit] feed latency: {}", latency);
+        }
+        for symbol_latency in feed_latency.values_mut() {
+            symbol_latency.skewed = 0;
+        }
+    }
+
+    /// Record the latency of a message of `symbol` and return it, clamped to zero
+    fn record_feed_latency(&self, symbol: &str, received_ms: i64, exchange_ms: i64) -> u64 {
+        let (latency, skewed) = feed_latency_ms(received_ms, exchange_ms);
+        let mut feed_latency = self.feed_latency.lock().unwrap();
+        let symbol_latency = feed_latency.entry(symbol.to_string()).or_default();
+        symbol_latency.window.record(latency);
+        if skewed {
+            symbol_latency.skewed += 1;
+            metrics::counter!("bybit_feed_clock_skew_total", "symbol" => symbol.to_string())
+                .increment(1);
+        }
+        latency
+    }
+
+    /// Record the arrival of a message of `symbol`
+    fn record_message(&self, symbol: &str) {
+        let now = tokio::time::Instant::now();
+        let mut last_seen = self.last_message_at.lock().unwrap();
+        match last_seen.get_mut(symbol) {
+            Some(seen) => *seen = now,
+            None => {
+                last_seen.insert(symbol.to_string(), now);
+            }
+        }
+    }
+
+    /// Watchdog check: when no message arrived for `stale_feed_timeout`, mark every book
+    /// stale so its states stop being persisted and return `Flow::Stop` to reconnect
+    fn check_feed(&self, session_start: tokio::time::Instant) -> Flow {
+        let silence = feed_silence(
+            &self.last_message_at.lock().unwrap(),
+            session_start,
+            tokio::time::Instant::now(),
+            self.stale_feed_timeout,
+        );
+        let Some(silence) = silence else {
+            return Flow::Continue;
+        };
+        error!(
+            "Bybit websocket received no message for {:?}, marking order books stale and reconnecting",
+            silence
+        );
+        metrics::counter!("bybit_stale_feeds_total").increment(1);
+        let map = self.order_book_map.read().unwrap();
+        let mut sequences = self.book_sequences.lock().unwrap();
+        for symbol in map.keys() {
+            sequences.insert(symbol.clone(), BookSequence::Gapped);
+        }
+        self.feed_stalled.store(true, Ordering::Relaxed);
+        Flow::Stop
+    }
+
+    /// Write the order book states queued so far and wait for the ticker writes
+    async fn flush_pending_writes(&self) {
+        self.cex_writer.flush().await;
+        let mut pending_writes = std::mem::take(&mut *self.pending_writes.lock().unwrap());
+        while let Some(result) = pending_writes.join_next().await {
+            if let Err(e) = result {
+                error!("Bybit ticker write failed: {}", e);
+            }
+        }
+    }
+
     pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.shutdown.store(true, Ordering::Relaxed);
+        self.shutdown.cancel();
         Ok(())
     }
 
-    fn handle_orderbook(&self, msg: BasePublicResponse<Orderbook>) {
-        let data = &msg.data;
-        let symbol = data.s.to_string();
-        let mut map = self.order_book_map.lock().unwrap();
-        let orderbook = map.get_mut(&symbol).unwrap();
+    fn handle_message(&self, msg: StreamMessage) -> Flow {
+        if let Some(capture) = &self.raw_capture
+            && let Some(frame) = msg.to_capture()
+        {
+            capture.write(&frame);
+        }
+        match &msg {
+            StreamMessage::Orderbook(msg) => self.record_message(&msg.symbol),
+            StreamMessage::Ticker(ticker) => self.record_message(&ticker.trade_pair),
+            StreamMessage::Klines(klines) => {
+                if let Some(kline) = klines.first() {
+                    self.record_message(&kline.trade_pair);
+                }
+            }
+            StreamMessage::Resubscribe => {}
+        }
+        match msg {
+            StreamMessage::Orderbook(msg) => self.handle_orderbook(&msg),
+            StreamMessage::Ticker(ticker) => {
+                self.handle_ticker(ticker);
+                Flow::Continue
+            }
+            StreamMessage::Klines(klines) => {
+                self.handle_klines(klines);
+                Flow::Continue
+            }
+            StreamMessage::Resubscribe => {
+                self.resubscribing.store(true, Ordering::Relaxed);
+                Flow::Stop
+            }
+        }
+    }
+
+    /// Keep the latest candle of each symbol and upsert the candles that changed.
+    /// A forming candle is updated in place until Bybit confirms it.
+    fn handle_klines(&self, klines: Vec<market::CEXKline>) {
+        let mut updated = Vec::new();
+        {
+            let map = self.order_book_map.read().unwrap();
+            let mut latest = self.klines.lock().unwrap();
+            for mut kline in klines {
+                // Frames do not tell the environment apart
+                kline.exchange = self.env.exchange().to_string();
+                // Candles of a removed symbol may still be in flight
+                if kline.interval != KLINE_INTERVAL || !map.contains_key(&kline.trade_pair) {
+                    continue;
+                }
+                let update = kline_update(latest.get(&kline.trade_pair), &kline);
+                if update == KlineUpdate::Ignored {
+                    continue;
+                }
+                if update == KlineUpdate::Confirmed {
+                    debug!(
+                        "Bybit {} candle at {} confirmed, close {}",
+                        kline.trade_pair, kline.open_time, kline.close
+                    );
+                }
+                latest.insert(kline.trade_pair.clone(), kline.clone());
+                updated.push(kline);
+            }
+        }
+        if updated.is_empty() {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let mut pending_writes = self.pending_writes.lock().unwrap();
+        // Forget the writes that already completed
+        while pending_writes.try_join_next().is_some() {}
+        pending_writes.spawn(async move {
+            for kline in &updated {
+                if let Err(e) = upsert_cex_kline(&db_pool, kline).await {
+                    error!(
+                        "Failed to save Bybit {} candle at {}: {}",
+                        kline.trade_pair, kline.open_time, e
+                    );
+                }
+            }
+        });
+    }
+
+    /// Keep the latest ticker of its symbol and cross-check the symbol's book against it.
+    /// Books waiting for a snapshot are not checked.
+    fn handle_ticker(&self, mut ticker: market::CEXTicker) {
+        // Frames do not tell the environment apart
+        ticker.exchange = self.env.exchange().to_string();
+        let symbol = ticker.trade_pair.clone();
+        // Ticker of a removed symbol still in flight
+        let Some(book) = self.order_book(&symbol) else {
+            return;
+        };
+        let best = {
+            let orderbook = book.read().unwrap();
+            let sequences = self.book_sequences.lock().unwrap();
+            match sequences.get(&symbol) {
+                Some(sequence) if sequence.is_synced() => {
+                    orderbook.best_bid().zip(orderbook.best_ask())
+                }
+                _ => None,
+            }
+        };
+
+        self.record_feed_latency(
+            &symbol,
+            ticker.fetch_time.timestamp_millis(),
+            ticker.trade_time.timestamp_millis(),
+        );
+        if let Some((best_bid, best_ask)) = best {
+            let deviation_bps =
+                ticker_deviation_bps(best_bid.price, best_ask.price, ticker.last_price);
+            let deviating = deviation_bps > self.ticker_check.max_deviation_bps;
+            let mut deviations = self.ticker_deviations.lock().unwrap();
+            let tracker = deviations.entry(symbol.clone()).or_default();
+            match tracker.observe(deviating, Instant::now(), self.ticker_check.grace) {
+                DeviationAlert::Exceeded => {
+                    warn!(
+                        "Bybit {} book (bid {} / ask {}) is {} bps off the ticker last price {} for over {:?}, the merged book may be broken",
+                        symbol,
+                        best_bid.price,
+                        best_ask.price,
+                        deviation_bps.round_dp(2),
+                        ticker.last_price,
+                        self.ticker_check.grace
+                    );
+                    metrics::counter!("bybit_ticker_deviations_total", "symbol" => symbol.clone())
+                        .increment(1);
+                }
+                DeviationAlert::Recovered => {
+                    info!("Bybit {} book is back in line with the ticker", symbol);
+                }
+                DeviationAlert::Quiet => {}
+            }
+        }
+
+        self.tickers.lock().unwrap().insert(symbol, ticker);
+    }
+
+    /// Persist the latest ticker of every symbol
+    fn save_tickers(&self) {
+        let tickers: Vec<market::CEXTicker> =
+            self.tickers.lock().unwrap().values().cloned().collect();
+        if tickers.is_empty() {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let mut pending_writes = self.pending_writes.lock().unwrap();
+        // Forget the writes that already completed
+        while pending_writes.try_join_next().is_some() {}
+        pending_writes.spawn(async move {
+            for ticker in &tickers {
+                if let Err(e) = insert_cex_ticker(&db_pool, ticker).await {
+                    error!(
+                        "Failed to save Bybit ticker for {}: {}",
+                        ticker.trade_pair, e
+                    );
+                }
+            }
+        });
+    }
+
+    /// Best levels of every synced book, truncated to the snapshot depth
+    fn orderbook_snapshots(&self, depth: usize) -> Vec<market::CEXOrderBookSnapshot> {
+        let now = Utc::now();
+        let books: Vec<SharedOrderBook> = {
+            let order_book_map = self.order_book_map.read().unwrap();
+            let sequences = self.book_sequences.lock().unwrap();
+            order_book_map
+                .iter()
+                .filter(|(symbol, _)| {
+                    sequences
+                        .get(*symbol)
+                        .is_some_and(|sequence| sequence.is_synced())
+                })
+                .map(|(_, book)| book.clone())
+                .collect()
+        };
+        books
+            .iter()
+            .map(|book| market::CEXOrderBookSnapshot::from_book(&book.read().unwrap(), depth, now))
+            .collect()
+    }
+
+    /// Store the best levels of every synced book in `cex_orderbook_snapshots`
+    fn save_orderbook_snapshots(&self) {
+        let Some(config) = self.book_snapshots else {
+            return;
+        };
+        let snapshots = self.orderbook_snapshots(config.depth);
+        if snapshots.is_empty() {
+            return;
+        }
 
-        self.merge_orderbook(orderbook, &msg.type_, &data.a, &data.b);
+        let db_pool = self.db_pool.clone();
+        let mut pending_writes = self.pending_writes.lock().unwrap();
+        while pending_writes.try_join_next().is_some() {}
+        pending_writes.spawn(async move {
+            for snapshot in &snapshots {
+                if let Err(e) = insert_orderbook_snapshot(&db_pool, snapshot).await {
+                    error!(
+                        "Failed to save Bybit order book snapshot for {}: {}",
+                        snapshot.trade_pair, e
+                    );
+                }
+            }
+        });
+    }
+
+    /// Apply an order book message and persist the resulting book.
+    /// A book that missed updates is rebuilt from a REST snapshot when a REST client is
+    /// configured; otherwise `Flow::Stop` is returned so it gets resubscribed.
+    fn handle_orderbook(&self, msg: &OrderbookMessage) -> Flow {
+        // Messages of a removed symbol may still be in flight until the session is rebuilt
+        let Some(book) = self.order_book(&msg.symbol) else {
+            return Flow::Continue;
+        };
+        let latency = self.record_feed_latency(&msg.symbol, msg.received_ms, msg.ts as i64);
+        self.check_tick_alignment(msg);
+        // Held from the sequence check to the merge, so updates of a symbol apply in order
+        let mut orderbook = book.write().unwrap();
+        let mut sequences = self.book_sequences.lock().unwrap();
 
-        self.save_order_book_state(msg.data.u.to_string(), orderbook.clone(), msg.ts);
+        let last = sequences.get(&msg.symbol).copied();
+        let msg_type = match check_sequence(last, &msg.msg_type, msg.update_id) {
+            SequenceCheck::Snapshot => {
+                if msg.msg_type != "snapshot" {
+                    info!(
+                        "Bybit {} update ids restarted, rebuilding the order book",
+                        msg.symbol
+                    );
+                }
+                self.recovery_buffers.lock().unwrap().remove(&msg.symbol);
+                "snapshot"
+            }
+            SequenceCheck::Delta => "delta",
+            SequenceCheck::Skip => return Flow::Continue,
+            SequenceCheck::Buffer => {
+                let mut buffers = self.recovery_buffers.lock().unwrap();
+                let buffered = buffers.entry(msg.symbol.clone()).or_default();
+                buffered.push(msg.clone());
+                if buffered.len() <= MAX_BUFFERED_DELTAS {
+                    return Flow::Continue;
+                }
+                warn!(
+                    "Bybit {} REST order book snapshot is taking too long, resubscribing",
+                    msg.symbol
+                );
+                buffers.remove(&msg.symbol);
+                sequences.insert(msg.symbol.clone(), BookSequence::Gapped);
+                return Flow::Stop;
+            }
+            SequenceCheck::Resync if self.rest_client.is_some() => {
+                warn!(
+                    "Bybit {} order book missed updates before {}, rebuilding it from a REST snapshot",
+                    msg.symbol, msg.update_id
+                );
+                metrics::counter!("bybit_orderbook_gaps_total", "symbol" => msg.symbol.clone())
+                    .increment(1);
+                sequences.insert(msg.symbol.clone(), BookSequence::Recovering);
+                self.recovery_buffers
+                    .lock()
+                    .unwrap()
+                    .insert(msg.symbol.clone(), vec![msg.clone()]);
+                self.snapshot_requests
+                    .lock()
+                    .unwrap()
+                    .push(msg.symbol.clone());
+                return Flow::Continue;
+            }
+            SequenceCheck::Resync => {
+                warn!(
+                    "Bybit {} order book missed updates before {}, resubscribing",
+                    msg.symbol, msg.update_id
+                );
+                metrics::counter!("bybit_orderbook_gaps_total", "symbol" => msg.symbol.clone())
+                    .increment(1);
+                sequences.insert(msg.symbol.clone(), BookSequence::Gapped);
+                return Flow::Stop;
+            }
+        };
+        sequences.insert(msg.symbol.clone(), BookSequence::Synced(msg.update_id));
+        drop(sequences);
+
+        self.merge_orderbook(
+            &mut orderbook,
+            msg_type,
+            &ws_levels(&msg.asks),
+            &ws_levels(&msg.bids),
+        );
+        if let Err(violation) = orderbook.validate() {
+            return self.resync_invalid_book(&orderbook, violation, true);
+        }
+        let view = BookView::of(&orderbook);
+        drop(orderbook);
+
+        self.save_order_book_state(msg.update_id.to_string(), &view, msg.ts, Some(latency));
+        Flow::Continue
+    }
+
+    /// Report prices of a message that are not on the tick grid of their symbol, once per
+    /// symbol in the logs and every time in `bybit_misaligned_prices_total`
+    fn check_tick_alignment(&self, msg: &OrderbookMessage) {
+        let Some(info) = self.instruments.get(&msg.symbol) else {
+            return;
+        };
+        let misaligned = msg
+            .bids
+            .iter()
+            .chain(&msg.asks)
+            .filter_map(|(price, _)| price.parse::<Decimal>().ok())
+            .find(|price| !info.is_tick_aligned(*price));
+        let Some(price) = misaligned else {
+            return;
+        };
+        metrics::counter!("bybit_misaligned_prices_total", "symbol" => msg.symbol.clone())
+            .increment(1);
+        if self
+            .misaligned_symbols
+            .lock()
+            .unwrap()
+            .insert(msg.symbol.clone())
+        {
+            warn!(
+                "Bybit {} price {} in update {} is not a multiple of the tick size {}",
+                msg.symbol, price, msg.update_id, info.tick_size
+            );
+        }
+    }
+
+    /// Drop a merged book that cannot describe a real market instead of persisting it, and
+    /// rebuild it: from a REST snapshot when allowed and a REST client is configured,
+    /// otherwise by resubscribing (`Flow::Stop`). Called with the book still locked.
+    fn resync_invalid_book(
+        &self,
+        orderbook: &market::OrderBook,
+        violation: market::BookViolation,
+        rest_allowed: bool,
+    ) -> Flow {
+        let symbol = &orderbook.symbol;
+        error!(
+            "Bybit {} order book rejected, {}: {}",
+            symbol,
+            violation,
+            orderbook.describe_top(5)
+        );
+        metrics::counter!(
+            "bybit_invalid_books_total",
+            "symbol" => symbol.clone(),
+            "reason" => violation.kind()
+        )
+        .increment(1);
+
+        let mut sequences = self.book_sequences.lock().unwrap();
+        if rest_allowed && self.rest_client.is_some() {
+            sequences.insert(symbol.clone(), BookSequence::Recovering);
+            self.recovery_buffers
+                .lock()
+                .unwrap()
+                .insert(symbol.clone(), Vec::new());
+            self.snapshot_requests.lock().unwrap().push(symbol.clone());
+            return Flow::Continue;
+        }
+        sequences.insert(symbol.clone(), BookSequence::Gapped);
+        Flow::Stop
+    }
+
+    /// Book of `symbol`, if it is streamed
+    fn order_book(&self, symbol: &str) -> Option<SharedOrderBook> {
+        self.order_book_map.read().unwrap().get(symbol).cloned()
     }
 
     fn merge_orderbook(
@@ -118,70 +2023,192 @@ impl BybitScreener {
         asks: &Vec<OrderbookItem>,
         bids: &Vec<OrderbookItem>,
     ) {
-        // TODO: Improve merge algorithm. BTreeMap can be used for better performance.
         match msg_type {
             "snapshot" => {
-                orderbook.bids = bids
-                    .iter()
-                    .map(|orderbook_item| {
-                        market::OrderBookItem::new(orderbook_item.0, orderbook_item.1)
-                    })
-                    .collect();
-                orderbook.asks = asks
-                    .iter()
-                    .map(|orderbook_item| {
-                        market::OrderBookItem::new(orderbook_item.0, orderbook_item.1)
-                    })
-                    .collect();
-            }
-            "delta" => {
-                for orderbook_item in bids {
-                    market::OrderBook::merge_item(
-                        &mut orderbook.bids,
-                        orderbook_item.0,
-                        orderbook_item.1,
-                    );
-                }
-                if bids.len() > 0 {
-                    orderbook.bids.sort_by(|a, b| b.price.cmp(&a.price));
-                }
-
-                for orderbook_item in asks {
-                    market::OrderBook::merge_item(
-                        &mut orderbook.asks,
-                        orderbook_item.0,
-                        orderbook_item.1,
-                    );
-                }
-                if asks.len() > 0 {
-                    orderbook.asks.sort_by(|a, b| a.price.cmp(&b.price));
-                }
+                orderbook.bids.clear();
+                orderbook.asks.clear();
             }
-            _ => {}
+            "delta" => {}
+            _ => return,
+        }
+        for orderbook_item in bids {
+            market::OrderBook::merge_item(&mut orderbook.bids, orderbook_item.0, orderbook_item.1);
+        }
+        for orderbook_item in asks {
+            market::OrderBook::merge_item(&mut orderbook.asks, orderbook_item.0, orderbook_item.1);
         }
     }
 
-    fn save_order_book_state(&self, trade_id: String, orderbook: market::OrderBook, ts: u64) {
-        let best_bid = &orderbook.bids[0];
-        let best_ask = &orderbook.asks[0];
+    /// Persist the top of book, unless it is unchanged since the last write of its symbol
+    /// and that write is more recent than the heartbeat interval
+    fn save_order_book_state(
+        &self,
+        trade_id: String,
+        book: &BookView,
+        ts: u64,
+        feed_latency_ms: Option<u64>,
+    ) {
+        // Levels of a book that never received its snapshot do not describe the market
+        let synced = self
+            .book_sequences
+            .lock()
+            .unwrap()
+            .get(&book.symbol)
+            .is_some_and(|sequence| sequence.is_synced());
+        if !synced {
+            debug!(
+                "Bybit {} order book has no snapshot yet, not persisting update {}",
+                book.symbol, trade_id
+            );
+            return;
+        }
+        // An illiquid book can empty out on one side
+        let (Some(best_bid), Some(best_ask)) = (&book.best_bid, &book.best_ask) else {
+            debug!(
+                "Bybit {} order book has an empty side (bids: {}, asks: {}), not persisting update {}",
+                book.symbol, book.bid_levels, book.ask_levels, trade_id
+            );
+            return;
+        };
+        let top = TopOfBook {
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+        };
+        {
+            let now = Instant::now();
+            let mut last_persisted = self.last_persisted.lock().unwrap();
+            let decision = persist_decision(
+                last_persisted.get(&book.symbol),
+                &top,
+                now,
+                self.heartbeat_interval,
+            );
+            self.persist_stats.record(&decision);
+            if decision == PersistDecision::Unchanged {
+                metrics::counter!("bybit_cex_states_skipped_total", "symbol" => book.symbol.clone())
+                    .increment(1);
+                return;
+            }
+            last_persisted.insert(book.symbol.clone(), (top, now));
+        }
+
         let cex_state = market::CEXState {
             trade_id: trade_id,
-            exchange: String::from("bybit"),
-            trade_pair: orderbook.symbol,
+            exchange: self.env.exchange().to_string(),
+            trade_pair: book.symbol.clone(),
             bid_price: best_bid.price,
             bid_volume: best_bid.volume,
             ask_price: best_ask.price,
             ask_volume: best_ask.volume,
             trade_time: DateTime::from_timestamp_millis(ts as i64).unwrap_or_else(Utc::now),
             fetch_time: Utc::now(),
+            feed_latency_ms,
+            depth: Some(book.depth.clone()),
         };
         cex_state.log();
 
-        let db_pool = self.db_pool.clone();
-        tokio::spawn(async move {
-            let _ = insert_cex_market(&db_pool, &cex_state).await;
-        });
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+/// Drops the states of a replay, which must not touch the database
+struct DiscardSink;
+
+impl CexMarketSink for DiscardSink {
+    async fn write_states(
+        &self,
+        _states: &[market::CEXState],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// Order books left by a replayed capture
+#[derive(Debug)]
+pub struct ReplayOutcome {
+    pub books: BTreeMap<String, market::OrderBook>,
+    /// Order book frames fed to the screener
+    pub applied: usize,
+    /// Frames of other topics
+    pub skipped: usize,
+    /// Gaps that reset the books, as a reconnect would
+    pub resyncs: usize,
+}
+
+impl ReplayOutcome {
+    /// SHA-256 of every book's levels, equal for two replays reaching the same state
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (symbol, book) in &self.books {
+            hasher.update(symbol.as_bytes());
+            for (side, levels) in [("b", &book.bids), ("a", &book.asks)] {
+                hasher.update(side.as_bytes());
+                for (price, volume) in levels {
+                    hasher.update(format!("{}:{};", price.normalize(), volume.normalize()));
+                }
+            }
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Feed the order book frames of a capture file through the screener in the order they were
+/// received, without network or database. Missing REST snapshots make a gap reset the books
+/// until the next websocket snapshot, as a reconnect does.
+pub async fn replay_capture(path: &Path) -> Result<ReplayOutcome, Box<dyn std::error::Error>> {
+    let frames = read_capture(path)?;
+    let mut trade_pairs = BTreeMap::new();
+    for frame in &frames {
+        if let Some(msg) = OrderbookMessage::from_capture(frame) {
+            let depth = msg
+                .topic
+                .split('.')
+                .nth(1)
+                .and_then(|depth| depth.parse().ok())
+                .unwrap_or(SUPPORTED_DEPTHS[1]);
+            trade_pairs.insert(msg.symbol, TradeConfig { depth });
+        }
     }
+
+    let env = BybitEnv::Mainnet;
+    let config = BybitConfig {
+        trade_pairs,
+        env,
+        rest_url: env.rest_url().to_string(),
+    };
+    // Never connected: the states go to the discarding sink
+    let db_pool = lazy_pool();
+    let cex_writer = CexMarketWriter::spawn(DiscardSink, CexWriterConfig::from_env());
+    let screener = BybitScreener::build(db_pool, config, cex_writer);
+
+    let (mut applied, mut skipped, mut resyncs) = (0, 0, 0);
+    for frame in &frames {
+        let Some(msg) = OrderbookMessage::from_capture(frame) else {
+            skipped += 1;
+            continue;
+        };
+        applied += 1;
+        if screener.handle_orderbook(&msg) == Flow::Stop {
+            resyncs += 1;
+            screener.reset_order_books();
+        }
+    }
+
+    let books = screener
+        .order_book_map
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(symbol, book)| (symbol.clone(), book.read().unwrap().clone()))
+        .collect();
+    Ok(ReplayOutcome {
+        books,
+        applied,
+        skipped,
+        resyncs,
+    })
 }
 
 #[cfg(test)]
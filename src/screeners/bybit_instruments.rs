@@ -0,0 +1,132 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+use super::bybit_rest::{BybitRestClient, RestResult};
+use super::symbols::is_valid_symbol;
+
+/// Price and quantity filters of a spot instrument, from `/v5/market/instruments-info`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentInfo {
+    pub tick_size: Decimal,
+    /// Smallest base quantity increment
+    pub qty_step: Decimal,
+    pub min_qty: Decimal,
+    pub max_qty: Decimal,
+    /// Smallest order value in the quote coin
+    pub min_notional: Decimal,
+}
+
+/// Round `value` to a multiple of `step`; a zero step leaves it unchanged
+fn round_to_step(value: Decimal, step: Decimal, strategy: RoundingStrategy) -> Decimal {
+    if step.is_zero() {
+        return value.normalize();
+    }
+    ((value / step).round_dp_with_strategy(0, strategy) * step).normalize()
+}
+
+impl InstrumentInfo {
+    /// Round a price onto the tick grid with `strategy`
+    pub fn round_price_to_tick(&self, price: Decimal, strategy: RoundingStrategy) -> Decimal {
+        round_to_step(price, self.tick_size, strategy)
+    }
+
+    /// Round a quantity down to the lot step, so it never exceeds the requested size
+    pub fn round_qty_to_step(&self, qty: Decimal) -> Decimal {
+        round_to_step(qty, self.qty_step, RoundingStrategy::ToZero)
+    }
+
+    /// Whether an order of `qty` at `price` is worth at least the minimum order value
+    pub fn meets_min_notional(&self, qty: Decimal, price: Decimal) -> bool {
+        qty * price >= self.min_notional
+    }
+
+    /// Whether `price` is a multiple of the tick size
+    pub fn is_tick_aligned(&self, price: Decimal) -> bool {
+        self.tick_size.is_zero() || (price % self.tick_size).is_zero()
+    }
+}
+
+/// Decode a `/v5/market/instruments-info` result of a spot symbol
+pub(crate) fn parse_instrument_info(result: &Value) -> RestResult<InstrumentInfo> {
+    let instrument = result["list"].get(0).ok_or("no such Bybit instrument")?;
+    if instrument["status"] != "Trading" {
+        return Err(format!("Bybit instrument is {}", instrument["status"]).into());
+    }
+    let decimal = |value: &Value| -> RestResult<Decimal> {
+        let text = value.as_str().ok_or("missing instrument filter")?;
+        Ok(text.parse()?)
+    };
+    let lot = &instrument["lotSizeFilter"];
+    Ok(InstrumentInfo {
+        tick_size: decimal(&instrument["priceFilter"]["tickSize"])?,
+        qty_step: decimal(&lot["basePrecision"])?,
+        min_qty: decimal(&lot["minOrderQty"])?,
+        max_qty: decimal(&lot["maxOrderQty"])?,
+        min_notional: decimal(&lot["minOrderAmt"])?,
+    })
+}
+
+/// Instrument filters per symbol, fetched over REST and kept until the next refresh
+#[derive(Debug, Default)]
+pub struct BybitInstruments {
+    cache: Mutex<HashMap<String, InstrumentInfo>>,
+}
+
+impl BybitInstruments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filters of `symbol`, if they were fetched
+    pub fn get(&self, symbol: &str) -> Option<InstrumentInfo> {
+        self.cache.lock().unwrap().get(symbol).cloned()
+    }
+
+    /// Fetch the filters of `symbol` and cache them
+    pub async fn fetch(&self, rest: &BybitRestClient, symbol: &str) -> RestResult<InstrumentInfo> {
+        if !is_valid_symbol(symbol) {
+            return Err(format!("`{}` is not a valid Bybit symbol", symbol).into());
+        }
+        let query = format!("category=spot&symbol={}", symbol);
+        let result = rest.get("/v5/market/instruments-info", &query).await?;
+        let info = parse_instrument_info(&result)
+            .map_err(|e| format!("Bybit {} instrument info: {}", symbol, e))?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string(), info.clone());
+        Ok(info)
+    }
+
+    /// Cached filters of `symbol`, fetched on first use
+    pub async fn get_or_fetch(
+        &self,
+        rest: &BybitRestClient,
+        symbol: &str,
+    ) -> RestResult<InstrumentInfo> {
+        match self.get(symbol) {
+            Some(info) => Ok(info),
+            None => self.fetch(rest, symbol).await,
+        }
+    }
+
+    /// Fetch the filters of every symbol again; a failed fetch keeps the previous filters
+    pub async fn refresh(&self, rest: &BybitRestClient, symbols: &[String]) {
+        for symbol in symbols {
+            match self.fetch(rest, symbol).await {
+                Ok(info) => info!(
+                    "Bybit {} instrument info: tick {}, lot step {}, min order value {}",
+                    symbol, info.tick_size, info.qty_step, info.min_notional
+                ),
+                Err(e) => warn!("Failed to refresh Bybit {} instrument info: {}", symbol, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "bybit_instruments_tests.rs"]
+mod bybit_instruments_tests;
@@ -0,0 +1,109 @@
+use super::*;
+use std::str::FromStr;
+
+use crate::screeners::bybit_rest::parse_envelope;
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// `/v5/market/instruments-info` reply recorded for a spot symbol
+const INSTRUMENT_FIXTURE: &str = r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[{"symbol":"TRUMPUSDC","baseCoin":"TRUMP","quoteCoin":"USDC","innovation":"0","status":"Trading","marginTrading":"none","lotSizeFilter":{"basePrecision":"0.01","quotePrecision":"0.000001","minOrderQty":"0.1","maxOrderQty":"50000","minOrderAmt":"5","maxOrderAmt":"200000"},"priceFilter":{"tickSize":"0.001"}}]},"retExtInfo":{},"time":1716863719382}"#;
+
+fn info() -> InstrumentInfo {
+    parse_instrument_info(&parse_envelope(INSTRUMENT_FIXTURE).unwrap()).unwrap()
+}
+
+#[test]
+fn instrument_info_is_read_from_the_fixture() {
+    assert_eq!(
+        info(),
+        InstrumentInfo {
+            tick_size: decimal("0.001"),
+            qty_step: decimal("0.01"),
+            min_qty: decimal("0.1"),
+            max_qty: decimal("50000"),
+            min_notional: decimal("5"),
+        }
+    );
+    let halted = INSTRUMENT_FIXTURE.replace(r#""status":"Trading""#, r#""status":"Closed""#);
+    assert!(parse_instrument_info(&parse_envelope(&halted).unwrap()).is_err());
+    let missing = r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[]}}"#;
+    assert!(parse_instrument_info(&parse_envelope(missing).unwrap()).is_err());
+}
+
+#[test]
+fn prices_round_onto_the_tick() {
+    let info = info();
+
+    assert_eq!(
+        info.round_price_to_tick(decimal("10.250"), RoundingStrategy::ToZero),
+        decimal("10.25")
+    );
+    assert_eq!(
+        info.round_price_to_tick(decimal("10.2509"), RoundingStrategy::ToZero),
+        decimal("10.25")
+    );
+    assert_eq!(
+        info.round_price_to_tick(decimal("10.2501"), RoundingStrategy::AwayFromZero),
+        decimal("10.251")
+    );
+    assert_eq!(
+        info.round_price_to_tick(decimal("0.0009"), RoundingStrategy::ToZero),
+        Decimal::ZERO
+    );
+}
+
+#[test]
+fn quantities_round_down_to_the_step() {
+    let info = info();
+
+    assert_eq!(info.round_qty_to_step(decimal("1.23")), decimal("1.23"));
+    assert_eq!(info.round_qty_to_step(decimal("1.239")), decimal("1.23"));
+    assert_eq!(info.round_qty_to_step(decimal("0.009")), Decimal::ZERO);
+    assert_eq!(info.round_qty_to_step(Decimal::ZERO), Decimal::ZERO);
+}
+
+#[test]
+fn very_small_and_zero_steps_keep_the_precision() {
+    let info = InstrumentInfo {
+        tick_size: decimal("0.00000001"),
+        qty_step: decimal("0.000001"),
+        ..info()
+    };
+
+    assert_eq!(
+        info.round_price_to_tick(decimal("0.000012345678"), RoundingStrategy::ToZero),
+        decimal("0.00001234")
+    );
+    assert_eq!(
+        info.round_qty_to_step(decimal("123456.1234567")),
+        decimal("123456.123456")
+    );
+    assert!(info.is_tick_aligned(decimal("0.00001234")));
+    assert!(!info.is_tick_aligned(decimal("0.000012345")));
+
+    let unfiltered = InstrumentInfo {
+        tick_size: Decimal::ZERO,
+        qty_step: Decimal::ZERO,
+        ..info
+    };
+    assert_eq!(
+        unfiltered.round_price_to_tick(decimal("1.23450"), RoundingStrategy::ToZero),
+        decimal("1.2345")
+    );
+    assert_eq!(unfiltered.round_qty_to_step(decimal("7.5")), decimal("7.5"));
+    assert!(unfiltered.is_tick_aligned(decimal("1.2345")));
+}
+
+#[test]
+fn tick_alignment_and_min_notional() {
+    let info = info();
+
+    assert!(info.is_tick_aligned(decimal("10.25")));
+    assert!(info.is_tick_aligned(decimal("10.251000")));
+    assert!(!info.is_tick_aligned(decimal("10.2505")));
+    assert!(info.meets_min_notional(decimal("0.5"), decimal("10")));
+    assert!(!info.meets_min_notional(decimal("0.4"), decimal("10")));
+    assert!(!info.meets_min_notional(Decimal::ZERO, decimal("10")));
+}
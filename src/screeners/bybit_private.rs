@@ -0,0 +1,588 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::balance::CEXBalance;
+use crate::solana::retry::RetryPolicy;
+use crate::store::balances::insert_cex_balance;
+use crate::store::db::DatabasePool;
+
+use super::bybit::BybitEnv;
+
+/// Order events buffered for the consumer of `BybitPrivateClient`
+const ORDER_EVENT_CHANNEL_CAPACITY: usize = 1024;
+/// Lifetime of a signed auth request
+const AUTH_EXPIRY_MS: i64 = 10_000;
+/// Longest wait for the reply to the auth request
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Interval between two pings; Bybit drops private connections silent for longer than 10 minutes
+/// and recommends a ping every 20 seconds
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+/// Topics of the private stream
+const PRIVATE_TOPICS: [&str; 2] = ["wallet", "order"];
+
+/// API key pair of a Bybit account
+#[derive(Clone)]
+pub struct PrivateCredentials {
+    pub api_key: String,
+    api_secret: String,
+}
+
+impl PrivateCredentials {
+    pub fn new(api_key: &str, api_secret: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        }
+    }
+
+    /// Read `BYBIT_API_KEY` and `BYBIT_API_SECRET`.
+    /// Returns `None` when neither is set; only one of them set is an error.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let read = |name| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        match (read("BYBIT_API_KEY"), read("BYBIT_API_SECRET")) {
+            (Some(api_key), Some(api_secret)) => Ok(Some(Self::new(&api_key, &api_secret))),
+            (None, None) => Ok(None),
+            _ => Err("BYBIT_API_KEY and BYBIT_API_SECRET must be set together".into()),
+        }
+    }
+}
+
+impl std::fmt::Debug for PrivateCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivateCredentials")
+            .field("api_key", &self.api_key)
+            .field("api_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+impl PrivateCredentials {
+    /// Hex HMAC-SHA256 of `message` keyed by the API secret, as Bybit signs every private request
+    pub(crate) fn sign(&self, message: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(message.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Signature of a websocket auth request valid until `expires_ms`
+fn auth_signature(credentials: &PrivateCredentials, expires_ms: i64) -> String {
+    credentials.sign(&format!("GET/realtime{}", expires_ms))
+}
+
+/// Auth request of the private websocket, valid until `expires_ms`
+fn auth_message(credentials: &PrivateCredentials, expires_ms: i64) -> String {
+    json!({
+        "op": "auth",
+        "args": [
+            credentials.api_key,
+            expires_ms,
+            auth_signature(credentials, expires_ms),
+        ],
+    })
+    .to_string()
+}
+
+/// Free and locked amount of a coin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Balance {
+    pub free: Decimal,
+    pub locked: Decimal,
+}
+
+/// Balance per coin of the account
+pub type Balances = HashMap<String, Balance>;
+
+/// Lifecycle state of a Bybit order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    PartiallyFilledCanceled,
+    Rejected,
+    Untriggered,
+    Triggered,
+    Deactivated,
+    /// Status this client does not know yet
+    Other(String),
+}
+
+impl OrderStatus {
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "New" => Self::New,
+            "PartiallyFilled" => Self::PartiallyFilled,
+            "Filled" => Self::Filled,
+            "Cancelled" => Self::Cancelled,
+            "PartiallyFilledCanceled" => Self::PartiallyFilledCanceled,
+            "Rejected" => Self::Rejected,
+            "Untriggered" => Self::Untriggered,
+            "Triggered" => Self::Triggered,
+            "Deactivated" => Self::Deactivated,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// Bybit name of the status
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::New => "New",
+            Self::PartiallyFilled => "PartiallyFilled",
+            Self::Filled => "Filled",
+            Self::Cancelled => "Cancelled",
+            Self::PartiallyFilledCanceled => "PartiallyFilledCanceled",
+            Self::Rejected => "Rejected",
+            Self::Untriggered => "Untriggered",
+            Self::Triggered => "Triggered",
+            Self::Deactivated => "Deactivated",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Whether the order can no longer change
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Filled
+                | Self::Cancelled
+                | Self::PartiallyFilledCanceled
+                | Self::Rejected
+                | Self::Deactivated
+        )
+    }
+}
+
+/// State of an order after one of its updates
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEvent {
+    pub order_id: String,
+    /// Client order id, empty when the order was placed without one
+    pub order_link_id: String,
+    pub symbol: String,
+    /// `Buy` or `Sell`
+    pub side: String,
+    /// `Limit` or `Market`
+    pub order_type: String,
+    pub status: OrderStatus,
+    pub price: Decimal,
+    pub qty: Decimal,
+    pub cum_exec_qty: Decimal,
+    /// Average fill price, `None` until the order is filled
+    pub avg_price: Option<Decimal>,
+    /// Bybit's reason for a rejection, `None` for accepted orders
+    pub reject_reason: Option<String>,
+    pub update_time: DateTime<Utc>,
+}
+
+/// Decoded frame of the private websocket
+#[derive(Debug, Clone, PartialEq)]
+enum PrivateFrame {
+    /// Reply to an `auth`, `subscribe` or `ping` request
+    Op {
+        op: String,
+        success: bool,
+        ret_msg: String,
+    },
+    /// Current balances of the coins that changed, at the exchange time in milliseconds
+    Wallet {
+        ts: i64,
+        balances: Vec<(String, Balance)>,
+    },
+    Orders(Vec<OrderEvent>),
+    Other,
+}
+
+/// Decimal of a string field; Bybit sends an empty string for unset amounts
+fn decimal_field(value: &Value, field: &str) -> Result<Option<Decimal>, String> {
+    match value[field].as_str() {
+        None | Some("") => Ok(None),
+        Some(text) => text
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("`{}` of {:?} is not a decimal: {}", field, text, e)),
+    }
+}
+
+/// Decode a text frame of the private websocket
+fn parse_frame(text: &str) -> Result<PrivateFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if let Some(op) = frame["op"].as_str() {
+        return Ok(PrivateFrame::Op {
+            op: op.to_string(),
+            // Pongs carry no `success` flag
+            success: frame["success"].as_bool().unwrap_or(true),
+            ret_msg: frame["ret_msg"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    let data = frame["data"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    match frame["topic"].as_str() {
+        Some("wallet") => {
+            let mut balances = Vec::new();
+            for account in data {
+                for coin in account["coin"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                {
+                    let name = coin["coin"].as_str().ok_or("wallet coin without a name")?;
+                    let total = decimal_field(coin, "walletBalance")?.unwrap_or_default();
+                    let locked = decimal_field(coin, "locked")?.unwrap_or_default();
+                    balances.push((
+                        name.to_string(),
+                        Balance {
+                            free: total - locked,
+                            locked,
+                        },
+                    ));
+                }
+            }
+            Ok(PrivateFrame::Wallet {
+                ts: frame["creationTime"].as_i64().unwrap_or_default(),
+                balances,
+            })
+        }
+        Some("order") => data
+            .iter()
+            .map(order_event)
+            .collect::<Result<_, _>>()
+            .map(PrivateFrame::Orders),
+        _ => Ok(PrivateFrame::Other),
+    }
+}
+
+/// Decode one entry of an `order` frame, or of a REST order query
+pub(crate) fn order_event(order: &Value) -> Result<OrderEvent, String> {
+    let text = |field: &str| order[field].as_str().unwrap_or_default().to_string();
+    let updated_ms: i64 = order["updatedTime"]
+        .as_str()
+        .and_then(|ms| ms.parse().ok())
+        .ok_or("order without an update time")?;
+    Ok(OrderEvent {
+        order_id: text("orderId"),
+        order_link_id: text("orderLinkId"),
+        symbol: text("symbol"),
+        side: text("side"),
+        order_type: text("orderType"),
+        status: OrderStatus::parse(&text("orderStatus")),
+        price: decimal_field(order, "price")?.unwrap_or_default(),
+        qty: decimal_field(order, "qty")?.unwrap_or_default(),
+        cum_exec_qty: decimal_field(order, "cumExecQty")?.unwrap_or_default(),
+        avg_price: decimal_field(order, "avgPrice")?.filter(|price| !price.is_zero()),
+        reject_reason: Some(text("rejectReason"))
+            .filter(|reason| !reason.is_empty() && reason != "EC_NoError"),
+        update_time: DateTime::from_timestamp_millis(updated_ms).unwrap_or_else(Utc::now),
+    })
+}
+
+/// Apply the balances of a wallet frame, which carries the current amounts of the coins that
+/// changed. Coins left with nothing are removed.
+/// Returns the coins whose balance differs from the known one, with their new balance.
+fn apply_balance_updates(
+    balances: &mut Balances,
+    updates: Vec<(String, Balance)>,
+) -> Vec<(String, Balance)> {
+    let mut changed = Vec::new();
+    for (coin, balance) in updates {
+        let known = balances.get(&coin).copied().unwrap_or_default();
+        if known == balance {
+            continue;
+        }
+        if balance == Balance::default() {
+            balances.remove(&coin);
+        } else {
+            balances.insert(coin.clone(), balance);
+        }
+        changed.push((coin, balance));
+    }
+    changed
+}
+
+/// Authenticated Bybit websocket tracking the account's balances and orders
+pub struct BybitPrivateClient {
+    db_pool: DatabasePool,
+    env: BybitEnv,
+    credentials: PrivateCredentials,
+    shutdown: CancellationToken,
+    /// Latest balance per coin, filled by wallet updates
+    balances: Mutex<Balances>,
+    /// Order lifecycle events, drained by the receiver returned by `new`
+    order_tx: mpsc::Sender<OrderEvent>,
+    /// Database writes of changed balances, flushed when a session ends
+    pending_writes: Mutex<JoinSet<()>>,
+    /// Backoff between reconnects of a dropped or rejected connection
+    reconnect_policy: RetryPolicy,
+}
+
+impl BybitPrivateClient {
+    /// Build the client and the receiver of its order events.
+    /// Order events wait for the receiver, so it must be drained while the client runs.
+    pub fn new(
+        db_pool: DatabasePool,
+        env: BybitEnv,
+        credentials: PrivateCredentials,
+    ) -> (Self, mpsc::Receiver<OrderEvent>) {
+        let (order_tx, order_rx) = mpsc::channel(ORDER_EVENT_CHANNEL_CAPACITY);
+        let client = Self {
+            db_pool,
+            env,
+            credentials,
+            shutdown: CancellationToken::new(),
+            balances: Mutex::new(HashMap::new()),
+            order_tx,
+            pending_writes: Mutex::new(JoinSet::new()),
+            reconnect_policy: RetryPolicy {
+                max_attempts: u32::MAX,
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(30),
+            },
+        };
+        (client, order_rx)
+    }
+
+    /// Latest known balance per coin
+    pub fn balances(&self) -> Balances {
+        self.balances.lock().unwrap().clone()
+    }
+
+    /// Stream the account's updates until stopped. Every connection is authenticated with a
+    /// freshly signed request; a dropped, silent or rejected connection is retried after an
+    /// exponential backoff.
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Bybit private stream on {} ({}) for key {}...",
+            self.env.to_string().to_uppercase(),
+            self.env.private_ws_url(),
+            self.credentials.api_key
+        );
+
+        let mut reconnects = 0;
+        loop {
+            let mut delivered = 0;
+            let result = self.run_session(&mut delivered).await;
+            self.flush_pending_writes().await;
+            if self.shutdown.is_cancelled() {
+                break;
+            }
+
+            if delivered > 0 {
+                reconnects = 0;
+            }
+            reconnects += 1;
+            let delay = self.reconnect_policy.backoff(reconnects);
+            let reason = result
+                .err()
+                .unwrap_or_else(|| "closed by server".to_string());
+            warn!(
+                "Bybit private websocket disconnected ({}), reconnect attempt {} in {:?}",
+                reason, reconnects, delay
+            );
+            metrics::counter!("bybit_private_reconnects_total").increment(1);
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        info!("Bybit private stream stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Connect, authenticate, subscribe and handle frames until the connection drops or the
+    /// client stops. `delivered` counts the account updates received.
+    async fn run_session(&self, delivered: &mut usize) -> Result<(), String> {
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.env.private_ws_url())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+
+        let expires_ms = Utc::now().timestamp_millis() + AUTH_EXPIRY_MS;
+        ws.send(Message::Text(auth_message(&self.credentials, expires_ms)))
+            .await
+            .map_err(|e| e.to_string())?;
+        loop {
+            let frame = tokio::time::timeout(AUTH_TIMEOUT, ws.next())
+                .await
+                .map_err(|_| format!("no auth reply within {:?}", AUTH_TIMEOUT))?;
+            let text = match frame {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.to_string()),
+                None => return Err("closed before authentication".to_string()),
+            };
+            match parse_frame(&text)? {
+                PrivateFrame::Op { op, success, .. } if op == "auth" && success => break,
+                PrivateFrame::Op { op, ret_msg, .. } if op == "auth" => {
+                    metrics::counter!("bybit_private_auth_failures_total").increment(1);
+                    return Err(format!("authentication failed: {}", ret_msg));
+                }
+                _ => continue,
+            }
+        }
+
+        let subscribe = json!({ "op": "subscribe", "args": PRIVATE_TOPICS }).to_string();
+        ws.send(Message::Text(subscribe))
+            .await
+            .map_err(|e| e.to_string())?;
+        info!(
+            "Bybit private websocket authenticated, subscribing to {:?}",
+            PRIVATE_TOPICS
+        );
+
+        let mut ping =
+            tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        let mut last_frame = tokio::time::Instant::now();
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                _ = ping.tick() => {
+                    // Bybit answers every ping, so two intervals without a frame mean a dead link
+                    if last_frame.elapsed() > 2 * PING_INTERVAL {
+                        return Err(format!("no frame for {:?}", last_frame.elapsed()));
+                    }
+                    ws.send(Message::Text(json!({ "op": "ping" }).to_string()))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                frame = ws.next() => {
+                    last_frame = tokio::time::Instant::now();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            *delivered += self.handle_frame(&text).await;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply one text frame; returns the number of account updates it carried
+    async fn handle_frame(&self, text: &str) -> usize {
+        match parse_frame(text) {
+            Ok(PrivateFrame::Wallet { ts, balances }) => {
+                let updates = balances.len();
+                self.handle_balances(ts, balances);
+                updates
+            }
+            Ok(PrivateFrame::Orders(events)) => {
+                let updates = events.len();
+                for event in events {
+                    self.handle_order(event).await;
+                }
+                updates
+            }
+            Ok(PrivateFrame::Op {
+                op,
+                success: false,
+                ret_msg,
+            }) => {
+                warn!("Bybit private `{}` request failed: {}", op, ret_msg);
+                0
+            }
+            Ok(PrivateFrame::Op { .. } | PrivateFrame::Other) => 0,
+            Err(e) => {
+                warn!("Skipping malformed Bybit private frame: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Apply a wallet update and persist the balances that changed
+    fn handle_balances(&self, ts: i64, updates: Vec<(String, Balance)>) {
+        let changed = apply_balance_updates(&mut self.balances.lock().unwrap(), updates);
+        if changed.is_empty() {
+            return;
+        }
+
+        let update_time = DateTime::from_timestamp_millis(ts).unwrap_or_else(Utc::now);
+        let rows: Vec<CEXBalance> = changed
+            .into_iter()
+            .map(|(coin, balance)| {
+                info!(
+                    "Bybit {} balance: free {}, locked {}",
+                    coin, balance.free, balance.locked
+                );
+                CEXBalance {
+                    exchange: self.env.exchange().to_string(),
+                    coin,
+                    free: balance.free,
+                    locked: balance.locked,
+                    update_time,
+                    fetch_time: Utc::now(),
+                }
+            })
+            .collect();
+        let db_pool = self.db_pool.clone();
+        let mut pending_writes = self.pending_writes.lock().unwrap();
+        // Forget the writes that already completed
+        while pending_writes.try_join_next().is_some() {}
+        pending_writes.spawn(async move {
+            for row in &rows {
+                if let Err(e) = insert_cex_balance(&db_pool, row).await {
+                    error!("Failed to save Bybit {} balance: {}", row.coin, e);
+                }
+            }
+        });
+    }
+
+    /// Hand an order event to the receiver
+    async fn handle_order(&self, event: OrderEvent) {
+        debug!(
+            "Bybit order {} {} {} {:?}: {}/{} filled",
+            event.order_id, event.side, event.symbol, event.status, event.cum_exec_qty, event.qty
+        );
+        metrics::counter!("bybit_private_order_events_total", "final" => event.status.is_final().to_string())
+            .increment(1);
+        if self.order_tx.send(event).await.is_err() {
+            debug!("Bybit order event receiver dropped, event discarded");
+        }
+    }
+
+    /// Wait for the pending balance writes
+    async fn flush_pending_writes(&self) {
+        let mut pending_writes = std::mem::take(&mut *self.pending_writes.lock().unwrap());
+        while let Some(result) = pending_writes.join_next().await {
+            if let Err(e) = result {
+                error!("Bybit balance write failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "bybit_private_tests.rs"]
+mod bybit_private_tests;
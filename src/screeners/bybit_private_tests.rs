@@ -0,0 +1,173 @@
+use super::*;
+use std::str::FromStr;
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn balance(free: &str, locked: &str) -> Balance {
+    Balance {
+        free: decimal(free),
+        locked: decimal(locked),
+    }
+}
+
+#[test]
+fn auth_signature_matches_bybit_example() {
+    // Bybit signs `GET/realtime{expires}` with HMAC-SHA256 and hex encodes it, see
+    // https://bybit-exchange.github.io/docs/v5/ws/connect#authentication
+    assert_eq!(
+        auth_signature(
+            &PrivateCredentials::new("test_key", "test_secret"),
+            1662350400000
+        ),
+        "dad2d67a66c9ee401d2e6e7bf26ebd10eb1026db8a703bf3139c6950ce11a5c6"
+    );
+}
+
+#[test]
+fn auth_message_carries_key_expiry_and_signature() {
+    let credentials = PrivateCredentials::new("test_key", "test_secret");
+
+    let message: Value = serde_json::from_str(&auth_message(&credentials, 1662350400000)).unwrap();
+
+    assert_eq!(message["op"], "auth");
+    assert_eq!(
+        message["args"],
+        json!([
+            "test_key",
+            1662350400000i64,
+            "dad2d67a66c9ee401d2e6e7bf26ebd10eb1026db8a703bf3139c6950ce11a5c6"
+        ])
+    );
+}
+
+#[test]
+fn credentials_debug_hides_the_secret() {
+    let credentials = PrivateCredentials::new("test_key", "test_secret");
+
+    let debug = format!("{:?}", credentials);
+
+    assert!(debug.contains("test_key"), "{}", debug);
+    assert!(!debug.contains("test_secret"), "{}", debug);
+}
+
+#[test]
+fn parse_frame_reads_op_replies() {
+    assert_eq!(
+        parse_frame(r#"{"success":false,"ret_msg":"Params Error","op":"auth","conn_id":"cejreassvfrsfvb9v1a0-2m"}"#)
+            .unwrap(),
+        PrivateFrame::Op {
+            op: "auth".to_string(),
+            success: false,
+            ret_msg: "Params Error".to_string(),
+        }
+    );
+    assert_eq!(
+        parse_frame(r#"{"req_id":"","op":"pong","args":["1675418560633"],"conn_id":"cfcb4ocsvfrsfvb9v1a0-2m"}"#)
+            .unwrap(),
+        PrivateFrame::Op {
+            op: "pong".to_string(),
+            success: true,
+            ret_msg: String::new(),
+        }
+    );
+}
+
+#[test]
+fn parse_frame_reads_wallet_balances() {
+    let text = r#"{"id":"592324d2bce751-ad38-48eb-8f42-4671d1fb4d4e","topic":"wallet","creationTime":1700034722104,"data":[{"accountType":"UNIFIED","coin":[{"coin":"USDC","walletBalance":"1000.5","locked":"200","availableToWithdraw":""},{"coin":"TRUMP","walletBalance":"12","locked":""}]}]}"#;
+
+    assert_eq!(
+        parse_frame(text).unwrap(),
+        PrivateFrame::Wallet {
+            ts: 1700034722104,
+            balances: vec![
+                ("USDC".to_string(), balance("800.5", "200")),
+                ("TRUMP".to_string(), balance("12", "0")),
+            ],
+        }
+    );
+}
+
+#[test]
+fn parse_frame_reads_order_events() {
+    let text = r#"{"id":"5923240c6880ab-c59f-420b-9adb-3639adc9dd90","topic":"order","creationTime":1672364262474,"data":[{"category":"spot","symbol":"TRUMPUSDC","orderId":"1321003749386327552","orderLinkId":"arb-1","side":"Buy","orderType":"Limit","price":"10.5","qty":"3","orderStatus":"PartiallyFilled","cumExecQty":"1","avgPrice":"10.49","leavesQty":"2","rejectReason":"EC_NoError","createdTime":"1672364262444","updatedTime":"1672364262457"},{"category":"spot","symbol":"TRUMPUSDC","orderId":"1321003749386327553","orderLinkId":"","side":"Sell","price":"11","qty":"1","orderStatus":"Rejected","cumExecQty":"0","avgPrice":"","rejectReason":"EC_InsufficientBalance","updatedTime":"1672364262460"}]}"#;
+
+    let PrivateFrame::Orders(events) = parse_frame(text).unwrap() else {
+        panic!("not an order frame");
+    };
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].order_link_id, "arb-1");
+    assert_eq!(events[0].order_type, "Limit");
+    assert_eq!(events[0].status, OrderStatus::PartiallyFilled);
+    assert!(!events[0].status.is_final());
+    assert_eq!(events[0].cum_exec_qty, decimal("1"));
+    assert_eq!(events[0].avg_price, Some(decimal("10.49")));
+    assert_eq!(events[0].reject_reason, None);
+    assert_eq!(events[0].update_time.timestamp_millis(), 1672364262457);
+    assert_eq!(events[1].status, OrderStatus::Rejected);
+    assert!(events[1].status.is_final());
+    assert_eq!(events[1].avg_price, None);
+    assert_eq!(
+        events[1].reject_reason.as_deref(),
+        Some("EC_InsufficientBalance")
+    );
+}
+
+#[test]
+fn parse_frame_rejects_malformed_amounts() {
+    let text = r#"{"topic":"wallet","creationTime":1,"data":[{"coin":[{"coin":"USDC","walletBalance":"n/a"}]}]}"#;
+
+    assert!(parse_frame(text).is_err());
+    assert!(parse_frame("not json").is_err());
+    assert_eq!(
+        parse_frame(r#"{"topic":"position","data":[]}"#).unwrap(),
+        PrivateFrame::Other
+    );
+}
+
+#[test]
+fn apply_balance_updates_reports_only_changed_coins() {
+    let mut balances = Balances::new();
+    balances.insert("USDC".to_string(), balance("800", "200"));
+    balances.insert("TRUMP".to_string(), balance("12", "0"));
+
+    let changed = apply_balance_updates(
+        &mut balances,
+        vec![
+            ("USDC".to_string(), balance("800", "200")),
+            ("TRUMP".to_string(), balance("10", "2")),
+            ("SOL".to_string(), balance("1.5", "0")),
+        ],
+    );
+
+    assert_eq!(
+        changed,
+        [
+            ("TRUMP".to_string(), balance("10", "2")),
+            ("SOL".to_string(), balance("1.5", "0")),
+        ]
+    );
+    assert_eq!(balances["USDC"], balance("800", "200"));
+    assert_eq!(balances["TRUMP"], balance("10", "2"));
+    assert_eq!(balances.len(), 3);
+}
+
+#[test]
+fn apply_balance_updates_removes_emptied_coins() {
+    let mut balances = Balances::new();
+    balances.insert("TRUMP".to_string(), balance("12", "0"));
+
+    let changed = apply_balance_updates(
+        &mut balances,
+        vec![
+            ("TRUMP".to_string(), Balance::default()),
+            ("SOL".to_string(), Balance::default()),
+        ],
+    );
+
+    assert_eq!(changed, [("TRUMP".to_string(), Balance::default())]);
+    assert!(balances.is_empty());
+}
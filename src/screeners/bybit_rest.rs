@@ -0,0 +1,442 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::models::market;
+use crate::screeners::bybit_private::PrivateCredentials;
+use crate::solana::retry::RetryPolicy;
+
+/// Default timeout of a single request
+const DEFAULT_REST_TIMEOUT_MS: u64 = 5_000;
+/// Default attempts per snapshot, including the first one
+const DEFAULT_REST_MAX_ATTEMPTS: u32 = 3;
+/// Default time Bybit accepts a signed request after its timestamp
+pub const DEFAULT_RECV_WINDOW_MS: u64 = 5_000;
+/// Longest wait for a rate limit window to reset
+pub(crate) const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(10);
+/// Requests left in the window at or below which the next ones are spread over the window
+pub(crate) const LOW_RATE_LIMIT_REMAINING: u32 = 2;
+/// `retCode`s of a request rejected by the rate limiter, per UID and per IP
+const RATE_LIMITED_CODES: [i64; 2] = [10006, 10018];
+/// `retCode`s of a request with a bad timestamp, API key, signature or permission
+const AUTH_CODES: [i64; 7] = [10002, 10003, 10004, 10005, 10007, 10010, 33004];
+
+pub type RestResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Why a Bybit REST request failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BybitRestError {
+    /// Rejected by the rate limiter on every attempt: HTTP 403 or 429, or `retCode` 10006/10018
+    RateLimited { message: String },
+    /// Timestamp, API key, signature or permission refused: HTTP 401 or an auth `retCode`,
+    /// or a signed request without credentials
+    Auth { code: i64, message: String },
+    /// Request refused by the API with another `retCode` or 4xx status; never retried
+    InvalidRequest { code: i64, message: String },
+    /// The request did not complete: connection error, timeout, 5xx status or unreadable body
+    Transport { message: String },
+}
+
+impl std::fmt::Display for BybitRestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RateLimited { message } => write!(f, "Bybit rate limit hit: {}", message),
+            Self::Auth { code, message } => {
+                write!(f, "Bybit authentication error {}: {}", code, message)
+            }
+            Self::InvalidRequest { code, message } => {
+                write!(f, "Bybit API error {}: {}", code, message)
+            }
+            Self::Transport { message } => write!(f, "Bybit request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BybitRestError {}
+
+impl BybitRestError {
+    /// Error reported by a non-zero `retCode`
+    fn from_ret_code(code: i64, message: String) -> Self {
+        if RATE_LIMITED_CODES.contains(&code) {
+            Self::RateLimited { message }
+        } else if AUTH_CODES.contains(&code) {
+            Self::Auth { code, message }
+        } else {
+            Self::InvalidRequest { code, message }
+        }
+    }
+
+    /// Error of a request that failed in reqwest, with whether it may succeed when sent again
+    fn from_transport(error: reqwest::Error) -> (Self, bool) {
+        if let Some(status) = error.status() {
+            return Self::from_status(status, error.to_string());
+        }
+        let retryable = is_retryable(&error);
+        (
+            Self::Transport {
+                message: error.to_string(),
+            },
+            retryable,
+        )
+    }
+
+    /// Error of a non-success HTTP status, with whether it may succeed when sent again
+    fn from_status(status: StatusCode, message: String) -> (Self, bool) {
+        match status {
+            // Bybit answers 403 once the IP rate limit is breached
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS => {
+                (Self::RateLimited { message }, true)
+            }
+            StatusCode::UNAUTHORIZED => (
+                Self::Auth {
+                    code: status.as_u16() as i64,
+                    message,
+                },
+                false,
+            ),
+            status if status.is_server_error() => (Self::Transport { message }, true),
+            status => (
+                Self::InvalidRequest {
+                    code: status.as_u16() as i64,
+                    message,
+                },
+                false,
+            ),
+        }
+    }
+
+    /// Whether sending the request again may succeed
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimited { .. })
+    }
+}
+
+/// Order book fetched over REST, with the update id it reflects
+#[derive(Debug, Clone)]
+pub struct OrderBookSnapshot {
+    pub orderbook: market::OrderBook,
+    /// Update id `u` of the book, in the same sequence as websocket deltas
+    pub update_id: u64,
+    /// Bybit timestamp of the snapshot, in milliseconds
+    pub ts: u64,
+}
+
+/// Envelope of every v5 response; `result` is an empty object when `retCode` is not 0
+#[derive(Debug, Deserialize)]
+struct RestResponse {
+    #[serde(rename = "retCode")]
+    ret_code: i64,
+    #[serde(rename = "retMsg")]
+    ret_msg: String,
+    #[serde(default)]
+    result: Value,
+}
+
+/// `/v5/market/orderbook` result; levels are `[price, size]` pairs, best first
+#[derive(Debug, Deserialize)]
+struct RestOrderbook {
+    s: String,
+    a: Vec<(Decimal, Decimal)>,
+    b: Vec<(Decimal, Decimal)>,
+    ts: u64,
+    u: u64,
+}
+
+/// Request budget reported by the last signed response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RateLimit {
+    /// Requests left in the current window, `X-Bapi-Limit-Status`
+    pub(crate) remaining: Option<u32>,
+    /// When the window resets, in milliseconds, `X-Bapi-Limit-Reset-Timestamp`
+    pub(crate) reset_ms: Option<i64>,
+}
+
+impl RateLimit {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<i64>().ok())
+        };
+        Self {
+            remaining: number("X-Bapi-Limit-Status").map(|remaining| remaining.max(0) as u32),
+            reset_ms: number("X-Bapi-Limit-Reset-Timestamp"),
+        }
+    }
+
+    /// Time to wait before the next request once the budget runs low: the requests left are
+    /// spread evenly over the rest of the window, and none is sent before it resets once the
+    /// budget is used up
+    pub(crate) fn wait(&self, now_ms: i64) -> Option<Duration> {
+        let reset_ms = self.reset_ms?;
+        let remaining = self.remaining?;
+        if remaining > LOW_RATE_LIMIT_REMAINING || reset_ms <= now_ms {
+            return None;
+        }
+        let window = Duration::from_millis((reset_ms - now_ms) as u64);
+        Some((window / (remaining + 1)).min(MAX_RATE_LIMIT_WAIT))
+    }
+}
+
+/// Client of the Bybit v5 REST API shared by the market, order and fee modules.
+/// Signed requests are sent one at a time and paced on the `X-Bapi-Limit-*` budget of the
+/// last response; rate-limited requests, timeouts, connection errors and 5xx responses are
+/// retried with backoff, and every failure is reported as a `BybitRestError`.
+pub struct BybitRestClient {
+    http: reqwest::Client,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    credentials: Option<PrivateCredentials>,
+    recv_window_ms: u64,
+    rate_limit: Mutex<RateLimit>,
+    /// Held for the whole of a signed request, retries included
+    signed_lock: tokio::sync::Mutex<()>,
+}
+
+impl BybitRestClient {
+    pub fn new(base_url: &str, timeout: Duration, retry_policy: RetryPolicy) -> RestResult<Self> {
+        let http = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            retry_policy,
+            credentials: None,
+            recv_window_ms: DEFAULT_RECV_WINDOW_MS,
+            rate_limit: Mutex::new(RateLimit::default()),
+            signed_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Build the client on `base_url` from `BYBIT_REST_TIMEOUT_MS` and
+    /// `BYBIT_REST_MAX_ATTEMPTS`, falling back to the defaults
+    pub fn from_env(base_url: &str) -> RestResult<Self> {
+        let timeout_ms = std::env::var("BYBIT_REST_TIMEOUT_MS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&ms| ms > 0)
+            .unwrap_or(DEFAULT_REST_TIMEOUT_MS);
+        let max_attempts = std::env::var("BYBIT_REST_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&attempts| attempts > 0)
+            .unwrap_or(DEFAULT_REST_MAX_ATTEMPTS);
+        let retry_policy = RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+        };
+        Self::new(base_url, Duration::from_millis(timeout_ms), retry_policy)
+    }
+
+    /// Sign requests with `credentials`, valid for `recv_window_ms` after their timestamp
+    pub fn with_credentials(
+        mut self,
+        credentials: PrivateCredentials,
+        recv_window_ms: u64,
+    ) -> Self {
+        self.credentials = Some(credentials);
+        self.recv_window_ms = recv_window_ms;
+        self
+    }
+
+    /// Spot order book of `symbol` with up to `depth` levels per side.
+    /// Timeouts, connection errors, rate limits and 5xx responses are retried; API errors and
+    /// malformed bodies are not.
+    pub async fn get_orderbook(&self, symbol: &str, depth: u16) -> RestResult<OrderBookSnapshot> {
+        let query = format!("category=spot&limit={}&symbol={}", depth, symbol);
+        let result = self.get("/v5/market/orderbook", &query).await?;
+        orderbook_from_result(result)
+    }
+
+    /// Unsigned GET of a public endpoint, returning the `result` of the response
+    pub async fn get(&self, path: &str, query: &str) -> Result<Value, BybitRestError> {
+        self.request(&reqwest::Method::GET, path, query, false)
+            .await
+    }
+
+    /// Signed GET of a private endpoint, returning the `result` of the response
+    pub async fn signed_get(&self, path: &str, query: &str) -> Result<Value, BybitRestError> {
+        let _guard = self.signed_lock.lock().await;
+        self.request(&reqwest::Method::GET, path, query, true).await
+    }
+
+    /// Signed POST of a JSON body to a private endpoint, returning the `result` of the response
+    pub async fn signed_post(&self, path: &str, body: &str) -> Result<Value, BybitRestError> {
+        let _guard = self.signed_lock.lock().await;
+        self.request(&reqwest::Method::POST, path, body, true).await
+    }
+
+    /// Send a request until it succeeds, fails for good or runs out of attempts; waits before
+    /// each attempt while the rate limit budget is low
+    async fn request(
+        &self,
+        method: &reqwest::Method,
+        path: &str,
+        payload: &str,
+        signed: bool,
+    ) -> Result<Value, BybitRestError> {
+        let mut attempt = 1;
+        loop {
+            let wait = self
+                .rate_limit
+                .lock()
+                .unwrap()
+                .wait(Utc::now().timestamp_millis());
+            if let Some(wait) = wait {
+                warn!(
+                    "Bybit rate limit running low, waiting {:?} before {}",
+                    wait, path
+                );
+                metrics::counter!("bybit_rest_rate_limited_total").increment(1);
+                tokio::time::sleep(wait).await;
+            }
+
+            let (error, retryable) = match self.send(method, path, payload, signed).await {
+                Ok(result) => return Ok(result),
+                Err(failure) => failure,
+            };
+            if !retryable || attempt >= self.retry_policy.max_attempts {
+                return Err(error);
+            }
+            let delay = self.retry_policy.backoff(attempt);
+            warn!(
+                "Bybit {} failed on attempt {}/{}: {}, retrying in {:?}",
+                path, attempt, self.retry_policy.max_attempts, error, delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// One attempt of a request; a failure comes with whether it may succeed when sent again
+    async fn send(
+        &self,
+        method: &reqwest::Method,
+        path: &str,
+        payload: &str,
+        signed: bool,
+    ) -> Result<Value, (BybitRestError, bool)> {
+        let mut request = if *method == reqwest::Method::GET {
+            self.http
+                .get(format!("{}{}?{}", self.base_url, path, payload))
+        } else {
+            self.http
+                .post(format!("{}{}", self.base_url, path))
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload.to_string())
+        };
+        if signed {
+            let credentials = self.credentials.as_ref().ok_or_else(|| {
+                let error = BybitRestError::Auth {
+                    code: -1,
+                    message: format!("{} needs an API key", path),
+                };
+                (error, false)
+            })?;
+            let timestamp_ms = Utc::now().timestamp_millis();
+            request = request
+                .header("X-BAPI-API-KEY", &credentials.api_key)
+                .header("X-BAPI-TIMESTAMP", timestamp_ms.to_string())
+                .header("X-BAPI-RECV-WINDOW", self.recv_window_ms.to_string())
+                .header(
+                    "X-BAPI-SIGN",
+                    sign_request(credentials, timestamp_ms, self.recv_window_ms, payload),
+                );
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(BybitRestError::from_transport)?;
+        let limit = RateLimit::from_headers(response.headers());
+        if limit.remaining.is_some() {
+            *self.rate_limit.lock().unwrap() = limit;
+        }
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(BybitRestError::from_transport)?;
+        if !status.is_success() {
+            return Err(BybitRestError::from_status(
+                status,
+                format!("HTTP {}: {}", status, body.trim()),
+            ));
+        }
+        parse_envelope(&body).map_err(|error| {
+            let retryable = error.is_retryable();
+            (error, retryable)
+        })
+    }
+}
+
+/// `X-BAPI-SIGN` of a request: HMAC-SHA256 of timestamp, API key, receive window and the
+/// query string (GET) or JSON body (POST)
+pub(crate) fn sign_request(
+    credentials: &PrivateCredentials,
+    timestamp_ms: i64,
+    recv_window_ms: u64,
+    payload: &str,
+) -> String {
+    credentials.sign(&format!(
+        "{}{}{}{}",
+        timestamp_ms, credentials.api_key, recv_window_ms, payload
+    ))
+}
+
+/// Whether a failed request may succeed when sent again
+pub(crate) fn is_retryable(error: &reqwest::Error) -> bool {
+    if error.is_timeout() || error.is_connect() {
+        return true;
+    }
+    error.status().is_some_and(|status| {
+        status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    })
+}
+
+/// `result` of a v5 response body, or the error it reports
+pub(crate) fn parse_envelope(body: &str) -> Result<Value, BybitRestError> {
+    let response: RestResponse =
+        serde_json::from_str(body).map_err(|e| BybitRestError::Transport {
+            message: format!("malformed response: {}", e),
+        })?;
+    if response.ret_code != 0 {
+        return Err(BybitRestError::from_ret_code(
+            response.ret_code,
+            response.ret_msg,
+        ));
+    }
+    Ok(response.result)
+}
+
+/// Decode a `/v5/market/orderbook` result into an order book
+fn orderbook_from_result(result: Value) -> RestResult<OrderBookSnapshot> {
+    let result: RestOrderbook = serde_json::from_value(result)
+        .map_err(|e| format!("malformed Bybit order book result: {}", e))?;
+
+    let mut orderbook = market::OrderBook::new("bybit", &result.s);
+    orderbook.last_update_ts =
+        DateTime::from_timestamp_millis(result.ts as i64).unwrap_or_else(Utc::now);
+    for (price, volume) in result.b.into_iter().filter(|(_, volume)| !volume.is_zero()) {
+        orderbook.bids.insert(price, volume);
+    }
+    for (price, volume) in result.a.into_iter().filter(|(_, volume)| !volume.is_zero()) {
+        orderbook.asks.insert(price, volume);
+    }
+    Ok(OrderBookSnapshot {
+        orderbook,
+        update_id: result.u,
+        ts: result.ts,
+    })
+}
+
+#[cfg(test)]
+#[path = "bybit_rest_tests.rs"]
+mod bybit_rest_tests;
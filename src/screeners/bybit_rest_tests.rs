@@ -0,0 +1,402 @@
+use super::*;
+use reqwest::header::HeaderValue;
+use std::str::FromStr;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Decode a `/v5/market/orderbook` response body into an order book
+fn parse_orderbook(body: &str) -> RestResult<OrderBookSnapshot> {
+    orderbook_from_result(parse_envelope(body)?)
+}
+
+#[test]
+fn parse_orderbook_reads_levels_and_update_id() {
+    let body = r#"{"retCode":0,"retMsg":"OK","result":{"s":"TRUMPUSDC","a":[["101.5","2.0"],["102","0"],["103","1.5"]],"b":[["100.5","3.0"],["99","4.25"]],"ts":1716863719031,"u":230704,"seq":1432604333,"cts":1716863718905},"retExtInfo":{},"time":1716863719382}"#;
+
+    let snapshot = parse_orderbook(body).unwrap();
+
+    assert_eq!(snapshot.update_id, 230704);
+    assert_eq!(snapshot.ts, 1716863719031);
+    assert_eq!(snapshot.orderbook.symbol, "TRUMPUSDC");
+    assert_eq!(
+        snapshot.orderbook.best_bid().unwrap().price,
+        decimal("100.5")
+    );
+    assert_eq!(snapshot.orderbook.bids[&decimal("99")], decimal("4.25"));
+    assert_eq!(
+        snapshot.orderbook.best_ask().unwrap().price,
+        decimal("101.5")
+    );
+    assert_eq!(snapshot.orderbook.asks.len(), 2);
+    assert_eq!(
+        snapshot.orderbook.last_update_ts.timestamp_millis(),
+        1716863719031
+    );
+}
+
+#[test]
+fn parse_orderbook_reports_api_errors() {
+    let body = r#"{"retCode":10001,"retMsg":"params error: symbol invalid","result":{},"retExtInfo":{},"time":1716863719382}"#;
+
+    let error = parse_orderbook(body).unwrap_err().to_string();
+
+    assert!(error.contains("10001"), "{}", error);
+    assert!(error.contains("symbol invalid"), "{}", error);
+}
+
+#[test]
+fn parse_orderbook_rejects_malformed_bodies() {
+    for body in [
+        "<html>502 Bad Gateway</html>",
+        r#"{"retCode":0,"retMsg":"OK","result":{"s":"TRUMPUSDC","a":[["n/a","1"]],"b":[],"ts":1,"u":2}}"#,
+        r#"{"retCode":0,"retMsg":"OK"}"#,
+    ] {
+        assert!(parse_orderbook(body).is_err(), "{}", body);
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn unreachable_endpoint_fails_after_its_attempts() {
+    let client = BybitRestClient::new(
+        "http://127.0.0.1:1/",
+        Duration::from_millis(200),
+        RetryPolicy {
+            max_attempts: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        },
+    )
+    .unwrap();
+
+    assert!(client.get_orderbook("TRUMPUSDC", 50).await.is_err());
+}
+
+const OK_BODY: &str =
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[]},"retExtInfo":{},"time":1716863719382}"#;
+
+fn ret_code_body(code: i64, message: &str) -> String {
+    format!(
+        r#"{{"retCode":{},"retMsg":"{}","result":{{}},"retExtInfo":{{}},"time":1716863719382}}"#,
+        code, message
+    )
+}
+
+fn signed_client(base_url: &str, max_attempts: u32) -> BybitRestClient {
+    BybitRestClient::new(
+        base_url,
+        Duration::from_millis(500),
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        },
+    )
+    .unwrap()
+    .with_credentials(PrivateCredentials::new("test_key", "test_secret"), 5_000)
+}
+
+#[test]
+fn sign_request_signs_timestamp_key_window_and_payload() {
+    let credentials = PrivateCredentials::new("XXXXXXXXXX", "test_secret");
+    let body = r#"{"category":"spot","orderLinkId":"arb-1","orderType":"Limit","price":"10.25","qty":"1.5","side":"Buy","symbol":"TRUMPUSDC","timeInForce":"GTC"}"#;
+
+    assert_eq!(
+        sign_request(&credentials, 1658384314791, 5000, body),
+        "ee8a281189c0f9340ade3d13f9600a4f05ae50b42b690a2e690489367dd349e1"
+    );
+    assert_eq!(
+        sign_request(
+            &credentials,
+            1658384314791,
+            5000,
+            "category=spot&orderLinkId=arb-1&symbol=TRUMPUSDC"
+        ),
+        "4644757eef93dd408522b5f567f281c193b6785926ce42b4efa85c1ae28bf894"
+    );
+}
+
+#[test]
+fn parse_envelope_reports_typed_api_errors() {
+    assert_eq!(
+        parse_envelope(&ret_code_body(170141, "Duplicate clientOrderId.")).unwrap_err(),
+        BybitRestError::InvalidRequest {
+            code: 170141,
+            message: "Duplicate clientOrderId.".to_string(),
+        }
+    );
+    assert_eq!(
+        parse_envelope(&ret_code_body(10006, "Too many visits!")).unwrap_err(),
+        BybitRestError::RateLimited {
+            message: "Too many visits!".to_string(),
+        }
+    );
+    assert_eq!(
+        parse_envelope(&ret_code_body(10003, "API key is invalid.")).unwrap_err(),
+        BybitRestError::Auth {
+            code: 10003,
+            message: "API key is invalid.".to_string(),
+        }
+    );
+    assert!(matches!(
+        parse_envelope("<html>").unwrap_err(),
+        BybitRestError::Transport { .. }
+    ));
+}
+
+#[test]
+fn rate_limit_waits_once_the_budget_runs_low() {
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Bapi-Limit-Status", HeaderValue::from_static("0"));
+    headers.insert(
+        "X-Bapi-Limit-Reset-Timestamp",
+        HeaderValue::from_static("1672219527500"),
+    );
+
+    let exhausted = RateLimit::from_headers(&headers);
+
+    assert_eq!(
+        exhausted,
+        RateLimit {
+            remaining: Some(0),
+            reset_ms: Some(1672219527500),
+        }
+    );
+    assert_eq!(
+        exhausted.wait(1672219527000),
+        Some(Duration::from_millis(500))
+    );
+    assert_eq!(exhausted.wait(1672219527600), None);
+    assert_eq!(exhausted.wait(1672219000000), Some(MAX_RATE_LIMIT_WAIT));
+    let low = RateLimit {
+        remaining: Some(1),
+        ..exhausted
+    };
+    assert_eq!(low.wait(1672219527000), Some(Duration::from_millis(250)));
+    let available = RateLimit {
+        remaining: Some(LOW_RATE_LIMIT_REMAINING + 1),
+        ..exhausted
+    };
+    assert_eq!(available.wait(1672219527000), None);
+    assert_eq!(RateLimit::from_headers(&HeaderMap::new()).wait(0), None);
+}
+
+#[tokio::test]
+async fn signed_get_sends_auth_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/account/fee-rate"))
+        .and(query_param("symbol", "TRUMPUSDC"))
+        .and(header("X-BAPI-API-KEY", "test_key"))
+        .and(header("X-BAPI-RECV-WINDOW", "5000"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(OK_BODY))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = signed_client(&server.uri(), 1);
+
+    let result = client
+        .signed_get("/v5/account/fee-rate", "category=spot&symbol=TRUMPUSDC")
+        .await
+        .unwrap();
+
+    assert_eq!(result["list"], serde_json::json!([]));
+    let requests = server.received_requests().await.unwrap();
+    let headers = &requests[0].headers;
+    let timestamp_ms: i64 = headers["X-BAPI-TIMESTAMP"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(
+        headers["X-BAPI-SIGN"].to_str().unwrap(),
+        sign_request(
+            &PrivateCredentials::new("test_key", "test_secret"),
+            timestamp_ms,
+            5_000,
+            "category=spot&symbol=TRUMPUSDC"
+        )
+    );
+}
+
+#[tokio::test]
+async fn signed_requests_need_credentials() {
+    let server = MockServer::start().await;
+    let client = BybitRestClient::new(
+        &server.uri(),
+        Duration::from_millis(500),
+        RetryPolicy::default(),
+    )
+    .unwrap();
+
+    let error = client
+        .signed_post("/v5/order/create", "{}")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, BybitRestError::Auth { .. }), "{}", error);
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn exhausted_rate_limit_delays_the_next_request() {
+    let server = MockServer::start().await;
+    let reset_ms = Utc::now().timestamp_millis() + 400;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(OK_BODY)
+                .insert_header("X-Bapi-Limit-Status", "0")
+                .insert_header(
+                    "X-Bapi-Limit-Reset-Timestamp",
+                    reset_ms.to_string().as_str(),
+                ),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client = signed_client(&server.uri(), 1);
+
+    client
+        .signed_get("/v5/order/realtime", "category=spot")
+        .await
+        .unwrap();
+    let started = std::time::Instant::now();
+    client
+        .signed_get("/v5/order/realtime", "category=spot")
+        .await
+        .unwrap();
+
+    assert!(
+        started.elapsed() >= Duration::from_millis(250),
+        "{:?}",
+        started.elapsed()
+    );
+    assert!(Utc::now().timestamp_millis() >= reset_ms - 50);
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_until_they_succeed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(OK_BODY))
+        .mount(&server)
+        .await;
+    let client = signed_client(&server.uri(), 3);
+
+    assert!(client.signed_post("/v5/order/create", "{}").await.is_ok());
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_retried_then_reported() {
+    let server = MockServer::start().await;
+    Mock::given(path("/v5/order/create"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(ret_code_body(10006, "Too many visits!")),
+        )
+        .expect(3)
+        .mount(&server)
+        .await;
+    Mock::given(path("/v5/market/orderbook"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("access too frequent"))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client = signed_client(&server.uri(), 3);
+
+    let error = client
+        .signed_post("/v5/order/create", "{}")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, BybitRestError::RateLimited { .. }),
+        "{}",
+        error
+    );
+    let client = signed_client(&server.uri(), 2);
+    let error = client
+        .get("/v5/market/orderbook", "category=spot")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, BybitRestError::RateLimited { .. }),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn auth_and_invalid_requests_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(path("/v5/account/fee-rate"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(ret_code_body(10003, "API key is invalid.")),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(path("/v5/order/create"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(ret_code_body(170141, "Duplicate clientOrderId.")),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(path("/v5/order/cancel"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = signed_client(&server.uri(), 3);
+
+    assert_eq!(
+        client
+            .signed_get("/v5/account/fee-rate", "category=spot")
+            .await
+            .unwrap_err(),
+        BybitRestError::Auth {
+            code: 10003,
+            message: "API key is invalid.".to_string(),
+        }
+    );
+    assert_eq!(
+        client
+            .signed_post("/v5/order/create", "{}")
+            .await
+            .unwrap_err(),
+        BybitRestError::InvalidRequest {
+            code: 170141,
+            message: "Duplicate clientOrderId.".to_string(),
+        }
+    );
+    assert!(matches!(
+        client
+            .signed_post("/v5/order/cancel", "{}")
+            .await
+            .unwrap_err(),
+        BybitRestError::Auth { code: 401, .. }
+    ));
+}
+
+#[tokio::test]
+async fn unreachable_endpoint_is_a_transport_error() {
+    let client = signed_client("http://127.0.0.1:1", 2);
+
+    let error = client.get("/v5/market/time", "").await.unwrap_err();
+
+    assert!(
+        matches!(error, BybitRestError::Transport { .. }),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, warn};
+
+use crate::models::market::CEXState;
+use crate::store::db::DatabasePool;
+use crate::store::markets::insert_cex_markets_batch;
+use crate::store::write_buffer::{StateSink, WriteBuffer, WriteBufferConfig};
+
+/// Interval between two batched writes when `CEX_WRITE_FLUSH_INTERVAL_MS` is unset
+const DEFAULT_FLUSH_INTERVAL_MS: u64 = 200;
+/// States queued for the writer task when `CEX_WRITE_QUEUE_CAPACITY` is unset
+const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Destination of the batched CEX market states
+pub trait CexMarketSink: Send + Sync + 'static {
+    fn write_states(
+        &self,
+        states: &[CEXState],
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send;
+}
+
+impl CexMarketSink for DatabasePool {
+    async fn write_states(&self, states: &[CEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        insert_cex_markets_batch(self, states).await.map(|_| ())
+    }
+}
+
+impl<S: StateSink<CEXState>> CexMarketSink for WriteBuffer<CEXState, S> {
+    async fn write_states(&self, states: &[CEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        self.write(states).await;
+        Ok(())
+    }
+}
+
+/// Queue size and flush interval of a `CexMarketWriter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CexWriterConfig {
+    pub flush_interval: Duration,
+    pub queue_capacity: usize,
+}
+
+impl CexWriterConfig {
+    /// Read `CEX_WRITE_FLUSH_INTERVAL_MS` and `CEX_WRITE_QUEUE_CAPACITY`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let flush_interval_ms = std::env::var("CEX_WRITE_FLUSH_INTERVAL_MS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|millis| *millis > 0)
+            .unwrap_or(DEFAULT_FLUSH_INTERVAL_MS);
+        let queue_capacity = std::env::var("CEX_WRITE_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|capacity| *capacity > 0)
+            .unwrap_or(DEFAULT_QUEUE_CAPACITY);
+        Self {
+            flush_interval: Duration::from_millis(flush_interval_ms),
+            queue_capacity,
+        }
+    }
+}
+
+/// State waiting to be written, numbered in submission order
+#[derive(Debug)]
+struct PendingState {
+    seq: u64,
+    state: CEXState,
+}
+
+/// States waiting to be written, one per (exchange, trade pair)
+type PendingStates = HashMap<(String, String), PendingState>;
+
+/// Keep the newest of `pending` and the state already waiting for its pair.
+/// Returns the state that was superseded.
+fn coalesce(states: &mut PendingStates, pending: PendingState) -> Option<PendingState> {
+    let key = (
+        pending.state.exchange.clone(),
+        pending.state.trade_pair.clone(),
+    );
+    match states.get(&key) {
+        Some(current) if current.seq > pending.seq => Some(pending),
+        _ => states.insert(key, pending),
+    }
+}
+
+/// Queue of CEX market states drained by a single writer task, which keeps the newest
+/// state per pair and writes them with one multi-row insert every flush interval
+pub struct CexMarketWriter {
+    states: mpsc::Sender<PendingState>,
+    flushes: mpsc::Sender<oneshot::Sender<()>>,
+    /// Newest state per pair that did not fit in the full queue
+    overflow: Arc<Mutex<PendingStates>>,
+    next_seq: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl CexMarketWriter {
+    /// Spawn the writer task on `sink`. It stops after a final write once the writer is dropped.
+    pub fn spawn<S: CexMarketSink>(sink: S, config: CexWriterConfig) -> Self {
+        let (states, states_rx) = mpsc::channel(config.queue_capacity);
+        let (flushes, flushes_rx) = mpsc::channel(1);
+        let overflow = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(run_writer(
+            sink,
+            states_rx,
+            flushes_rx,
+            overflow.clone(),
+            config.flush_interval,
+        ));
+
+        Self {
+            states,
+            flushes,
+            overflow,
+            next_seq: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Spawn the writer of a venue on the database, behind a `WriteBuffer` keeping the states
+    /// it fails to write, both configured from the environment
+    pub fn for_database(db_pool: DatabasePool, venue: &str) -> Self {
+        let buffer = WriteBuffer::spawn(db_pool, WriteBufferConfig::from_env(venue));
+        Self::spawn(buffer, CexWriterConfig::from_env())
+    }
+
+    /// Queue a state for the next write without waiting. While the queue is full the state
+    /// waits in an overflow slot of its pair instead, where the latest state wins and the
+    /// one it replaces is dropped.
+    pub fn submit(&self, state: CEXState) {
+        let pending = PendingState {
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            state,
+        };
+        match self.states.try_send(pending) {
+            Ok(()) => {}
+            Err(TrySendError::Full(pending)) => {
+                let superseded = coalesce(&mut self.overflow.lock().unwrap(), pending);
+                if let Some(superseded) = superseded {
+                    self.count_drop(&superseded.state);
+                }
+            }
+            Err(TrySendError::Closed(pending)) => {
+                warn!(
+                    "CEX market writer stopped, dropping {} state {}",
+                    pending.state.trade_pair, pending.state.trade_id
+                );
+                self.count_drop(&pending.state);
+            }
+        }
+    }
+
+    /// Write every state submitted so far
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.flushes.send(done).await.is_ok() {
+            let _ = flushed.await;
+        }
+    }
+
+    /// States dropped because a newer one replaced them while the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn count_drop(&self, state: &CEXState) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!("cex_market_states_dropped_total", "trade_pair" => state.trade_pair.clone())
+            .increment(1);
+    }
+}
+
+/// Coalesce queued states and write them every `flush_interval` or on request,
+/// until every sender is gone
+async fn run_writer<S: CexMarketSink>(
+    sink: S,
+    mut states: mpsc::Receiver<PendingState>,
+    mut flushes: mpsc::Receiver<oneshot::Sender<()>>,
+    overflow: Arc<Mutex<PendingStates>>,
+    flush_interval: Duration,
+) {
+    let mut pending = HashMap::new();
+    let mut interval =
+        tokio::time::interval_at(tokio::time::Instant::now() + flush_interval, flush_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            state = states.recv() => match state {
+                Some(state) => {
+                    coalesce(&mut pending, state);
+                }
+                None => break,
+            },
+            Some(done) = flushes.recv() => {
+                // Take the states queued before the flush request
+                while let Ok(state) = states.try_recv() {
+                    coalesce(&mut pending, state);
+                }
+                write_pending(&sink, &mut pending, &overflow).await;
+                let _ = done.send(());
+            }
+            _ = interval.tick() => write_pending(&sink, &mut pending, &overflow).await,
+        }
+    }
+    write_pending(&sink, &mut pending, &overflow).await;
+}
+
+/// Write the pending states, including the ones parked in the overflow slots
+async fn write_pending<S: CexMarketSink>(
+    sink: &S,
+    pending: &mut PendingStates,
+    overflow: &Mutex<PendingStates>,
+) {
+    let parked: Vec<PendingState> = overflow.lock().unwrap().drain().map(|(_, p)| p).collect();
+    for state in parked {
+        coalesce(pending, state);
+    }
+    if pending.is_empty() {
+        return;
+    }
+
+    let mut batch: Vec<CEXState> = pending.drain().map(|(_, p)| p.state).collect();
+    batch.sort_by(|a, b| (&a.exchange, &a.trade_pair).cmp(&(&b.exchange, &b.trade_pair)));
+    if let Err(e) = sink.write_states(&batch).await {
+        let states: Vec<String> = batch
+            .iter()
+            .map(|state| format!("{} {} {}", state.exchange, state.trade_pair, state.trade_id))
+            .collect();
+        error!(
+            "Failed to write {} CEX market states ({}): {}",
+            batch.len(),
+            states.join(", "),
+            e
+        );
+        metrics::counter!("cex_market_write_errors_total").increment(1);
+    }
+}
+
+#[cfg(test)]
+#[path = "cex_writer_tests.rs"]
+mod cex_writer_tests;
@@ -0,0 +1,251 @@
+use super::*;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::sync::atomic::AtomicBool;
+
+use crate::store::markets::{CexMarketFilter, get_cex_markets};
+use crate::store::test_db::{test_pool, unique_exchange};
+use crate::store::write_buffer::DropPolicy;
+
+/// Fake sink recording every written batch, optionally failing the writes
+#[derive(Clone, Default)]
+struct MockSink {
+    batches: Arc<Mutex<Vec<Vec<CEXState>>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl MockSink {
+    /// `trade_pair:trade_id` of the states of every written batch
+    fn written(&self) -> Vec<Vec<String>> {
+        self.batches
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|state| format!("{}:{}", state.trade_pair, state.trade_id))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl CexMarketSink for MockSink {
+    async fn write_states(&self, states: &[CEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("database unavailable".into());
+        }
+        self.batches.lock().unwrap().push(states.to_vec());
+        Ok(())
+    }
+}
+
+impl StateSink<CEXState> for MockSink {
+    async fn write_batch(&self, states: &[CEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_states(states).await
+    }
+}
+
+fn state(trade_pair: &str, trade_id: &str) -> CEXState {
+    CEXState {
+        trade_id: trade_id.to_string(),
+        exchange: "bybit".to_string(),
+        trade_pair: trade_pair.to_string(),
+        bid_price: Decimal::from(100),
+        bid_volume: Decimal::ONE,
+        ask_price: Decimal::from(101),
+        ask_volume: Decimal::ONE,
+        trade_time: Utc::now(),
+        fetch_time: Utc::now(),
+        feed_latency_ms: None,
+        depth: None,
+    }
+}
+
+fn spawn_writer(sink: &MockSink, queue_capacity: usize) -> CexMarketWriter {
+    CexMarketWriter::spawn(
+        sink.clone(),
+        CexWriterConfig {
+            flush_interval: Duration::from_secs(60),
+            queue_capacity,
+        },
+    )
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn flush_writes_the_newest_state_per_pair_in_one_batch() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 16);
+
+    writer.submit(state("TRUMPUSDT", "1"));
+    writer.submit(state("TRUMPUSDC", "1"));
+    writer.submit(state("TRUMPUSDT", "2"));
+    writer.flush().await;
+
+    assert_eq!(sink.written(), [["TRUMPUSDC:1", "TRUMPUSDT:2"]]);
+    assert_eq!(writer.dropped(), 0);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn flush_without_new_states_writes_nothing() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 16);
+
+    writer.submit(state("TRUMPUSDC", "1"));
+    writer.flush().await;
+    writer.flush().await;
+
+    assert_eq!(sink.written().len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_queue_keeps_the_latest_state_per_pair_and_counts_drops() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 2);
+
+    // The writer task does not run before the flush, so the queue fills up
+    writer.submit(state("TRUMPUSDC", "1"));
+    writer.submit(state("TRUMPUSDC", "2"));
+    writer.submit(state("TRUMPUSDC", "3"));
+    writer.submit(state("TRUMPUSDC", "4"));
+    writer.submit(state("TRUMPUSDT", "1"));
+    writer.flush().await;
+
+    assert_eq!(sink.written(), [["TRUMPUSDC:4", "TRUMPUSDT:1"]]);
+    assert_eq!(writer.dropped(), 1);
+}
+
+#[test]
+fn newer_queued_state_wins_over_an_older_parked_one() {
+    let mut states = PendingStates::new();
+    let older = PendingState {
+        seq: 1,
+        state: state("TRUMPUSDC", "1"),
+    };
+    let newer = PendingState {
+        seq: 2,
+        state: state("TRUMPUSDC", "2"),
+    };
+
+    assert!(coalesce(&mut states, newer).is_none());
+    let superseded = coalesce(&mut states, older).unwrap();
+
+    assert_eq!(superseded.state.trade_id, "1");
+    assert_eq!(states.len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn failed_write_does_not_stop_the_writer() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 16);
+
+    sink.failing.store(true, Ordering::SeqCst);
+    writer.submit(state("TRUMPUSDC", "1"));
+    writer.flush().await;
+    sink.failing.store(false, Ordering::SeqCst);
+    writer.submit(state("TRUMPUSDC", "2"));
+    writer.flush().await;
+
+    assert_eq!(sink.written(), [["TRUMPUSDC:2"]]);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn write_buffer_keeps_failed_states_until_the_sink_recovers() {
+    let sink = MockSink::default();
+    let buffer = WriteBuffer::spawn(
+        sink.clone(),
+        WriteBufferConfig {
+            name: "test".to_string(),
+            capacity: 16,
+            spill_path: None,
+            drop_policy: DropPolicy::Oldest,
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(1),
+        },
+    );
+    let writer = CexMarketWriter::spawn(
+        buffer,
+        CexWriterConfig {
+            flush_interval: Duration::from_secs(60),
+            queue_capacity: 16,
+        },
+    );
+
+    sink.failing.store(true, Ordering::SeqCst);
+    writer.submit(state("TRUMPUSDC", "1"));
+    writer.flush().await;
+    sink.failing.store(false, Ordering::SeqCst);
+    writer.submit(state("TRUMPUSDC", "2"));
+    writer.flush().await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // In one or two batches, depending on whether the retry ran before the second write
+    assert_eq!(sink.written().concat(), ["TRUMPUSDC:1", "TRUMPUSDC:2"]);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn states_are_written_every_flush_interval() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 16);
+
+    writer.submit(state("TRUMPUSDC", "1"));
+    tokio::time::sleep(Duration::from_secs(61)).await;
+
+    assert_eq!(sink.written(), [["TRUMPUSDC:1"]]);
+}
+
+#[tokio::test]
+async fn writer_upserts_the_latest_state_of_every_pair_into_the_database() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("writer");
+    let writer = CexMarketWriter::spawn(
+        pool.clone(),
+        CexWriterConfig {
+            flush_interval: Duration::from_secs(60),
+            queue_capacity: 16,
+        },
+    );
+    let states = [
+        ("TRUMPUSDC", "1", 100),
+        ("TRUMPUSDC", "2", 101),
+        ("SOLUSDC", "3", 150),
+    ];
+    for (trade_pair, trade_id, bid) in states {
+        writer.submit(CEXState {
+            exchange: exchange.clone(),
+            bid_price: Decimal::from(bid),
+            ..state(trade_pair, trade_id)
+        });
+    }
+
+    writer.flush().await;
+    // Written again, the state of a trade updates its row
+    writer.submit(CEXState {
+        exchange: exchange.clone(),
+        bid_price: Decimal::from(102),
+        ..state("TRUMPUSDC", "2")
+    });
+    writer.flush().await;
+
+    let filter = CexMarketFilter {
+        exchange: Some(exchange),
+        ..CexMarketFilter::default()
+    };
+    let mut written: Vec<(String, String, Decimal)> = get_cex_markets(&pool, &filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|state| (state.trade_pair, state.trade_id, state.bid_price))
+        .collect();
+    written.sort();
+    assert_eq!(
+        written,
+        vec![
+            ("SOLUSDC".to_string(), "3".to_string(), Decimal::from(150)),
+            ("TRUMPUSDC".to_string(), "2".to_string(), Decimal::from(102)),
+        ]
+    );
+}
@@ -0,0 +1,563 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "coinbase";
+/// Symbols streamed when `COINBASE_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSD";
+/// Advanced Trade market data websocket when `COINBASE_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://advanced-trade-ws.coinbase.com";
+/// Order book channel; its messages arrive on `l2_data`
+const LEVEL2_CHANNEL: &str = "level2";
+/// Keeps the connection open while the books are quiet
+const HEARTBEATS_CHANNEL: &str = "heartbeats";
+/// Silence after which the connection is considered dead; heartbeats arrive every second
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// API key pair signing the subscriptions of a Coinbase account
+#[derive(Clone)]
+pub struct CoinbaseCredentials {
+    pub api_key: String,
+    api_secret: String,
+}
+
+impl CoinbaseCredentials {
+    pub fn new(api_key: &str, api_secret: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        }
+    }
+
+    /// Read `COINBASE_API_KEY` and `COINBASE_API_SECRET`.
+    /// Returns `None` when neither is set; only one of them set is an error.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let read = |name| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        match (read("COINBASE_API_KEY"), read("COINBASE_API_SECRET")) {
+            (Some(api_key), Some(api_secret)) => Ok(Some(Self::new(&api_key, &api_secret))),
+            (None, None) => Ok(None),
+            _ => Err("COINBASE_API_KEY and COINBASE_API_SECRET must be set together".into()),
+        }
+    }
+
+    /// Hex HMAC-SHA256 of the timestamp, channel and comma-separated product ids, keyed by
+    /// the API secret, as Coinbase signs websocket subscriptions
+    fn sign(&self, timestamp: i64, channel: &str, product_ids: &[String]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("{}{}{}", timestamp, channel, product_ids.join(",")).as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+impl std::fmt::Debug for CoinbaseCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoinbaseCredentials")
+            .field("api_key", &self.api_key)
+            .field("api_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Symbols, endpoint and optional credentials of the Coinbase screener
+#[derive(Debug, Clone)]
+pub struct CoinbaseConfig {
+    /// Internal symbols such as `TRUMPUSD`
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+    /// Signs every subscription when set; the public channels work without
+    pub credentials: Option<CoinbaseCredentials>,
+}
+
+impl CoinbaseConfig {
+    /// Read `COINBASE_SYMBOLS` (comma-separated internal symbols), `COINBASE_WS_URL` and the
+    /// optional `COINBASE_API_KEY`/`COINBASE_API_SECRET`, falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("COINBASE_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("COINBASE_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            credentials: CoinbaseCredentials::from_env()?,
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to a Coinbase product id
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "COINBASE_SYMBOLS entry `{}` has no Coinbase product id (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("COINBASE_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// `subscribe` or `unsubscribe` request of `channel` for `symbols`, signed at `timestamp`
+/// (seconds) when credentials are set
+fn channel_request(
+    op: &str,
+    channel: &str,
+    symbols: &[String],
+    credentials: Option<&CoinbaseCredentials>,
+    timestamp: i64,
+) -> String {
+    let product_ids: Vec<String> = symbols
+        .iter()
+        .filter_map(|symbol| to_venue_symbol(EXCHANGE, symbol))
+        .collect();
+    let mut request = json!({"type": op, "product_ids": product_ids, "channel": channel});
+    if let Some(credentials) = credentials {
+        request["api_key"] = json!(credentials.api_key);
+        request["timestamp"] = json!(timestamp.to_string());
+        request["signature"] = json!(credentials.sign(timestamp, channel, &product_ids));
+    }
+    request.to_string()
+}
+
+/// Price levels as `(price, quantity)`; a zero quantity removes the level
+type Levels = Vec<(Decimal, Decimal)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookAction {
+    Snapshot,
+    Update,
+}
+
+/// Snapshot or updates of one product in an `l2_data` message
+#[derive(Debug, Clone, PartialEq)]
+struct BookEvent {
+    /// Internal symbol of the product
+    symbol: String,
+    action: BookAction,
+    bids: Levels,
+    asks: Levels,
+}
+
+/// Decoded text frame of the websocket
+#[derive(Debug, Clone, PartialEq)]
+enum CoinbaseFrame {
+    /// Order book events, with the connection's message sequence number
+    Level2 {
+        sequence: u64,
+        timestamp: DateTime<Utc>,
+        events: Vec<BookEvent>,
+    },
+    /// Message of another channel, such as heartbeats or subscriptions
+    Other { sequence: u64, channel: String },
+    /// Rejected request
+    Error(String),
+}
+
+fn parse_event(event: &Value) -> Result<BookEvent, String> {
+    let product_id = event["product_id"].as_str().ok_or("missing `product_id`")?;
+    let symbol = from_venue_symbol(EXCHANGE, product_id)
+        .ok_or_else(|| format!("unexpected product {}", product_id))?;
+    let action = match event["type"].as_str() {
+        Some("snapshot") => BookAction::Snapshot,
+        Some("update") => BookAction::Update,
+        other => return Err(format!("unknown book event {:?}", other)),
+    };
+    let updates = event["updates"]
+        .as_array()
+        .ok_or("updates are not an array")?;
+    let (mut bids, mut asks) = (Vec::new(), Vec::new());
+    for update in updates {
+        let field = |name: &str| -> Result<Decimal, String> {
+            let text = update[name]
+                .as_str()
+                .ok_or_else(|| format!("malformed update {}", update))?;
+            text.parse()
+                .map_err(|e| format!("malformed update {}: {}", update, e))
+        };
+        let level = (field("price_level")?, field("new_quantity")?);
+        match update["side"].as_str() {
+            Some("bid") => bids.push(level),
+            Some("offer") => asks.push(level),
+            _ => return Err(format!("unknown side in {}", update)),
+        }
+    }
+    Ok(BookEvent {
+        symbol,
+        action,
+        bids,
+        asks,
+    })
+}
+
+fn parse_frame(text: &str) -> Result<CoinbaseFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if frame["type"] == "error" {
+        return Ok(CoinbaseFrame::Error(
+            frame["message"].as_str().unwrap_or_default().to_string(),
+        ));
+    }
+    let channel = frame["channel"].as_str().ok_or("missing `channel`")?;
+    let sequence = frame["sequence_num"]
+        .as_u64()
+        .ok_or("missing `sequence_num`")?;
+    if channel != "l2_data" {
+        return Ok(CoinbaseFrame::Other {
+            sequence,
+            channel: channel.to_string(),
+        });
+    }
+    let timestamp = frame["timestamp"]
+        .as_str()
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .ok_or("missing `timestamp`")?
+        .with_timezone(&Utc);
+    let events = frame["events"]
+        .as_array()
+        .ok_or("events are not an array")?
+        .iter()
+        .map(parse_event)
+        .collect::<Result<_, _>>()?;
+    Ok(CoinbaseFrame::Level2 {
+        sequence,
+        timestamp,
+        events,
+    })
+}
+
+fn apply_levels(levels: &mut market::OrderBookLevels, updates: &Levels) {
+    for (price, quantity) in updates {
+        if quantity.is_zero() {
+            levels.remove(price);
+        } else {
+            levels.insert(*price, *quantity);
+        }
+    }
+}
+
+/// Local book of a symbol
+#[derive(Debug, Clone)]
+struct SymbolBook {
+    orderbook: market::OrderBook,
+    /// Whether the snapshot arrived; updates before it are ignored
+    synced: bool,
+    /// Best bid and ask of the last persisted state
+    last_top: Option<(market::OrderBookItem, market::OrderBookItem)>,
+}
+
+impl SymbolBook {
+    fn new(symbol: &str) -> Self {
+        Self {
+            orderbook: market::OrderBook::new(EXCHANGE, symbol),
+            synced: false,
+            last_top: None,
+        }
+    }
+
+    /// Drop the book until the next snapshot
+    fn reset(&mut self) {
+        self.orderbook.bids.clear();
+        self.orderbook.asks.clear();
+        self.synced = false;
+        self.last_top = None;
+    }
+
+    /// Apply an event; returns whether the book is synced
+    fn on_event(&mut self, event: &BookEvent) -> bool {
+        match event.action {
+            BookAction::Snapshot => {
+                self.orderbook.replace_levels(&event.bids, &event.asks);
+                self.synced = true;
+                self.last_top = None;
+            }
+            BookAction::Update if self.synced => {
+                apply_levels(&mut self.orderbook.bids, &event.bids);
+                apply_levels(&mut self.orderbook.asks, &event.asks);
+            }
+            BookAction::Update => return false,
+        }
+        self.orderbook.last_update_ts = Utc::now();
+        true
+    }
+}
+
+/// Coinbase screener keeping a local book per symbol from the Advanced Trade `level2`
+/// channel, persisted as CEX market states
+pub struct CoinbaseScreener {
+    config: CoinbaseConfig,
+    shutdown: CancellationToken,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl CoinbaseScreener {
+    /// Create a new CoinbaseScreener instance on the symbols of `COINBASE_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, CoinbaseConfig::from_env()?))
+    }
+
+    pub fn with_config(db_pool: DatabasePool, config: CoinbaseConfig) -> Self {
+        let cex_writer = CexMarketWriter::for_database(db_pool, "coinbase");
+        Self {
+            config,
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Coinbase", EXCHANGE, STALE_FEED_TIMEOUT),
+        }
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection, or a missed message,
+    /// is retried after an exponential backoff and every book is rebuilt from new snapshots
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Coinbase screener for {:?} ({}, {})...",
+            self.config.symbols,
+            self.config.ws_url,
+            if self.config.credentials.is_some() {
+                "signed"
+            } else {
+                "unauthenticated"
+            }
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Coinbase screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(symbol)))
+            .collect();
+    }
+
+    /// Request of `channel` for `symbols`, signed now when credentials are set
+    fn request(&self, op: &str, channel: &str, symbols: &[String]) -> Message {
+        Message::Text(channel_request(
+            op,
+            channel,
+            symbols,
+            self.config.credentials.as_ref(),
+            Utc::now().timestamp(),
+        ))
+    }
+
+    /// Apply one text frame, adding the symbols whose book must be resubscribed to
+    /// `resubscribe`; returns whether it was an order book message. Sequence numbers count
+    /// every message of the connection, so a gap fails the session.
+    fn handle_frame(
+        &self,
+        text: &str,
+        last_sequence: &mut Option<u64>,
+        resubscribe: &mut Vec<String>,
+    ) -> Result<bool, String> {
+        let frame = match parse_frame(text) {
+            Ok(frame) => frame,
+            Err(e) => {
+                warn!("Skipping malformed Coinbase frame: {}", e);
+                return Ok(false);
+            }
+        };
+        let (sequence, timestamp, events) = match frame {
+            CoinbaseFrame::Error(message) => {
+                error!("Coinbase websocket error: {}", message);
+                return Ok(false);
+            }
+            CoinbaseFrame::Other { sequence, channel } => {
+                debug!("Coinbase {} message {}", channel, sequence);
+                check_sequence(last_sequence, sequence)?;
+                return Ok(false);
+            }
+            CoinbaseFrame::Level2 {
+                sequence,
+                timestamp,
+                events,
+            } => (sequence, timestamp, events),
+        };
+        check_sequence(last_sequence, sequence)?;
+
+        let mut books = self.books.lock().unwrap();
+        for event in events {
+            // Events of a symbol that is not streamed cannot be tracked
+            let Some(book) = books.get_mut(&event.symbol) else {
+                continue;
+            };
+            if !book.on_event(&event) {
+                continue;
+            }
+            if let Err(violation) = book.orderbook.validate() {
+                error!(
+                    "Coinbase {} order book rejected, {}: {}",
+                    event.symbol,
+                    violation,
+                    book.orderbook.describe_top(5)
+                );
+                metrics::counter!(
+                    "coinbase_invalid_books_total",
+                    "symbol" => event.symbol.clone(),
+                    "reason" => violation.kind()
+                )
+                .increment(1);
+                book.reset();
+                resubscribe.push(event.symbol);
+                continue;
+            }
+            self.persist(book, sequence, timestamp);
+        }
+        Ok(true)
+    }
+
+    /// Persist the top of book when it changed
+    fn persist(&self, book: &mut SymbolBook, sequence: u64, timestamp: DateTime<Utc>) {
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: sequence.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: book.orderbook.symbol.clone(),
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: timestamp,
+            fetch_time: now,
+            feed_latency_ms: Some((now - timestamp).num_milliseconds().max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+/// Check that `sequence` follows the last message of the connection
+fn check_sequence(last_sequence: &mut Option<u64>, sequence: u64) -> Result<(), String> {
+    if let Some(last) = *last_sequence
+        && sequence != last + 1
+    {
+        metrics::counter!("coinbase_sequence_gaps_total").increment(1);
+        return Err(format!(
+            "missed messages: sequence {} after {}",
+            sequence, last
+        ));
+    }
+    *last_sequence = Some(sequence);
+    Ok(())
+}
+
+impl WsSession for CoinbaseScreener {
+    /// Empty the books, connect, subscribe to the books and heartbeats and apply the stream until
+    /// the connection drops, a message is missed or the screener stops. `feed` counts the order
+    /// book messages delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        for channel in [HEARTBEATS_CHANNEL, LEVEL2_CHANNEL] {
+            ws.send(self.request("subscribe", channel, &self.config.symbols))
+                .await
+                .map_err(|e| format!("subscribe failed: {}", e))?;
+        }
+        info!(
+            "Coinbase websocket connected, subscribed to {:?}",
+            self.config.symbols
+        );
+
+        let mut last_sequence = None;
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            let mut resubscribe = Vec::new();
+                            if self.handle_frame(&text, &mut last_sequence, &mut resubscribe)? {
+                                feed.delivered();
+                            }
+                            if !resubscribe.is_empty() {
+                                // The new subscription starts with a snapshot
+                                for op in ["unsubscribe", "subscribe"] {
+                                    ws.send(self.request(op, LEVEL2_CHANNEL, &resubscribe))
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "coinbase_tests.rs"]
+mod coinbase_tests;
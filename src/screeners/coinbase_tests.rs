@@ -0,0 +1,230 @@
+use super::*;
+use std::str::FromStr;
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// `l2_data` snapshot as documented by Coinbase, with an offer added
+const SNAPSHOT_FIXTURE: &str = r#"{"channel":"l2_data","client_id":"","timestamp":"2023-02-09T20:32:50.714964855Z","sequence_num":1,"events":[{"type":"snapshot","product_id":"BTC-USD","updates":[{"side":"bid","event_time":"1970-01-01T00:00:00Z","price_level":"21921.73","new_quantity":"0.06317902"},{"side":"bid","event_time":"1970-01-01T00:00:00Z","price_level":"21921.3","new_quantity":"0.02"},{"side":"offer","event_time":"1970-01-01T00:00:00Z","price_level":"21921.74","new_quantity":"0.5"}]}]}"#;
+
+/// Updates following the snapshot: the best bid leaves, then a new best offer appears
+const UPDATE_FIXTURES: [&str; 2] = [
+    r#"{"channel":"l2_data","client_id":"","timestamp":"2023-02-09T20:32:50.800000000Z","sequence_num":2,"events":[{"type":"update","product_id":"BTC-USD","updates":[{"side":"bid","event_time":"2023-02-09T20:32:50.790000Z","price_level":"21921.73","new_quantity":"0"}]}]}"#,
+    r#"{"channel":"l2_data","client_id":"","timestamp":"2023-02-09T20:32:50.900000000Z","sequence_num":4,"events":[{"type":"update","product_id":"BTC-USD","updates":[{"side":"offer","event_time":"2023-02-09T20:32:50.890000Z","price_level":"21921.5","new_quantity":"1.25"}]}]}"#,
+];
+
+/// `heartbeats` message as documented by Coinbase
+const HEARTBEAT_FIXTURE: &str = r#"{"channel":"heartbeats","client_id":"","timestamp":"2023-06-23T20:31:26.122969572Z","sequence_num":3,"events":[{"current_time":"2023-06-23 20:31:56.121961769 +0000 UTC m=+91717.525857105","heartbeat_counter":"3049"}]}"#;
+
+fn events(text: &str) -> Vec<BookEvent> {
+    match parse_frame(text).unwrap() {
+        CoinbaseFrame::Level2 { events, .. } => events,
+        other => panic!("not an l2_data message: {:?}", other),
+    }
+}
+
+impl TestScreener for CoinbaseScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = CoinbaseScreener {
+            config: CoinbaseConfig {
+                symbols: vec!["BTCUSD".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+                credentials: None,
+            },
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Coinbase", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn level2_messages_are_decoded_with_internal_symbols() {
+    let frame = parse_frame(SNAPSHOT_FIXTURE).unwrap();
+
+    let CoinbaseFrame::Level2 {
+        sequence,
+        timestamp,
+        events,
+    } = frame
+    else {
+        panic!("not an l2_data message");
+    };
+    assert_eq!(sequence, 1);
+    assert_eq!(timestamp.timestamp_millis(), 1675974770714);
+    assert_eq!(
+        events,
+        [BookEvent {
+            symbol: "BTCUSD".to_string(),
+            action: BookAction::Snapshot,
+            bids: vec![
+                (decimal("21921.73"), decimal("0.06317902")),
+                (decimal("21921.3"), decimal("0.02"))
+            ],
+            asks: vec![(decimal("21921.74"), decimal("0.5"))],
+        }]
+    );
+    assert_eq!(events_action(UPDATE_FIXTURES[0]), BookAction::Update);
+}
+
+fn events_action(text: &str) -> BookAction {
+    events(text)[0].action
+}
+
+#[test]
+fn other_channels_and_errors_are_recognized() {
+    assert_eq!(
+        parse_frame(HEARTBEAT_FIXTURE),
+        Ok(CoinbaseFrame::Other {
+            sequence: 3,
+            channel: "heartbeats".to_string()
+        })
+    );
+    assert_eq!(
+        parse_frame(r#"{"type":"error","message":"authentication failure"}"#),
+        Ok(CoinbaseFrame::Error("authentication failure".to_string()))
+    );
+    assert!(parse_frame(&SNAPSHOT_FIXTURE.replace(r#""offer""#, r#""ask""#)).is_err());
+    assert!(parse_frame(&SNAPSHOT_FIXTURE.replace(r#""sequence_num":1,"#, "")).is_err());
+}
+
+#[test]
+fn subscriptions_are_signed_only_with_credentials() {
+    let symbols = ["ETHUSD".to_string(), "ETHEUR".to_string()];
+    let public: Value =
+        serde_json::from_str(&channel_request("subscribe", "level2", &symbols, None, 0)).unwrap();
+    assert_eq!(
+        public,
+        json!({"type": "subscribe", "product_ids": ["ETH-USD", "ETH-EUR"], "channel": "level2"})
+    );
+
+    // Documented message layout: timestamp + channel + comma-separated product ids
+    let credentials = CoinbaseCredentials::new("exampleApiKey123", "exampleApiSecret");
+    assert_eq!(
+        credentials.sign(
+            1660838876,
+            "level2",
+            &["ETH-USD".to_string(), "ETH-EUR".to_string()]
+        ),
+        "3de35b8d6af9d8b3cfed17de8a3d3ee24d31d9a3c30bad890ae60db1c06b0fba"
+    );
+    let signed: Value = serde_json::from_str(&channel_request(
+        "subscribe",
+        "level2",
+        &symbols,
+        Some(&credentials),
+        1660838876,
+    ))
+    .unwrap();
+    assert_eq!(
+        signed,
+        json!({
+            "type": "subscribe",
+            "product_ids": ["ETH-USD", "ETH-EUR"],
+            "channel": "level2",
+            "api_key": "exampleApiKey123",
+            "timestamp": "1660838876",
+            "signature": "3de35b8d6af9d8b3cfed17de8a3d3ee24d31d9a3c30bad890ae60db1c06b0fba"
+        })
+    );
+    assert!(!format!("{:?}", credentials).contains("exampleApiSecret"));
+}
+
+#[test]
+fn symbols_map_to_coinbase_product_ids() {
+    assert_eq!(
+        parse_symbols("trumpusd, TRUMPUSDC,TRUMPUSD").unwrap(),
+        ["TRUMPUSD", "TRUMPUSDC"]
+    );
+    assert!(parse_symbols("TRUMP").is_err());
+    assert!(parse_symbols("").is_err());
+}
+
+#[test]
+fn updates_wait_for_the_snapshot() {
+    let mut book = SymbolBook::new("BTCUSD");
+    assert!(!book.on_event(&events(UPDATE_FIXTURES[0])[0]));
+    assert!(book.orderbook.bids.is_empty());
+
+    assert!(book.on_event(&events(SNAPSHOT_FIXTURE)[0]));
+    assert!(book.on_event(&events(UPDATE_FIXTURES[0])[0]));
+    assert_eq!(
+        book.orderbook.bids.keys().copied().collect::<Vec<_>>(),
+        [decimal("21921.3")]
+    );
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_coinbase_states() {
+    let (screener, sink) = build_screener_with_sink::<CoinbaseScreener>();
+    let (mut last_sequence, mut resubscribe) = (None, Vec::new());
+
+    for text in [SNAPSHOT_FIXTURE, UPDATE_FIXTURES[0]] {
+        assert_eq!(
+            screener.handle_frame(text, &mut last_sequence, &mut resubscribe),
+            Ok(true)
+        );
+        screener.cex_writer.flush().await;
+    }
+    assert_eq!(
+        screener.handle_frame(HEARTBEAT_FIXTURE, &mut last_sequence, &mut resubscribe),
+        Ok(false)
+    );
+    screener
+        .handle_frame(UPDATE_FIXTURES[1], &mut last_sequence, &mut resubscribe)
+        .unwrap();
+    screener.cex_writer.flush().await;
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "coinbase" && state.trade_pair == "BTCUSD")
+    );
+    assert_eq!(states[0].bid_price, decimal("21921.73"));
+    assert_eq!(states[1].bid_price, decimal("21921.3"));
+    assert_eq!(
+        (states[2].trade_id.as_str(), states[2].ask_price),
+        ("4", decimal("21921.5"))
+    );
+    assert_eq!(states[0].trade_time.timestamp_millis(), 1675974770714);
+    assert!(resubscribe.is_empty());
+}
+
+#[tokio::test]
+async fn missed_messages_fail_the_session() {
+    let (screener, _sink) = build_screener_with_sink::<CoinbaseScreener>();
+    let (mut last_sequence, mut resubscribe) = (None, Vec::new());
+
+    screener
+        .handle_frame(SNAPSHOT_FIXTURE, &mut last_sequence, &mut resubscribe)
+        .unwrap();
+    let error = screener
+        .handle_frame(UPDATE_FIXTURES[1], &mut last_sequence, &mut resubscribe)
+        .unwrap_err();
+
+    assert!(error.contains("sequence 4 after 1"), "{}", error);
+}
+
+#[tokio::test]
+async fn crossed_books_are_resubscribed() {
+    let (screener, sink) = build_screener_with_sink::<CoinbaseScreener>();
+    let (mut last_sequence, mut resubscribe) = (None, Vec::new());
+    let crossed = SNAPSHOT_FIXTURE.replace(r#""21921.74""#, r#""21900""#);
+
+    screener
+        .handle_frame(&crossed, &mut last_sequence, &mut resubscribe)
+        .unwrap();
+
+    assert_eq!(resubscribe, ["BTCUSD"]);
+    assert!(!screener.books.lock().unwrap()["BTCUSD"].synced);
+    screener.cex_writer.flush().await;
+    assert!(sink.states.lock().unwrap().is_empty());
+}
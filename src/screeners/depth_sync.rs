@@ -0,0 +1,213 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use crate::models::market;
+
+/// Updates kept per book while its snapshot is fetched; more means the fetch is stuck
+pub(super) const MAX_BUFFERED_UPDATES: usize = 1_000;
+
+/// Price levels as `(price, quantity)`; a zero quantity removes the level
+pub(super) type Levels = Vec<(Decimal, Decimal)>;
+
+/// Event of the diff depth stream
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct DepthUpdate {
+    pub(super) symbol: String,
+    /// Event time `E`, in milliseconds
+    pub(super) event_ms: i64,
+    /// First update id `U` of the event
+    pub(super) first_update_id: u64,
+    /// Last update id `u` of the event
+    pub(super) last_update_id: u64,
+    pub(super) bids: Levels,
+    pub(super) asks: Levels,
+}
+
+/// REST depth snapshot of a symbol
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct DepthSnapshot {
+    pub(super) last_update_id: u64,
+    pub(super) bids: Levels,
+    pub(super) asks: Levels,
+}
+
+/// Decode `[["price", "quantity"], ...]` levels
+pub(super) fn parse_levels(value: &Value) -> Result<Levels, String> {
+    let levels = value.as_array().ok_or("levels are not an array")?;
+    levels
+        .iter()
+        .map(|level| {
+            let field = |index: usize| -> Result<Decimal, String> {
+                let text = level[index]
+                    .as_str()
+                    .ok_or_else(|| format!("malformed level {}", level))?;
+                text.parse()
+                    .map_err(|e| format!("malformed level {}: {}", level, e))
+            };
+            Ok((field(0)?, field(1)?))
+        })
+        .collect()
+}
+
+pub(super) fn parse_update_id(value: &Value, field: &str) -> Result<u64, String> {
+    value[field]
+        .as_u64()
+        .ok_or_else(|| format!("missing update id `{}`", field))
+}
+
+/// What to do with a diff depth update of a synced book
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum UpdateCheck {
+    Apply,
+    /// Already contained in the book
+    Skip,
+    /// Updates were missed, the book needs a new snapshot
+    Gap,
+}
+
+/// Check an update against the last update id applied to the book. The first update after
+/// a snapshot may start before it, as long as it reaches past it.
+pub(super) fn check_update(last_update_id: u64, update: &DepthUpdate) -> UpdateCheck {
+    if update.last_update_id <= last_update_id {
+        UpdateCheck::Skip
+    } else if update.first_update_id > last_update_id + 1 {
+        UpdateCheck::Gap
+    } else {
+        UpdateCheck::Apply
+    }
+}
+
+/// Synchronization of a local book with the diff depth stream
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum BookSync {
+    /// Updates are buffered until the REST snapshot arrives
+    AwaitingSnapshot(Vec<DepthUpdate>),
+    /// Book follows the stream up to update id `u`
+    Synced(u64),
+}
+
+/// Result of feeding an update to a book
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum UpdateOutcome {
+    Buffered,
+    Applied,
+    Skipped,
+    /// The book lost its sync and needs a new snapshot
+    Resync,
+}
+
+/// Result of feeding a REST snapshot to a book
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum SnapshotOutcome {
+    /// Book rebuilt from the snapshot with `replayed` buffered updates on top
+    Synced { replayed: usize },
+    /// The snapshot is older than the buffered updates and must be fetched again
+    Stale,
+    /// The book was not waiting for a snapshot
+    Ignored,
+}
+
+/// Local book of a symbol with its sync state
+#[derive(Debug, Clone)]
+pub(super) struct SymbolBook {
+    pub(super) orderbook: market::OrderBook,
+    pub(super) sync: BookSync,
+    /// Best bid and ask of the last persisted state
+    pub(super) last_top: Option<(market::OrderBookItem, market::OrderBookItem)>,
+}
+
+pub(super) fn apply_levels(levels: &mut market::OrderBookLevels, updates: &Levels) {
+    for (price, quantity) in updates {
+        if quantity.is_zero() {
+            levels.remove(price);
+        } else {
+            levels.insert(*price, *quantity);
+        }
+    }
+}
+
+impl SymbolBook {
+    pub(super) fn new(exchange: &str, symbol: &str) -> Self {
+        Self {
+            orderbook: market::OrderBook::new(exchange, symbol),
+            sync: BookSync::AwaitingSnapshot(Vec::new()),
+            last_top: None,
+        }
+    }
+
+    pub(super) fn apply(&mut self, update: &DepthUpdate) {
+        apply_levels(&mut self.orderbook.bids, &update.bids);
+        apply_levels(&mut self.orderbook.asks, &update.asks);
+        self.orderbook.last_update_ts = Utc::now();
+        self.sync = BookSync::Synced(update.last_update_id);
+    }
+
+    /// Drop the book and buffer updates, starting with `buffered`, until a new snapshot arrives
+    pub(super) fn resync_from(&mut self, buffered: Vec<DepthUpdate>) {
+        self.orderbook.bids.clear();
+        self.orderbook.asks.clear();
+        self.last_top = None;
+        self.sync = BookSync::AwaitingSnapshot(buffered);
+    }
+
+    pub(super) fn on_update(&mut self, update: DepthUpdate) -> UpdateOutcome {
+        match &mut self.sync {
+            BookSync::AwaitingSnapshot(buffered) => {
+                if buffered.len() >= MAX_BUFFERED_UPDATES {
+                    // Only the updates after the next snapshot matter
+                    buffered.remove(0);
+                }
+                buffered.push(update);
+                UpdateOutcome::Buffered
+            }
+            BookSync::Synced(last) => match check_update(*last, &update) {
+                UpdateCheck::Skip => UpdateOutcome::Skipped,
+                UpdateCheck::Apply => {
+                    self.apply(&update);
+                    UpdateOutcome::Applied
+                }
+                UpdateCheck::Gap => {
+                    self.resync_from(vec![update]);
+                    UpdateOutcome::Resync
+                }
+            },
+        }
+    }
+
+    /// Rebuild the book from `snapshot` and replay the buffered updates it does not contain
+    pub(super) fn on_snapshot(&mut self, snapshot: DepthSnapshot) -> SnapshotOutcome {
+        let BookSync::AwaitingSnapshot(buffered) = &mut self.sync else {
+            return SnapshotOutcome::Ignored;
+        };
+        if buffered
+            .first()
+            .is_some_and(|first| first.first_update_id > snapshot.last_update_id + 1)
+        {
+            return SnapshotOutcome::Stale;
+        }
+        let buffered = std::mem::take(buffered);
+
+        self.orderbook
+            .replace_levels(&snapshot.bids, &snapshot.asks);
+        self.sync = BookSync::Synced(snapshot.last_update_id);
+        let mut replayed = 0;
+        for (index, update) in buffered.iter().enumerate() {
+            let BookSync::Synced(last) = self.sync else {
+                unreachable!("the book is synced while replaying");
+            };
+            match check_update(last, update) {
+                UpdateCheck::Skip => {}
+                UpdateCheck::Apply => {
+                    self.apply(update);
+                    replayed += 1;
+                }
+                UpdateCheck::Gap => {
+                    self.resync_from(buffered[index..].to_vec());
+                    return SnapshotOutcome::Stale;
+                }
+            }
+        }
+        SnapshotOutcome::Synced { replayed }
+    }
+}
@@ -0,0 +1,216 @@
+//! Shared driver of the on-chain DEX screeners.
+//!
+//! Every DEX screener does the same thing on each tick: fetch the accounts of a pair's pools,
+//! quote a sell and a buy against them, keep the best bid and ask and persist them as DEX
+//! market states. Only the account decoding and swap math differ between venues, so a venue
+//! implements [`DexQuoter`] and [`DexScreenerRunner`] owns the rest: trade pair loading and
+//! reloading, the poll loop and its shutdown, persistence and metrics.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora::{
+    BestPriceQuote, DEFAULT_AMOUNT_IN, DiscoveredPools, PoolConfig, TradeConfig, build_dex_states,
+    ensure_quote_fresh, load_trade_configs, refresh_trade_configs, run_poll_loop,
+};
+use crate::store::db::DatabasePool;
+
+/// Quoting core of a DEX venue: decode the pool accounts of a pair and run its swap math
+#[async_trait]
+pub(super) trait DexQuoter: Send + Sync + 'static {
+    /// Venue of the pairs in the trade_pairs table, also used as the DEX market exchange
+    fn venue(&self) -> &'static str;
+
+    /// Sell `amount_in` base token on every pool of `trade_config`, buy it back with the
+    /// quote token received and keep the best bid and best ask. Both directions of a pool are
+    /// quoted against the same snapshot, so they refer to the same slot.
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>>;
+
+    /// Trade configs the screener starts with. Defaults to the enabled pairs of the venue.
+    async fn initial_trade_configs(
+        &self,
+        db_pool: &DatabasePool,
+        discovered: &RwLock<HashMap<String, Vec<PoolConfig>>>,
+    ) -> Result<HashMap<String, TradeConfig>, Box<dyn std::error::Error>> {
+        load_trade_configs(db_pool, self.venue(), discovered).await
+    }
+
+    /// Called with every best quote that passed the freshness check, before it is returned
+    fn on_quote(&self, best: &mut BestPriceQuote) {
+        info!(
+            "[{}] {} best bid={:.6} ({}) best ask={:.6} ({}) across {} pools",
+            self.venue(),
+            best.symbol,
+            best.bid.bid_price,
+            best.bid.pool,
+            best.ask.ask_price,
+            best.ask.pool,
+            best.pools_quoted,
+        );
+    }
+
+    /// Persist what the venue records next to the DEX market states of a best quote
+    fn save_quote_details(
+        &self,
+        _db_pool: &DatabasePool,
+        _quote: &BestPriceQuote,
+        _fetch_time: DateTime<Utc>,
+    ) {
+    }
+}
+
+/// Quote `symbol` on its configured pools and return the best bid and ask, rejecting it
+/// when older than `max_quote_age`
+pub(super) async fn get_best_price<Q: DexQuoter + ?Sized>(
+    quoter: &Q,
+    trade_pairs: &RwLock<HashMap<String, TradeConfig>>,
+    symbol: &str,
+    amount_in: u64,
+    max_quote_age: Duration,
+) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+    let trade_config = trade_pairs
+        .read()
+        .unwrap()
+        .get(symbol)
+        .cloned()
+        .ok_or("Trade config not found")?;
+    let mut best = quoter
+        .quote_exact_in(symbol, &trade_config, amount_in)
+        .await?;
+    ensure_quote_fresh(&best, max_quote_age, Utc::now())?;
+    quoter.on_quote(&mut best);
+    Ok(best)
+}
+
+/// Persist the best bid and best ask of a pair as DEX market states through `dex_writer`,
+/// along with whatever the venue records next to them
+pub(super) fn save_best_price<Q: DexQuoter + ?Sized>(
+    quoter: &Q,
+    db_pool: &DatabasePool,
+    dex_writer: &DexMarketWriter,
+    quote: &BestPriceQuote,
+) {
+    let fetch_time = Utc::now();
+    let dex_states = build_dex_states(quote, quoter.venue(), fetch_time);
+    for dex_state in &dex_states {
+        dex_state.log();
+    }
+    dex_writer.submit(dex_states);
+    quoter.save_quote_details(db_pool, quote, fetch_time);
+}
+
+/// Polls every enabled pair of a venue through its [`DexQuoter`] until `shutdown` is
+/// cancelled. Built by a screener's `start` from its own settings, sharing its trade pairs.
+pub(super) struct DexScreenerRunner<Q: DexQuoter> {
+    pub(super) quoter: Arc<Q>,
+    pub(super) db_pool: DatabasePool,
+    /// Writer of the DEX market states, flushed when the polling loop ends
+    pub(super) dex_writer: Arc<DexMarketWriter>,
+    /// Cancelled by the screener's `stop()` to end the polling loop
+    pub(super) shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub(super) poll_interval: Duration,
+    /// Delay between two reloads of the trade pairs table
+    pub(super) pairs_refresh_interval: Duration,
+    /// Number of pairs quoted concurrently within a tick
+    pub(super) max_concurrent_pairs: usize,
+    /// Age above which a best quote is rejected instead of being persisted
+    pub(super) max_quote_age: Duration,
+    /// Trade configs keyed by symbol, replaced on every reload
+    pub(super) trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    /// Last known pools of auto-discovered pairs, merged into every reload
+    pub(super) discovered_pools: DiscoveredPools,
+}
+
+impl<Q: DexQuoter> DexScreenerRunner<Q> {
+    /// Load the trade pairs, then poll them until shutdown
+    pub(super) async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.load_trade_pairs().await?;
+        self.poll().await;
+        Ok(())
+    }
+
+    /// Replace the trade pairs with the quoter's initial trade configs
+    pub(super) async fn load_trade_pairs(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let venue = self.quoter.venue();
+        let trade_configs = self
+            .quoter
+            .initial_trade_configs(&self.db_pool, &self.discovered_pools)
+            .await?;
+        if trade_configs.is_empty() {
+            warn!("No enabled {} trade pairs found", venue);
+        }
+        info!("Loaded {} {} trade pairs", trade_configs.len(), venue);
+        *self.trade_pairs.write().unwrap() = trade_configs;
+        Ok(())
+    }
+
+    /// Quote and persist every pair on each tick until shutdown, reloading the trade pairs
+    /// table in the background meanwhile, then flush the DEX market states
+    pub(super) async fn poll(&self) {
+        let refresher = tokio::spawn(refresh_trade_configs(
+            self.db_pool.clone(),
+            self.quoter.venue(),
+            self.trade_pairs.clone(),
+            self.discovered_pools.clone(),
+            self.shutdown.clone(),
+            self.pairs_refresh_interval,
+        ));
+
+        run_poll_loop(
+            &self.shutdown,
+            self.poll_interval,
+            self.max_concurrent_pairs,
+            || self.trade_pairs.read().unwrap().keys().cloned().collect(),
+            |symbol| {
+                let quoter = self.quoter.clone();
+                let db_pool = self.db_pool.clone();
+                let dex_writer = self.dex_writer.clone();
+                let trade_pairs = self.trade_pairs.clone();
+                let max_quote_age = self.max_quote_age;
+                async move {
+                    let started = Instant::now();
+                    let result = get_best_price(
+                        quoter.as_ref(),
+                        &trade_pairs,
+                        &symbol,
+                        DEFAULT_AMOUNT_IN,
+                        max_quote_age,
+                    )
+                    .await
+                    .map_err(|e| e.to_string());
+                    record_quote(quoter.venue(), started.elapsed(), result.is_ok());
+                    save_best_price(quoter.as_ref(), &db_pool, &dex_writer, &result?);
+                    Ok(())
+                }
+            },
+        )
+        .await;
+
+        refresher.abort();
+        self.dex_writer.flush().await;
+    }
+}
+
+/// Record the duration and outcome of one pair quote of `venue`
+fn record_quote(venue: &'static str, duration: Duration, success: bool) {
+    let status = if success { "ok" } else { "error" };
+    metrics::histogram!("dex_quote_duration_seconds", "venue" => venue)
+        .record(duration.as_secs_f64());
+    metrics::counter!("dex_quotes_total", "venue" => venue, "status" => status).increment(1);
+}
+
+#[cfg(test)]
+#[path = "dex_runner_tests.rs"]
+mod dex_runner_tests;
@@ -0,0 +1,253 @@
+use super::*;
+use crate::screeners::meteora::{PoolLiquidity, PriceQuote, SwapQuote};
+use crate::store::db::lazy_pool;
+use crate::store::markets::{DexMarketFilter, get_dex_markets};
+use crate::store::test_db::test_pool;
+use rust_decimal::Decimal;
+use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Quoter answering every symbol with the same best price fetched at `fetched_at`
+struct FakeQuoter {
+    fetched_at: DateTime<Utc>,
+    quotes: AtomicUsize,
+    handled: AtomicUsize,
+}
+
+impl FakeQuoter {
+    fn fetched_at(fetched_at: DateTime<Utc>) -> Self {
+        Self {
+            fetched_at,
+            quotes: AtomicUsize::new(0),
+            handled: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl DexQuoter for FakeQuoter {
+    fn venue(&self) -> &'static str {
+        "fake_dex"
+    }
+
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        _trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        self.quotes.fetch_add(1, Ordering::SeqCst);
+        let quote = price_quote(symbol, amount_in, self.fetched_at);
+        Ok(BestPriceQuote {
+            symbol: symbol.to_string(),
+            bid: quote.clone(),
+            ask: quote,
+            pools_quoted: 1,
+            degraded: false,
+        })
+    }
+
+    async fn initial_trade_configs(
+        &self,
+        _db_pool: &DatabasePool,
+        _discovered: &RwLock<HashMap<String, Vec<PoolConfig>>>,
+    ) -> Result<HashMap<String, TradeConfig>, Box<dyn std::error::Error>> {
+        Ok(trade_pairs("TRUMPUSDC"))
+    }
+
+    fn on_quote(&self, best: &mut BestPriceQuote) {
+        self.handled.fetch_add(1, Ordering::SeqCst);
+        best.degraded = true;
+    }
+}
+
+/// Quote selling `amount_in` base atoms at 9.9 and buying back at 10.1, both at 6 decimals
+fn price_quote(symbol: &str, amount_in: u64, fetched_at: DateTime<Utc>) -> PriceQuote {
+    let sell = SwapQuote::without_transfer_fees(amount_in, amount_in * 99 / 10, 0);
+    let buy = SwapQuote::without_transfer_fees(sell.amount_out, amount_in * 98 / 100, 0);
+    PriceQuote {
+        symbol: symbol.to_string(),
+        pool: Pubkey::new_unique(),
+        route: None,
+        hops: Vec::new(),
+        slot: 321_000_123,
+        block_time: fetched_at,
+        fetched_at,
+        fetch_latency: Duration::from_millis(120),
+        commitment: CommitmentLevel::Confirmed,
+        stale: false,
+        missing_bin_arrays: 0,
+        base_decimals: 6,
+        quote_decimals: 6,
+        sell,
+        buy,
+        bid_price: Decimal::from_str("9.9").unwrap(),
+        ask_price: Decimal::from_str("10.1").unwrap(),
+        spot_price: Some(Decimal::TEN),
+        bid_impact_bps: None,
+        ask_impact_bps: None,
+        sell_fee_pct: Decimal::ZERO,
+        buy_fee_pct: Decimal::ZERO,
+        fee_rate: None,
+        liquidity: PoolLiquidity::new(0, 0, 6, 6, None),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+fn trade_pairs(symbol: &str) -> HashMap<String, TradeConfig> {
+    let trade_config = TradeConfig {
+        pools: Vec::new(),
+        routes: Vec::new(),
+    };
+    HashMap::from([(symbol.to_string(), trade_config)])
+}
+
+fn build_runner(quoter: FakeQuoter) -> DexScreenerRunner<FakeQuoter> {
+    DexScreenerRunner {
+        quoter: Arc::new(quoter),
+        db_pool: lazy_pool(),
+        dex_writer: Arc::new(DexMarketWriter::for_database(lazy_pool(), "fake_dex")),
+        shutdown: CancellationToken::new(),
+        poll_interval: Duration::from_millis(1),
+        pairs_refresh_interval: Duration::from_secs(60),
+        max_concurrent_pairs: 4,
+        max_quote_age: Duration::from_secs(5),
+        trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+        discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_best_price_fails_for_unknown_symbol() {
+    let quoter = FakeQuoter::fetched_at(Utc::now());
+    let trade_pairs = RwLock::new(trade_pairs("TRUMPUSDC"));
+
+    let result = get_best_price(
+        &quoter,
+        &trade_pairs,
+        "UNKNOWN",
+        DEFAULT_AMOUNT_IN,
+        Duration::from_secs(5),
+    )
+    .await;
+
+    assert_eq!(result.unwrap_err().to_string(), "Trade config not found");
+    assert_eq!(quoter.quotes.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_best_price_hands_fresh_quotes_to_the_quoter() {
+    let quoter = FakeQuoter::fetched_at(Utc::now());
+    let trade_pairs = RwLock::new(trade_pairs("TRUMPUSDC"));
+
+    let best = get_best_price(
+        &quoter,
+        &trade_pairs,
+        "TRUMPUSDC",
+        DEFAULT_AMOUNT_IN,
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(best.symbol, "TRUMPUSDC");
+    assert_eq!(best.bid.sell.amount_in, DEFAULT_AMOUNT_IN);
+    assert!(best.degraded);
+    assert_eq!(quoter.handled.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_best_price_rejects_stale_quotes_before_the_quoter_sees_them() {
+    let quoter = FakeQuoter::fetched_at(Utc::now() - chrono::Duration::seconds(10));
+    let trade_pairs = RwLock::new(trade_pairs("TRUMPUSDC"));
+
+    let result = get_best_price(
+        &quoter,
+        &trade_pairs,
+        "TRUMPUSDC",
+        DEFAULT_AMOUNT_IN,
+        Duration::from_secs(5),
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(quoter.quotes.load(Ordering::SeqCst), 1);
+    assert_eq!(quoter.handled.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn run_polls_the_initial_trade_pairs_until_shutdown() {
+    let runner = Arc::new(build_runner(FakeQuoter::fetched_at(Utc::now())));
+
+    let running = tokio::spawn({
+        let runner = runner.clone();
+        async move { runner.run().await.map_err(|e| e.to_string()) }
+    });
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while runner.quoter.handled.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .expect("the runner never quoted twice");
+    runner.shutdown.cancel();
+
+    let finished = tokio::time::timeout(Duration::from_secs(1), running).await;
+
+    assert_eq!(finished.unwrap().unwrap(), Ok(()));
+    assert!(runner.trade_pairs.read().unwrap().contains_key("TRUMPUSDC"));
+}
+
+#[tokio::test]
+async fn run_writes_the_best_bid_and_ask_to_the_database() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let started = Utc::now();
+    let mut runner = build_runner(FakeQuoter::fetched_at(started));
+    runner.db_pool = pool.clone();
+    runner.dex_writer = Arc::new(DexMarketWriter::for_database(pool.clone(), "fake_dex"));
+    let runner = Arc::new(runner);
+
+    let running = tokio::spawn({
+        let runner = runner.clone();
+        async move { runner.run().await.map_err(|e| e.to_string()) }
+    });
+    let filter = DexMarketFilter {
+        exchange: Some("fake_dex".to_string()),
+        trade_pair: Some("TRUMPUSDC".to_string()),
+        from: Some(started),
+        ..DexMarketFilter::default()
+    };
+    let written = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let written = get_dex_markets(&pool, &filter).await.unwrap();
+            if written.iter().any(|state| state.direction == "sell")
+                && written.iter().any(|state| state.direction == "buy")
+            {
+                return written;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the runner never wrote both sides of a quote");
+    runner.shutdown.cancel();
+    running.await.unwrap().unwrap();
+
+    let sell = written
+        .iter()
+        .find(|state| state.direction == "sell")
+        .unwrap();
+    let buy = written
+        .iter()
+        .find(|state| state.direction == "buy")
+        .unwrap();
+    assert_eq!(sell.price, Decimal::from_str("9.9").unwrap());
+    assert_eq!(buy.price, Decimal::from_str("10.1").unwrap());
+    assert_eq!(sell.block_number, 321_000_123);
+    assert_eq!(sell.fetch_latency_ms, Some(120));
+}
@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+use crate::models::market::DEXState;
+use crate::store::db::DatabasePool;
+use crate::store::write_buffer::{StateSink, WriteBuffer, WriteBufferConfig};
+
+/// Batches queued for the writer task when `DEX_WRITE_QUEUE_CAPACITY` is unset
+const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Work for the writer task, handled in submission order
+#[derive(Debug)]
+enum WriterMessage {
+    States(Vec<DEXState>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Queue of DEX market states written in submission order by a single writer task, through a
+/// `WriteBuffer` that keeps the states the database fails to store and retries them
+pub struct DexMarketWriter {
+    messages: mpsc::Sender<WriterMessage>,
+    dropped: AtomicU64,
+}
+
+impl DexMarketWriter {
+    /// Spawn the writer task on `buffer`. It stops once the writer is dropped, leaving the
+    /// states still buffered to the buffer's spill file.
+    pub fn spawn<S: StateSink<DEXState>>(
+        buffer: WriteBuffer<DEXState, S>,
+        queue_capacity: usize,
+    ) -> Self {
+        let (messages, messages_rx) = mpsc::channel(queue_capacity);
+        tokio::spawn(run_writer(buffer, messages_rx));
+
+        Self {
+            messages,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Spawn the writer of a venue on the database, behind a `WriteBuffer` configured from the
+    /// environment like the CEX ones, with `DEX_WRITE_QUEUE_CAPACITY` batches queued
+    pub fn for_database(db_pool: DatabasePool, venue: &str) -> Self {
+        let queue_capacity = std::env::var("DEX_WRITE_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|capacity| *capacity > 0)
+            .unwrap_or(DEFAULT_QUEUE_CAPACITY);
+        let buffer = WriteBuffer::spawn(db_pool, WriteBufferConfig::from_env(venue));
+        Self::spawn(buffer, queue_capacity)
+    }
+
+    /// Queue the states of one quote for writing without waiting. They are dropped, and
+    /// counted, when the queue is full.
+    pub fn submit(&self, states: Vec<DEXState>) {
+        if states.is_empty() {
+            return;
+        }
+        match self.messages.try_send(WriterMessage::States(states)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(WriterMessage::States(states))) => {
+                warn!(
+                    "DEX market writer queue is full, dropping {} states",
+                    states.len()
+                );
+                self.count_drops(&states);
+            }
+            Err(TrySendError::Closed(WriterMessage::States(states))) => {
+                warn!(
+                    "DEX market writer stopped, dropping {} states",
+                    states.len()
+                );
+                self.count_drops(&states);
+            }
+            Err(_) => unreachable!("only states are submitted"),
+        }
+    }
+
+    /// Hand every state submitted so far to the write buffer, which has written them or keeps
+    /// them for a retry when this returns
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.messages.send(WriterMessage::Flush(done)).await.is_ok() {
+            let _ = flushed.await;
+        }
+    }
+
+    /// States dropped because the queue was full or the writer stopped
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn count_drops(&self, states: &[DEXState]) {
+        self.dropped
+            .fetch_add(states.len() as u64, Ordering::Relaxed);
+        for state in states {
+            let trade_pair = state.trade_pair.clone();
+            metrics::counter!("dex_market_states_dropped_total", "trade_pair" => trade_pair)
+                .increment(1);
+        }
+    }
+}
+
+/// Write the submitted states in order until every sender is gone
+async fn run_writer<S: StateSink<DEXState>>(
+    buffer: WriteBuffer<DEXState, S>,
+    mut messages: mpsc::Receiver<WriterMessage>,
+) {
+    while let Some(message) = messages.recv().await {
+        match message {
+            WriterMessage::States(states) => buffer.write(&states).await,
+            WriterMessage::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "dex_writer_tests.rs"]
+mod dex_writer_tests;
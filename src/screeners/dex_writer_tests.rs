@@ -0,0 +1,120 @@
+use super::*;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::store::write_buffer::DropPolicy;
+
+/// Fake sink recording every written batch, failing the writes while `failing` is set
+#[derive(Clone, Default)]
+struct MockSink {
+    batches: Arc<Mutex<Vec<Vec<DEXState>>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl MockSink {
+    /// `trade_id` of every written state, in write order
+    fn written(&self) -> Vec<String> {
+        self.batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(|state| state.trade_id.clone())
+            .collect()
+    }
+}
+
+impl StateSink<DEXState> for MockSink {
+    async fn write_batch(&self, states: &[DEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("database unavailable".into());
+        }
+        self.batches.lock().unwrap().push(states.to_vec());
+        Ok(())
+    }
+}
+
+fn state(trade_id: &str, direction: &str) -> DEXState {
+    DEXState {
+        trade_id: trade_id.to_string(),
+        exchange: "meteora".to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        direction: direction.to_string(),
+        price: Decimal::TEN,
+        volume: Decimal::ONE,
+        trade_time: Utc::now(),
+        fetch_time: Utc::now(),
+        block_number: 321_000_000,
+        price_impact_bps: None,
+        pool_address: None,
+        stale: false,
+        fetch_latency_ms: None,
+        route: None,
+        route_plan: None,
+    }
+}
+
+/// Writer on a write buffer over `sink`, retrying every second
+fn spawn_writer(sink: &MockSink, queue_capacity: usize) -> DexMarketWriter {
+    let buffer = WriteBuffer::spawn(
+        sink.clone(),
+        WriteBufferConfig {
+            name: "test".to_string(),
+            capacity: 16,
+            spill_path: None,
+            drop_policy: DropPolicy::Oldest,
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(1),
+        },
+    );
+    DexMarketWriter::spawn(buffer, queue_capacity)
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn flush_writes_the_submitted_states_in_order() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 16);
+
+    writer.submit(vec![state("1", "sell"), state("1", "buy")]);
+    writer.submit(vec![state("2", "sell"), state("2", "buy")]);
+    writer.flush().await;
+
+    assert_eq!(sink.written(), ["1", "1", "2", "2"]);
+    assert_eq!(writer.dropped(), 0);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn failed_writes_are_retried_in_order_once_the_sink_recovers() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 16);
+
+    sink.failing.store(true, Ordering::SeqCst);
+    writer.submit(vec![state("1", "sell"), state("1", "buy")]);
+    writer.flush().await;
+    assert!(sink.written().is_empty());
+    sink.failing.store(false, Ordering::SeqCst);
+    // Queued behind the buffered states even though the sink is back
+    writer.submit(vec![state("2", "sell")]);
+    writer.flush().await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert_eq!(sink.written(), ["1", "1", "2"]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_queue_drops_and_counts_the_states() {
+    let sink = MockSink::default();
+    let writer = spawn_writer(&sink, 1);
+
+    // The writer task does not run before the test yields, so the second batch finds the
+    // queue full
+    writer.submit(vec![state("1", "sell")]);
+    writer.submit(vec![state("2", "sell"), state("2", "buy")]);
+    writer.flush().await;
+
+    assert_eq!(sink.written(), ["1"]);
+    assert_eq!(writer.dropped(), 2);
+}
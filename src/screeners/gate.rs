@@ -0,0 +1,492 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::depth_sync::{
+    BookSync, DepthSnapshot, DepthUpdate, SnapshotOutcome, SymbolBook, UpdateOutcome, parse_levels,
+    parse_update_id,
+};
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "gate";
+/// Symbols streamed when `GATE_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDT";
+/// Spot websocket URL when `GATE_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://api.gateio.ws/ws/v4/";
+/// REST base URL when `GATE_REST_URL` is unset
+const DEFAULT_REST_URL: &str = "https://api.gateio.ws/api/v4";
+/// Levels per side of the REST depth snapshot when `GATE_SNAPSHOT_LIMIT` is unset
+const DEFAULT_SNAPSHOT_LIMIT: u32 = 100;
+/// Channel of the incremental order book updates
+const CHANNEL: &str = "spot.order_book_update";
+/// Push interval of the incremental updates
+const UPDATE_INTERVAL: &str = "100ms";
+/// Timeout of a REST depth snapshot request
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a snapshot older than the buffered updates is fetched again
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Interval of the `spot.ping` requests keeping the connection alive
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+/// Silence after which the connection is considered dead
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Symbols and endpoints of the Gate.io screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct GateConfig {
+    /// Internal symbols such as `TRUMPUSDT`, streamed as `TRUMP_USDT`
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+    pub rest_url: String,
+    /// Levels per side of the REST depth snapshots
+    pub snapshot_limit: u32,
+}
+
+impl GateConfig {
+    /// Read `GATE_SYMBOLS` (comma-separated), `GATE_WS_URL`, `GATE_REST_URL` and
+    /// `GATE_SNAPSHOT_LIMIT`, falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols = std::env::var("GATE_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        let snapshot_limit = match std::env::var("GATE_SNAPSHOT_LIMIT") {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|limit| *limit > 0)
+                .ok_or_else(|| {
+                    format!("GATE_SNAPSHOT_LIMIT `{}` is not a positive number", value)
+                })?,
+            Err(_) => DEFAULT_SNAPSHOT_LIMIT,
+        };
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("GATE_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            rest_url: std::env::var("GATE_REST_URL")
+                .unwrap_or_else(|_| DEFAULT_REST_URL.to_string()),
+            snapshot_limit,
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to a Gate currency pair
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "GATE_SYMBOLS entry `{}` has no Gate currency pair (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("GATE_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// `spot.order_book_update` subscription of one symbol; Gate takes a single pair per request
+fn subscribe_request(symbol: &str, time: i64) -> String {
+    let pair = to_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.to_string());
+    json!({
+        "time": time,
+        "channel": CHANNEL,
+        "event": "subscribe",
+        "payload": [pair, UPDATE_INTERVAL],
+    })
+    .to_string()
+}
+
+/// Application level ping, answered with `spot.pong`
+fn ping_request(time: i64) -> String {
+    json!({"time": time, "channel": "spot.ping"}).to_string()
+}
+
+/// Decoded websocket message
+#[derive(Debug, PartialEq)]
+enum GateFrame {
+    Update(DepthUpdate),
+    /// Error reply to a request
+    Error(String),
+    /// Subscription replies, pongs and other channels
+    Other,
+}
+
+/// Decode a websocket message, with the pair of updates mapped to its internal symbol
+fn parse_frame(text: &str) -> Result<GateFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if !frame["error"].is_null() {
+        return Ok(GateFrame::Error(frame["error"].to_string()));
+    }
+    if frame["channel"] != CHANNEL || frame["event"] != "update" {
+        return Ok(GateFrame::Other);
+    }
+    let result = &frame["result"];
+    let pair = result["s"].as_str().ok_or("missing pair `s`")?;
+    Ok(GateFrame::Update(DepthUpdate {
+        symbol: from_venue_symbol(EXCHANGE, pair)
+            .ok_or_else(|| format!("unexpected pair `{}`", pair))?,
+        event_ms: result["t"].as_i64().ok_or("missing update time `t`")?,
+        first_update_id: parse_update_id(result, "U")?,
+        last_update_id: parse_update_id(result, "u")?,
+        bids: parse_levels(&result["b"])?,
+        asks: parse_levels(&result["a"])?,
+    }))
+}
+
+/// Decode a `/spot/order_book` reply requested `with_id`
+fn parse_depth_snapshot(body: &Value) -> Result<DepthSnapshot, String> {
+    Ok(DepthSnapshot {
+        last_update_id: parse_update_id(body, "id")?,
+        bids: parse_levels(&body["bids"])?,
+        asks: parse_levels(&body["asks"])?,
+    })
+}
+
+/// Fetch the baseline depth snapshot of `symbol`
+async fn fetch_depth_snapshot(
+    http: &reqwest::Client,
+    rest_url: &str,
+    symbol: &str,
+    limit: u32,
+) -> Result<DepthSnapshot, String> {
+    let pair =
+        to_venue_symbol(EXCHANGE, symbol).ok_or_else(|| format!("no Gate pair for {}", symbol))?;
+    let url = format!(
+        "{}/spot/order_book?currency_pair={}&limit={}&with_id=true",
+        rest_url.trim_end_matches('/'),
+        pair,
+        limit
+    );
+    let response = http.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!(
+            "HTTP {}: {} {}",
+            status, body["label"], body["message"]
+        ));
+    }
+    parse_depth_snapshot(&body)
+}
+
+/// Fetches of REST depth snapshots in flight, with their symbol
+type SnapshotFetches = JoinSet<(String, Result<DepthSnapshot, String>)>;
+
+/// Gate.io spot screener keeping a local book per symbol from the incremental order book
+/// channel and REST baseline snapshots, persisted as CEX market states
+pub struct GateScreener {
+    config: GateConfig,
+    shutdown: CancellationToken,
+    http: reqwest::Client,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl GateScreener {
+    /// Create a new GateScreener instance on the symbols of `GATE_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, GateConfig::from_env()?)
+    }
+
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: GateConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder().timeout(REST_TIMEOUT).build()?;
+        let cex_writer = CexMarketWriter::for_database(db_pool, "gate");
+        Ok(Self {
+            config,
+            shutdown: CancellationToken::new(),
+            http,
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Gate", EXCHANGE, STALE_FEED_TIMEOUT),
+        })
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from a new snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Gate screener for {:?} ({})...",
+            self.config.symbols, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Gate screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(EXCHANGE, symbol)))
+            .collect();
+    }
+
+    /// Fetch the snapshot of `symbol` after `delay`
+    fn spawn_snapshot_fetch(&self, fetches: &mut SnapshotFetches, symbol: &str, delay: Duration) {
+        let http = self.http.clone();
+        let rest_url = self.config.rest_url.clone();
+        let limit = self.config.snapshot_limit;
+        let symbol = symbol.to_string();
+        fetches.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let snapshot = fetch_depth_snapshot(&http, &rest_url, &symbol, limit).await;
+            (symbol, snapshot)
+        });
+    }
+
+    /// Apply one text frame; returns whether it was an order book update
+    fn handle_frame(&self, fetches: &mut SnapshotFetches, text: &str) -> bool {
+        let update = match parse_frame(text) {
+            Ok(GateFrame::Update(update)) => update,
+            Ok(GateFrame::Error(detail)) => {
+                error!("Gate websocket error: {}", detail);
+                return false;
+            }
+            Ok(GateFrame::Other) => {
+                debug!("Skipping Gate frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed Gate frame: {}", e);
+                return false;
+            }
+        };
+        let (symbol, update_id, event_ms) = (
+            update.symbol.clone(),
+            update.last_update_id,
+            update.event_ms,
+        );
+
+        let mut books = self.books.lock().unwrap();
+        // Updates of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&symbol) else {
+            return true;
+        };
+        match book.on_update(update) {
+            UpdateOutcome::Applied => self.persist(book, update_id, event_ms, fetches),
+            UpdateOutcome::Resync => {
+                warn!(
+                    "Gate {} order book missed updates before {}, fetching a new snapshot",
+                    symbol, update_id
+                );
+                metrics::counter!("gate_orderbook_gaps_total", "symbol" => symbol.clone())
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            }
+            UpdateOutcome::Buffered | UpdateOutcome::Skipped => {}
+        }
+        true
+    }
+
+    /// Rebuild a book from its snapshot, fetching it again when it is too old or failed
+    fn handle_snapshot(
+        &self,
+        fetches: &mut SnapshotFetches,
+        symbol: &str,
+        snapshot: Result<DepthSnapshot, String>,
+    ) {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("Gate {} depth snapshot failed, retrying: {}", symbol, e);
+                metrics::counter!("gate_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+                return;
+            }
+        };
+        let update_id = snapshot.last_update_id;
+
+        let mut books = self.books.lock().unwrap();
+        let Some(book) = books.get_mut(symbol) else {
+            return;
+        };
+        match book.on_snapshot(snapshot) {
+            SnapshotOutcome::Synced { replayed } => {
+                info!(
+                    "Gate {} order book synced from a snapshot at {}, replayed {} updates",
+                    symbol, update_id, replayed
+                );
+                metrics::counter!("gate_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "ok")
+                    .increment(1);
+                let BookSync::Synced(last) = book.sync else {
+                    return;
+                };
+                self.persist(book, last, Utc::now().timestamp_millis(), fetches);
+            }
+            SnapshotOutcome::Stale => {
+                debug!(
+                    "Gate {} depth snapshot at {} is older than the buffered updates, retrying",
+                    symbol, update_id
+                );
+                metrics::counter!("gate_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "stale")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+            }
+            SnapshotOutcome::Ignored => {}
+        }
+    }
+
+    /// Persist the top of book when it changed. A book that cannot describe a real market is
+    /// dropped and rebuilt from a new snapshot instead.
+    fn persist(
+        &self,
+        book: &mut SymbolBook,
+        update_id: u64,
+        event_ms: i64,
+        fetches: &mut SnapshotFetches,
+    ) {
+        let symbol = book.orderbook.symbol.clone();
+        if let Err(violation) = book.orderbook.validate() {
+            error!(
+                "Gate {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "gate_invalid_books_total",
+                "symbol" => symbol.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            book.resync_from(Vec::new());
+            self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            return;
+        }
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: update_id.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol,
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(event_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - event_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for GateScreener {
+    /// Empty the books, connect, subscribe every symbol, fetch their baseline snapshots and apply
+    /// the updates until the connection drops or the screener stops. `feed` counts the updates
+    /// delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        for symbol in &self.config.symbols {
+            ws.send(Message::Text(subscribe_request(
+                symbol,
+                Utc::now().timestamp(),
+            )))
+            .await
+            .map_err(|e| format!("subscribe failed: {}", e))?;
+        }
+        info!(
+            "Gate websocket connected, subscribed to {:?}",
+            self.config.symbols
+        );
+
+        // Updates are buffered from the subscription on, so the snapshots are fetched after it
+        let mut fetches = SnapshotFetches::new();
+        for symbol in &self.config.symbols {
+            self.spawn_snapshot_fetch(&mut fetches, symbol, Duration::ZERO);
+        }
+        let mut ping =
+            tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                _ = ping.tick() => {
+                    ws.send(Message::Text(ping_request(Utc::now().timestamp())))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                Some(fetched) = fetches.join_next(), if !fetches.is_empty() => {
+                    if let Ok((symbol, snapshot)) = fetched {
+                        self.handle_snapshot(&mut fetches, &symbol, snapshot);
+                    }
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            if self.handle_frame(&mut fetches, &text) {
+                                feed.delivered();
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "gate_tests.rs"]
+mod gate_tests;
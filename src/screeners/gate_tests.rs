@@ -0,0 +1,261 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// `spot.order_book_update` messages recorded in order for TRUMP_USDT: one already in the
+/// snapshot, one straddling it, two following it, then a gap
+const UPDATE_FIXTURES: [&str; 5] = [
+    r#"{"time":1716863719,"time_ms":1716863719050,"channel":"spot.order_book_update","event":"update","result":{"t":1716863719050,"e":"depthUpdate","E":1716863719,"s":"TRUMP_USDT","U":1027010,"u":1027019,"b":[["10.21","4"]],"a":[]}}"#,
+    r#"{"time":1716863719,"time_ms":1716863719100,"channel":"spot.order_book_update","event":"update","result":{"t":1716863719100,"e":"depthUpdate","E":1716863719,"s":"TRUMP_USDT","U":1027020,"u":1027025,"b":[["10.24","0"],["10.25","1.5"]],"a":[["10.27","3"]]}}"#,
+    r#"{"time":1716863719,"time_ms":1716863719200,"channel":"spot.order_book_update","event":"update","result":{"t":1716863719200,"e":"depthUpdate","E":1716863719,"s":"TRUMP_USDT","U":1027026,"u":1027028,"b":[["10.25","2"]],"a":[]}}"#,
+    r#"{"time":1716863719,"time_ms":1716863719300,"channel":"spot.order_book_update","event":"update","result":{"t":1716863719300,"e":"depthUpdate","E":1716863719,"s":"TRUMP_USDT","U":1027029,"u":1027029,"b":[],"a":[["10.28","0"],["10.26","1"]]}}"#,
+    r#"{"time":1716863719,"time_ms":1716863719400,"channel":"spot.order_book_update","event":"update","result":{"t":1716863719400,"e":"depthUpdate","E":1716863719,"s":"TRUMP_USDT","U":1027035,"u":1027036,"b":[["10.2","9"]],"a":[]}}"#,
+];
+
+/// `/spot/order_book?with_id=true` reply recorded for TRUMP_USDT
+const SNAPSHOT_FIXTURE: &str = r#"{"id":1027024,"current":1716863719120,"update":1716863719110,"asks":[["10.27","4"],["10.28","5"]],"bids":[["10.24","2"],["10.23","3"]]}"#;
+
+fn update(index: usize) -> DepthUpdate {
+    match parse_frame(UPDATE_FIXTURES[index]).unwrap() {
+        GateFrame::Update(update) => update,
+        other => panic!("not an order book update: {:?}", other),
+    }
+}
+
+fn snapshot_at(last_update_id: u64) -> DepthSnapshot {
+    DepthSnapshot {
+        last_update_id,
+        ..parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap()).unwrap()
+    }
+}
+
+fn prices(levels: &market::OrderBookLevels) -> Vec<Decimal> {
+    levels.keys().copied().collect()
+}
+
+impl TestScreener for GateScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = GateScreener {
+            config: GateConfig {
+                symbols: vec!["TRUMPUSDT".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+                rest_url: "http://127.0.0.1:1".to_string(),
+                snapshot_limit: 100,
+            },
+            shutdown: CancellationToken::new(),
+            http: reqwest::Client::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Gate", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn updates_are_decoded_with_internal_symbols() {
+    assert_eq!(
+        update(1),
+        DepthUpdate {
+            symbol: "TRUMPUSDT".to_string(),
+            event_ms: 1716863719100,
+            first_update_id: 1027020,
+            last_update_id: 1027025,
+            bids: vec![
+                (decimal("10.24"), Decimal::ZERO),
+                (decimal("10.25"), decimal("1.5"))
+            ],
+            asks: vec![(decimal("10.27"), decimal("3"))],
+        }
+    );
+    assert_eq!(
+        parse_frame(
+            r#"{"time":1716863718,"time_ms":1716863718900,"channel":"spot.order_book_update","event":"subscribe","result":{"status":"success"}}"#
+        ),
+        Ok(GateFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(
+            r#"{"time":1716863718,"time_ms":1716863718900,"channel":"spot.pong","event":"","result":null}"#
+        ),
+        Ok(GateFrame::Other)
+    );
+    assert!(matches!(
+        parse_frame(
+            r#"{"time":1716863718,"channel":"spot.order_book_update","event":"subscribe","error":{"code":2,"message":"unknown currency pair NOPE_USDT"},"result":{"status":"fail"}}"#
+        ),
+        Ok(GateFrame::Error(detail)) if detail.contains("unknown currency pair")
+    ));
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace(r#""2""#, r#""two""#)).is_err());
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace(r#""U":1027026,"#, "")).is_err());
+}
+
+#[test]
+fn requests_and_symbols_follow_gate_conventions() {
+    let subscribe: Value =
+        serde_json::from_str(&subscribe_request("TRUMPUSDT", 1716863718)).unwrap();
+    assert_eq!(
+        subscribe,
+        json!({
+            "time": 1716863718,
+            "channel": "spot.order_book_update",
+            "event": "subscribe",
+            "payload": ["TRUMP_USDT", "100ms"]
+        })
+    );
+    let ping: Value = serde_json::from_str(&ping_request(1716863718)).unwrap();
+    assert_eq!(ping, json!({"time": 1716863718, "channel": "spot.ping"}));
+    assert_eq!(
+        parse_symbols(" trumpusdt,TRUMPUSDC,,TRUMPUSDT ").unwrap(),
+        ["TRUMPUSDT", "TRUMPUSDC"]
+    );
+    assert!(parse_symbols("TRUMP").is_err());
+    assert!(parse_symbols(" , ").is_err());
+}
+
+#[test]
+fn baseline_snapshot_syncs_the_buffered_sequence() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDT");
+    for index in 0..3 {
+        assert_eq!(book.on_update(update(index)), UpdateOutcome::Buffered);
+    }
+
+    // The first update is older than the snapshot, the second one straddles its id
+    assert_eq!(
+        book.on_snapshot(snapshot_at(1027024)),
+        SnapshotOutcome::Synced { replayed: 2 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(1027028));
+    assert_eq!(
+        prices(&book.orderbook.bids),
+        [decimal("10.23"), decimal("10.25")]
+    );
+    assert_eq!(book.orderbook.bids[&decimal("10.25")], decimal("2"));
+
+    assert_eq!(book.on_update(update(3)), UpdateOutcome::Applied);
+    assert_eq!(
+        prices(&book.orderbook.asks),
+        [decimal("10.26"), decimal("10.27")]
+    );
+    assert_eq!(book.on_update(update(2)), UpdateOutcome::Skipped);
+}
+
+#[test]
+fn sequence_gap_waits_for_a_fresh_snapshot() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDT");
+    for index in 1..4 {
+        book.on_update(update(index));
+    }
+    book.on_snapshot(snapshot_at(1027024));
+
+    assert_eq!(book.on_update(update(4)), UpdateOutcome::Resync);
+    assert!(book.orderbook.bids.is_empty() && book.orderbook.asks.is_empty());
+    assert_eq!(book.sync, BookSync::AwaitingSnapshot(vec![update(4)]));
+
+    // A snapshot taken before the missed updates cannot bridge the gap
+    assert_eq!(
+        book.on_snapshot(snapshot_at(1027029)),
+        SnapshotOutcome::Stale
+    );
+    assert_eq!(
+        book.on_snapshot(snapshot_at(1027034)),
+        SnapshotOutcome::Synced { replayed: 1 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(1027036));
+    assert_eq!(book.orderbook.bids[&decimal("10.2")], decimal("9"));
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_gate_states() {
+    let (screener, sink) = build_screener_with_sink::<GateScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    assert!(screener.handle_frame(&mut fetches, UPDATE_FIXTURES[1]));
+    let pong = r#"{"time":1716863719,"channel":"spot.pong","event":"","result":null}"#;
+    assert!(!screener.handle_frame(&mut fetches, pong));
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDT", Ok(snapshot_at(1027024)));
+    screener.cex_writer.flush().await;
+    for fixture in &UPDATE_FIXTURES[2..4] {
+        assert!(screener.handle_frame(&mut fetches, fixture));
+        screener.cex_writer.flush().await;
+    }
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "gate" && state.trade_pair == "TRUMPUSDT")
+    );
+    assert_eq!(states[0].trade_id, "1027025");
+    assert_eq!(
+        (states[0].bid_price, states[0].ask_price),
+        (decimal("10.25"), decimal("10.27"))
+    );
+    assert_eq!(states[1].bid_volume, decimal("2"));
+    assert_eq!(
+        (states[2].trade_id.as_str(), states[2].ask_price),
+        ("1027029", decimal("10.26"))
+    );
+    assert_eq!(states[2].trade_time.timestamp_millis(), 1716863719300);
+    assert!(fetches.is_empty());
+}
+
+#[tokio::test]
+async fn sequence_gap_fetches_a_fresh_snapshot() {
+    let (screener, sink) = build_screener_with_sink::<GateScreener>();
+    let mut fetches = SnapshotFetches::new();
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[1]);
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDT", Ok(snapshot_at(1027024)));
+
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[4]);
+
+    assert_eq!(fetches.len(), 1);
+    assert_eq!(
+        screener.books.lock().unwrap()["TRUMPUSDT"].sync,
+        BookSync::AwaitingSnapshot(vec![update(4)])
+    );
+    screener.cex_writer.flush().await;
+    assert_eq!(sink.states.lock().unwrap().len(), 1);
+    fetches.abort_all();
+}
+
+#[tokio::test]
+async fn depth_snapshots_are_fetched_with_their_id() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/spot/order_book"))
+        .and(query_param("currency_pair", "TRUMP_USDT"))
+        .and(query_param("limit", "100"))
+        .and(query_param("with_id", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SNAPSHOT_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/spot/order_book"))
+        .and(query_param("currency_pair", "NOPE_USDT"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            r#"{"label":"INVALID_CURRENCY_PAIR","message":"Invalid currency pair NOPE_USDT"}"#,
+        ))
+        .mount(&server)
+        .await;
+    let http = reqwest::Client::new();
+
+    assert_eq!(
+        fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDT", 100).await,
+        Ok(snapshot_at(1027024))
+    );
+    let error = fetch_depth_snapshot(&http, &server.uri(), "NOPEUSDT", 100)
+        .await
+        .unwrap_err();
+    assert!(error.contains("INVALID_CURRENCY_PAIR"), "{}", error);
+}
@@ -0,0 +1,498 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::depth_sync::{
+    BookSync, DepthSnapshot, DepthUpdate, Levels, SnapshotOutcome, SymbolBook, UpdateOutcome,
+    parse_update_id,
+};
+use super::kraken::parse_number;
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_codec::{embedded_pong, gunzip_text};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "htx";
+/// Symbols streamed when `HTX_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDT";
+/// Market-by-price websocket URL when `HTX_WS_URL` is unset; the mbp channels are only
+/// served on `/feed`
+const DEFAULT_WS_URL: &str = "wss://api.huobi.pro/feed";
+/// REST base URL when `HTX_REST_URL` is unset
+const DEFAULT_REST_URL: &str = "https://api.huobi.pro";
+/// Levels per side of the mbp channel, matching the 150 levels of a `step0` REST snapshot
+const MBP_LEVELS: usize = 150;
+/// Timeout of a REST depth snapshot request
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a snapshot older than the buffered updates is fetched again
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// HTX pings every 5 seconds, so a connection silent this long is dead
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Symbols and endpoints of the HTX screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtxConfig {
+    /// Internal symbols such as `TRUMPUSDT`, streamed as `trumpusdt`
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+    pub rest_url: String,
+}
+
+impl HtxConfig {
+    /// Read `HTX_SYMBOLS` (comma-separated), `HTX_WS_URL` and `HTX_REST_URL`, falling back to
+    /// the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols = std::env::var("HTX_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("HTX_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            rest_url: std::env::var("HTX_REST_URL")
+                .unwrap_or_else(|_| DEFAULT_REST_URL.to_string()),
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to an HTX symbol
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "HTX_SYMBOLS entry `{}` has no HTX symbol (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("HTX_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// Market-by-price incremental channel of an HTX symbol
+fn mbp_channel(htx_symbol: &str) -> String {
+    format!("market.{}.mbp.{}", htx_symbol, MBP_LEVELS)
+}
+
+/// Subscription of the mbp channel of one symbol
+fn subscribe_request(symbol: &str) -> String {
+    let htx_symbol = to_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.to_lowercase());
+    json!({"sub": mbp_channel(&htx_symbol), "id": htx_symbol}).to_string()
+}
+
+/// Decode `[[price, size], ...]` levels sent as JSON numbers; a missing side did not change
+fn parse_levels(value: &Value) -> Result<Levels, String> {
+    if value.is_null() {
+        return Ok(Vec::new());
+    }
+    let levels = value.as_array().ok_or("levels are not an array")?;
+    levels
+        .iter()
+        .map(|level| Ok((parse_number(&level[0])?, parse_number(&level[1])?)))
+        .collect()
+}
+
+/// Decoded websocket message, once decompressed
+#[derive(Debug, PartialEq)]
+enum HtxFrame {
+    Update(DepthUpdate),
+    /// Error reply to a request
+    Error(String),
+    /// Subscription replies and other channels
+    Other,
+}
+
+/// Decode a websocket message, with the symbol of updates mapped to its internal symbol.
+/// An mbp update follows the previous one when its `prevSeqNum` is that one's `seqNum`, so
+/// it covers the update ids from `prevSeqNum` + 1 to `seqNum`.
+fn parse_frame(text: &str) -> Result<HtxFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if frame["status"] == "error" {
+        return Ok(HtxFrame::Error(format!(
+            "{} {}",
+            frame["err-code"].as_str().unwrap_or_default(),
+            frame["err-msg"].as_str().unwrap_or_default()
+        )));
+    }
+    let Some(channel) = frame["ch"].as_str() else {
+        return Ok(HtxFrame::Other);
+    };
+    let Some(htx_symbol) = channel
+        .strip_prefix("market.")
+        .and_then(|rest| rest.strip_suffix(&format!(".mbp.{}", MBP_LEVELS)))
+    else {
+        return Ok(HtxFrame::Other);
+    };
+    let tick = &frame["tick"];
+    Ok(HtxFrame::Update(DepthUpdate {
+        symbol: from_venue_symbol(EXCHANGE, htx_symbol)
+            .ok_or_else(|| format!("unexpected symbol `{}`", htx_symbol))?,
+        event_ms: frame["ts"].as_i64().ok_or("missing `ts`")?,
+        first_update_id: parse_update_id(tick, "prevSeqNum")? + 1,
+        last_update_id: parse_update_id(tick, "seqNum")?,
+        bids: parse_levels(&tick["bids"])?,
+        asks: parse_levels(&tick["asks"])?,
+    }))
+}
+
+/// Decode a `/market/depth` reply. The `version` of a `step0` snapshot is the `seqNum` of
+/// the mbp stream it was taken at.
+fn parse_depth_snapshot(body: &Value) -> Result<DepthSnapshot, String> {
+    if body["status"] != "ok" {
+        return Err(format!(
+            "{} {}",
+            body["err-code"].as_str().unwrap_or_default(),
+            body["err-msg"].as_str().unwrap_or_default()
+        ));
+    }
+    let tick = &body["tick"];
+    Ok(DepthSnapshot {
+        last_update_id: parse_update_id(tick, "version")?,
+        bids: parse_levels(&tick["bids"])?,
+        asks: parse_levels(&tick["asks"])?,
+    })
+}
+
+/// Fetch the full-precision depth snapshot of `symbol`
+async fn fetch_depth_snapshot(
+    http: &reqwest::Client,
+    rest_url: &str,
+    symbol: &str,
+) -> Result<DepthSnapshot, String> {
+    let htx_symbol =
+        to_venue_symbol(EXCHANGE, symbol).ok_or_else(|| format!("no HTX symbol for {}", symbol))?;
+    let url = format!(
+        "{}/market/depth?symbol={}&type=step0",
+        rest_url.trim_end_matches('/'),
+        htx_symbol
+    );
+    let response = http.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {}", status, body));
+    }
+    parse_depth_snapshot(&body)
+}
+
+/// Fetches of REST depth snapshots in flight, with their symbol
+type SnapshotFetches = JoinSet<(String, Result<DepthSnapshot, String>)>;
+
+/// HTX spot screener keeping a local book per symbol from the gzip-compressed mbp
+/// incremental channel and REST snapshots, persisted as CEX market states
+pub struct HtxScreener {
+    config: HtxConfig,
+    shutdown: CancellationToken,
+    http: reqwest::Client,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl HtxScreener {
+    /// Create a new HtxScreener instance on the symbols of `HTX_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, HtxConfig::from_env()?)
+    }
+
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: HtxConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder().timeout(REST_TIMEOUT).build()?;
+        let cex_writer = CexMarketWriter::for_database(db_pool, "htx");
+        Ok(Self {
+            config,
+            shutdown: CancellationToken::new(),
+            http,
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("HTX", EXCHANGE, STALE_FEED_TIMEOUT),
+        })
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from a new snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting HTX screener for {:?} ({})...",
+            self.config.symbols, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("HTX screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(EXCHANGE, symbol)))
+            .collect();
+    }
+
+    /// Fetch the snapshot of `symbol` after `delay`
+    fn spawn_snapshot_fetch(&self, fetches: &mut SnapshotFetches, symbol: &str, delay: Duration) {
+        let http = self.http.clone();
+        let rest_url = self.config.rest_url.clone();
+        let symbol = symbol.to_string();
+        fetches.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let snapshot = fetch_depth_snapshot(&http, &rest_url, &symbol).await;
+            (symbol, snapshot)
+        });
+    }
+
+    /// Apply one decompressed message; returns whether it was an order book update
+    fn handle_frame(&self, fetches: &mut SnapshotFetches, text: &str) -> bool {
+        let update = match parse_frame(text) {
+            Ok(HtxFrame::Update(update)) => update,
+            Ok(HtxFrame::Error(detail)) => {
+                error!("HTX websocket error: {}", detail);
+                return false;
+            }
+            Ok(HtxFrame::Other) => {
+                debug!("Skipping HTX frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed HTX frame: {}", e);
+                return false;
+            }
+        };
+        let (symbol, seq_num, event_ms) = (
+            update.symbol.clone(),
+            update.last_update_id,
+            update.event_ms,
+        );
+
+        let mut books = self.books.lock().unwrap();
+        // Updates of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&symbol) else {
+            return true;
+        };
+        match book.on_update(update) {
+            UpdateOutcome::Applied => self.persist(book, seq_num, event_ms, fetches),
+            UpdateOutcome::Resync => {
+                warn!(
+                    "HTX {} order book missed updates before {}, fetching a new snapshot",
+                    symbol, seq_num
+                );
+                metrics::counter!("htx_orderbook_gaps_total", "symbol" => symbol.clone())
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            }
+            UpdateOutcome::Buffered | UpdateOutcome::Skipped => {}
+        }
+        true
+    }
+
+    /// Rebuild a book from its snapshot, fetching it again when it is too old or failed
+    fn handle_snapshot(
+        &self,
+        fetches: &mut SnapshotFetches,
+        symbol: &str,
+        snapshot: Result<DepthSnapshot, String>,
+    ) {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("HTX {} depth snapshot failed, retrying: {}", symbol, e);
+                metrics::counter!("htx_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+                return;
+            }
+        };
+        let version = snapshot.last_update_id;
+
+        let mut books = self.books.lock().unwrap();
+        let Some(book) = books.get_mut(symbol) else {
+            return;
+        };
+        match book.on_snapshot(snapshot) {
+            SnapshotOutcome::Synced { replayed } => {
+                info!(
+                    "HTX {} order book synced from a snapshot at {}, replayed {} updates",
+                    symbol, version, replayed
+                );
+                metrics::counter!("htx_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "ok")
+                    .increment(1);
+                let BookSync::Synced(last) = book.sync else {
+                    return;
+                };
+                self.persist(book, last, Utc::now().timestamp_millis(), fetches);
+            }
+            SnapshotOutcome::Stale => {
+                debug!(
+                    "HTX {} depth snapshot at {} is older than the buffered updates, retrying",
+                    symbol, version
+                );
+                metrics::counter!("htx_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "stale")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+            }
+            SnapshotOutcome::Ignored => {}
+        }
+    }
+
+    /// Persist the top of book when it changed. A book that cannot describe a real market is
+    /// dropped and rebuilt from a new snapshot instead.
+    fn persist(
+        &self,
+        book: &mut SymbolBook,
+        seq_num: u64,
+        event_ms: i64,
+        fetches: &mut SnapshotFetches,
+    ) {
+        let symbol = book.orderbook.symbol.clone();
+        if let Err(violation) = book.orderbook.validate() {
+            error!(
+                "HTX {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "htx_invalid_books_total",
+                "symbol" => symbol.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            book.resync_from(Vec::new());
+            self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            return;
+        }
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: seq_num.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol,
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(event_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - event_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for HtxScreener {
+    /// Empty the books, connect, subscribe every symbol, fetch their snapshots and apply the
+    /// updates until the connection drops or the screener stops. `feed` counts the updates
+    /// delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        for symbol in &self.config.symbols {
+            ws.send(Message::Text(subscribe_request(symbol)))
+                .await
+                .map_err(|e| format!("subscribe failed: {}", e))?;
+        }
+        info!(
+            "HTX websocket connected, subscribed to {:?}",
+            self.config.symbols
+        );
+
+        // Updates are buffered from the subscription on, so the snapshots are fetched after it
+        let mut fetches = SnapshotFetches::new();
+        for symbol in &self.config.symbols {
+            self.spawn_snapshot_fetch(&mut fetches, symbol, Duration::ZERO);
+        }
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                Some(fetched) = fetches.join_next(), if !fetches.is_empty() => {
+                    if let Ok((symbol, snapshot)) = fetched {
+                        self.handle_snapshot(&mut fetches, &symbol, snapshot);
+                    }
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Binary(data))) => {
+                            let text = match gunzip_text(&data) {
+                                Ok(text) => text,
+                                Err(e) => {
+                                    warn!("Skipping HTX frame: {}", e);
+                                    continue;
+                                }
+                            };
+                            // HTX closes connections that miss two of its pings
+                            if let Some(pong) = embedded_pong(&text) {
+                                ws.send(Message::Text(pong))
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+                            } else if self.handle_frame(&mut fetches, &text) {
+                                feed.delivered();
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "htx_tests.rs"]
+mod htx_tests;
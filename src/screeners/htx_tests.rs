@@ -0,0 +1,225 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// `market.trumpusdt.mbp.150` messages recorded in order, decompressed: one already in the
+/// snapshot, one straddling it, two following it (`seqNum`s are not consecutive), then a gap
+const UPDATE_FIXTURES: [&str; 5] = [
+    r#"{"ch":"market.trumpusdt.mbp.150","ts":1716863719050,"tick":{"seqNum":999,"prevSeqNum":998,"bids":[[10.21,4]],"asks":[]}}"#,
+    r#"{"ch":"market.trumpusdt.mbp.150","ts":1716863719100,"tick":{"seqNum":1001,"prevSeqNum":999,"bids":[[10.24,0],[10.25,1.5]],"asks":[[10.27,3]]}}"#,
+    r#"{"ch":"market.trumpusdt.mbp.150","ts":1716863719200,"tick":{"seqNum":1002,"prevSeqNum":1001,"bids":[[10.25,2]]}}"#,
+    r#"{"ch":"market.trumpusdt.mbp.150","ts":1716863719300,"tick":{"seqNum":1005,"prevSeqNum":1002,"asks":[[10.28,0],[10.26,1]]}}"#,
+    r#"{"ch":"market.trumpusdt.mbp.150","ts":1716863719400,"tick":{"seqNum":1012,"prevSeqNum":1010,"bids":[[10.2,9]],"asks":[]}}"#,
+];
+
+/// The second update as received: a gzip-compressed binary frame
+const COMPRESSED_UPDATE: &str = "1f8b08000000000002033d8bcb0ac2301000ff65cfcbb26b4d63f2115e3c961cfa08584220e6e1a5f4df0d22de8619e680f50916e29c83af54738ba995ad525c12896240a805ac68196fe3a0c5087357fb1ac01e50fcebde62cfcc8290b27f3f7ec61883b0ec5b7fa749982e5764875f5228a49c43984bf8678d8373e7f901ff44c9878e000000";
+
+/// `/market/depth?type=step0` reply recorded for trumpusdt
+const SNAPSHOT_FIXTURE: &str = r#"{"ch":"market.trumpusdt.depth.step0","status":"ok","ts":1716863719120,"tick":{"ts":1716863719110,"version":1000,"bids":[[10.24,2],[10.23,3]],"asks":[[10.27,4],[10.28,5]]}}"#;
+
+fn update(index: usize) -> DepthUpdate {
+    match parse_frame(UPDATE_FIXTURES[index]).unwrap() {
+        HtxFrame::Update(update) => update,
+        other => panic!("not an order book update: {:?}", other),
+    }
+}
+
+fn snapshot_at(version: u64) -> DepthSnapshot {
+    DepthSnapshot {
+        last_update_id: version,
+        ..parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap()).unwrap()
+    }
+}
+
+fn prices(levels: &market::OrderBookLevels) -> Vec<Decimal> {
+    levels.keys().copied().collect()
+}
+
+impl TestScreener for HtxScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = HtxScreener {
+            config: HtxConfig {
+                symbols: vec!["TRUMPUSDT".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+                rest_url: "http://127.0.0.1:1".to_string(),
+            },
+            shutdown: CancellationToken::new(),
+            http: reqwest::Client::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("HTX", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn compressed_updates_are_decoded_with_internal_symbols() {
+    let text = gunzip_text(&hex::decode(COMPRESSED_UPDATE).unwrap()).unwrap();
+    assert_eq!(text, UPDATE_FIXTURES[1]);
+    assert_eq!(embedded_pong(&text), None);
+    assert_eq!(
+        parse_frame(&text),
+        Ok(HtxFrame::Update(DepthUpdate {
+            symbol: "TRUMPUSDT".to_string(),
+            event_ms: 1716863719100,
+            first_update_id: 1000,
+            last_update_id: 1001,
+            bids: vec![
+                (decimal("10.24"), Decimal::ZERO),
+                (decimal("10.25"), decimal("1.5"))
+            ],
+            asks: vec![(decimal("10.27"), decimal("3"))],
+        }))
+    );
+    assert!(update(2).asks.is_empty());
+
+    assert_eq!(
+        parse_frame(
+            r#"{"id":"trumpusdt","status":"ok","subbed":"market.trumpusdt.mbp.150","ts":1716863719000}"#
+        ),
+        Ok(HtxFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(r#"{"ch":"market.trumpusdt.trade.detail","ts":1716863719000,"tick":{}}"#),
+        Ok(HtxFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(
+            r#"{"status":"error","ts":1716863719000,"id":"nopeusdt","err-code":"bad-request","err-msg":"invalid symbol nopeusdt"}"#
+        ),
+        Ok(HtxFrame::Error(
+            "bad-request invalid symbol nopeusdt".to_string()
+        ))
+    );
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace(r#""prevSeqNum":1001,"#, "")).is_err());
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace("[10.25,2]", r#"["10.25","2"]"#)).is_err());
+}
+
+#[test]
+fn requests_and_symbols_follow_htx_conventions() {
+    let subscribe: Value = serde_json::from_str(&subscribe_request("TRUMPUSDT")).unwrap();
+    assert_eq!(
+        subscribe,
+        json!({"sub": "market.trumpusdt.mbp.150", "id": "trumpusdt"})
+    );
+    assert_eq!(
+        parse_symbols(" trumpusdt,TRUMPUSDC,,TRUMPUSDT ").unwrap(),
+        ["TRUMPUSDT", "TRUMPUSDC"]
+    );
+    assert!(parse_symbols("TRUMP").is_err());
+    assert!(parse_symbols(" , ").is_err());
+}
+
+#[test]
+fn incremental_updates_merge_onto_the_snapshot() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDT");
+    for index in 0..3 {
+        assert_eq!(book.on_update(update(index)), UpdateOutcome::Buffered);
+    }
+
+    // The first update is older than the snapshot, the second one follows its version
+    assert_eq!(
+        book.on_snapshot(snapshot_at(1000)),
+        SnapshotOutcome::Synced { replayed: 2 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(1002));
+    assert_eq!(
+        prices(&book.orderbook.bids),
+        [decimal("10.23"), decimal("10.25")]
+    );
+    assert_eq!(book.orderbook.bids[&decimal("10.25")], decimal("2"));
+
+    // `prevSeqNum` 1002 follows `seqNum` 1002 even though 1003 and 1004 were never sent
+    assert_eq!(book.on_update(update(3)), UpdateOutcome::Applied);
+    assert_eq!(
+        prices(&book.orderbook.asks),
+        [decimal("10.26"), decimal("10.27")]
+    );
+    assert_eq!(book.on_update(update(2)), UpdateOutcome::Skipped);
+
+    assert_eq!(book.on_update(update(4)), UpdateOutcome::Resync);
+    assert_eq!(book.sync, BookSync::AwaitingSnapshot(vec![update(4)]));
+    assert_eq!(book.on_snapshot(snapshot_at(1005)), SnapshotOutcome::Stale);
+    assert_eq!(
+        book.on_snapshot(snapshot_at(1010)),
+        SnapshotOutcome::Synced { replayed: 1 }
+    );
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_htx_states() {
+    let (screener, sink) = build_screener_with_sink::<HtxScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    assert!(screener.handle_frame(&mut fetches, UPDATE_FIXTURES[1]));
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDT", Ok(snapshot_at(1000)));
+    screener.cex_writer.flush().await;
+    for fixture in &UPDATE_FIXTURES[2..4] {
+        assert!(screener.handle_frame(&mut fetches, fixture));
+        screener.cex_writer.flush().await;
+    }
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "htx" && state.trade_pair == "TRUMPUSDT")
+    );
+    assert_eq!(states[0].trade_id, "1001");
+    assert_eq!(
+        (states[0].bid_price, states[0].ask_price),
+        (decimal("10.25"), decimal("10.27"))
+    );
+    assert_eq!(
+        (states[2].trade_id.as_str(), states[2].ask_price),
+        ("1005", decimal("10.26"))
+    );
+    assert_eq!(states[2].trade_time.timestamp_millis(), 1716863719300);
+    assert!(fetches.is_empty());
+
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[4]);
+    assert_eq!(fetches.len(), 1);
+    fetches.abort_all();
+}
+
+#[tokio::test]
+async fn depth_snapshots_are_fetched_at_full_precision() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/market/depth"))
+        .and(query_param("symbol", "trumpusdt"))
+        .and(query_param("type", "step0"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SNAPSHOT_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/market/depth"))
+        .and(query_param("symbol", "nopeusdt"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"status":"error","err-code":"invalid-parameter","err-msg":"invalid symbol","data":null}"#,
+        ))
+        .mount(&server)
+        .await;
+    let http = reqwest::Client::new();
+
+    assert_eq!(
+        fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDT").await,
+        Ok(snapshot_at(1000))
+    );
+    let error = fetch_depth_snapshot(&http, &server.uri(), "NOPEUSDT")
+        .await
+        .unwrap_err();
+    assert!(error.contains("invalid-parameter"), "{}", error);
+}
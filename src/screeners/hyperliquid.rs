@@ -0,0 +1,353 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "hyperliquid";
+/// Perp coins streamed when `HYPERLIQUID_COINS` is unset
+const DEFAULT_COINS: &str = "TRUMP";
+/// Public websocket URL when `HYPERLIQUID_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://api.hyperliquid.xyz/ws";
+/// Quote asset of every perp, appended to the coin for the persisted trade pair
+const QUOTE_ASSET: &str = "USDC";
+/// Hyperliquid drops connections that sent nothing for 60 seconds
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Silence after which the connection is considered dead; pings are answered with pongs
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Coins and endpoint of the Hyperliquid screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperliquidConfig {
+    /// Perp coins as Hyperliquid names them, e.g. `TRUMP` or `kPEPE`
+    pub coins: Vec<String>,
+    pub ws_url: String,
+}
+
+impl HyperliquidConfig {
+    /// Read `HYPERLIQUID_COINS` (comma-separated) and `HYPERLIQUID_WS_URL`, falling back to
+    /// the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let coins =
+            std::env::var("HYPERLIQUID_COINS").unwrap_or_else(|_| DEFAULT_COINS.to_string());
+        Ok(Self {
+            coins: parse_coins(&coins)?,
+            ws_url: std::env::var("HYPERLIQUID_WS_URL")
+                .unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+        })
+    }
+}
+
+/// Parse comma-separated coins. Names are case sensitive, since Hyperliquid prefixes the
+/// thousand-unit coins with a lowercase `k`.
+fn parse_coins(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut coins = Vec::new();
+    for coin in value.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        if !coin.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("HYPERLIQUID_COINS entry `{}` is not a coin name", coin).into());
+        }
+        if !coins.iter().any(|known| known == coin) {
+            coins.push(coin.to_string());
+        }
+    }
+    if coins.is_empty() {
+        return Err("HYPERLIQUID_COINS has no coin".into());
+    }
+    Ok(coins)
+}
+
+/// Internal symbol the book of a perp coin is persisted under, `TRUMP` → `TRUMPUSDC`
+fn coin_symbol(coin: &str) -> String {
+    format!("{}{}", coin.to_uppercase(), QUOTE_ASSET)
+}
+
+/// `subscribe` or `unsubscribe` request of the l2Book channel of `coin`
+fn l2_book_request(method: &str, coin: &str) -> String {
+    json!({"method": method, "subscription": {"type": "l2Book", "coin": coin}}).to_string()
+}
+
+/// Price levels as `(price, size)`
+type Levels = Vec<(Decimal, Decimal)>;
+
+/// Message of the l2Book channel: the full top of the book of a coin
+#[derive(Debug, Clone, PartialEq)]
+struct L2Book {
+    coin: String,
+    /// Exchange timestamp, in milliseconds
+    time_ms: i64,
+    bids: Levels,
+    asks: Levels,
+}
+
+/// Decoded text frame of the public websocket
+#[derive(Debug, Clone, PartialEq)]
+enum HyperliquidFrame {
+    Book(L2Book),
+    Error(String),
+    /// Subscription replies, pongs and other channels
+    Other,
+}
+
+/// Decode `[{"px": "10.25", "sz": "120.5", "n": 3}, ...]` levels
+fn parse_levels(value: &Value) -> Result<Levels, String> {
+    let levels = value.as_array().ok_or("levels are not an array")?;
+    levels
+        .iter()
+        .map(|level| {
+            let field = |name: &str| -> Result<Decimal, String> {
+                let text = level[name]
+                    .as_str()
+                    .ok_or_else(|| format!("malformed level {}", level))?;
+                text.parse()
+                    .map_err(|e| format!("malformed level {}: {}", level, e))
+            };
+            Ok((field("px")?, field("sz")?))
+        })
+        .collect()
+}
+
+fn parse_frame(text: &str) -> Result<HyperliquidFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    match frame["channel"].as_str() {
+        Some("l2Book") => {}
+        Some("error") => return Ok(HyperliquidFrame::Error(frame["data"].to_string())),
+        _ => return Ok(HyperliquidFrame::Other),
+    }
+    let data = &frame["data"];
+    // `levels` holds the bids then the asks, each best first
+    let levels = data["levels"].as_array().ok_or("missing `levels`")?;
+    let [bids, asks] = levels.as_slice() else {
+        return Err(format!("{} sides of levels instead of 2", levels.len()));
+    };
+    Ok(HyperliquidFrame::Book(L2Book {
+        coin: data["coin"].as_str().ok_or("missing `coin`")?.to_string(),
+        time_ms: data["time"].as_i64().ok_or("missing `time`")?,
+        bids: parse_levels(bids)?,
+        asks: parse_levels(asks)?,
+    }))
+}
+
+/// Local book of a coin with its last persisted top
+#[derive(Debug, Clone)]
+struct CoinBook {
+    orderbook: market::OrderBook,
+    /// Best bid and ask of the last persisted state
+    last_top: Option<(market::OrderBookItem, market::OrderBookItem)>,
+}
+
+/// Hyperliquid perp screener keeping the l2Book of every coin, persisted as CEX market
+/// states. Every l2Book message is a full snapshot, so it replaces the book instead of
+/// being merged into it.
+pub struct HyperliquidScreener {
+    config: HyperliquidConfig,
+    shutdown: CancellationToken,
+    /// Books by coin, each named after the coin's internal symbol
+    books: Mutex<HashMap<String, CoinBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl HyperliquidScreener {
+    /// Create a new HyperliquidScreener instance on the coins of `HYPERLIQUID_COINS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, HyperliquidConfig::from_env()?))
+    }
+
+    pub fn with_config(db_pool: DatabasePool, config: HyperliquidConfig) -> Self {
+        let cex_writer = CexMarketWriter::for_database(db_pool, "hyperliquid");
+        Self {
+            config,
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Hyperliquid", EXCHANGE, STALE_FEED_TIMEOUT),
+        }
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Hyperliquid screener for {:?} ({})...",
+            self.config.coins, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Hyperliquid screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book, so nothing is persisted until the new session's first snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .coins
+            .iter()
+            .map(|coin| {
+                let book = CoinBook {
+                    orderbook: market::OrderBook::new(EXCHANGE, &coin_symbol(coin)),
+                    last_top: None,
+                };
+                (coin.clone(), book)
+            })
+            .collect();
+    }
+
+    /// Apply one text frame; returns whether it was a book message
+    fn handle_frame(&self, text: &str) -> bool {
+        let book = match parse_frame(text) {
+            Ok(HyperliquidFrame::Book(book)) => book,
+            Ok(HyperliquidFrame::Error(detail)) => {
+                error!("Hyperliquid websocket error: {}", detail);
+                return false;
+            }
+            Ok(HyperliquidFrame::Other) => {
+                debug!("Skipping Hyperliquid frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed Hyperliquid frame: {}", e);
+                return false;
+            }
+        };
+
+        let mut books = self.books.lock().unwrap();
+        // Books of a coin that is not streamed cannot be tracked
+        let Some(coin_book) = books.get_mut(&book.coin) else {
+            return true;
+        };
+        coin_book.orderbook.replace_levels(&book.bids, &book.asks);
+        if let Err(violation) = coin_book.orderbook.validate() {
+            // The next snapshot replaces the book, so it only has to be kept out of the store
+            error!(
+                "Hyperliquid {} order book rejected, {}: {}",
+                book.coin,
+                violation,
+                coin_book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "hyperliquid_invalid_books_total",
+                "coin" => book.coin.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            return true;
+        }
+        self.persist(coin_book, book.time_ms);
+        true
+    }
+
+    /// Persist the top of book when it changed
+    fn persist(&self, book: &mut CoinBook, time_ms: i64) {
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            // Snapshots carry no sequence number, their timestamp identifies them
+            trade_id: time_ms.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: book.orderbook.symbol.clone(),
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(time_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - time_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for HyperliquidScreener {
+    /// Empty the books, connect, subscribe to every coin and apply the stream until the connection
+    /// drops or the screener stops. `feed` counts the book messages delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        // Hyperliquid takes a single subscription per request
+        for coin in &self.config.coins {
+            ws.send(Message::Text(l2_book_request("subscribe", coin)))
+                .await
+                .map_err(|e| format!("subscribe failed: {}", e))?;
+        }
+        info!(
+            "Hyperliquid websocket connected, subscribed to {:?}",
+            self.config.coins
+        );
+
+        let mut ping =
+            tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                _ = ping.tick() => {
+                    ws.send(Message::Text(json!({"method": "ping"}).to_string()))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            if self.handle_frame(&text) {
+                                feed.delivered();
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "hyperliquid_tests.rs"]
+mod hyperliquid_tests;
@@ -0,0 +1,159 @@
+use super::*;
+use std::str::FromStr;
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// l2Book messages of TRUMP recorded in order, truncated to a few levels: the second one
+/// drops a bid level and moves the best ask, the third one only changes volumes deeper in
+/// the book
+const BOOK_FIXTURES: [&str; 3] = [
+    r#"{"channel":"l2Book","data":{"coin":"TRUMP","time":1716863719100,"levels":[[{"px":"10.25","sz":"120.5","n":3},{"px":"10.24","sz":"80","n":2},{"px":"10.23","sz":"40","n":1}],[{"px":"10.27","sz":"95.1","n":4},{"px":"10.28","sz":"60","n":2}]]}}"#,
+    r#"{"channel":"l2Book","data":{"coin":"TRUMP","time":1716863719600,"levels":[[{"px":"10.25","sz":"110","n":3},{"px":"10.23","sz":"40","n":1}],[{"px":"10.26","sz":"12","n":1},{"px":"10.27","sz":"95.1","n":4}]]}}"#,
+    r#"{"channel":"l2Book","data":{"coin":"TRUMP","time":1716863720100,"levels":[[{"px":"10.25","sz":"110","n":3},{"px":"10.23","sz":"55","n":2}],[{"px":"10.26","sz":"12","n":1},{"px":"10.27","sz":"90","n":3}]]}}"#,
+];
+
+fn book(index: usize) -> L2Book {
+    match parse_frame(BOOK_FIXTURES[index]).unwrap() {
+        HyperliquidFrame::Book(book) => book,
+        other => panic!("not an l2Book message: {:?}", other),
+    }
+}
+
+impl TestScreener for HyperliquidScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = HyperliquidScreener {
+            config: HyperliquidConfig {
+                coins: vec!["TRUMP".to_string(), "kPEPE".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+            },
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Hyperliquid", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn l2_book_messages_are_decoded_into_levels() {
+    assert_eq!(
+        book(0),
+        L2Book {
+            coin: "TRUMP".to_string(),
+            time_ms: 1716863719100,
+            bids: vec![
+                (decimal("10.25"), decimal("120.5")),
+                (decimal("10.24"), decimal("80")),
+                (decimal("10.23"), decimal("40")),
+            ],
+            asks: vec![
+                (decimal("10.27"), decimal("95.1")),
+                (decimal("10.28"), decimal("60")),
+            ],
+        }
+    );
+
+    assert_eq!(
+        parse_frame(
+            r#"{"channel":"subscriptionResponse","data":{"method":"subscribe","subscription":{"type":"l2Book","coin":"TRUMP"}}}"#
+        ),
+        Ok(HyperliquidFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(r#"{"channel":"pong"}"#),
+        Ok(HyperliquidFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(r#"{"channel":"error","data":"Invalid subscription"}"#),
+        Ok(HyperliquidFrame::Error(
+            r#""Invalid subscription""#.to_string()
+        ))
+    );
+    assert!(parse_frame(&BOOK_FIXTURES[0].replace(r#""time":1716863719100,"#, "")).is_err());
+    assert!(parse_frame(&BOOK_FIXTURES[0].replace(r#""px":"10.24""#, r#""px":10.24"#)).is_err());
+    assert!(
+        parse_frame(r#"{"channel":"l2Book","data":{"coin":"TRUMP","time":1,"levels":[[]]}}"#)
+            .is_err()
+    );
+}
+
+#[test]
+fn requests_and_coins_follow_hyperliquid_conventions() {
+    let subscribe: Value = serde_json::from_str(&l2_book_request("subscribe", "kPEPE")).unwrap();
+    assert_eq!(
+        subscribe,
+        json!({"method": "subscribe", "subscription": {"type": "l2Book", "coin": "kPEPE"}})
+    );
+    assert_eq!(
+        parse_coins(" TRUMP,kPEPE,,TRUMP ").unwrap(),
+        ["TRUMP", "kPEPE"]
+    );
+    assert!(parse_coins("TRUMP-PERP").is_err());
+    assert!(parse_coins(" , ").is_err());
+    assert_eq!(coin_symbol("kPEPE"), "KPEPEUSDC");
+}
+
+#[test]
+fn every_message_replaces_the_whole_book() {
+    let (screener, _sink) = build_screener_with_sink::<HyperliquidScreener>();
+    for fixture in &BOOK_FIXTURES[..2] {
+        assert!(screener.handle_frame(fixture));
+    }
+
+    let books = screener.books.lock().unwrap();
+    let orderbook = &books["TRUMP"].orderbook;
+    // 10.24 and 10.28 are gone although no message sent them with a zero size
+    assert_eq!(
+        orderbook.bids.keys().copied().collect::<Vec<_>>(),
+        [decimal("10.23"), decimal("10.25")]
+    );
+    assert_eq!(
+        orderbook.asks.keys().copied().collect::<Vec<_>>(),
+        [decimal("10.26"), decimal("10.27")]
+    );
+    assert_eq!(orderbook.bids[&decimal("10.25")], decimal("110"));
+    assert!(books["kPEPE"].orderbook.bids.is_empty());
+}
+
+#[tokio::test]
+async fn l2_books_are_persisted_as_hyperliquid_states() {
+    let (screener, sink) = build_screener_with_sink::<HyperliquidScreener>();
+    for fixture in BOOK_FIXTURES {
+        assert!(screener.handle_frame(fixture));
+        screener.cex_writer.flush().await;
+    }
+    assert!(!screener.handle_frame(r#"{"channel":"pong"}"#));
+    // A crossed book is kept out of the store
+    assert!(screener.handle_frame(&BOOK_FIXTURES[0].replace(r#""px":"10.27""#, r#""px":"10.2""#)));
+    screener.cex_writer.flush().await;
+
+    // The third message left the top of the book unchanged
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 2);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "hyperliquid" && state.trade_pair == "TRUMPUSDC")
+    );
+    assert_eq!(states[0].trade_id, "1716863719100");
+    assert_eq!(
+        (states[0].bid_price, states[0].bid_volume),
+        (decimal("10.25"), decimal("120.5"))
+    );
+    assert_eq!(
+        (states[0].ask_price, states[0].ask_volume),
+        (decimal("10.27"), decimal("95.1"))
+    );
+    assert_eq!(states[0].trade_time.timestamp_millis(), 1716863719100);
+    assert!(states[0].depth.is_some());
+    assert_eq!(
+        (states[1].trade_id.as_str(), states[1].ask_price),
+        ("1716863719600", decimal("10.26"))
+    );
+}
@@ -0,0 +1,685 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::models::market;
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora::{
+    MeteoraConfig, max_concurrent_pairs_from_env, mint_decimals, poll_interval_from_env,
+    run_poll_loop,
+};
+use crate::solana::rate_limit::RateLimiter;
+use crate::solana::retry::RetryPolicy;
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::store::db::DatabasePool;
+
+use super::symbols::split_symbol;
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "jupiter";
+/// Public quote API when `JUPITER_API_URL` is unset
+const DEFAULT_API_URL: &str = "https://quote-api.jup.ag/v6";
+/// Pairs quoted when `JUPITER_PAIRS` is unset, TRUMP against USDC
+const DEFAULT_PAIRS: &str = "TRUMPUSDC:6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN:EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+/// Base token amounts quoted when `JUPITER_AMOUNTS` is unset
+const DEFAULT_AMOUNTS: &str = "1,10,100";
+/// Requests per second when `JUPITER_MAX_RPS` is unset, the public API's free tier
+const DEFAULT_MAX_RPS: u32 = 1;
+/// Slippage sent with every quote request; it only sets `otherAmountThreshold`
+const SLIPPAGE_BPS: u16 = 50;
+/// Timeout of a single quote request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Length of the `dex_markets.route_plan` column
+const ROUTE_PLAN_MAX_LEN: usize = 512;
+
+/// Pair quoted through Jupiter under an internal symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct JupiterPair {
+    pub symbol: String,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+}
+
+/// Pairs, amount ladder and API settings of the Jupiter screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct JupiterConfig {
+    pub api_url: String,
+    pub pairs: Vec<JupiterPair>,
+    /// Base token amounts quoted on every poll, in whole tokens, smallest first
+    pub amounts: Vec<Decimal>,
+    pub max_rps: u32,
+}
+
+impl JupiterConfig {
+    /// Read `JUPITER_API_URL`, `JUPITER_PAIRS` (comma-separated `SYMBOL:BASE_MINT:QUOTE_MINT`
+    /// entries), `JUPITER_AMOUNTS` (comma-separated base token amounts) and `JUPITER_MAX_RPS`,
+    /// falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let pairs = std::env::var("JUPITER_PAIRS").unwrap_or_else(|_| DEFAULT_PAIRS.to_string());
+        let amounts =
+            std::env::var("JUPITER_AMOUNTS").unwrap_or_else(|_| DEFAULT_AMOUNTS.to_string());
+        Ok(Self {
+            api_url: std::env::var("JUPITER_API_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_API_URL.to_string()),
+            pairs: parse_pairs(&pairs)?,
+            amounts: parse_amounts(&amounts)?,
+            max_rps: std::env::var("JUPITER_MAX_RPS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|rps| *rps > 0)
+                .unwrap_or(DEFAULT_MAX_RPS),
+        })
+    }
+}
+
+/// Parse comma-separated `SYMBOL:BASE_MINT:QUOTE_MINT` entries, each symbol once
+fn parse_pairs(value: &str) -> Result<Vec<JupiterPair>, Box<dyn std::error::Error>> {
+    let mut pairs: Vec<JupiterPair> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let [symbol, base_mint, quote_mint] = entry.split(':').collect::<Vec<_>>()[..] else {
+            return Err(format!(
+                "JUPITER_PAIRS entry `{}` is not SYMBOL:BASE_MINT:QUOTE_MINT",
+                entry
+            )
+            .into());
+        };
+        let symbol = symbol.trim().to_uppercase();
+        if split_symbol(&symbol).is_none() {
+            return Err(
+                format!("JUPITER_PAIRS entry `{}` has an unknown quote asset", entry).into(),
+            );
+        }
+        let mint = |value: &str| {
+            Pubkey::from_str(value.trim())
+                .map_err(|e| format!("JUPITER_PAIRS entry `{}`: {}", entry, e))
+        };
+        let (base_mint, quote_mint) = (mint(base_mint)?, mint(quote_mint)?);
+        if base_mint == quote_mint {
+            return Err(format!(
+                "JUPITER_PAIRS entry `{}` quotes a mint against itself",
+                entry
+            )
+            .into());
+        }
+        if pairs.iter().any(|pair| pair.symbol == symbol) {
+            return Err(format!("JUPITER_PAIRS entry `{}` is configured twice", entry).into());
+        }
+        pairs.push(JupiterPair {
+            symbol,
+            base_mint,
+            quote_mint,
+        });
+    }
+    if pairs.is_empty() {
+        return Err("JUPITER_PAIRS has no pair".into());
+    }
+    Ok(pairs)
+}
+
+/// Parse comma-separated positive base token amounts, sorted and deduplicated
+fn parse_amounts(value: &str) -> Result<Vec<Decimal>, Box<dyn std::error::Error>> {
+    let mut amounts = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let amount: Decimal = entry
+            .parse()
+            .map_err(|e| format!("JUPITER_AMOUNTS entry `{}`: {}", entry, e))?;
+        if amount <= Decimal::ZERO {
+            return Err(format!("JUPITER_AMOUNTS entry `{}` is not positive", entry).into());
+        }
+        amounts.push(amount.normalize());
+    }
+    amounts.sort();
+    amounts.dedup();
+    if amounts.is_empty() {
+        return Err("JUPITER_AMOUNTS has no amount".into());
+    }
+    Ok(amounts)
+}
+
+/// Body of a `/quote` reply. Amounts are strings of token atoms.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiQuote {
+    input_mint: String,
+    in_amount: String,
+    output_mint: String,
+    out_amount: String,
+    price_impact_pct: String,
+    route_plan: Vec<ApiRoutePlanStep>,
+    #[serde(default)]
+    context_slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiRoutePlanStep {
+    swap_info: ApiSwapInfo,
+    percent: u8,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiSwapInfo {
+    amm_key: String,
+    #[serde(default)]
+    label: String,
+    input_mint: String,
+    output_mint: String,
+    in_amount: String,
+    out_amount: String,
+}
+
+/// Error reply of the quote API, e.g. `COULD_NOT_FIND_ANY_ROUTE`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiError {
+    error: String,
+    #[serde(default)]
+    error_code: Option<String>,
+}
+
+/// Swap through one AMM, for `percent` of the amount entering its hop
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteStep {
+    pub amm_key: Pubkey,
+    /// AMM name given by Jupiter, e.g. `Meteora DLMM`
+    pub label: String,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub percent: u8,
+}
+
+/// Exact-in quote returned by Jupiter
+#[derive(Debug, Clone, PartialEq)]
+pub struct JupiterQuote {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// Price impact as a fraction, `0.01` being 1%
+    pub price_impact: Decimal,
+    pub route_plan: Vec<RouteStep>,
+    /// Slot the quote was computed at, 0 when the reply omits it
+    pub context_slot: u64,
+}
+
+impl JupiterQuote {
+    pub fn price_impact_bps(&self) -> Decimal {
+        self.price_impact * Decimal::from(10_000)
+    }
+
+    /// Hops of the route joined by `>`, the AMMs splitting a hop joined by `+`, e.g.
+    /// `Meteora DLMM 70% + Whirlpool 30% > Raydium CLMM 100%`
+    pub fn route_summary(&self) -> String {
+        let mut hops: Vec<(Pubkey, Pubkey, Vec<String>)> = Vec::new();
+        for step in &self.route_plan {
+            let leg = format!("{} {}%", step.label, step.percent);
+            match hops.last_mut() {
+                Some((input, output, legs))
+                    if *input == step.input_mint && *output == step.output_mint =>
+                {
+                    legs.push(leg)
+                }
+                _ => hops.push((step.input_mint, step.output_mint, vec![leg])),
+            }
+        }
+        hops.into_iter()
+            .map(|(_, _, legs)| legs.join(" + "))
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
+fn parse_amount(value: &str, field: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid `{}` amount `{}`", field, value))
+}
+
+fn parse_pubkey(value: &str, field: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(value).map_err(|_| format!("invalid `{}` pubkey `{}`", field, value))
+}
+
+/// Decode a `/quote` reply, or the error it carries instead of a quote
+fn parse_quote(body: &str) -> Result<JupiterQuote, String> {
+    if let Ok(error) = serde_json::from_str::<ApiError>(body) {
+        return Err(match error.error_code {
+            Some(code) => format!("{} ({})", error.error, code),
+            None => error.error,
+        });
+    }
+    let quote: ApiQuote = serde_json::from_str(body).map_err(|e| e.to_string())?;
+    if quote.route_plan.is_empty() {
+        return Err("quote has an empty route plan".to_string());
+    }
+    let route_plan = quote
+        .route_plan
+        .iter()
+        .map(|step| {
+            let swap = &step.swap_info;
+            Ok(RouteStep {
+                amm_key: parse_pubkey(&swap.amm_key, "ammKey")?,
+                label: swap.label.clone(),
+                input_mint: parse_pubkey(&swap.input_mint, "inputMint")?,
+                output_mint: parse_pubkey(&swap.output_mint, "outputMint")?,
+                in_amount: parse_amount(&swap.in_amount, "inAmount")?,
+                out_amount: parse_amount(&swap.out_amount, "outAmount")?,
+                percent: step.percent,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(JupiterQuote {
+        input_mint: parse_pubkey(&quote.input_mint, "inputMint")?,
+        output_mint: parse_pubkey(&quote.output_mint, "outputMint")?,
+        in_amount: parse_amount(&quote.in_amount, "inAmount")?,
+        out_amount: parse_amount(&quote.out_amount, "outAmount")?,
+        price_impact: quote
+            .price_impact_pct
+            .parse()
+            .map_err(|_| format!("invalid `priceImpactPct` `{}`", quote.price_impact_pct))?,
+        route_plan,
+        context_slot: quote.context_slot,
+    })
+}
+
+/// Sell of one ladder amount and the buy back with its proceeds
+#[derive(Debug, Clone)]
+pub struct LadderQuote {
+    /// Base atoms sold
+    pub amount: u64,
+    pub sell: JupiterQuote,
+    pub buy: JupiterQuote,
+    pub fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of both requests, throttling included
+    pub fetch_latency: Duration,
+}
+
+fn to_ui_amount(amount: u64, decimals: u32) -> Decimal {
+    Decimal::from_i128_with_scale(amount as i128, decimals)
+}
+
+/// Base atoms of `amount` whole tokens, `None` when it is below one atom or overflows
+fn to_atoms(amount: Decimal, decimals: u32) -> Option<u64> {
+    let atoms = amount.checked_mul(Decimal::from(10u64.checked_pow(decimals)?))?;
+    u64::try_from(atoms.trunc()).ok().filter(|atoms| *atoms > 0)
+}
+
+/// DEX market states of a ladder rung: the sell at its quote proceeds per base token and
+/// the buy at its quote cost per base token received. Trade ids
+/// `{symbol}:{slot}:{direction}:{amount}` keep one row per rung and slot.
+fn build_dex_states(
+    symbol: &str,
+    ladder: &LadderQuote,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> Vec<market::DEXState> {
+    let sell_base = to_ui_amount(ladder.sell.in_amount, base_decimals);
+    let buy_base = to_ui_amount(ladder.buy.out_amount, base_decimals);
+    let mut states = Vec::new();
+    for (direction, quote, base, quote_amount) in [
+        ("sell", &ladder.sell, sell_base, ladder.sell.out_amount),
+        ("buy", &ladder.buy, buy_base, ladder.buy.in_amount),
+    ] {
+        if base.is_zero() {
+            continue;
+        }
+        states.push(market::DEXState {
+            trade_id: format!(
+                "{}:{}:{}:{}",
+                symbol, quote.context_slot, direction, ladder.amount
+            ),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol.to_string(),
+            direction: direction.to_string(),
+            price: to_ui_amount(quote_amount, quote_decimals) / base,
+            volume: base,
+            trade_time: ladder.fetched_at,
+            fetch_time: ladder.fetched_at,
+            block_number: quote.context_slot,
+            price_impact_bps: Some(quote.price_impact_bps()),
+            pool_address: None,
+            stale: false,
+            fetch_latency_ms: Some(ladder.fetch_latency.as_millis() as u64),
+            route: None,
+            route_plan: Some(
+                quote
+                    .route_summary()
+                    .chars()
+                    .take(ROUTE_PLAN_MAX_LEN)
+                    .collect(),
+            ),
+        });
+    }
+    states
+}
+
+/// Quote request failure, with whether it is worth retrying
+#[derive(Debug)]
+struct RequestError {
+    message: String,
+    retryable: bool,
+}
+
+/// Polls Jupiter quotes for a ladder of amounts in both directions and persists them as
+/// DEX market states, a reference price routed across every venue Jupiter knows
+pub struct JupiterScreener {
+    pub db_pool: DatabasePool,
+    pub config: JupiterConfig,
+    /// Reads the mint decimals, once per mint
+    pub rpc_client: FailoverRpcClient,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Number of pairs quoted concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    http: reqwest::Client,
+    /// Shared by every quote request so the screener stays within `JUPITER_MAX_RPS`
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+    /// Decimals of every mint quoted so far
+    mint_decimals: RwLock<HashMap<Pubkey, u32>>,
+}
+
+impl JupiterScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(
+            db_pool,
+            MeteoraConfig::from_env()?,
+            JupiterConfig::from_env()?,
+        )
+    }
+
+    /// Build the screener on the shared RPC settings, used to read mint decimals
+    pub fn with_config(
+        db_pool: DatabasePool,
+        rpc_config: MeteoraConfig,
+        config: JupiterConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        info!(
+            "Jupiter API {} at {} req/s, RPC endpoints: {}",
+            config.api_url,
+            config.max_rps,
+            rpc_config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            db_pool,
+            rpc_client: FailoverRpcClient::from_urls(
+                rpc_config.rpc_endpoints,
+                rpc_config.commitment,
+            ),
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            http,
+            rate_limiter: Arc::new(RateLimiter::new("jupiter", config.max_rps)),
+            retry_policy: RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(10),
+            },
+            mint_decimals: RwLock::new(HashMap::new()),
+            config,
+        })
+    }
+
+    /// Poll the ladder of every configured pair until the screener is stopped
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Jupiter screener for {:?}, amounts {:?} (poll interval {:?})...",
+            self.config
+                .pairs
+                .iter()
+                .map(|pair| pair.symbol.as_str())
+                .collect::<Vec<_>>(),
+            self.config.amounts,
+            self.poll_interval
+        );
+
+        let dex_writer = Arc::new(DexMarketWriter::for_database(
+            self.db_pool.clone(),
+            EXCHANGE,
+        ));
+        run_poll_loop(
+            &self.shutdown,
+            self.poll_interval,
+            self.max_concurrent_pairs,
+            || {
+                self.config
+                    .pairs
+                    .iter()
+                    .map(|pair| pair.symbol.clone())
+                    .collect()
+            },
+            |symbol| {
+                let screener = self.clone();
+                let dex_writer = dex_writer.clone();
+                async move { screener.poll_pair(&symbol, &dex_writer).await }
+            },
+        )
+        .await;
+        dex_writer.flush().await;
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Quote every ladder amount of `symbol` and persist the quotes through `dex_writer`. A
+    /// failed rung is logged and skipped; the poll fails only when no rung was quoted.
+    async fn poll_pair(&self, symbol: &str, dex_writer: &DexMarketWriter) -> Result<(), String> {
+        let pair = self
+            .config
+            .pairs
+            .iter()
+            .find(|pair| pair.symbol == symbol)
+            .ok_or_else(|| format!("No Jupiter pair configured for {}", symbol))?
+            .clone();
+        let (base_decimals, quote_decimals) = self.pair_decimals(&pair).await?;
+
+        let mut quoted = 0;
+        for &amount in &self.config.amounts {
+            let Some(atoms) = to_atoms(amount, base_decimals) else {
+                warn!(
+                    "Jupiter {} amount {} is not a whole number of atoms",
+                    symbol, amount
+                );
+                continue;
+            };
+            match self.quote_ladder_amount(&pair, atoms).await {
+                Ok(ladder) => {
+                    quoted += 1;
+                    self.save_ladder_quote(
+                        symbol,
+                        &ladder,
+                        base_decimals,
+                        quote_decimals,
+                        dex_writer,
+                    );
+                }
+                Err(e) => {
+                    warn!("Jupiter {} quote of {} failed: {}", symbol, amount, e);
+                    metrics::counter!("jupiter_quote_failures_total", "symbol" => symbol.to_string())
+                        .increment(1);
+                }
+            }
+        }
+        if quoted == 0 {
+            return Err(format!("no Jupiter quote for {}", symbol));
+        }
+        Ok(())
+    }
+
+    /// Sell `amount` base atoms, then buy base back with the quote tokens the sell returns
+    async fn quote_ladder_amount(
+        &self,
+        pair: &JupiterPair,
+        amount: u64,
+    ) -> Result<LadderQuote, String> {
+        let fetched_at = Utc::now();
+        let started = Instant::now();
+        let sell = self
+            .fetch_quote(&pair.base_mint, &pair.quote_mint, amount)
+            .await?;
+        if sell.out_amount == 0 {
+            return Err("sell returns no quote tokens".to_string());
+        }
+        let buy = self
+            .fetch_quote(&pair.quote_mint, &pair.base_mint, sell.out_amount)
+            .await?;
+        Ok(LadderQuote {
+            amount,
+            sell,
+            buy,
+            fetched_at,
+            fetch_latency: started.elapsed(),
+        })
+    }
+
+    /// Exact-in quote, retried with backoff on rate limits, server errors and transport
+    /// failures. Every attempt waits for the shared rate limiter.
+    async fn fetch_quote(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<JupiterQuote, String> {
+        let mut attempt = 1;
+        loop {
+            self.rate_limiter.acquire().await;
+            match self.request_quote(input_mint, output_mint, amount).await {
+                Ok(quote) => return Ok(quote),
+                Err(e) if e.retryable && attempt < self.retry_policy.max_attempts => {
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!(
+                        "Jupiter quote failed on attempt {}/{}: {}, retrying in {:?}",
+                        attempt, self.retry_policy.max_attempts, e.message, delay
+                    );
+                    metrics::counter!("jupiter_quote_retries_total").increment(1);
+                    tokio::select! {
+                        _ = self.shutdown.cancelled() => return Err(e.message),
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.message),
+            }
+        }
+    }
+
+    async fn request_quote(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<JupiterQuote, RequestError> {
+        let url = format!("{}/quote", self.config.api_url.trim_end_matches('/'));
+        let response = self
+            .http
+            .get(&url)
+            .query(&[
+                ("inputMint", input_mint.to_string()),
+                ("outputMint", output_mint.to_string()),
+                ("amount", amount.to_string()),
+                ("slippageBps", SLIPPAGE_BPS.to_string()),
+                ("swapMode", "ExactIn".to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| RequestError {
+                retryable: e.is_timeout() || e.is_connect() || e.is_request(),
+                message: e.to_string(),
+            })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| RequestError {
+            message: e.to_string(),
+            retryable: true,
+        })?;
+        if !status.is_success() {
+            let reason = parse_quote(&body).err().unwrap_or(body);
+            return Err(RequestError {
+                message: format!("HTTP {}: {}", status, reason),
+                retryable: status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            });
+        }
+        parse_quote(&body).map_err(|message| RequestError {
+            message,
+            retryable: false,
+        })
+    }
+
+    /// Decimals of the base and quote mints, read over RPC on first use
+    async fn pair_decimals(&self, pair: &JupiterPair) -> Result<(u32, u32), String> {
+        {
+            let cached = self.mint_decimals.read().unwrap();
+            if let (Some(base), Some(quote)) =
+                (cached.get(&pair.base_mint), cached.get(&pair.quote_mint))
+            {
+                return Ok((*base, *quote));
+            }
+        }
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[pair.base_mint, pair.quote_mint])
+            .await
+            .map_err(|e| format!("Failed to read {} mints: {}", pair.symbol, e))?;
+        let mut decimals = Vec::with_capacity(2);
+        for (mint, account) in [pair.base_mint, pair.quote_mint].iter().zip(&accounts) {
+            let account = account
+                .as_ref()
+                .ok_or_else(|| format!("Mint {} not found", mint))?;
+            decimals.push(
+                mint_decimals(account)
+                    .map_err(|e| format!("Failed to read decimals of mint {}: {}", mint, e))?,
+            );
+        }
+        let [base, quote] = decimals[..] else {
+            return Err(format!("Unexpected number of {} mints", pair.symbol));
+        };
+        let mut cached = self.mint_decimals.write().unwrap();
+        cached.insert(pair.base_mint, base);
+        cached.insert(pair.quote_mint, quote);
+        Ok((base, quote))
+    }
+
+    fn save_ladder_quote(
+        &self,
+        symbol: &str,
+        ladder: &LadderQuote,
+        base_decimals: u32,
+        quote_decimals: u32,
+        dex_writer: &DexMarketWriter,
+    ) {
+        let dex_states = build_dex_states(symbol, ladder, base_decimals, quote_decimals);
+        for dex_state in &dex_states {
+            dex_state.log();
+        }
+        dex_writer.submit(dex_states);
+    }
+}
+
+#[cfg(test)]
+#[path = "jupiter_tests.rs"]
+mod jupiter_tests;
@@ -0,0 +1,256 @@
+use super::*;
+
+const TRUMP: &str = "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN";
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const SOL: &str = "So11111111111111111111111111111111111111112";
+
+/// 10 TRUMP sold for 97.35 USDC on a single Meteora DLMM pool, as `/v6/quote` returns it
+const SINGLE_HOP_QUOTE: &str = r#"{
+    "inputMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN",
+    "inAmount": "10000000",
+    "outputMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    "outAmount": "97350000",
+    "otherAmountThreshold": "96863250",
+    "swapMode": "ExactIn",
+    "slippageBps": 50,
+    "platformFee": null,
+    "priceImpactPct": "0.0012",
+    "routePlan": [
+        {
+            "swapInfo": {
+                "ammKey": "9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2",
+                "label": "Meteora DLMM",
+                "inputMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN",
+                "outputMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                "inAmount": "10000000",
+                "outAmount": "97350000",
+                "feeAmount": "50000",
+                "feeMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN"
+            },
+            "percent": 100
+        }
+    ],
+    "contextSlot": 312345678,
+    "timeTaken": 0.0123
+}"#;
+
+/// 10 TRUMP split 70/30 into SOL across two pools, then SOL into USDC on one
+const MULTI_HOP_QUOTE: &str = r#"{
+    "inputMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN",
+    "inAmount": "10000000",
+    "outputMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    "outAmount": "97300000",
+    "otherAmountThreshold": "96813500",
+    "swapMode": "ExactIn",
+    "slippageBps": 50,
+    "platformFee": null,
+    "priceImpactPct": "0.00034",
+    "routePlan": [
+        {
+            "swapInfo": {
+                "ammKey": "9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2",
+                "label": "Meteora DLMM",
+                "inputMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN",
+                "outputMint": "So11111111111111111111111111111111111111112",
+                "inAmount": "7000000",
+                "outAmount": "350000000",
+                "feeAmount": "7000",
+                "feeMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN"
+            },
+            "percent": 70
+        },
+        {
+            "swapInfo": {
+                "ammKey": "4DoNfFBfF7UokCC2FQzriy7yHK6DY6NVdYpuekQ5pRgg",
+                "label": "Raydium CLMM",
+                "inputMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN",
+                "outputMint": "So11111111111111111111111111111111111111112",
+                "inAmount": "3000000",
+                "outAmount": "149900000",
+                "feeAmount": "750",
+                "feeMint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN"
+            },
+            "percent": 30
+        },
+        {
+            "swapInfo": {
+                "ammKey": "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+                "label": "Whirlpool",
+                "inputMint": "So11111111111111111111111111111111111111112",
+                "outputMint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                "inAmount": "499900000",
+                "outAmount": "97300000",
+                "feeAmount": "24995",
+                "feeMint": "So11111111111111111111111111111111111111112"
+            },
+            "percent": 100
+        }
+    ],
+    "contextSlot": 312345680,
+    "timeTaken": 0.041
+}"#;
+
+fn pubkey(value: &str) -> Pubkey {
+    Pubkey::from_str(value).unwrap()
+}
+
+#[test]
+fn parse_quote_reads_a_single_hop_quote() {
+    let quote = parse_quote(SINGLE_HOP_QUOTE).unwrap();
+
+    assert_eq!(quote.input_mint, pubkey(TRUMP));
+    assert_eq!(quote.output_mint, pubkey(USDC));
+    assert_eq!(quote.in_amount, 10_000_000);
+    assert_eq!(quote.out_amount, 97_350_000);
+    assert_eq!(quote.context_slot, 312_345_678);
+    assert_eq!(quote.price_impact_bps(), Decimal::from(12));
+    assert_eq!(
+        quote.route_plan,
+        vec![RouteStep {
+            amm_key: pubkey("9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2"),
+            label: "Meteora DLMM".to_string(),
+            input_mint: pubkey(TRUMP),
+            output_mint: pubkey(USDC),
+            in_amount: 10_000_000,
+            out_amount: 97_350_000,
+            percent: 100,
+        }]
+    );
+    assert_eq!(quote.route_summary(), "Meteora DLMM 100%");
+}
+
+#[test]
+fn parse_quote_reads_split_and_multi_hop_route_plans() {
+    let quote = parse_quote(MULTI_HOP_QUOTE).unwrap();
+
+    assert_eq!(quote.route_plan.len(), 3);
+    assert_eq!(quote.route_plan[1].percent, 30);
+    assert_eq!(quote.route_plan[1].output_mint, pubkey(SOL));
+    assert_eq!(quote.route_plan[2].input_mint, pubkey(SOL));
+    assert_eq!(quote.route_plan[2].in_amount, 499_900_000);
+    assert_eq!(
+        quote.route_summary(),
+        "Meteora DLMM 70% + Raydium CLMM 30% > Whirlpool 100%"
+    );
+    assert_eq!(quote.price_impact_bps(), "3.4".parse::<Decimal>().unwrap());
+}
+
+#[test]
+fn parse_quote_surfaces_api_errors() {
+    let error = parse_quote(
+        r#"{"error":"Could not find any route","errorCode":"COULD_NOT_FIND_ANY_ROUTE"}"#,
+    )
+    .unwrap_err();
+
+    assert_eq!(error, "Could not find any route (COULD_NOT_FIND_ANY_ROUTE)");
+    assert_eq!(
+        parse_quote(r#"{"error":"Rate limit exceeded"}"#).unwrap_err(),
+        "Rate limit exceeded"
+    );
+}
+
+#[test]
+fn parse_quote_rejects_malformed_quotes() {
+    let bad_amount =
+        SINGLE_HOP_QUOTE.replace(r#""outAmount": "97350000","#, r#""outAmount": "-1","#);
+    let bad_mint = SINGLE_HOP_QUOTE.replacen(TRUMP, "not-a-mint", 1);
+    let empty_route = r#"{"inputMint":"6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN","inAmount":"1","outputMint":"EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v","outAmount":"1","priceImpactPct":"0","routePlan":[]}"#;
+
+    assert!(parse_quote(&bad_amount).is_err());
+    assert!(parse_quote(&bad_mint).is_err());
+    assert!(parse_quote(empty_route).is_err());
+    assert!(parse_quote("<html>Bad Gateway</html>").is_err());
+}
+
+#[test]
+fn build_dex_states_prices_both_directions_per_base_token() {
+    let sell = parse_quote(MULTI_HOP_QUOTE).unwrap();
+    // 97.3 USDC buy back 9.95 TRUMP
+    let buy = JupiterQuote {
+        input_mint: pubkey(USDC),
+        output_mint: pubkey(TRUMP),
+        in_amount: 97_300_000,
+        out_amount: 9_950_000,
+        price_impact: "0.0005".parse().unwrap(),
+        route_plan: vec![RouteStep {
+            amm_key: pubkey("9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2"),
+            label: "Meteora DLMM".to_string(),
+            input_mint: pubkey(USDC),
+            output_mint: pubkey(TRUMP),
+            in_amount: 97_300_000,
+            out_amount: 9_950_000,
+            percent: 100,
+        }],
+        context_slot: 312_345_681,
+    };
+    let ladder = LadderQuote {
+        amount: 10_000_000,
+        sell,
+        buy,
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(250),
+    };
+
+    let states = build_dex_states("TRUMPUSDC", &ladder, 6, 6);
+
+    assert_eq!(states.len(), 2);
+    let (sell, buy) = (&states[0], &states[1]);
+    assert_eq!(sell.trade_id, "TRUMPUSDC:312345680:sell:10000000");
+    assert_eq!(sell.exchange, "jupiter");
+    assert_eq!(sell.price, "9.73".parse::<Decimal>().unwrap());
+    assert_eq!(sell.volume, Decimal::TEN);
+    assert_eq!(sell.block_number, 312_345_680);
+    assert_eq!(
+        sell.route_plan.as_deref(),
+        Some("Meteora DLMM 70% + Raydium CLMM 30% > Whirlpool 100%")
+    );
+    assert_eq!(sell.fetch_latency_ms, Some(250));
+    assert_eq!(buy.trade_id, "TRUMPUSDC:312345681:buy:10000000");
+    assert_eq!(buy.volume, "9.95".parse::<Decimal>().unwrap());
+    assert!(buy.price > sell.price);
+    assert_eq!(buy.price_impact_bps, Some(Decimal::from(5)));
+}
+
+#[test]
+fn to_atoms_scales_whole_tokens() {
+    assert_eq!(to_atoms(Decimal::TEN, 6), Some(10_000_000));
+    assert_eq!(to_atoms("0.5".parse().unwrap(), 9), Some(500_000_000));
+    assert_eq!(to_atoms("0.0000001".parse().unwrap(), 6), None);
+    assert_eq!(to_atoms(Decimal::from(u64::MAX), 9), None);
+}
+
+#[test]
+fn parse_pairs_reads_symbol_and_mints() {
+    let pairs = parse_pairs(&format!("trumpusdc:{}:{}", TRUMP, USDC)).unwrap();
+
+    assert_eq!(
+        pairs,
+        vec![JupiterPair {
+            symbol: "TRUMPUSDC".to_string(),
+            base_mint: pubkey(TRUMP),
+            quote_mint: pubkey(USDC),
+        }]
+    );
+    assert!(parse_pairs("").is_err());
+    assert!(parse_pairs(&format!("TRUMPUSDC:{}", TRUMP)).is_err());
+    assert!(parse_pairs(&format!("TRUMPUSDC:{}:{}", TRUMP, TRUMP)).is_err());
+    assert!(parse_pairs(&format!("TRUMPXYZ:{}:{}", TRUMP, USDC)).is_err());
+    assert!(
+        parse_pairs(&format!(
+            "TRUMPUSDC:{}:{},TRUMPUSDC:{}:{}",
+            TRUMP, USDC, TRUMP, SOL
+        ))
+        .is_err()
+    );
+}
+
+#[test]
+fn parse_amounts_sorts_the_ladder() {
+    assert_eq!(
+        parse_amounts("100, 1,10,1.0").unwrap(),
+        vec![Decimal::ONE, Decimal::TEN, Decimal::from(100)]
+    );
+    assert!(parse_amounts("").is_err());
+    assert!(parse_amounts("1,0").is_err());
+    assert!(parse_amounts("1,abc").is_err());
+}
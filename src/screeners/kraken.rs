@@ -0,0 +1,610 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "kraken";
+/// Symbols streamed when `KRAKEN_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSD";
+/// Websocket v2 URL when `KRAKEN_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://ws.kraken.com/v2";
+/// Levels per side subscribed when `KRAKEN_BOOK_DEPTH` is unset
+const DEFAULT_BOOK_DEPTH: usize = 10;
+/// Depths the book channel accepts
+const SUPPORTED_DEPTHS: [usize; 5] = [10, 25, 100, 500, 1000];
+/// Levels per side covered by the Kraken checksum
+const CHECKSUM_LEVELS: usize = 10;
+/// Silence after which the connection is considered dead; heartbeats arrive every second
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Symbols, endpoint and depth of the Kraken screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct KrakenConfig {
+    /// Internal symbols such as `TRUMPUSD`
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+    /// Levels per side of the subscribed books
+    pub depth: usize,
+}
+
+impl KrakenConfig {
+    /// Read `KRAKEN_SYMBOLS` (comma-separated internal symbols), `KRAKEN_WS_URL` and
+    /// `KRAKEN_BOOK_DEPTH`, falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("KRAKEN_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        let depth = match std::env::var("KRAKEN_BOOK_DEPTH") {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|depth| SUPPORTED_DEPTHS.contains(depth))
+                .ok_or_else(|| {
+                    format!(
+                        "KRAKEN_BOOK_DEPTH `{}` is not one of {:?}",
+                        value, SUPPORTED_DEPTHS
+                    )
+                })?,
+            Err(_) => DEFAULT_BOOK_DEPTH,
+        };
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("KRAKEN_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            depth,
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to a Kraken symbol
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "KRAKEN_SYMBOLS entry `{}` has no Kraken symbol (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("KRAKEN_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// Subscription to the instrument channel, which carries the precision of every pair
+fn instrument_request() -> String {
+    json!({"method": "subscribe", "params": {"channel": "instrument", "snapshot": true}})
+        .to_string()
+}
+
+/// `subscribe` or `unsubscribe` request of the books of `symbols`
+fn book_request(method: &str, symbols: &[String], depth: usize) -> String {
+    let pairs: Vec<String> = symbols
+        .iter()
+        .filter_map(|symbol| to_venue_symbol(EXCHANGE, symbol))
+        .collect();
+    let mut params = json!({"channel": "book", "symbol": pairs});
+    if method == "subscribe" {
+        params["depth"] = json!(depth);
+    }
+    json!({"method": method, "params": params}).to_string()
+}
+
+/// Decimal places Kraken quotes a pair with; the book checksum covers values at this scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Precision {
+    price: u32,
+    qty: u32,
+}
+
+/// Price levels as `(price, quantity)`; a zero quantity removes the level
+type Levels = Vec<(Decimal, Decimal)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookAction {
+    Snapshot,
+    Update,
+}
+
+/// Snapshot or update of one book
+#[derive(Debug, Clone, PartialEq)]
+struct BookMessage {
+    /// Internal symbol of the pair
+    symbol: String,
+    action: BookAction,
+    bids: Levels,
+    asks: Levels,
+    /// CRC32 of the top 10 levels after the message is applied
+    checksum: u32,
+    /// Exchange time of updates; snapshots carry none
+    timestamp: Option<DateTime<Utc>>,
+}
+
+/// Decoded text frame of the websocket
+#[derive(Debug, Clone, PartialEq)]
+enum KrakenFrame {
+    Books(Vec<BookMessage>),
+    /// Precision of the pairs in an instrument message, by internal symbol
+    Instruments(HashMap<String, Precision>),
+    /// Rejected request
+    Error(String),
+    Other,
+}
+
+/// Decimal of a JSON number. Kraken v2 sends numbers, not strings, so trailing zeros are
+/// lost until the value is rescaled to the pair's precision. HTX sends numbers as well.
+pub(super) fn parse_number(value: &Value) -> Result<Decimal, String> {
+    let number = value
+        .as_number()
+        .ok_or_else(|| format!("{} is not a number", value))?;
+    let text = number.to_string();
+    text.parse()
+        .or_else(|_| Decimal::from_scientific(&text))
+        .map_err(|e| format!("malformed number {}: {}", text, e))
+}
+
+fn parse_levels(value: &Value) -> Result<Levels, String> {
+    let levels = value.as_array().ok_or("levels are not an array")?;
+    levels
+        .iter()
+        .map(|level| Ok((parse_number(&level["price"])?, parse_number(&level["qty"])?)))
+        .collect()
+}
+
+fn parse_book(data: &Value, action: BookAction) -> Result<BookMessage, String> {
+    let pair = data["symbol"].as_str().ok_or("missing `symbol`")?;
+    Ok(BookMessage {
+        symbol: from_venue_symbol(EXCHANGE, pair)
+            .ok_or_else(|| format!("unexpected symbol {}", pair))?,
+        action,
+        bids: parse_levels(&data["bids"])?,
+        asks: parse_levels(&data["asks"])?,
+        checksum: data["checksum"]
+            .as_u64()
+            .and_then(|checksum| u32::try_from(checksum).ok())
+            .ok_or("missing `checksum`")?,
+        timestamp: data["timestamp"]
+            .as_str()
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc)),
+    })
+}
+
+fn parse_instruments(data: &Value) -> Result<HashMap<String, Precision>, String> {
+    let pairs = data["pairs"].as_array().ok_or("missing `pairs`")?;
+    let mut precisions = HashMap::new();
+    for pair in pairs {
+        let Some(symbol) = pair["symbol"]
+            .as_str()
+            .and_then(|pair| from_venue_symbol(EXCHANGE, pair))
+        else {
+            continue;
+        };
+        let decimals = |field: &str| -> Result<u32, String> {
+            pair[field]
+                .as_u64()
+                .and_then(|decimals| u32::try_from(decimals).ok())
+                .ok_or_else(|| format!("missing `{}` of {}", field, symbol))
+        };
+        let precision = Precision {
+            price: decimals("price_precision")?,
+            qty: decimals("qty_precision")?,
+        };
+        precisions.insert(symbol, precision);
+    }
+    Ok(precisions)
+}
+
+fn parse_frame(text: &str) -> Result<KrakenFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if frame["success"] == false {
+        return Ok(KrakenFrame::Error(
+            frame["error"].as_str().unwrap_or_default().to_string(),
+        ));
+    }
+    let action = match frame["type"].as_str() {
+        Some("snapshot") => BookAction::Snapshot,
+        Some("update") => BookAction::Update,
+        _ => return Ok(KrakenFrame::Other),
+    };
+    match frame["channel"].as_str() {
+        Some("book") => {
+            let data = frame["data"].as_array().ok_or("missing book data")?;
+            let books = data
+                .iter()
+                .map(|book| parse_book(book, action))
+                .collect::<Result<_, _>>()?;
+            Ok(KrakenFrame::Books(books))
+        }
+        Some("instrument") => Ok(KrakenFrame::Instruments(parse_instruments(&frame["data"])?)),
+        _ => Ok(KrakenFrame::Other),
+    }
+}
+
+/// Digits of `value` at `decimals` places without the decimal point and leading zeros, as
+/// the checksum spells a price or quantity
+fn checksum_digits(value: Decimal, decimals: u32) -> String {
+    let mut value = value;
+    value.rescale(decimals);
+    value
+        .to_string()
+        .replace('.', "")
+        .trim_start_matches('0')
+        .to_string()
+}
+
+/// Kraken checksum of a book: CRC32 of the best 10 asks, then the best 10 bids, each level
+/// spelled as its price then quantity digits at the pair's precision
+fn checksum(orderbook: &market::OrderBook, precision: Precision) -> u32 {
+    let asks = orderbook.asks.iter().take(CHECKSUM_LEVELS);
+    let bids = orderbook.bids.iter().rev().take(CHECKSUM_LEVELS);
+    let mut text = String::new();
+    for (price, qty) in asks.chain(bids) {
+        text.push_str(&checksum_digits(*price, precision.price));
+        text.push_str(&checksum_digits(*qty, precision.qty));
+    }
+    crc32fast::hash(text.as_bytes())
+}
+
+/// Apply levels rescaled to the pair's precision, so the book holds the values as Kraken
+/// quotes them
+fn apply_levels(levels: &mut market::OrderBookLevels, updates: &Levels, precision: Precision) {
+    for (price, qty) in updates {
+        let (mut price, mut qty) = (*price, *qty);
+        price.rescale(precision.price);
+        qty.rescale(precision.qty);
+        levels.remove(&price);
+        if !qty.is_zero() {
+            levels.insert(price, qty);
+        }
+    }
+}
+
+/// Result of feeding a message to a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BookOutcome {
+    Applied,
+    /// Update of a book waiting for its snapshot
+    Ignored,
+    /// The book was dropped and needs a new snapshot
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+/// Local book of a symbol
+#[derive(Debug, Clone)]
+struct SymbolBook {
+    orderbook: market::OrderBook,
+    /// Known once the instrument snapshot arrived; the book is subscribed after it
+    precision: Option<Precision>,
+    /// Whether the snapshot arrived; updates before it are ignored
+    synced: bool,
+    /// Messages applied since the snapshot, stored as the state's trade id since Kraken
+    /// books carry no sequence number
+    applied: u64,
+    /// Best bid and ask of the last persisted state
+    last_top: Option<(market::OrderBookItem, market::OrderBookItem)>,
+}
+
+impl SymbolBook {
+    fn new(symbol: &str) -> Self {
+        Self {
+            orderbook: market::OrderBook::new(EXCHANGE, symbol),
+            precision: None,
+            synced: false,
+            applied: 0,
+            last_top: None,
+        }
+    }
+
+    /// Drop the book until the next snapshot
+    fn reset(&mut self) {
+        self.orderbook.bids.clear();
+        self.orderbook.asks.clear();
+        self.synced = false;
+        self.last_top = None;
+    }
+
+    /// Apply a message, keeping the best `depth` levels per side, and verify its checksum
+    fn on_message(&mut self, message: &BookMessage, depth: usize) -> BookOutcome {
+        let Some(precision) = self.precision else {
+            return BookOutcome::Ignored;
+        };
+        match message.action {
+            BookAction::Snapshot => {
+                self.orderbook.bids.clear();
+                self.orderbook.asks.clear();
+                self.synced = true;
+                self.applied = 0;
+                self.last_top = None;
+            }
+            BookAction::Update if !self.synced => return BookOutcome::Ignored,
+            BookAction::Update => {}
+        }
+        apply_levels(&mut self.orderbook.bids, &message.bids, precision);
+        apply_levels(&mut self.orderbook.asks, &message.asks, precision);
+        // Levels pushed out of the subscribed depth get no further updates
+        while self.orderbook.bids.len() > depth {
+            self.orderbook.bids.pop_first();
+        }
+        while self.orderbook.asks.len() > depth {
+            self.orderbook.asks.pop_last();
+        }
+
+        let actual = checksum(&self.orderbook, precision);
+        if actual != message.checksum {
+            self.reset();
+            return BookOutcome::ChecksumMismatch {
+                expected: message.checksum,
+                actual,
+            };
+        }
+        self.orderbook.last_update_ts = Utc::now();
+        self.applied += 1;
+        BookOutcome::Applied
+    }
+}
+
+/// Kraken screener keeping a checksum-verified local book per symbol from the websocket v2
+/// `book` channel, persisted as CEX market states
+pub struct KrakenScreener {
+    config: KrakenConfig,
+    shutdown: CancellationToken,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl KrakenScreener {
+    /// Create a new KrakenScreener instance on the symbols of `KRAKEN_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, KrakenConfig::from_env()?))
+    }
+
+    pub fn with_config(db_pool: DatabasePool, config: KrakenConfig) -> Self {
+        let cex_writer = CexMarketWriter::for_database(db_pool, "kraken");
+        Self {
+            config,
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Kraken", EXCHANGE, STALE_FEED_TIMEOUT),
+        }
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from the new subscription's snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Kraken screener for {:?} ({}, depth {})...",
+            self.config.symbols, self.config.ws_url, self.config.depth
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("Kraken screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for its precision and a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(symbol)))
+            .collect();
+    }
+
+    /// Apply one text frame, adding the requests it calls for to `requests`; returns
+    /// whether it was a book message
+    fn handle_frame(&self, text: &str, requests: &mut Vec<String>) -> bool {
+        let messages = match parse_frame(text) {
+            Ok(KrakenFrame::Books(messages)) => messages,
+            Ok(KrakenFrame::Instruments(precisions)) => {
+                self.set_precisions(precisions, requests);
+                return false;
+            }
+            Ok(KrakenFrame::Error(message)) => {
+                error!("Kraken websocket error: {}", message);
+                return false;
+            }
+            Ok(KrakenFrame::Other) => {
+                debug!("Skipping Kraken frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed Kraken frame: {}", e);
+                return false;
+            }
+        };
+
+        let mut resubscribe = Vec::new();
+        let mut books = self.books.lock().unwrap();
+        for message in messages {
+            // Messages of a symbol that is not streamed cannot be tracked
+            let Some(book) = books.get_mut(&message.symbol) else {
+                continue;
+            };
+            match book.on_message(&message, self.config.depth) {
+                BookOutcome::Applied => {
+                    if let Err(violation) = book.orderbook.validate() {
+                        error!(
+                            "Kraken {} order book rejected, {}: {}",
+                            message.symbol,
+                            violation,
+                            book.orderbook.describe_top(5)
+                        );
+                        metrics::counter!(
+                            "kraken_orderbook_resyncs_total",
+                            "symbol" => message.symbol.clone(),
+                            "reason" => violation.kind()
+                        )
+                        .increment(1);
+                        book.reset();
+                        resubscribe.push(message.symbol);
+                    } else {
+                        self.persist(book, message.timestamp);
+                    }
+                }
+                BookOutcome::ChecksumMismatch { expected, actual } => {
+                    warn!(
+                        "Kraken {} order book checksum {} instead of {}, resubscribing",
+                        message.symbol, actual, expected
+                    );
+                    metrics::counter!(
+                        "kraken_orderbook_resyncs_total",
+                        "symbol" => message.symbol.clone(),
+                        "reason" => "checksum"
+                    )
+                    .increment(1);
+                    resubscribe.push(message.symbol);
+                }
+                BookOutcome::Ignored => {}
+            }
+        }
+        if !resubscribe.is_empty() {
+            // The new subscription starts with a snapshot
+            for method in ["unsubscribe", "subscribe"] {
+                requests.push(book_request(method, &resubscribe, self.config.depth));
+            }
+        }
+        true
+    }
+
+    /// Record the precision of the streamed pairs and subscribe the books that just got it
+    fn set_precisions(&self, precisions: HashMap<String, Precision>, requests: &mut Vec<String>) {
+        let mut books = self.books.lock().unwrap();
+        let mut subscribe = Vec::new();
+        for (symbol, book) in books.iter_mut() {
+            let Some(precision) = precisions.get(symbol) else {
+                continue;
+            };
+            if book.precision.replace(*precision).is_none() {
+                subscribe.push(symbol.clone());
+            }
+        }
+        if subscribe.is_empty() {
+            return;
+        }
+        subscribe.sort();
+        info!("Kraken precisions received, subscribing to {:?}", subscribe);
+        requests.push(book_request("subscribe", &subscribe, self.config.depth));
+    }
+
+    /// Persist the top of book when it changed
+    fn persist(&self, book: &mut SymbolBook, timestamp: Option<DateTime<Utc>>) {
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: book.applied.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: book.orderbook.symbol.clone(),
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: timestamp.unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: timestamp
+                .map(|timestamp| (now - timestamp).num_milliseconds().max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for KrakenScreener {
+    /// Empty the books, connect, subscribe to the instruments, then to the books once their
+    /// precision is known, and apply the stream until the connection drops or the screener stops.
+    /// `feed` counts the book messages delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        ws.send(Message::Text(instrument_request()))
+            .await
+            .map_err(|e| format!("subscribe failed: {}", e))?;
+        info!("Kraken websocket connected");
+
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            let mut requests = Vec::new();
+                            if self.handle_frame(&text, &mut requests) {
+                                feed.delivered();
+                            }
+                            for request in requests {
+                                ws.send(Message::Text(request))
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "kraken_tests.rs"]
+mod kraken_tests;
@@ -0,0 +1,248 @@
+use super::*;
+use std::str::FromStr;
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Book of Kraken's published checksum example (BTC/USD, 1 price and 8 quantity decimals)
+const CHECKSUM_EXAMPLE: &str = r#"{"channel":"book","type":"snapshot","data":[{"symbol":"BTC/USD","bids":[{"price":45283.5,"qty":0.10000000},{"price":45283.4,"qty":1.54582015},{"price":45282.1,"qty":0.10000000},{"price":45281.0,"qty":0.10000000},{"price":45280.3,"qty":1.54592586},{"price":45279.0,"qty":0.07990000},{"price":45277.6,"qty":0.03310103},{"price":45277.5,"qty":0.30000000},{"price":45277.3,"qty":1.54602737},{"price":45276.6,"qty":0.15445238}],"asks":[{"price":45285.2,"qty":0.00100000},{"price":45286.4,"qty":1.54571953},{"price":45286.6,"qty":1.54571109},{"price":45289.6,"qty":1.54560911},{"price":45290.2,"qty":0.15890660},{"price":45291.8,"qty":1.54553491},{"price":45294.7,"qty":0.04454749},{"price":45296.1,"qty":0.35380000},{"price":45297.5,"qty":0.09945542},{"price":45299.5,"qty":0.18772827}],"checksum":3310070434}]}"#;
+
+const BTC_PRECISION: Precision = Precision { price: 1, qty: 8 };
+
+/// Instrument snapshot, trimmed to two pairs
+const INSTRUMENT_FIXTURE: &str = r#"{"channel":"instrument","type":"snapshot","data":{"assets":[{"id":"USD","status":"enabled","precision":4,"precision_display":2,"borrowable":true,"collateral_value":1.0,"margin_rate":0.025}],"pairs":[{"symbol":"TRUMP/USD","base":"TRUMP","quote":"USD","status":"online","qty_precision":8,"qty_increment":0.00000001,"price_precision":3,"cost_precision":5,"marginable":false,"has_index":true,"cost_min":0.5,"tick_size":0.001,"price_increment":0.001,"qty_min":0.5},{"symbol":"BTC/USD","base":"BTC","quote":"USD","status":"online","qty_precision":8,"qty_increment":0.00000001,"price_precision":1,"cost_precision":5,"marginable":true,"has_index":true,"cost_min":0.5,"tick_size":0.1,"price_increment":0.1,"qty_min":0.0001}]}}"#;
+
+/// Book messages recorded in order for TRUMP/USD, with their checksums
+const BOOK_FIXTURES: [&str; 3] = [
+    r#"{"channel":"book","type":"snapshot","data":[{"symbol":"TRUMP/USD","bids":[{"price":10.25,"qty":120.5},{"price":10.24,"qty":300.0},{"price":10.2,"qty":15.0}],"asks":[{"price":10.27,"qty":80.0},{"price":10.28,"qty":150.25},{"price":10.3,"qty":5.0}],"checksum":2998901550}]}"#,
+    r#"{"channel":"book","type":"update","data":[{"symbol":"TRUMP/USD","bids":[{"price":10.25,"qty":100.0}],"asks":[{"price":10.26,"qty":5.0}],"checksum":1667386335,"timestamp":"2024-05-28T02:35:19.200000Z"}]}"#,
+    r#"{"channel":"book","type":"update","data":[{"symbol":"TRUMP/USD","bids":[{"price":10.23,"qty":50.0}],"asks":[{"price":10.26,"qty":0.0}],"checksum":1600685721,"timestamp":"2024-05-28T02:35:19.300000Z"}]}"#,
+];
+
+fn book_message(text: &str) -> BookMessage {
+    match parse_frame(text).unwrap() {
+        KrakenFrame::Books(mut messages) => messages.remove(0),
+        other => panic!("not a book message: {:?}", other),
+    }
+}
+
+fn synced_btc_book() -> SymbolBook {
+    let mut book = SymbolBook::new("BTCUSD");
+    book.precision = Some(BTC_PRECISION);
+    assert_eq!(
+        book.on_message(&book_message(CHECKSUM_EXAMPLE), 10),
+        BookOutcome::Applied
+    );
+    book
+}
+
+impl TestScreener for KrakenScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = KrakenScreener {
+            config: KrakenConfig {
+                symbols: vec!["TRUMPUSD".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+                depth: 10,
+            },
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("Kraken", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn checksum_matches_the_kraken_test_vector() {
+    let book = synced_btc_book();
+
+    assert_eq!(checksum(&book.orderbook, BTC_PRECISION), 3310070434);
+    // 45281.0 and 0.10000000 arrive as 45281 and 0.1, the book keeps the quoted precision
+    assert_eq!(
+        book.orderbook.bids[&decimal("45281")].to_string(),
+        "0.10000000"
+    );
+    assert!(
+        book.orderbook
+            .bids
+            .keys()
+            .any(|price| price.to_string() == "45281.0")
+    );
+}
+
+#[test]
+fn checksum_digits_drop_the_point_and_leading_zeros() {
+    assert_eq!(checksum_digits(decimal("0.001"), 8), "100000");
+    assert_eq!(checksum_digits(decimal("45281"), 1), "452810");
+    assert_eq!(checksum_digits(decimal("0.0799"), 8), "7990000");
+}
+
+#[test]
+fn books_are_truncated_to_the_subscribed_depth() {
+    let mut book = synced_btc_book();
+    let update = BookMessage {
+        symbol: "BTCUSD".to_string(),
+        action: BookAction::Update,
+        bids: vec![(decimal("45283.6"), decimal("0.5"))],
+        asks: Vec::new(),
+        checksum: 1267292553,
+        timestamp: None,
+    };
+
+    assert_eq!(book.on_message(&update, 10), BookOutcome::Applied);
+    assert_eq!(book.orderbook.bids.len(), 10);
+    assert!(!book.orderbook.bids.contains_key(&decimal("45276.6")));
+}
+
+#[test]
+fn checksum_mismatch_drops_the_book() {
+    let mut book = synced_btc_book();
+    let mut update = book_message(CHECKSUM_EXAMPLE);
+    update.action = BookAction::Update;
+    update.bids = vec![(decimal("45283.5"), decimal("0.2"))];
+    update.asks = Vec::new();
+
+    assert!(matches!(
+        book.on_message(&update, 10),
+        BookOutcome::ChecksumMismatch {
+            expected: 3310070434,
+            ..
+        }
+    ));
+    assert!(!book.synced && book.orderbook.bids.is_empty());
+    assert_eq!(book.on_message(&update, 10), BookOutcome::Ignored);
+}
+
+#[test]
+fn frames_are_decoded_with_internal_symbols() {
+    let update = book_message(BOOK_FIXTURES[1]);
+    assert_eq!(
+        update,
+        BookMessage {
+            symbol: "TRUMPUSD".to_string(),
+            action: BookAction::Update,
+            bids: vec![(decimal("10.25"), decimal("100"))],
+            asks: vec![(decimal("10.26"), decimal("5"))],
+            checksum: 1667386335,
+            timestamp: Some("2024-05-28T02:35:19.200Z".parse().unwrap()),
+        }
+    );
+
+    assert_eq!(
+        parse_frame(INSTRUMENT_FIXTURE),
+        Ok(KrakenFrame::Instruments(HashMap::from([
+            ("TRUMPUSD".to_string(), Precision { price: 3, qty: 8 }),
+            ("BTCUSD".to_string(), BTC_PRECISION),
+        ])))
+    );
+    assert_eq!(
+        parse_frame(r#"{"channel":"heartbeat"}"#),
+        Ok(KrakenFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(
+            r#"{"error":"Currency pair not supported NOPE/USD","method":"subscribe","success":false,"symbol":"NOPE/USD","time_in":"2024-05-28T02:35:19.100000Z","time_out":"2024-05-28T02:35:19.100100Z"}"#
+        ),
+        Ok(KrakenFrame::Error(
+            "Currency pair not supported NOPE/USD".to_string()
+        ))
+    );
+    assert!(parse_frame(&BOOK_FIXTURES[1].replace("10.26", r#""10.26""#)).is_err());
+}
+
+#[test]
+fn symbols_and_requests_use_kraken_symbols() {
+    assert_eq!(
+        parse_symbols("trumpusd,TRUMPUSDC").unwrap(),
+        ["TRUMPUSD", "TRUMPUSDC"]
+    );
+    assert!(parse_symbols("TRUMP").is_err());
+
+    let subscribe: Value =
+        serde_json::from_str(&book_request("subscribe", &["TRUMPUSD".to_string()], 25)).unwrap();
+    assert_eq!(
+        subscribe,
+        json!({"method": "subscribe", "params": {"channel": "book", "symbol": ["TRUMP/USD"], "depth": 25}})
+    );
+    let unsubscribe: Value =
+        serde_json::from_str(&book_request("unsubscribe", &["TRUMPUSD".to_string()], 25)).unwrap();
+    assert_eq!(
+        unsubscribe["params"],
+        json!({"channel": "book", "symbol": ["TRUMP/USD"]})
+    );
+}
+
+#[tokio::test]
+async fn recorded_session_is_replayed_into_kraken_states() {
+    let (screener, sink) = build_screener_with_sink::<KrakenScreener>();
+    let mut requests = Vec::new();
+
+    // Books are only subscribed once their precision is known
+    assert!(!screener.handle_frame(INSTRUMENT_FIXTURE, &mut requests));
+    assert_eq!(
+        requests,
+        [book_request("subscribe", &["TRUMPUSD".to_string()], 10)]
+    );
+    requests.clear();
+    screener.handle_frame(INSTRUMENT_FIXTURE, &mut requests);
+    assert!(requests.is_empty());
+
+    for fixture in BOOK_FIXTURES {
+        assert!(screener.handle_frame(fixture, &mut requests));
+        screener.cex_writer.flush().await;
+    }
+
+    assert!(requests.is_empty());
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "kraken" && state.trade_pair == "TRUMPUSD")
+    );
+    assert_eq!(
+        (
+            states[0].bid_price.to_string(),
+            states[0].bid_volume.to_string()
+        ),
+        ("10.250".to_string(), "120.50000000".to_string())
+    );
+    assert_eq!(states[1].ask_price, decimal("10.26"));
+    assert_eq!(states[2].ask_price, decimal("10.27"));
+    assert_eq!(
+        states[2].trade_time,
+        "2024-05-28T02:35:19.300Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    let book = screener.books.lock().unwrap()["TRUMPUSD"].clone();
+    assert_eq!(
+        checksum(&book.orderbook, Precision { price: 3, qty: 8 }),
+        1600685721
+    );
+}
+
+#[tokio::test]
+async fn checksum_mismatch_resubscribes_the_pair() {
+    let (screener, sink) = build_screener_with_sink::<KrakenScreener>();
+    let mut requests = Vec::new();
+    screener.handle_frame(INSTRUMENT_FIXTURE, &mut requests);
+    requests.clear();
+
+    screener.handle_frame(BOOK_FIXTURES[0], &mut requests);
+    screener.handle_frame(BOOK_FIXTURES[2], &mut requests);
+
+    let symbols = ["TRUMPUSD".to_string()];
+    assert_eq!(
+        requests,
+        [
+            book_request("unsubscribe", &symbols, 10),
+            book_request("subscribe", &symbols, 10)
+        ]
+    );
+    screener.cex_writer.flush().await;
+    assert_eq!(sink.states.lock().unwrap().len(), 1);
+}
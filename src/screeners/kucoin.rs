@@ -0,0 +1,599 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::depth_sync::{
+    BookSync, DepthSnapshot, DepthUpdate, Levels, SnapshotOutcome, SymbolBook, UpdateOutcome,
+    parse_levels,
+};
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "kucoin";
+/// Symbols streamed when `KUCOIN_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDT";
+/// REST base URL when `KUCOIN_REST_URL` is unset
+const DEFAULT_REST_URL: &str = "https://api.kucoin.com";
+/// Levels per side of the REST depth snapshot when `KUCOIN_SNAPSHOT_DEPTH` is unset
+const DEFAULT_SNAPSHOT_DEPTH: u32 = 100;
+/// Depths of the public partial order book endpoints
+const SNAPSHOT_DEPTHS: [u32; 2] = [20, 100];
+/// `code` of a successful REST reply
+const SUCCESS_CODE: &str = "200000";
+/// Timeout of a REST request
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a snapshot older than the buffered updates is fetched again
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Silence after which the connection is considered dead, until the token names the ping
+/// interval and timeout of the server
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Symbols and endpoints of the KuCoin screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct KuCoinConfig {
+    /// Internal symbols such as `TRUMPUSDT`, streamed as `TRUMP-USDT`
+    pub symbols: Vec<String>,
+    /// REST API serving the websocket token and the depth snapshots
+    pub rest_url: String,
+    /// Levels per side of the REST depth snapshots, 20 or 100
+    pub snapshot_depth: u32,
+}
+
+impl KuCoinConfig {
+    /// Read `KUCOIN_SYMBOLS` (comma-separated), `KUCOIN_REST_URL` and `KUCOIN_SNAPSHOT_DEPTH`,
+    /// falling back to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols =
+            std::env::var("KUCOIN_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        let snapshot_depth = match std::env::var("KUCOIN_SNAPSHOT_DEPTH") {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|depth| SNAPSHOT_DEPTHS.contains(depth))
+                .ok_or_else(|| {
+                    format!(
+                        "KUCOIN_SNAPSHOT_DEPTH `{}` is not one of {:?}",
+                        value, SNAPSHOT_DEPTHS
+                    )
+                })?,
+            Err(_) => DEFAULT_SNAPSHOT_DEPTH,
+        };
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            rest_url: std::env::var("KUCOIN_REST_URL")
+                .unwrap_or_else(|_| DEFAULT_REST_URL.to_string()),
+            snapshot_depth,
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to a KuCoin symbol
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "KUCOIN_SYMBOLS entry `{}` has no KuCoin symbol (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("KUCOIN_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// Websocket server handed out by the bullet-public handshake
+#[derive(Debug, Clone, PartialEq)]
+struct WsEndpoint {
+    endpoint: String,
+    token: String,
+    /// Interval the server expects pings at
+    ping_interval: Duration,
+    /// Silence after a missed ping before the server drops the connection
+    ping_timeout: Duration,
+}
+
+impl WsEndpoint {
+    /// URL to connect to; `connect_id` is echoed back in the welcome message
+    fn connect_url(&self, connect_id: &str) -> String {
+        format!(
+            "{}?token={}&connectId={}",
+            self.endpoint, self.token, connect_id
+        )
+    }
+}
+
+/// `data` of a KuCoin REST reply, or its error when `code` is not a success
+fn reply_data(body: &Value) -> Result<&Value, String> {
+    if body["code"] != SUCCESS_CODE {
+        return Err(format!("code {}: {}", body["code"], body["msg"]));
+    }
+    Ok(&body["data"])
+}
+
+/// Decode a `/api/v1/bullet-public` reply, keeping its first websocket server
+fn parse_bullet(body: &Value) -> Result<WsEndpoint, String> {
+    let data = reply_data(body)?;
+    let token = data["token"].as_str().ok_or("missing token")?;
+    let server = data["instanceServers"]
+        .as_array()
+        .and_then(|servers| servers.first())
+        .ok_or("no instance server")?;
+    let millis = |field: &str| -> Result<Duration, String> {
+        server[field]
+            .as_u64()
+            .filter(|millis| *millis > 0)
+            .map(Duration::from_millis)
+            .ok_or_else(|| format!("missing `{}`", field))
+    };
+    Ok(WsEndpoint {
+        endpoint: server["endpoint"]
+            .as_str()
+            .ok_or("missing endpoint")?
+            .to_string(),
+        token: token.to_string(),
+        ping_interval: millis("pingInterval")?,
+        ping_timeout: millis("pingTimeout")?,
+    })
+}
+
+/// Obtain a websocket token and server; public channels need no credentials
+async fn fetch_ws_endpoint(http: &reqwest::Client, rest_url: &str) -> Result<WsEndpoint, String> {
+    let url = format!("{}/api/v1/bullet-public", rest_url.trim_end_matches('/'));
+    let response = http.post(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {} {}", status, body["code"], body["msg"]));
+    }
+    parse_bullet(&body)
+}
+
+/// `/market/level2` subscription of every symbol
+fn subscribe_request(symbols: &[String], id: i64) -> String {
+    let topic: Vec<String> = symbols
+        .iter()
+        .map(|symbol| to_venue_symbol(EXCHANGE, symbol).unwrap_or_else(|| symbol.clone()))
+        .collect();
+    json!({
+        "id": id.to_string(),
+        "type": "subscribe",
+        "topic": format!("/market/level2:{}", topic.join(",")),
+        "privateChannel": false,
+        "response": true,
+    })
+    .to_string()
+}
+
+/// Ping the server expects every `pingInterval`
+fn ping_request(id: i64) -> String {
+    json!({"id": id.to_string(), "type": "ping"}).to_string()
+}
+
+/// Decoded websocket message
+#[derive(Debug, PartialEq)]
+enum KuCoinFrame {
+    /// First message of a connection, before which nothing may be sent
+    Welcome,
+    Update(DepthUpdate),
+    Error(String),
+    /// Acks, pongs and other topics
+    Other,
+}
+
+/// Changes `[price, size, sequence]` of one side; a zero price only carries a sequence
+fn parse_changes(value: &Value) -> Result<Levels, String> {
+    let levels = parse_levels(value)?;
+    Ok(levels
+        .into_iter()
+        .filter(|(price, _)| !price.is_zero())
+        .collect())
+}
+
+/// KuCoin sends sequences both as numbers and as strings
+fn parse_sequence(value: &Value, field: &str) -> Result<u64, String> {
+    let sequence = &value[field];
+    sequence
+        .as_u64()
+        .or_else(|| sequence.as_str().and_then(|text| text.parse().ok()))
+        .ok_or_else(|| format!("missing sequence `{}`", field))
+}
+
+/// Decode a websocket message, with the symbol of updates mapped to its internal symbol
+fn parse_frame(text: &str) -> Result<KuCoinFrame, String> {
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    match frame["type"].as_str() {
+        Some("welcome") => return Ok(KuCoinFrame::Welcome),
+        Some("error") => {
+            return Ok(KuCoinFrame::Error(format!(
+                "{} {}",
+                frame["code"], frame["data"]
+            )));
+        }
+        Some("message") if frame["subject"] == "trade.l2update" => {}
+        _ => return Ok(KuCoinFrame::Other),
+    }
+    let data = &frame["data"];
+    let symbol = data["symbol"].as_str().ok_or("missing symbol")?;
+    Ok(KuCoinFrame::Update(DepthUpdate {
+        symbol: from_venue_symbol(EXCHANGE, symbol)
+            .ok_or_else(|| format!("unexpected symbol `{}`", symbol))?,
+        event_ms: data["time"].as_i64().ok_or("missing time")?,
+        first_update_id: parse_sequence(data, "sequenceStart")?,
+        last_update_id: parse_sequence(data, "sequenceEnd")?,
+        bids: parse_changes(&data["changes"]["bids"])?,
+        asks: parse_changes(&data["changes"]["asks"])?,
+    }))
+}
+
+/// Decode a `/api/v1/market/orderbook/level2_*` reply
+fn parse_depth_snapshot(body: &Value) -> Result<DepthSnapshot, String> {
+    let data = reply_data(body)?;
+    Ok(DepthSnapshot {
+        last_update_id: parse_sequence(data, "sequence")?,
+        bids: parse_levels(&data["bids"])?,
+        asks: parse_levels(&data["asks"])?,
+    })
+}
+
+/// Fetch the partial depth snapshot of `symbol`
+async fn fetch_depth_snapshot(
+    http: &reqwest::Client,
+    rest_url: &str,
+    symbol: &str,
+    depth: u32,
+) -> Result<DepthSnapshot, String> {
+    let kucoin_symbol = to_venue_symbol(EXCHANGE, symbol)
+        .ok_or_else(|| format!("no KuCoin symbol for {}", symbol))?;
+    let url = format!(
+        "{}/api/v1/market/orderbook/level2_{}?symbol={}",
+        rest_url.trim_end_matches('/'),
+        depth,
+        kucoin_symbol
+    );
+    let response = http.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {} {}", status, body["code"], body["msg"]));
+    }
+    parse_depth_snapshot(&body)
+}
+
+/// Fetches of REST depth snapshots in flight, with their symbol
+type SnapshotFetches = JoinSet<(String, Result<DepthSnapshot, String>)>;
+
+/// KuCoin spot screener keeping a local book per symbol from the level2 increments and REST
+/// snapshots, persisted as CEX market states
+pub struct KuCoinScreener {
+    config: KuCoinConfig,
+    shutdown: CancellationToken,
+    http: reqwest::Client,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl KuCoinScreener {
+    /// Create a new KuCoinScreener instance on the symbols of `KUCOIN_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, KuCoinConfig::from_env()?)
+    }
+
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: KuCoinConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder().timeout(REST_TIMEOUT).build()?;
+        let cex_writer = CexMarketWriter::for_database(db_pool, "kucoin");
+        Ok(Self {
+            config,
+            shutdown: CancellationToken::new(),
+            http,
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("KuCoin", EXCHANGE, STALE_FEED_TIMEOUT),
+        })
+    }
+
+    /// Stream the books until stopped; a failed handshake or a dropped or silent connection
+    /// is retried after an exponential backoff with a new token, and every book is rebuilt
+    /// from a new snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting KuCoin screener for {:?} ({})...",
+            self.config.symbols, self.config.rest_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("KuCoin screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(EXCHANGE, symbol)))
+            .collect();
+    }
+
+    /// Fetch the snapshot of `symbol` after `delay`
+    fn spawn_snapshot_fetch(&self, fetches: &mut SnapshotFetches, symbol: &str, delay: Duration) {
+        let http = self.http.clone();
+        let rest_url = self.config.rest_url.clone();
+        let depth = self.config.snapshot_depth;
+        let symbol = symbol.to_string();
+        fetches.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let snapshot = fetch_depth_snapshot(&http, &rest_url, &symbol, depth).await;
+            (symbol, snapshot)
+        });
+    }
+
+    /// Apply one text frame; returns whether it was a level2 increment
+    fn handle_frame(&self, fetches: &mut SnapshotFetches, text: &str) -> bool {
+        let update = match parse_frame(text) {
+            Ok(KuCoinFrame::Update(update)) => update,
+            Ok(KuCoinFrame::Error(detail)) => {
+                error!("KuCoin websocket error: {}", detail);
+                return false;
+            }
+            Ok(KuCoinFrame::Welcome | KuCoinFrame::Other) => {
+                debug!("Skipping KuCoin frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed KuCoin frame: {}", e);
+                return false;
+            }
+        };
+        let (symbol, sequence, event_ms) = (
+            update.symbol.clone(),
+            update.last_update_id,
+            update.event_ms,
+        );
+
+        let mut books = self.books.lock().unwrap();
+        // Increments of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&symbol) else {
+            return true;
+        };
+        match book.on_update(update) {
+            UpdateOutcome::Applied => self.persist(book, sequence, event_ms, fetches),
+            UpdateOutcome::Resync => {
+                warn!(
+                    "KuCoin {} order book missed increments before {}, fetching a new snapshot",
+                    symbol, sequence
+                );
+                metrics::counter!("kucoin_orderbook_gaps_total", "symbol" => symbol.clone())
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            }
+            UpdateOutcome::Buffered | UpdateOutcome::Skipped => {}
+        }
+        true
+    }
+
+    /// Rebuild a book from its snapshot, fetching it again when it is too old or failed
+    fn handle_snapshot(
+        &self,
+        fetches: &mut SnapshotFetches,
+        symbol: &str,
+        snapshot: Result<DepthSnapshot, String>,
+    ) {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("KuCoin {} depth snapshot failed, retrying: {}", symbol, e);
+                metrics::counter!("kucoin_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+                return;
+            }
+        };
+        let sequence = snapshot.last_update_id;
+
+        let mut books = self.books.lock().unwrap();
+        let Some(book) = books.get_mut(symbol) else {
+            return;
+        };
+        match book.on_snapshot(snapshot) {
+            SnapshotOutcome::Synced { replayed } => {
+                info!(
+                    "KuCoin {} order book synced from a snapshot at {}, replayed {} increments",
+                    symbol, sequence, replayed
+                );
+                metrics::counter!("kucoin_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "ok")
+                    .increment(1);
+                let BookSync::Synced(last) = book.sync else {
+                    return;
+                };
+                self.persist(book, last, Utc::now().timestamp_millis(), fetches);
+            }
+            SnapshotOutcome::Stale => {
+                debug!(
+                    "KuCoin {} depth snapshot at {} is older than the buffered increments, retrying",
+                    symbol, sequence
+                );
+                metrics::counter!("kucoin_orderbook_snapshots_total", "symbol" => symbol.to_string(), "status" => "stale")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, symbol, SNAPSHOT_RETRY_DELAY);
+            }
+            SnapshotOutcome::Ignored => {}
+        }
+    }
+
+    /// Persist the top of book when it changed. A book that cannot describe a real market is
+    /// dropped and rebuilt from a new snapshot instead.
+    fn persist(
+        &self,
+        book: &mut SymbolBook,
+        sequence: u64,
+        event_ms: i64,
+        fetches: &mut SnapshotFetches,
+    ) {
+        let symbol = book.orderbook.symbol.clone();
+        if let Err(violation) = book.orderbook.validate() {
+            error!(
+                "KuCoin {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "kucoin_invalid_books_total",
+                "symbol" => symbol.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            book.resync_from(Vec::new());
+            self.spawn_snapshot_fetch(fetches, &symbol, Duration::ZERO);
+            return;
+        }
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: sequence.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol,
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(event_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - event_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for KuCoinScreener {
+    /// Empty the books, obtain a token, connect, wait for the welcome message, subscribe every
+    /// symbol, fetch their snapshots and apply the increments until the connection drops or the
+    /// screener stops. `feed` counts the increments delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let endpoint = fetch_ws_endpoint(&self.http, &self.config.rest_url)
+            .await
+            .map_err(|e| format!("bullet-public failed: {}", e))?;
+        let connect_id = Utc::now().timestamp_millis();
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(endpoint.connect_url(&connect_id.to_string()))
+                .await
+                .map_err(|e| format!("connect failed: {}", e))?;
+        match tokio::time::timeout(endpoint.ping_timeout, ws.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) if parse_frame(&text) == Ok(KuCoinFrame::Welcome) => {
+                feed.frame()
+            }
+            other => return Err(format!("no welcome message: {:?}", other)),
+        }
+        ws.send(Message::Text(subscribe_request(
+            &self.config.symbols,
+            connect_id,
+        )))
+        .await
+        .map_err(|e| format!("subscribe failed: {}", e))?;
+        info!(
+            "KuCoin websocket connected to {}, subscribed to {:?}",
+            endpoint.endpoint, self.config.symbols
+        );
+
+        // Increments are buffered from the subscription on, so the snapshots are fetched after it
+        let mut fetches = SnapshotFetches::new();
+        for symbol in &self.config.symbols {
+            self.spawn_snapshot_fetch(&mut fetches, symbol, Duration::ZERO);
+        }
+        let mut ping = tokio::time::interval_at(
+            tokio::time::Instant::now() + endpoint.ping_interval,
+            endpoint.ping_interval,
+        );
+        // Pongs count as frames, so a server missing a whole ping round is gone
+        feed.set_stale_timeout(endpoint.ping_interval + endpoint.ping_timeout);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                _ = ping.tick() => {
+                    ws.send(Message::Text(ping_request(Utc::now().timestamp_millis())))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                Some(fetched) = fetches.join_next(), if !fetches.is_empty() => {
+                    if let Ok((symbol, snapshot)) = fetched {
+                        self.handle_snapshot(&mut fetches, &symbol, snapshot);
+                    }
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            if self.handle_frame(&mut fetches, &text) {
+                                feed.delivered();
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "kucoin_tests.rs"]
+mod kucoin_tests;
@@ -0,0 +1,286 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// `/api/v1/bullet-public` reply in the layout documented by KuCoin
+const BULLET_FIXTURE: &str = r#"{"code":"200000","data":{"token":"2neAiuYvAU61ZDXANAGAsiL4-iAExhsBXZxftpOeh_55i3Ysy2q2LEsEWU64mdzUOPusi34M_wGoSf7iNyEWJ4aBZXpWhrmY9jKtqkdWoFa75w3istPvPtiYB9J6i9GjsxUuhPw3BlrzazF6ghq4L_bPjRMP98g2GtH1h_uXVGN1_ttA2B2q9-2Lc3uHCdgo.tFNa9w8dhKQxE-ex15WuxA==","instanceServers":[{"endpoint":"wss://ws-api-spot.kucoin.com/","encrypt":true,"protocol":"websocket","pingInterval":18000,"pingTimeout":10000}]}}"#;
+
+/// `trade.l2update` messages recorded in order for TRUMP-USDT: one already in the snapshot,
+/// one straddling it, two following it, then a gap
+const UPDATE_FIXTURES: [&str; 5] = [
+    r#"{"type":"message","topic":"/market/level2:TRUMP-USDT","subject":"trade.l2update","data":{"changes":{"asks":[],"bids":[["10.21","4","14103840"]]},"sequenceEnd":14103840,"sequenceStart":14103838,"symbol":"TRUMP-USDT","time":1716863719050}}"#,
+    r#"{"type":"message","topic":"/market/level2:TRUMP-USDT","subject":"trade.l2update","data":{"changes":{"asks":[["10.27","3","14103845"]],"bids":[["10.24","0","14103843"],["10.25","1.5","14103846"]]},"sequenceEnd":14103846,"sequenceStart":14103843,"symbol":"TRUMP-USDT","time":1716863719100}}"#,
+    r#"{"type":"message","topic":"/market/level2:TRUMP-USDT","subject":"trade.l2update","data":{"changes":{"asks":[["0","0","14103847"]],"bids":[["10.25","2","14103848"]]},"sequenceEnd":14103848,"sequenceStart":14103847,"symbol":"TRUMP-USDT","time":1716863719200}}"#,
+    r#"{"type":"message","topic":"/market/level2:TRUMP-USDT","subject":"trade.l2update","data":{"changes":{"asks":[["10.28","0","14103849"],["10.26","1","14103850"]],"bids":[]},"sequenceEnd":14103850,"sequenceStart":14103849,"symbol":"TRUMP-USDT","time":1716863719300}}"#,
+    r#"{"type":"message","topic":"/market/level2:TRUMP-USDT","subject":"trade.l2update","data":{"changes":{"asks":[],"bids":[["10.2","9","14103856"]]},"sequenceEnd":14103856,"sequenceStart":14103856,"symbol":"TRUMP-USDT","time":1716863719400}}"#,
+];
+
+/// `/api/v1/market/orderbook/level2_100` reply recorded for TRUMP-USDT
+const SNAPSHOT_FIXTURE: &str = r#"{"code":"200000","data":{"time":1716863719120,"sequence":"14103844","bids":[["10.24","2"],["10.23","3"]],"asks":[["10.27","4"],["10.28","5"]]}}"#;
+
+fn update(index: usize) -> DepthUpdate {
+    match parse_frame(UPDATE_FIXTURES[index]).unwrap() {
+        KuCoinFrame::Update(update) => update,
+        other => panic!("not a level2 increment: {:?}", other),
+    }
+}
+
+fn snapshot_at(sequence: u64) -> DepthSnapshot {
+    DepthSnapshot {
+        last_update_id: sequence,
+        ..parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap()).unwrap()
+    }
+}
+
+fn prices(levels: &market::OrderBookLevels) -> Vec<Decimal> {
+    levels.keys().copied().collect()
+}
+
+impl TestScreener for KuCoinScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = KuCoinScreener {
+            config: KuCoinConfig {
+                symbols: vec!["TRUMPUSDT".to_string()],
+                rest_url: "http://127.0.0.1:1".to_string(),
+                snapshot_depth: 100,
+            },
+            shutdown: CancellationToken::new(),
+            http: reqwest::Client::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("KuCoin", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn bullet_reply_yields_the_websocket_server() {
+    let endpoint = parse_bullet(&serde_json::from_str(BULLET_FIXTURE).unwrap()).unwrap();
+
+    assert_eq!(endpoint.endpoint, "wss://ws-api-spot.kucoin.com/");
+    assert!(endpoint.token.starts_with("2neAiuYvAU61ZDXANAGAsiL4"));
+    assert_eq!(endpoint.ping_interval, Duration::from_secs(18));
+    assert_eq!(endpoint.ping_timeout, Duration::from_secs(10));
+    assert_eq!(
+        endpoint.connect_url("1716863718000"),
+        format!(
+            "wss://ws-api-spot.kucoin.com/?token={}&connectId=1716863718000",
+            endpoint.token
+        )
+    );
+}
+
+#[test]
+fn malformed_bullet_replies_are_rejected() {
+    let error = parse_bullet(&json!({"code": "429000", "msg": "Too Many Requests"})).unwrap_err();
+    assert!(error.contains("429000"), "{}", error);
+
+    let no_server = BULLET_FIXTURE.replace(
+        r#""instanceServers":[{"endpoint":"wss://ws-api-spot.kucoin.com/","encrypt":true,"protocol":"websocket","pingInterval":18000,"pingTimeout":10000}]"#,
+        r#""instanceServers":[]"#,
+    );
+    assert_eq!(
+        parse_bullet(&serde_json::from_str(&no_server).unwrap()),
+        Err("no instance server".to_string())
+    );
+    let no_ping = BULLET_FIXTURE.replace(r#""pingInterval":18000"#, r#""pingInterval":0"#);
+    assert!(parse_bullet(&serde_json::from_str(&no_ping).unwrap()).is_err());
+}
+
+#[test]
+fn increments_are_decoded_with_internal_symbols() {
+    assert_eq!(
+        update(1),
+        DepthUpdate {
+            symbol: "TRUMPUSDT".to_string(),
+            event_ms: 1716863719100,
+            first_update_id: 14103843,
+            last_update_id: 14103846,
+            bids: vec![
+                (decimal("10.24"), Decimal::ZERO),
+                (decimal("10.25"), decimal("1.5"))
+            ],
+            asks: vec![(decimal("10.27"), decimal("3"))],
+        }
+    );
+    // A zero price only advances the sequence
+    assert!(update(2).asks.is_empty());
+
+    assert_eq!(
+        parse_frame(r#"{"id":"hQvf8jkno","type":"welcome"}"#),
+        Ok(KuCoinFrame::Welcome)
+    );
+    assert_eq!(
+        parse_frame(r#"{"id":"1716863718000","type":"ack"}"#),
+        Ok(KuCoinFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(r#"{"id":"1716863718000","type":"pong"}"#),
+        Ok(KuCoinFrame::Other)
+    );
+    assert_eq!(
+        parse_frame(
+            r#"{"id":"1716863718000","type":"error","code":401,"data":"token is invalid"}"#
+        ),
+        Ok(KuCoinFrame::Error(r#"401 "token is invalid""#.to_string()))
+    );
+    assert!(parse_frame(&UPDATE_FIXTURES[2].replace(r#""sequenceStart":14103847,"#, "")).is_err());
+}
+
+#[test]
+fn requests_and_symbols_follow_kucoin_conventions() {
+    let symbols = ["TRUMPUSDT".to_string(), "TRUMPUSDC".to_string()];
+    let subscribe: Value = serde_json::from_str(&subscribe_request(&symbols, 42)).unwrap();
+    assert_eq!(
+        subscribe,
+        json!({
+            "id": "42",
+            "type": "subscribe",
+            "topic": "/market/level2:TRUMP-USDT,TRUMP-USDC",
+            "privateChannel": false,
+            "response": true
+        })
+    );
+    let ping: Value = serde_json::from_str(&ping_request(43)).unwrap();
+    assert_eq!(ping, json!({"id": "43", "type": "ping"}));
+    assert_eq!(
+        parse_symbols(" trumpusdt,TRUMPUSDC,,TRUMPUSDT ").unwrap(),
+        ["TRUMPUSDT", "TRUMPUSDC"]
+    );
+    assert!(parse_symbols("TRUMP").is_err());
+}
+
+#[test]
+fn snapshot_reconciles_the_buffered_increments() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDT");
+    for index in 0..3 {
+        assert_eq!(book.on_update(update(index)), UpdateOutcome::Buffered);
+    }
+
+    // The first increment is in the snapshot, the second one straddles its sequence
+    assert_eq!(
+        book.on_snapshot(snapshot_at(14103844)),
+        SnapshotOutcome::Synced { replayed: 2 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(14103848));
+    assert_eq!(
+        prices(&book.orderbook.bids),
+        [decimal("10.23"), decimal("10.25")]
+    );
+    assert_eq!(book.orderbook.bids[&decimal("10.25")], decimal("2"));
+
+    assert_eq!(book.on_update(update(3)), UpdateOutcome::Applied);
+    assert_eq!(
+        prices(&book.orderbook.asks),
+        [decimal("10.26"), decimal("10.27")]
+    );
+    assert_eq!(book.on_update(update(2)), UpdateOutcome::Skipped);
+}
+
+#[test]
+fn sequence_gap_waits_for_a_newer_snapshot() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDT");
+    for index in 1..4 {
+        book.on_update(update(index));
+    }
+    book.on_snapshot(snapshot_at(14103844));
+
+    assert_eq!(book.on_update(update(4)), UpdateOutcome::Resync);
+    assert!(book.orderbook.bids.is_empty() && book.orderbook.asks.is_empty());
+    assert_eq!(
+        book.on_snapshot(snapshot_at(14103850)),
+        SnapshotOutcome::Stale
+    );
+    assert_eq!(
+        book.on_snapshot(snapshot_at(14103855)),
+        SnapshotOutcome::Synced { replayed: 1 }
+    );
+    assert_eq!(book.sync, BookSync::Synced(14103856));
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_kucoin_states() {
+    let (screener, sink) = build_screener_with_sink::<KuCoinScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    assert!(screener.handle_frame(&mut fetches, UPDATE_FIXTURES[1]));
+    assert!(!screener.handle_frame(&mut fetches, r#"{"id":"42","type":"ack"}"#));
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDT", Ok(snapshot_at(14103844)));
+    screener.cex_writer.flush().await;
+    for fixture in &UPDATE_FIXTURES[2..4] {
+        assert!(screener.handle_frame(&mut fetches, fixture));
+        screener.cex_writer.flush().await;
+    }
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "kucoin" && state.trade_pair == "TRUMPUSDT")
+    );
+    assert_eq!(states[0].trade_id, "14103846");
+    assert_eq!(
+        (states[0].bid_price, states[0].ask_price),
+        (decimal("10.25"), decimal("10.27"))
+    );
+    assert_eq!(states[1].bid_volume, decimal("2"));
+    assert_eq!(
+        (states[2].trade_id.as_str(), states[2].ask_price),
+        ("14103850", decimal("10.26"))
+    );
+    assert_eq!(states[2].trade_time.timestamp_millis(), 1716863719300);
+    assert!(fetches.is_empty());
+
+    screener.handle_frame(&mut fetches, UPDATE_FIXTURES[4]);
+    assert_eq!(fetches.len(), 1);
+    fetches.abort_all();
+}
+
+#[tokio::test]
+async fn bootstrap_and_snapshots_are_fetched_over_rest() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/bullet-public"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULLET_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/market/orderbook/level2_100"))
+        .and(query_param("symbol", "TRUMP-USDT"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SNAPSHOT_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/market/orderbook/level2_100"))
+        .and(query_param("symbol", "NOPE-USDT"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"code":"400100","msg":"This pair is not provided at present"}"#,
+            ),
+        )
+        .mount(&server)
+        .await;
+    let http = reqwest::Client::new();
+
+    let endpoint = fetch_ws_endpoint(&http, &server.uri()).await.unwrap();
+    assert_eq!(endpoint.ping_interval, Duration::from_secs(18));
+    assert_eq!(
+        fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDT", 100).await,
+        Ok(snapshot_at(14103844))
+    );
+    let error = fetch_depth_snapshot(&http, &server.uri(), "NOPEUSDT", 100)
+        .await
+        .unwrap_err();
+    assert!(error.contains("400100"), "{}", error);
+}
@@ -1,233 +1,3337 @@
-// use rust_decimal::Decimal;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::commitment_config::CommitmentConfig;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
 use solana_sdk::pubkey::Pubkey;
-use sqlx::{MySql, Pool};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+    Arc, RwLock,
+    atomic::{AtomicU64, Ordering},
 };
+use std::time::Duration;
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
 use commons::dlmm::accounts::{BinArray, BinArrayBitmapExtension, LbPair};
 use commons::{
-    derive_bin_array_bitmap_extension, get_bin_array_pubkeys_for_swap, quote_exact_in,
-    rpc_client_extension::RpcClientExtension,
+    derive_bin_array_bitmap_extension, dlmm, get_bin_array_pubkeys_for_swap, quote_exact_in,
+    quote_exact_out,
+};
+use solana_client::rpc_config::{
+    RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
 };
 use solana_sdk::account::Account;
+use solana_sdk::message::Message;
+use solana_sdk::transaction::Transaction;
+use spl_token_2022::check_spl_token_program_account;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_2022::state::Mint;
+
+use crate::execution::meteora::{UserTokenAccounts, build_swap_ix};
+use crate::fees::solana::{LandingCostConfig, SolanaFeeEstimator, lamports_to_quote_amount};
+use crate::models::market;
+use crate::models::pool_fee::PoolFee;
+use crate::models::pool_stats::PoolStats;
+use crate::models::quote_check::QuoteCheck;
+use crate::models::trade_pair::TradePair;
+use crate::screeners::dex_runner::{DexQuoter, DexScreenerRunner, get_best_price};
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora_api::{DiscoveredPool, MeteoraApiClient};
+use crate::solana::account_cache::{AccountCache, AccountFetcher, Freshness};
+use crate::solana::rpc::{
+    FailoverRpcClient, commitment_from_env, redact_url, rpc_endpoints_from_env,
+};
+use crate::solana::utils::{read_anchor_account, token_account_amount};
+use crate::store::db::DatabasePool;
+use crate::store::pool_fees::insert_pool_fee;
+use crate::store::pool_stats::insert_pool_stats;
+use crate::store::quote_checks::insert_quote_check;
+use crate::store::trade_pairs::get_enabled_pairs;
+use crate::telemetry::{DEFAULT_SUMMARY_INTERVAL_SECS, LatencyMetrics};
+
+/// Default delay between two polling ticks
+const DEFAULT_POLL_INTERVAL_MS: u64 = 1000;
+/// Amount (in input token base units) quoted on every tick
+pub(super) const DEFAULT_AMOUNT_IN: u64 = 1_000_000;
+/// Default depth ladder notionals in quote token base units ($100, $1k, $10k, $50k for a 6-decimal stablecoin)
+pub const DEFAULT_DEPTH_LADDER_SIZES: [u64; 4] =
+    [100_000_000, 1_000_000_000, 10_000_000_000, 50_000_000_000];
+/// Default delay between two reloads of the trade pairs table
+const DEFAULT_PAIRS_REFRESH_MINS: u64 = 5;
+/// Default delay between two pool discoveries through the Meteora API
+const DEFAULT_DISCOVERY_REFRESH_MINS: u64 = 15;
+/// Default number of pairs quoted concurrently within a tick
+const DEFAULT_MAX_CONCURRENT_PAIRS: usize = 8;
+/// Default number of slots a cached LbPair/bin array may lag behind the latest slot
+const DEFAULT_CACHE_MAX_SLOT_AGE: u64 = 2;
+/// Default lifetime of cached mint accounts
+const DEFAULT_MINT_CACHE_TTL_SECS: u64 = 3600;
+/// Default lifetime of a cached bitmap extension lookup, present or absent
+const DEFAULT_BITMAP_EXTENSION_TTL_SECS: u64 = 300;
+/// Default number of bin arrays fetched on each side of the active bin
+const DEFAULT_BIN_ARRAY_COUNT: u8 = 4;
+/// Upper bound for the bin array count when retrying an out-of-liquidity quote
+const MAX_BIN_ARRAY_COUNT: u8 = 16;
+/// Default exact-out search tolerance, in input token base units
+const DEFAULT_EXACT_OUT_TOLERANCE: u64 = 1;
+/// Default maximum number of quotes evaluated by an exact-out search
+const DEFAULT_EXACT_OUT_MAX_ITERATIONS: u32 = 64;
+/// Default pool liquidity floor, in quote token units, below which a pair is degraded
+const DEFAULT_MIN_POOL_LIQUIDITY: u64 = 10_000;
+/// Default drift between the Clock sysvar and wall time above which a quote is stale
+const DEFAULT_MAX_CLOCK_DRIFT_SECS: u64 = 15;
+/// Default age above which a quote no longer describes the live pool
+const DEFAULT_MAX_QUOTE_AGE_MS: u64 = 2000;
+/// Default share of quotes cross-checked against a simulated swap
+const DEFAULT_VERIFY_SAMPLE_RATE: f64 = 0.01;
+/// Default deviation (in bps) between local and simulated outputs above which a check is flagged
+const DEFAULT_VERIFY_MAX_DEVIATION_BPS: u32 = 10;
+/// Venue name of Meteora pairs in the trade_pairs table
+const VENUE: &str = "meteora";
+/// Direction of active bin spot prices in dex_markets, distinct from the executable `sell`/`buy` quotes
+const SPOT_DIRECTION: &str = "spot";
+/// Anchor discriminators (`sha256("account:<Name>")[..8]`) of the DLMM accounts we decode
+/// DLMM fee rates are fractions scaled by 1e9; one basis point is 1e5
+const FEE_PRECISION_PER_BPS: u32 = 5;
+/// Maximum total fee rate enforced by the DLMM program (10%), in fee precision
+const MAX_FEE_RATE: u128 = 100_000_000;
+/// Scale of the squared volatility term of the DLMM variable fee
+const VARIABLE_FEE_SCALE: u128 = 100_000_000_000;
 
-struct TradeConfig {
+const LB_PAIR_DISCRIMINATOR: [u8; 8] = [33, 11, 49, 98, 181, 101, 177, 13];
+const BIN_ARRAY_DISCRIMINATOR: [u8; 8] = [92, 142, 92, 220, 5, 148, 70, 181];
+const BIN_ARRAY_BITMAP_EXTENSION_DISCRIMINATOR: [u8; 8] = [80, 111, 124, 113, 55, 237, 18, 5];
+
+/// DLMM pool quoted for a symbol
+#[derive(Debug, Clone)]
+pub(super) struct PoolConfig {
     pub pool_pubkey: Pubkey,
-    pub _precision: u64,
+    /// Whether the base token of the pair is token X of the pool
+    pub base_is_x: bool,
+    /// Bin arrays fetched on each side of the active bin for quoting
+    pub bin_array_count: u8,
 }
 
-fn get_trade_pairs() -> HashMap<String, TradeConfig> {
-    let mut map = HashMap::new();
-    map.insert(
-        "TRUMPUSDC".to_string(),
-        TradeConfig {
-            pool_pubkey: Pubkey::from_str_const("9d9mb8kooFfaD3SctgZtkxQypkshx6ezhbKio89ixyy2"),
-            _precision: 6,
-        },
-    );
+/// Two-hop path quoting a pair through an intermediate token, e.g. TRUMP -> SOL -> USDC.
+/// Each hop's `base_is_x` tells whether the token it sells is token X of its pool.
+#[derive(Debug, Clone)]
+pub(super) struct RouteConfig {
+    /// Route name persisted with its quotes, e.g. `TRUMP-SOL-USDC`
+    pub name: String,
+    /// Base to intermediate token pool, then intermediate to quote token pool
+    pub hops: [PoolConfig; 2],
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct TradeConfig {
+    /// Pools quoted for the symbol, in configuration order
+    pub pools: Vec<PoolConfig>,
+    /// Multi-hop routes quoted alongside the pools
+    pub routes: Vec<RouteConfig>,
+}
+
+impl TradeConfig {
+    fn empty() -> Self {
+        Self {
+            pools: Vec::new(),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Every pool the pair is quoted against, including the hops of its routes
+    fn pool_pubkeys(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        self.pools
+            .iter()
+            .chain(self.routes.iter().flat_map(|route| route.hops.iter()))
+            .map(|pool| pool.pool_pubkey)
+    }
+}
+
+/// Pools discovered through the Meteora API, keyed by symbol
+pub(super) type DiscoveredPools = Arc<RwLock<HashMap<String, Vec<PoolConfig>>>>;
+
+/// Trade pair whose pools are discovered from its mints
+#[derive(Debug, Clone)]
+struct DiscoveryTarget {
+    symbol: String,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    bin_array_count: u8,
+}
+
+/// Build the per-symbol trade configs from database rows, one pool or route per row.
+/// Rows with an invalid pool pubkey are logged and skipped; auto-discovered
+/// rows carry no pool and are resolved by `discover_pools` instead.
+fn trade_configs_from_pairs(pairs: Vec<TradePair>) -> HashMap<String, TradeConfig> {
+    let mut map: HashMap<String, TradeConfig> = HashMap::new();
+    for pair in pairs {
+        if pair.auto_discover {
+            continue;
+        }
+        if pair.route.is_some() || pair.route_pool_pubkey.is_some() {
+            if let Some(route) = route_config_from_pair(&pair) {
+                map.entry(pair.symbol)
+                    .or_insert_with(TradeConfig::empty)
+                    .routes
+                    .push(route);
+            }
+            continue;
+        }
+        let pool_pubkey = match pair.pool_pubkey.parse::<Pubkey>() {
+            Ok(pool_pubkey) => pool_pubkey,
+            Err(e) => {
+                warn!(
+                    "Skipping Meteora pair {}: invalid pool pubkey '{}': {}",
+                    pair.symbol, pair.pool_pubkey, e
+                );
+                continue;
+            }
+        };
+        let pool = PoolConfig {
+            pool_pubkey,
+            base_is_x: pair.base_is_x,
+            bin_array_count: bin_array_count_or_default(pair.bin_array_count),
+        };
+        map.entry(pair.symbol)
+            .or_insert_with(TradeConfig::empty)
+            .pools
+            .push(pool);
+    }
     map
 }
 
+/// Route config of a route row. Rows missing the route name or second hop pool,
+/// or with an invalid pool pubkey, are logged and yield `None`.
+fn route_config_from_pair(pair: &TradePair) -> Option<RouteConfig> {
+    let (Some(name), Some(route_pool_pubkey)) = (&pair.route, &pair.route_pool_pubkey) else {
+        warn!(
+            "Skipping Meteora route of {}: both route and route_pool_pubkey must be set, got {:?}/{:?}",
+            pair.symbol, pair.route, pair.route_pool_pubkey
+        );
+        return None;
+    };
+    let parse_pool = |pool_pubkey: &str| match pool_pubkey.parse::<Pubkey>() {
+        Ok(pool_pubkey) => Some(pool_pubkey),
+        Err(e) => {
+            warn!(
+                "Skipping Meteora route {} of {}: invalid pool pubkey '{}': {}",
+                name, pair.symbol, pool_pubkey, e
+            );
+            None
+        }
+    };
+    let first = parse_pool(&pair.pool_pubkey)?;
+    let second = parse_pool(route_pool_pubkey)?;
+    let bin_array_count = bin_array_count_or_default(pair.bin_array_count);
+    Some(RouteConfig {
+        name: name.clone(),
+        hops: [
+            PoolConfig {
+                pool_pubkey: first,
+                base_is_x: pair.base_is_x,
+                bin_array_count,
+            },
+            PoolConfig {
+                pool_pubkey: second,
+                base_is_x: pair.route_base_is_x,
+                bin_array_count,
+            },
+        ],
+    })
+}
+
+fn bin_array_count_or_default(bin_array_count: u8) -> u8 {
+    if bin_array_count == 0 {
+        DEFAULT_BIN_ARRAY_COUNT
+    } else {
+        bin_array_count
+    }
+}
+
+/// Auto-discovered trade pairs of the database rows.
+/// Rows with missing or invalid mints are logged and skipped.
+fn discovery_targets_from_pairs(pairs: &[TradePair]) -> Vec<DiscoveryTarget> {
+    let mut targets = Vec::new();
+    for pair in pairs.iter().filter(|pair| pair.auto_discover) {
+        let parse_mint = |mint: &Option<String>| mint.as_deref()?.parse::<Pubkey>().ok();
+        let (Some(base_mint), Some(quote_mint)) =
+            (parse_mint(&pair.base_mint), parse_mint(&pair.quote_mint))
+        else {
+            warn!(
+                "Skipping auto-discovered Meteora pair {}: invalid base/quote mint {:?}/{:?}",
+                pair.symbol, pair.base_mint, pair.quote_mint
+            );
+            continue;
+        };
+        targets.push(DiscoveryTarget {
+            symbol: pair.symbol.clone(),
+            base_mint,
+            quote_mint,
+            bin_array_count: bin_array_count_or_default(pair.bin_array_count),
+        });
+    }
+    targets
+}
+
+/// Pool configs of the pools discovered for `target`, in discovery order
+fn pool_configs_from_discovered(
+    target: &DiscoveryTarget,
+    pools: &[DiscoveredPool],
+) -> Vec<PoolConfig> {
+    pools
+        .iter()
+        .map(|pool| PoolConfig {
+            pool_pubkey: pool.address,
+            base_is_x: pool.mint_x == target.base_mint,
+            bin_array_count: target.bin_array_count,
+        })
+        .collect()
+}
+
+/// Add the discovered pools to the trade configs, skipping pools already configured
+fn merge_discovered_pools(
+    trade_configs: &mut HashMap<String, TradeConfig>,
+    discovered: &HashMap<String, Vec<PoolConfig>>,
+) {
+    for (symbol, pools) in discovered {
+        let config = trade_configs
+            .entry(symbol.clone())
+            .or_insert_with(TradeConfig::empty);
+        for pool in pools {
+            if !config
+                .pools
+                .iter()
+                .any(|configured| configured.pool_pubkey == pool.pool_pubkey)
+            {
+                config.pools.push(pool.clone());
+            }
+        }
+    }
+}
+
+/// Resolve the pools of every auto-discovered pair through the Meteora API.
+/// A failed or empty discovery keeps the last known pools of the pair.
+async fn discover_pools(
+    api: &MeteoraApiClient,
+    targets: &[DiscoveryTarget],
+    discovered: &RwLock<HashMap<String, Vec<PoolConfig>>>,
+) {
+    for target in targets {
+        let pools = match api
+            .discover_pools(&target.base_mint, &target.quote_mint)
+            .await
+        {
+            Ok(pools) if !pools.is_empty() => pools,
+            Ok(_) => {
+                warn!(
+                    "No Meteora pools above the discovery thresholds for {}, keeping the last known pools",
+                    target.symbol
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to discover Meteora pools for {}, keeping the last known pools: {}",
+                    target.symbol, e
+                );
+                continue;
+            }
+        };
+        info!(
+            "Discovered {} Meteora pools for {}: {}",
+            pools.len(),
+            target.symbol,
+            pools
+                .iter()
+                .map(|pool| format!("{} (tvl ${})", pool.address, pool.tvl_usd.round()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        discovered.write().unwrap().insert(
+            target.symbol.clone(),
+            pool_configs_from_discovered(target, &pools),
+        );
+    }
+    // Forget pairs that are no longer auto-discovered
+    discovered
+        .write()
+        .unwrap()
+        .retain(|symbol, _| targets.iter().any(|target| &target.symbol == symbol));
+}
+
+/// Load the enabled Meteora trade configs from the database, including the
+/// last known pools of auto-discovered pairs
+pub(super) async fn load_trade_configs(
+    db_pool: &DatabasePool,
+    venue: &str,
+    discovered: &RwLock<HashMap<String, Vec<PoolConfig>>>,
+) -> Result<HashMap<String, TradeConfig>, Box<dyn std::error::Error>> {
+    let pairs = get_enabled_pairs(db_pool, venue).await?;
+    let mut trade_configs = trade_configs_from_pairs(pairs);
+    merge_discovered_pools(&mut trade_configs, &discovered.read().unwrap());
+    Ok(trade_configs)
+}
+
 /// Helper struct to hold all accounts needed for swap quote calculation
 pub struct SwapQuoteAccounts {
     pub lb_pair_state: LbPair,
     pub clock: solana_sdk::clock::Clock,
+    /// Slot the accounts were read at, taken from the Clock sysvar
+    pub slot: u64,
+    /// Commitment the pool accounts were read at
+    pub commitment: CommitmentLevel,
+    /// Whether the Clock sysvar drifted from wall time by more than the allowed drift
+    pub stale: bool,
+    /// Number of requested bin arrays the RPC returned no account for
+    pub missing_bin_arrays: usize,
     pub mint_x_account: Account,
     pub mint_y_account: Account,
     pub bin_arrays: HashMap<Pubkey, BinArray>,
 }
 
-pub struct MeteoraScreener {
-    pub db_pool: Pool<MySql>,
-    pub rpc_client: RpcClient,
-    pub shutdown: Arc<AtomicBool>,
+/// Result of quoting a single swap direction, in raw token units.
+/// `amount_out` is what the trader receives, net of Token-2022 transfer fees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    /// Pool output for the full `amount_in`, before transfer fees
+    pub gross_amount_out: u64,
+    /// Transfer fee withheld on the input token
+    pub transfer_fee_in: u64,
+    /// Transfer fee withheld on the output token
+    pub transfer_fee_out: u64,
 }
 
-impl MeteoraScreener {
-    pub fn new(db_pool: Pool<MySql>) -> Self {
-        let helus_api_key = std::env::var("HELIUS_API_KEY").expect("HELIUS_API_KEY must be set");
-        let rpc_client = RpcClient::new_with_commitment(
-            format!("https://mainnet.helius-rpc.com/?api-key={}", helus_api_key),
-            CommitmentConfig::confirmed(),
+impl SwapQuote {
+    pub fn without_transfer_fees(amount_in: u64, amount_out: u64, fee: u64) -> Self {
+        Self {
+            amount_in,
+            amount_out,
+            fee,
+            gross_amount_out: amount_out,
+            transfer_fee_in: 0,
+            transfer_fee_out: 0,
+        }
+    }
+}
+
+/// Two-sided quote for a pair: selling base (bid) and buying base (ask)
+#[derive(Debug, Clone)]
+pub struct PriceQuote {
+    pub symbol: String,
+    /// Pool the quote was computed against, the first hop for a route quote
+    pub pool: Pubkey,
+    /// Multi-hop route the quote went through, `None` for a single pool quote
+    pub route: Option<String>,
+    /// Per-hop breakdown of a route quote, empty for a single pool quote
+    pub hops: Vec<RouteHop>,
+    /// Slot the pool accounts were read at
+    pub slot: u64,
+    /// On-chain time of the Clock sysvar the quote was computed with
+    pub block_time: DateTime<Utc>,
+    /// Wall time the account fetch behind the quote started at
+    pub fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of the account fetch
+    pub fetch_latency: Duration,
+    /// Commitment the pool accounts were read at
+    pub commitment: CommitmentLevel,
+    /// Whether the Clock sysvar drifted from wall time, skewing time-dependent fees
+    pub stale: bool,
+    /// Number of bin arrays missing from the snapshot, which may truncate the quote
+    pub missing_bin_arrays: usize,
+    /// Decimals of the base token, used to normalize volumes
+    pub base_decimals: u32,
+    /// Decimals of the quote token, used to normalize prices
+    pub quote_decimals: u32,
+    /// Base token sold for quote token
+    pub sell: SwapQuote,
+    /// Quote token spent on base token
+    pub buy: SwapQuote,
+    /// Quote tokens received per base token sold
+    pub bid_price: Decimal,
+    /// Quote tokens paid per base token bought
+    pub ask_price: Decimal,
+    /// Quote tokens per base token at the active bin, before any price impact
+    pub spot_price: Option<Decimal>,
+    /// Impact of the sell side against the spot price, `None` when it produced no output
+    pub bid_impact_bps: Option<Decimal>,
+    /// Impact of the buy side against the spot price, `None` when it produced no output
+    pub ask_impact_bps: Option<Decimal>,
+    /// Fee of the sell side as a percentage of its input
+    pub sell_fee_pct: Decimal,
+    /// Fee of the buy side as a percentage of its input
+    pub buy_fee_pct: Decimal,
+    /// Fee rate of the DLMM pool at the fetched slot, `None` for routes and other pool types
+    pub fee_rate: Option<PoolFeeRate>,
+    /// Liquidity of the pool the quote was computed against
+    pub liquidity: PoolLiquidity,
+    /// Lamports paid to land the swap: base fee, priority fee and tip
+    pub landing_cost: u64,
+    /// Quote token received by the sell side minus the landing cost, in raw units.
+    /// `None` until a landing cost is applied with a known SOL price.
+    pub net_amount_out: Option<u64>,
+}
+
+impl PriceQuote {
+    /// Charge the landing cost of the swap, valued in quote token at `sol_price`
+    pub fn with_landing_cost(mut self, landing_cost: u64, sol_price: Option<Decimal>) -> Self {
+        self.landing_cost = landing_cost;
+        self.net_amount_out = sol_price
+            .and_then(|price| lamports_to_quote_amount(landing_cost, price, self.quote_decimals))
+            .map(|cost| self.sell.amount_out.saturating_sub(cost));
+        self
+    }
+
+    /// Time elapsed since the accounts behind the quote started being fetched
+    pub fn age(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.fetched_at).to_std().unwrap_or(Duration::ZERO)
+    }
+
+    /// Whether the quote was computed without some of the bin arrays it needed
+    pub fn is_partial(&self) -> bool {
+        self.missing_bin_arrays > 0
+    }
+
+    /// Whether both quotes were computed from accounts read at the same slot of the same pool,
+    /// i.e. the newer one carries no new information
+    pub fn same_slot_as(&self, other: &PriceQuote) -> bool {
+        self.pool == other.pool && self.route == other.route && self.slot == other.slot
+    }
+
+    /// Pools the quote was computed against, joined with `:` for a route
+    fn pool_key(&self) -> String {
+        if self.hops.is_empty() {
+            return self.pool.to_string();
+        }
+        self.hops
+            .iter()
+            .map(|hop| hop.pool.to_string())
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    pub fn log(&self) {
+        info!(
+            "[meteora] {} bid={:.6} ask={:.6} spot={:?} bid_impact_bps={:?} ask_impact_bps={:?} sell_fee={:.4}% buy_fee={:.4}% commitment={:?} stale={} missing_bin_arrays={} route={:?}",
+            self.symbol,
+            self.bid_price,
+            self.ask_price,
+            self.spot_price,
+            self.bid_impact_bps,
+            self.ask_impact_bps,
+            self.sell_fee_pct,
+            self.buy_fee_pct,
+            self.commitment,
+            self.stale,
+            self.missing_bin_arrays,
+            self.route,
         );
+    }
+}
+
+/// One hop of a route quote, in the tokens of the hop pool
+#[derive(Debug, Clone)]
+pub struct RouteHop {
+    pub pool: Pubkey,
+    /// Hop input token sold for its output token
+    pub sell: SwapQuote,
+    /// Hop output token spent back on its input token
+    pub buy: SwapQuote,
+    pub bid_price: Decimal,
+    pub ask_price: Decimal,
+    pub spot_price: Option<Decimal>,
+    pub sell_fee_pct: Decimal,
+    pub buy_fee_pct: Decimal,
+    pub fee_rate: Option<PoolFeeRate>,
+}
+
+impl From<&PriceQuote> for RouteHop {
+    fn from(quote: &PriceQuote) -> Self {
         Self {
-            db_pool,
-            rpc_client,
-            shutdown: Arc::new(AtomicBool::new(false)),
+            pool: quote.pool,
+            sell: quote.sell.clone(),
+            buy: quote.buy.clone(),
+            bid_price: quote.bid_price,
+            ask_price: quote.ask_price,
+            spot_price: quote.spot_price,
+            sell_fee_pct: quote.sell_fee_pct,
+            buy_fee_pct: quote.buy_fee_pct,
+            fee_rate: quote.fee_rate,
         }
     }
+}
 
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.get_price("TRUMPUSDC", 1_000_000).await?;
-        Ok(())
+/// Bin arrays a quote needs that the RPC returned no account for.
+/// Quoting without them could silently stop short of the requested amount.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingBinArrays(pub Vec<Pubkey>);
+
+impl std::fmt::Display for MissingBinArrays {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} required bin arrays are missing: {}",
+            self.0.len(),
+            self.0
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
     }
+}
 
-    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.shutdown.store(true, Ordering::Relaxed);
-        Ok(())
+impl std::error::Error for MissingBinArrays {}
+
+/// Bitmap extension lookup of one pool
+struct CachedBitmapExtension {
+    /// Derived address of the extension account
+    key: Pubkey,
+    /// `None` when the pool has no extension account
+    extension: Option<BinArrayBitmapExtension>,
+    fetched_at: Instant,
+}
+
+/// Bitmap extensions per pool. Extensions are rarely created or closed, so both present and
+/// absent lookups are kept for `ttl`; RPC errors are never cached.
+pub struct BitmapExtensionCache {
+    entries: RwLock<HashMap<Pubkey, CachedBitmapExtension>>,
+    ttl: Duration,
+}
+
+impl BitmapExtensionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            ttl,
+        }
     }
 
-    pub async fn get_price(
+    /// Bitmap extension of `lb_pair`, `None` when the account does not exist
+    pub async fn get<F: AccountFetcher>(
         &self,
-        symbol: &str,
-        amount_in: u64,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let trade_pairs = get_trade_pairs();
-        let trade_config = trade_pairs.get(symbol).ok_or("Trade config not found")?;
-        let lb_pair = trade_config.pool_pubkey;
-        let swap_for_y = false; // Swap TRUMP for USDC
-
-        // Fetch the LB pair state from the chain
-        let lb_pair_state: LbPair = self
-            .rpc_client
-            .get_account_and_deserialize(&lb_pair, |account| {
-                Ok(bytemuck::pod_read_unaligned(&account.data[8..]))
+        fetcher: &F,
+        lb_pair: Pubkey,
+    ) -> Result<Option<BinArrayBitmapExtension>, Box<dyn std::error::Error>> {
+        if let Some(entry) = self.entries.read().unwrap().get(&lb_pair)
+            && entry.fetched_at.elapsed() < self.ttl
+        {
+            return Ok(entry.extension);
+        }
+        let key = self.key(lb_pair);
+        let extension = fetcher
+            .fetch_accounts(&[key])
+            .await?
+            .pop()
+            .flatten()
+            .map(|account| {
+                read_anchor_account(
+                    "BinArrayBitmapExtension",
+                    &account.data,
+                    &BIN_ARRAY_BITMAP_EXTENSION_DISCRIMINATOR,
+                )
+                .map_err(|e| format!("Invalid bitmap extension {}: {}", key, e))
             })
-            .await?;
+            .transpose()?;
+        self.entries.write().unwrap().insert(
+            lb_pair,
+            CachedBitmapExtension {
+                key,
+                extension,
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(extension)
+    }
 
-        // Get bitmap extension (optional, for pools with extended liquidity range)
-        let bitmap_extension = self.fetch_bitmap_extension(lb_pair).await?;
+    /// Address of the bitmap extension of `lb_pair`, derived once per pool
+    pub fn key(&self, lb_pair: Pubkey) -> Pubkey {
+        self.entries
+            .read()
+            .unwrap()
+            .get(&lb_pair)
+            .map(|entry| entry.key)
+            .unwrap_or_else(|| derive_bin_array_bitmap_extension(lb_pair).0)
+    }
+}
 
-        // Get bin arrays needed for the swap (4 is usually enough for most swaps)
-        let bin_arrays_for_swap = get_bin_array_pubkeys_for_swap(
-            lb_pair,
-            &lb_pair_state,
-            bitmap_extension.as_ref(),
-            swap_for_y,
-            4,
-        )?;
-        // Fetch required accounts for quote calculation
-        let quote_accounts = self
-            .fetch_quote_required_accounts(lb_pair, &lb_pair_state, bin_arrays_for_swap)
-            .await?;
+/// Fee rate a DLMM pool charges on swaps, derived from its LbPair parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolFeeRate {
+    /// Static part of the fee, from the base factor and bin step
+    pub base_fee_bps: Decimal,
+    /// Part of the fee that ramps with the volatility accumulator
+    pub variable_fee_bps: Decimal,
+    /// Fee charged on a swap, capped at the program's maximum fee rate
+    pub total_fee_bps: Decimal,
+}
+
+/// Token amounts held by a pool and their value in quote token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolLiquidity {
+    /// Base token held by the pool, in UI units
+    pub base_amount: Decimal,
+    /// Quote token held by the pool, in UI units
+    pub quote_amount: Decimal,
+    /// Both amounts valued in quote token at the spot price, `None` without a spot price
+    pub notional: Option<Decimal>,
+}
+
+impl PoolLiquidity {
+    /// Value raw pool amounts at `spot_price` quote tokens per base token
+    pub(super) fn new(
+        base_amount: u128,
+        quote_amount: u128,
+        base_decimals: u32,
+        quote_decimals: u32,
+        spot_price: Option<Decimal>,
+    ) -> Self {
+        let base_amount = Decimal::from_i128_with_scale(base_amount as i128, base_decimals);
+        let quote_amount = Decimal::from_i128_with_scale(quote_amount as i128, quote_decimals);
+        let notional = spot_price.and_then(|price| {
+            base_amount
+                .checked_mul(price)
+                .and_then(|base_value| base_value.checked_add(quote_amount))
+        });
+        Self {
+            base_amount,
+            quote_amount,
+            notional,
+        }
+    }
+
+    /// Whether the pool is known to hold less than `floor` quote tokens of liquidity
+    pub fn is_below(&self, floor: Decimal) -> bool {
+        self.notional.is_some_and(|notional| notional < floor)
+    }
+}
+
+/// Best bid and best ask of a symbol across all of its pools
+#[derive(Debug, Clone)]
+pub struct BestPriceQuote {
+    pub symbol: String,
+    /// Quote of the pool paying the most for the base token
+    pub bid: PriceQuote,
+    /// Quote of the pool charging the least for the base token
+    pub ask: PriceQuote,
+    /// Number of pools that produced a quote
+    pub pools_quoted: usize,
+    /// Whether the best bid or ask pool is below the screener's liquidity floor,
+    /// in which case consumers should not act on the quote
+    pub degraded: bool,
+}
+
+impl BestPriceQuote {
+    /// Age of the older of the best bid and ask quotes
+    pub fn age(&self, now: DateTime<Utc>) -> Duration {
+        self.bid.age(now).max(self.ask.age(now))
+    }
+
+    pub fn log(&self) {
+        info!(
+            "[meteora] {} best bid={:.6} ({}) best ask={:.6} ({}) across {} pools",
+            self.symbol,
+            self.bid.bid_price,
+            self.bid.pool,
+            self.ask.ask_price,
+            self.ask.pool,
+            self.pools_quoted,
+        );
+    }
+}
+
+/// Marginal price of a pool's active bin, read from the LbPair account alone
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotPrice {
+    pub symbol: String,
+    pub pool: Pubkey,
+    pub active_id: i32,
+    /// Active bin price in quote tokens per base token
+    pub price: Decimal,
+    /// Latest slot seen by the screener; the LbPair itself is read at the current slot
+    pub slot: u64,
+}
+
+/// Base token that has to be sold to receive a requested amount of quote token
+#[derive(Debug, Clone)]
+pub struct ExactOutQuote {
+    pub symbol: String,
+    /// Pool requiring the smallest input
+    pub pool: Pubkey,
+    /// Slot of the Clock sysvar the quote was computed with
+    pub slot: u64,
+    /// Base token to sell, in raw units
+    pub amount_in: u64,
+    /// Quote token actually received for `amount_in`, at least the requested amount
+    pub amount_out: u64,
+    pub fee: u64,
+    /// Number of exact-in quotes evaluated to find `amount_in`
+    pub iterations: u32,
+}
+
+/// Bounds of the exact-out search over exact-in quotes
+#[derive(Debug, Clone, Copy)]
+pub struct ExactOutSearch {
+    /// Maximum distance, in input token base units, from the smallest sufficient input
+    pub tolerance: u64,
+    /// Maximum number of exact-in quotes evaluated
+    pub max_iterations: u32,
+}
+
+impl ExactOutSearch {
+    /// Read `METEORA_EXACT_OUT_TOLERANCE` and `METEORA_EXACT_OUT_MAX_ITERATIONS`, falling back to the defaults
+    pub fn from_env() -> Self {
+        Self {
+            tolerance: std::env::var("METEORA_EXACT_OUT_TOLERANCE")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_EXACT_OUT_TOLERANCE),
+            max_iterations: std::env::var("METEORA_EXACT_OUT_MAX_ITERATIONS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_EXACT_OUT_MAX_ITERATIONS),
+        }
+    }
+}
+
+/// Sampled cross-check of local quotes against `simulateTransaction` of the same swap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuoteVerification {
+    /// Wallet the simulated swap is built for. It must hold the sold amount of base token
+    /// and pay the fee, since simulation runs the full swap against its token accounts.
+    pub wallet: Pubkey,
+    /// Share of quotes checked, between 0 and 1
+    pub sample_rate: f64,
+    /// Deviation above which a check is logged as an error
+    pub max_deviation_bps: Decimal,
+}
+
+impl QuoteVerification {
+    /// Read `METEORA_VERIFY_QUOTES`, `METEORA_VERIFY_WALLET`, `METEORA_VERIFY_SAMPLE_RATE` and
+    /// `METEORA_VERIFY_MAX_DEVIATION_BPS`. Returns `None` when verification is disabled.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        resolve_quote_verification(
+            std::env::var("METEORA_VERIFY_QUOTES").ok(),
+            std::env::var("METEORA_VERIFY_WALLET").ok(),
+            std::env::var("METEORA_VERIFY_SAMPLE_RATE").ok(),
+            std::env::var("METEORA_VERIFY_MAX_DEVIATION_BPS").ok(),
+        )
+    }
+
+    /// Whether the current quote should be cross-checked
+    fn sampled(&self) -> bool {
+        rand::random::<f64>() < self.sample_rate
+    }
+}
+
+fn resolve_quote_verification(
+    enabled: Option<String>,
+    wallet: Option<String>,
+    sample_rate: Option<String>,
+    max_deviation_bps: Option<String>,
+) -> Result<Option<QuoteVerification>, Box<dyn std::error::Error>> {
+    if !matches!(enabled.as_deref(), Some("true") | Some("1")) {
+        return Ok(None);
+    }
+    let wallet =
+        wallet.ok_or("METEORA_VERIFY_WALLET must be set when METEORA_VERIFY_QUOTES is enabled")?;
+    let wallet = wallet
+        .parse::<Pubkey>()
+        .map_err(|e| format!("Invalid METEORA_VERIFY_WALLET '{}': {}", wallet, e))?;
+    let sample_rate = sample_rate
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .unwrap_or(DEFAULT_VERIFY_SAMPLE_RATE);
+    let max_deviation_bps = max_deviation_bps
+        .and_then(|value| value.parse::<Decimal>().ok())
+        .unwrap_or(Decimal::from(DEFAULT_VERIFY_MAX_DEVIATION_BPS));
+    Ok(Some(QuoteVerification {
+        wallet,
+        sample_rate,
+        max_deviation_bps,
+    }))
+}
+
+/// Output of a simulated swap: balance of the output token account after the swap,
+/// as returned by the simulation, minus its balance before
+fn simulated_amount_out(
+    balance_before: u64,
+    accounts: Option<Vec<Option<Account>>>,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let account = accounts
+        .and_then(|accounts| accounts.into_iter().next().flatten())
+        .ok_or("Simulation returned no output token account")?;
+    let balance_after = token_account_amount(&account)?;
+    balance_after.checked_sub(balance_before).ok_or_else(|| {
+        format!(
+            "Output token balance decreased from {} to {}",
+            balance_before, balance_after
+        )
+        .into()
+    })
+}
+
+/// Deviation of the simulated output from the local quote, in bps of the local output
+fn quote_deviation_bps(local_amount_out: u64, simulated_amount_out: u64) -> Option<Decimal> {
+    if local_amount_out == 0 {
+        return None;
+    }
+    let local = Decimal::from(local_amount_out);
+    Some((Decimal::from(simulated_amount_out) - local) / local * Decimal::from(10_000))
+}
+
+/// Synthetic orderbook built from ladders of quotes against fetched pool states
+#[derive(Debug, Clone)]
+pub struct DepthLadder {
+    pub book: market::OrderBook,
+    /// Latest slot of the pool states the ladder was computed with
+    pub slot: u64,
+    /// Whether a pool could not absorb every requested size
+    pub truncated: bool,
+    /// Pools whose levels are included in the book
+    pub pools: Vec<Pubkey>,
+}
+
+/// Pool state fetched once and shared by every quote of a tick
+struct PoolSnapshot {
+    lb_pair: Pubkey,
+    pool: PoolConfig,
+    base_decimals: u32,
+    quote_decimals: u32,
+    /// Transfer fee configs of Token-2022 mints, `None` for fee-less tokens
+    transfer_fee_x: Option<TransferFeeConfig>,
+    transfer_fee_y: Option<TransferFeeConfig>,
+    bitmap_extension: Option<BinArrayBitmapExtension>,
+    accounts: SwapQuoteAccounts,
+    /// Wall time the account fetch started at
+    fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of the account fetch
+    fetch_latency: Duration,
+}
+
+impl PoolSnapshot {
+    /// Swap direction that sells the base token
+    fn sell_swap_for_y(&self) -> bool {
+        // Selling base means swapping X for Y when base is token X
+        self.pool.base_is_x
+    }
+
+    fn base_decimals(&self) -> u32 {
+        self.base_decimals
+    }
+
+    fn quote_decimals(&self) -> u32 {
+        self.quote_decimals
+    }
+
+    fn block_time(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.accounts.clock.unix_timestamp, 0).unwrap_or_else(Utc::now)
+    }
+
+    /// Quote an exact-in swap against the snapshot, net of transfer fees
+    fn quote(
+        &self,
+        amount_in: u64,
+        swap_for_y: bool,
+    ) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+        let (fee_in, fee_out) = if swap_for_y {
+            (self.transfer_fee_x.as_ref(), self.transfer_fee_y.as_ref())
+        } else {
+            (self.transfer_fee_y.as_ref(), self.transfer_fee_x.as_ref())
+        };
+        apply_transfer_fees(
+            amount_in,
+            self.accounts.clock.epoch,
+            fee_in,
+            fee_out,
+            |amount| self.quote_gross(amount, swap_for_y),
+        )
+    }
 
-        // Calculate the swap quote using commons::quote_exact_in
+    /// Raw pool quote, ignoring transfer fees
+    fn quote_gross(
+        &self,
+        amount_in: u64,
+        swap_for_y: bool,
+    ) -> Result<SwapQuote, Box<dyn std::error::Error>> {
         let quote = quote_exact_in(
-            lb_pair,
-            &quote_accounts.lb_pair_state,
+            self.lb_pair,
+            &self.accounts.lb_pair_state,
             amount_in,
             swap_for_y,
-            quote_accounts.bin_arrays,
-            bitmap_extension.as_ref(),
-            &quote_accounts.clock,
-            &quote_accounts.mint_x_account,
-            &quote_accounts.mint_y_account,
+            self.accounts.bin_arrays.clone(),
+            self.bitmap_extension.as_ref(),
+            &self.accounts.clock,
+            &self.accounts.mint_x_account,
+            &self.accounts.mint_y_account,
         )?;
-
-        tracing::info!(
-            "Swap quote: amount_in={}, amount_out={}, fee={}",
+        Ok(SwapQuote::without_transfer_fees(
             amount_in,
             quote.amount_out,
-            quote.fee
+            quote.fee,
+        ))
+    }
+
+    /// Input needed to receive `amount_out` according to the commons exact-out quote
+    fn quote_exact_out(
+        &self,
+        amount_out: u64,
+        swap_for_y: bool,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let quote = quote_exact_out(
+            self.lb_pair,
+            &self.accounts.lb_pair_state,
+            amount_out,
+            swap_for_y,
+            self.accounts.bin_arrays.clone(),
+            self.bitmap_extension.as_ref(),
+            &self.accounts.clock,
+            &self.accounts.mint_x_account,
+            &self.accounts.mint_y_account,
+        )?;
+        Ok(quote.amount_in)
+    }
+}
+
+pub struct MeteoraScreener {
+    pub db_pool: DatabasePool,
+    pub rpc_client: FailoverRpcClient,
+    /// Latency and errors of every RPC call, summarized every minute
+    pub rpc_metrics: Arc<LatencyMetrics>,
+    /// Priority fee and SOL price estimates pricing the landing of a swap
+    pub fee_estimator: Arc<SolanaFeeEstimator>,
+    /// Commitment of pool, bin array and mint reads
+    pub commitment: CommitmentConfig,
+    /// Commitment of the Clock sysvar read, usually processed for freshness
+    pub clock_commitment: CommitmentConfig,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Delay between two reloads of the trade pairs table
+    pub pairs_refresh_interval: Duration,
+    /// Delay between two pool discoveries of auto-discovered pairs
+    pub discovery_refresh_interval: Duration,
+    /// Meteora API client resolving the pools of auto-discovered pairs
+    pub meteora_api: Arc<MeteoraApiClient>,
+    /// Number of pairs quoted concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    /// Bounds of `get_price_exact_out` searches
+    pub exact_out_search: ExactOutSearch,
+    /// Sampled simulation cross-check of quotes, `None` when disabled
+    pub quote_verification: Option<QuoteVerification>,
+    /// Delay between two active bin spot price polls, `None` when disabled
+    pub spot_poll_interval: Option<Duration>,
+    /// Pool liquidity, in quote token units, below which a pair is marked degraded
+    pub min_pool_liquidity: Decimal,
+    /// Drift between the Clock sysvar and wall time above which a quote is tagged stale
+    pub max_clock_drift: Duration,
+    /// Whether a stale snapshot is fetched again from the next healthiest RPC endpoint
+    pub retry_stale_clock: bool,
+    /// Whether pools are quoted without missing bin arrays instead of failing with `MissingBinArrays`
+    pub allow_partial_quotes: bool,
+    /// Age above which a best quote is rejected instead of being returned or persisted
+    pub max_quote_age: Duration,
+    /// Trade configs loaded from the database, keyed by symbol
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    /// Last known pools of auto-discovered pairs, kept when a discovery fails
+    discovered_pools: DiscoveredPools,
+    /// Cache of pool, bin array and mint accounts between quotes
+    account_cache: AccountCache,
+    /// Bitmap extension of every pool quoted so far
+    bitmap_extensions: BitmapExtensionCache,
+    /// Latest slot seen in a fetched Clock sysvar
+    last_slot: AtomicU64,
+    /// Decimals of every mint quoted so far
+    mint_decimals: RwLock<HashMap<Pubkey, u32>>,
+    /// Pool and bin array accounts of the latest snapshot of every pool, keyed by pool
+    watched_accounts: RwLock<HashMap<Pubkey, Vec<Pubkey>>>,
+    /// Symbols whose latest best quote came from a pool below `min_pool_liquidity`
+    degraded_pairs: RwLock<HashSet<String>>,
+}
+
+/// RPC settings shared by the Meteora screeners, resolved before any screener is built
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeteoraConfig {
+    /// RPC endpoint urls, tried in order of health
+    pub rpc_endpoints: Vec<String>,
+    /// Commitment of pool, bin array and mint reads
+    pub commitment: CommitmentConfig,
+    /// Commitment of the Clock sysvar read
+    pub clock_commitment: CommitmentConfig,
+}
+
+impl MeteoraConfig {
+    /// Read `RPC_ENDPOINTS` (or `HELIUS_API_KEY`), `SOLANA_COMMITMENT` and `SOLANA_CLOCK_COMMITMENT`
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            rpc_endpoints: rpc_endpoints_from_env()?,
+            commitment: commitment_from_env("SOLANA_COMMITMENT", CommitmentConfig::confirmed())?,
+            clock_commitment: commitment_from_env(
+                "SOLANA_CLOCK_COMMITMENT",
+                CommitmentConfig::processed(),
+            )?,
+        })
+    }
+}
+
+impl MeteoraScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, MeteoraConfig::from_env()?)
+    }
+
+    /// Build the screener on already resolved RPC settings; the other settings are read from the env
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: MeteoraConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        info!(
+            "Meteora RPC endpoints: {}",
+            config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        info!(
+            "Meteora RPC commitment: {:?} (clock {:?})",
+            config.commitment.commitment, config.clock_commitment.commitment
+        );
+        let rpc_metrics = Arc::new(LatencyMetrics::new("rpc"));
+        let rpc_client = FailoverRpcClient::from_urls(config.rpc_endpoints, config.commitment)
+            .with_metrics(rpc_metrics.clone());
+        Ok(Self {
+            db_pool,
+            rpc_client,
+            rpc_metrics,
+            fee_estimator: Arc::new(SolanaFeeEstimator::new(LandingCostConfig::from_env())),
+            commitment: config.commitment,
+            clock_commitment: config.clock_commitment,
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            pairs_refresh_interval: pairs_refresh_interval_from_env(),
+            discovery_refresh_interval: discovery_refresh_interval_from_env(),
+            meteora_api: Arc::new(
+                MeteoraApiClient::from_env()
+                    .map_err(|e| format!("Failed to build Meteora API client: {}", e))?,
+            ),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            exact_out_search: ExactOutSearch::from_env(),
+            quote_verification: QuoteVerification::from_env()?,
+            spot_poll_interval: spot_poll_interval_from_env(),
+            min_pool_liquidity: min_pool_liquidity_from_env(),
+            max_clock_drift: max_clock_drift_from_env(),
+            retry_stale_clock: matches!(
+                std::env::var("METEORA_RETRY_STALE_CLOCK").as_deref(),
+                Ok("true") | Ok("1")
+            ),
+            allow_partial_quotes: matches!(
+                std::env::var("METEORA_ALLOW_PARTIAL_QUOTES").as_deref(),
+                Ok("true") | Ok("1")
+            ),
+            max_quote_age: max_quote_age_from_env(),
+            trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+            discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+            account_cache: account_cache_from_env(),
+            bitmap_extensions: BitmapExtensionCache::new(bitmap_extension_ttl_from_env()),
+            last_slot: AtomicU64::new(0),
+            mint_decimals: RwLock::new(HashMap::new()),
+            watched_accounts: RwLock::new(HashMap::new()),
+            degraded_pairs: RwLock::new(HashSet::new()),
+        })
+    }
+
+    /// Poll quotes for every configured pair until the screener is stopped.
+    /// Each tick quotes the pairs concurrently, one task per pair.
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Meteora screener (poll interval {:?})...",
+            self.poll_interval
         );
 
-        // Calculate price impact and other metrics
-        let effective_price = if quote.amount_out > 0 {
-            (amount_in as f64) / (quote.amount_out as f64)
-        } else {
-            0.0
+        let dex_writer = Arc::new(DexMarketWriter::for_database(self.db_pool.clone(), VENUE));
+        let runner = DexScreenerRunner {
+            quoter: self.clone(),
+            db_pool: self.db_pool.clone(),
+            dex_writer: dex_writer.clone(),
+            shutdown: self.shutdown.clone(),
+            poll_interval: self.poll_interval,
+            pairs_refresh_interval: self.pairs_refresh_interval,
+            max_concurrent_pairs: self.max_concurrent_pairs,
+            max_quote_age: self.max_quote_age,
+            trade_pairs: self.trade_pairs.clone(),
+            discovered_pools: self.discovered_pools.clone(),
         };
+        runner.load_trade_pairs().await?;
 
-        tracing::info!("Effective price: {:.6}", effective_price);
-        tracing::info!(
-            "Fee percentage: {:.4}%",
-            (quote.fee as f64 / amount_in as f64) * 100.0
-        );
+        let discoverer = tokio::spawn(refresh_discovered_pools(
+            self.meteora_api.clone(),
+            self.db_pool.clone(),
+            self.trade_pairs.clone(),
+            self.discovered_pools.clone(),
+            self.shutdown.clone(),
+            self.discovery_refresh_interval,
+        ));
+        let metrics_reporter = {
+            let screener = self.clone();
+            tokio::spawn(async move {
+                screener
+                    .rpc_metrics
+                    .report_every(
+                        Duration::from_secs(DEFAULT_SUMMARY_INTERVAL_SECS),
+                        &screener.shutdown,
+                    )
+                    .await
+            })
+        };
+        let fee_refresher = {
+            let screener = self.clone();
+            tokio::spawn(async move {
+                screener
+                    .fee_estimator
+                    .run(
+                        &screener.rpc_client,
+                        || screener.landing_accounts(),
+                        &screener.shutdown,
+                    )
+                    .await
+            })
+        };
+        let spot_poller = self.spot_poll_interval.map(|interval| {
+            tokio::spawn(self.clone().poll_spot_prices(interval, dex_writer.clone()))
+        });
+        #[cfg(feature = "geyser")]
+        let geyser = crate::solana::geyser::GeyserConfig::from_env().map(|config| {
+            let screener = self.clone();
+            tokio::spawn(async move {
+                crate::solana::geyser::GeyserSource::new(config)
+                    .run(
+                        &screener.account_cache,
+                        || screener.watched_accounts(),
+                        &screener.shutdown,
+                    )
+                    .await
+            })
+        });
 
+        runner.poll().await;
+
+        discoverer.abort();
+        metrics_reporter.abort();
+        fee_refresher.abort();
+        if let Some(spot_poller) = spot_poller {
+            spot_poller.abort();
+            // Spot prices submitted after the runner's final flush
+            dex_writer.flush().await;
+        }
+        #[cfg(feature = "geyser")]
+        if let Some(geyser) = geyser {
+            geyser.abort();
+        }
         Ok(())
     }
 
-    /// Fetch all required accounts for swap quote calculation
-    async fn fetch_quote_required_accounts(
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Quote both swap directions on every pool and route of a pair concurrently and keep
+    /// the best bid and best ask. Pools and routes that fail to quote are skipped with a warning.
+    /// `amount_in` is the amount of base token sold; the buy side spends the quote
+    /// token received for it so both prices refer to equivalent notional.
+    pub async fn get_price(
         &self,
-        lb_pair: Pubkey,
-        lb_pair_state: &LbPair,
-        bin_arrays_for_swap: Vec<Pubkey>,
-    ) -> Result<SwapQuoteAccounts, Box<dyn std::error::Error>> {
-        let prerequisite_accounts = [
-            lb_pair,
-            solana_sdk::sysvar::clock::ID,
-            lb_pair_state.token_x_mint,
-            lb_pair_state.token_y_mint,
-        ];
+        symbol: &str,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        get_best_price(
+            self,
+            &self.trade_pairs,
+            symbol,
+            amount_in,
+            self.max_quote_age,
+        )
+        .await
+    }
 
-        let accounts_to_fetch =
-            [prerequisite_accounts.to_vec(), bin_arrays_for_swap.clone()].concat();
+    /// Accounts a swap on any configured pool would lock: the DLMM program and the pools
+    fn landing_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![dlmm::ID];
+        for trade_config in self.trade_pairs.read().unwrap().values() {
+            for pool_pubkey in trade_config.pool_pubkeys() {
+                if !accounts.contains(&pool_pubkey) {
+                    accounts.push(pool_pubkey);
+                }
+            }
+        }
+        accounts
+    }
 
-        let accounts = self
-            .rpc_client
-            .get_multiple_accounts(&accounts_to_fetch)
-            .await?;
+    /// Whether the latest best quote of `symbol` came from a pool below the liquidity floor
+    pub fn is_degraded(&self, symbol: &str) -> bool {
+        self.degraded_pairs.read().unwrap().contains(symbol)
+    }
 
-        // Parse accounts
-        let mut index = 0;
+    /// Record the liquidity state of a pair, logging when it changes
+    fn mark_degraded(&self, best: &BestPriceQuote) {
+        let mut degraded_pairs = self.degraded_pairs.write().unwrap();
+        if best.degraded {
+            if degraded_pairs.insert(best.symbol.clone()) {
+                warn!(
+                    "Meteora pair {} degraded: liquidity bid pool {} = {:?}, ask pool {} = {:?}, below {}",
+                    best.symbol,
+                    best.bid.pool,
+                    best.bid.liquidity.notional,
+                    best.ask.pool,
+                    best.ask.liquidity.notional,
+                    self.min_pool_liquidity
+                );
+            }
+        } else if degraded_pairs.remove(&best.symbol) {
+            info!(
+                "Meteora pair {} liquidity back above {}",
+                best.symbol, self.min_pool_liquidity
+            );
+        }
+    }
+
+    /// Quote both swap directions of a single pool against the same fetched pool state
+    async fn get_pool_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        retry_with_more_bin_arrays(symbol, pool.bin_array_count, |count| {
+            self.quote_price(symbol, pool, amount_in, count)
+        })
+        .await
+    }
 
-        // Skip LB pair (we already have it)
-        index += 1;
+    async fn quote_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+        bin_array_count: u8,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let snapshot = self.fetch_pool_snapshot(pool, bin_array_count).await?;
 
-        // Clock account
-        let clock_account = accounts
-            .get(index)
-            .and_then(ToOwned::to_owned)
-            .ok_or("Failed to fetch clock account")?;
-        let clock: solana_sdk::clock::Clock = bincode::deserialize(clock_account.data.as_ref())?;
-        index += 1;
-
-        // Mint X account
-        let mint_x_account = accounts
-            .get(index)
-            .and_then(ToOwned::to_owned)
-            .ok_or("Failed to fetch mint X account")?;
-        index += 1;
-
-        // Mint Y account
-        let mint_y_account = accounts
-            .get(index)
-            .and_then(ToOwned::to_owned)
-            .ok_or("Failed to fetch mint Y account")?;
-
-        // Bin array accounts
-        let bin_array_accounts = accounts
-            .get(prerequisite_accounts.len()..)
-            .ok_or("Failed to fetch bin array accounts")?
-            .to_vec();
-
-        let bin_arrays: HashMap<Pubkey, BinArray> = bin_array_accounts
-            .into_iter()
-            .zip(bin_arrays_for_swap.iter())
-            .filter_map(|(account, &key)| {
-                let account = account?;
-                let bin_array: BinArray = bytemuck::pod_read_unaligned(&account.data[8..]);
-                Some((key, bin_array))
-            })
-            .collect();
+        let sell = snapshot.quote(amount_in, snapshot.sell_swap_for_y())?;
+        if sell.amount_out == 0 {
+            return Err(format!("Pool returned no output when selling {}", symbol).into());
+        }
+        if let Some(verification) = &self.quote_verification
+            && verification.sampled()
+        {
+            self.check_quote(verification, symbol, &snapshot, &sell, bin_array_count)
+                .await;
+        }
+        let buy = snapshot.quote(sell.amount_out, !snapshot.sell_swap_for_y())?;
 
-        Ok(SwapQuoteAccounts {
-            lb_pair_state: *lb_pair_state,
-            clock,
-            mint_x_account,
-            mint_y_account,
-            bin_arrays,
-        })
+        let price_quote = build_price_quote(symbol, &snapshot, sell, buy)?.with_landing_cost(
+            self.fee_estimator.current_landing_cost_lamports(),
+            self.fee_estimator.sol_price(),
+        );
+        price_quote.log();
+        self.save_pool_fees(&price_quote);
+
+        Ok(price_quote)
     }
 
-    async fn fetch_bitmap_extension(
+    /// Quote both swap directions of a route against the same fetched pool states
+    async fn get_route_price(
         &self,
-        lb_pair: Pubkey,
-    ) -> Result<Option<BinArrayBitmapExtension>, Box<dyn std::error::Error>> {
-        let (bitmap_extension_key, _bump) = derive_bin_array_bitmap_extension(lb_pair);
-        let bitmap_extension: Option<BinArrayBitmapExtension> = self
+        symbol: &str,
+        route: &RouteConfig,
+        amount_in: u64,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let bin_array_count = route
+            .hops
+            .iter()
+            .map(|hop| hop.bin_array_count)
+            .max()
+            .unwrap_or(DEFAULT_BIN_ARRAY_COUNT);
+        retry_with_more_bin_arrays(symbol, bin_array_count, |count| {
+            self.quote_route(symbol, route, amount_in, count)
+        })
+        .await
+    }
+
+    /// Quote a route hop by hop. Both hop pools are read with one batched fetch.
+    async fn quote_route(
+        &self,
+        symbol: &str,
+        route: &RouteConfig,
+        amount_in: u64,
+        bin_array_count: u8,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let pools: Vec<(PoolConfig, u8)> = route
+            .hops
+            .iter()
+            .map(|hop| (hop.clone(), bin_array_count))
+            .collect();
+        let snapshots = self.fetch_pool_snapshots(&pools).await?;
+
+        let price_quote = quote_route_hops(symbol, route, &snapshots[0], &snapshots[1], amount_in)?
+            .with_landing_cost(
+                self.fee_estimator.current_landing_cost_lamports(),
+                self.fee_estimator.sol_price(),
+            );
+        price_quote.log();
+        self.save_pool_fees(&price_quote);
+
+        Ok(price_quote)
+    }
+
+    /// Cross-check a sell quote against a simulation of the same swap and persist the result.
+    /// Failed checks are only logged, they never fail the quote.
+    async fn check_quote(
+        &self,
+        verification: &QuoteVerification,
+        symbol: &str,
+        snapshot: &PoolSnapshot,
+        sell: &SwapQuote,
+        bin_array_count: u8,
+    ) {
+        let simulated = match self
+            .simulate_sell(
+                verification.wallet,
+                snapshot,
+                sell.amount_in,
+                bin_array_count,
+            )
+            .await
+        {
+            Ok(simulated) => simulated,
+            Err(e) => {
+                warn!(
+                    "Meteora quote check for {} on {} failed: {}",
+                    symbol, snapshot.lb_pair, e
+                );
+                return;
+            }
+        };
+        let Some(deviation_bps) = quote_deviation_bps(sell.amount_out, simulated) else {
+            return;
+        };
+
+        if deviation_bps.abs() > verification.max_deviation_bps {
+            error!(
+                "[meteora] {} quote on {} deviates {:.2} bps from simulation: local {} simulated {} (slot {})",
+                symbol,
+                snapshot.lb_pair,
+                deviation_bps,
+                sell.amount_out,
+                simulated,
+                snapshot.accounts.slot
+            );
+        } else {
+            info!(
+                "[meteora] {} quote on {} matches simulation within {:.2} bps",
+                symbol, snapshot.lb_pair, deviation_bps
+            );
+        }
+
+        let check = QuoteCheck {
+            exchange: VENUE.to_string(),
+            trade_pair: symbol.to_string(),
+            pool_address: snapshot.lb_pair.to_string(),
+            block_number: snapshot.accounts.slot,
+            amount_in: sell.amount_in,
+            local_amount_out: sell.amount_out,
+            simulated_amount_out: simulated,
+            deviation_bps,
+            check_time: Utc::now(),
+        };
+        let db_pool = self.db_pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = insert_quote_check(&db_pool, &check).await {
+                error!(
+                    "Failed to insert quote check for {}: {}",
+                    check.trade_pair, e
+                );
+            }
+        });
+    }
+
+    /// Simulate selling `amount_in` of base token from `wallet` on the snapshot pool and
+    /// return the quote token received
+    async fn simulate_sell(
+        &self,
+        wallet: Pubkey,
+        snapshot: &PoolSnapshot,
+        amount_in: u64,
+        bin_array_count: u8,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let lb_pair_state = &snapshot.accounts.lb_pair_state;
+        let swap_for_y = snapshot.sell_swap_for_y();
+        let bin_arrays = get_bin_array_pubkeys_for_swap(
+            snapshot.lb_pair,
+            lb_pair_state,
+            snapshot.bitmap_extension.as_ref(),
+            swap_for_y,
+            bin_array_count,
+        )?;
+        let bitmap_extension = snapshot
+            .bitmap_extension
+            .is_some()
+            .then(|| self.bitmap_extensions.key(snapshot.lb_pair));
+        let user_token_accounts = UserTokenAccounts::associated(wallet, lb_pair_state);
+        let user_token_out = if swap_for_y {
+            user_token_accounts.token_y
+        } else {
+            user_token_accounts.token_x
+        };
+        let ix = build_swap_ix(
+            snapshot.lb_pair,
+            lb_pair_state,
+            amount_in,
+            0,
+            swap_for_y,
+            &user_token_accounts,
+            &bin_arrays,
+            bitmap_extension,
+        )
+        .instruction;
+
+        let balance_before = match self
             .rpc_client
-            .get_account_and_deserialize(&bitmap_extension_key, |account| {
-                Ok(bytemuck::pod_read_unaligned(&account.data[8..]))
+            .get_account_with_commitment(&user_token_out, self.commitment)
+            .await?
+        {
+            Some(account) => token_account_amount(&account)?,
+            None => 0,
+        };
+
+        let transaction = Transaction::new_unsigned(Message::new(&[ix], Some(&wallet)));
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.commitment),
+            // Simulated accounts default to base64 encoding
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                addresses: vec![user_token_out.to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let result = self
+            .rpc_client
+            .simulate_transaction(&transaction, config)
+            .await?;
+        if let Some(err) = result.err {
+            return Err(format!(
+                "Simulated swap failed: {:?} (logs: {})",
+                err,
+                result.logs.unwrap_or_default().join(" | ")
+            )
+            .into());
+        }
+
+        let accounts = result.accounts.map(|accounts| {
+            accounts
+                .into_iter()
+                .map(|account| account.and_then(|account| account.decode::<Account>()))
+                .collect()
+        });
+        simulated_amount_out(balance_before, accounts)
+    }
+
+    /// Find how much base token has to be sold to receive at least `amount_out` quote token,
+    /// using the pool that requires the smallest input.
+    /// The commons exact-out quote seeds a bounded search over exact-in quotes against the
+    /// same pool state, so the returned input is verified to produce the requested output.
+    pub async fn get_price_exact_out(
+        &self,
+        symbol: &str,
+        amount_out: u64,
+    ) -> Result<ExactOutQuote, Box<dyn std::error::Error>> {
+        let trade_config = self.trade_config(symbol)?;
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            retry_with_more_bin_arrays(symbol, pool.bin_array_count, |count| {
+                self.quote_exact_out(symbol, pool, amount_out, count)
             })
             .await
-            .ok();
-        Ok(bitmap_extension)
+            .map_err(|e| e.to_string())
+        }))
+        .await;
+
+        successful_pool_results(symbol, &trade_config.pools, results)
+            .into_iter()
+            .min_by_key(|quote| quote.amount_in)
+            .ok_or_else(|| {
+                format!(
+                    "No Meteora pool could produce {} for {}",
+                    amount_out, symbol
+                )
+                .into()
+            })
+    }
+
+    async fn quote_exact_out(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_out: u64,
+        bin_array_count: u8,
+    ) -> Result<ExactOutQuote, Box<dyn std::error::Error>> {
+        let snapshot = self.fetch_pool_snapshot(pool, bin_array_count).await?;
+        let sell_swap_for_y = snapshot.sell_swap_for_y();
+
+        let hint = match snapshot.quote_exact_out(amount_out, sell_swap_for_y) {
+            Ok(amount_in) => Some(amount_in),
+            Err(e) => {
+                warn!("Meteora exact-out quote for {} failed: {}", symbol, e);
+                None
+            }
+        };
+        let (quote, iterations) =
+            search_exact_out(amount_out, hint, self.exact_out_search, |amount_in| {
+                snapshot.quote(amount_in, sell_swap_for_y)
+            })?;
+
+        info!(
+            "[meteora] {} exact out on {}: sell {} for {} (fee {}, {} iterations)",
+            symbol, snapshot.lb_pair, quote.amount_in, quote.amount_out, quote.fee, iterations
+        );
+        Ok(ExactOutQuote {
+            symbol: symbol.to_string(),
+            pool: snapshot.lb_pair,
+            slot: snapshot.accounts.slot,
+            amount_in: quote.amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            iterations,
+        })
+    }
+
+    /// Quote a ladder of notional sizes (in quote token base units, ascending) against
+    /// every pool of a pair and merge the levels into one synthetic orderbook. Each size
+    /// is first spent buying base, then the base received is sold back, so both sides of
+    /// a ladder point refer to the same notional.
+    pub async fn get_depth_ladder(
+        &self,
+        symbol: &str,
+        sizes: &[u64],
+    ) -> Result<DepthLadder, Box<dyn std::error::Error>> {
+        let trade_config = self.trade_config(symbol)?;
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            self.get_pool_depth_ladder(symbol, pool, sizes)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+        .await;
+        let ladders = successful_pool_results(symbol, &trade_config.pools, results);
+
+        merge_ladders(symbol, ladders)
+            .ok_or_else(|| format!("No Meteora pool could build a ladder for {}", symbol).into())
+    }
+
+    async fn get_pool_depth_ladder(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        sizes: &[u64],
+    ) -> Result<DepthLadder, Box<dyn std::error::Error>> {
+        let snapshot = self.fetch_pool_snapshot(pool, pool.bin_array_count).await?;
+        let sell_swap_for_y = snapshot.sell_swap_for_y();
+
+        let (points, truncated) = collect_ladder_points(sizes, |size| {
+            let buy = snapshot.quote(size, !sell_swap_for_y)?;
+            let sell = snapshot.quote(buy.amount_out, sell_swap_for_y)?;
+            Ok((buy, sell))
+        });
+        if truncated {
+            warn!(
+                "Meteora depth ladder for {} on {} truncated after {} of {} sizes",
+                symbol,
+                snapshot.lb_pair,
+                points.len(),
+                sizes.len()
+            );
+        }
+
+        let book = build_ladder_book(
+            symbol,
+            snapshot.base_decimals(),
+            snapshot.quote_decimals(),
+            &points,
+        );
+        Ok(DepthLadder {
+            book,
+            slot: snapshot.accounts.slot,
+            truncated,
+            pools: vec![snapshot.lb_pair],
+        })
+    }
+
+    fn trade_config(&self, symbol: &str) -> Result<TradeConfig, Box<dyn std::error::Error>> {
+        let trade_config = self
+            .trade_pairs
+            .read()
+            .unwrap()
+            .get(symbol)
+            .cloned()
+            .ok_or("Trade config not found")?;
+        Ok(trade_config)
+    }
+
+    /// Fetch a pool snapshot. A snapshot read with a stale Clock is fetched once more from
+    /// the next healthiest endpoint when `retry_stale_clock` is set and a fallback exists.
+    async fn fetch_pool_snapshot(
+        &self,
+        pool: &PoolConfig,
+        bin_array_count: u8,
+    ) -> Result<PoolSnapshot, Box<dyn std::error::Error>> {
+        let mut snapshots = self
+            .fetch_pool_snapshots(&[(pool.clone(), bin_array_count)])
+            .await?;
+        Ok(snapshots.remove(0))
+    }
+
+    /// Fetch the snapshots of several pools at the same slot, in `pools` order.
+    /// Stale snapshots are retried like in `fetch_pool_snapshot`.
+    async fn fetch_pool_snapshots(
+        &self,
+        pools: &[(PoolConfig, u8)],
+    ) -> Result<Vec<PoolSnapshot>, Box<dyn std::error::Error>> {
+        let snapshots = self.fetch_pool_snapshots_once(pools).await?;
+        if snapshots.iter().any(|snapshot| snapshot.accounts.stale)
+            && self.retry_stale_clock
+            && self.rpc_client.demote_preferred_endpoint()
+        {
+            return self.fetch_pool_snapshots_once(pools).await;
+        }
+        Ok(snapshots)
+    }
+
+    /// Fetch everything needed to quote the given pools: pool states, bitmap extensions,
+    /// clock, mints and the bin arrays around the active bin in both directions.
+    /// The accounts of all pools are read with shared batched calls.
+    async fn fetch_pool_snapshots_once(
+        &self,
+        pools: &[(PoolConfig, u8)],
+    ) -> Result<Vec<PoolSnapshot>, Box<dyn std::error::Error>> {
+        let fetched_at = Utc::now();
+        let fetch_started = Instant::now();
+
+        // Fetch the LB pair states from the chain
+        let lb_pair_requests: Vec<(Pubkey, Freshness)> = pools
+            .iter()
+            .map(|(pool, _)| (pool.pool_pubkey, Freshness::Slots))
+            .collect();
+        let lb_pair_accounts = self
+            .account_cache
+            .get_accounts(
+                &self.rpc_client,
+                &lb_pair_requests,
+                self.last_slot.load(Ordering::Relaxed),
+            )
+            .await?;
+
+        let mut required = Vec::with_capacity(pools.len());
+        let mut bitmap_extensions = Vec::with_capacity(pools.len());
+        for ((pool, bin_array_count), lb_pair_account) in pools.iter().zip(lb_pair_accounts) {
+            let lb_pair = pool.pool_pubkey;
+            // Selling base means swapping X for Y when base is token X
+            let sell_swap_for_y = pool.base_is_x;
+            let buy_swap_for_y = !sell_swap_for_y;
+
+            let lb_pair_account = lb_pair_account.ok_or("Failed to fetch LB pair account")?;
+            let lb_pair_state: LbPair =
+                read_anchor_account("LbPair", &lb_pair_account.data, &LB_PAIR_DISCRIMINATOR)
+                    .map_err(|e| format!("Invalid LB pair {}: {}", lb_pair, e))?;
+
+            // Get bitmap extension (optional, for pools with extended liquidity range)
+            let bitmap_extension = self
+                .bitmap_extensions
+                .get(&self.rpc_client, lb_pair)
+                .await?;
+
+            // Get bin arrays needed for each direction.
+            // Both directions walk away from the active bin on opposite sides, so the sets differ.
+            let sell_bin_arrays = get_bin_array_pubkeys_for_swap(
+                lb_pair,
+                &lb_pair_state,
+                bitmap_extension.as_ref(),
+                sell_swap_for_y,
+                *bin_array_count,
+            )?;
+            let buy_bin_arrays = get_bin_array_pubkeys_for_swap(
+                lb_pair,
+                &lb_pair_state,
+                bitmap_extension.as_ref(),
+                buy_swap_for_y,
+                *bin_array_count,
+            )?;
+            let mut bin_arrays_for_swap = sell_bin_arrays;
+            for key in buy_bin_arrays {
+                if !bin_arrays_for_swap.contains(&key) {
+                    bin_arrays_for_swap.push(key);
+                }
+            }
+            self.watch_pool_accounts(lb_pair, &bin_arrays_for_swap);
+
+            required.push((lb_pair, lb_pair_state, bin_arrays_for_swap));
+            bitmap_extensions.push(bitmap_extension);
+        }
+
+        // Fetch required accounts once so all quotes see the same clock and pool state
+        let pool_accounts = self.fetch_quote_required_accounts(&required).await?;
+        let fetch_latency = fetch_started.elapsed();
+
+        let mut snapshots = Vec::with_capacity(pools.len());
+        for (((pool, _), accounts), bitmap_extension) in
+            pools.iter().zip(pool_accounts).zip(bitmap_extensions)
+        {
+            let lb_pair_state = &accounts.lb_pair_state;
+            let decimals_x =
+                self.cached_mint_decimals(lb_pair_state.token_x_mint, &accounts.mint_x_account)?;
+            let decimals_y =
+                self.cached_mint_decimals(lb_pair_state.token_y_mint, &accounts.mint_y_account)?;
+            let (base_decimals, quote_decimals) = if pool.base_is_x {
+                (decimals_x, decimals_y)
+            } else {
+                (decimals_y, decimals_x)
+            };
+            let transfer_fee_x =
+                transfer_fee_config_or_gross(lb_pair_state.token_x_mint, &accounts.mint_x_account);
+            let transfer_fee_y =
+                transfer_fee_config_or_gross(lb_pair_state.token_y_mint, &accounts.mint_y_account);
+
+            snapshots.push(PoolSnapshot {
+                lb_pair: pool.pool_pubkey,
+                pool: pool.clone(),
+                base_decimals,
+                quote_decimals,
+                transfer_fee_x,
+                transfer_fee_y,
+                bitmap_extension,
+                accounts,
+                fetched_at,
+                fetch_latency,
+            });
+        }
+        Ok(snapshots)
+    }
+
+    /// Active bin price of every pool of a pair. Only the LbPair accounts are read, so this
+    /// is cheap enough to poll between full quotes: each pool costs one `getMultipleAccounts`
+    /// call, or none when a stream keeps the pool up to date.
+    pub async fn get_spot_price(
+        &self,
+        symbol: &str,
+    ) -> Result<Vec<SpotPrice>, Box<dyn std::error::Error>> {
+        let trade_config = self.trade_config(symbol)?;
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            self.pool_spot_price(symbol, pool)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+        .await;
+        let prices = successful_pool_results(symbol, &trade_config.pools, results);
+        if prices.is_empty() {
+            return Err(format!("No Meteora pool could price {}", symbol).into());
+        }
+        Ok(prices)
+    }
+
+    async fn pool_spot_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+    ) -> Result<SpotPrice, Box<dyn std::error::Error>> {
+        let lb_pair = pool.pool_pubkey;
+        let slot = self.last_slot.load(Ordering::Relaxed);
+        let lb_pair_account = self
+            .account_cache
+            .get_accounts(&self.rpc_client, &[(lb_pair, Freshness::Streamed)], slot)
+            .await?
+            .pop()
+            .flatten()
+            .ok_or("Failed to fetch LB pair account")?;
+        let lb_pair_state: LbPair =
+            read_anchor_account("LbPair", &lb_pair_account.data, &LB_PAIR_DISCRIMINATOR)
+                .map_err(|e| format!("Invalid LB pair {}: {}", lb_pair, e))?;
+
+        let decimals_x = self.mint_decimals_of(lb_pair_state.token_x_mint).await?;
+        let decimals_y = self.mint_decimals_of(lb_pair_state.token_y_mint).await?;
+        let (base_decimals, quote_decimals) = if pool.base_is_x {
+            (decimals_x, decimals_y)
+        } else {
+            (decimals_y, decimals_x)
+        };
+        let price = active_bin_spot_price(
+            &lb_pair_state,
+            pool.base_is_x,
+            base_decimals,
+            quote_decimals,
+        )
+        .ok_or_else(|| {
+            format!(
+                "Active bin {} of {} has no representable price",
+                lb_pair_state.active_id, lb_pair
+            )
+        })?;
+
+        Ok(SpotPrice {
+            symbol: symbol.to_string(),
+            pool: lb_pair,
+            active_id: lb_pair_state.active_id,
+            price,
+            slot,
+        })
+    }
+
+    /// Decimals of a mint, fetching the mint account only when they are not cached yet
+    async fn mint_decimals_of(&self, mint: Pubkey) -> Result<u32, Box<dyn std::error::Error>> {
+        if let Some(decimals) = self.mint_decimals.read().unwrap().get(&mint) {
+            return Ok(*decimals);
+        }
+        let mint_account = self
+            .account_cache
+            .get_accounts(
+                &self.rpc_client,
+                &[(mint, Freshness::Ttl)],
+                self.last_slot.load(Ordering::Relaxed),
+            )
+            .await?
+            .pop()
+            .flatten()
+            .ok_or_else(|| format!("Failed to fetch mint {}", mint))?;
+        self.cached_mint_decimals(mint, &mint_account)
+    }
+
+    /// Poll the spot price of every configured pair until the screener is stopped
+    async fn poll_spot_prices(
+        self: Arc<Self>,
+        interval: Duration,
+        dex_writer: Arc<DexMarketWriter>,
+    ) {
+        info!("🚀 Polling Meteora spot prices every {:?}", interval);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+            let symbols: Vec<String> = self.trade_pairs.read().unwrap().keys().cloned().collect();
+            let screener = &self;
+            let results = join_all(symbols.iter().map(|symbol| async move {
+                screener
+                    .get_spot_price(symbol)
+                    .await
+                    .map_err(|e| e.to_string())
+            }))
+            .await;
+            for (symbol, result) in symbols.iter().zip(results) {
+                match result {
+                    Ok(prices) => self.save_spot_prices(&prices, &dex_writer),
+                    Err(e) => warn!("Meteora spot price for {} failed: {}", symbol, e),
+                }
+            }
+        }
+    }
+
+    /// Persist spot prices as DEX market states with the `spot` direction through `dex_writer`
+    fn save_spot_prices(&self, prices: &[SpotPrice], dex_writer: &DexMarketWriter) {
+        let fetch_time = Utc::now();
+        let dex_states: Vec<market::DEXState> = prices
+            .iter()
+            .map(|price| build_spot_dex_state(price, VENUE, fetch_time))
+            .collect();
+        for dex_state in &dex_states {
+            dex_state.log();
+        }
+        dex_writer.submit(dex_states);
+    }
+
+    /// Decimals of a mint, unpacked from its account on first use.
+    /// Decimals of an initialized mint never change, so they are cached for the screener lifetime.
+    fn cached_mint_decimals(
+        &self,
+        mint: Pubkey,
+        mint_account: &Account,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        if let Some(decimals) = self.mint_decimals.read().unwrap().get(&mint) {
+            return Ok(*decimals);
+        }
+        let decimals = mint_decimals(mint_account)
+            .map_err(|e| format!("Failed to read decimals of mint {}: {}", mint, e))?;
+        self.mint_decimals.write().unwrap().insert(mint, decimals);
+        Ok(decimals)
+    }
+
+    /// Persist the fee rates of the pools behind a quote
+    fn save_pool_fees(&self, quote: &PriceQuote) {
+        for fee in build_pool_fees(quote, VENUE) {
+            let db_pool = self.db_pool.clone();
+            tokio::spawn(async move {
+                if let Err(e) = insert_pool_fee(&db_pool, &fee).await {
+                    error!("Failed to insert pool fee for {}: {}", fee.pool_address, e);
+                }
+            });
+        }
+    }
+
+    /// Fetch all required accounts for swap quote calculation, for every given pool.
+    /// Cached accounts are reused and all misses are fetched with one RPC call;
+    /// the pools share one Clock read.
+    async fn fetch_quote_required_accounts(
+        &self,
+        pools: &[(Pubkey, LbPair, Vec<Pubkey>)],
+    ) -> Result<Vec<SwapQuoteAccounts>, Box<dyn std::error::Error>> {
+        // Each pool reads its LB pair, both mints and its bin arrays, in that order
+        let accounts_to_fetch: Vec<(Pubkey, Freshness)> = pools
+            .iter()
+            .flat_map(|(lb_pair, lb_pair_state, bin_arrays_for_swap)| {
+                [
+                    (*lb_pair, Freshness::Slots),
+                    (lb_pair_state.token_x_mint, Freshness::Ttl),
+                    (lb_pair_state.token_y_mint, Freshness::Ttl),
+                ]
+                .into_iter()
+                .chain(
+                    bin_arrays_for_swap
+                        .iter()
+                        .map(|&key| (key, Freshness::Slots)),
+                )
+            })
+            .collect();
+
+        // The Clock is read separately so it can use its own commitment
+        let (accounts, clock_account) = tokio::try_join!(
+            self.account_cache.get_accounts(
+                &self.rpc_client,
+                &accounts_to_fetch,
+                self.last_slot.load(Ordering::Relaxed),
+            ),
+            self.rpc_client
+                .get_account_with_commitment(&solana_sdk::sysvar::clock::ID, self.clock_commitment),
+        )?;
+
+        // Clock account
+        let clock_account = clock_account.ok_or("Failed to fetch clock account")?;
+        let clock: solana_sdk::clock::Clock = bincode::deserialize(clock_account.data.as_ref())?;
+        self.last_slot.fetch_max(clock.slot, Ordering::Relaxed);
+        let now = Utc::now();
+        let stale = is_clock_stale(&clock, now, self.max_clock_drift);
+        if stale {
+            warn!(
+                "Clock sysvar at slot {} drifted {}s from wall time, tagging {} quote as stale",
+                clock.slot,
+                clock_drift_secs(&clock, now),
+                pools
+                    .iter()
+                    .map(|(lb_pair, _, _)| lb_pair.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        // Parse accounts
+        let mut accounts = accounts.into_iter();
+        let mut pool_accounts = Vec::with_capacity(pools.len());
+        for (lb_pair, lb_pair_state, bin_arrays_for_swap) in pools {
+            // Skip LB pair (we already have it)
+            accounts.next();
+
+            // Mint X account
+            let mint_x_account = accounts
+                .next()
+                .flatten()
+                .ok_or("Failed to fetch mint X account")?;
+
+            // Mint Y account
+            let mint_y_account = accounts
+                .next()
+                .flatten()
+                .ok_or("Failed to fetch mint Y account")?;
+
+            // Bin array accounts
+            let bin_array_accounts: Vec<Option<Account>> =
+                accounts.by_ref().take(bin_arrays_for_swap.len()).collect();
+            if bin_array_accounts.len() < bin_arrays_for_swap.len() {
+                return Err("Failed to fetch bin array accounts".into());
+            }
+
+            let (bin_arrays, missing) = decode_bin_arrays(
+                bin_arrays_for_swap,
+                bin_array_accounts,
+                self.allow_partial_quotes,
+            )?;
+            if !missing.is_empty() {
+                warn!(
+                    "Quoting {} without {} of {} bin arrays: {}",
+                    lb_pair,
+                    missing.len(),
+                    bin_arrays_for_swap.len(),
+                    MissingBinArrays(missing.clone())
+                );
+            }
+
+            pool_accounts.push(SwapQuoteAccounts {
+                lb_pair_state: *lb_pair_state,
+                slot: clock.slot,
+                commitment: self.commitment.commitment,
+                stale,
+                missing_bin_arrays: missing.len(),
+                clock: clock.clone(),
+                mint_x_account,
+                mint_y_account,
+                bin_arrays,
+            });
+        }
+        Ok(pool_accounts)
+    }
+
+    /// Remember the accounts a pool snapshot read through the account cache
+    fn watch_pool_accounts(&self, lb_pair: Pubkey, bin_arrays: &[Pubkey]) {
+        let mut accounts = vec![lb_pair];
+        accounts.extend_from_slice(bin_arrays);
+        self.watched_accounts
+            .write()
+            .unwrap()
+            .insert(lb_pair, accounts);
+    }
+
+    /// Pool and bin array accounts worth streaming, as of the latest snapshot of every pool
+    pub fn watched_accounts(&self) -> Vec<Pubkey> {
+        let configured: Vec<Pubkey> = self
+            .trade_pairs
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(TradeConfig::pool_pubkeys)
+            .collect();
+        let mut watched = self.watched_accounts.write().unwrap();
+        // Stop streaming pools removed from the trade pairs table
+        watched.retain(|lb_pair, _| configured.contains(lb_pair));
+        watched.values().flatten().copied().collect()
+    }
+}
+
+#[async_trait]
+impl DexQuoter for MeteoraScreener {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        let (pool_results, route_results) = tokio::join!(
+            join_all(trade_config.pools.iter().map(|pool| async move {
+                self.get_pool_price(symbol, pool, amount_in)
+                    .await
+                    .map_err(|e| e.to_string())
+            })),
+            join_all(trade_config.routes.iter().map(|route| async move {
+                self.get_route_price(symbol, route, amount_in)
+                    .await
+                    .map_err(|e| e.to_string())
+            })),
+        );
+        let mut quotes = successful_pool_results(symbol, &trade_config.pools, pool_results);
+        quotes.extend(successful_route_results(
+            symbol,
+            &trade_config.routes,
+            route_results,
+        ));
+
+        select_best_price(symbol, quotes)
+            .ok_or_else(|| format!("No Meteora pool could quote {}", symbol).into())
+    }
+
+    /// Resolve the pools of auto-discovered pairs before the first tick, so they are
+    /// quoted right away
+    async fn initial_trade_configs(
+        &self,
+        db_pool: &DatabasePool,
+        discovered: &RwLock<HashMap<String, Vec<PoolConfig>>>,
+    ) -> Result<HashMap<String, TradeConfig>, Box<dyn std::error::Error>> {
+        let pairs = get_enabled_pairs(db_pool, VENUE).await?;
+        discover_pools(
+            &self.meteora_api,
+            &discovery_targets_from_pairs(&pairs),
+            discovered,
+        )
+        .await;
+        let mut trade_configs = trade_configs_from_pairs(pairs);
+        merge_discovered_pools(&mut trade_configs, &discovered.read().unwrap());
+        Ok(trade_configs)
+    }
+
+    /// Tag quotes from pools below the liquidity floor and feed the SOL price to the
+    /// landing cost estimator
+    fn on_quote(&self, best: &mut BestPriceQuote) {
+        best.degraded = below_liquidity_floor(best, self.min_pool_liquidity);
+        if best.symbol == self.fee_estimator.config.sol_price_symbol {
+            self.fee_estimator
+                .set_sol_price((best.bid.bid_price + best.ask.ask_price) / Decimal::TWO);
+        }
+        self.mark_degraded(best);
+        best.log();
+    }
+
+    /// Record the liquidity of the best bid and ask pools next to their DEX market states
+    fn save_quote_details(
+        &self,
+        db_pool: &DatabasePool,
+        quote: &BestPriceQuote,
+        fetch_time: DateTime<Utc>,
+    ) {
+        for stats in build_pool_stats(quote, VENUE, fetch_time) {
+            let db_pool = db_pool.clone();
+            tokio::spawn(async move {
+                if let Err(e) = insert_pool_stats(&db_pool, &stats).await {
+                    error!(
+                        "Failed to insert pool stats for {}: {}",
+                        stats.pool_address, e
+                    );
+                }
+            });
+        }
     }
 }
+
+/// Read the polling interval from `METEORA_POLL_INTERVAL_MS`, falling back to the default
+pub(super) fn poll_interval_from_env() -> Duration {
+    let millis = std::env::var("METEORA_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_POLL_INTERVAL_MS);
+    Duration::from_millis(millis)
+}
+
+/// Read the trade pairs reload interval from `METEORA_PAIRS_REFRESH_MINS`, falling back to the default
+pub(super) fn pairs_refresh_interval_from_env() -> Duration {
+    let minutes = std::env::var("METEORA_PAIRS_REFRESH_MINS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_PAIRS_REFRESH_MINS);
+    Duration::from_secs(minutes * 60)
+}
+
+/// Read the pool liquidity floor from `METEORA_MIN_POOL_LIQUIDITY`, falling back to the default
+fn min_pool_liquidity_from_env() -> Decimal {
+    std::env::var("METEORA_MIN_POOL_LIQUIDITY")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(Decimal::from(DEFAULT_MIN_POOL_LIQUIDITY))
+}
+
+/// Read the allowed Clock drift from `METEORA_MAX_CLOCK_DRIFT_SECS`, falling back to the default
+pub(super) fn max_clock_drift_from_env() -> Duration {
+    let secs = std::env::var("METEORA_MAX_CLOCK_DRIFT_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_CLOCK_DRIFT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Read the maximum quote age from `METEORA_MAX_QUOTE_AGE_MS`, falling back to the default
+pub(super) fn max_quote_age_from_env() -> Duration {
+    let millis = std::env::var("METEORA_MAX_QUOTE_AGE_MS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_QUOTE_AGE_MS);
+    Duration::from_millis(millis)
+}
+
+/// Read the spot price polling interval from `METEORA_SPOT_POLL_INTERVAL_MS`.
+/// Spot polling is disabled when the variable is unset or 0.
+fn spot_poll_interval_from_env() -> Option<Duration> {
+    std::env::var("METEORA_SPOT_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .filter(|millis| *millis > 0)
+        .map(Duration::from_millis)
+}
+
+/// Read the pool discovery interval from `METEORA_DISCOVERY_REFRESH_MINS`, falling back to the default
+fn discovery_refresh_interval_from_env() -> Duration {
+    let minutes = std::env::var("METEORA_DISCOVERY_REFRESH_MINS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_DISCOVERY_REFRESH_MINS);
+    Duration::from_secs(minutes * 60)
+}
+
+/// Read the per-tick pair concurrency from `METEORA_MAX_CONCURRENT_PAIRS`, falling back to the default
+pub(super) fn max_concurrent_pairs_from_env() -> usize {
+    std::env::var("METEORA_MAX_CONCURRENT_PAIRS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&pairs| pairs > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_PAIRS)
+}
+
+/// Read the bitmap extension cache lifetime from `METEORA_BITMAP_EXTENSION_TTL_SECS`, falling back to the default
+fn bitmap_extension_ttl_from_env() -> Duration {
+    let secs = std::env::var("METEORA_BITMAP_EXTENSION_TTL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_BITMAP_EXTENSION_TTL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Build the account cache from `METEORA_CACHE_MAX_SLOT_AGE` and `METEORA_MINT_CACHE_TTL_SECS`
+fn account_cache_from_env() -> AccountCache {
+    let max_slot_age = std::env::var("METEORA_CACHE_MAX_SLOT_AGE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_CACHE_MAX_SLOT_AGE);
+    let mint_ttl_secs = std::env::var("METEORA_MINT_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MINT_CACHE_TTL_SECS);
+    AccountCache::new(max_slot_age, Duration::from_secs(mint_ttl_secs))
+}
+
+/// Periodically reload the trade pairs table so new pools are picked up without a restart.
+/// A failed reload keeps the previously loaded pairs.
+pub(super) async fn refresh_trade_configs(
+    db_pool: DatabasePool,
+    venue: &'static str,
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    discovered: DiscoveredPools,
+    shutdown: CancellationToken,
+    interval: Duration,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        match load_trade_configs(&db_pool, venue, &discovered).await {
+            Ok(trade_configs) => {
+                info!("Reloaded {} {} trade pairs", trade_configs.len(), venue);
+                *trade_pairs.write().unwrap() = trade_configs;
+            }
+            Err(e) => warn!("Failed to reload {} trade pairs: {}", venue, e),
+        }
+    }
+}
+
+/// Periodically rediscover the pools of auto-discovered pairs and rebuild the trade configs.
+/// A failed discovery keeps the last known pools of the pair.
+async fn refresh_discovered_pools(
+    api: Arc<MeteoraApiClient>,
+    db_pool: DatabasePool,
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    discovered: DiscoveredPools,
+    shutdown: CancellationToken,
+    interval: Duration,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        let pairs = match get_enabled_pairs(&db_pool, VENUE).await {
+            Ok(pairs) => pairs,
+            Err(e) => {
+                warn!("Failed to load Meteora trade pairs for discovery: {}", e);
+                continue;
+            }
+        };
+        discover_pools(&api, &discovery_targets_from_pairs(&pairs), &discovered).await;
+        let mut trade_configs = trade_configs_from_pairs(pairs);
+        merge_discovered_pools(&mut trade_configs, &discovered.read().unwrap());
+        *trade_pairs.write().unwrap() = trade_configs;
+    }
+}
+
+/// Assemble a two-sided quote from the sell and buy swaps computed against `snapshot`.
+/// The quote carries the snapshot slot so consumers can tell quotes of the same slot apart.
+fn build_price_quote(
+    symbol: &str,
+    snapshot: &PoolSnapshot,
+    sell: SwapQuote,
+    buy: SwapQuote,
+) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+    let base_decimals = snapshot.base_decimals();
+    let quote_decimals = snapshot.quote_decimals();
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, base_decimals, quote_decimals);
+    let spot_price = active_bin_spot_price(
+        &snapshot.accounts.lb_pair_state,
+        snapshot.pool.base_is_x,
+        base_decimals,
+        quote_decimals,
+    );
+    let (reserve_x, reserve_y) = bin_array_reserves(snapshot.accounts.bin_arrays.values());
+    let (base_reserve, quote_reserve) = if snapshot.pool.base_is_x {
+        (reserve_x, reserve_y)
+    } else {
+        (reserve_y, reserve_x)
+    };
+
+    Ok(PriceQuote {
+        symbol: symbol.to_string(),
+        pool: snapshot.lb_pair,
+        route: None,
+        hops: Vec::new(),
+        slot: snapshot.accounts.slot,
+        block_time: snapshot.block_time(),
+        fetched_at: snapshot.fetched_at,
+        fetch_latency: snapshot.fetch_latency,
+        commitment: snapshot.accounts.commitment,
+        stale: snapshot.accounts.stale,
+        missing_bin_arrays: snapshot.accounts.missing_bin_arrays,
+        base_decimals,
+        quote_decimals,
+        bid_impact_bps: spot_price.and_then(|spot| price_impact_bps(bid_price, spot)),
+        ask_impact_bps: spot_price.and_then(|spot| price_impact_bps(ask_price, spot)),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: Some(pool_fee_rate(&snapshot.accounts.lb_pair_state)),
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price,
+        liquidity: PoolLiquidity::new(
+            base_reserve,
+            quote_reserve,
+            base_decimals,
+            quote_decimals,
+            spot_price,
+        ),
+        landing_cost: 0,
+        net_amount_out: None,
+    })
+}
+
+/// Quote a route against the snapshots of its hops, feeding each hop's output into the next.
+/// The sell side sells `amount_in` base token on the first hop and its whole output on the
+/// second; the buy side spends the quote token received back through the second hop, then
+/// the first.
+fn quote_route_hops(
+    symbol: &str,
+    route: &RouteConfig,
+    first: &PoolSnapshot,
+    second: &PoolSnapshot,
+    amount_in: u64,
+) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+    let first_sell = first.quote(amount_in, first.sell_swap_for_y())?;
+    let second_sell = second.quote(first_sell.amount_out, second.sell_swap_for_y())?;
+    if second_sell.amount_out == 0 {
+        return Err(format!(
+            "Route {} returned no output when selling {}",
+            route.name, symbol
+        )
+        .into());
+    }
+    let second_buy = second.quote(second_sell.amount_out, !second.sell_swap_for_y())?;
+    let first_buy = first.quote(second_buy.amount_out, !first.sell_swap_for_y())?;
+
+    let first_quote = build_price_quote(symbol, first, first_sell, first_buy)?;
+    let second_quote = build_price_quote(symbol, second, second_sell, second_buy)?;
+    Ok(chain_route_quote(&route.name, first_quote, second_quote))
+}
+
+/// Chain the quotes of the two hops of a route into one quote of the pair.
+/// Prices, fees and impact are those of the whole path; the hop quotes are kept as the breakdown.
+fn chain_route_quote(name: &str, first: PriceQuote, second: PriceQuote) -> PriceQuote {
+    let sell = SwapQuote {
+        amount_in: first.sell.amount_in,
+        amount_out: second.sell.amount_out,
+        fee: chained_fee(&first.sell, &second.sell),
+        gross_amount_out: second.sell.gross_amount_out,
+        transfer_fee_in: first.sell.transfer_fee_in,
+        transfer_fee_out: second.sell.transfer_fee_out,
+    };
+    let buy = SwapQuote {
+        amount_in: second.buy.amount_in,
+        amount_out: first.buy.amount_out,
+        fee: chained_fee(&second.buy, &first.buy),
+        gross_amount_out: first.buy.gross_amount_out,
+        transfer_fee_in: second.buy.transfer_fee_in,
+        transfer_fee_out: first.buy.transfer_fee_out,
+    };
+    let base_decimals = first.base_decimals;
+    let quote_decimals = second.quote_decimals;
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, base_decimals, quote_decimals);
+    let spot_price = first
+        .spot_price
+        .zip(second.spot_price)
+        .and_then(|(first_spot, second_spot)| first_spot.checked_mul(second_spot));
+
+    PriceQuote {
+        symbol: first.symbol.clone(),
+        pool: first.pool,
+        route: Some(name.to_string()),
+        hops: vec![RouteHop::from(&first), RouteHop::from(&second)],
+        slot: first.slot.max(second.slot),
+        block_time: first.block_time.max(second.block_time),
+        fetched_at: first.fetched_at.min(second.fetched_at),
+        fetch_latency: first.fetch_latency.max(second.fetch_latency),
+        commitment: first.commitment,
+        stale: first.stale || second.stale,
+        missing_bin_arrays: first.missing_bin_arrays + second.missing_bin_arrays,
+        base_decimals,
+        quote_decimals,
+        bid_impact_bps: spot_price.and_then(|spot| price_impact_bps(bid_price, spot)),
+        ask_impact_bps: spot_price.and_then(|spot| price_impact_bps(ask_price, spot)),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: None,
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price,
+        liquidity: route_liquidity(&first.liquidity, &second.liquidity, second.spot_price),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+/// Fee of two chained swaps charged as one: the compounded share of the input each hop
+/// keeps as fee, applied to the input of the first swap
+fn chained_fee(first: &SwapQuote, second: &SwapQuote) -> u64 {
+    let kept_share = |quote: &SwapQuote| Decimal::ONE - fee_pct(quote) / Decimal::ONE_HUNDRED;
+    let fee_share = Decimal::ONE - kept_share(first) * kept_share(second);
+    (Decimal::from(first.amount_in) * fee_share)
+        .round()
+        .to_u64()
+        .unwrap_or(first.amount_in)
+}
+
+/// Liquidity of a route, bounded by its shallowest hop. The first hop is valued in
+/// intermediate token and converted to quote token at `intermediate_price`.
+fn route_liquidity(
+    first: &PoolLiquidity,
+    second: &PoolLiquidity,
+    intermediate_price: Option<Decimal>,
+) -> PoolLiquidity {
+    let first_notional = first
+        .notional
+        .zip(intermediate_price)
+        .and_then(|(notional, price)| notional.checked_mul(price));
+    PoolLiquidity {
+        base_amount: first.base_amount,
+        quote_amount: second.quote_amount,
+        notional: first_notional
+            .zip(second.notional)
+            .map(|(first, second)| first.min(second)),
+    }
+}
+
+/// Decoded bin arrays keyed by pubkey, and the requested bin arrays that were missing
+type DecodedBinArrays = (HashMap<Pubkey, BinArray>, Vec<Pubkey>);
+
+/// Decode the fetched bin arrays, keyed by pubkey, and list the requested ones that
+/// came back empty. Missing bin arrays fail with `MissingBinArrays` unless `allow_partial` is set.
+fn decode_bin_arrays(
+    keys: &[Pubkey],
+    accounts: Vec<Option<Account>>,
+    allow_partial: bool,
+) -> Result<DecodedBinArrays, Box<dyn std::error::Error>> {
+    let mut bin_arrays = HashMap::new();
+    let mut missing = Vec::new();
+    for (account, &key) in accounts.into_iter().zip(keys) {
+        let Some(account) = account else {
+            missing.push(key);
+            continue;
+        };
+        let bin_array = read_anchor_account("BinArray", &account.data, &BIN_ARRAY_DISCRIMINATOR)
+            .map_err(|e| format!("Invalid bin array {}: {}", key, e))?;
+        bin_arrays.insert(key, bin_array);
+    }
+    if !missing.is_empty() && !allow_partial {
+        return Err(Box::new(MissingBinArrays(missing)));
+    }
+    Ok((bin_arrays, missing))
+}
+
+/// Reject a best quote older than `max_age`: it describes the pool as it was when the
+/// fetch started, which a slow RPC can put well behind a live CEX book
+pub(super) fn ensure_quote_fresh(
+    best: &BestPriceQuote,
+    max_age: Duration,
+    now: DateTime<Utc>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let age = best.age(now);
+    if age > max_age {
+        return Err(format!(
+            "Quote for {} is {}ms old (fetch took {}ms/{}ms), above the {}ms limit",
+            best.symbol,
+            age.as_millis(),
+            best.bid.fetch_latency.as_millis(),
+            best.ask.fetch_latency.as_millis(),
+            max_age.as_millis()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Seconds the Clock sysvar lags behind `now`, negative when it runs ahead
+pub(super) fn clock_drift_secs(clock: &solana_sdk::clock::Clock, now: DateTime<Utc>) -> i64 {
+    now.timestamp() - clock.unix_timestamp
+}
+
+/// Whether the Clock sysvar drifted from `now` by more than `max_drift`, in either direction
+pub(super) fn is_clock_stale(
+    clock: &solana_sdk::clock::Clock,
+    now: DateTime<Utc>,
+    max_drift: Duration,
+) -> bool {
+    clock_drift_secs(clock, now).unsigned_abs() > max_drift.as_secs()
+}
+
+/// Token X and Y amounts held by the bins of `bin_arrays`.
+/// Only the fetched bin arrays around the active bin are counted, i.e. the liquidity
+/// a quote can actually reach.
+fn bin_array_reserves<'a>(bin_arrays: impl IntoIterator<Item = &'a BinArray>) -> (u128, u128) {
+    bin_arrays
+        .into_iter()
+        .flat_map(|bin_array| bin_array.bins.iter())
+        .fold((0, 0), |(amount_x, amount_y), bin| {
+            (
+                amount_x + bin.amount_x as u128,
+                amount_y + bin.amount_y as u128,
+            )
+        })
+}
+
+/// Whether the best bid or ask of a pair comes from a pool below the liquidity floor
+fn below_liquidity_floor(best: &BestPriceQuote, floor: Decimal) -> bool {
+    best.bid.liquidity.is_below(floor) || best.ask.liquidity.is_below(floor)
+}
+
+/// Liquidity records of the pools behind the best bid and ask, once per pool.
+/// Route quotes are skipped: their liquidity spans several pools.
+fn build_pool_stats(
+    best: &BestPriceQuote,
+    exchange: &str,
+    fetch_time: DateTime<Utc>,
+) -> Vec<PoolStats> {
+    let mut quotes = vec![&best.bid];
+    if best.ask.pool != best.bid.pool || best.ask.route != best.bid.route {
+        quotes.push(&best.ask);
+    }
+    quotes
+        .into_iter()
+        .filter(|quote| quote.route.is_none())
+        .map(|quote| PoolStats {
+            exchange: exchange.to_string(),
+            trade_pair: quote.symbol.clone(),
+            pool_address: quote.pool.to_string(),
+            block_number: quote.slot,
+            base_amount: quote.liquidity.base_amount,
+            quote_amount: quote.liquidity.quote_amount,
+            liquidity: quote.liquidity.notional,
+            degraded: best.degraded,
+            fetch_time,
+        })
+        .collect()
+}
+
+/// Build the `sell` DEX market state from the best bid and the `buy` one from the best ask,
+/// each tagged with the pool or route that produced it.
+/// The trade id `{pool}:{slot}:{direction}`, or `{first_hop}:{second_hop}:{slot}:{direction}`
+/// for a route, is stable within a slot so re-quoting the same slot updates the existing row.
+pub(super) fn build_dex_states(
+    best: &BestPriceQuote,
+    exchange: &str,
+    fetch_time: DateTime<Utc>,
+) -> Vec<market::DEXState> {
+    let sides = [
+        (
+            "sell",
+            &best.bid,
+            best.bid.bid_price,
+            best.bid.sell.amount_in,
+            best.bid.bid_impact_bps,
+        ),
+        (
+            "buy",
+            &best.ask,
+            best.ask.ask_price,
+            best.ask.buy.amount_out,
+            best.ask.ask_impact_bps,
+        ),
+    ];
+
+    sides
+        .into_iter()
+        .map(
+            |(direction, quote, price, base_amount, impact_bps)| market::DEXState {
+                trade_id: format!("{}:{}:{}", quote.pool_key(), quote.slot, direction),
+                exchange: exchange.to_string(),
+                trade_pair: quote.symbol.clone(),
+                direction: direction.to_string(),
+                price,
+                volume: to_ui_amount(base_amount, quote.base_decimals),
+                trade_time: quote.block_time,
+                fetch_time,
+                block_number: quote.slot,
+                price_impact_bps: impact_bps,
+                pool_address: quote.route.is_none().then(|| quote.pool.to_string()),
+                stale: quote.stale,
+                fetch_latency_ms: Some(quote.fetch_latency.as_millis() as u64),
+                route: quote.route.clone(),
+                route_plan: None,
+            },
+        )
+        .collect()
+}
+
+/// Build the DEX market state of a spot price. It carries no volume or impact, and its
+/// trade id `{pool}:{slot}:spot:{active_id}` adds a row whenever the active bin moves
+/// while the slot known to the screener stays the same.
+fn build_spot_dex_state(
+    spot: &SpotPrice,
+    exchange: &str,
+    fetch_time: DateTime<Utc>,
+) -> market::DEXState {
+    market::DEXState {
+        trade_id: format!(
+            "{}:{}:{}:{}",
+            spot.pool, spot.slot, SPOT_DIRECTION, spot.active_id
+        ),
+        exchange: exchange.to_string(),
+        trade_pair: spot.symbol.clone(),
+        direction: SPOT_DIRECTION.to_string(),
+        price: spot.price,
+        volume: Decimal::ZERO,
+        trade_time: fetch_time,
+        fetch_time,
+        block_number: spot.slot,
+        price_impact_bps: None,
+        pool_address: Some(spot.pool.to_string()),
+        stale: false,
+        fetch_latency_ms: None,
+        route: None,
+        route_plan: None,
+    }
+}
+
+/// Keep the successful per-pool results, logging the pools that failed.
+/// Errors arrive as strings: results of finished pools are held while the others
+/// are awaited, and boxed errors would make the screener future non-`Send`.
+pub(super) fn successful_pool_results<T>(
+    symbol: &str,
+    pools: &[PoolConfig],
+    results: Vec<Result<T, String>>,
+) -> Vec<T> {
+    pools
+        .iter()
+        .zip(results)
+        .filter_map(|(pool, result)| match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!(
+                    "Skipping Meteora pool {} for {}: {}",
+                    pool.pool_pubkey, symbol, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Keep the successful per-route results, logging the routes that failed
+fn successful_route_results<T>(
+    symbol: &str,
+    routes: &[RouteConfig],
+    results: Vec<Result<T, String>>,
+) -> Vec<T> {
+    routes
+        .iter()
+        .zip(results)
+        .filter_map(|(route, result)| match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!(
+                    "Skipping Meteora route {} for {}: {}",
+                    route.name, symbol, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Pick the highest bid and the lowest ask across pool quotes.
+/// Sides that produced no output are ignored; `None` when either side has no quote.
+pub(super) fn select_best_price(symbol: &str, quotes: Vec<PriceQuote>) -> Option<BestPriceQuote> {
+    let bid = quotes
+        .iter()
+        .filter(|quote| quote.bid_price > Decimal::ZERO)
+        .max_by_key(|quote| quote.bid_price)?;
+    let ask = quotes
+        .iter()
+        .filter(|quote| quote.ask_price > Decimal::ZERO)
+        .min_by_key(|quote| quote.ask_price)?;
+    Some(BestPriceQuote {
+        symbol: symbol.to_string(),
+        bid: bid.clone(),
+        ask: ask.clone(),
+        pools_quoted: quotes.len(),
+        degraded: false,
+    })
+}
+
+/// Merge per-pool ladders into one book: pools are independent liquidity, so the
+/// volumes of their levels add up at equal prices
+fn merge_ladders(symbol: &str, ladders: Vec<DepthLadder>) -> Option<DepthLadder> {
+    if ladders.is_empty() {
+        return None;
+    }
+    let mut merged = DepthLadder {
+        book: market::OrderBook::new("meteora", symbol),
+        slot: 0,
+        truncated: false,
+        pools: Vec::new(),
+    };
+    for ladder in ladders {
+        for (price, volume) in ladder.book.bids {
+            *merged.book.bids.entry(price).or_default() += volume;
+        }
+        for (price, volume) in ladder.book.asks {
+            *merged.book.asks.entry(price).or_default() += volume;
+        }
+        merged.slot = merged.slot.max(ladder.slot);
+        merged.truncated |= ladder.truncated;
+        merged.pools.extend(ladder.pools);
+    }
+    Some(merged)
+}
+
+/// Convert a raw token amount into whole tokens
+fn to_ui_amount(amount: u64, decimals: u32) -> Decimal {
+    Decimal::from_i128_with_scale(amount as i128, decimals)
+}
+
+/// Read the decimals of an SPL Token / Token-2022 mint account.
+/// Token-2022 mints may carry extensions after the base mint layout.
+pub(super) fn mint_decimals(mint_account: &Account) -> Result<u32, Box<dyn std::error::Error>> {
+    check_spl_token_program_account(&mint_account.owner).map_err(|_| {
+        format!(
+            "Mint is owned by {}, not a token program",
+            mint_account.owner
+        )
+    })?;
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data)?;
+    Ok(mint.base.decimals as u32)
+}
+
+/// Transfer fee config of a Token-2022 mint, `None` for mints without the extension
+fn transfer_fee_config(
+    mint_account: &Account,
+) -> Result<Option<TransferFeeConfig>, Box<dyn std::error::Error>> {
+    if mint_account.owner != spl_token_2022::id() {
+        return Ok(None);
+    }
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data)?;
+    if !mint
+        .get_extension_types()?
+        .contains(&ExtensionType::TransferFeeConfig)
+    {
+        return Ok(None);
+    }
+    Ok(Some(*mint.get_extension::<TransferFeeConfig>()?))
+}
+
+/// Transfer fee config of a mint; unparseable configs are logged and quotes fall back to gross
+fn transfer_fee_config_or_gross(mint: Pubkey, mint_account: &Account) -> Option<TransferFeeConfig> {
+    transfer_fee_config(mint_account).unwrap_or_else(|e| {
+        warn!(
+            "Failed to parse transfer fee config of mint {}, quoting gross amounts: {}",
+            mint, e
+        );
+        None
+    })
+}
+
+/// Transfer fee withheld when moving `amount` of a token during `epoch`
+fn epoch_transfer_fee(
+    config: Option<&TransferFeeConfig>,
+    epoch: u64,
+    amount: u64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    match config {
+        Some(config) => Ok(config
+            .calculate_epoch_fee(epoch, amount)
+            .ok_or("Transfer fee calculation overflowed")?),
+        None => Ok(0),
+    }
+}
+
+/// Quote an exact-in swap net of Token-2022 transfer fees: the pool only receives
+/// `amount_in` minus the input token fee, and the output token fee is withheld from
+/// what the pool sends back. `quote` returns the raw pool quote for an input amount.
+fn apply_transfer_fees(
+    amount_in: u64,
+    epoch: u64,
+    fee_in: Option<&TransferFeeConfig>,
+    fee_out: Option<&TransferFeeConfig>,
+    quote: impl Fn(u64) -> Result<SwapQuote, Box<dyn std::error::Error>>,
+) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    let gross = quote(amount_in)?;
+    let transfer_fee_in = epoch_transfer_fee(fee_in, epoch, amount_in)?;
+    let received = if transfer_fee_in == 0 {
+        gross.clone()
+    } else {
+        quote(amount_in.saturating_sub(transfer_fee_in))?
+    };
+    let transfer_fee_out = epoch_transfer_fee(fee_out, epoch, received.amount_out)?;
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out: received.amount_out.saturating_sub(transfer_fee_out),
+        fee: received.fee,
+        gross_amount_out: gross.amount_out,
+        transfer_fee_in,
+        transfer_fee_out,
+    })
+}
+
+/// Quote tokens per base token for a swap of `base_amount` against `quote_amount`.
+/// Returns 0 when no base token is involved.
+pub(super) fn normalized_price(
+    base_amount: u64,
+    quote_amount: u64,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> Decimal {
+    let base = to_ui_amount(base_amount, base_decimals);
+    if base.is_zero() {
+        return Decimal::ZERO;
+    }
+    to_ui_amount(quote_amount, quote_decimals) / base
+}
+
+/// Current fee rate of a DLMM pool, following the program's fee math: the base fee is
+/// `base_factor * bin_step * 10 * 10^base_fee_power_factor` and the variable fee
+/// `variable_fee_control * (volatility_accumulator * bin_step)^2`, rounded up to fee precision.
+/// The variable part uses the volatility accumulator as of the pool's last swap.
+fn pool_fee_rate(lb_pair: &LbPair) -> PoolFeeRate {
+    let parameters = &lb_pair.parameters;
+    let bin_step = u128::from(lb_pair.bin_step);
+    let base_fee_rate = u128::from(parameters.base_factor)
+        * bin_step
+        * 10
+        * 10u128.pow(u32::from(parameters.base_fee_power_factor));
+    let variable_fee_rate = if parameters.variable_fee_control == 0 {
+        0
+    } else {
+        let volatility = u128::from(lb_pair.v_parameters.volatility_accumulator) * bin_step;
+        (u128::from(parameters.variable_fee_control) * volatility * volatility)
+            .div_ceil(VARIABLE_FEE_SCALE)
+    };
+    let total_fee_rate = (base_fee_rate + variable_fee_rate).min(MAX_FEE_RATE);
+    let to_bps = |rate: u128| Decimal::from_i128_with_scale(rate as i128, FEE_PRECISION_PER_BPS);
+    PoolFeeRate {
+        base_fee_bps: to_bps(base_fee_rate),
+        variable_fee_bps: to_bps(variable_fee_rate),
+        total_fee_bps: to_bps(total_fee_rate),
+    }
+}
+
+/// Fee records of the pools behind a quote: the quoted pool, or every hop of a route
+fn build_pool_fees(quote: &PriceQuote, exchange: &str) -> Vec<PoolFee> {
+    let pools: Vec<(Pubkey, Option<PoolFeeRate>)> = if quote.hops.is_empty() {
+        vec![(quote.pool, quote.fee_rate)]
+    } else {
+        quote
+            .hops
+            .iter()
+            .map(|hop| (hop.pool, hop.fee_rate))
+            .collect()
+    };
+    pools
+        .into_iter()
+        .filter_map(|(pool, fee_rate)| {
+            let fee_rate = fee_rate?;
+            Some(PoolFee {
+                exchange: exchange.to_string(),
+                trade_pair: quote.symbol.clone(),
+                pool_address: pool.to_string(),
+                block_number: quote.slot,
+                base_fee_bps: fee_rate.base_fee_bps,
+                variable_fee_bps: fee_rate.variable_fee_bps,
+                total_fee_bps: fee_rate.total_fee_bps,
+                fetch_time: quote.fetched_at,
+            })
+        })
+        .collect()
+}
+
+/// Fee of a swap as a percentage of its input amount
+pub(super) fn fee_pct(quote: &SwapQuote) -> Decimal {
+    if quote.amount_in == 0 {
+        return Decimal::ZERO;
+    }
+    Decimal::from(quote.fee) * Decimal::ONE_HUNDRED / Decimal::from(quote.amount_in)
+}
+
+/// Spot price of the active bin in quote tokens per base token.
+/// DLMM bin prices are `(1 + bin_step / 10_000) ^ active_id` in raw token Y per raw token X,
+/// so the price is inverted when base is token Y and then scaled by the mint decimals.
+/// Returns `None` when the price does not fit in a `Decimal`.
+fn active_bin_spot_price(
+    lb_pair_state: &LbPair,
+    base_is_x: bool,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> Option<Decimal> {
+    let step = Decimal::ONE + Decimal::from(lb_pair_state.bin_step) / Decimal::from(10_000);
+    let exponent = if base_is_x {
+        lb_pair_state.active_id as i64
+    } else {
+        -(lb_pair_state.active_id as i64)
+    };
+    let raw_price = decimal_powi(step, exponent)?;
+    let scale = decimal_powi(Decimal::TEN, base_decimals as i64 - quote_decimals as i64)?;
+    raw_price.checked_mul(scale)
+}
+
+/// `base ^ exponent` by repeated squaring, `None` on overflow
+fn decimal_powi(base: Decimal, exponent: i64) -> Option<Decimal> {
+    let mut result = Decimal::ONE;
+    let mut factor = base;
+    let mut remaining = exponent.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = result.checked_mul(factor)?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            factor = factor.checked_mul(factor)?;
+        }
+    }
+    if exponent < 0 {
+        Decimal::ONE.checked_div(result)
+    } else {
+        Some(result)
+    }
+}
+
+/// Deviation of an effective price from the spot price in basis points.
+/// A side that produced no output has a zero effective price and no impact.
+pub(super) fn price_impact_bps(effective_price: Decimal, spot_price: Decimal) -> Option<Decimal> {
+    if effective_price <= Decimal::ZERO || spot_price <= Decimal::ZERO {
+        return None;
+    }
+    Some((effective_price - spot_price) / spot_price * Decimal::from(10_000))
+}
+
+/// Derive the synthetic (bid, ask) prices in quote tokens per base token.
+/// A side that produced no output is reported as 0.
+pub(super) fn derive_bid_ask(
+    sell: &SwapQuote,
+    buy: &SwapQuote,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> (Decimal, Decimal) {
+    let bid = if sell.amount_out > 0 {
+        normalized_price(
+            sell.amount_in,
+            sell.amount_out,
+            base_decimals,
+            quote_decimals,
+        )
+    } else {
+        Decimal::ZERO
+    };
+    let ask = normalized_price(buy.amount_out, buy.amount_in, base_decimals, quote_decimals);
+    (bid, ask)
+}
+
+/// Run `quote` with the configured bin array count and, when it fails because the
+/// fetched bin arrays did not hold enough liquidity, retry once with twice as many
+/// (capped at `MAX_BIN_ARRAY_COUNT`).
+async fn retry_with_more_bin_arrays<T, F, Fut>(
+    symbol: &str,
+    bin_array_count: u8,
+    mut quote: F,
+) -> Result<T, Box<dyn std::error::Error>>
+where
+    F: FnMut(u8) -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+{
+    // Decide on the retry before awaiting again so the error is not held across the await
+    let retry_count = match quote(bin_array_count).await {
+        Err(e) if is_out_of_liquidity(e.as_ref()) && bin_array_count < MAX_BIN_ARRAY_COUNT => {
+            let retry_count = bin_array_count.saturating_mul(2).min(MAX_BIN_ARRAY_COUNT);
+            warn!(
+                "Meteora quote for {} failed with {} bin arrays ({}), retrying with {}; the configured bin_array_count is too small",
+                symbol, bin_array_count, e, retry_count
+            );
+            retry_count
+        }
+        result => return result,
+    };
+    quote(retry_count).await
+}
+
+/// Whether a quote error means the swap walked past the fetched bin arrays
+fn is_out_of_liquidity(error: &(dyn std::error::Error + 'static)) -> bool {
+    let message = error.to_string().to_lowercase();
+    message.contains("out of liquidity") || message.contains("bin array not found")
+}
+
+/// Find the smallest input (within `search.tolerance`) whose exact-in quote produces at
+/// least `amount_out`, returning that quote and the number of quotes evaluated.
+/// The upper bound starts at `hint` (or 1) and doubles until it is sufficient; the
+/// search fails when the pool stops producing more output or the iteration budget
+/// runs out before any sufficient input is found.
+fn search_exact_out<F>(
+    amount_out: u64,
+    hint: Option<u64>,
+    search: ExactOutSearch,
+    mut quote: F,
+) -> Result<(SwapQuote, u32), Box<dyn std::error::Error>>
+where
+    F: FnMut(u64) -> Result<SwapQuote, Box<dyn std::error::Error>>,
+{
+    let mut iterations = 0;
+
+    // Grow the upper bound until it produces enough output
+    let mut low = 0;
+    let mut high = hint.unwrap_or(1).max(1);
+    let mut previous_out = 0;
+    let mut best = loop {
+        iterations += 1;
+        let candidate = quote(high)?;
+        if candidate.amount_out >= amount_out {
+            break candidate;
+        }
+        if previous_out > 0 && candidate.amount_out <= previous_out {
+            return Err(format!(
+                "Pool cannot produce {} (output capped at {})",
+                amount_out, candidate.amount_out
+            )
+            .into());
+        }
+        if iterations >= search.max_iterations {
+            return Err(format!(
+                "Exact-out search gave up after {} quotes below {}",
+                iterations, amount_out
+            )
+            .into());
+        }
+        previous_out = candidate.amount_out;
+        low = high;
+        high = high.checked_mul(2).ok_or("Exact-out search overflowed")?;
+    };
+
+    // An accurate hint only needs to be confirmed one tolerance step below
+    if hint == Some(high) && high > search.tolerance && iterations < search.max_iterations {
+        let below = high - search.tolerance - 1;
+        iterations += 1;
+        let candidate = quote(below)?;
+        if candidate.amount_out >= amount_out {
+            high = below;
+            best = candidate;
+        } else {
+            low = below;
+        }
+    }
+
+    // Narrow the bracket while the budget allows
+    while high - low > search.tolerance.saturating_add(1) && iterations < search.max_iterations {
+        let mid = low + (high - low) / 2;
+        iterations += 1;
+        let candidate = quote(mid)?;
+        if candidate.amount_out >= amount_out {
+            high = mid;
+            best = candidate;
+        } else {
+            low = mid;
+        }
+    }
+
+    Ok((best, iterations))
+}
+
+/// Quote every ladder size in order and return the `(buy, sell)` points together with
+/// whether the ladder was truncated. The ladder stops at the first failed quote or once
+/// outputs stop increasing, i.e. the pool can't absorb a larger size.
+fn collect_ladder_points<F>(sizes: &[u64], mut quote: F) -> (Vec<(SwapQuote, SwapQuote)>, bool)
+where
+    F: FnMut(u64) -> Result<(SwapQuote, SwapQuote), Box<dyn std::error::Error>>,
+{
+    let mut points: Vec<(SwapQuote, SwapQuote)> = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let (buy, sell) = match quote(size) {
+            Ok(point) => point,
+            Err(e) => {
+                warn!("Meteora ladder quote for size {} failed: {}", size, e);
+                return (points, true);
+            }
+        };
+        let exhausted = points.last().is_some_and(|(last_buy, last_sell)| {
+            buy.amount_out <= last_buy.amount_out || sell.amount_out <= last_sell.amount_out
+        });
+        if exhausted || buy.amount_out == 0 || sell.amount_out == 0 {
+            return (points, true);
+        }
+        points.push((buy, sell));
+    }
+    (points, false)
+}
+
+/// Build a synthetic orderbook from cumulative `(buy, sell)` ladder points.
+/// Each level is the marginal price and base volume between two consecutive points:
+/// asks come from spending quote on base, bids from selling that base back.
+fn build_ladder_book(
+    symbol: &str,
+    base_decimals: u32,
+    quote_decimals: u32,
+    points: &[(SwapQuote, SwapQuote)],
+) -> market::OrderBook {
+    let mut book = market::OrderBook::new("meteora", symbol);
+    let mut previous = (0u64, 0u64, 0u64, 0u64);
+    for (buy, sell) in points {
+        let (prev_ask_quote, prev_ask_base, prev_bid_base, prev_bid_quote) = previous;
+
+        let ask_base = buy.amount_out - prev_ask_base;
+        let ask_quote = buy.amount_in - prev_ask_quote;
+        *book
+            .asks
+            .entry(normalized_price(
+                ask_base,
+                ask_quote,
+                base_decimals,
+                quote_decimals,
+            ))
+            .or_default() += to_ui_amount(ask_base, base_decimals);
+
+        let bid_base = sell.amount_in - prev_bid_base;
+        let bid_quote = sell.amount_out - prev_bid_quote;
+        *book
+            .bids
+            .entry(normalized_price(
+                bid_base,
+                bid_quote,
+                base_decimals,
+                quote_decimals,
+            ))
+            .or_default() += to_ui_amount(bid_base, base_decimals);
+
+        previous = (
+            buy.amount_in,
+            buy.amount_out,
+            sell.amount_in,
+            sell.amount_out,
+        );
+    }
+    book
+}
+
+/// Call `quote` for every symbol returned by `symbols` on each tick until `shutdown` is cancelled.
+/// Cancellation interrupts both the in-flight tick and the sleep between ticks.
+/// A failed quote is logged and does not stop the loop.
+pub(super) async fn run_poll_loop<S, F, Fut>(
+    shutdown: &CancellationToken,
+    interval: Duration,
+    max_concurrency: usize,
+    symbols: S,
+    mut quote: F,
+) where
+    S: Fn() -> Vec<String>,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    while !shutdown.is_cancelled() {
+        let started = Instant::now();
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            durations = run_poll_tick(symbols(), max_concurrency, &mut quote) => {
+                log_tick(started.elapsed(), &durations);
+            }
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+    info!("Meteora screener stopped");
+}
+
+/// Quote every symbol in its own task, at most `max_concurrency` at a time, and wait for
+/// all of them. A failing pair does not cancel the others. Returns how long each pair took.
+async fn run_poll_tick<F, Fut>(
+    symbols: Vec<String>,
+    max_concurrency: usize,
+    quote: &mut F,
+) -> Vec<(String, Duration)>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    let mut durations = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        if tasks.len() >= max_concurrency.max(1)
+            && let Some(joined) = tasks.join_next().await
+        {
+            record_pair_result(joined, &mut durations);
+        }
+        let pair_quote = quote(symbol.clone());
+        tasks.spawn(async move {
+            let started = Instant::now();
+            let result = pair_quote.await;
+            (symbol, started.elapsed(), result)
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        record_pair_result(joined, &mut durations);
+    }
+    durations
+}
+
+/// Log the outcome of a pair task and record its duration
+fn record_pair_result(
+    joined: Result<(String, Duration, Result<(), String>), JoinError>,
+    durations: &mut Vec<(String, Duration)>,
+) {
+    match joined {
+        Ok((symbol, elapsed, result)) => {
+            if let Err(e) = result {
+                error!(
+                    "Meteora quote for {} failed after {:?}: {}",
+                    symbol, elapsed, e
+                );
+            }
+            durations.push((symbol, elapsed));
+        }
+        Err(e) => error!("Meteora quote task panicked: {}", e),
+    }
+}
+
+/// Log the tick duration along with its slowest pair
+fn log_tick(elapsed: Duration, durations: &[(String, Duration)]) {
+    if let Some((symbol, slowest)) = durations.iter().max_by_key(|(_, duration)| *duration) {
+        info!(
+            "[meteora] quoted {} pairs in {:?}, slowest {} took {:?}",
+            durations.len(),
+            elapsed,
+            symbol,
+            slowest
+        );
+    }
+}
+
+#[cfg(test)]
+#[path = "meteora_tests.rs"]
+mod meteora_tests;
@@ -0,0 +1,188 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+
+/// Default base URL of the public Meteora DLMM API
+const DEFAULT_API_URL: &str = "https://dlmm-api.meteora.ag";
+/// Default minimum pool TVL, in USD
+const DEFAULT_MIN_TVL_USD: u64 = 10_000;
+/// Default minimum 24h trade volume, in USD
+const DEFAULT_MIN_VOLUME_24H_USD: u64 = 1_000;
+/// Default number of discovered pools kept per pair
+const DEFAULT_MAX_POOLS: usize = 3;
+/// Timeout of a single API request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type ApiResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Thresholds a pool must meet to be picked up by discovery
+#[derive(Debug, Clone)]
+pub struct DiscoveryFilter {
+    pub min_tvl_usd: Decimal,
+    pub min_volume_24h_usd: Decimal,
+    /// Maximum number of pools kept per pair, deepest first
+    pub max_pools: usize,
+}
+
+impl DiscoveryFilter {
+    /// Read `METEORA_DISCOVERY_MIN_TVL_USD`, `METEORA_DISCOVERY_MIN_VOLUME_24H_USD` and
+    /// `METEORA_DISCOVERY_MAX_POOLS`, falling back to the defaults
+    pub fn from_env() -> Self {
+        Self {
+            min_tvl_usd: decimal_from_env("METEORA_DISCOVERY_MIN_TVL_USD", DEFAULT_MIN_TVL_USD),
+            min_volume_24h_usd: decimal_from_env(
+                "METEORA_DISCOVERY_MIN_VOLUME_24H_USD",
+                DEFAULT_MIN_VOLUME_24H_USD,
+            ),
+            max_pools: std::env::var("METEORA_DISCOVERY_MAX_POOLS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|&pools| pools > 0)
+                .unwrap_or(DEFAULT_MAX_POOLS),
+        }
+    }
+}
+
+fn decimal_from_env(name: &str, default: u64) -> Decimal {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| Decimal::from(default))
+}
+
+/// DLMM pool returned by the Meteora API
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredPool {
+    pub address: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub tvl_usd: Decimal,
+    pub volume_24h_usd: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiGroups {
+    groups: Vec<ApiGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiGroup {
+    pairs: Vec<ApiPair>,
+}
+
+/// Pair entry of the API response; `liquidity` is the pool TVL in USD
+#[derive(Debug, Deserialize)]
+struct ApiPair {
+    address: String,
+    mint_x: String,
+    mint_y: String,
+    #[serde(default)]
+    liquidity: Decimal,
+    #[serde(default)]
+    trade_volume_24h: Decimal,
+    #[serde(default)]
+    is_blacklisted: bool,
+    #[serde(default)]
+    hide: bool,
+}
+
+/// Client of the Meteora DLMM API used to discover pools for a token pair
+pub struct MeteoraApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    filter: DiscoveryFilter,
+}
+
+impl MeteoraApiClient {
+    pub fn new(base_url: &str, filter: DiscoveryFilter) -> ApiResult<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            filter,
+        })
+    }
+
+    /// Build the client from `METEORA_API_URL` and the discovery thresholds
+    pub fn from_env() -> ApiResult<Self> {
+        let base_url = std::env::var("METEORA_API_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+        Self::new(&base_url, DiscoveryFilter::from_env())
+    }
+
+    /// Pools trading `mint_a` against `mint_b` in either orientation that pass the
+    /// discovery filter, sorted by TVL, deepest first
+    pub async fn discover_pools(
+        &self,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+    ) -> ApiResult<Vec<DiscoveredPool>> {
+        let url = format!("{}/pair/all_by_groups", self.base_url);
+        let token_pairs = format!("{}-{},{}-{}", mint_a, mint_b, mint_b, mint_a);
+        let groups: ApiGroups = self
+            .http
+            .get(&url)
+            .query(&[
+                ("include_pool_token_pairs", token_pairs.as_str()),
+                ("sort_key", "tvl"),
+                ("order_by", "desc"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(select_pools(groups, mint_a, mint_b, &self.filter))
+    }
+}
+
+/// Keep the listed pools of the mint pair that pass `filter`, deepest first.
+/// Entries with malformed addresses are skipped.
+fn select_pools(
+    groups: ApiGroups,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    filter: &DiscoveryFilter,
+) -> Vec<DiscoveredPool> {
+    let mut pools: Vec<DiscoveredPool> = groups
+        .groups
+        .into_iter()
+        .flat_map(|group| group.pairs)
+        .filter(|pair| !pair.is_blacklisted && !pair.hide)
+        .filter(|pair| {
+            pair.liquidity >= filter.min_tvl_usd
+                && pair.trade_volume_24h >= filter.min_volume_24h_usd
+        })
+        .filter_map(|pair| {
+            Some(DiscoveredPool {
+                address: pair.address.parse().ok()?,
+                mint_x: pair.mint_x.parse().ok()?,
+                mint_y: pair.mint_y.parse().ok()?,
+                tvl_usd: pair.liquidity,
+                volume_24h_usd: pair.trade_volume_24h,
+            })
+        })
+        .filter(|pool| {
+            (pool.mint_x == *mint_a && pool.mint_y == *mint_b)
+                || (pool.mint_x == *mint_b && pool.mint_y == *mint_a)
+        })
+        .collect();
+    // Ties are ordered by address so duplicates listed in several groups end up adjacent
+    pools.sort_by(|a, b| {
+        b.tvl_usd
+            .cmp(&a.tvl_usd)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    pools.dedup_by_key(|pool| pool.address);
+    pools.truncate(filter.max_pools);
+    pools
+}
+
+#[cfg(test)]
+#[path = "meteora_api_tests.rs"]
+mod meteora_api_tests;
@@ -0,0 +1,106 @@
+use super::*;
+
+fn filter() -> DiscoveryFilter {
+    DiscoveryFilter {
+        min_tvl_usd: Decimal::from(10_000),
+        min_volume_24h_usd: Decimal::from(1_000),
+        max_pools: 3,
+    }
+}
+
+fn api_pair(address: &Pubkey, mint_x: &Pubkey, mint_y: &Pubkey, tvl: &str, volume: f64) -> String {
+    format!(
+        r#"{{"address":"{}","name":"TRUMP-USDC","mint_x":"{}","mint_y":"{}","liquidity":"{}","trade_volume_24h":{},"bin_step":25}}"#,
+        address, mint_x, mint_y, tvl, volume
+    )
+}
+
+fn groups(pairs: &[String]) -> ApiGroups {
+    let json = format!(
+        r#"{{"groups":[{{"name":"TRUMP-USDC","pairs":[{}]}}],"total":{}}}"#,
+        pairs.join(","),
+        pairs.len()
+    );
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn select_pools_sorts_by_tvl_and_accepts_both_orientations() {
+    let (trump, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (shallow, deep) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let response = groups(&[
+        api_pair(&shallow, &trump, &usdc, "20000.5", 5000.0),
+        api_pair(&deep, &usdc, &trump, "900000", 250000.0),
+    ]);
+
+    let pools = select_pools(response, &trump, &usdc, &filter());
+
+    assert_eq!(
+        pools.iter().map(|pool| pool.address).collect::<Vec<_>>(),
+        vec![deep, shallow]
+    );
+    assert_eq!(pools[1].tvl_usd, "20000.5".parse::<Decimal>().unwrap());
+    assert_eq!(pools[0].volume_24h_usd, Decimal::from(250_000));
+}
+
+#[test]
+fn select_pools_applies_thresholds_and_skips_other_pairs() {
+    let (trump, usdc, sol) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let kept = Pubkey::new_unique();
+    let response = groups(&[
+        api_pair(&kept, &trump, &usdc, "50000", 2000.0),
+        api_pair(&Pubkey::new_unique(), &trump, &usdc, "9999", 2000.0),
+        api_pair(&Pubkey::new_unique(), &trump, &usdc, "50000", 10.0),
+        api_pair(&Pubkey::new_unique(), &trump, &sol, "50000", 2000.0),
+        r#"{"address":"not-a-pubkey","mint_x":"x","mint_y":"y","liquidity":"50000","trade_volume_24h":2000}"#
+            .to_string(),
+    ]);
+
+    let pools = select_pools(response, &trump, &usdc, &filter());
+
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].address, kept);
+}
+
+#[test]
+fn select_pools_skips_hidden_and_blacklisted_pools() {
+    let (trump, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let hidden = api_pair(&Pubkey::new_unique(), &trump, &usdc, "50000", 2000.0)
+        .replace("\"bin_step\"", "\"hide\":true,\"bin_step\"");
+    let blacklisted = api_pair(&Pubkey::new_unique(), &trump, &usdc, "50000", 2000.0)
+        .replace("\"bin_step\"", "\"is_blacklisted\":true,\"bin_step\"");
+
+    let pools = select_pools(groups(&[hidden, blacklisted]), &trump, &usdc, &filter());
+
+    assert!(pools.is_empty());
+}
+
+#[test]
+fn select_pools_keeps_the_deepest_pools_once() {
+    let (trump, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let duplicated = Pubkey::new_unique();
+    let mut pairs: Vec<String> = (0..4)
+        .map(|i| {
+            api_pair(
+                &Pubkey::new_unique(),
+                &trump,
+                &usdc,
+                &(20_000 + i).to_string(),
+                2000.0,
+            )
+        })
+        .collect();
+    pairs.push(api_pair(&duplicated, &trump, &usdc, "80000", 2000.0));
+    pairs.push(api_pair(&duplicated, &trump, &usdc, "80000", 2000.0));
+
+    let pools = select_pools(groups(&pairs), &trump, &usdc, &filter());
+
+    assert_eq!(pools.len(), 3);
+    assert_eq!(pools[0].address, duplicated);
+    assert_eq!(pools[1].tvl_usd, Decimal::from(20_003));
+    assert_eq!(pools[2].tvl_usd, Decimal::from(20_002));
+}
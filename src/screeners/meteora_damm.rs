@@ -0,0 +1,455 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::screeners::dex_runner::{DexQuoter, DexScreenerRunner, get_best_price};
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora::{
+    BestPriceQuote, MeteoraConfig, PoolConfig, PoolLiquidity, PriceQuote, SwapQuote, TradeConfig,
+    clock_drift_secs, derive_bid_ask, fee_pct, is_clock_stale, max_clock_drift_from_env,
+    max_concurrent_pairs_from_env, max_quote_age_from_env, mint_decimals, normalized_price,
+    pairs_refresh_interval_from_env, poll_interval_from_env, price_impact_bps, select_best_price,
+    successful_pool_results,
+};
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::solana::utils::token_account_amount;
+use crate::store::db::DatabasePool;
+
+/// Venue name of DAMM v2 pairs in the trade_pairs table, also used as the DEX market exchange
+const VENUE: &str = "meteora_damm";
+/// Anchor discriminator (`sha256("account:Pool")[..8]`) of the DAMM v2 pool account
+const POOL_DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+/// Offset of the base fee `cliff_fee_numerator`, the first field of the pool fees
+const CLIFF_FEE_NUMERATOR_OFFSET: usize = 8;
+/// Offsets of the mints and vaults, right after the 160-byte pool fees struct
+const TOKEN_A_MINT_OFFSET: usize = 168;
+const TOKEN_B_MINT_OFFSET: usize = 200;
+const TOKEN_A_VAULT_OFFSET: usize = 232;
+const TOKEN_B_VAULT_OFFSET: usize = 264;
+const POOL_MIN_LEN: usize = TOKEN_B_VAULT_OFFSET + 32;
+/// Denominator of DAMM v2 fee numerators
+const FEE_DENOMINATOR: u64 = 1_000_000_000;
+
+/// Fields of a DAMM v2 pool account needed to quote it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DammPoolState {
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    /// Base trading fee over `FEE_DENOMINATOR`
+    pub fee_numerator: u64,
+}
+
+/// Pool state and vault reserves read at the same slot
+#[derive(Debug, Clone)]
+pub struct DammSnapshot {
+    pub pool: Pubkey,
+    pub state: DammPoolState,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub decimals_a: u32,
+    pub decimals_b: u32,
+    pub clock: Clock,
+    /// Commitment the accounts were read at
+    pub commitment: CommitmentLevel,
+    /// Whether the Clock drifted from wall time by more than the allowed drift
+    pub stale: bool,
+    /// Wall time the account fetch started at
+    pub fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of both account round trips
+    pub fetch_latency: Duration,
+}
+
+impl DammSnapshot {
+    fn block_time(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.clock.unix_timestamp, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+/// Polls Meteora DAMM v2 (constant-product) pools and persists their best bid and ask
+pub struct DammScreener {
+    pub db_pool: DatabasePool,
+    pub rpc_client: FailoverRpcClient,
+    /// Commitment of pool, vault, mint and Clock reads
+    pub commitment: CommitmentConfig,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Delay between two reloads of the trade pairs table
+    pub pairs_refresh_interval: Duration,
+    /// Number of pairs quoted concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    /// Drift between the Clock sysvar and wall time above which a quote is tagged stale
+    pub max_clock_drift: Duration,
+    /// Age above which a best quote is rejected instead of being returned or persisted
+    pub max_quote_age: Duration,
+    /// Trade configs loaded from the database, keyed by symbol; `base_is_x` means base is token A
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    /// Decimals of every mint quoted so far
+    mint_decimals: RwLock<HashMap<Pubkey, u32>>,
+}
+
+impl DammScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, MeteoraConfig::from_env()?))
+    }
+
+    /// Build the screener on already resolved RPC settings. Pool, vault and Clock reads
+    /// all use `config.commitment`.
+    pub fn with_config(db_pool: DatabasePool, config: MeteoraConfig) -> Self {
+        info!(
+            "Meteora DAMM RPC endpoints: {}",
+            config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Self {
+            db_pool,
+            rpc_client: FailoverRpcClient::from_urls(config.rpc_endpoints, config.commitment),
+            commitment: config.commitment,
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            pairs_refresh_interval: pairs_refresh_interval_from_env(),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            max_clock_drift: max_clock_drift_from_env(),
+            max_quote_age: max_quote_age_from_env(),
+            trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+            mint_decimals: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Poll quotes for every configured pair until the screener is stopped
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Meteora DAMM screener (poll interval {:?})...",
+            self.poll_interval
+        );
+
+        DexScreenerRunner {
+            quoter: self.clone(),
+            db_pool: self.db_pool.clone(),
+            dex_writer: Arc::new(DexMarketWriter::for_database(self.db_pool.clone(), VENUE)),
+            shutdown: self.shutdown.clone(),
+            poll_interval: self.poll_interval,
+            pairs_refresh_interval: self.pairs_refresh_interval,
+            max_concurrent_pairs: self.max_concurrent_pairs,
+            max_quote_age: self.max_quote_age,
+            trade_pairs: self.trade_pairs.clone(),
+            // DAMM pairs are never auto-discovered
+            discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+        }
+        .run()
+        .await
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Quote both swap directions on every pool of a pair and keep the best bid and best ask.
+    /// The buy side spends the quote token received for `amount_in` base token.
+    pub async fn get_price(
+        &self,
+        symbol: &str,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        get_best_price(
+            self,
+            &self.trade_pairs,
+            symbol,
+            amount_in,
+            self.max_quote_age,
+        )
+        .await
+    }
+
+    async fn get_pool_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let snapshot = self.fetch_snapshot(pool.pool_pubkey).await?;
+        let sell_a_for_b = pool.base_is_x;
+        let sell = quote_exact_in(&snapshot, amount_in, sell_a_for_b)?;
+        if sell.amount_out == 0 {
+            return Err(format!("Pool returned no output when selling {}", symbol).into());
+        }
+        let buy = quote_exact_in(&snapshot, sell.amount_out, !sell_a_for_b)?;
+        Ok(build_price_quote(
+            symbol,
+            &snapshot,
+            pool.base_is_x,
+            sell,
+            buy,
+        ))
+    }
+
+    /// Fetch the pool account and Clock, then both vaults and mints in a second round trip
+    async fn fetch_snapshot(
+        &self,
+        pool: Pubkey,
+    ) -> Result<DammSnapshot, Box<dyn std::error::Error>> {
+        let fetched_at = Utc::now();
+        let fetch_started = Instant::now();
+        let mut accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[pool, solana_sdk::sysvar::clock::ID])
+            .await?
+            .into_iter();
+        let pool_account = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| format!("DAMM pool {} not found", pool))?;
+        let clock_account = accounts.next().flatten().ok_or("Failed to fetch clock")?;
+        let state = decode_pool(&pool_account.data)
+            .map_err(|e| format!("Invalid DAMM pool {}: {}", pool, e))?;
+        let clock: Clock = bincode::deserialize(&clock_account.data)?;
+        let now = Utc::now();
+        let stale = is_clock_stale(&clock, now, self.max_clock_drift);
+        if stale {
+            warn!(
+                "Clock sysvar at slot {} drifted {}s from wall time, tagging DAMM pool {} quote as stale",
+                clock.slot,
+                clock_drift_secs(&clock, now),
+                pool
+            );
+        }
+
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[
+                state.token_a_vault,
+                state.token_b_vault,
+                state.token_a_mint,
+                state.token_b_mint,
+            ])
+            .await?;
+        let [vault_a, vault_b, mint_a, mint_b] = accounts.as_slice() else {
+            return Err("Unexpected number of DAMM accounts".into());
+        };
+        let required = |account: &Option<Account>, name: &str| {
+            account
+                .clone()
+                .ok_or_else(|| format!("DAMM pool {} {} not found", pool, name))
+        };
+        let reserve_a = token_account_amount(&required(vault_a, "token A vault")?)?;
+        let reserve_b = token_account_amount(&required(vault_b, "token B vault")?)?;
+        let decimals_a = self.cached_mint_decimals(state.token_a_mint, mint_a)?;
+        let decimals_b = self.cached_mint_decimals(state.token_b_mint, mint_b)?;
+
+        Ok(DammSnapshot {
+            pool,
+            state,
+            reserve_a,
+            reserve_b,
+            decimals_a,
+            decimals_b,
+            clock,
+            commitment: self.commitment.commitment,
+            stale,
+            fetched_at,
+            fetch_latency: fetch_started.elapsed(),
+        })
+    }
+
+    /// Decimals of a mint, unpacked from its account on first use
+    fn cached_mint_decimals(
+        &self,
+        mint: Pubkey,
+        mint_account: &Option<Account>,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        if let Some(decimals) = self.mint_decimals.read().unwrap().get(&mint) {
+            return Ok(*decimals);
+        }
+        let mint_account = mint_account
+            .as_ref()
+            .ok_or_else(|| format!("Mint {} not found", mint))?;
+        let decimals = mint_decimals(mint_account)
+            .map_err(|e| format!("Failed to read decimals of mint {}: {}", mint, e))?;
+        self.mint_decimals.write().unwrap().insert(mint, decimals);
+        Ok(decimals)
+    }
+}
+
+#[async_trait]
+impl DexQuoter for DammScreener {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            self.get_pool_price(symbol, pool, amount_in)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+        .await;
+        let quotes = successful_pool_results(symbol, &trade_config.pools, results);
+
+        select_best_price(symbol, quotes)
+            .ok_or_else(|| format!("No Meteora DAMM pool could quote {}", symbol).into())
+    }
+}
+
+/// Decode the DAMM v2 pool fields needed for quoting, checking the discriminator and length
+fn decode_pool(data: &[u8]) -> Result<DammPoolState, String> {
+    if data.len() < POOL_MIN_LEN {
+        return Err(format!(
+            "account is {} bytes, expected at least {}",
+            data.len(),
+            POOL_MIN_LEN
+        ));
+    }
+    if data[..8] != POOL_DISCRIMINATOR {
+        return Err("account discriminator does not match Pool".to_string());
+    }
+    let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).unwrap();
+    Ok(DammPoolState {
+        token_a_mint: pubkey_at(TOKEN_A_MINT_OFFSET),
+        token_b_mint: pubkey_at(TOKEN_B_MINT_OFFSET),
+        token_a_vault: pubkey_at(TOKEN_A_VAULT_OFFSET),
+        token_b_vault: pubkey_at(TOKEN_B_VAULT_OFFSET),
+        fee_numerator: u64::from_le_bytes(
+            data[CLIFF_FEE_NUMERATOR_OFFSET..CLIFF_FEE_NUMERATOR_OFFSET + 8]
+                .try_into()
+                .unwrap(),
+        ),
+    })
+}
+
+/// Quote an exact-in swap against the vault reserves with the constant-product formula.
+/// The fee is taken from the input, rounded up, before it reaches the curve.
+/// Only the base fee is applied, and the reserves are treated as full-range liquidity,
+/// so pools with a dynamic fee or a narrowed price range are quoted approximately.
+fn quote_exact_in(
+    snapshot: &DammSnapshot,
+    amount_in: u64,
+    a_for_b: bool,
+) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    let (reserve_in, reserve_out) = if a_for_b {
+        (snapshot.reserve_a, snapshot.reserve_b)
+    } else {
+        (snapshot.reserve_b, snapshot.reserve_a)
+    };
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(format!("DAMM pool {} has an empty reserve", snapshot.pool).into());
+    }
+    let fee = (amount_in as u128 * snapshot.state.fee_numerator as u128)
+        .div_ceil(FEE_DENOMINATOR as u128) as u64;
+    let amount_in_after_fee = amount_in.saturating_sub(fee) as u128;
+    let amount_out =
+        reserve_out as u128 * amount_in_after_fee / (reserve_in as u128 + amount_in_after_fee);
+    Ok(SwapQuote::without_transfer_fees(
+        amount_in,
+        amount_out as u64,
+        fee,
+    ))
+}
+
+/// Quote tokens per base token implied by the reserves, before fees and price impact
+fn spot_price(snapshot: &DammSnapshot, base_is_a: bool) -> Option<Decimal> {
+    let (base_reserve, quote_reserve, base_decimals, quote_decimals) = if base_is_a {
+        (
+            snapshot.reserve_a,
+            snapshot.reserve_b,
+            snapshot.decimals_a,
+            snapshot.decimals_b,
+        )
+    } else {
+        (
+            snapshot.reserve_b,
+            snapshot.reserve_a,
+            snapshot.decimals_b,
+            snapshot.decimals_a,
+        )
+    };
+    let price = normalized_price(base_reserve, quote_reserve, base_decimals, quote_decimals);
+    (price > Decimal::ZERO).then_some(price)
+}
+
+fn build_price_quote(
+    symbol: &str,
+    snapshot: &DammSnapshot,
+    base_is_a: bool,
+    sell: SwapQuote,
+    buy: SwapQuote,
+) -> PriceQuote {
+    let (base_decimals, quote_decimals, base_reserve, quote_reserve) = if base_is_a {
+        (
+            snapshot.decimals_a,
+            snapshot.decimals_b,
+            snapshot.reserve_a,
+            snapshot.reserve_b,
+        )
+    } else {
+        (
+            snapshot.decimals_b,
+            snapshot.decimals_a,
+            snapshot.reserve_b,
+            snapshot.reserve_a,
+        )
+    };
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, base_decimals, quote_decimals);
+    let spot_price = spot_price(snapshot, base_is_a);
+
+    PriceQuote {
+        symbol: symbol.to_string(),
+        pool: snapshot.pool,
+        route: None,
+        hops: Vec::new(),
+        slot: snapshot.clock.slot,
+        block_time: snapshot.block_time(),
+        fetched_at: snapshot.fetched_at,
+        fetch_latency: snapshot.fetch_latency,
+        commitment: snapshot.commitment,
+        stale: snapshot.stale,
+        missing_bin_arrays: 0,
+        base_decimals,
+        quote_decimals,
+        bid_impact_bps: spot_price.and_then(|spot| price_impact_bps(bid_price, spot)),
+        ask_impact_bps: spot_price.and_then(|spot| price_impact_bps(ask_price, spot)),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: None,
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price,
+        liquidity: PoolLiquidity::new(
+            base_reserve as u128,
+            quote_reserve as u128,
+            base_decimals,
+            quote_decimals,
+            spot_price,
+        ),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+#[cfg(test)]
+#[path = "meteora_damm_tests.rs"]
+mod meteora_damm_tests;
@@ -0,0 +1,147 @@
+use super::*;
+use crate::solana::utils::{TOKEN_ACCOUNT_AMOUNT_OFFSET, TOKEN_ACCOUNT_LEN};
+
+/// 1M token A against 10M token B, both with 6 decimals, and a 0.25% fee
+fn fixture_snapshot() -> DammSnapshot {
+    DammSnapshot {
+        pool: Pubkey::new_unique(),
+        state: DammPoolState {
+            token_a_mint: Pubkey::new_unique(),
+            token_b_mint: Pubkey::new_unique(),
+            token_a_vault: Pubkey::new_unique(),
+            token_b_vault: Pubkey::new_unique(),
+            fee_numerator: 2_500_000,
+        },
+        reserve_a: 1_000_000_000_000,
+        reserve_b: 10_000_000_000_000,
+        decimals_a: 6,
+        decimals_b: 6,
+        clock: Clock {
+            slot: 42,
+            unix_timestamp: 1_700_000_000,
+            ..Clock::default()
+        },
+        commitment: CommitmentLevel::Confirmed,
+        stale: false,
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(40),
+    }
+}
+
+fn pool_account_data(state: &DammPoolState) -> Vec<u8> {
+    let mut data = vec![0u8; 1112];
+    data[..8].copy_from_slice(&POOL_DISCRIMINATOR);
+    data[CLIFF_FEE_NUMERATOR_OFFSET..CLIFF_FEE_NUMERATOR_OFFSET + 8]
+        .copy_from_slice(&state.fee_numerator.to_le_bytes());
+    for (offset, pubkey) in [
+        (TOKEN_A_MINT_OFFSET, state.token_a_mint),
+        (TOKEN_B_MINT_OFFSET, state.token_b_mint),
+        (TOKEN_A_VAULT_OFFSET, state.token_a_vault),
+        (TOKEN_B_VAULT_OFFSET, state.token_b_vault),
+    ] {
+        data[offset..offset + 32].copy_from_slice(pubkey.as_ref());
+    }
+    data
+}
+
+fn token_account(amount: u64) -> Account {
+    let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+    data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+        .copy_from_slice(&amount.to_le_bytes());
+    Account {
+        data,
+        owner: spl_token_2022::id(),
+        ..Account::default()
+    }
+}
+
+#[test]
+fn decode_pool_reads_mints_vaults_and_fee() {
+    let state = fixture_snapshot().state;
+
+    assert_eq!(decode_pool(&pool_account_data(&state)).unwrap(), state);
+}
+
+#[test]
+fn decode_pool_rejects_other_accounts() {
+    let data = pool_account_data(&fixture_snapshot().state);
+    let mut wrong_discriminator = data.clone();
+    wrong_discriminator[0] ^= 1;
+
+    assert!(decode_pool(&data[..POOL_MIN_LEN - 1]).is_err());
+    assert!(decode_pool(&wrong_discriminator).is_err());
+}
+
+#[test]
+fn token_account_amount_reads_vault_balance() {
+    assert_eq!(
+        token_account_amount(&token_account(123_456)).unwrap(),
+        123_456
+    );
+
+    let not_a_token_account = Account {
+        owner: Pubkey::new_unique(),
+        ..token_account(1)
+    };
+    assert!(token_account_amount(&not_a_token_account).is_err());
+}
+
+#[test]
+fn quote_exact_in_applies_fee_and_constant_product() {
+    let snapshot = fixture_snapshot();
+
+    let sell = quote_exact_in(&snapshot, 1_000_000_000, true).unwrap();
+    assert_eq!(sell.fee, 2_500_000);
+    assert_eq!(sell.amount_out, 9_965_059_852);
+
+    let buy = quote_exact_in(&snapshot, 10_000_000_000, false).unwrap();
+    assert_eq!(buy.fee, 25_000_000);
+    assert_eq!(buy.amount_out, 996_505_985);
+}
+
+#[test]
+fn quote_exact_in_rounds_the_fee_up() {
+    let quote = quote_exact_in(&fixture_snapshot(), 7, true).unwrap();
+
+    assert_eq!(quote.fee, 1);
+    assert_eq!(quote.amount_out, 59);
+}
+
+#[test]
+fn quote_exact_in_rejects_empty_reserves() {
+    let mut snapshot = fixture_snapshot();
+    snapshot.reserve_b = 0;
+
+    assert!(quote_exact_in(&snapshot, 1_000, true).is_err());
+}
+
+#[test]
+fn build_price_quote_brackets_the_reserve_price() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 1_000_000_000, true).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, false).unwrap();
+
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, true, sell, buy);
+
+    assert_eq!(quote.spot_price, Some(Decimal::TEN));
+    assert!(quote.bid_price < Decimal::TEN);
+    assert!(quote.ask_price > Decimal::TEN);
+    assert!(quote.bid_impact_bps.unwrap() < Decimal::ZERO);
+    assert_eq!(quote.sell_fee_pct, "0.25".parse::<Decimal>().unwrap());
+    assert_eq!(quote.slot, 42);
+    assert_eq!(quote.pool, snapshot.pool);
+    // 1M base at 10 plus 10M quote
+    assert_eq!(quote.liquidity.notional, Some(Decimal::from(20_000_000)));
+}
+
+#[test]
+fn build_price_quote_inverts_when_base_is_token_b() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 1_000_000_000, false).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, true).unwrap();
+
+    let quote = build_price_quote("USDCTRUMP", &snapshot, false, sell, buy);
+
+    assert_eq!(quote.spot_price, Some("0.1".parse::<Decimal>().unwrap()));
+    assert!(quote.bid_price < quote.ask_price);
+}
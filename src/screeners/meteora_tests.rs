@@ -0,0 +1,2121 @@
+use super::*;
+use crate::screeners::dex_runner::save_best_price;
+use crate::screeners::meteora_api::DiscoveryFilter;
+use crate::solana::utils::{TOKEN_ACCOUNT_AMOUNT_OFFSET, TOKEN_ACCOUNT_LEN};
+use crate::store::db::lazy_pool;
+use crate::store::markets::{DexMarketFilter, get_dex_markets};
+use crate::store::test_db::test_pool;
+use std::str::FromStr;
+use std::sync::atomic::AtomicUsize;
+
+fn build_screener() -> MeteoraScreener {
+    let pool = lazy_pool();
+
+    MeteoraScreener {
+        db_pool: pool,
+        rpc_client: FailoverRpcClient::from_urls(
+            vec!["http://localhost:8899".to_string()],
+            CommitmentConfig::confirmed(),
+        ),
+        rpc_metrics: Arc::new(LatencyMetrics::new("rpc")),
+        fee_estimator: Arc::new(SolanaFeeEstimator::new(LandingCostConfig::from_env())),
+        commitment: CommitmentConfig::confirmed(),
+        clock_commitment: CommitmentConfig::processed(),
+        shutdown: CancellationToken::new(),
+        poll_interval: Duration::from_millis(1),
+        pairs_refresh_interval: Duration::from_secs(60),
+        discovery_refresh_interval: Duration::from_secs(60),
+        meteora_api: Arc::new(
+            MeteoraApiClient::new("http://localhost:8080", DiscoveryFilter::from_env()).unwrap(),
+        ),
+        max_concurrent_pairs: DEFAULT_MAX_CONCURRENT_PAIRS,
+        exact_out_search: ExactOutSearch {
+            tolerance: DEFAULT_EXACT_OUT_TOLERANCE,
+            max_iterations: DEFAULT_EXACT_OUT_MAX_ITERATIONS,
+        },
+        quote_verification: None,
+        spot_poll_interval: None,
+        min_pool_liquidity: Decimal::from(DEFAULT_MIN_POOL_LIQUIDITY),
+        max_clock_drift: Duration::from_secs(DEFAULT_MAX_CLOCK_DRIFT_SECS),
+        retry_stale_clock: false,
+        allow_partial_quotes: false,
+        max_quote_age: Duration::from_millis(DEFAULT_MAX_QUOTE_AGE_MS),
+        trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+        discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+        account_cache: AccountCache::new(DEFAULT_CACHE_MAX_SLOT_AGE, Duration::from_secs(60)),
+        bitmap_extensions: BitmapExtensionCache::new(Duration::from_secs(
+            DEFAULT_BITMAP_EXTENSION_TTL_SECS,
+        )),
+        last_slot: AtomicU64::new(0),
+        mint_decimals: RwLock::new(HashMap::new()),
+        watched_accounts: RwLock::new(HashMap::new()),
+        degraded_pairs: RwLock::new(HashSet::new()),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn run_poll_loop_exits_when_shutdown_is_cancelled() {
+    let shutdown = CancellationToken::new();
+    let calls = AtomicUsize::new(0);
+    let symbols = vec!["TRUMPUSDC".to_string()];
+
+    let finished = tokio::time::timeout(
+        Duration::from_secs(1),
+        run_poll_loop(
+            &shutdown,
+            Duration::from_millis(1),
+            DEFAULT_MAX_CONCURRENT_PAIRS,
+            || symbols.clone(),
+            |_| {
+                let count = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                if count == 3 {
+                    shutdown.cancel();
+                }
+                async { Ok(()) }
+            },
+        ),
+    )
+    .await;
+
+    assert!(finished.is_ok());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn run_poll_loop_keeps_polling_after_failed_quote() {
+    let shutdown = CancellationToken::new();
+    let calls = AtomicUsize::new(0);
+    let symbols = vec!["TRUMPUSDC".to_string(), "TRUMPUSDT".to_string()];
+
+    run_poll_loop(
+        &shutdown,
+        Duration::from_millis(1),
+        DEFAULT_MAX_CONCURRENT_PAIRS,
+        || symbols.clone(),
+        |symbol| {
+            let count = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if count == 4 {
+                shutdown.cancel();
+            }
+            async move {
+                if symbol == "TRUMPUSDC" {
+                    Err("quote failed".into())
+                } else {
+                    Ok(())
+                }
+            }
+        },
+    )
+    .await;
+
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn stop_interrupts_poll_loop_sleep() {
+    let screener = Arc::new(build_screener());
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let task = {
+        let screener = screener.clone();
+        let calls = calls.clone();
+        tokio::spawn(async move {
+            run_poll_loop(
+                &screener.shutdown,
+                Duration::from_secs(60),
+                DEFAULT_MAX_CONCURRENT_PAIRS,
+                || vec!["TRUMPUSDC".to_string()],
+                |_| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async { Ok(()) }
+                },
+            )
+            .await;
+        })
+    };
+
+    while calls.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+    screener.stop().await.unwrap();
+
+    let finished = tokio::time::timeout(Duration::from_secs(1), task).await;
+    assert!(finished.is_ok());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn run_poll_loop_does_not_quote_when_already_stopped() {
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+    let calls = AtomicUsize::new(0);
+    let symbols = vec!["TRUMPUSDC".to_string()];
+
+    run_poll_loop(
+        &shutdown,
+        Duration::from_millis(1),
+        DEFAULT_MAX_CONCURRENT_PAIRS,
+        || symbols.clone(),
+        |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok(()) }
+        },
+    )
+    .await;
+
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+fn symbols(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("PAIR{}", i)).collect()
+}
+
+/// Quoter sleeping for 100ms per pair
+fn slow_quote(_symbol: String) -> impl Future<Output = Result<(), String>> + Send + 'static {
+    async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_poll_tick_quotes_pairs_in_parallel() {
+    let started = tokio::time::Instant::now();
+
+    let durations = run_poll_tick(symbols(4), 8, &mut slow_quote).await;
+
+    assert_eq!(durations.len(), 4);
+    assert!(
+        durations
+            .iter()
+            .all(|(_, duration)| *duration == Duration::from_millis(100))
+    );
+    // Sequential quoting would take the sum of the sleeps, 400ms
+    assert_eq!(started.elapsed(), Duration::from_millis(100));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_poll_tick_bounds_concurrency() {
+    let started = tokio::time::Instant::now();
+
+    let durations = run_poll_tick(symbols(4), 2, &mut slow_quote).await;
+
+    assert_eq!(durations.len(), 4);
+    assert_eq!(started.elapsed(), Duration::from_millis(200));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_poll_tick_isolates_failing_pairs() {
+    let mut quote = |symbol: String| async move {
+        if symbol == "PAIR0" {
+            return Err("Pool out of liquidity".to_string());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Ok(())
+    };
+
+    let mut durations = run_poll_tick(symbols(3), 8, &mut quote).await;
+    durations.sort();
+
+    assert_eq!(
+        durations,
+        vec![
+            ("PAIR0".to_string(), Duration::ZERO),
+            ("PAIR1".to_string(), Duration::from_millis(100)),
+            ("PAIR2".to_string(), Duration::from_millis(100)),
+        ]
+    );
+}
+
+/// Sell 1 base unit into a pool priced at 10 quote per base with a 1% fee,
+/// then spend the proceeds buying back base through the same fee.
+fn fixture_quotes() -> (SwapQuote, SwapQuote) {
+    let sell = SwapQuote::without_transfer_fees(1_000_000, 9_900_000, 10_000);
+    let buy = SwapQuote::without_transfer_fees(9_900_000, 980_100, 99_000);
+    (sell, buy)
+}
+
+#[test]
+fn derive_bid_ask_keeps_ask_above_bid_for_fee_charging_pool() {
+    let (sell, buy) = fixture_quotes();
+
+    let (bid, ask) = derive_bid_ask(&sell, &buy, 6, 6);
+
+    assert_eq!(bid, Decimal::from_str("9.9").unwrap());
+    assert_eq!(
+        ask,
+        Decimal::from_str("9.9").unwrap() / Decimal::from_str("0.9801").unwrap()
+    );
+    assert!(ask >= bid);
+}
+
+#[test]
+fn derive_bid_ask_is_symmetric_without_fees() {
+    let sell = SwapQuote::without_transfer_fees(1_000_000, 10_000_000, 0);
+    let buy = SwapQuote::without_transfer_fees(10_000_000, 1_000_000, 0);
+
+    let (bid, ask) = derive_bid_ask(&sell, &buy, 6, 6);
+
+    assert_eq!(bid, ask);
+}
+
+#[test]
+fn derive_bid_ask_reports_zero_for_empty_side() {
+    let (sell, mut buy) = fixture_quotes();
+    buy.amount_out = 0;
+
+    let (bid, ask) = derive_bid_ask(&sell, &buy, 6, 6);
+
+    assert!(bid > Decimal::ZERO);
+    assert_eq!(ask, Decimal::ZERO);
+}
+
+#[test]
+fn derive_bid_ask_normalizes_nine_decimal_base() {
+    // Selling 1 SOL (9 decimals) for 150.25 USDC (6 decimals), buying 0.99 SOL back for 150 USDC
+    let sell = swap(1_000_000_000, 150_250_000);
+    let buy = swap(150_000_000, 990_000_000);
+
+    let (bid, ask) = derive_bid_ask(&sell, &buy, 9, 6);
+
+    assert_eq!(bid, Decimal::from_str("150.25").unwrap());
+    assert_eq!(ask, Decimal::from(150) / Decimal::from_str("0.99").unwrap());
+}
+
+#[test]
+fn derive_bid_ask_normalizes_nine_decimal_quote() {
+    // Selling 2 TRUMP (6 decimals) for 0.05 SOL (9 decimals)
+    let sell = swap(2_000_000, 50_000_000);
+    let buy = swap(50_000_000, 1_990_000);
+
+    let (bid, ask) = derive_bid_ask(&sell, &buy, 6, 9);
+
+    assert_eq!(bid, Decimal::from_str("0.025").unwrap());
+    assert_eq!(
+        ask,
+        Decimal::from_str("0.05").unwrap() / Decimal::from_str("1.99").unwrap()
+    );
+}
+
+#[test]
+fn fee_pct_is_relative_to_input() {
+    let (sell, buy) = fixture_quotes();
+
+    assert_eq!(fee_pct(&sell), Decimal::ONE);
+    assert_eq!(fee_pct(&buy), Decimal::ONE);
+    assert_eq!(fee_pct(&swap(0, 0)), Decimal::ZERO);
+}
+
+const LEGACY_TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// Initialized legacy SPL Token mint
+fn mint_account(decimals: u8) -> Account {
+    // Mint layout: mint_authority (36 bytes), supply (8 bytes), decimals, is_initialized
+    let mut data = vec![0u8; 82];
+    data[44] = decimals;
+    data[45] = 1;
+    Account {
+        data,
+        owner: Pubkey::from_str(LEGACY_TOKEN_PROGRAM).unwrap(),
+        ..Account::default()
+    }
+}
+
+/// Initialized Token-2022 mint with room for extensions after the base layout
+fn token_2022_mint_account(decimals: u8) -> Account {
+    let mut account = mint_account(decimals);
+    // Base mint padded to the token account size, then the account type (1 = mint)
+    account.data.resize(166, 0);
+    account.data[165] = 1;
+    account.owner = spl_token_2022::id();
+    account
+}
+
+/// Token-2022 mint carrying a TransferFeeConfig extension with the same fee in every epoch
+fn transfer_fee_mint_account(decimals: u8, basis_points: u16, maximum_fee: u64) -> Account {
+    let mut account = token_2022_mint_account(decimals);
+    // TLV entry: extension type 1 (TransferFeeConfig), 108 bytes of value
+    account.data.extend_from_slice(&1u16.to_le_bytes());
+    account.data.extend_from_slice(&108u16.to_le_bytes());
+    // Config and withdraw authorities, withheld amount
+    account.data.extend_from_slice(&[0u8; 72]);
+    // Older and newer transfer fees: epoch, maximum fee, basis points
+    for _ in 0..2 {
+        account.data.extend_from_slice(&0u64.to_le_bytes());
+        account.data.extend_from_slice(&maximum_fee.to_le_bytes());
+        account.data.extend_from_slice(&basis_points.to_le_bytes());
+    }
+    account
+}
+
+#[test]
+fn transfer_fee_config_reads_crafted_extension() {
+    let config = transfer_fee_config(&transfer_fee_mint_account(6, 100, u64::MAX))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(config.calculate_epoch_fee(700, 1_000_000), Some(10_000));
+    assert_eq!(
+        mint_decimals(&transfer_fee_mint_account(6, 100, u64::MAX)).unwrap(),
+        6
+    );
+}
+
+#[test]
+fn transfer_fee_config_is_none_without_extension() {
+    assert!(transfer_fee_config(&mint_account(6)).unwrap().is_none());
+    assert!(
+        transfer_fee_config(&token_2022_mint_account(9))
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn transfer_fee_config_or_gross_falls_back_on_corrupt_extension() {
+    let mut account = transfer_fee_mint_account(6, 100, u64::MAX);
+    // Declare a shorter value than TransferFeeConfig
+    account.data[168..170].copy_from_slice(&50u16.to_le_bytes());
+
+    assert!(transfer_fee_config(&account).is_err());
+    assert!(transfer_fee_config_or_gross(Pubkey::new_unique(), &account).is_none());
+}
+
+/// Pool quoting 10 output per input without swap fees
+fn linear_pool(amount_in: u64) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    Ok(SwapQuote::without_transfer_fees(
+        amount_in,
+        amount_in * 10,
+        0,
+    ))
+}
+
+#[test]
+fn apply_transfer_fees_deducts_input_and_output_fees() {
+    let fee_in = transfer_fee_config(&transfer_fee_mint_account(6, 100, u64::MAX))
+        .unwrap()
+        .unwrap();
+    let fee_out = transfer_fee_config(&transfer_fee_mint_account(6, 200, u64::MAX))
+        .unwrap()
+        .unwrap();
+
+    let quote =
+        apply_transfer_fees(1_000_000, 700, Some(&fee_in), Some(&fee_out), linear_pool).unwrap();
+
+    assert_eq!(quote.amount_in, 1_000_000);
+    assert_eq!(quote.gross_amount_out, 10_000_000);
+    assert_eq!(quote.transfer_fee_in, 10_000);
+    // The pool swaps 990_000 into 9_900_000, of which 2% is withheld on transfer
+    assert_eq!(quote.transfer_fee_out, 198_000);
+    assert_eq!(quote.amount_out, 9_702_000);
+}
+
+#[test]
+fn apply_transfer_fees_caps_fee_at_maximum() {
+    let fee_out = transfer_fee_config(&transfer_fee_mint_account(6, 100, 5_000))
+        .unwrap()
+        .unwrap();
+
+    let quote = apply_transfer_fees(1_000_000, 700, None, Some(&fee_out), linear_pool).unwrap();
+
+    assert_eq!(quote.transfer_fee_out, 5_000);
+    assert_eq!(quote.amount_out, 9_995_000);
+}
+
+#[test]
+fn apply_transfer_fees_is_gross_without_fee_configs() {
+    let quote = apply_transfer_fees(1_000_000, 700, None, None, linear_pool).unwrap();
+
+    assert_eq!(
+        quote,
+        SwapQuote::without_transfer_fees(1_000_000, 10_000_000, 0)
+    );
+}
+
+#[test]
+fn mint_decimals_reads_spl_mint_layout() {
+    assert_eq!(mint_decimals(&mint_account(6)).unwrap(), 6);
+    assert_eq!(mint_decimals(&mint_account(9)).unwrap(), 9);
+}
+
+#[test]
+fn mint_decimals_reads_token_2022_mints_with_extensions() {
+    assert_eq!(mint_decimals(&token_2022_mint_account(9)).unwrap(), 9);
+}
+
+#[test]
+fn mint_decimals_rejects_invalid_mints() {
+    let mut wrong_owner = mint_account(6);
+    wrong_owner.owner = Pubkey::new_unique();
+    let mut truncated = mint_account(6);
+    truncated.data.truncate(44);
+
+    assert!(mint_decimals(&wrong_owner).is_err());
+    assert!(mint_decimals(&truncated).is_err());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn cached_mint_decimals_reuses_first_read() {
+    let screener = build_screener();
+    let mint = Pubkey::new_unique();
+
+    assert_eq!(
+        screener
+            .cached_mint_decimals(mint, &mint_account(6))
+            .unwrap(),
+        6
+    );
+    assert_eq!(
+        screener
+            .cached_mint_decimals(mint, &Account::default())
+            .unwrap(),
+        6
+    );
+    assert!(
+        screener
+            .cached_mint_decimals(Pubkey::new_unique(), &Account::default())
+            .is_err()
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn watched_accounts_follow_configured_pools() {
+    let screener = build_screener();
+    let (kept, removed) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let bin_array = Pubkey::new_unique();
+    screener.trade_pairs.write().unwrap().insert(
+        "SOL/USDC".to_string(),
+        TradeConfig {
+            pools: vec![PoolConfig {
+                pool_pubkey: kept,
+                base_is_x: true,
+                bin_array_count: DEFAULT_BIN_ARRAY_COUNT,
+            }],
+            routes: Vec::new(),
+        },
+    );
+    screener.watch_pool_accounts(kept, &[bin_array]);
+    screener.watch_pool_accounts(removed, &[Pubkey::new_unique()]);
+
+    let mut watched = screener.watched_accounts();
+    watched.sort();
+    let mut expected = vec![kept, bin_array];
+    expected.sort();
+
+    assert_eq!(watched, expected);
+    // Re-snapshotting a pool replaces its bin arrays
+    screener.watch_pool_accounts(kept, &[]);
+    assert_eq!(screener.watched_accounts(), vec![kept]);
+}
+
+fn fixture_price_quote() -> PriceQuote {
+    let (sell, buy) = fixture_quotes();
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, 6, 6);
+    let spot_price = Decimal::TEN;
+    PriceQuote {
+        symbol: "TRUMPUSDC".to_string(),
+        pool: Pubkey::new_unique(),
+        route: None,
+        hops: Vec::new(),
+        slot: 321_000_123,
+        block_time: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(120),
+        commitment: CommitmentLevel::Confirmed,
+        stale: false,
+        missing_bin_arrays: 0,
+        base_decimals: 6,
+        quote_decimals: 6,
+        bid_impact_bps: price_impact_bps(bid_price, spot_price),
+        ask_impact_bps: price_impact_bps(ask_price, spot_price),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: None,
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price: Some(spot_price),
+        liquidity: PoolLiquidity::new(50_000_000_000, 500_000_000_000, 6, 6, Some(spot_price)),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+/// Best price taking both sides from the same pool quote
+fn fixture_best_price(quote: PriceQuote) -> BestPriceQuote {
+    BestPriceQuote {
+        symbol: quote.symbol.clone(),
+        bid: quote.clone(),
+        ask: quote,
+        pools_quoted: 1,
+        degraded: false,
+    }
+}
+
+/// Pool quote with the given bid and ask, in quote token per base token
+fn price_quote_with(bid: &str, ask: &str) -> PriceQuote {
+    PriceQuote {
+        pool: Pubkey::new_unique(),
+        bid_price: Decimal::from_str(bid).unwrap(),
+        ask_price: Decimal::from_str(ask).unwrap(),
+        ..fixture_price_quote()
+    }
+}
+
+#[test]
+fn build_dex_states_creates_sell_and_buy_rows() {
+    let quote = fixture_price_quote();
+    let fetch_time = Utc::now();
+
+    let states = build_dex_states(&fixture_best_price(quote.clone()), "meteora", fetch_time);
+
+    assert_eq!(states.len(), 2);
+    let sell = &states[0];
+    assert_eq!(sell.trade_id, format!("{}:321000123:sell", quote.pool));
+    assert_eq!(sell.exchange, "meteora");
+    assert_eq!(sell.trade_pair, "TRUMPUSDC");
+    assert_eq!(sell.direction, "sell");
+    assert_eq!(sell.price, Decimal::from_str("9.9").unwrap());
+    assert_eq!(sell.volume, Decimal::from_str("1").unwrap());
+    assert_eq!(sell.block_number, 321_000_123);
+    assert_eq!(sell.trade_time, quote.block_time);
+    assert_eq!(sell.fetch_time, fetch_time);
+    assert!(sell.price_impact_bps.unwrap() < Decimal::ZERO);
+    assert_eq!(sell.pool_address, Some(quote.pool.to_string()));
+
+    let buy = &states[1];
+    assert_eq!(buy.trade_id, format!("{}:321000123:buy", quote.pool));
+    assert_eq!(buy.direction, "buy");
+    assert_eq!(buy.volume, Decimal::from_str("0.9801").unwrap());
+    assert!(buy.price > sell.price);
+    assert!(buy.price_impact_bps.unwrap() > Decimal::ZERO);
+}
+
+#[test]
+fn build_dex_states_tags_each_side_with_its_winning_pool() {
+    let bid = price_quote_with("9.95", "10.2");
+    let ask = price_quote_with("9.8", "10.05");
+    let best = BestPriceQuote {
+        symbol: "TRUMPUSDC".to_string(),
+        bid: bid.clone(),
+        ask: ask.clone(),
+        pools_quoted: 2,
+        degraded: false,
+    };
+
+    let states = build_dex_states(&best, "meteora", Utc::now());
+
+    assert_eq!(states[0].price, bid.bid_price);
+    assert_eq!(states[0].pool_address, Some(bid.pool.to_string()));
+    assert_eq!(states[0].trade_id, format!("{}:321000123:sell", bid.pool));
+    assert_eq!(states[1].price, ask.ask_price);
+    assert_eq!(states[1].pool_address, Some(ask.pool.to_string()));
+    assert_eq!(states[1].trade_id, format!("{}:321000123:buy", ask.pool));
+}
+
+#[test]
+fn select_best_price_picks_highest_bid_and_lowest_ask() {
+    let quotes = vec![
+        price_quote_with("9.9", "10.1"),
+        price_quote_with("9.95", "10.2"),
+        price_quote_with("9.8", "10.05"),
+    ];
+    let bid_pool = quotes[1].pool;
+    let ask_pool = quotes[2].pool;
+
+    let best = select_best_price("TRUMPUSDC", quotes).unwrap();
+
+    assert_eq!(best.bid.pool, bid_pool);
+    assert_eq!(best.ask.pool, ask_pool);
+    assert_eq!(best.pools_quoted, 3);
+}
+
+#[test]
+fn select_best_price_ignores_empty_sides() {
+    let quotes = vec![
+        price_quote_with("9.9", "0"),
+        price_quote_with("9.7", "10.3"),
+    ];
+    let ask_pool = quotes[1].pool;
+
+    let best = select_best_price("TRUMPUSDC", quotes).unwrap();
+
+    assert_eq!(best.ask.pool, ask_pool);
+    assert!(select_best_price("TRUMPUSDC", vec![]).is_none());
+    assert!(select_best_price("TRUMPUSDC", vec![price_quote_with("9.9", "0")]).is_none());
+}
+
+#[test]
+fn successful_pool_results_skips_failed_pools() {
+    let pools: Vec<PoolConfig> = (0..3)
+        .map(|_| PoolConfig {
+            pool_pubkey: Pubkey::new_unique(),
+            base_is_x: false,
+            bin_array_count: DEFAULT_BIN_ARRAY_COUNT,
+        })
+        .collect();
+    let results = vec![Ok(1), Err("Pool out of liquidity".to_string()), Ok(3)];
+
+    assert_eq!(
+        successful_pool_results("TRUMPUSDC", &pools, results),
+        vec![1, 3]
+    );
+}
+
+#[tokio::test]
+async fn save_best_price_writes_both_sides_of_the_quote() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let screener = build_screener();
+    let quote = fixture_price_quote();
+    let started = Utc::now();
+
+    let dex_writer = DexMarketWriter::for_database(pool.clone(), VENUE);
+
+    save_best_price(
+        &screener,
+        &pool,
+        &dex_writer,
+        &fixture_best_price(quote.clone()),
+    );
+    dex_writer.flush().await;
+
+    let filter = DexMarketFilter {
+        exchange: Some(VENUE.to_string()),
+        trade_pair: Some("TRUMPUSDC".to_string()),
+        from: Some(started),
+        ..DexMarketFilter::default()
+    };
+    let pool_address = quote.pool.to_string();
+    let mut written: Vec<market::DEXState> = get_dex_markets(&pool, &filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|state| state.pool_address.as_deref() == Some(pool_address.as_str()))
+        .collect();
+    assert_eq!(written.len(), 2);
+    written.sort_by(|a, b| a.direction.cmp(&b.direction));
+
+    let (buy, sell) = (&written[0], &written[1]);
+    assert_eq!(sell.trade_id, format!("{}:321000123:sell", quote.pool));
+    assert_eq!(sell.price, Decimal::from_str("9.9").unwrap());
+    assert_eq!(sell.block_number, 321_000_123);
+    assert_eq!(buy.trade_id, format!("{}:321000123:buy", quote.pool));
+    assert_eq!(buy.volume, Decimal::from_str("0.9801").unwrap());
+}
+
+fn make_trade_pair(symbol: &str, pool_pubkey: &str) -> TradePair {
+    TradePair {
+        id: 1,
+        symbol: symbol.to_string(),
+        venue: "meteora".to_string(),
+        pool_pubkey: pool_pubkey.to_string(),
+        precision: 6,
+        base_is_x: false,
+        bin_array_count: 8,
+        base_mint: None,
+        quote_mint: None,
+        auto_discover: false,
+        route: None,
+        route_pool_pubkey: None,
+        route_base_is_x: false,
+        enabled: true,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn trade_configs_from_pairs_parses_valid_rows() {
+    let pool = Pubkey::new_unique();
+    let pairs = vec![make_trade_pair("TRUMPUSDC", &pool.to_string())];
+
+    let configs = trade_configs_from_pairs(pairs);
+
+    assert_eq!(configs.len(), 1);
+    let config = &configs["TRUMPUSDC"];
+    assert_eq!(config.pools.len(), 1);
+    assert_eq!(config.pools[0].pool_pubkey, pool);
+    assert!(!config.pools[0].base_is_x);
+    assert_eq!(config.pools[0].bin_array_count, 8);
+}
+
+#[test]
+fn trade_configs_from_pairs_groups_pools_by_symbol() {
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    let mut second_pair = make_trade_pair("TRUMPUSDC", &second.to_string());
+    second_pair.base_is_x = true;
+    let pairs = vec![
+        make_trade_pair("TRUMPUSDC", &first.to_string()),
+        second_pair,
+        make_trade_pair("SOLUSDC", &Pubkey::new_unique().to_string()),
+    ];
+
+    let configs = trade_configs_from_pairs(pairs);
+
+    assert_eq!(configs.len(), 2);
+    let pools = &configs["TRUMPUSDC"].pools;
+    assert_eq!(pools.len(), 2);
+    assert_eq!(pools[0].pool_pubkey, first);
+    assert_eq!(pools[1].pool_pubkey, second);
+    assert!(pools[1].base_is_x);
+}
+
+#[test]
+fn trade_configs_from_pairs_defaults_missing_bin_array_count() {
+    let mut pair = make_trade_pair("TRUMPUSDC", &Pubkey::new_unique().to_string());
+    pair.bin_array_count = 0;
+
+    let configs = trade_configs_from_pairs(vec![pair]);
+
+    assert_eq!(
+        configs["TRUMPUSDC"].pools[0].bin_array_count,
+        DEFAULT_BIN_ARRAY_COUNT
+    );
+}
+
+fn auto_discovered_pair(symbol: &str, base_mint: &Pubkey, quote_mint: &Pubkey) -> TradePair {
+    let mut pair = make_trade_pair(symbol, "");
+    pair.auto_discover = true;
+    pair.base_mint = Some(base_mint.to_string());
+    pair.quote_mint = Some(quote_mint.to_string());
+    pair
+}
+
+fn discovered_pool(address: Pubkey, mint_x: Pubkey, mint_y: Pubkey) -> DiscoveredPool {
+    DiscoveredPool {
+        address,
+        mint_x,
+        mint_y,
+        tvl_usd: Decimal::from(100_000),
+        volume_24h_usd: Decimal::from(10_000),
+    }
+}
+
+#[test]
+fn trade_configs_from_pairs_leaves_auto_discovered_pairs_to_discovery() {
+    let pairs = vec![auto_discovered_pair(
+        "TRUMPUSDC",
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    )];
+
+    assert!(trade_configs_from_pairs(pairs).is_empty());
+}
+
+#[test]
+fn discovery_targets_from_pairs_requires_valid_mints() {
+    let (trump, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut missing_mint = auto_discovered_pair("SOLUSDC", &trump, &usdc);
+    missing_mint.quote_mint = None;
+    let pairs = vec![
+        auto_discovered_pair("TRUMPUSDC", &trump, &usdc),
+        missing_mint,
+        make_trade_pair("JUPUSDC", &Pubkey::new_unique().to_string()),
+    ];
+
+    let targets = discovery_targets_from_pairs(&pairs);
+
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].symbol, "TRUMPUSDC");
+    assert_eq!(targets[0].base_mint, trump);
+    assert_eq!(targets[0].quote_mint, usdc);
+    assert_eq!(targets[0].bin_array_count, 8);
+}
+
+#[test]
+fn pool_configs_from_discovered_derives_base_side() {
+    let (trump, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let target =
+        &discovery_targets_from_pairs(&[auto_discovered_pair("TRUMPUSDC", &trump, &usdc)])[0];
+    let (base_x, base_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let pools = pool_configs_from_discovered(
+        target,
+        &[
+            discovered_pool(base_x, trump, usdc),
+            discovered_pool(base_y, usdc, trump),
+        ],
+    );
+
+    assert_eq!(pools[0].pool_pubkey, base_x);
+    assert!(pools[0].base_is_x);
+    assert_eq!(pools[1].pool_pubkey, base_y);
+    assert!(!pools[1].base_is_x);
+}
+
+#[test]
+fn merge_discovered_pools_skips_configured_pools() {
+    let configured = Pubkey::new_unique();
+    let discovered = Pubkey::new_unique();
+    let mut trade_configs =
+        trade_configs_from_pairs(vec![make_trade_pair("TRUMPUSDC", &configured.to_string())]);
+    let pool = |pool_pubkey| PoolConfig {
+        pool_pubkey,
+        base_is_x: true,
+        bin_array_count: DEFAULT_BIN_ARRAY_COUNT,
+    };
+
+    merge_discovered_pools(
+        &mut trade_configs,
+        &HashMap::from([
+            (
+                "TRUMPUSDC".to_string(),
+                vec![pool(configured), pool(discovered)],
+            ),
+            ("SOLUSDC".to_string(), vec![pool(discovered)]),
+        ]),
+    );
+
+    let pools = &trade_configs["TRUMPUSDC"].pools;
+    assert_eq!(pools.len(), 2);
+    assert_eq!(pools[0].pool_pubkey, configured);
+    assert!(!pools[0].base_is_x);
+    assert_eq!(pools[1].pool_pubkey, discovered);
+    assert_eq!(trade_configs["SOLUSDC"].pools.len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn discover_pools_keeps_last_known_pools_when_the_api_fails() {
+    let api = MeteoraApiClient::new("http://127.0.0.1:1", DiscoveryFilter::from_env()).unwrap();
+    let (trump, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let targets = discovery_targets_from_pairs(&[auto_discovered_pair("TRUMPUSDC", &trump, &usdc)]);
+    let known = pool_configs_from_discovered(
+        &targets[0],
+        &[discovered_pool(Pubkey::new_unique(), trump, usdc)],
+    );
+    let discovered = RwLock::new(HashMap::from([
+        ("TRUMPUSDC".to_string(), known.clone()),
+        ("SOLUSDC".to_string(), known.clone()),
+    ]));
+
+    discover_pools(&api, &targets, &discovered).await;
+
+    let discovered = discovered.read().unwrap();
+    assert_eq!(discovered.len(), 1);
+    assert_eq!(discovered["TRUMPUSDC"][0].pool_pubkey, known[0].pool_pubkey);
+}
+
+#[test]
+fn trade_configs_from_pairs_skips_invalid_pubkeys() {
+    let pool = Pubkey::new_unique();
+    let pairs = vec![
+        make_trade_pair("TRUMPUSDC", &pool.to_string()),
+        make_trade_pair("BROKEN", "not-a-pubkey"),
+    ];
+
+    let configs = trade_configs_from_pairs(pairs);
+
+    assert_eq!(configs.len(), 1);
+    assert!(configs.contains_key("TRUMPUSDC"));
+    assert!(!configs.contains_key("BROKEN"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_price_fails_for_unknown_symbol() {
+    let screener = build_screener();
+
+    let result = screener.get_price("UNKNOWN", DEFAULT_AMOUNT_IN).await;
+
+    assert!(result.is_err());
+}
+
+fn swap(amount_in: u64, amount_out: u64) -> SwapQuote {
+    SwapQuote::without_transfer_fees(amount_in, amount_out, 0)
+}
+
+/// Bids and asks of a book, best levels first
+fn ladder_levels(
+    book: &market::OrderBook,
+) -> (Vec<market::OrderBookItem>, Vec<market::OrderBookItem>) {
+    (book.bid_levels().collect(), book.ask_levels().collect())
+}
+
+#[test]
+fn build_ladder_book_uses_marginal_levels() {
+    // Spending 100 then 300 quote buys 50 then 120 base; selling it back yields 90 then 200 quote
+    let points = vec![
+        (swap(100, 50), swap(50, 90)),
+        (swap(300, 120), swap(120, 200)),
+    ];
+
+    let book = build_ladder_book("TRUMPUSDC", 0, 0, &points);
+    let (bids, asks) = ladder_levels(&book);
+
+    assert_eq!(book.exchange, "meteora");
+    assert_eq!(book.asks.len(), 2);
+    assert_eq!(asks[0].price, Decimal::from(2));
+    assert_eq!(asks[0].volume, Decimal::from(50));
+    assert_eq!(asks[1].price, Decimal::from(200) / Decimal::from(70));
+    assert_eq!(asks[1].volume, Decimal::from(70));
+    assert_eq!(bids[0].price, Decimal::from(90) / Decimal::from(50));
+    assert_eq!(bids[1].price, Decimal::from(110) / Decimal::from(70));
+    assert_eq!(bids[1].volume, Decimal::from(70));
+    assert!(bids[0].price > bids[1].price);
+    assert!(asks[0].price < asks[1].price);
+}
+
+#[test]
+fn build_ladder_book_scales_volume_by_base_decimals() {
+    let points = vec![(swap(2_000_000, 1_000_000), swap(1_000_000, 1_900_000))];
+
+    let book = build_ladder_book("TRUMPUSDC", 6, 6, &points);
+    let (bids, asks) = ladder_levels(&book);
+
+    assert_eq!(asks[0].volume, Decimal::ONE);
+    assert_eq!(bids[0].volume, Decimal::ONE);
+    assert_eq!(asks[0].price, Decimal::TWO);
+    assert_eq!(bids[0].price, Decimal::from_str("1.9").unwrap());
+}
+
+fn ladder(slot: u64, truncated: bool, points: &[(SwapQuote, SwapQuote)]) -> DepthLadder {
+    DepthLadder {
+        book: build_ladder_book("TRUMPUSDC", 0, 0, points),
+        slot,
+        truncated,
+        pools: vec![Pubkey::new_unique()],
+    }
+}
+
+#[test]
+fn merge_ladders_sorts_levels_across_pools() {
+    let first = ladder(100, false, &[(swap(100, 50), swap(50, 90))]);
+    let second = ladder(101, true, &[(swap(100, 40), swap(40, 95))]);
+    let pools = [first.pools[0], second.pools[0]];
+
+    let merged = merge_ladders("TRUMPUSDC", vec![first, second]).unwrap();
+    let (bids, asks) = ladder_levels(&merged.book);
+
+    assert_eq!(merged.slot, 101);
+    assert!(merged.truncated);
+    assert_eq!(merged.pools, pools);
+    assert_eq!(bids.len(), 2);
+    assert!(bids[0].price > bids[1].price);
+    assert_eq!(asks[0].price, Decimal::from(2));
+    assert!(asks[0].price < asks[1].price);
+    assert!(merge_ladders("TRUMPUSDC", vec![]).is_none());
+}
+
+#[test]
+fn merge_ladders_adds_volume_at_equal_prices() {
+    let points = [(swap(100, 50), swap(50, 90))];
+
+    let merged = merge_ladders(
+        "TRUMPUSDC",
+        vec![ladder(100, false, &points), ladder(100, false, &points)],
+    )
+    .unwrap();
+
+    assert_eq!(merged.book.asks.len(), 1);
+    assert_eq!(merged.book.best_ask().unwrap().volume, Decimal::from(100));
+    assert_eq!(merged.book.best_bid().unwrap().volume, Decimal::from(100));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_depth_ladder_fails_for_unknown_symbol() {
+    let screener = build_screener();
+
+    let result = screener
+        .get_depth_ladder("UNKNOWN", &DEFAULT_DEPTH_LADDER_SIZES)
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn collect_ladder_points_keeps_every_absorbed_size() {
+    let (points, truncated) = collect_ladder_points(&[100, 200], |size| {
+        Ok((swap(size, size / 2), swap(size / 2, size)))
+    });
+
+    assert!(!truncated);
+    assert_eq!(points.len(), 2);
+}
+
+#[test]
+fn collect_ladder_points_truncates_when_output_stops_increasing() {
+    // The pool runs out of base liquidity after 50 units
+    let (points, truncated) = collect_ladder_points(&[100, 200, 300], |size| {
+        let base_out = (size / 2).min(50);
+        Ok((swap(size, base_out), swap(base_out, base_out * 2)))
+    });
+
+    assert!(truncated);
+    assert_eq!(points.len(), 1);
+}
+
+#[test]
+fn collect_ladder_points_truncates_on_quote_error() {
+    let (points, truncated) = collect_ladder_points(&[100, 200], |size| {
+        if size > 100 {
+            Err("not enough liquidity".into())
+        } else {
+            Ok((swap(size, 50), swap(50, 90)))
+        }
+    });
+
+    assert!(truncated);
+    assert_eq!(points.len(), 1);
+}
+
+fn fixture_lb_pair(active_id: i32, bin_step: u16) -> LbPair {
+    let mut lb_pair: LbPair = bytemuck::Zeroable::zeroed();
+    lb_pair.active_id = active_id;
+    lb_pair.bin_step = bin_step;
+    lb_pair
+}
+
+#[test]
+fn active_bin_spot_price_follows_bin_step_and_active_id() {
+    // 1% bins, 10 bins above the zero price: 1.01^10
+    let lb_pair = fixture_lb_pair(10, 100);
+
+    let price_x = active_bin_spot_price(&lb_pair, true, 6, 6).unwrap();
+    let price_y = active_bin_spot_price(&lb_pair, false, 6, 6).unwrap();
+
+    assert_eq!(
+        price_x,
+        Decimal::from_str("1.10462212541120451001").unwrap()
+    );
+    assert_eq!(price_y, Decimal::ONE / price_x);
+}
+
+#[test]
+fn active_bin_spot_price_handles_negative_bins_and_decimals() {
+    // 1bp bins, 5 bins below the zero price, base with 9 decimals against a 6 decimal quote
+    let lb_pair = fixture_lb_pair(-5, 1);
+
+    let price = active_bin_spot_price(&lb_pair, true, 9, 6).unwrap();
+
+    let expected =
+        Decimal::from_str("0.99950014996500699874020996700").unwrap() * Decimal::ONE_THOUSAND;
+    assert!((price - expected).abs() < Decimal::from_str("0.000000000000000001").unwrap());
+}
+
+#[test]
+fn active_bin_spot_price_is_none_on_overflow() {
+    let lb_pair = fixture_lb_pair(443_636, 100);
+
+    assert_eq!(active_bin_spot_price(&lb_pair, true, 6, 6), None);
+}
+
+#[test]
+fn price_impact_bps_matches_known_values() {
+    let lb_pair = fixture_lb_pair(0, 25);
+    let spot = active_bin_spot_price(&lb_pair, true, 6, 6).unwrap();
+
+    let bid_impact = price_impact_bps(Decimal::from_str("0.99").unwrap(), spot).unwrap();
+    let ask_impact = price_impact_bps(Decimal::from_str("1.0025").unwrap(), spot).unwrap();
+
+    assert_eq!(bid_impact, Decimal::from(-100));
+    assert_eq!(ask_impact, Decimal::from(25));
+}
+
+#[test]
+fn price_impact_bps_is_none_without_output() {
+    let (sell, mut buy) = fixture_quotes();
+    buy.amount_out = 0;
+    let (_, ask) = derive_bid_ask(&sell, &buy, 6, 6);
+
+    assert_eq!(price_impact_bps(ask, Decimal::TEN), None);
+}
+
+/// Pool paying 2 quote units per base unit until it runs out of 1_000 quote units
+fn capped_pool(amount_in: u64) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    Ok(swap(amount_in, (amount_in * 2).min(1_000)))
+}
+
+fn exact_out_search(tolerance: u64) -> ExactOutSearch {
+    ExactOutSearch {
+        tolerance,
+        max_iterations: DEFAULT_EXACT_OUT_MAX_ITERATIONS,
+    }
+}
+
+#[test]
+fn search_exact_out_converges_without_hint() {
+    let (quote, iterations) =
+        search_exact_out(501, None, exact_out_search(0), capped_pool).unwrap();
+
+    assert_eq!(quote.amount_in, 251);
+    assert_eq!(quote.amount_out, 502);
+    assert!(iterations > 1);
+}
+
+#[test]
+fn search_exact_out_stays_within_tolerance() {
+    let (quote, _) = search_exact_out(501, Some(300), exact_out_search(10), capped_pool).unwrap();
+
+    assert!(quote.amount_out >= 501);
+    assert!(quote.amount_in >= 251 && quote.amount_in <= 261);
+}
+
+#[test]
+fn search_exact_out_confirms_accurate_hint_in_two_quotes() {
+    let (quote, iterations) =
+        search_exact_out(501, Some(251), exact_out_search(0), capped_pool).unwrap();
+
+    assert_eq!(quote.amount_in, 251);
+    assert_eq!(iterations, 2);
+}
+
+#[test]
+fn search_exact_out_fails_when_pool_cannot_produce_output() {
+    let result = search_exact_out(5_000, None, exact_out_search(0), capped_pool);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn search_exact_out_respects_iteration_budget() {
+    let search = ExactOutSearch {
+        tolerance: 0,
+        max_iterations: 3,
+    };
+
+    let result = search_exact_out(1_000_000, None, search, |amount_in| {
+        Ok(swap(amount_in, amount_in))
+    });
+
+    assert!(result.is_err());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_price_exact_out_fails_for_unknown_symbol() {
+    let screener = build_screener();
+
+    let result = screener.get_price_exact_out("UNKNOWN", 1_000_000).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_with_more_bin_arrays_doubles_count_on_liquidity_error() {
+    let counts = std::sync::Mutex::new(Vec::new());
+
+    let result = retry_with_more_bin_arrays("TRUMPUSDC", 4, |count| {
+        counts.lock().unwrap().push(count);
+        async move {
+            if count < 8 {
+                Err("Pool out of liquidity".into())
+            } else {
+                Ok(count)
+            }
+        }
+    })
+    .await;
+
+    assert_eq!(result.unwrap(), 8);
+    assert_eq!(*counts.lock().unwrap(), vec![4, 8]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_with_more_bin_arrays_retries_only_once_and_caps_count() {
+    let counts = std::sync::Mutex::new(Vec::new());
+
+    let result: Result<u8, _> = retry_with_more_bin_arrays("TRUMPUSDC", 12, |count| {
+        counts.lock().unwrap().push(count);
+        async { Err("Active bin array not found".into()) }
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(*counts.lock().unwrap(), vec![12, MAX_BIN_ARRAY_COUNT]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_with_more_bin_arrays_ignores_other_errors() {
+    let calls = AtomicUsize::new(0);
+
+    let result: Result<u8, _> = retry_with_more_bin_arrays("TRUMPUSDC", 4, |_| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        async { Err("connection reset".into()) }
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_with_more_bin_arrays_stops_at_cap() {
+    let calls = AtomicUsize::new(0);
+
+    let result: Result<u8, _> =
+        retry_with_more_bin_arrays("TRUMPUSDC", MAX_BIN_ARRAY_COUNT, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err("Pool out of liquidity".into()) }
+        })
+        .await;
+
+    assert!(result.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+fn fixture_snapshot(slot: u64) -> PoolSnapshot {
+    let clock = solana_sdk::clock::Clock {
+        slot,
+        unix_timestamp: 1_700_000_000,
+        ..solana_sdk::clock::Clock::default()
+    };
+    PoolSnapshot {
+        lb_pair: Pubkey::new_unique(),
+        pool: PoolConfig {
+            pool_pubkey: Pubkey::new_unique(),
+            base_is_x: false,
+            bin_array_count: DEFAULT_BIN_ARRAY_COUNT,
+        },
+        base_decimals: 6,
+        quote_decimals: 6,
+        transfer_fee_x: None,
+        transfer_fee_y: None,
+        bitmap_extension: None,
+        accounts: SwapQuoteAccounts {
+            lb_pair_state: fixture_lb_pair(0, 25),
+            slot: clock.slot,
+            commitment: CommitmentLevel::Confirmed,
+            stale: false,
+            missing_bin_arrays: 0,
+            clock,
+            mint_x_account: mint_account(6),
+            mint_y_account: mint_account(6),
+            bin_arrays: HashMap::new(),
+        },
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(120),
+    }
+}
+
+#[test]
+fn build_price_quote_carries_clock_slot_into_dex_states() {
+    let snapshot = fixture_snapshot(321_000_456);
+    let (sell, buy) = fixture_quotes();
+
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, sell, buy).unwrap();
+    let states = build_dex_states(&fixture_best_price(quote.clone()), "meteora", Utc::now());
+
+    assert_eq!(quote.slot, 321_000_456);
+    assert_eq!(quote.block_time.timestamp(), 1_700_000_000);
+    assert_eq!(quote.commitment, CommitmentLevel::Confirmed);
+    assert!(states.iter().all(|state| state.block_number == 321_000_456));
+}
+
+fn clock_at(unix_timestamp: i64) -> solana_sdk::clock::Clock {
+    solana_sdk::clock::Clock {
+        unix_timestamp,
+        ..solana_sdk::clock::Clock::default()
+    }
+}
+
+#[test]
+fn clock_drift_is_measured_against_wall_time() {
+    let now = DateTime::from_timestamp(1_700_000_030, 0).unwrap();
+    let max_drift = Duration::from_secs(DEFAULT_MAX_CLOCK_DRIFT_SECS);
+
+    assert_eq!(clock_drift_secs(&clock_at(1_700_000_000), now), 30);
+    assert_eq!(clock_drift_secs(&clock_at(1_700_000_040), now), -10);
+    assert!(!is_clock_stale(&clock_at(1_700_000_030), now, max_drift));
+    // Exactly the allowed drift is still fresh
+    assert!(!is_clock_stale(&clock_at(1_700_000_015), now, max_drift));
+    assert!(is_clock_stale(&clock_at(1_700_000_014), now, max_drift));
+    // A clock running ahead of wall time is as suspect as a lagging one
+    assert!(is_clock_stale(&clock_at(1_700_000_050), now, max_drift));
+}
+
+#[test]
+fn stale_snapshot_tags_the_quote_and_its_dex_states() {
+    let mut snapshot = fixture_snapshot(100);
+    snapshot.accounts.stale = true;
+    let (sell, buy) = fixture_quotes();
+
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, sell.clone(), buy.clone()).unwrap();
+    let states = build_dex_states(&fixture_best_price(quote.clone()), "meteora", Utc::now());
+    let fresh = build_price_quote("TRUMPUSDC", &fixture_snapshot(100), sell, buy).unwrap();
+
+    assert!(quote.stale);
+    assert!(states.iter().all(|state| state.stale));
+    assert!(!fresh.stale);
+}
+
+#[test]
+fn same_slot_as_detects_repeated_slots() {
+    let snapshot = fixture_snapshot(100);
+    let (sell, buy) = fixture_quotes();
+    let first = build_price_quote("TRUMPUSDC", &snapshot, sell.clone(), buy.clone()).unwrap();
+    let repeated = build_price_quote("TRUMPUSDC", &snapshot, sell.clone(), buy.clone()).unwrap();
+    let mut next = fixture_snapshot(101);
+    next.lb_pair = snapshot.lb_pair;
+    let newer = build_price_quote("TRUMPUSDC", &next, sell, buy).unwrap();
+
+    assert!(repeated.same_slot_as(&first));
+    assert!(!newer.same_slot_as(&first));
+}
+
+#[test]
+fn quote_deviation_bps_is_relative_to_the_local_output() {
+    assert_eq!(quote_deviation_bps(10_000, 10_000), Some(Decimal::ZERO));
+    assert_eq!(quote_deviation_bps(10_000, 9_990), Some(Decimal::from(-10)));
+    assert_eq!(
+        quote_deviation_bps(20_000, 20_001),
+        Some("0.5".parse::<Decimal>().unwrap())
+    );
+    assert_eq!(quote_deviation_bps(0, 5), None);
+}
+
+#[test]
+fn simulated_amount_out_subtracts_the_balance_before() {
+    let token_account = |amount: u64| {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&amount.to_le_bytes());
+        Account {
+            data,
+            owner: spl_token_2022::id(),
+            ..Account::default()
+        }
+    };
+
+    assert_eq!(
+        simulated_amount_out(500, Some(vec![Some(token_account(1_700))])).unwrap(),
+        1_200
+    );
+    assert!(simulated_amount_out(500, Some(vec![Some(token_account(400))])).is_err());
+    assert!(simulated_amount_out(500, Some(vec![None])).is_err());
+    assert!(simulated_amount_out(500, None).is_err());
+}
+
+#[test]
+fn resolve_quote_verification_requires_a_wallet_when_enabled() {
+    let wallet = Pubkey::new_unique();
+
+    assert_eq!(
+        resolve_quote_verification(None, None, None, None).unwrap(),
+        None
+    );
+    assert!(resolve_quote_verification(Some("true".to_string()), None, None, None).is_err());
+    assert!(
+        resolve_quote_verification(
+            Some("1".to_string()),
+            Some("not-a-pubkey".to_string()),
+            None,
+            None
+        )
+        .is_err()
+    );
+
+    let verification = resolve_quote_verification(
+        Some("true".to_string()),
+        Some(wallet.to_string()),
+        Some("0.5".to_string()),
+        Some("25".to_string()),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(verification.wallet, wallet);
+    assert_eq!(verification.sample_rate, 0.5);
+    assert_eq!(verification.max_deviation_bps, Decimal::from(25));
+
+    let defaults = resolve_quote_verification(
+        Some("true".to_string()),
+        Some(wallet.to_string()),
+        Some("2".to_string()),
+        None,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(defaults.sample_rate, DEFAULT_VERIFY_SAMPLE_RATE);
+    assert_eq!(
+        defaults.max_deviation_bps,
+        Decimal::from(DEFAULT_VERIFY_MAX_DEVIATION_BPS)
+    );
+}
+
+#[test]
+fn active_bin_spot_price_matches_known_bin_ids() {
+    // Bin 0 is the 1:1 raw price whatever the bin step, scaled by the decimals
+    assert_eq!(
+        active_bin_spot_price(&fixture_lb_pair(0, 25), true, 9, 6).unwrap(),
+        Decimal::ONE_THOUSAND
+    );
+
+    // SOL/USDC 10bp bins around $150: 1.001^-1897 * 10^(9-6) = 150.1602...
+    let price = active_bin_spot_price(&fixture_lb_pair(-1897, 10), true, 9, 6).unwrap();
+    let expected = Decimal::from_str("150.1602").unwrap();
+    assert!((price - expected).abs() < Decimal::from_str("0.0001").unwrap());
+
+    // One bin up moves the price by exactly one bin step
+    let next = active_bin_spot_price(&fixture_lb_pair(-1896, 10), true, 9, 6).unwrap();
+    assert!((next / price - Decimal::from_str("1.001").unwrap()).abs() < Decimal::new(1, 20));
+}
+
+#[test]
+fn build_spot_dex_state_uses_the_spot_direction() {
+    let spot = SpotPrice {
+        symbol: "SOLUSDC".to_string(),
+        pool: Pubkey::new_unique(),
+        active_id: -1897,
+        price: Decimal::from(150),
+        slot: 42,
+    };
+    let fetch_time = Utc::now();
+
+    let dex_state = build_spot_dex_state(&spot, VENUE, fetch_time);
+
+    assert_eq!(dex_state.direction, "spot");
+    assert_eq!(dex_state.trade_id, format!("{}:42:spot:-1897", spot.pool));
+    assert_eq!(dex_state.price, Decimal::from(150));
+    assert_eq!(dex_state.volume, Decimal::ZERO);
+    assert_eq!(dex_state.price_impact_bps, None);
+    assert_eq!(dex_state.block_number, 42);
+    assert_eq!(dex_state.trade_time, fetch_time);
+}
+
+/// Bin array whose first bins hold the given (X, Y) amounts
+fn fixture_bin_array(amounts: &[(u64, u64)]) -> BinArray {
+    let mut bin_array: BinArray = bytemuck::Zeroable::zeroed();
+    for (bin, &(amount_x, amount_y)) in bin_array.bins.iter_mut().zip(amounts) {
+        bin.amount_x = amount_x;
+        bin.amount_y = amount_y;
+    }
+    bin_array
+}
+
+#[test]
+fn bin_array_reserves_sums_every_bin() {
+    let bin_arrays = [
+        fixture_bin_array(&[(1_000, 0), (2_000, 0), (0, 500)]),
+        fixture_bin_array(&[(0, 7_000), (u64::MAX, 0)]),
+    ];
+
+    let (amount_x, amount_y) = bin_array_reserves(bin_arrays.iter());
+
+    assert_eq!(amount_x, 3_000 + u64::MAX as u128);
+    assert_eq!(amount_y, 7_500);
+    assert_eq!(bin_array_reserves([]), (0, 0));
+}
+
+#[test]
+fn pool_liquidity_values_both_tokens_in_quote() {
+    // 2 base (9 decimals) at 150 plus 300 quote (6 decimals)
+    let liquidity = PoolLiquidity::new(2_000_000_000, 300_000_000, 9, 6, Some(Decimal::from(150)));
+
+    assert_eq!(liquidity.base_amount, Decimal::from(2));
+    assert_eq!(liquidity.quote_amount, Decimal::from(300));
+    assert_eq!(liquidity.notional, Some(Decimal::from(600)));
+    assert!(liquidity.is_below(Decimal::from(601)));
+    assert!(!liquidity.is_below(Decimal::from(600)));
+
+    // Without a price the liquidity is unknown and never below the floor
+    let unpriced = PoolLiquidity::new(2_000_000_000, 300_000_000, 9, 6, None);
+    assert_eq!(unpriced.notional, None);
+    assert!(!unpriced.is_below(Decimal::MAX));
+}
+
+#[test]
+fn below_liquidity_floor_checks_both_best_pools() {
+    let deep = fixture_price_quote();
+    let shallow = PriceQuote {
+        pool: Pubkey::new_unique(),
+        liquidity: PoolLiquidity::new(0, 5_000_000_000, 6, 6, Some(Decimal::TEN)),
+        ..fixture_price_quote()
+    };
+    let floor = Decimal::from(DEFAULT_MIN_POOL_LIQUIDITY);
+
+    assert!(!below_liquidity_floor(
+        &fixture_best_price(deep.clone()),
+        floor
+    ));
+    let best = BestPriceQuote {
+        bid: deep,
+        ask: shallow,
+        pools_quoted: 2,
+        ..fixture_best_price(fixture_price_quote())
+    };
+    assert!(below_liquidity_floor(&best, floor));
+}
+
+#[test]
+fn build_pool_stats_records_each_best_pool_once() {
+    let fetch_time = Utc::now();
+    let quote = fixture_price_quote();
+
+    let same_pool = build_pool_stats(&fixture_best_price(quote.clone()), "meteora", fetch_time);
+    assert_eq!(same_pool.len(), 1);
+    assert_eq!(same_pool[0].pool_address, quote.pool.to_string());
+    assert_eq!(same_pool[0].base_amount, Decimal::from(50_000));
+    assert_eq!(same_pool[0].quote_amount, Decimal::from(500_000));
+    assert_eq!(same_pool[0].liquidity, Some(Decimal::from(1_000_000)));
+    assert_eq!(same_pool[0].block_number, quote.slot);
+    assert!(!same_pool[0].degraded);
+
+    let best = BestPriceQuote {
+        ask: price_quote_with("9.8", "10.05"),
+        degraded: true,
+        ..fixture_best_price(quote)
+    };
+    let stats = build_pool_stats(&best, "meteora", fetch_time);
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[1].pool_address, best.ask.pool.to_string());
+    assert!(stats.iter().all(|stats| stats.degraded));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn mark_degraded_tracks_the_latest_quote_of_a_pair() {
+    let screener = build_screener();
+    let mut best = fixture_best_price(fixture_price_quote());
+
+    best.degraded = true;
+    screener.mark_degraded(&best);
+    assert!(screener.is_degraded("TRUMPUSDC"));
+    assert!(!screener.is_degraded("SOLUSDC"));
+
+    best.degraded = false;
+    screener.mark_degraded(&best);
+    assert!(!screener.is_degraded("TRUMPUSDC"));
+}
+
+#[test]
+fn with_landing_cost_subtracts_the_cost_in_quote_token() {
+    let quote = fixture_price_quote();
+    let amount_out = quote.sell.amount_out;
+
+    // 6_500 lamports at 150 quote per SOL = 975 raw quote units with 6 decimals
+    let priced = quote
+        .clone()
+        .with_landing_cost(6_500, Some(Decimal::from(150)));
+    assert_eq!(priced.landing_cost, 6_500);
+    assert_eq!(priced.net_amount_out, Some(amount_out - 975));
+
+    let unpriced = quote.clone().with_landing_cost(6_500, None);
+    assert_eq!(unpriced.landing_cost, 6_500);
+    assert_eq!(unpriced.net_amount_out, None);
+
+    let unprofitable = quote.with_landing_cost(u64::MAX, Some(Decimal::from(150)));
+    assert_eq!(unprofitable.net_amount_out, Some(0));
+}
+
+fn bin_array_account(bin_array: &BinArray) -> Account {
+    let mut data = BIN_ARRAY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(bin_array));
+    Account {
+        data,
+        ..Account::default()
+    }
+}
+
+#[test]
+fn decode_bin_arrays_fails_on_missing_bin_arrays_by_default() {
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let accounts = vec![
+        Some(bin_array_account(&fixture_bin_array(&[(1_000, 0)]))),
+        None,
+        None,
+    ];
+
+    let error = decode_bin_arrays(&keys, accounts, false).unwrap_err();
+
+    assert_eq!(
+        error.downcast_ref::<MissingBinArrays>(),
+        Some(&MissingBinArrays(vec![keys[1], keys[2]]))
+    );
+    assert!(
+        error
+            .to_string()
+            .starts_with("2 required bin arrays are missing")
+    );
+    assert!(!is_out_of_liquidity(error.as_ref()));
+}
+
+#[test]
+fn decode_bin_arrays_lists_missing_bin_arrays_when_partial_quotes_are_allowed() {
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let accounts = vec![None, Some(bin_array_account(&fixture_bin_array(&[(0, 7)])))];
+
+    let (bin_arrays, missing) = decode_bin_arrays(&keys, accounts, true).unwrap();
+
+    assert_eq!(missing, vec![keys[0]]);
+    assert_eq!(bin_arrays.len(), 1);
+    assert_eq!(bin_arrays[&keys[1]].bins[0].amount_y, 7);
+}
+
+#[test]
+fn partial_snapshot_marks_the_quote_partial() {
+    let mut snapshot = fixture_snapshot(100);
+    let (sell, buy) = fixture_quotes();
+    let complete = build_price_quote("TRUMPUSDC", &snapshot, sell.clone(), buy.clone()).unwrap();
+    snapshot.accounts.missing_bin_arrays = 2;
+
+    let partial = build_price_quote("TRUMPUSDC", &snapshot, sell, buy).unwrap();
+
+    assert!(!complete.is_partial());
+    assert!(partial.is_partial());
+    assert_eq!(partial.missing_bin_arrays, 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn with_config_uses_the_injected_rpc_settings() {
+    let config = MeteoraConfig {
+        rpc_endpoints: vec![
+            "http://localhost:8899".to_string(),
+            "http://localhost:8900".to_string(),
+        ],
+        commitment: CommitmentConfig::finalized(),
+        clock_commitment: CommitmentConfig::confirmed(),
+    };
+
+    let screener = MeteoraScreener::with_config(lazy_pool(), config).unwrap();
+
+    assert_eq!(screener.rpc_client.error_counts().len(), 2);
+    assert_eq!(screener.commitment, CommitmentConfig::finalized());
+    assert_eq!(screener.clock_commitment, CommitmentConfig::confirmed());
+}
+
+/// Fetcher answering every call with the next queued result
+struct QueuedFetcher {
+    results: std::sync::Mutex<Vec<solana_client::client_error::Result<Vec<Option<Account>>>>>,
+    calls: AtomicUsize,
+}
+
+impl QueuedFetcher {
+    fn new(results: Vec<solana_client::client_error::Result<Vec<Option<Account>>>>) -> Self {
+        Self {
+            results: std::sync::Mutex::new(results),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl AccountFetcher for QueuedFetcher {
+    async fn fetch_accounts(
+        &self,
+        _pubkeys: &[Pubkey],
+    ) -> solana_client::client_error::Result<Vec<Option<Account>>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.results.lock().unwrap().remove(0)
+    }
+}
+
+fn bitmap_extension_account(lb_pair: Pubkey) -> Account {
+    let mut extension: BinArrayBitmapExtension = bytemuck::Zeroable::zeroed();
+    extension.lb_pair = lb_pair;
+    let mut data = BIN_ARRAY_BITMAP_EXTENSION_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&extension));
+    Account {
+        data,
+        ..Account::default()
+    }
+}
+
+fn rpc_transport_error() -> solana_client::client_error::ClientError {
+    solana_client::client_error::ClientErrorKind::Io(std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        "connection reset",
+    ))
+    .into()
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn bitmap_extension_cache_keeps_a_present_extension() {
+    let cache = BitmapExtensionCache::new(Duration::from_secs(60));
+    let lb_pair = Pubkey::new_unique();
+    let fetcher = QueuedFetcher::new(vec![Ok(vec![Some(bitmap_extension_account(lb_pair))])]);
+
+    let first = cache.get(&fetcher, lb_pair).await.unwrap();
+    let second = cache.get(&fetcher, lb_pair).await.unwrap();
+
+    assert_eq!(first.unwrap().lb_pair, lb_pair);
+    assert_eq!(second.unwrap().lb_pair, lb_pair);
+    assert_eq!(fetcher.calls(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn bitmap_extension_cache_keeps_an_absent_extension_until_the_ttl() {
+    let cache = BitmapExtensionCache::new(Duration::ZERO);
+    let lb_pair = Pubkey::new_unique();
+    let fetcher = QueuedFetcher::new(vec![
+        Ok(vec![None]),
+        Ok(vec![Some(bitmap_extension_account(lb_pair))]),
+    ]);
+
+    assert!(cache.get(&fetcher, lb_pair).await.unwrap().is_none());
+    // An expired lookup is fetched again and picks up a newly created extension
+    assert!(cache.get(&fetcher, lb_pair).await.unwrap().is_some());
+    assert_eq!(fetcher.calls(), 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn bitmap_extension_cache_propagates_rpc_errors_without_caching_them() {
+    let cache = BitmapExtensionCache::new(Duration::from_secs(60));
+    let lb_pair = Pubkey::new_unique();
+    let fetcher = QueuedFetcher::new(vec![Err(rpc_transport_error()), Ok(vec![None])]);
+
+    assert!(cache.get(&fetcher, lb_pair).await.is_err());
+    assert!(cache.get(&fetcher, lb_pair).await.unwrap().is_none());
+    assert!(cache.get(&fetcher, lb_pair).await.unwrap().is_none());
+    assert_eq!(fetcher.calls(), 2);
+}
+
+#[test]
+fn bitmap_extension_key_is_the_derived_address() {
+    let cache = BitmapExtensionCache::new(Duration::from_secs(60));
+    let lb_pair = Pubkey::new_unique();
+
+    assert_eq!(
+        cache.key(lb_pair),
+        derive_bin_array_bitmap_extension(lb_pair).0
+    );
+}
+
+#[test]
+fn ensure_quote_fresh_rejects_an_aged_quote() {
+    let best = fixture_best_price(fixture_price_quote());
+    let max_age = Duration::from_millis(DEFAULT_MAX_QUOTE_AGE_MS);
+    let fetched_at = best.bid.fetched_at;
+
+    assert!(
+        ensure_quote_fresh(
+            &best,
+            max_age,
+            fetched_at + chrono::Duration::milliseconds(500)
+        )
+        .is_ok()
+    );
+    let error = ensure_quote_fresh(&best, max_age, fetched_at + chrono::Duration::seconds(5))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("5000ms old"));
+}
+
+#[test]
+fn best_quote_age_is_the_age_of_its_older_side() {
+    let mut bid = fixture_price_quote();
+    let ask = fixture_price_quote();
+    bid.fetched_at = ask.fetched_at - chrono::Duration::seconds(3);
+    let best = BestPriceQuote {
+        symbol: "TRUMPUSDC".to_string(),
+        bid,
+        ask: ask.clone(),
+        pools_quoted: 2,
+        degraded: false,
+    };
+    let now = ask.fetched_at + chrono::Duration::milliseconds(100);
+
+    assert_eq!(best.age(now), Duration::from_millis(3_100));
+    assert!(
+        ensure_quote_fresh(&best, Duration::from_millis(DEFAULT_MAX_QUOTE_AGE_MS), now).is_err()
+    );
+    // A fetch that started after `now` is not aged
+    assert_eq!(
+        ask.age(ask.fetched_at - chrono::Duration::seconds(1)),
+        Duration::ZERO
+    );
+}
+
+#[test]
+fn build_dex_states_carry_the_fetch_latency() {
+    let states = build_dex_states(
+        &fixture_best_price(fixture_price_quote()),
+        "meteora",
+        Utc::now(),
+    );
+
+    assert!(
+        states
+            .iter()
+            .all(|state| state.fetch_latency_ms == Some(120))
+    );
+}
+
+fn route_pair(first: &Pubkey, second: &Pubkey) -> TradePair {
+    let mut pair = make_trade_pair("TRUMPUSDC", &first.to_string());
+    pair.route = Some("TRUMP-SOL-USDC".to_string());
+    pair.route_pool_pubkey = Some(second.to_string());
+    pair.route_base_is_x = true;
+    pair
+}
+
+#[test]
+fn trade_configs_from_pairs_parses_route_rows() {
+    let (first, second, direct) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let pairs = vec![
+        make_trade_pair("TRUMPUSDC", &direct.to_string()),
+        route_pair(&first, &second),
+    ];
+
+    let configs = trade_configs_from_pairs(pairs);
+
+    let config = &configs["TRUMPUSDC"];
+    assert_eq!(config.pools.len(), 1);
+    assert_eq!(config.pools[0].pool_pubkey, direct);
+    assert_eq!(config.routes.len(), 1);
+    let route = &config.routes[0];
+    assert_eq!(route.name, "TRUMP-SOL-USDC");
+    assert_eq!(route.hops[0].pool_pubkey, first);
+    assert!(!route.hops[0].base_is_x);
+    assert_eq!(route.hops[1].pool_pubkey, second);
+    assert!(route.hops[1].base_is_x);
+    assert_eq!(route.hops[1].bin_array_count, 8);
+    assert_eq!(
+        config.pool_pubkeys().collect::<Vec<_>>(),
+        vec![direct, first, second]
+    );
+}
+
+#[test]
+fn trade_configs_from_pairs_skips_incomplete_routes() {
+    let mut missing_pool = route_pair(&Pubkey::new_unique(), &Pubkey::new_unique());
+    missing_pool.route_pool_pubkey = None;
+    let mut missing_name = route_pair(&Pubkey::new_unique(), &Pubkey::new_unique());
+    missing_name.route = None;
+    let mut invalid_pool = route_pair(&Pubkey::new_unique(), &Pubkey::new_unique());
+    invalid_pool.route_pool_pubkey = Some("not-a-pubkey".to_string());
+
+    let configs = trade_configs_from_pairs(vec![missing_pool, missing_name, invalid_pool]);
+
+    assert!(configs.is_empty());
+}
+
+/// Hop quote with the given swaps, tokens and spot price
+fn hop_quote(
+    sell: SwapQuote,
+    buy: SwapQuote,
+    decimals: (u32, u32),
+    spot_price: &str,
+    liquidity: PoolLiquidity,
+) -> PriceQuote {
+    PriceQuote {
+        pool: Pubkey::new_unique(),
+        base_decimals: decimals.0,
+        quote_decimals: decimals.1,
+        sell,
+        buy,
+        spot_price: Some(Decimal::from_str(spot_price).unwrap()),
+        liquidity,
+        ..fixture_price_quote()
+    }
+}
+
+#[test]
+fn chain_route_quote_compounds_hops_into_one_quote() {
+    // 1 TRUMP -> 0.05 SOL at 1% fee, then 0.05 SOL -> 9.95 USDC at 0.5% fee
+    let first = PriceQuote {
+        missing_bin_arrays: 1,
+        ..hop_quote(
+            SwapQuote::without_transfer_fees(1_000_000, 50_000_000, 10_000),
+            SwapQuote::without_transfer_fees(49_500_000, 980_000, 495_000),
+            (6, 9),
+            "0.05",
+            PoolLiquidity {
+                base_amount: Decimal::from(1_000),
+                quote_amount: Decimal::from(50),
+                notional: Some(Decimal::from(100)),
+            },
+        )
+    };
+    let second = PriceQuote {
+        stale: true,
+        ..hop_quote(
+            SwapQuote::without_transfer_fees(50_000_000, 9_950_000, 250_000),
+            SwapQuote::without_transfer_fees(9_950_000, 49_500_000, 49_750),
+            (9, 6),
+            "200",
+            PoolLiquidity {
+                base_amount: Decimal::from(10),
+                quote_amount: Decimal::from(3_000),
+                notional: Some(Decimal::from(5_000)),
+            },
+        )
+    };
+
+    let route = chain_route_quote("TRUMP-SOL-USDC", first.clone(), second.clone());
+
+    assert_eq!(route.route.as_deref(), Some("TRUMP-SOL-USDC"));
+    assert_eq!(route.pool, first.pool);
+    assert_eq!(route.sell.amount_in, 1_000_000);
+    assert_eq!(route.sell.amount_out, 9_950_000);
+    // 1 - 0.99 * 0.995 of the TRUMP sold
+    assert_eq!(route.sell.fee, 14_950);
+    assert_eq!(route.sell_fee_pct, Decimal::from_str("1.495").unwrap());
+    assert_eq!(route.buy.amount_in, 9_950_000);
+    assert_eq!(route.buy.amount_out, 980_000);
+    assert_eq!((route.base_decimals, route.quote_decimals), (6, 6));
+    assert_eq!(route.bid_price, Decimal::from_str("9.95").unwrap());
+    assert_eq!(
+        route.ask_price,
+        Decimal::from_str("9.95").unwrap() / Decimal::from_str("0.98").unwrap()
+    );
+    assert_eq!(route.spot_price, Some(Decimal::TEN));
+    assert_eq!(
+        route.bid_impact_bps,
+        price_impact_bps(route.bid_price, Decimal::TEN)
+    );
+    // The first hop holds 100 SOL = 20 000 USDC, so the second hop bounds the route
+    assert_eq!(route.liquidity.notional, Some(Decimal::from(5_000)));
+    assert_eq!(route.liquidity.base_amount, Decimal::from(1_000));
+    assert_eq!(route.liquidity.quote_amount, Decimal::from(3_000));
+    assert!(route.stale);
+    assert_eq!(route.missing_bin_arrays, 1);
+    assert_eq!(route.hops.len(), 2);
+    assert_eq!(route.hops[0].pool, first.pool);
+    assert_eq!(route.hops[1].sell, second.sell);
+}
+
+#[test]
+fn quote_route_hops_feeds_each_hop_output_into_the_next() {
+    let first = fixture_snapshot(500);
+    let mut second = fixture_snapshot(500);
+    second.pool.base_is_x = true;
+    let route = RouteConfig {
+        name: "TRUMP-SOL-USDC".to_string(),
+        hops: [first.pool.clone(), second.pool.clone()],
+    };
+
+    let quote = quote_route_hops("TRUMPUSDC", &route, &first, &second, 1_000_000).unwrap();
+
+    let [first_hop, second_hop] = &quote.hops[..] else {
+        panic!("expected two hops, got {}", quote.hops.len());
+    };
+    assert_eq!(first_hop.pool, first.lb_pair);
+    assert_eq!(second_hop.pool, second.lb_pair);
+    assert_eq!(first_hop.sell.amount_in, 1_000_000);
+    assert_eq!(second_hop.sell.amount_in, first_hop.sell.amount_out);
+    assert_eq!(quote.sell.amount_out, second_hop.sell.amount_out);
+    // The buy side spends what the sell side received, back through the second hop first
+    assert_eq!(second_hop.buy.amount_in, quote.sell.amount_out);
+    assert_eq!(first_hop.buy.amount_in, second_hop.buy.amount_out);
+    assert_eq!(quote.buy.amount_out, first_hop.buy.amount_out);
+    assert_eq!(quote.slot, 500);
+}
+
+#[test]
+fn build_dex_states_tag_route_quotes() {
+    let first = fixture_snapshot(700);
+    let second = fixture_snapshot(700);
+    let route = RouteConfig {
+        name: "TRUMP-SOL-USDC".to_string(),
+        hops: [first.pool.clone(), second.pool.clone()],
+    };
+    let quote = quote_route_hops("TRUMPUSDC", &route, &first, &second, 1_000_000).unwrap();
+    let best = fixture_best_price(quote);
+
+    let states = build_dex_states(&best, "meteora", Utc::now());
+
+    assert_eq!(
+        states[0].trade_id,
+        format!("{}:{}:700:sell", first.lb_pair, second.lb_pair)
+    );
+    assert!(states.iter().all(|state| state.trade_pair == "TRUMPUSDC"));
+    assert!(
+        states
+            .iter()
+            .all(|state| state.route.as_deref() == Some("TRUMP-SOL-USDC"))
+    );
+    assert!(states.iter().all(|state| state.pool_address.is_none()));
+    assert!(build_pool_stats(&best, "meteora", Utc::now()).is_empty());
+}
+
+fn fee_lb_pair(
+    bin_step: u16,
+    base_factor: u16,
+    variable_fee_control: u32,
+    volatility: u32,
+) -> LbPair {
+    let mut lb_pair = fixture_lb_pair(0, bin_step);
+    lb_pair.parameters.base_factor = base_factor;
+    lb_pair.parameters.variable_fee_control = variable_fee_control;
+    lb_pair.v_parameters.volatility_accumulator = volatility;
+    lb_pair
+}
+
+#[test]
+fn pool_fee_rate_derives_base_fee_from_base_factor_and_bin_step() {
+    // 25 bps bins with base factor 10 000 charge 0.25%
+    let fee_rate = pool_fee_rate(&fee_lb_pair(25, 10_000, 0, 50_000));
+
+    assert_eq!(fee_rate.base_fee_bps, Decimal::from(25));
+    // No variable fee without a variable fee control, whatever the volatility
+    assert_eq!(fee_rate.variable_fee_bps, Decimal::ZERO);
+    assert_eq!(fee_rate.total_fee_bps, Decimal::from(25));
+
+    let mut powered = fee_lb_pair(1, 8_000, 0, 0);
+    powered.parameters.base_fee_power_factor = 1;
+    assert_eq!(pool_fee_rate(&powered).base_fee_bps, Decimal::from(8));
+}
+
+#[test]
+fn pool_fee_rate_adds_variable_fee_from_volatility() {
+    // (100 000 * 25)^2 * 7 500 / 1e11 = 468 750 in 1e9 precision
+    let fee_rate = pool_fee_rate(&fee_lb_pair(25, 10_000, 7_500, 100_000));
+
+    assert_eq!(
+        fee_rate.variable_fee_bps,
+        Decimal::from_str("4.6875").unwrap()
+    );
+    assert_eq!(
+        fee_rate.total_fee_bps,
+        Decimal::from_str("29.6875").unwrap()
+    );
+    // The program rounds the variable fee up: 4 687.5 becomes 4 688
+    let rounded = pool_fee_rate(&fee_lb_pair(25, 10_000, 7_500, 10_000));
+    assert_eq!(
+        rounded.variable_fee_bps,
+        Decimal::from_str("0.04688").unwrap()
+    );
+}
+
+#[test]
+fn pool_fee_rate_caps_total_fee_at_ten_percent() {
+    let fee_rate = pool_fee_rate(&fee_lb_pair(100, 10_000, 1_000_000, 1_000_000));
+
+    assert_eq!(fee_rate.base_fee_bps, Decimal::from(100));
+    assert!(fee_rate.variable_fee_bps > Decimal::from(1_000));
+    assert_eq!(fee_rate.total_fee_bps, Decimal::from(1_000));
+}
+
+#[test]
+fn build_pool_fees_records_the_quoted_pool_or_every_route_hop() {
+    let mut snapshot = fixture_snapshot(900);
+    snapshot.accounts.lb_pair_state = fee_lb_pair(25, 10_000, 0, 0);
+    let (sell, buy) = fixture_quotes();
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, sell, buy).unwrap();
+
+    let fees = build_pool_fees(&quote, "meteora");
+
+    assert_eq!(fees.len(), 1);
+    assert_eq!(fees[0].pool_address, snapshot.lb_pair.to_string());
+    assert_eq!(fees[0].block_number, 900);
+    assert_eq!(fees[0].total_fee_bps, Decimal::from(25));
+    assert_eq!(fees[0].fetch_time, snapshot.fetched_at);
+
+    let second = fixture_snapshot(900);
+    let route = RouteConfig {
+        name: "TRUMP-SOL-USDC".to_string(),
+        hops: [snapshot.pool.clone(), second.pool.clone()],
+    };
+    let route_quote = quote_route_hops("TRUMPUSDC", &route, &snapshot, &second, 1_000_000).unwrap();
+    let route_fees = build_pool_fees(&route_quote, "meteora");
+
+    assert!(route_quote.fee_rate.is_none());
+    assert_eq!(
+        route_fees
+            .iter()
+            .map(|fee| fee.pool_address.clone())
+            .collect::<Vec<_>>(),
+        vec![snapshot.lb_pair.to_string(), second.lb_pair.to_string()]
+    );
+    assert_eq!(build_pool_fees(&fixture_price_quote(), "meteora").len(), 0);
+}
@@ -0,0 +1,578 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use prost::Message as _;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::depth_sync::{
+    BookSync, DepthSnapshot, DepthUpdate, Levels, SnapshotOutcome, SymbolBook, UpdateOutcome,
+    parse_levels, parse_update_id,
+};
+use super::symbols::is_valid_symbol;
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "mexc";
+/// Pairs streamed when `MEXC_SYMBOLS` is unset; MEXC has no TRUMP/USDC book
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDC:TRUMPUSDT";
+/// Spot websocket URL when `MEXC_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://wbs-api.mexc.com/ws";
+/// Spot REST base URL when `MEXC_REST_URL` is unset
+const DEFAULT_REST_URL: &str = "https://api.mexc.com";
+/// Levels per side of the REST depth snapshot when `MEXC_SNAPSHOT_LIMIT` is unset
+const DEFAULT_SNAPSHOT_LIMIT: u32 = 1_000;
+/// Largest depth snapshot MEXC serves
+const MAX_SNAPSHOT_LIMIT: u32 = 5_000;
+/// Subscriptions MEXC accepts on one connection
+const MAX_SUBSCRIPTIONS: usize = 30;
+/// Silence of the depth pushes after which the feed is considered stale
+const DEFAULT_STALE_FEED_SECS: u64 = 30;
+/// Interval of the `PING` requests; MEXC drops connections idle for a minute
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+/// Timeout of a REST depth snapshot request
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a snapshot older than the buffered updates is fetched again
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Pairs and endpoints of the MEXC screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct MexcConfig {
+    /// MEXC symbol streamed for every internal symbol, e.g. `TRUMPUSDC` → `TRUMPUSDT`
+    pub pairs: BTreeMap<String, String>,
+    pub ws_url: String,
+    pub rest_url: String,
+    /// Levels per side of the REST depth snapshots
+    pub snapshot_limit: u32,
+    /// Silence of the depth pushes after which the session is dropped
+    pub stale_feed_timeout: Duration,
+}
+
+impl MexcConfig {
+    /// Read `MEXC_SYMBOLS` (comma-separated `SYMBOL:MEXC_SYMBOL` entries), `MEXC_WS_URL`,
+    /// `MEXC_REST_URL`, `MEXC_SNAPSHOT_LIMIT` and `MEXC_STALE_FEED_SECS`, falling back to the
+    /// defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols = std::env::var("MEXC_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        let snapshot_limit = match std::env::var("MEXC_SNAPSHOT_LIMIT") {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|limit| (1..=MAX_SNAPSHOT_LIMIT).contains(limit))
+                .ok_or_else(|| {
+                    format!(
+                        "MEXC_SNAPSHOT_LIMIT `{}` is not between 1 and {}",
+                        value, MAX_SNAPSHOT_LIMIT
+                    )
+                })?,
+            Err(_) => DEFAULT_SNAPSHOT_LIMIT,
+        };
+        let stale_feed_secs = std::env::var("MEXC_STALE_FEED_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_STALE_FEED_SECS);
+        Ok(Self {
+            pairs: parse_pairs(&symbols)?,
+            ws_url: std::env::var("MEXC_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+            rest_url: std::env::var("MEXC_REST_URL")
+                .unwrap_or_else(|_| DEFAULT_REST_URL.to_string()),
+            snapshot_limit,
+            stale_feed_timeout: Duration::from_secs(stale_feed_secs),
+        })
+    }
+}
+
+/// Parse `SYMBOL:MEXC_SYMBOL` entries such as `TRUMPUSDC:TRUMPUSDT`. The MEXC symbol is
+/// always spelled out, since the quote asset of the internal symbol is often not listed.
+fn parse_pairs(value: &str) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let mut pairs = BTreeMap::new();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let Some((symbol, mexc_symbol)) = entry.split_once(':') else {
+            return Err(format!("MEXC_SYMBOLS entry `{}` is not SYMBOL:MEXC_SYMBOL", entry).into());
+        };
+        let (symbol, mexc_symbol) = (
+            symbol.trim().to_uppercase(),
+            mexc_symbol.trim().to_uppercase(),
+        );
+        if !is_valid_symbol(&symbol) || !is_valid_symbol(&mexc_symbol) {
+            return Err(format!("MEXC_SYMBOLS entry `{}` has an invalid symbol", entry).into());
+        }
+        if pairs.values().any(|mapped| *mapped == mexc_symbol) {
+            return Err(format!("MEXC_SYMBOLS streams {} more than once", mexc_symbol).into());
+        }
+        if pairs.insert(symbol.clone(), mexc_symbol).is_some() {
+            return Err(format!("MEXC_SYMBOLS lists {} more than once", symbol).into());
+        }
+    }
+    if pairs.is_empty() {
+        return Err("MEXC_SYMBOLS lists no symbols".into());
+    }
+    if pairs.len() > MAX_SUBSCRIPTIONS {
+        return Err(format!(
+            "MEXC_SYMBOLS lists {} symbols, one connection takes at most {}",
+            pairs.len(),
+            MAX_SUBSCRIPTIONS
+        )
+        .into());
+    }
+    Ok(pairs)
+}
+
+/// Channel of the 100ms aggregated depth updates of `mexc_symbol`
+fn depth_channel(mexc_symbol: &str) -> String {
+    format!("spot@public.aggre.depth.v3.api.pb@100ms@{}", mexc_symbol)
+}
+
+/// Subscription of the depth channels of every MEXC symbol
+fn subscribe_request<'a>(mexc_symbols: impl Iterator<Item = &'a String>) -> String {
+    let params: Vec<String> = mexc_symbols.map(|symbol| depth_channel(symbol)).collect();
+    json!({"method": "SUBSCRIPTION", "params": params}).to_string()
+}
+
+/// Envelope of every protobuf push, `PushDataV3ApiWrapper` of MEXC's websocket-proto. Only
+/// the aggregated depth member of the `body` oneof is declared; prost skips the others.
+#[derive(Clone, PartialEq, prost::Message)]
+struct PushDataV3ApiWrapper {
+    #[prost(string, tag = "1")]
+    channel: String,
+    #[prost(message, optional, tag = "313")]
+    public_aggre_depths: Option<PublicAggreDepthsV3Api>,
+    #[prost(string, optional, tag = "3")]
+    symbol: Option<String>,
+    #[prost(int64, optional, tag = "5")]
+    create_time: Option<i64>,
+    #[prost(int64, optional, tag = "6")]
+    send_time: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct PublicAggreDepthsV3Api {
+    #[prost(message, repeated, tag = "1")]
+    asks: Vec<PublicAggreDepthV3ApiItem>,
+    #[prost(message, repeated, tag = "2")]
+    bids: Vec<PublicAggreDepthV3ApiItem>,
+    #[prost(string, tag = "3")]
+    event_type: String,
+    #[prost(string, tag = "4")]
+    from_version: String,
+    #[prost(string, tag = "5")]
+    to_version: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct PublicAggreDepthV3ApiItem {
+    #[prost(string, tag = "1")]
+    price: String,
+    #[prost(string, tag = "2")]
+    quantity: String,
+}
+
+fn parse_items(items: &[PublicAggreDepthV3ApiItem]) -> Result<Levels, String> {
+    items
+        .iter()
+        .map(|item| {
+            let price = item
+                .price
+                .parse()
+                .map_err(|e| format!("malformed price `{}`: {}", item.price, e))?;
+            let quantity = item
+                .quantity
+                .parse()
+                .map_err(|e| format!("malformed quantity `{}`: {}", item.quantity, e))?;
+            Ok((price, quantity))
+        })
+        .collect()
+}
+
+fn parse_version(value: &str, field: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("malformed version `{}` `{}`", field, value))
+}
+
+/// Decode a binary push; pushes of other channels are `None`. The update carries the MEXC
+/// symbol, which is mapped to the internal one by the screener.
+fn parse_push(data: &[u8]) -> Result<Option<DepthUpdate>, String> {
+    let push = PushDataV3ApiWrapper::decode(data).map_err(|e| e.to_string())?;
+    let Some(depths) = push.public_aggre_depths else {
+        return Ok(None);
+    };
+    Ok(Some(DepthUpdate {
+        symbol: push.symbol.ok_or("missing symbol")?,
+        event_ms: push
+            .send_time
+            .or(push.create_time)
+            .ok_or("missing send time")?,
+        first_update_id: parse_version(&depths.from_version, "fromVersion")?,
+        last_update_id: parse_version(&depths.to_version, "toVersion")?,
+        bids: parse_items(&depths.bids)?,
+        asks: parse_items(&depths.asks)?,
+    }))
+}
+
+/// Decode a JSON control reply such as `{"id":0,"code":0,"msg":"PONG"}`; returns the error
+/// of a failed request
+fn parse_reply(text: &str) -> Result<Option<String>, String> {
+    let reply: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if reply["code"].as_i64().unwrap_or(0) != 0 {
+        return Ok(Some(format!("code {}: {}", reply["code"], reply["msg"])));
+    }
+    Ok(None)
+}
+
+/// Decode a `/api/v3/depth` reply
+fn parse_depth_snapshot(body: &Value) -> Result<DepthSnapshot, String> {
+    Ok(DepthSnapshot {
+        last_update_id: parse_update_id(body, "lastUpdateId")?,
+        bids: parse_levels(&body["bids"])?,
+        asks: parse_levels(&body["asks"])?,
+    })
+}
+
+/// Fetch the depth snapshot of `mexc_symbol`
+async fn fetch_depth_snapshot(
+    http: &reqwest::Client,
+    rest_url: &str,
+    mexc_symbol: &str,
+    limit: u32,
+) -> Result<DepthSnapshot, String> {
+    let url = format!(
+        "{}/api/v3/depth?symbol={}&limit={}",
+        rest_url.trim_end_matches('/'),
+        mexc_symbol,
+        limit
+    );
+    let response = http.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {} {}", status, body["code"], body["msg"]));
+    }
+    parse_depth_snapshot(&body)
+}
+
+/// Fetches of REST depth snapshots in flight, with their MEXC symbol
+type SnapshotFetches = JoinSet<(String, Result<DepthSnapshot, String>)>;
+
+/// MEXC spot screener keeping a local book per pair from the protobuf aggregated depth
+/// channel and REST snapshots, persisted as CEX market states under the internal symbol
+pub struct MexcScreener {
+    config: MexcConfig,
+    shutdown: CancellationToken,
+    http: reqwest::Client,
+    /// Books by MEXC symbol, each named after its internal symbol
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl MexcScreener {
+    /// Create a new MexcScreener instance on the pairs of `MEXC_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_config(db_pool, MexcConfig::from_env()?)
+    }
+
+    pub fn with_config(
+        db_pool: DatabasePool,
+        config: MexcConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder().timeout(REST_TIMEOUT).build()?;
+        let cex_writer = CexMarketWriter::for_database(db_pool, "mexc");
+        let supervisor = WsSupervisor::new("MEXC", EXCHANGE, config.stale_feed_timeout);
+        Ok(Self {
+            config,
+            shutdown: CancellationToken::new(),
+            http,
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor,
+        })
+    }
+
+    /// Stream the books until stopped; a dropped or stale connection is retried after an
+    /// exponential backoff and every book is rebuilt from a new snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting MEXC screener for {:?} ({})...",
+            self.config.pairs, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("MEXC screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .pairs
+            .iter()
+            .map(|(symbol, mexc_symbol)| (mexc_symbol.clone(), SymbolBook::new(EXCHANGE, symbol)))
+            .collect();
+    }
+
+    /// Fetch the snapshot of `mexc_symbol` after `delay`
+    fn spawn_snapshot_fetch(
+        &self,
+        fetches: &mut SnapshotFetches,
+        mexc_symbol: &str,
+        delay: Duration,
+    ) {
+        let http = self.http.clone();
+        let rest_url = self.config.rest_url.clone();
+        let limit = self.config.snapshot_limit;
+        let mexc_symbol = mexc_symbol.to_string();
+        fetches.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let snapshot = fetch_depth_snapshot(&http, &rest_url, &mexc_symbol, limit).await;
+            (mexc_symbol, snapshot)
+        });
+    }
+
+    /// Apply one binary push; returns whether it was a depth update
+    fn handle_push(&self, fetches: &mut SnapshotFetches, data: &[u8]) -> bool {
+        let update = match parse_push(data) {
+            Ok(Some(update)) => update,
+            Ok(None) => {
+                debug!("Skipping MEXC push of {} bytes", data.len());
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed MEXC push: {}", e);
+                return false;
+            }
+        };
+        let (mexc_symbol, version, event_ms) = (
+            update.symbol.clone(),
+            update.last_update_id,
+            update.event_ms,
+        );
+
+        let mut books = self.books.lock().unwrap();
+        // Pushes of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&mexc_symbol) else {
+            return true;
+        };
+        match book.on_update(update) {
+            UpdateOutcome::Applied => self.persist(book, version, event_ms, fetches),
+            UpdateOutcome::Resync => {
+                warn!(
+                    "MEXC {} order book missed versions before {}, fetching a new snapshot",
+                    mexc_symbol, version
+                );
+                metrics::counter!("mexc_orderbook_gaps_total", "symbol" => mexc_symbol.clone())
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, &mexc_symbol, Duration::ZERO);
+            }
+            UpdateOutcome::Buffered | UpdateOutcome::Skipped => {}
+        }
+        true
+    }
+
+    /// Rebuild a book from its snapshot, fetching it again when it is too old or failed
+    fn handle_snapshot(
+        &self,
+        fetches: &mut SnapshotFetches,
+        mexc_symbol: &str,
+        snapshot: Result<DepthSnapshot, String>,
+    ) {
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!(
+                    "MEXC {} depth snapshot failed, retrying: {}",
+                    mexc_symbol, e
+                );
+                metrics::counter!("mexc_orderbook_snapshots_total", "symbol" => mexc_symbol.to_string(), "status" => "failed")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, mexc_symbol, SNAPSHOT_RETRY_DELAY);
+                return;
+            }
+        };
+        let version = snapshot.last_update_id;
+
+        let mut books = self.books.lock().unwrap();
+        let Some(book) = books.get_mut(mexc_symbol) else {
+            return;
+        };
+        match book.on_snapshot(snapshot) {
+            SnapshotOutcome::Synced { replayed } => {
+                info!(
+                    "MEXC {} order book synced from a snapshot at {}, replayed {} updates",
+                    mexc_symbol, version, replayed
+                );
+                metrics::counter!("mexc_orderbook_snapshots_total", "symbol" => mexc_symbol.to_string(), "status" => "ok")
+                    .increment(1);
+                let BookSync::Synced(last) = book.sync else {
+                    return;
+                };
+                self.persist(book, last, Utc::now().timestamp_millis(), fetches);
+            }
+            SnapshotOutcome::Stale => {
+                debug!(
+                    "MEXC {} depth snapshot at {} is older than the buffered updates, retrying",
+                    mexc_symbol, version
+                );
+                metrics::counter!("mexc_orderbook_snapshots_total", "symbol" => mexc_symbol.to_string(), "status" => "stale")
+                    .increment(1);
+                self.spawn_snapshot_fetch(fetches, mexc_symbol, SNAPSHOT_RETRY_DELAY);
+            }
+            SnapshotOutcome::Ignored => {}
+        }
+    }
+
+    /// Persist the top of book under its internal symbol when it changed. A book that cannot
+    /// describe a real market is dropped and rebuilt from a new snapshot instead.
+    fn persist(
+        &self,
+        book: &mut SymbolBook,
+        version: u64,
+        event_ms: i64,
+        fetches: &mut SnapshotFetches,
+    ) {
+        let symbol = book.orderbook.symbol.clone();
+        if let Err(violation) = book.orderbook.validate() {
+            error!(
+                "MEXC {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                book.orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "mexc_invalid_books_total",
+                "symbol" => symbol.clone(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            book.resync_from(Vec::new());
+            if let Some(mexc_symbol) = self.config.pairs.get(&symbol) {
+                self.spawn_snapshot_fetch(fetches, mexc_symbol, Duration::ZERO);
+            }
+            return;
+        }
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: version.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol,
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(event_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - event_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for MexcScreener {
+    /// Empty the books, connect, subscribe every pair, fetch their snapshots and apply the depth
+    /// pushes until the connection drops, the feed goes stale or the screener stops. `feed` counts
+    /// the depth pushes delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        ws.send(Message::Text(subscribe_request(self.config.pairs.values())))
+            .await
+            .map_err(|e| format!("subscribe failed: {}", e))?;
+        info!(
+            "MEXC websocket connected, subscribed to {:?}",
+            self.config.pairs
+        );
+
+        // Pushes are buffered from the subscription on, so the snapshots are fetched after it
+        let mut fetches = SnapshotFetches::new();
+        for mexc_symbol in self.config.pairs.values() {
+            self.spawn_snapshot_fetch(&mut fetches, mexc_symbol, Duration::ZERO);
+        }
+        let mut ping =
+            tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                _ = ping.tick() => {
+                    ws.send(Message::Text(json!({"method": "PING"}).to_string()))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                Some(fetched) = fetches.join_next(), if !fetches.is_empty() => {
+                    if let Ok((mexc_symbol, snapshot)) = fetched {
+                        self.handle_snapshot(&mut fetches, &mexc_symbol, snapshot);
+                    }
+                }
+                reason = feed.stale() => return Err(reason),
+                // Only depth pushes keep the feed alive, replies and pings do not
+                frame = ws.next() => match frame {
+                    Some(Ok(Message::Binary(data))) => {
+                        if self.handle_push(&mut fetches, &data) {
+                            feed.frame();
+                            feed.delivered();
+                        }
+                    }
+                    Some(Ok(Message::Text(text))) => match parse_reply(&text) {
+                        Ok(Some(detail)) => error!("MEXC websocket error: {}", detail),
+                        Ok(None) => debug!("MEXC websocket reply: {}", text),
+                        Err(e) => warn!("Skipping malformed MEXC reply: {}", e),
+                    },
+                    Some(Ok(Message::Ping(payload))) => {
+                        ws.send(Message::Pong(payload))
+                            .await
+                            .map_err(|e| e.to_string())?;
+                    }
+                    Some(Ok(Message::Close(frame))) => {
+                        return Err(format!("closed by server: {:?}", frame));
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.to_string()),
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "mexc_tests.rs"]
+mod mexc_tests;
@@ -0,0 +1,216 @@
+use super::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Protobuf pushes of `spot@public.aggre.depth.v3.api.pb@100ms@TRUMPUSDT`, hex encoded, in
+/// order: versions 157-160, 161-163, 164 and, after a gap, 170-172
+const PUSH_FIXTURES: [&str; 4] = [
+    "0a3173706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d73405452554d5055534454ca13590a0a0a0531302e3237120133120a0a0531302e3234120130120c0a0531302e32351203312e351a2773706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d7322033135372a033136301a095452554d505553445430bcadb5e8fb31",
+    "0a3173706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d73405452554d5055534454ca133f120a0a0531302e32351201321a2773706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d7322033136312a033136331a095452554d505553445430a0aeb5e8fb31",
+    "0a3173706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d73405452554d5055534454ca134b0a0a0a0531302e32381201300a0a0a0531302e32361201311a2773706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d7322033136342a033136341a095452554d50555344543084afb5e8fb31",
+    "0a3173706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d73405452554d5055534454ca133e12090a0431302e321201391a2773706f74407075626c69632e61676772652e64657074682e76332e6170692e7062403130306d7322033137302a033137321a095452554d505553445430e8afb5e8fb31",
+];
+
+/// Protobuf push of `spot@public.aggre.deals.v3.api.pb@100ms@TRUMPUSDT`, hex encoded
+const DEALS_FIXTURE: &str = "0a3173706f74407075626c69632e61676772652e6465616c732e76332e6170692e7062403130306d73405452554d5055534454d2133e0a130a0531302e3235120133180120eeadb5e8fb31122773706f74407075626c69632e61676772652e6465616c732e76332e6170692e7062403130306d731a095452554d505553445430eeadb5e8fb31";
+
+/// `/api/v3/depth` reply recorded for TRUMPUSDT
+const SNAPSHOT_FIXTURE: &str = r#"{"lastUpdateId":158,"bids":[["10.24","2"],["10.23","3"]],"asks":[["10.27","4"],["10.28","5"]]}"#;
+
+fn push(index: usize) -> Vec<u8> {
+    hex::decode(PUSH_FIXTURES[index]).unwrap()
+}
+
+fn update(index: usize) -> DepthUpdate {
+    parse_push(&push(index)).unwrap().unwrap()
+}
+
+fn snapshot() -> DepthSnapshot {
+    parse_depth_snapshot(&serde_json::from_str(SNAPSHOT_FIXTURE).unwrap()).unwrap()
+}
+
+impl TestScreener for MexcScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = MexcScreener {
+            config: MexcConfig {
+                pairs: BTreeMap::from([("TRUMPUSDC".to_string(), "TRUMPUSDT".to_string())]),
+                ws_url: DEFAULT_WS_URL.to_string(),
+                rest_url: "http://127.0.0.1:1".to_string(),
+                snapshot_limit: 100,
+                stale_feed_timeout: Duration::from_secs(30),
+            },
+            shutdown: CancellationToken::new(),
+            http: reqwest::Client::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("MEXC", EXCHANGE, Duration::from_secs(30)),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn depth_pushes_are_decoded_from_protobuf() {
+    assert_eq!(
+        update(0),
+        DepthUpdate {
+            symbol: "TRUMPUSDT".to_string(),
+            event_ms: 1716863719100,
+            first_update_id: 157,
+            last_update_id: 160,
+            bids: vec![
+                (decimal("10.24"), Decimal::ZERO),
+                (decimal("10.25"), decimal("1.5"))
+            ],
+            asks: vec![(decimal("10.27"), decimal("3"))],
+        }
+    );
+    assert_eq!(parse_push(&hex::decode(DEALS_FIXTURE).unwrap()), Ok(None));
+    assert!(parse_push(&push(0)[..40]).is_err());
+    assert!(parse_push(&[0xff, 0xff, 0xff]).is_err());
+}
+
+#[test]
+fn control_replies_report_failed_requests() {
+    assert_eq!(parse_reply(r#"{"id":0,"code":0,"msg":"PONG"}"#), Ok(None));
+    assert_eq!(
+        parse_reply(
+            r#"{"id":0,"code":0,"msg":"spot@public.aggre.depth.v3.api.pb@100ms@TRUMPUSDT"}"#
+        ),
+        Ok(None)
+    );
+    assert_eq!(
+        parse_reply(r#"{"id":0,"code":1,"msg":"Not Subscribed successfully! [spot@public.aggre.depth.v3.api.pb@100ms@NOPEUSDT]"}"#),
+        Ok(Some(
+            "code 1: \"Not Subscribed successfully! [spot@public.aggre.depth.v3.api.pb@100ms@NOPEUSDT]\""
+                .to_string()
+        ))
+    );
+}
+
+#[test]
+fn pairs_map_internal_symbols_to_mexc_symbols() {
+    assert_eq!(
+        parse_pairs(" trumpusdc:TRUMPUSDT, SOLUSDC : SOLUSDT ").unwrap(),
+        BTreeMap::from([
+            ("SOLUSDC".to_string(), "SOLUSDT".to_string()),
+            ("TRUMPUSDC".to_string(), "TRUMPUSDT".to_string())
+        ])
+    );
+    assert!(parse_pairs("TRUMPUSDC").is_err());
+    assert!(parse_pairs("TRUMPUSDC:TRUMP-USDT").is_err());
+    assert!(parse_pairs("TRUMPUSDC:TRUMPUSDT,TRUMPUSDT:TRUMPUSDT").is_err());
+    assert!(parse_pairs("TRUMPUSDC:TRUMPUSDT,TRUMPUSDC:TRUMPUSDC").is_err());
+    assert!(parse_pairs(" , ").is_err());
+
+    let subscribe: Value = serde_json::from_str(&subscribe_request(
+        ["TRUMPUSDT".to_string(), "SOLUSDT".to_string()].iter(),
+    ))
+    .unwrap();
+    assert_eq!(
+        subscribe,
+        json!({
+            "method": "SUBSCRIPTION",
+            "params": [
+                "spot@public.aggre.depth.v3.api.pb@100ms@TRUMPUSDT",
+                "spot@public.aggre.depth.v3.api.pb@100ms@SOLUSDT"
+            ]
+        })
+    );
+}
+
+#[test]
+fn versions_follow_the_snapshot_and_gaps_drop_the_book() {
+    let mut book = SymbolBook::new(EXCHANGE, "TRUMPUSDC");
+    book.on_update(update(0));
+    assert_eq!(
+        book.on_snapshot(snapshot()),
+        SnapshotOutcome::Synced { replayed: 1 }
+    );
+    assert_eq!(book.on_update(update(1)), UpdateOutcome::Applied);
+    assert_eq!(book.on_update(update(2)), UpdateOutcome::Applied);
+    assert_eq!(
+        book.orderbook.asks.keys().copied().collect::<Vec<_>>(),
+        [decimal("10.26"), decimal("10.27")]
+    );
+
+    assert_eq!(book.on_update(update(3)), UpdateOutcome::Resync);
+    assert_eq!(book.sync, BookSync::AwaitingSnapshot(vec![update(3)]));
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_under_the_internal_symbol() {
+    let (screener, sink) = build_screener_with_sink::<MexcScreener>();
+    let mut fetches = SnapshotFetches::new();
+
+    assert!(screener.handle_push(&mut fetches, &push(0)));
+    assert!(!screener.handle_push(&mut fetches, &hex::decode(DEALS_FIXTURE).unwrap()));
+    screener.handle_snapshot(&mut fetches, "TRUMPUSDT", Ok(snapshot()));
+    screener.cex_writer.flush().await;
+    for index in 1..3 {
+        assert!(screener.handle_push(&mut fetches, &push(index)));
+        screener.cex_writer.flush().await;
+    }
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "mexc" && state.trade_pair == "TRUMPUSDC")
+    );
+    assert_eq!(states[0].trade_id, "160");
+    assert_eq!(
+        (states[0].bid_price, states[0].ask_price),
+        (decimal("10.25"), decimal("10.27"))
+    );
+    assert_eq!(
+        (states[2].trade_id.as_str(), states[2].ask_price),
+        ("164", decimal("10.26"))
+    );
+    assert_eq!(states[2].trade_time.timestamp_millis(), 1716863719300);
+    assert!(fetches.is_empty());
+
+    screener.handle_push(&mut fetches, &push(3));
+    assert_eq!(fetches.len(), 1);
+    fetches.abort_all();
+}
+
+#[tokio::test]
+async fn depth_snapshots_are_fetched_over_rest() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .and(query_param("symbol", "TRUMPUSDT"))
+        .and(query_param("limit", "100"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SNAPSHOT_FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .and(query_param("symbol", "TRUMPUSDC"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_string(r#"{"code":-1121,"msg":"Invalid symbol."}"#),
+        )
+        .mount(&server)
+        .await;
+    let http = reqwest::Client::new();
+
+    assert_eq!(
+        fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDT", 100).await,
+        Ok(snapshot())
+    );
+    let error = fetch_depth_snapshot(&http, &server.uri(), "TRUMPUSDC", 100)
+        .await
+        .unwrap_err();
+    assert!(error.contains("-1121"), "{}", error);
+}
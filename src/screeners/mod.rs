@@ -1,2 +1,34 @@
+pub mod backpack;
+pub mod binance;
+pub mod bitget;
 pub mod bybit;
+pub mod bybit_instruments;
+pub mod bybit_private;
+pub mod bybit_rest;
+pub mod cex_writer;
+pub mod coinbase;
+mod depth_sync;
+mod dex_runner;
+pub mod dex_writer;
+pub mod gate;
+pub mod htx;
+pub mod hyperliquid;
+pub mod jupiter;
+pub mod kraken;
+pub mod kucoin;
 pub mod meteora;
+pub mod meteora_api;
+pub mod meteora_damm;
+pub mod mexc;
+pub mod okx;
+pub mod phoenix;
+pub mod pumpfun;
+pub mod raw_capture;
+pub mod raydium_amm;
+pub mod raydium_clmm;
+pub mod screener;
+pub mod symbols;
+#[cfg(test)]
+pub(crate) mod test_support;
+mod ws_codec;
+mod ws_supervisor;
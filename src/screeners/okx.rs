@@ -0,0 +1,562 @@
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market;
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::symbols::{from_venue_symbol, to_venue_symbol};
+use super::ws_supervisor::{SessionFeed, WsSession, WsSupervisor};
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "okx";
+/// Symbols streamed when `OKX_SYMBOLS` is unset
+const DEFAULT_SYMBOLS: &str = "TRUMPUSDC,TRUMPUSDT";
+/// Public websocket URL when `OKX_WS_URL` is unset
+const DEFAULT_WS_URL: &str = "wss://ws.okx.com:8443/ws/v5/public";
+/// 400-level order book channel with checksums
+const BOOKS_CHANNEL: &str = "books";
+/// Levels per side covered by the OKX checksum
+const CHECKSUM_LEVELS: usize = 25;
+/// OKX drops connections silent for 30 seconds, so a ping goes out more often
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+/// Silence after which the connection is considered dead; pings are answered with pongs
+const STALE_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Symbols and endpoint of the OKX screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct OKXConfig {
+    /// Internal symbols such as `TRUMPUSDC`
+    pub symbols: Vec<String>,
+    pub ws_url: String,
+}
+
+impl OKXConfig {
+    /// Read `OKX_SYMBOLS` (comma-separated internal symbols) and `OKX_WS_URL`, falling back
+    /// to the defaults
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols = std::env::var("OKX_SYMBOLS").unwrap_or_else(|_| DEFAULT_SYMBOLS.to_string());
+        Ok(Self {
+            symbols: parse_symbols(&symbols)?,
+            ws_url: std::env::var("OKX_WS_URL").unwrap_or_else(|_| DEFAULT_WS_URL.to_string()),
+        })
+    }
+}
+
+/// Parse comma-separated internal symbols, each of which must map to an OKX instrument id
+fn parse_symbols(value: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut symbols = Vec::new();
+    for symbol in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol = symbol.to_uppercase();
+        if to_venue_symbol(EXCHANGE, &symbol).is_none() {
+            return Err(format!(
+                "OKX_SYMBOLS entry `{}` has no OKX instrument id (unknown quote asset?)",
+                symbol
+            )
+            .into());
+        }
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err("OKX_SYMBOLS has no symbol".into());
+    }
+    Ok(symbols)
+}
+
+/// `subscribe` or `unsubscribe` request of the books channel of `symbols`
+fn books_request(op: &str, symbols: &[String]) -> String {
+    let args: Vec<Value> = symbols
+        .iter()
+        .filter_map(|symbol| to_venue_symbol(EXCHANGE, symbol))
+        .map(|inst_id| json!({"channel": BOOKS_CHANNEL, "instId": inst_id}))
+        .collect();
+    json!({"op": op, "args": args}).to_string()
+}
+
+/// Price levels as `(price, size)`, keeping the scale OKX sent since the checksum covers
+/// the original strings; a zero size removes the level
+pub(super) type Levels = Vec<(Decimal, Decimal)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookAction {
+    Snapshot,
+    Update,
+}
+
+/// Message of the books channel
+#[derive(Debug, Clone, PartialEq)]
+struct BookMessage {
+    /// Internal symbol of the instrument
+    symbol: String,
+    action: BookAction,
+    bids: Levels,
+    asks: Levels,
+    /// Exchange timestamp, in milliseconds
+    ts_ms: i64,
+    /// CRC32 of the top levels after the message is applied, as a signed 32-bit integer
+    checksum: i32,
+    /// Sequence id of the previous message; -1 on snapshots
+    prev_seq_id: i64,
+    seq_id: i64,
+}
+
+/// Decoded text frame of the public websocket
+#[derive(Debug, Clone, PartialEq)]
+enum OkxFrame {
+    Book(BookMessage),
+    /// Reply to a request, such as `subscribe` or `error`, with its details
+    Event {
+        event: String,
+        detail: String,
+    },
+    Pong,
+    Other,
+}
+
+pub(super) fn parse_levels(value: &Value) -> Result<Levels, String> {
+    let levels = value.as_array().ok_or("levels are not an array")?;
+    levels
+        .iter()
+        .map(|level| {
+            let field = |index: usize| -> Result<Decimal, String> {
+                let text = level[index]
+                    .as_str()
+                    .ok_or_else(|| format!("malformed level {}", level))?;
+                text.parse()
+                    .map_err(|e| format!("malformed level {}: {}", level, e))
+            };
+            Ok((field(0)?, field(1)?))
+        })
+        .collect()
+}
+
+fn parse_frame(text: &str) -> Result<OkxFrame, String> {
+    if text == "pong" {
+        return Ok(OkxFrame::Pong);
+    }
+    let frame: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if let Some(event) = frame["event"].as_str() {
+        let detail = match frame["code"].as_str() {
+            Some(code) => format!("{} {}", code, frame["msg"].as_str().unwrap_or_default()),
+            None => frame["arg"].to_string(),
+        };
+        return Ok(OkxFrame::Event {
+            event: event.to_string(),
+            detail,
+        });
+    }
+    if frame["arg"]["channel"] != BOOKS_CHANNEL {
+        return Ok(OkxFrame::Other);
+    }
+    let inst_id = frame["arg"]["instId"]
+        .as_str()
+        .ok_or("missing instrument id")?;
+    let symbol = from_venue_symbol(EXCHANGE, inst_id)
+        .ok_or_else(|| format!("unexpected instrument {}", inst_id))?;
+    let action = match frame["action"].as_str() {
+        Some("snapshot") => BookAction::Snapshot,
+        Some("update") => BookAction::Update,
+        other => return Err(format!("unknown book action {:?}", other)),
+    };
+    let data = frame["data"].get(0).ok_or("missing book data")?;
+    let number = |field: &str| -> Result<i64, String> {
+        data[field]
+            .as_i64()
+            .ok_or_else(|| format!("missing `{}`", field))
+    };
+    Ok(OkxFrame::Book(BookMessage {
+        symbol,
+        action,
+        bids: parse_levels(&data["bids"])?,
+        asks: parse_levels(&data["asks"])?,
+        ts_ms: data["ts"]
+            .as_str()
+            .and_then(|ts| ts.parse().ok())
+            .ok_or("missing `ts`")?,
+        checksum: number("checksum")? as i32,
+        prev_seq_id: number("prevSeqId")?,
+        seq_id: number("seqId")?,
+    }))
+}
+
+/// OKX checksum of a book: CRC32 of the best 25 bids and asks as `price:size`, alternating
+/// bid and ask, with the deeper side's remaining levels appended, read as a signed integer.
+/// Bitget checksums its books the same way.
+pub(super) fn checksum(orderbook: &market::OrderBook) -> i32 {
+    let mut bids = orderbook.bids.iter().rev().take(CHECKSUM_LEVELS);
+    let mut asks = orderbook.asks.iter().take(CHECKSUM_LEVELS);
+    let mut fields = Vec::with_capacity(CHECKSUM_LEVELS * 4);
+    loop {
+        let (bid, ask) = (bids.next(), asks.next());
+        if bid.is_none() && ask.is_none() {
+            break;
+        }
+        for (price, size) in bid.into_iter().chain(ask) {
+            fields.push(price.to_string());
+            fields.push(size.to_string());
+        }
+    }
+    crc32fast::hash(fields.join(":").as_bytes()) as i32
+}
+
+pub(super) fn apply_levels(levels: &mut market::OrderBookLevels, updates: &Levels) {
+    for (price, size) in updates {
+        // Removing first replaces the key too, so its scale follows the latest message
+        levels.remove(price);
+        if !size.is_zero() {
+            levels.insert(*price, *size);
+        }
+    }
+}
+
+/// Why a book was dropped until a new snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ResyncReason {
+    /// The update did not follow the last applied sequence id
+    Sequence {
+        expected: i64,
+        prev_seq_id: i64,
+    },
+    Checksum {
+        expected: i32,
+        actual: i32,
+    },
+}
+
+impl ResyncReason {
+    /// Metric label of the reason
+    fn kind(&self) -> &'static str {
+        match self {
+            ResyncReason::Sequence { .. } => "sequence",
+            ResyncReason::Checksum { .. } => "checksum",
+        }
+    }
+}
+
+impl std::fmt::Display for ResyncReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResyncReason::Sequence {
+                expected,
+                prev_seq_id,
+            } => write!(
+                f,
+                "update follows sequence {} instead of {}",
+                prev_seq_id, expected
+            ),
+            ResyncReason::Checksum { expected, actual } => {
+                write!(f, "checksum {} instead of {}", actual, expected)
+            }
+        }
+    }
+}
+
+/// Result of feeding a message to a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BookOutcome {
+    Applied,
+    /// Update of a book waiting for its snapshot
+    Ignored,
+    /// The book was dropped and needs a new snapshot
+    Resync(ResyncReason),
+}
+
+/// Local book of a symbol with the sequence id of its last message
+#[derive(Debug, Clone)]
+struct SymbolBook {
+    orderbook: market::OrderBook,
+    /// `None` until a snapshot arrives
+    seq_id: Option<i64>,
+    /// Best bid and ask of the last persisted state
+    last_top: Option<(market::OrderBookItem, market::OrderBookItem)>,
+}
+
+impl SymbolBook {
+    fn new(symbol: &str) -> Self {
+        Self {
+            orderbook: market::OrderBook::new(EXCHANGE, symbol),
+            seq_id: None,
+            last_top: None,
+        }
+    }
+
+    /// Drop the book until the next snapshot
+    fn reset(&mut self) {
+        self.orderbook.bids.clear();
+        self.orderbook.asks.clear();
+        self.seq_id = None;
+        self.last_top = None;
+    }
+
+    fn on_message(&mut self, message: &BookMessage) -> BookOutcome {
+        match message.action {
+            BookAction::Snapshot => {
+                self.orderbook.replace_levels(&message.bids, &message.asks);
+                self.last_top = None;
+            }
+            BookAction::Update => {
+                let Some(expected) = self.seq_id else {
+                    return BookOutcome::Ignored;
+                };
+                if message.prev_seq_id != expected {
+                    self.reset();
+                    return BookOutcome::Resync(ResyncReason::Sequence {
+                        expected,
+                        prev_seq_id: message.prev_seq_id,
+                    });
+                }
+                apply_levels(&mut self.orderbook.bids, &message.bids);
+                apply_levels(&mut self.orderbook.asks, &message.asks);
+            }
+        }
+        let actual = checksum(&self.orderbook);
+        if actual != message.checksum {
+            self.reset();
+            return BookOutcome::Resync(ResyncReason::Checksum {
+                expected: message.checksum,
+                actual,
+            });
+        }
+        self.orderbook.last_update_ts = Utc::now();
+        self.seq_id = Some(message.seq_id);
+        BookOutcome::Applied
+    }
+}
+
+/// OKX spot screener keeping a checksum-verified local book per symbol from the `books`
+/// channel, persisted as CEX market states
+pub struct OKXScreener {
+    config: OKXConfig,
+    shutdown: CancellationToken,
+    books: Mutex<HashMap<String, SymbolBook>>,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    supervisor: WsSupervisor,
+}
+
+impl OKXScreener {
+    /// Create a new OKXScreener instance on the symbols of `OKX_SYMBOLS`
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, OKXConfig::from_env()?))
+    }
+
+    pub fn with_config(db_pool: DatabasePool, config: OKXConfig) -> Self {
+        let cex_writer = CexMarketWriter::for_database(db_pool, "okx");
+        Self {
+            config,
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("OKX", EXCHANGE, STALE_FEED_TIMEOUT),
+        }
+    }
+
+    /// Stream the books until stopped; a dropped or silent connection is retried after an
+    /// exponential backoff and every book is rebuilt from the new subscription's snapshot
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting OKX screener for {:?} ({})...",
+            self.config.symbols, self.config.ws_url
+        );
+
+        self.supervisor.run(&self.shutdown, self).await;
+        self.cex_writer.flush().await;
+        info!("OKX screener stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Forget every book so each one waits for a new snapshot
+    fn reset_books(&self) {
+        *self.books.lock().unwrap() = self
+            .config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), SymbolBook::new(symbol)))
+            .collect();
+    }
+
+    /// Apply one text frame, adding the symbols whose book must be resubscribed to
+    /// `resubscribe`; returns whether it was a book message
+    fn handle_frame(&self, text: &str, resubscribe: &mut Vec<String>) -> bool {
+        let message = match parse_frame(text) {
+            Ok(OkxFrame::Book(message)) => message,
+            Ok(OkxFrame::Event { event, detail }) => {
+                if event == "error" {
+                    error!("OKX websocket error: {}", detail);
+                } else {
+                    info!("OKX websocket {}: {}", event, detail);
+                }
+                return false;
+            }
+            Ok(OkxFrame::Pong | OkxFrame::Other) => {
+                debug!("Skipping OKX frame: {}", text);
+                return false;
+            }
+            Err(e) => {
+                warn!("Skipping malformed OKX frame: {}", e);
+                return false;
+            }
+        };
+
+        let mut books = self.books.lock().unwrap();
+        // Messages of a symbol that is not streamed cannot be tracked
+        let Some(book) = books.get_mut(&message.symbol) else {
+            return true;
+        };
+        match book.on_message(&message) {
+            BookOutcome::Applied => {
+                if let Err(violation) = book.orderbook.validate() {
+                    error!(
+                        "OKX {} order book rejected, {}: {}",
+                        message.symbol,
+                        violation,
+                        book.orderbook.describe_top(5)
+                    );
+                    metrics::counter!(
+                        "okx_orderbook_resyncs_total",
+                        "symbol" => message.symbol.clone(),
+                        "reason" => violation.kind()
+                    )
+                    .increment(1);
+                    book.reset();
+                    resubscribe.push(message.symbol);
+                } else {
+                    self.persist(book, message.seq_id, message.ts_ms);
+                }
+            }
+            BookOutcome::Resync(reason) => {
+                warn!(
+                    "OKX {} order book out of sync at sequence {} ({}), resubscribing",
+                    message.symbol, message.seq_id, reason
+                );
+                metrics::counter!(
+                    "okx_orderbook_resyncs_total",
+                    "symbol" => message.symbol.clone(),
+                    "reason" => reason.kind()
+                )
+                .increment(1);
+                resubscribe.push(message.symbol);
+            }
+            BookOutcome::Ignored => {}
+        }
+        true
+    }
+
+    /// Persist the top of book when it changed
+    fn persist(&self, book: &mut SymbolBook, seq_id: i64, ts_ms: i64) {
+        let (Some(best_bid), Some(best_ask)) =
+            (book.orderbook.best_bid(), book.orderbook.best_ask())
+        else {
+            return;
+        };
+        let top = (best_bid, best_ask);
+        if book.last_top.as_ref() == Some(&top) {
+            return;
+        }
+        let (best_bid, best_ask) = top.clone();
+        book.last_top = Some(top);
+
+        let now = Utc::now();
+        let cex_state = market::CEXState {
+            trade_id: seq_id.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: book.orderbook.symbol.clone(),
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp_millis(ts_ms).unwrap_or(now),
+            fetch_time: now,
+            feed_latency_ms: Some((now.timestamp_millis() - ts_ms).max(0) as u64),
+            depth: Some(market::CEXDepth::from_book(&book.orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+    }
+}
+
+impl WsSession for OKXScreener {
+    /// Empty the books, connect, subscribe to every book and apply the stream until the connection
+    /// drops or the screener stops. `feed` counts the book messages delivered.
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        self.reset_books();
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.config.ws_url.as_str())
+            .await
+            .map_err(|e| format!("connect failed: {}", e))?;
+        ws.send(Message::Text(books_request(
+            "subscribe",
+            &self.config.symbols,
+        )))
+        .await
+        .map_err(|e| format!("subscribe failed: {}", e))?;
+        info!(
+            "OKX websocket connected, subscribed to {:?}",
+            self.config.symbols
+        );
+
+        let mut ping =
+            tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                _ = ping.tick() => {
+                    ws.send(Message::Text("ping".to_string()))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                reason = feed.stale() => return Err(reason),
+                frame = ws.next() => {
+                    feed.frame();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            let mut resubscribe = Vec::new();
+                            if self.handle_frame(&text, &mut resubscribe) {
+                                feed.delivered();
+                            }
+                            if !resubscribe.is_empty() {
+                                // The new subscription starts with a snapshot
+                                for op in ["unsubscribe", "subscribe"] {
+                                    ws.send(Message::Text(books_request(op, &resubscribe)))
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Ping(payload))) => {
+                            ws.send(Message::Pong(payload))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Err(format!("closed by server: {:?}", frame));
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "okx_tests.rs"]
+mod okx_tests;
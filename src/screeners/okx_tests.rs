@@ -0,0 +1,284 @@
+use super::*;
+use std::str::FromStr;
+
+use crate::screeners::test_support::{TestScreener, build_screener_with_sink};
+
+fn decimal(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+/// Books channel frames recorded in order for TRUMP-USDC, with their checksums
+const BOOK_FIXTURES: [&str; 3] = [
+    r#"{"arg":{"channel":"books","instId":"TRUMP-USDC"},"action":"snapshot","data":[{"asks":[["10.27","80","0","3"],["10.28","150.25","0","5"]],"bids":[["10.25","120.5","0","4"],["10.24","300","0","2"]],"ts":"1716863719100","checksum":-1251578382,"prevSeqId":-1,"seqId":1000}]}"#,
+    r#"{"arg":{"channel":"books","instId":"TRUMP-USDC"},"action":"update","data":[{"asks":[["10.26","5","0","1"]],"bids":[["10.25","100","0","3"]],"ts":"1716863719200","checksum":-1526121530,"prevSeqId":1000,"seqId":1001}]}"#,
+    r#"{"arg":{"channel":"books","instId":"TRUMP-USDC"},"action":"update","data":[{"asks":[["10.26","0","0","0"]],"bids":[["10.23","50","0","1"]],"ts":"1716863719300","checksum":-1984531463,"prevSeqId":1001,"seqId":1002}]}"#,
+];
+
+fn message(index: usize) -> BookMessage {
+    match parse_frame(BOOK_FIXTURES[index]).unwrap() {
+        OkxFrame::Book(message) => message,
+        other => panic!("not a book message: {:?}", other),
+    }
+}
+
+fn levels(levels: &[(&str, &str)]) -> market::OrderBookLevels {
+    levels
+        .iter()
+        .map(|(price, size)| (decimal(price), decimal(size)))
+        .collect()
+}
+
+fn book(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> market::OrderBook {
+    let mut orderbook = market::OrderBook::new(EXCHANGE, "TRUMPUSDC");
+    orderbook.bids = levels(bids);
+    orderbook.asks = levels(asks);
+    orderbook
+}
+
+impl TestScreener for OKXScreener {
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self {
+        let screener = OKXScreener {
+            config: OKXConfig {
+                symbols: vec!["TRUMPUSDC".to_string()],
+                ws_url: DEFAULT_WS_URL.to_string(),
+            },
+            shutdown: CancellationToken::new(),
+            books: Mutex::new(HashMap::new()),
+            cex_writer,
+            supervisor: WsSupervisor::new("OKX", EXCHANGE, STALE_FEED_TIMEOUT),
+        };
+        screener.reset_books();
+        screener
+    }
+}
+
+#[test]
+fn checksum_matches_the_okx_documentation_examples() {
+    // "3366.1:7:3366.8:9:3366:6:3368:8"
+    let deep = book(
+        &[("3366.1", "7"), ("3366", "6")],
+        &[("3366.8", "9"), ("3368", "8")],
+    );
+    assert_eq!(checksum(&deep), -1881014294);
+
+    // "3366.1:7:3366.8:9:3368:8:3372:8": the deeper side's levels follow the shallow one's
+    let uneven = book(
+        &[("3366.1", "7")],
+        &[("3366.8", "9"), ("3368", "8"), ("3372", "8")],
+    );
+    assert_eq!(checksum(&uneven), 831078360);
+}
+
+#[test]
+fn checksum_covers_only_the_top_25_levels_as_sent() {
+    let mut orderbook = book(&[("3366.1", "7")], &[("3366.8", "9")]);
+    let top = checksum(&orderbook);
+    for tick in 0..30 {
+        orderbook
+            .asks
+            .insert(Decimal::new(3370 + tick, 0), Decimal::ONE);
+    }
+    let with_depth = checksum(&orderbook);
+    assert_ne!(with_depth, top);
+    orderbook.asks.insert(decimal("3500"), Decimal::ONE);
+    assert_eq!(checksum(&orderbook), with_depth);
+
+    // Trailing zeros are part of the checksummed string
+    assert_ne!(
+        checksum(&book(&[("3366.10", "7")], &[("3366.8", "9")])),
+        top
+    );
+    let mut rescaled = book(&[("3366.10", "7")], &[("3366.8", "9")]);
+    apply_levels(&mut rescaled.bids, &vec![(decimal("3366.1"), decimal("7"))]);
+    assert_eq!(checksum(&rescaled), top);
+}
+
+#[test]
+fn book_frames_are_decoded_with_internal_symbols() {
+    assert_eq!(
+        message(1),
+        BookMessage {
+            symbol: "TRUMPUSDC".to_string(),
+            action: BookAction::Update,
+            bids: vec![(decimal("10.25"), decimal("100"))],
+            asks: vec![(decimal("10.26"), decimal("5"))],
+            ts_ms: 1716863719200,
+            checksum: -1526121530,
+            prev_seq_id: 1000,
+            seq_id: 1001,
+        }
+    );
+    assert_eq!(message(0).action, BookAction::Snapshot);
+
+    assert_eq!(parse_frame("pong"), Ok(OkxFrame::Pong));
+    assert_eq!(
+        parse_frame(
+            r#"{"event":"error","code":"60018","msg":"Wrong URL or channel:books,instId:NOPE-USDC doesn't exist.","connId":"a4d3ae55"}"#
+        ),
+        Ok(OkxFrame::Event {
+            event: "error".to_string(),
+            detail: "60018 Wrong URL or channel:books,instId:NOPE-USDC doesn't exist.".to_string()
+        })
+    );
+    assert!(matches!(
+        parse_frame(r#"{"event":"subscribe","arg":{"channel":"books","instId":"TRUMP-USDC"},"connId":"a4d3ae55"}"#),
+        Ok(OkxFrame::Event { event, .. }) if event == "subscribe"
+    ));
+    assert_eq!(
+        parse_frame(r#"{"arg":{"channel":"tickers","instId":"TRUMP-USDC"},"data":[]}"#),
+        Ok(OkxFrame::Other)
+    );
+    assert!(
+        parse_frame(&BOOK_FIXTURES[1].replace(r#""seqId":1001"#, r#""seqId":"1001""#)).is_err()
+    );
+    assert!(parse_frame(&BOOK_FIXTURES[1].replace(r#""update""#, r#""partial""#)).is_err());
+}
+
+#[test]
+fn symbols_and_requests_use_okx_instrument_ids() {
+    assert_eq!(
+        parse_symbols(" trumpusdc,TRUMPUSDT,TRUMPUSDC ").unwrap(),
+        ["TRUMPUSDC", "TRUMPUSDT"]
+    );
+    assert!(parse_symbols("TRUMPXYZ").is_err());
+    assert!(parse_symbols(",").is_err());
+
+    let request: Value = serde_json::from_str(&books_request(
+        "subscribe",
+        &["TRUMPUSDC".to_string(), "TRUMPUSDT".to_string()],
+    ))
+    .unwrap();
+    assert_eq!(
+        request,
+        json!({"op": "subscribe", "args": [
+            {"channel": "books", "instId": "TRUMP-USDC"},
+            {"channel": "books", "instId": "TRUMP-USDT"}
+        ]})
+    );
+}
+
+#[test]
+fn updates_follow_the_snapshot_sequence() {
+    let mut book = SymbolBook::new("TRUMPUSDC");
+    assert_eq!(book.on_message(&message(1)), BookOutcome::Ignored);
+
+    for index in 0..3 {
+        assert_eq!(book.on_message(&message(index)), BookOutcome::Applied);
+    }
+    assert_eq!(book.seq_id, Some(1002));
+    assert_eq!(
+        book.orderbook.bids,
+        levels(&[("10.23", "50"), ("10.24", "300"), ("10.25", "100")])
+    );
+    assert_eq!(
+        book.orderbook.asks,
+        levels(&[("10.27", "80"), ("10.28", "150.25")])
+    );
+}
+
+#[test]
+fn missed_updates_drop_the_book() {
+    let mut book = SymbolBook::new("TRUMPUSDC");
+    book.on_message(&message(0));
+
+    assert_eq!(
+        book.on_message(&message(2)),
+        BookOutcome::Resync(ResyncReason::Sequence {
+            expected: 1000,
+            prev_seq_id: 1001
+        })
+    );
+    assert_eq!(book.seq_id, None);
+    assert!(book.orderbook.bids.is_empty() && book.orderbook.asks.is_empty());
+    assert_eq!(book.on_message(&message(1)), BookOutcome::Ignored);
+    assert_eq!(book.on_message(&message(0)), BookOutcome::Applied);
+}
+
+#[test]
+fn checksum_mismatch_drops_the_book() {
+    let mut book = SymbolBook::new("TRUMPUSDC");
+    book.on_message(&message(0));
+    let mut corrupted = message(1);
+    corrupted.bids = vec![(decimal("10.25"), decimal("99"))];
+
+    assert_eq!(
+        book.on_message(&corrupted),
+        BookOutcome::Resync(ResyncReason::Checksum {
+            expected: -1526121530,
+            actual: checksum(&book_after(&corrupted)),
+        })
+    );
+    assert_eq!(book.seq_id, None);
+    assert!(book.orderbook.bids.is_empty());
+
+    let mut bad_snapshot = message(0);
+    bad_snapshot.checksum = 0;
+    assert!(matches!(
+        book.on_message(&bad_snapshot),
+        BookOutcome::Resync(ResyncReason::Checksum { expected: 0, .. })
+    ));
+}
+
+/// Book of the snapshot fixture with `update` applied
+fn book_after(update: &BookMessage) -> market::OrderBook {
+    let snapshot = message(0);
+    let mut orderbook = market::OrderBook::new(EXCHANGE, "TRUMPUSDC");
+    orderbook.bids = snapshot.bids.into_iter().collect();
+    orderbook.asks = snapshot.asks.into_iter().collect();
+    apply_levels(&mut orderbook.bids, &update.bids);
+    apply_levels(&mut orderbook.asks, &update.asks);
+    orderbook
+}
+
+#[tokio::test]
+async fn synced_books_are_persisted_as_okx_states() {
+    let (screener, sink) = build_screener_with_sink::<OKXScreener>();
+    let mut resubscribe = Vec::new();
+
+    for fixture in BOOK_FIXTURES {
+        assert!(screener.handle_frame(fixture, &mut resubscribe));
+        screener.cex_writer.flush().await;
+    }
+    assert!(!screener.handle_frame("pong", &mut resubscribe));
+
+    let states = sink.states.lock().unwrap().clone();
+    assert_eq!(states.len(), 3);
+    assert!(
+        states
+            .iter()
+            .all(|state| state.exchange == "okx" && state.trade_pair == "TRUMPUSDC")
+    );
+    assert_eq!(
+        states
+            .iter()
+            .map(|state| state.trade_id.as_str())
+            .collect::<Vec<_>>(),
+        ["1000", "1001", "1002"]
+    );
+    assert_eq!(
+        (states[1].bid_volume, states[1].ask_price),
+        (decimal("100"), decimal("10.26"))
+    );
+    assert_eq!(states[2].ask_price, decimal("10.27"));
+    assert!(resubscribe.is_empty());
+}
+
+#[tokio::test]
+async fn checksum_mismatch_resubscribes_the_symbol() {
+    let (screener, sink) = build_screener_with_sink::<OKXScreener>();
+    let mut resubscribe = Vec::new();
+
+    screener.handle_frame(BOOK_FIXTURES[0], &mut resubscribe);
+    let corrupted = BOOK_FIXTURES[1].replace("-1526121530", "123");
+    screener.handle_frame(&corrupted, &mut resubscribe);
+    assert_eq!(resubscribe, ["TRUMPUSDC"]);
+
+    // Updates are dropped until the new subscription's snapshot
+    resubscribe.clear();
+    screener.handle_frame(BOOK_FIXTURES[2], &mut resubscribe);
+    assert!(resubscribe.is_empty());
+    assert_eq!(screener.books.lock().unwrap()["TRUMPUSDC"].seq_id, None);
+
+    screener.cex_writer.flush().await;
+    assert_eq!(sink.states.lock().unwrap().len(), 1);
+}
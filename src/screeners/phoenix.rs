@@ -0,0 +1,555 @@
+//! Phoenix is an on-chain central limit order book, so unlike the AMM screeners it is read
+//! as an order book: each poll decodes the resting orders of the market account into a
+//! `market::OrderBook` and persists it like a CEX book, as an exchange `phoenix` `CEXState`
+//! (best bid and ask with their depth) through `CexMarketWriter`. The market sequence number
+//! is the `trade_id` and the Clock sysvar time of the read the `trade_time`.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::models::market::{self, OrderBook, OrderBookItem};
+use crate::screeners::meteora::{
+    MeteoraConfig, clock_drift_secs, is_clock_stale, max_clock_drift_from_env,
+    max_concurrent_pairs_from_env, poll_interval_from_env, run_poll_loop,
+};
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::store::db::DatabasePool;
+
+use super::cex_writer::CexMarketWriter;
+use super::symbols::split_symbol;
+
+/// Exchange name of the persisted rows
+const EXCHANGE: &str = "phoenix";
+/// Phoenix v1 program, owner of every market account
+pub const PHOENIX_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY");
+/// Markets polled when `PHOENIX_MARKETS` is unset
+const DEFAULT_MARKETS: &str = "SOLUSDC:4DoNfFBfF7UokCC2FQzriy7yHK6DY6NVdYpuekQ5pRgg";
+/// `MarketHeader` discriminant, `sha256("phoenix::program::accounts::MarketHeader")[..8]`
+const MARKET_DISCRIMINANT: u64 = 8167313896524341111;
+/// `MarketHeader` layout: status, the sizes of both trees, then the base and quote token
+/// params (decimals u32, vault bump u32, mint, vault), each followed by its lot size
+const STATUS_OFFSET: usize = 8;
+const BIDS_SIZE_OFFSET: usize = 16;
+const ASKS_SIZE_OFFSET: usize = 24;
+const BASE_DECIMALS_OFFSET: usize = 40;
+const BASE_MINT_OFFSET: usize = 48;
+const BASE_LOT_SIZE_OFFSET: usize = 112;
+const QUOTE_DECIMALS_OFFSET: usize = 120;
+const QUOTE_MINT_OFFSET: usize = 128;
+const QUOTE_LOT_SIZE_OFFSET: usize = 192;
+const TICK_SIZE_IN_QUOTE_ATOMS_OFFSET: usize = 200;
+const SEQUENCE_NUMBER_OFFSET: usize = 272;
+const RAW_BASE_UNITS_PER_BASE_UNIT_OFFSET: usize = 312;
+const MARKET_HEADER_LEN: usize = 576;
+/// `FIFOMarket` fields after the header and its 256 bytes of padding
+const BASE_LOTS_PER_BASE_UNIT_OFFSET: usize = MARKET_HEADER_LEN + 256;
+const TICK_SIZE_IN_QUOTE_LOTS_OFFSET: usize = BASE_LOTS_PER_BASE_UNIT_OFFSET + 8;
+const TAKER_FEE_BPS_OFFSET: usize = BASE_LOTS_PER_BASE_UNIT_OFFSET + 24;
+const BIDS_OFFSET: usize = BASE_LOTS_PER_BASE_UNIT_OFFSET + 48;
+/// Sokoban red-black tree: root u32 and 12 bytes of padding, then the node allocator's
+/// size u64, bump index u32 and free list head u32
+const TREE_HEADER_LEN: usize = 32;
+/// Tree node: left, right, parent and color registers (u32 each), the `FIFOOrderId` key
+/// (price in ticks, sequence number) and the `FIFORestingOrder` value (trader index, base
+/// lots, last valid slot, last valid unix timestamp)
+const NODE_LEN: usize = 64;
+const NODE_RIGHT_OFFSET: usize = 4;
+const NODE_PRICE_OFFSET: usize = 16;
+const NODE_BASE_LOTS_OFFSET: usize = 40;
+const NODE_LAST_VALID_SLOT_OFFSET: usize = 48;
+const NODE_LAST_VALID_TIMESTAMP_OFFSET: usize = 56;
+/// Node index meaning "no node"; real nodes are numbered from 1
+const SENTINEL: u32 = 0;
+/// Market statuses whose resting orders can be traded against, `Active` and `PostOnly`
+const STATUS_ACTIVE: u64 = 1;
+const STATUS_POST_ONLY: u64 = 2;
+
+/// Phoenix market polled under an internal symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoenixMarketConfig {
+    pub symbol: String,
+    pub market: Pubkey,
+}
+
+/// Markets of the Phoenix screener
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoenixConfig {
+    pub markets: Vec<PhoenixMarketConfig>,
+}
+
+impl PhoenixConfig {
+    /// Read `PHOENIX_MARKETS` (comma-separated `SYMBOL:MARKET_PUBKEY` entries), falling back
+    /// to the SOL/USDC market
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let markets =
+            std::env::var("PHOENIX_MARKETS").unwrap_or_else(|_| DEFAULT_MARKETS.to_string());
+        Ok(Self {
+            markets: parse_markets(&markets)?,
+        })
+    }
+}
+
+/// Parse comma-separated `SYMBOL:MARKET_PUBKEY` entries, each symbol and market once
+fn parse_markets(value: &str) -> Result<Vec<PhoenixMarketConfig>, Box<dyn std::error::Error>> {
+    let mut markets: Vec<PhoenixMarketConfig> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (symbol, market) = entry.split_once(':').ok_or_else(|| {
+            format!(
+                "PHOENIX_MARKETS entry `{}` is not SYMBOL:MARKET_PUBKEY",
+                entry
+            )
+        })?;
+        let symbol = symbol.trim().to_uppercase();
+        if split_symbol(&symbol).is_none() {
+            return Err(format!(
+                "PHOENIX_MARKETS entry `{}` has an unknown quote asset",
+                entry
+            )
+            .into());
+        }
+        let market = Pubkey::from_str(market.trim())
+            .map_err(|e| format!("PHOENIX_MARKETS entry `{}`: {}", entry, e))?;
+        if markets
+            .iter()
+            .any(|m| m.symbol == symbol || m.market == market)
+        {
+            return Err(format!("PHOENIX_MARKETS entry `{}` is configured twice", entry).into());
+        }
+        markets.push(PhoenixMarketConfig { symbol, market });
+    }
+    if markets.is_empty() {
+        return Err("PHOENIX_MARKETS has no market".into());
+    }
+    Ok(markets)
+}
+
+/// Header and `FIFOMarket` fields needed to turn lots and ticks into prices and sizes
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketParams {
+    pub status: u64,
+    pub bids_size: usize,
+    pub asks_size: usize,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+    /// Base atoms per base lot
+    pub base_lot_size: u64,
+    /// Quote atoms per quote lot
+    pub quote_lot_size: u64,
+    pub tick_size_in_quote_atoms_per_base_unit: u64,
+    /// Whole base tokens in one Phoenix base unit, 1 except for low-priced tokens
+    pub raw_base_units_per_base_unit: u32,
+    /// Incremented by every event of the market
+    pub sequence_number: u64,
+    pub taker_fee_bps: u64,
+}
+
+impl MarketParams {
+    /// Whether the resting orders can be traded against
+    pub fn is_tradable(&self) -> bool {
+        matches!(self.status, STATUS_ACTIVE | STATUS_POST_ONLY)
+    }
+
+    /// Quote tokens per whole base token of a price in ticks:
+    /// `ticks * tick_size_in_quote_atoms_per_base_unit / 10^quote_decimals / raw_base_units_per_base_unit`
+    pub fn price(&self, price_in_ticks: u64) -> Decimal {
+        let quote_atoms =
+            price_in_ticks as u128 * self.tick_size_in_quote_atoms_per_base_unit as u128;
+        Decimal::from_i128_with_scale(quote_atoms as i128, self.quote_decimals)
+            / Decimal::from(self.raw_base_units_per_base_unit)
+    }
+
+    /// Whole base tokens of a size in base lots: `lots * base_lot_size / 10^base_decimals`
+    pub fn size(&self, base_lots: u64) -> Decimal {
+        let base_atoms = base_lots as u128 * self.base_lot_size as u128;
+        Decimal::from_i128_with_scale(base_atoms as i128, self.base_decimals)
+    }
+}
+
+/// Order resting in one of the trees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestingOrder {
+    pub price_in_ticks: u64,
+    pub base_lots: u64,
+    /// Last slot the order can fill at, 0 when it does not expire by slot
+    pub last_valid_slot: u64,
+    /// Last unix time the order can fill at, 0 when it does not expire by time
+    pub last_valid_unix_timestamp: u64,
+}
+
+impl RestingOrder {
+    /// Whether the order can no longer fill at `clock`, as the program checks it
+    pub fn is_expired(&self, clock: &Clock) -> bool {
+        (self.last_valid_slot != 0 && self.last_valid_slot < clock.slot)
+            || (self.last_valid_unix_timestamp != 0
+                && (self.last_valid_unix_timestamp as i64) < clock.unix_timestamp)
+    }
+}
+
+/// Decoded market account
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoenixMarket {
+    pub params: MarketParams,
+    pub bids: Vec<RestingOrder>,
+    pub asks: Vec<RestingOrder>,
+}
+
+impl PhoenixMarket {
+    /// Order book of the orders still live at `clock`, with the base lots of each price
+    /// level summed and converted to whole tokens
+    pub fn to_orderbook(&self, symbol: &str, clock: &Clock) -> OrderBook {
+        let levels = |orders: &[RestingOrder]| {
+            let mut lots: BTreeMap<u64, u64> = BTreeMap::new();
+            for order in orders.iter().filter(|order| !order.is_expired(clock)) {
+                *lots.entry(order.price_in_ticks).or_default() += order.base_lots;
+            }
+            lots.into_iter()
+                .filter(|(_, lots)| *lots > 0)
+                .map(|(ticks, lots)| (self.params.price(ticks), self.params.size(lots)))
+                .collect::<Vec<_>>()
+        };
+        let mut orderbook = OrderBook::new(EXCHANGE, symbol);
+        orderbook.replace_levels(&levels(&self.bids), &levels(&self.asks));
+        orderbook
+    }
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn pubkey_at(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::try_from(&data[offset..offset + 32]).unwrap()
+}
+
+/// Length of a tree of `capacity` nodes
+fn tree_len(capacity: usize) -> usize {
+    TREE_HEADER_LEN + capacity * NODE_LEN
+}
+
+/// Decode the header, lot and tick sizes and both order trees of a market account
+pub fn decode_market(data: &[u8]) -> Result<PhoenixMarket, String> {
+    if data.len() < BIDS_OFFSET {
+        return Err(format!(
+            "account is {} bytes, expected at least {}",
+            data.len(),
+            BIDS_OFFSET
+        ));
+    }
+    if u64_at(data, 0) != MARKET_DISCRIMINANT {
+        return Err("account discriminant does not match MarketHeader".to_string());
+    }
+    let params = MarketParams {
+        status: u64_at(data, STATUS_OFFSET),
+        bids_size: u64_at(data, BIDS_SIZE_OFFSET) as usize,
+        asks_size: u64_at(data, ASKS_SIZE_OFFSET) as usize,
+        base_mint: pubkey_at(data, BASE_MINT_OFFSET),
+        quote_mint: pubkey_at(data, QUOTE_MINT_OFFSET),
+        base_decimals: u32_at(data, BASE_DECIMALS_OFFSET),
+        quote_decimals: u32_at(data, QUOTE_DECIMALS_OFFSET),
+        base_lot_size: u64_at(data, BASE_LOT_SIZE_OFFSET),
+        quote_lot_size: u64_at(data, QUOTE_LOT_SIZE_OFFSET),
+        tick_size_in_quote_atoms_per_base_unit: u64_at(data, TICK_SIZE_IN_QUOTE_ATOMS_OFFSET),
+        raw_base_units_per_base_unit: u32_at(data, RAW_BASE_UNITS_PER_BASE_UNIT_OFFSET),
+        sequence_number: u64_at(data, SEQUENCE_NUMBER_OFFSET),
+        taker_fee_bps: u64_at(data, TAKER_FEE_BPS_OFFSET),
+    };
+    // Decimals beyond 28 do not fit a Decimal scale, no SPL mint uses them
+    if params.base_decimals > 28 || params.quote_decimals > 28 {
+        return Err(format!(
+            "unsupported decimals {}/{}",
+            params.base_decimals, params.quote_decimals
+        ));
+    }
+    if params.base_lot_size == 0 || params.quote_lot_size == 0 {
+        return Err("lot size is zero".to_string());
+    }
+    if params.raw_base_units_per_base_unit == 0 {
+        return Err("raw base units per base unit is zero".to_string());
+    }
+    // The market stores the tick size in quote lots too; both must describe the same tick
+    let tick_size_in_quote_lots = u64_at(data, TICK_SIZE_IN_QUOTE_LOTS_OFFSET);
+    if tick_size_in_quote_lots.checked_mul(params.quote_lot_size)
+        != Some(params.tick_size_in_quote_atoms_per_base_unit)
+    {
+        return Err(format!(
+            "tick size of {} quote lots of {} atoms does not match {} quote atoms",
+            tick_size_in_quote_lots,
+            params.quote_lot_size,
+            params.tick_size_in_quote_atoms_per_base_unit
+        ));
+    }
+
+    let asks_offset = BIDS_OFFSET + tree_len(params.bids_size);
+    let min_len = asks_offset + tree_len(params.asks_size);
+    if data.len() < min_len {
+        return Err(format!(
+            "account is {} bytes, expected at least {} for {} bids and {} asks",
+            data.len(),
+            min_len,
+            params.bids_size,
+            params.asks_size
+        ));
+    }
+    let bids = decode_tree(&data[BIDS_OFFSET..asks_offset], params.bids_size)
+        .map_err(|e| format!("bids: {}", e))?;
+    let asks = decode_tree(&data[asks_offset..min_len], params.asks_size)
+        .map_err(|e| format!("asks: {}", e))?;
+    Ok(PhoenixMarket { params, bids, asks })
+}
+
+/// Orders of a tree, walked from its root so freed nodes are skipped
+fn decode_tree(tree: &[u8], capacity: usize) -> Result<Vec<RestingOrder>, String> {
+    let node_at = |index: u32| -> Result<&[u8], String> {
+        if index == SENTINEL || index as usize > capacity {
+            return Err(format!("node index {} out of 1..={}", index, capacity));
+        }
+        let offset = TREE_HEADER_LEN + (index as usize - 1) * NODE_LEN;
+        Ok(&tree[offset..offset + NODE_LEN])
+    };
+
+    let mut orders = Vec::new();
+    let mut pending = vec![u32_at(tree, 0)];
+    while let Some(index) = pending.pop() {
+        if index == SENTINEL {
+            continue;
+        }
+        // A well-formed tree never holds more nodes than its capacity
+        if orders.len() == capacity {
+            return Err(format!("more than {} nodes reachable, cycle?", capacity));
+        }
+        let node = node_at(index)?;
+        pending.push(u32_at(node, 0));
+        pending.push(u32_at(node, NODE_RIGHT_OFFSET));
+        orders.push(RestingOrder {
+            price_in_ticks: u64_at(node, NODE_PRICE_OFFSET),
+            base_lots: u64_at(node, NODE_BASE_LOTS_OFFSET),
+            last_valid_slot: u64_at(node, NODE_LAST_VALID_SLOT_OFFSET),
+            last_valid_unix_timestamp: u64_at(node, NODE_LAST_VALID_TIMESTAMP_OFFSET),
+        });
+    }
+    Ok(orders)
+}
+
+/// Polls Phoenix markets and persists their books as CEX market states
+pub struct PhoenixScreener {
+    pub config: PhoenixConfig,
+    pub rpc_client: FailoverRpcClient,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Number of markets read concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    /// Drift between the Clock sysvar and wall time above which a book is not persisted
+    pub max_clock_drift: Duration,
+    /// Batched writes of order book states
+    cex_writer: CexMarketWriter,
+    /// Best bid and ask last persisted per symbol
+    last_tops: Mutex<HashMap<String, (OrderBookItem, OrderBookItem)>>,
+}
+
+impl PhoenixScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(
+            db_pool,
+            MeteoraConfig::from_env()?,
+            PhoenixConfig::from_env()?,
+        ))
+    }
+
+    /// Build the screener on the shared RPC settings; market and Clock reads use
+    /// `rpc_config.commitment`
+    pub fn with_config(
+        db_pool: DatabasePool,
+        rpc_config: MeteoraConfig,
+        config: PhoenixConfig,
+    ) -> Self {
+        info!(
+            "Phoenix RPC endpoints: {}",
+            rpc_config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Self {
+            config,
+            rpc_client: FailoverRpcClient::from_urls(
+                rpc_config.rpc_endpoints,
+                rpc_config.commitment,
+            ),
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            max_clock_drift: max_clock_drift_from_env(),
+            cex_writer: CexMarketWriter::for_database(db_pool, "phoenix"),
+            last_tops: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Poll every configured market until the screener is stopped
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Phoenix screener for {:?} (poll interval {:?})...",
+            self.config
+                .markets
+                .iter()
+                .map(|m| m.symbol.as_str())
+                .collect::<Vec<_>>(),
+            self.poll_interval
+        );
+
+        run_poll_loop(
+            &self.shutdown,
+            self.poll_interval,
+            self.max_concurrent_pairs,
+            || {
+                self.config
+                    .markets
+                    .iter()
+                    .map(|m| m.symbol.clone())
+                    .collect()
+            },
+            |symbol| {
+                let screener = self.clone();
+                async move {
+                    screener
+                        .poll_market(&symbol)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+            },
+        )
+        .await;
+
+        self.cex_writer.flush().await;
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Read the market of `symbol` and submit its book when the best bid or ask changed
+    async fn poll_market(&self, symbol: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (market, clock) = self.fetch_market(symbol).await?;
+        let now = Utc::now();
+        if is_clock_stale(&clock, now, self.max_clock_drift) {
+            warn!(
+                "Clock sysvar at slot {} drifted {}s from wall time, skipping Phoenix {} book",
+                clock.slot,
+                clock_drift_secs(&clock, now),
+                symbol
+            );
+            return Ok(());
+        }
+        if !market.params.is_tradable() {
+            debug!(
+                "Phoenix {} market status is {}, skipping",
+                symbol, market.params.status
+            );
+            return Ok(());
+        }
+
+        let orderbook = market.to_orderbook(symbol, &clock);
+        if let Err(violation) = orderbook.validate() {
+            error!(
+                "Phoenix {} order book rejected, {}: {}",
+                symbol,
+                violation,
+                orderbook.describe_top(5)
+            );
+            metrics::counter!(
+                "phoenix_invalid_books_total",
+                "symbol" => symbol.to_string(),
+                "reason" => violation.kind()
+            )
+            .increment(1);
+            return Ok(());
+        }
+        let (Some(best_bid), Some(best_ask)) = (orderbook.best_bid(), orderbook.best_ask()) else {
+            debug!("Phoenix {} book has an empty side", symbol);
+            return Ok(());
+        };
+        let top = (best_bid.clone(), best_ask.clone());
+        {
+            let mut last_tops = self.last_tops.lock().unwrap();
+            if last_tops.get(symbol) == Some(&top) {
+                return Ok(());
+            }
+            last_tops.insert(symbol.to_string(), top);
+        }
+
+        let cex_state = market::CEXState {
+            trade_id: market.params.sequence_number.to_string(),
+            exchange: EXCHANGE.to_string(),
+            trade_pair: symbol.to_string(),
+            bid_price: best_bid.price,
+            bid_volume: best_bid.volume,
+            ask_price: best_ask.price,
+            ask_volume: best_ask.volume,
+            trade_time: DateTime::from_timestamp(clock.unix_timestamp, 0).unwrap_or(now),
+            fetch_time: now,
+            // The Clock only has second resolution, too coarse for a feed latency
+            feed_latency_ms: None,
+            depth: Some(market::CEXDepth::from_book(&orderbook)),
+        };
+        self.cex_writer.submit(cex_state);
+        Ok(())
+    }
+
+    /// Market account of `symbol` and the Clock, read in one round trip
+    async fn fetch_market(
+        &self,
+        symbol: &str,
+    ) -> Result<(PhoenixMarket, Clock), Box<dyn std::error::Error>> {
+        let market = self
+            .config
+            .markets
+            .iter()
+            .find(|m| m.symbol == symbol)
+            .ok_or_else(|| format!("No Phoenix market configured for {}", symbol))?
+            .market;
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[market, solana_sdk::sysvar::clock::ID])
+            .await?;
+        let [Some(market_account), Some(clock_account)] = accounts.as_slice() else {
+            return Err(format!("Phoenix market {} or Clock not found", market).into());
+        };
+        let decoded = decode_market_account(market_account)
+            .map_err(|e| format!("Invalid Phoenix market {} ({}): {}", market, symbol, e))?;
+        let clock: Clock = bincode::deserialize(&clock_account.data)?;
+        Ok((decoded, clock))
+    }
+}
+
+/// Decode a market account, which must be owned by the Phoenix program
+fn decode_market_account(account: &Account) -> Result<PhoenixMarket, String> {
+    if account.owner != PHOENIX_PROGRAM_ID {
+        return Err(format!("owned by {}, not Phoenix", account.owner));
+    }
+    decode_market(&account.data)
+}
+
+#[cfg(test)]
+#[path = "phoenix_tests.rs"]
+mod phoenix_tests;
@@ -0,0 +1,338 @@
+use super::*;
+
+const SOL_USDC_MARKET: &str = "4DoNfFBfF7UokCC2FQzriy7yHK6DY6NVdYpuekQ5pRgg";
+
+/// SOL/USDC market params: 0.001 SOL lots, 1e-6 USDC quote lots and a 0.001 USDC tick
+fn sol_usdc_params() -> MarketParams {
+    MarketParams {
+        status: STATUS_ACTIVE,
+        bids_size: 4,
+        asks_size: 4,
+        base_mint: Pubkey::new_unique(),
+        quote_mint: Pubkey::new_unique(),
+        base_decimals: 9,
+        quote_decimals: 6,
+        base_lot_size: 1_000_000,
+        quote_lot_size: 1,
+        tick_size_in_quote_atoms_per_base_unit: 1_000,
+        raw_base_units_per_base_unit: 1,
+        sequence_number: 123_456,
+        taker_fee_bps: 2,
+    }
+}
+
+fn order(price_in_ticks: u64, base_lots: u64) -> RestingOrder {
+    RestingOrder {
+        price_in_ticks,
+        base_lots,
+        last_valid_slot: 0,
+        last_valid_unix_timestamp: 0,
+    }
+}
+
+fn clock() -> Clock {
+    Clock {
+        slot: 1_000,
+        unix_timestamp: 1_700_000_000,
+        ..Clock::default()
+    }
+}
+
+/// Node of a tree fixture, `index` numbered from 1
+struct FixtureNode {
+    index: u32,
+    left: u32,
+    right: u32,
+    order: RestingOrder,
+}
+
+fn write_tree(data: &mut [u8], offset: usize, root: u32, nodes: &[FixtureNode]) {
+    let root = if nodes.is_empty() { SENTINEL } else { root };
+    data[offset..offset + 4].copy_from_slice(&root.to_le_bytes());
+    data[offset + 16..offset + 24].copy_from_slice(&(nodes.len() as u64).to_le_bytes());
+    for node in nodes {
+        let start = offset + TREE_HEADER_LEN + (node.index as usize - 1) * NODE_LEN;
+        let fields: [(usize, &[u8]); 6] = [
+            (0, &node.left.to_le_bytes()),
+            (NODE_RIGHT_OFFSET, &node.right.to_le_bytes()),
+            (NODE_PRICE_OFFSET, &node.order.price_in_ticks.to_le_bytes()),
+            (NODE_BASE_LOTS_OFFSET, &node.order.base_lots.to_le_bytes()),
+            (
+                NODE_LAST_VALID_SLOT_OFFSET,
+                &node.order.last_valid_slot.to_le_bytes(),
+            ),
+            (
+                NODE_LAST_VALID_TIMESTAMP_OFFSET,
+                &node.order.last_valid_unix_timestamp.to_le_bytes(),
+            ),
+        ];
+        for (field_offset, bytes) in fields {
+            data[start + field_offset..start + field_offset + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+}
+
+/// Market account laid out as the program stores it (header, `FIFOMarket` fields, then the
+/// bids and asks trees), shrunk to 4 orders per side. Built from the program's layout, not
+/// captured from mainnet, so it pins the offsets this decoder relies on.
+fn market_account_data(
+    params: &MarketParams,
+    bids: &[FixtureNode],
+    asks: &[FixtureNode],
+) -> Vec<u8> {
+    let asks_offset = BIDS_OFFSET + tree_len(params.bids_size);
+    let mut data = vec![0u8; asks_offset + tree_len(params.asks_size)];
+    for (offset, value) in [
+        (0, MARKET_DISCRIMINANT),
+        (STATUS_OFFSET, params.status),
+        (BIDS_SIZE_OFFSET, params.bids_size as u64),
+        (ASKS_SIZE_OFFSET, params.asks_size as u64),
+        (BASE_LOT_SIZE_OFFSET, params.base_lot_size),
+        (QUOTE_LOT_SIZE_OFFSET, params.quote_lot_size),
+        (
+            TICK_SIZE_IN_QUOTE_ATOMS_OFFSET,
+            params.tick_size_in_quote_atoms_per_base_unit,
+        ),
+        (SEQUENCE_NUMBER_OFFSET, params.sequence_number),
+        (
+            TICK_SIZE_IN_QUOTE_LOTS_OFFSET,
+            params.tick_size_in_quote_atoms_per_base_unit / params.quote_lot_size,
+        ),
+        (TAKER_FEE_BPS_OFFSET, params.taker_fee_bps),
+    ] {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    for (offset, value) in [
+        (BASE_DECIMALS_OFFSET, params.base_decimals),
+        (QUOTE_DECIMALS_OFFSET, params.quote_decimals),
+        (
+            RAW_BASE_UNITS_PER_BASE_UNIT_OFFSET,
+            params.raw_base_units_per_base_unit,
+        ),
+    ] {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    data[BASE_MINT_OFFSET..BASE_MINT_OFFSET + 32].copy_from_slice(params.base_mint.as_ref());
+    data[QUOTE_MINT_OFFSET..QUOTE_MINT_OFFSET + 32].copy_from_slice(params.quote_mint.as_ref());
+    write_tree(&mut data, BIDS_OFFSET, 1, bids);
+    write_tree(&mut data, asks_offset, 2, asks);
+    data
+}
+
+/// Bids at 150.120 (two orders), 150.100 and an order expired by slot at 150.150; asks at
+/// 150.130 and 150.200 rooted at node 2, with node 3 freed and unreachable
+fn fixture_data(params: &MarketParams) -> Vec<u8> {
+    let bids = [
+        FixtureNode {
+            index: 1,
+            left: 2,
+            right: 3,
+            order: order(150_120, 2_500),
+        },
+        FixtureNode {
+            index: 2,
+            left: 0,
+            right: 4,
+            order: order(150_100, 1_000),
+        },
+        FixtureNode {
+            index: 3,
+            left: 0,
+            right: 0,
+            order: RestingOrder {
+                last_valid_slot: 999,
+                ..order(150_150, 7_000)
+            },
+        },
+        FixtureNode {
+            index: 4,
+            left: 0,
+            right: 0,
+            order: order(150_120, 500),
+        },
+    ];
+    let asks = [
+        FixtureNode {
+            index: 1,
+            left: 0,
+            right: 0,
+            order: order(150_200, 40_000),
+        },
+        FixtureNode {
+            index: 2,
+            left: 0,
+            right: 1,
+            order: order(150_130, 1_250),
+        },
+        FixtureNode {
+            index: 3,
+            left: 0,
+            right: 0,
+            order: order(1, 1),
+        },
+    ];
+    market_account_data(params, &bids, &asks)
+}
+
+#[test]
+fn decode_market_reads_params_and_reachable_orders() {
+    let params = sol_usdc_params();
+
+    let market = decode_market(&fixture_data(&params)).unwrap();
+
+    assert_eq!(market.params, params);
+    let mut bid_ticks: Vec<u64> = market.bids.iter().map(|o| o.price_in_ticks).collect();
+    bid_ticks.sort();
+    assert_eq!(bid_ticks, vec![150_100, 150_120, 150_120, 150_150]);
+    let mut asks = market.asks.clone();
+    asks.sort_by_key(|o| o.price_in_ticks);
+    assert_eq!(asks, vec![order(150_130, 1_250), order(150_200, 40_000)]);
+}
+
+#[test]
+fn to_orderbook_sums_levels_and_drops_expired_orders() {
+    let market = decode_market(&fixture_data(&sol_usdc_params())).unwrap();
+
+    let orderbook = market.to_orderbook("SOLUSDC", &clock());
+
+    assert_eq!(orderbook.exchange, "phoenix");
+    let dec = |value: &str| value.parse::<Decimal>().unwrap();
+    assert_eq!(
+        orderbook.bid_levels().collect::<Vec<_>>(),
+        vec![
+            OrderBookItem {
+                price: dec("150.12"),
+                volume: dec("3"),
+            },
+            OrderBookItem {
+                price: dec("150.1"),
+                volume: dec("1"),
+            },
+        ]
+    );
+    assert_eq!(
+        orderbook.best_ask(),
+        Some(OrderBookItem {
+            price: dec("150.13"),
+            volume: dec("1.25"),
+        })
+    );
+    assert!(orderbook.validate().is_ok());
+}
+
+#[test]
+fn orders_expire_by_slot_or_time() {
+    let clock = clock();
+    let by_slot = |slot| RestingOrder {
+        last_valid_slot: slot,
+        ..order(1, 1)
+    };
+    let by_time = |timestamp| RestingOrder {
+        last_valid_unix_timestamp: timestamp,
+        ..order(1, 1)
+    };
+
+    assert!(!order(1, 1).is_expired(&clock));
+    assert!(!by_slot(1_000).is_expired(&clock));
+    assert!(by_slot(999).is_expired(&clock));
+    assert!(!by_time(1_700_000_000).is_expired(&clock));
+    assert!(by_time(1_699_999_999).is_expired(&clock));
+}
+
+#[test]
+fn price_and_size_follow_the_market_units() {
+    // A BONK-like market: 5 decimals, base units of 1M tokens and a tick of 10 quote atoms
+    // per base unit
+    let params = MarketParams {
+        base_decimals: 5,
+        base_lot_size: 1_000,
+        tick_size_in_quote_atoms_per_base_unit: 10,
+        raw_base_units_per_base_unit: 1_000_000,
+        ..sol_usdc_params()
+    };
+
+    // 2_345 ticks of 10 quote atoms for a million tokens
+    assert_eq!(
+        params.price(2_345),
+        "0.00000002345".parse::<Decimal>().unwrap()
+    );
+    // 7 lots of 1_000 atoms at 5 decimals
+    assert_eq!(params.size(7), "0.07".parse::<Decimal>().unwrap());
+    assert_eq!(
+        sol_usdc_params().price(150_123),
+        "150.123".parse::<Decimal>().unwrap()
+    );
+    assert_eq!(sol_usdc_params().size(2_500), Decimal::new(25, 1));
+}
+
+#[test]
+fn decode_market_rejects_other_accounts() {
+    let params = sol_usdc_params();
+    let data = fixture_data(&params);
+    let mut wrong_discriminant = data.clone();
+    wrong_discriminant[0] ^= 1;
+    let mut mismatched_tick = data.clone();
+    mismatched_tick[TICK_SIZE_IN_QUOTE_LOTS_OFFSET] ^= 1;
+
+    assert!(decode_market(&data[..data.len() - 1]).is_err());
+    assert!(decode_market(&data[..BIDS_OFFSET - 1]).is_err());
+    assert!(decode_market(&wrong_discriminant).is_err());
+    assert!(decode_market(&mismatched_tick).is_err());
+}
+
+#[test]
+fn decode_market_rejects_broken_trees() {
+    let params = sol_usdc_params();
+    let mut out_of_range = fixture_data(&params);
+    out_of_range[BIDS_OFFSET..BIDS_OFFSET + 4].copy_from_slice(&5u32.to_le_bytes());
+    let cycle = market_account_data(
+        &params,
+        &[FixtureNode {
+            index: 1,
+            left: 1,
+            right: 0,
+            order: order(150_000, 1),
+        }],
+        &[],
+    );
+
+    assert!(decode_market(&out_of_range).is_err());
+    assert!(decode_market(&cycle).is_err());
+}
+
+#[test]
+fn decode_market_account_checks_the_owner() {
+    let data = fixture_data(&sol_usdc_params());
+    let account = |owner| Account {
+        data: data.clone(),
+        owner,
+        ..Account::default()
+    };
+
+    assert!(decode_market_account(&account(PHOENIX_PROGRAM_ID)).is_ok());
+    assert!(decode_market_account(&account(Pubkey::new_unique())).is_err());
+}
+
+#[test]
+fn parse_markets_reads_symbol_and_market_entries() {
+    let markets = parse_markets(&format!(" solusdc:{} ", SOL_USDC_MARKET)).unwrap();
+
+    assert_eq!(
+        markets,
+        vec![PhoenixMarketConfig {
+            symbol: "SOLUSDC".to_string(),
+            market: Pubkey::from_str(SOL_USDC_MARKET).unwrap(),
+        }]
+    );
+    assert!(parse_markets("").is_err());
+    assert!(parse_markets(SOL_USDC_MARKET).is_err());
+    assert!(parse_markets("SOLUSDC:not-a-pubkey").is_err());
+    assert!(parse_markets(&format!("SOLXYZ:{}", SOL_USDC_MARKET)).is_err());
+    assert!(
+        parse_markets(&format!(
+            "SOLUSDC:{},SOLUSDT:{}",
+            SOL_USDC_MARKET, SOL_USDC_MARKET
+        ))
+        .is_err()
+    );
+}
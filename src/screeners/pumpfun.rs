@@ -0,0 +1,778 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::screeners::dex_runner::{DexQuoter, DexScreenerRunner, get_best_price};
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora::{
+    BestPriceQuote, MeteoraConfig, PoolConfig, PoolLiquidity, PriceQuote, SwapQuote, TradeConfig,
+    clock_drift_secs, derive_bid_ask, fee_pct, is_clock_stale, max_clock_drift_from_env,
+    max_concurrent_pairs_from_env, max_quote_age_from_env, mint_decimals, normalized_price,
+    pairs_refresh_interval_from_env, poll_interval_from_env, price_impact_bps, select_best_price,
+    successful_pool_results,
+};
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::solana::utils::token_account_amount;
+use crate::store::db::DatabasePool;
+
+/// Venue name of Pump.fun pairs in the trade_pairs table, also used as the DEX market exchange
+const VENUE: &str = "pumpfun";
+/// Pump.fun bonding curve program
+pub const PUMP_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+/// PumpSwap AMM program, where completed curves migrate to
+pub const PUMP_AMM_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+/// Wrapped SOL, the quote token of every curve and migrated pool
+const WSOL_MINT: Pubkey = Pubkey::from_str_const("So11111111111111111111111111111111111111112");
+const SOL_DECIMALS: u32 = 9;
+/// Anchor discriminators (`sha256("account:<Name>")[..8]`) of the accounts we decode
+const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+const GLOBAL_DISCRIMINATOR: [u8; 8] = [167, 232, 232, 177, 200, 108, 114, 127];
+const AMM_POOL_DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+const GLOBAL_CONFIG_DISCRIMINATOR: [u8; 8] = [149, 8, 156, 202, 160, 252, 176, 217];
+/// `BondingCurve` layout: four u64 reserves, the token supply, `complete`, then the creator
+/// on curves created since creator fees exist
+const VIRTUAL_TOKEN_RESERVES_OFFSET: usize = 8;
+const VIRTUAL_SOL_RESERVES_OFFSET: usize = 16;
+const REAL_TOKEN_RESERVES_OFFSET: usize = 24;
+const REAL_SOL_RESERVES_OFFSET: usize = 32;
+const COMPLETE_OFFSET: usize = 48;
+const CURVE_CREATOR_OFFSET: usize = 49;
+const BONDING_CURVE_MIN_LEN: usize = COMPLETE_OFFSET + 1;
+/// Offsets of the protocol and creator fees in the Pump.fun `Global` account
+const FEE_BASIS_POINTS_OFFSET: usize = 105;
+const CREATOR_FEE_BASIS_POINTS_OFFSET: usize = 154;
+const GLOBAL_MIN_LEN: usize = CREATOR_FEE_BASIS_POINTS_OFFSET + 8;
+/// Offsets of the mints, token accounts and coin creator in the PumpSwap `Pool` account
+const POOL_BASE_MINT_OFFSET: usize = 43;
+const POOL_QUOTE_MINT_OFFSET: usize = 75;
+const POOL_BASE_TOKEN_ACCOUNT_OFFSET: usize = 139;
+const POOL_QUOTE_TOKEN_ACCOUNT_OFFSET: usize = 171;
+const POOL_COIN_CREATOR_OFFSET: usize = 211;
+const AMM_POOL_MIN_LEN: usize = POOL_COIN_CREATOR_OFFSET + 32;
+/// Offsets of the fees in the PumpSwap `GlobalConfig` account, around the 8 protocol fee
+/// recipients
+const LP_FEE_BASIS_POINTS_OFFSET: usize = 40;
+const PROTOCOL_FEE_BASIS_POINTS_OFFSET: usize = 48;
+const COIN_CREATOR_FEE_BASIS_POINTS_OFFSET: usize = 313;
+const GLOBAL_CONFIG_MIN_LEN: usize = COIN_CREATOR_FEE_BASIS_POINTS_OFFSET + 8;
+/// Index of the canonical pool a completed curve migrates to
+const CANONICAL_POOL_INDEX: u16 = 0;
+const BASIS_POINTS: u128 = 10_000;
+
+/// Where a Pump.fun token trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PumpMarket {
+    /// Its bonding curve account, until the curve completes
+    BondingCurve(Pubkey),
+    /// The canonical PumpSwap pool it migrated to
+    Amm(Pubkey),
+}
+
+impl PumpMarket {
+    /// Market of a token not quoted yet: its bonding curve
+    pub fn of_mint(mint: &Pubkey) -> Self {
+        Self::BondingCurve(bonding_curve_address(mint))
+    }
+
+    pub fn address(&self) -> Pubkey {
+        match self {
+            Self::BondingCurve(address) | Self::Amm(address) => *address,
+        }
+    }
+}
+
+/// Market of a token after reading its bonding curve: the migrated pool once the curve is
+/// complete, the curve otherwise. A completed curve no longer trades.
+fn market_after_curve(mint: &Pubkey, curve: &BondingCurve) -> PumpMarket {
+    if curve.complete {
+        PumpMarket::Amm(canonical_pool_address(mint))
+    } else {
+        PumpMarket::of_mint(mint)
+    }
+}
+
+/// Fields of a Pump.fun bonding curve needed to quote it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondingCurve {
+    /// Reserves the curve prices against
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    /// Tokens the curve can still sell and SOL it can pay out
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    /// Set once the curve sold out; the token then migrates to PumpSwap
+    pub complete: bool,
+    /// Creator paid the creator fee, default for curves without one
+    pub creator: Pubkey,
+}
+
+/// Fields of a PumpSwap pool needed to quote it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmmPool {
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub pool_base_token_account: Pubkey,
+    pub pool_quote_token_account: Pubkey,
+    /// Creator paid the coin creator fee, default when none
+    pub coin_creator: Pubkey,
+}
+
+/// Outcome of reading a bonding curve
+enum CurveRead {
+    Trading(PumpSnapshot),
+    /// The curve completed, so the token is quoted on its PumpSwap pool instead
+    Complete(BondingCurve),
+}
+
+/// Reserves and fee schedule of a token's current market, read at the same slot
+#[derive(Debug, Clone)]
+pub struct PumpSnapshot {
+    pub mint: Pubkey,
+    pub market: PumpMarket,
+    /// Token and SOL reserves swaps are priced against: the virtual reserves on the curve,
+    /// the pool balances on the AMM
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    /// Token and SOL actually held, reported as the pool liquidity
+    pub real_base_reserve: u64,
+    pub real_quote_reserve: u64,
+    /// Fee components in basis points, each rounded up on its own when taken from SOL output
+    pub fee_bps: [u64; 3],
+    pub base_decimals: u32,
+    pub clock: Clock,
+    /// Commitment the accounts were read at
+    pub commitment: CommitmentLevel,
+    /// Whether the Clock drifted from wall time by more than the allowed drift
+    pub stale: bool,
+    /// Wall time the account fetch started at
+    pub fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of the account round trips
+    pub fetch_latency: Duration,
+}
+
+impl PumpSnapshot {
+    fn block_time(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.clock.unix_timestamp, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+/// Polls Pump.fun tokens on their bonding curve, then on their PumpSwap pool once they
+/// migrated, and persists their best bid and ask in SOL
+pub struct PumpFunScreener {
+    pub db_pool: DatabasePool,
+    pub rpc_client: FailoverRpcClient,
+    /// Commitment of curve, pool, config, mint and Clock reads
+    pub commitment: CommitmentConfig,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Delay between two reloads of the trade pairs table
+    pub pairs_refresh_interval: Duration,
+    /// Number of pairs quoted concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    /// Drift between the Clock sysvar and wall time above which a quote is tagged stale
+    pub max_clock_drift: Duration,
+    /// Age above which a best quote is rejected instead of being returned or persisted
+    pub max_quote_age: Duration,
+    /// Trade configs loaded from the database, keyed by symbol; `pool_pubkey` holds the token
+    /// mint, always the base token, quoted in SOL
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    /// Market of every token quoted so far, switched to the AMM when its curve completes
+    markets: RwLock<HashMap<Pubkey, PumpMarket>>,
+    /// Decimals of every mint quoted so far
+    mint_decimals: RwLock<HashMap<Pubkey, u32>>,
+}
+
+impl PumpFunScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, MeteoraConfig::from_env()?))
+    }
+
+    /// Build the screener on already resolved RPC settings, shared with the Meteora screeners.
+    /// Every account read uses `config.commitment`.
+    pub fn with_config(db_pool: DatabasePool, config: MeteoraConfig) -> Self {
+        info!(
+            "Pump.fun RPC endpoints: {}",
+            config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Self {
+            db_pool,
+            rpc_client: FailoverRpcClient::from_urls(config.rpc_endpoints, config.commitment),
+            commitment: config.commitment,
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            pairs_refresh_interval: pairs_refresh_interval_from_env(),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            max_clock_drift: max_clock_drift_from_env(),
+            max_quote_age: max_quote_age_from_env(),
+            trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+            markets: RwLock::new(HashMap::new()),
+            mint_decimals: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Poll quotes for every configured token until the screener is stopped
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Pump.fun screener (poll interval {:?})...",
+            self.poll_interval
+        );
+
+        DexScreenerRunner {
+            quoter: self.clone(),
+            db_pool: self.db_pool.clone(),
+            dex_writer: Arc::new(DexMarketWriter::for_database(self.db_pool.clone(), VENUE)),
+            shutdown: self.shutdown.clone(),
+            poll_interval: self.poll_interval,
+            pairs_refresh_interval: self.pairs_refresh_interval,
+            max_concurrent_pairs: self.max_concurrent_pairs,
+            max_quote_age: self.max_quote_age,
+            trade_pairs: self.trade_pairs.clone(),
+            // Pump.fun pairs are never auto-discovered
+            discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+        }
+        .run()
+        .await
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Quote selling `amount_in` token and buying it back with the SOL received, on the
+    /// current market of every configured mint of a pair
+    pub async fn get_price(
+        &self,
+        symbol: &str,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        get_best_price(
+            self,
+            &self.trade_pairs,
+            symbol,
+            amount_in,
+            self.max_quote_age,
+        )
+        .await
+    }
+
+    async fn get_pool_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let snapshot = self.fetch_snapshot(pool.pool_pubkey).await?;
+        let sell = quote_sell(&snapshot, amount_in)?;
+        if sell.amount_out == 0 {
+            return Err(format!("Market returned no output when selling {}", symbol).into());
+        }
+        let buy = quote_buy(&snapshot, sell.amount_out)?;
+        Ok(build_price_quote(symbol, &snapshot, sell, buy))
+    }
+
+    /// Read the token's current market. A curve found complete switches the token to its
+    /// PumpSwap pool for good, which is then read in the same poll.
+    async fn fetch_snapshot(
+        &self,
+        mint: Pubkey,
+    ) -> Result<PumpSnapshot, Box<dyn std::error::Error>> {
+        let market = self
+            .markets
+            .read()
+            .unwrap()
+            .get(&mint)
+            .copied()
+            .unwrap_or_else(|| PumpMarket::of_mint(&mint));
+        if let PumpMarket::Amm(pool) = market {
+            return self.fetch_amm_snapshot(mint, pool).await;
+        }
+
+        match self.fetch_curve_snapshot(mint).await? {
+            CurveRead::Trading(snapshot) => Ok(snapshot),
+            CurveRead::Complete(curve) => {
+                let market = market_after_curve(&mint, &curve);
+                info!(
+                    "Pump.fun bonding curve of {} is complete, quoting its PumpSwap pool {} from now on",
+                    mint,
+                    market.address()
+                );
+                self.markets.write().unwrap().insert(mint, market);
+                self.fetch_amm_snapshot(mint, market.address()).await
+            }
+        }
+    }
+
+    /// Read the bonding curve with the Pump.fun fees, the mint and the Clock in one round trip
+    async fn fetch_curve_snapshot(
+        &self,
+        mint: Pubkey,
+    ) -> Result<CurveRead, Box<dyn std::error::Error>> {
+        let fetched_at = Utc::now();
+        let fetch_started = Instant::now();
+        let curve_address = bonding_curve_address(&mint);
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[
+                curve_address,
+                global_address(),
+                mint,
+                solana_sdk::sysvar::clock::ID,
+            ])
+            .await?;
+        let [curve_account, global_account, mint_account, clock_account] = accounts.as_slice()
+        else {
+            return Err("Unexpected number of Pump.fun accounts".into());
+        };
+        let required = |account: &Option<Account>, name: &str| {
+            account
+                .clone()
+                .ok_or_else(|| format!("Pump.fun {} of {} not found", name, mint))
+        };
+        let curve = decode_bonding_curve(&required(curve_account, "bonding curve")?.data)
+            .map_err(|e| format!("Invalid Pump.fun bonding curve {}: {}", curve_address, e))?;
+        if curve.complete {
+            return Ok(CurveRead::Complete(curve));
+        }
+        let (fee_bps, creator_fee_bps) =
+            decode_global(&required(global_account, "global")?.data)
+                .map_err(|e| format!("Invalid Pump.fun global account: {}", e))?;
+        let base_decimals = self.cached_mint_decimals(mint, mint_account)?;
+        let (clock, stale) = self.read_clock(&required(clock_account, "clock")?, &mint)?;
+
+        // Curves without a creator do not charge the creator fee
+        let creator_fee_bps = if curve.creator == Pubkey::default() {
+            0
+        } else {
+            creator_fee_bps
+        };
+        Ok(CurveRead::Trading(PumpSnapshot {
+            mint,
+            market: PumpMarket::BondingCurve(curve_address),
+            base_reserve: curve.virtual_token_reserves,
+            quote_reserve: curve.virtual_sol_reserves,
+            real_base_reserve: curve.real_token_reserves,
+            real_quote_reserve: curve.real_sol_reserves,
+            fee_bps: [fee_bps, creator_fee_bps, 0],
+            base_decimals,
+            clock,
+            commitment: self.commitment.commitment,
+            stale,
+            fetched_at,
+            fetch_latency: fetch_started.elapsed(),
+        }))
+    }
+
+    /// Read the PumpSwap pool with its fee config, the mint and the Clock, then both pool
+    /// token accounts in a second round trip
+    async fn fetch_amm_snapshot(
+        &self,
+        mint: Pubkey,
+        pool: Pubkey,
+    ) -> Result<PumpSnapshot, Box<dyn std::error::Error>> {
+        let fetched_at = Utc::now();
+        let fetch_started = Instant::now();
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[
+                pool,
+                global_config_address(),
+                mint,
+                solana_sdk::sysvar::clock::ID,
+            ])
+            .await?;
+        let [pool_account, config_account, mint_account, clock_account] = accounts.as_slice()
+        else {
+            return Err("Unexpected number of PumpSwap accounts".into());
+        };
+        let required = |account: &Option<Account>, name: &str| {
+            account
+                .clone()
+                .ok_or_else(|| format!("PumpSwap {} of {} not found", name, mint))
+        };
+        // Right after a curve completes the pool may not be created yet; the next poll retries
+        let state = decode_amm_pool(&required(pool_account, "pool")?.data)
+            .map_err(|e| format!("Invalid PumpSwap pool {}: {}", pool, e))?;
+        if state.base_mint != mint || state.quote_mint != WSOL_MINT {
+            return Err(
+                format!("PumpSwap pool {} does not trade {} against SOL", pool, mint).into(),
+            );
+        }
+        let [lp_fee_bps, protocol_fee_bps, coin_creator_fee_bps] =
+            decode_global_config(&required(config_account, "global config")?.data)
+                .map_err(|e| format!("Invalid PumpSwap global config: {}", e))?;
+        let base_decimals = self.cached_mint_decimals(mint, mint_account)?;
+        let (clock, stale) = self.read_clock(&required(clock_account, "clock")?, &mint)?;
+
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[
+                state.pool_base_token_account,
+                state.pool_quote_token_account,
+            ])
+            .await?;
+        let [base_account, quote_account] = accounts.as_slice() else {
+            return Err("Unexpected number of PumpSwap accounts".into());
+        };
+        let base_reserve = token_account_amount(&required(base_account, "pool base account")?)?;
+        let quote_reserve = token_account_amount(&required(quote_account, "pool quote account")?)?;
+
+        // Pools without a coin creator do not charge the creator fee
+        let coin_creator_fee_bps = if state.coin_creator == Pubkey::default() {
+            0
+        } else {
+            coin_creator_fee_bps
+        };
+        Ok(PumpSnapshot {
+            mint,
+            market: PumpMarket::Amm(pool),
+            base_reserve,
+            quote_reserve,
+            real_base_reserve: base_reserve,
+            real_quote_reserve: quote_reserve,
+            fee_bps: [lp_fee_bps, protocol_fee_bps, coin_creator_fee_bps],
+            base_decimals,
+            clock,
+            commitment: self.commitment.commitment,
+            stale,
+            fetched_at,
+            fetch_latency: fetch_started.elapsed(),
+        })
+    }
+
+    /// Clock of a snapshot and whether it drifted from wall time
+    fn read_clock(
+        &self,
+        clock_account: &Account,
+        mint: &Pubkey,
+    ) -> Result<(Clock, bool), Box<dyn std::error::Error>> {
+        let clock: Clock = bincode::deserialize(&clock_account.data)?;
+        let now = Utc::now();
+        let stale = is_clock_stale(&clock, now, self.max_clock_drift);
+        if stale {
+            warn!(
+                "Clock sysvar at slot {} drifted {}s from wall time, tagging Pump.fun {} quote as stale",
+                clock.slot,
+                clock_drift_secs(&clock, now),
+                mint
+            );
+        }
+        Ok((clock, stale))
+    }
+
+    /// Decimals of a mint, unpacked from its account on first use
+    fn cached_mint_decimals(
+        &self,
+        mint: Pubkey,
+        mint_account: &Option<Account>,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        if let Some(decimals) = self.mint_decimals.read().unwrap().get(&mint) {
+            return Ok(*decimals);
+        }
+        let mint_account = mint_account
+            .as_ref()
+            .ok_or_else(|| format!("Mint {} not found", mint))?;
+        let decimals = mint_decimals(mint_account)
+            .map_err(|e| format!("Failed to read decimals of mint {}: {}", mint, e))?;
+        self.mint_decimals.write().unwrap().insert(mint, decimals);
+        Ok(decimals)
+    }
+}
+
+#[async_trait]
+impl DexQuoter for PumpFunScreener {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            self.get_pool_price(symbol, pool, amount_in)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+        .await;
+        let quotes = successful_pool_results(symbol, &trade_config.pools, results);
+
+        select_best_price(symbol, quotes)
+            .ok_or_else(|| format!("No Pump.fun market could quote {}", symbol).into())
+    }
+
+    /// Log prices with 9 decimals, most tokens trading far below a millionth of a SOL
+    fn on_quote(&self, best: &mut BestPriceQuote) {
+        info!(
+            "[pumpfun] {} best bid={:.9} ({}) best ask={:.9} ({}) across {} markets",
+            best.symbol,
+            best.bid.bid_price,
+            best.bid.pool,
+            best.ask.ask_price,
+            best.ask.pool,
+            best.pools_quoted,
+        );
+    }
+}
+
+/// Bonding curve account of a Pump.fun token
+pub fn bonding_curve_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_PROGRAM_ID).0
+}
+
+fn global_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"global"], &PUMP_PROGRAM_ID).0
+}
+
+fn global_config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"global_config"], &PUMP_AMM_PROGRAM_ID).0
+}
+
+/// Canonical PumpSwap pool a completed curve migrates to, created by the Pump.fun pool
+/// authority of the mint against SOL
+pub fn canonical_pool_address(mint: &Pubkey) -> Pubkey {
+    let pool_authority =
+        Pubkey::find_program_address(&[b"pool-authority", mint.as_ref()], &PUMP_PROGRAM_ID).0;
+    Pubkey::find_program_address(
+        &[
+            b"pool",
+            &CANONICAL_POOL_INDEX.to_le_bytes(),
+            pool_authority.as_ref(),
+            mint.as_ref(),
+            WSOL_MINT.as_ref(),
+        ],
+        &PUMP_AMM_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Check an account's length and Anchor discriminator before decoding it
+fn check_account(
+    data: &[u8],
+    discriminator: [u8; 8],
+    min_len: usize,
+    name: &str,
+) -> Result<(), String> {
+    if data.len() < min_len {
+        return Err(format!(
+            "account is {} bytes, expected at least {}",
+            data.len(),
+            min_len
+        ));
+    }
+    if data[..8] != discriminator {
+        return Err(format!("account discriminator does not match {}", name));
+    }
+    Ok(())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn pubkey_at(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::try_from(&data[offset..offset + 32]).unwrap()
+}
+
+fn decode_bonding_curve(data: &[u8]) -> Result<BondingCurve, String> {
+    check_account(
+        data,
+        BONDING_CURVE_DISCRIMINATOR,
+        BONDING_CURVE_MIN_LEN,
+        "BondingCurve",
+    )?;
+    let creator = if data.len() >= CURVE_CREATOR_OFFSET + 32 {
+        pubkey_at(data, CURVE_CREATOR_OFFSET)
+    } else {
+        Pubkey::default()
+    };
+    Ok(BondingCurve {
+        virtual_token_reserves: u64_at(data, VIRTUAL_TOKEN_RESERVES_OFFSET),
+        virtual_sol_reserves: u64_at(data, VIRTUAL_SOL_RESERVES_OFFSET),
+        real_token_reserves: u64_at(data, REAL_TOKEN_RESERVES_OFFSET),
+        real_sol_reserves: u64_at(data, REAL_SOL_RESERVES_OFFSET),
+        complete: data[COMPLETE_OFFSET] != 0,
+        creator,
+    })
+}
+
+/// Protocol and creator fees of the Pump.fun `Global` account, in basis points
+fn decode_global(data: &[u8]) -> Result<(u64, u64), String> {
+    check_account(data, GLOBAL_DISCRIMINATOR, GLOBAL_MIN_LEN, "Global")?;
+    Ok((
+        u64_at(data, FEE_BASIS_POINTS_OFFSET),
+        u64_at(data, CREATOR_FEE_BASIS_POINTS_OFFSET),
+    ))
+}
+
+fn decode_amm_pool(data: &[u8]) -> Result<AmmPool, String> {
+    check_account(data, AMM_POOL_DISCRIMINATOR, AMM_POOL_MIN_LEN, "Pool")?;
+    Ok(AmmPool {
+        base_mint: pubkey_at(data, POOL_BASE_MINT_OFFSET),
+        quote_mint: pubkey_at(data, POOL_QUOTE_MINT_OFFSET),
+        pool_base_token_account: pubkey_at(data, POOL_BASE_TOKEN_ACCOUNT_OFFSET),
+        pool_quote_token_account: pubkey_at(data, POOL_QUOTE_TOKEN_ACCOUNT_OFFSET),
+        coin_creator: pubkey_at(data, POOL_COIN_CREATOR_OFFSET),
+    })
+}
+
+/// LP, protocol and coin creator fees of the PumpSwap `GlobalConfig`, in basis points
+fn decode_global_config(data: &[u8]) -> Result<[u64; 3], String> {
+    check_account(
+        data,
+        GLOBAL_CONFIG_DISCRIMINATOR,
+        GLOBAL_CONFIG_MIN_LEN,
+        "GlobalConfig",
+    )?;
+    Ok([
+        u64_at(data, LP_FEE_BASIS_POINTS_OFFSET),
+        u64_at(data, PROTOCOL_FEE_BASIS_POINTS_OFFSET),
+        u64_at(data, COIN_CREATOR_FEE_BASIS_POINTS_OFFSET),
+    ])
+}
+
+fn check_reserves(snapshot: &PumpSnapshot) -> Result<(), String> {
+    if snapshot.base_reserve == 0 || snapshot.quote_reserve == 0 {
+        return Err(format!(
+            "Pump.fun market {} has an empty reserve",
+            snapshot.market.address()
+        ));
+    }
+    Ok(())
+}
+
+/// Quote selling `amount_in` token for SOL as both programs do: constant-product output on
+/// the reserves, less each fee component rounded up. The fee is reported in token units so
+/// `fee_pct` compares with the buy side.
+fn quote_sell(
+    snapshot: &PumpSnapshot,
+    amount_in: u64,
+) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    check_reserves(snapshot)?;
+    let gross_out = (amount_in as u128 * snapshot.quote_reserve as u128
+        / (snapshot.base_reserve as u128 + amount_in as u128)) as u64;
+    if gross_out > snapshot.real_quote_reserve {
+        return Err(format!(
+            "Pump.fun market {} holds {} lamports, less than the {} sold for",
+            snapshot.market.address(),
+            snapshot.real_quote_reserve,
+            gross_out
+        )
+        .into());
+    }
+    let fee_out: u64 = snapshot
+        .fee_bps
+        .iter()
+        .map(|bps| (gross_out as u128 * *bps as u128).div_ceil(BASIS_POINTS) as u64)
+        .sum();
+    let fee = if gross_out == 0 {
+        0
+    } else {
+        (fee_out as u128 * amount_in as u128 / gross_out as u128) as u64
+    };
+    Ok(SwapQuote::without_transfer_fees(
+        amount_in,
+        gross_out.saturating_sub(fee_out),
+        fee,
+    ))
+}
+
+/// Quote buying token with `amount_in` SOL as both programs do: the fees are carved out of
+/// the input, and the curve cannot sell more than its real token reserves
+fn quote_buy(
+    snapshot: &PumpSnapshot,
+    amount_in: u64,
+) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    check_reserves(snapshot)?;
+    let total_fee_bps: u128 = snapshot.fee_bps.iter().map(|bps| *bps as u128).sum();
+    let amount_in_after_fee = amount_in as u128 * BASIS_POINTS / (BASIS_POINTS + total_fee_bps);
+    let amount_out = (amount_in_after_fee * snapshot.base_reserve as u128
+        / (snapshot.quote_reserve as u128 + amount_in_after_fee)) as u64;
+    Ok(SwapQuote::without_transfer_fees(
+        amount_in,
+        amount_out.min(snapshot.real_base_reserve),
+        amount_in - amount_in_after_fee as u64,
+    ))
+}
+
+/// SOL per token implied by the reserves, before fees and price impact
+fn spot_price(snapshot: &PumpSnapshot) -> Option<Decimal> {
+    let price = normalized_price(
+        snapshot.base_reserve,
+        snapshot.quote_reserve,
+        snapshot.base_decimals,
+        SOL_DECIMALS,
+    );
+    (price > Decimal::ZERO).then_some(price)
+}
+
+fn build_price_quote(
+    symbol: &str,
+    snapshot: &PumpSnapshot,
+    sell: SwapQuote,
+    buy: SwapQuote,
+) -> PriceQuote {
+    let (base_decimals, quote_decimals) = (snapshot.base_decimals, SOL_DECIMALS);
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, base_decimals, quote_decimals);
+    let spot_price = spot_price(snapshot);
+
+    PriceQuote {
+        symbol: symbol.to_string(),
+        pool: snapshot.market.address(),
+        route: None,
+        hops: Vec::new(),
+        slot: snapshot.clock.slot,
+        block_time: snapshot.block_time(),
+        fetched_at: snapshot.fetched_at,
+        fetch_latency: snapshot.fetch_latency,
+        commitment: snapshot.commitment,
+        stale: snapshot.stale,
+        missing_bin_arrays: 0,
+        base_decimals,
+        quote_decimals,
+        bid_impact_bps: spot_price.and_then(|spot| price_impact_bps(bid_price, spot)),
+        ask_impact_bps: spot_price.and_then(|spot| price_impact_bps(ask_price, spot)),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: None,
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price,
+        liquidity: PoolLiquidity::new(
+            snapshot.real_base_reserve as u128,
+            snapshot.real_quote_reserve as u128,
+            base_decimals,
+            quote_decimals,
+            spot_price,
+        ),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+#[cfg(test)]
+#[path = "pumpfun_tests.rs"]
+mod pumpfun_tests;
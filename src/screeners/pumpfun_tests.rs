@@ -0,0 +1,279 @@
+use super::*;
+
+/// Curve a few trades after launch: 1.073B virtual tokens against 30 virtual SOL, with the
+/// 0.95% protocol and 0.05% creator fees
+fn fixture_curve() -> BondingCurve {
+    BondingCurve {
+        virtual_token_reserves: 1_073_000_000_000_000,
+        virtual_sol_reserves: 30_000_000_000,
+        real_token_reserves: 793_100_000_000_000,
+        real_sol_reserves: 5_000_000_000,
+        complete: false,
+        creator: Pubkey::new_unique(),
+    }
+}
+
+fn snapshot(market: PumpMarket, reserves: [u64; 4], fee_bps: [u64; 3]) -> PumpSnapshot {
+    let [
+        base_reserve,
+        quote_reserve,
+        real_base_reserve,
+        real_quote_reserve,
+    ] = reserves;
+    PumpSnapshot {
+        mint: Pubkey::new_unique(),
+        market,
+        base_reserve,
+        quote_reserve,
+        real_base_reserve,
+        real_quote_reserve,
+        fee_bps,
+        base_decimals: 6,
+        clock: Clock {
+            slot: 42,
+            unix_timestamp: 1_700_000_000,
+            ..Clock::default()
+        },
+        commitment: CommitmentLevel::Confirmed,
+        stale: false,
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(40),
+    }
+}
+
+fn curve_snapshot() -> PumpSnapshot {
+    let curve = fixture_curve();
+    snapshot(
+        PumpMarket::BondingCurve(Pubkey::new_unique()),
+        [
+            curve.virtual_token_reserves,
+            curve.virtual_sol_reserves,
+            curve.real_token_reserves,
+            curve.real_sol_reserves,
+        ],
+        [95, 5, 0],
+    )
+}
+
+/// 200M tokens against 400 SOL, with the 0.20% LP, 0.05% protocol and 0.05% creator fees
+fn amm_snapshot() -> PumpSnapshot {
+    snapshot(
+        PumpMarket::Amm(Pubkey::new_unique()),
+        [
+            200_000_000_000_000,
+            400_000_000_000,
+            200_000_000_000_000,
+            400_000_000_000,
+        ],
+        [20, 5, 5],
+    )
+}
+
+fn bonding_curve_data(curve: &BondingCurve) -> Vec<u8> {
+    let mut data = vec![0u8; 82];
+    data[..8].copy_from_slice(&BONDING_CURVE_DISCRIMINATOR);
+    for (offset, value) in [
+        (VIRTUAL_TOKEN_RESERVES_OFFSET, curve.virtual_token_reserves),
+        (VIRTUAL_SOL_RESERVES_OFFSET, curve.virtual_sol_reserves),
+        (REAL_TOKEN_RESERVES_OFFSET, curve.real_token_reserves),
+        (REAL_SOL_RESERVES_OFFSET, curve.real_sol_reserves),
+    ] {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    data[COMPLETE_OFFSET] = curve.complete as u8;
+    data[CURVE_CREATOR_OFFSET..CURVE_CREATOR_OFFSET + 32].copy_from_slice(curve.creator.as_ref());
+    data
+}
+
+#[test]
+fn decode_bonding_curve_reads_reserves_and_creator() {
+    let curve = fixture_curve();
+
+    assert_eq!(
+        decode_bonding_curve(&bonding_curve_data(&curve)).unwrap(),
+        curve
+    );
+}
+
+#[test]
+fn decode_bonding_curve_defaults_the_creator_of_old_curves() {
+    let curve = fixture_curve();
+    let data = bonding_curve_data(&curve);
+
+    let decoded = decode_bonding_curve(&data[..BONDING_CURVE_MIN_LEN]).unwrap();
+
+    assert_eq!(decoded.creator, Pubkey::default());
+    assert_eq!(decoded.virtual_sol_reserves, curve.virtual_sol_reserves);
+}
+
+#[test]
+fn decode_bonding_curve_rejects_other_accounts() {
+    let data = bonding_curve_data(&fixture_curve());
+    let mut wrong_discriminator = data.clone();
+    wrong_discriminator[0] ^= 1;
+
+    assert!(decode_bonding_curve(&data[..BONDING_CURVE_MIN_LEN - 1]).is_err());
+    assert!(decode_bonding_curve(&wrong_discriminator).is_err());
+}
+
+#[test]
+fn a_trading_curve_keeps_the_token_on_its_curve() {
+    let mint = Pubkey::new_unique();
+    let curve = decode_bonding_curve(&bonding_curve_data(&fixture_curve())).unwrap();
+
+    assert_eq!(
+        market_after_curve(&mint, &curve),
+        PumpMarket::BondingCurve(bonding_curve_address(&mint))
+    );
+}
+
+#[test]
+fn a_complete_curve_switches_the_token_to_its_canonical_pool() {
+    let mint = Pubkey::new_unique();
+    let complete = BondingCurve {
+        real_token_reserves: 0,
+        real_sol_reserves: 0,
+        complete: true,
+        ..fixture_curve()
+    };
+    let curve = decode_bonding_curve(&bonding_curve_data(&complete)).unwrap();
+
+    let market = market_after_curve(&mint, &curve);
+
+    assert_eq!(market, PumpMarket::Amm(canonical_pool_address(&mint)));
+    assert_ne!(market.address(), bonding_curve_address(&mint));
+    assert_ne!(
+        canonical_pool_address(&mint),
+        canonical_pool_address(&Pubkey::new_unique())
+    );
+}
+
+#[test]
+fn decode_amm_pool_reads_token_accounts_and_coin_creator() {
+    let pool = AmmPool {
+        base_mint: Pubkey::new_unique(),
+        quote_mint: WSOL_MINT,
+        pool_base_token_account: Pubkey::new_unique(),
+        pool_quote_token_account: Pubkey::new_unique(),
+        coin_creator: Pubkey::new_unique(),
+    };
+    let mut data = vec![0u8; 300];
+    data[..8].copy_from_slice(&AMM_POOL_DISCRIMINATOR);
+    for (offset, pubkey) in [
+        (POOL_BASE_MINT_OFFSET, pool.base_mint),
+        (POOL_QUOTE_MINT_OFFSET, pool.quote_mint),
+        (POOL_BASE_TOKEN_ACCOUNT_OFFSET, pool.pool_base_token_account),
+        (
+            POOL_QUOTE_TOKEN_ACCOUNT_OFFSET,
+            pool.pool_quote_token_account,
+        ),
+        (POOL_COIN_CREATOR_OFFSET, pool.coin_creator),
+    ] {
+        data[offset..offset + 32].copy_from_slice(pubkey.as_ref());
+    }
+
+    assert_eq!(decode_amm_pool(&data).unwrap(), pool);
+    assert!(decode_amm_pool(&data[..AMM_POOL_MIN_LEN - 1]).is_err());
+}
+
+#[test]
+fn fee_accounts_decode_their_basis_points() {
+    let mut global = vec![0u8; 300];
+    global[..8].copy_from_slice(&GLOBAL_DISCRIMINATOR);
+    global[FEE_BASIS_POINTS_OFFSET..FEE_BASIS_POINTS_OFFSET + 8]
+        .copy_from_slice(&95u64.to_le_bytes());
+    global[CREATOR_FEE_BASIS_POINTS_OFFSET..CREATOR_FEE_BASIS_POINTS_OFFSET + 8]
+        .copy_from_slice(&5u64.to_le_bytes());
+    let mut config = vec![0u8; 400];
+    config[..8].copy_from_slice(&GLOBAL_CONFIG_DISCRIMINATOR);
+    for (offset, bps) in [
+        (LP_FEE_BASIS_POINTS_OFFSET, 20u64),
+        (PROTOCOL_FEE_BASIS_POINTS_OFFSET, 5),
+        (COIN_CREATOR_FEE_BASIS_POINTS_OFFSET, 5),
+    ] {
+        config[offset..offset + 8].copy_from_slice(&bps.to_le_bytes());
+    }
+
+    assert_eq!(decode_global(&global).unwrap(), (95, 5));
+    assert_eq!(decode_global_config(&config).unwrap(), [20, 5, 5]);
+    assert!(decode_global(&config).is_err());
+    assert!(decode_global_config(&global).is_err());
+}
+
+#[test]
+fn curve_buy_carves_the_fees_out_of_the_sol_in() {
+    // 1 SOL: 1e9 * 10_000 / 10_100 = 990_099_009 lamports reach the curve
+    let buy = quote_buy(&curve_snapshot(), 1_000_000_000).unwrap();
+
+    assert_eq!(buy.fee, 9_900_991);
+    // 990_099_009 * 1.073e15 / (3e10 + 990_099_009)
+    assert_eq!(buy.amount_out, 34_281_150_129_545);
+}
+
+#[test]
+fn curve_buy_is_capped_by_the_real_token_reserves() {
+    let mut snapshot = curve_snapshot();
+    snapshot.real_base_reserve = 100_000_000_000_000;
+
+    let buy = quote_buy(&snapshot, 100_000_000_000).unwrap();
+
+    assert_eq!(buy.amount_out, 100_000_000_000_000);
+}
+
+#[test]
+fn curve_sell_rounds_each_fee_up() {
+    // 1M tokens: 1e12 * 3e10 / (1.073e15 + 1e12) = 27_932_960 lamports, less 265_364 and
+    // 13_967 lamports of fees
+    let sell = quote_sell(&curve_snapshot(), 1_000_000_000_000).unwrap();
+
+    assert_eq!(sell.amount_out, 27_653_629);
+    // The 279_331 lamports of fees, in token units
+    assert_eq!(sell.fee, 10_000_050_120);
+}
+
+#[test]
+fn curve_sell_fails_beyond_the_real_sol_reserves() {
+    let mut snapshot = curve_snapshot();
+    snapshot.real_quote_reserve = 1_000_000;
+
+    assert!(quote_sell(&snapshot, 1_000_000_000_000).is_err());
+}
+
+#[test]
+fn amm_quotes_apply_lp_protocol_and_creator_fees() {
+    let snapshot = amm_snapshot();
+
+    // 1M tokens: 1e12 * 4e11 / (2e14 + 1e12) = 1_990_049_751 lamports less 3 fees rounded up
+    let sell = quote_sell(&snapshot, 1_000_000_000_000).unwrap();
+    assert_eq!(sell.amount_out, 1_984_079_601);
+    assert_eq!(sell.fee, 3_000_000_375);
+
+    // 1 SOL: 1e9 * 10_000 / 10_030 = 997_008_973 lamports reach the pool
+    let buy = quote_buy(&snapshot, 1_000_000_000).unwrap();
+    assert_eq!(buy.fee, 2_991_027);
+    assert_eq!(buy.amount_out, 497_265_042_227);
+}
+
+#[test]
+fn quotes_reject_empty_reserves() {
+    let mut snapshot = amm_snapshot();
+    snapshot.quote_reserve = 0;
+
+    assert!(quote_sell(&snapshot, 1_000).is_err());
+    assert!(quote_buy(&snapshot, 1_000).is_err());
+}
+
+#[test]
+fn build_price_quote_brackets_the_curve_price() {
+    let snapshot = curve_snapshot();
+    let sell = quote_sell(&snapshot, 1_000_000_000_000).unwrap();
+    let buy = quote_buy(&snapshot, sell.amount_out).unwrap();
+
+    let quote = build_price_quote("PUMPSOL", &snapshot, sell, buy);
+
+    let spot = quote.spot_price.unwrap();
+    assert!(quote.bid_price < spot && spot < quote.ask_price);
+    assert_eq!(quote.pool, snapshot.market.address());
+    assert_eq!(quote.quote_decimals, SOL_DECIMALS);
+    assert_eq!(quote.sell_fee_pct.round_dp(2), Decimal::ONE);
+}
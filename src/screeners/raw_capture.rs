@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, info};
+use tracing_appender::non_blocking::{NonBlocking, NonBlockingBuilder, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// Directory of the capture files when `BYBIT_CAPTURE_DIR` is unset
+const DEFAULT_CAPTURE_DIR: &str = "logs/capture";
+
+/// Websocket message as received, one JSON line of a capture file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedFrame {
+    /// Local time the message was received, in milliseconds
+    pub received_ms: i64,
+    pub topic: String,
+    /// `snapshot` or `delta`
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    /// Exchange timestamp of the message, in milliseconds
+    pub ts: u64,
+    pub data: Value,
+}
+
+/// Appends captured frames to JSON lines files rotated every hour
+pub struct RawCapture {
+    writer: Mutex<NonBlocking>,
+    /// Writes out the queued lines when the capture is dropped
+    _guard: WorkerGuard,
+    /// Whether a failed write was already reported
+    write_failed: AtomicBool,
+}
+
+impl RawCapture {
+    /// Capture into `dir`, one `{prefix}.YYYY-MM-DD-HH.jsonl` file per hour
+    pub fn open(dir: &Path, prefix: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(dir)?;
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::HOURLY)
+            .filename_prefix(prefix)
+            .filename_suffix("jsonl")
+            .build(dir)?;
+        // A dropped line would break the replay of the file, so writes wait for room instead
+        let (writer, guard) = NonBlockingBuilder::default().lossy(false).finish(appender);
+        Ok(Self {
+            writer: Mutex::new(writer),
+            _guard: guard,
+            write_failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Capture under `BYBIT_CAPTURE_DIR` (`logs/capture` by default) when
+    /// `BYBIT_CAPTURE_RAW` is true; a directory that cannot be created disables the capture
+    pub fn from_env(prefix: &str) -> Option<Self> {
+        let enabled = std::env::var("BYBIT_CAPTURE_RAW")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let dir =
+            std::env::var("BYBIT_CAPTURE_DIR").unwrap_or_else(|_| DEFAULT_CAPTURE_DIR.to_string());
+        match Self::open(Path::new(&dir), prefix) {
+            Ok(capture) => {
+                info!("Capturing raw {} messages under {}", prefix, dir);
+                Some(capture)
+            }
+            Err(e) => {
+                error!("Raw message capture under {} disabled: {}", dir, e);
+                None
+            }
+        }
+    }
+
+    /// Append a frame as one line
+    pub fn write(&self, frame: &CapturedFrame) {
+        let mut line = match serde_json::to_string(frame) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize captured {} frame: {}", frame.topic, e);
+                return;
+            }
+        };
+        line.push('\n');
+        if let Err(e) = self.writer.lock().unwrap().write_all(line.as_bytes())
+            && !self.write_failed.swap(true, Ordering::Relaxed)
+        {
+            error!("Failed to write captured frames: {}", e);
+        }
+    }
+}
+
+/// Frames of a capture file in the order they were received
+pub fn read_capture(path: &Path) -> Result<Vec<CapturedFrame>, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("cannot open capture {}: {}", path.display(), e))?;
+    let mut frames = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "malformed frame on line {} of {}: {}",
+                index + 1,
+                path.display(),
+                e
+            )
+        })?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+#[path = "raw_capture_tests.rs"]
+mod raw_capture_tests;
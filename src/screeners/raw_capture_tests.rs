@@ -0,0 +1,72 @@
+use super::*;
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Empty directory of its own under the system temp dir
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zero-r-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn frame(update_id: u64) -> CapturedFrame {
+    CapturedFrame {
+        received_ms: 1_700_000_000_100 + update_id as i64,
+        topic: "orderbook.50.TRUMPUSDC".to_string(),
+        msg_type: "delta".to_string(),
+        ts: 1_700_000_000_000 + update_id,
+        data: json!({"s": "TRUMPUSDC", "u": update_id, "b": [["100.5", "1"]], "a": []}),
+    }
+}
+
+#[test]
+fn captured_frames_are_read_back_in_order() {
+    let dir = scratch_dir("capture");
+    let capture = RawCapture::open(&dir, "bybit-raw").unwrap();
+    for update_id in 1..=3 {
+        capture.write(&frame(update_id));
+    }
+    // Dropping the capture writes out the queued lines
+    drop(capture);
+
+    let files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(
+        name.starts_with("bybit-raw.") && name.ends_with(".jsonl"),
+        "{}",
+        name
+    );
+    assert_eq!(
+        read_capture(&files[0]).unwrap(),
+        [frame(1), frame(2), frame(3)]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn captured_frames_keep_bybit_field_names() {
+    let line = serde_json::to_value(frame(7)).unwrap();
+
+    assert_eq!(line["type"], "delta");
+    assert_eq!(line["topic"], "orderbook.50.TRUMPUSDC");
+    assert_eq!(line["data"]["u"], 7);
+}
+
+#[test]
+fn read_capture_reports_the_malformed_line() {
+    let dir = scratch_dir("malformed-capture");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bybit-raw.jsonl");
+    let valid = serde_json::to_string(&frame(1)).unwrap();
+    std::fs::write(&path, format!("{}\n\n{{\"topic\":\n", valid)).unwrap();
+
+    let error = read_capture(&path).unwrap_err().to_string();
+
+    assert!(error.contains("line 3"), "{}", error);
+    assert!(read_capture(&dir.join("missing.jsonl")).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,536 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::screeners::dex_runner::{DexQuoter, DexScreenerRunner, get_best_price};
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora::{
+    BestPriceQuote, MeteoraConfig, PoolConfig, PoolLiquidity, PriceQuote, SwapQuote, TradeConfig,
+    clock_drift_secs, derive_bid_ask, fee_pct, is_clock_stale, max_clock_drift_from_env,
+    max_concurrent_pairs_from_env, max_quote_age_from_env, normalized_price,
+    pairs_refresh_interval_from_env, poll_interval_from_env, price_impact_bps, select_best_price,
+    successful_pool_results,
+};
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::solana::utils::token_account_amount;
+use crate::store::db::DatabasePool;
+
+/// Venue name of Raydium AMM v4 pairs in the trade_pairs table, also used as the DEX market exchange
+const VENUE: &str = "raydium_amm";
+/// Raydium liquidity pool v4 program, owner of the AMM accounts
+pub const AMM_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+/// Size of the `AmmInfo` account, which has no discriminator
+const AMM_INFO_LEN: usize = 752;
+/// Offsets of the `AmmInfo` fields needed to quote a pool: the leading u64 parameters, the
+/// fees, the PnL owed to the protocol out of the vaults, and the vault keys
+const STATUS_OFFSET: usize = 0;
+const COIN_DECIMALS_OFFSET: usize = 32;
+const PC_DECIMALS_OFFSET: usize = 40;
+const SWAP_FEE_NUMERATOR_OFFSET: usize = 176;
+const SWAP_FEE_DENOMINATOR_OFFSET: usize = 184;
+const NEED_TAKE_PNL_COIN_OFFSET: usize = 192;
+const NEED_TAKE_PNL_PC_OFFSET: usize = 200;
+const POOL_OPEN_TIME_OFFSET: usize = 224;
+const COIN_VAULT_OFFSET: usize = 336;
+const PC_VAULT_OFFSET: usize = 368;
+const COIN_MINT_OFFSET: usize = 400;
+const PC_MINT_OFFSET: usize = 432;
+/// `AmmStatus` values under which the program accepts swaps; `WaitingTrade` only once the
+/// pool open time has passed
+const STATUS_INITIALIZED: u64 = 1;
+const STATUS_SWAP_ONLY: u64 = 6;
+const STATUS_WAITING_TRADE: u64 = 7;
+
+/// Fields of a Raydium AMM v4 pool account needed to quote it. The coin is the pool's
+/// first token and pc its quote token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmmPoolState {
+    pub status: u64,
+    pub coin_decimals: u32,
+    pub pc_decimals: u32,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    /// Protocol PnL still held in the vaults, not part of the swappable reserves
+    pub need_take_pnl_coin: u64,
+    pub need_take_pnl_pc: u64,
+    /// Unix time trading opens at, for pools still `WaitingTrade`
+    pub pool_open_time: u64,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+}
+
+impl AmmPoolState {
+    fn swap_enabled(&self, clock: &Clock) -> bool {
+        match self.status {
+            STATUS_INITIALIZED | STATUS_SWAP_ONLY => true,
+            STATUS_WAITING_TRADE => clock.unix_timestamp >= self.pool_open_time as i64,
+            _ => false,
+        }
+    }
+}
+
+/// Pool state and swappable reserves read at the same slot
+#[derive(Debug, Clone)]
+pub struct AmmSnapshot {
+    pub pool: Pubkey,
+    pub state: AmmPoolState,
+    /// Coin vault balance less the coin PnL owed to the protocol
+    pub reserve_coin: u64,
+    /// Pc vault balance less the pc PnL owed to the protocol
+    pub reserve_pc: u64,
+    pub clock: Clock,
+    /// Commitment the accounts were read at
+    pub commitment: CommitmentLevel,
+    /// Whether the Clock drifted from wall time by more than the allowed drift
+    pub stale: bool,
+    /// Wall time the account fetch started at
+    pub fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of the account round trip
+    pub fetch_latency: Duration,
+}
+
+impl AmmSnapshot {
+    fn block_time(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.clock.unix_timestamp, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+/// Polls Raydium AMM v4 (constant-product) pools and persists their best bid and ask
+pub struct RaydiumAmmScreener {
+    pub db_pool: DatabasePool,
+    pub rpc_client: FailoverRpcClient,
+    /// Commitment of pool, vault and Clock reads
+    pub commitment: CommitmentConfig,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Delay between two reloads of the trade pairs table
+    pub pairs_refresh_interval: Duration,
+    /// Number of pairs quoted concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    /// Drift between the Clock sysvar and wall time above which a quote is tagged stale
+    pub max_clock_drift: Duration,
+    /// Age above which a best quote is rejected instead of being returned or persisted
+    pub max_quote_age: Duration,
+    /// Trade configs loaded from the database, keyed by symbol; `base_is_x` means base is
+    /// the coin token
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+    /// Coin and pc vaults of every pool quoted so far, so later polls read the pool and its
+    /// vaults in one call
+    pool_vaults: RwLock<HashMap<Pubkey, (Pubkey, Pubkey)>>,
+}
+
+impl RaydiumAmmScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, MeteoraConfig::from_env()?))
+    }
+
+    /// Build the screener on already resolved RPC settings, shared with the Meteora screeners.
+    /// Pool, vault and Clock reads all use `config.commitment`.
+    pub fn with_config(db_pool: DatabasePool, config: MeteoraConfig) -> Self {
+        info!(
+            "Raydium AMM RPC endpoints: {}",
+            config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Self {
+            db_pool,
+            rpc_client: FailoverRpcClient::from_urls(config.rpc_endpoints, config.commitment),
+            commitment: config.commitment,
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            pairs_refresh_interval: pairs_refresh_interval_from_env(),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            max_clock_drift: max_clock_drift_from_env(),
+            max_quote_age: max_quote_age_from_env(),
+            trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+            pool_vaults: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Poll quotes for every configured pair until the screener is stopped
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Raydium AMM screener (poll interval {:?})...",
+            self.poll_interval
+        );
+
+        DexScreenerRunner {
+            quoter: self.clone(),
+            db_pool: self.db_pool.clone(),
+            dex_writer: Arc::new(DexMarketWriter::for_database(self.db_pool.clone(), VENUE)),
+            shutdown: self.shutdown.clone(),
+            poll_interval: self.poll_interval,
+            pairs_refresh_interval: self.pairs_refresh_interval,
+            max_concurrent_pairs: self.max_concurrent_pairs,
+            max_quote_age: self.max_quote_age,
+            trade_pairs: self.trade_pairs.clone(),
+            // Raydium pairs are never auto-discovered
+            discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+        }
+        .run()
+        .await
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Quote both swap directions on every pool of a pair and keep the best bid and best ask.
+    /// The buy side spends the quote token received for `amount_in` base token.
+    pub async fn get_price(
+        &self,
+        symbol: &str,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        get_best_price(
+            self,
+            &self.trade_pairs,
+            symbol,
+            amount_in,
+            self.max_quote_age,
+        )
+        .await
+    }
+
+    async fn get_pool_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let snapshot = self.fetch_snapshot(pool.pool_pubkey).await?;
+        let sell_coin_for_pc = pool.base_is_x;
+        let sell = quote_exact_in(&snapshot, amount_in, sell_coin_for_pc)?;
+        if sell.amount_out == 0 {
+            return Err(format!("Pool returned no output when selling {}", symbol).into());
+        }
+        let buy = quote_exact_in(&snapshot, sell.amount_out, !sell_coin_for_pc)?;
+        Ok(build_price_quote(
+            symbol,
+            &snapshot,
+            pool.base_is_x,
+            sell,
+            buy,
+        ))
+    }
+
+    /// Fetch the pool account, both vaults and the Clock in one round trip. The vault keys
+    /// come from the first read of the pool, which costs one extra round trip per pool.
+    async fn fetch_snapshot(
+        &self,
+        pool: Pubkey,
+    ) -> Result<AmmSnapshot, Box<dyn std::error::Error>> {
+        let (coin_vault, pc_vault) = self.cached_pool_vaults(pool).await?;
+        let fetched_at = Utc::now();
+        let fetch_started = Instant::now();
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[pool, coin_vault, pc_vault, solana_sdk::sysvar::clock::ID])
+            .await?;
+        let fetch_latency = fetch_started.elapsed();
+        let [
+            pool_account,
+            coin_vault_account,
+            pc_vault_account,
+            clock_account,
+        ] = accounts.as_slice()
+        else {
+            return Err("Unexpected number of Raydium AMM accounts".into());
+        };
+        let required = |account: &Option<Account>, name: &str| {
+            account
+                .clone()
+                .ok_or_else(|| format!("Raydium AMM pool {} {} not found", pool, name))
+        };
+        let pool_account = required(pool_account, "account")?;
+        if pool_account.owner != AMM_PROGRAM_ID {
+            return Err(
+                format!("Raydium AMM pool {} is not owned by the AMM program", pool).into(),
+            );
+        }
+        let state = decode_pool(&pool_account.data)
+            .map_err(|e| format!("Invalid Raydium AMM pool {}: {}", pool, e))?;
+        if (state.coin_vault, state.pc_vault) != (coin_vault, pc_vault) {
+            self.pool_vaults.write().unwrap().remove(&pool);
+            return Err(format!(
+                "Raydium AMM pool {} vaults changed since its first read",
+                pool
+            )
+            .into());
+        }
+        let clock: Clock = bincode::deserialize(&required(clock_account, "clock")?.data)?;
+        if !state.swap_enabled(&clock) {
+            return Err(format!(
+                "Raydium AMM pool {} does not accept swaps (status {})",
+                pool, state.status
+            )
+            .into());
+        }
+        let now = Utc::now();
+        let stale = is_clock_stale(&clock, now, self.max_clock_drift);
+        if stale {
+            warn!(
+                "Clock sysvar at slot {} drifted {}s from wall time, tagging Raydium AMM pool {} quote as stale",
+                clock.slot,
+                clock_drift_secs(&clock, now),
+                pool
+            );
+        }
+
+        let coin_vault_amount = token_account_amount(&required(coin_vault_account, "coin vault")?)?;
+        let pc_vault_amount = token_account_amount(&required(pc_vault_account, "pc vault")?)?;
+        let (reserve_coin, reserve_pc) =
+            swappable_reserves(&state, coin_vault_amount, pc_vault_amount)
+                .map_err(|e| format!("Raydium AMM pool {}: {}", pool, e))?;
+
+        Ok(AmmSnapshot {
+            pool,
+            state,
+            reserve_coin,
+            reserve_pc,
+            clock,
+            commitment: self.commitment.commitment,
+            stale,
+            fetched_at,
+            fetch_latency,
+        })
+    }
+
+    /// Coin and pc vaults of a pool, decoded from its account on first use
+    async fn cached_pool_vaults(
+        &self,
+        pool: Pubkey,
+    ) -> Result<(Pubkey, Pubkey), Box<dyn std::error::Error>> {
+        if let Some(vaults) = self.pool_vaults.read().unwrap().get(&pool) {
+            return Ok(*vaults);
+        }
+        let account = self
+            .rpc_client
+            .get_multiple_accounts(&[pool])
+            .await?
+            .into_iter()
+            .next()
+            .flatten()
+            .ok_or_else(|| format!("Raydium AMM pool {} not found", pool))?;
+        let state = decode_pool(&account.data)
+            .map_err(|e| format!("Invalid Raydium AMM pool {}: {}", pool, e))?;
+        let vaults = (state.coin_vault, state.pc_vault);
+        self.pool_vaults.write().unwrap().insert(pool, vaults);
+        Ok(vaults)
+    }
+}
+
+#[async_trait]
+impl DexQuoter for RaydiumAmmScreener {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            self.get_pool_price(symbol, pool, amount_in)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+        .await;
+        let quotes = successful_pool_results(symbol, &trade_config.pools, results);
+
+        select_best_price(symbol, quotes)
+            .ok_or_else(|| format!("No Raydium AMM pool could quote {}", symbol).into())
+    }
+}
+
+/// Decode the `AmmInfo` fields needed for quoting, checking the account size
+fn decode_pool(data: &[u8]) -> Result<AmmPoolState, String> {
+    if data.len() != AMM_INFO_LEN {
+        return Err(format!(
+            "account is {} bytes, expected {}",
+            data.len(),
+            AMM_INFO_LEN
+        ));
+    }
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).unwrap();
+    let decimals_at = |offset: usize| {
+        u32::try_from(u64_at(offset))
+            .ok()
+            .filter(|decimals| *decimals <= 18)
+            .ok_or_else(|| format!("invalid decimals {}", u64_at(offset)))
+    };
+    let state = AmmPoolState {
+        status: u64_at(STATUS_OFFSET),
+        coin_decimals: decimals_at(COIN_DECIMALS_OFFSET)?,
+        pc_decimals: decimals_at(PC_DECIMALS_OFFSET)?,
+        swap_fee_numerator: u64_at(SWAP_FEE_NUMERATOR_OFFSET),
+        swap_fee_denominator: u64_at(SWAP_FEE_DENOMINATOR_OFFSET),
+        need_take_pnl_coin: u64_at(NEED_TAKE_PNL_COIN_OFFSET),
+        need_take_pnl_pc: u64_at(NEED_TAKE_PNL_PC_OFFSET),
+        pool_open_time: u64_at(POOL_OPEN_TIME_OFFSET),
+        coin_vault: pubkey_at(COIN_VAULT_OFFSET),
+        pc_vault: pubkey_at(PC_VAULT_OFFSET),
+        coin_mint: pubkey_at(COIN_MINT_OFFSET),
+        pc_mint: pubkey_at(PC_MINT_OFFSET),
+    };
+    if state.swap_fee_denominator == 0 || state.swap_fee_numerator >= state.swap_fee_denominator {
+        return Err(format!(
+            "invalid swap fee {}/{}",
+            state.swap_fee_numerator, state.swap_fee_denominator
+        ));
+    }
+    Ok(state)
+}
+
+/// Reserves the program swaps against: the vault balances less the PnL owed to the protocol.
+/// Liquidity parked in OpenBook orders is not counted, as pools no longer place any.
+fn swappable_reserves(
+    state: &AmmPoolState,
+    coin_vault_amount: u64,
+    pc_vault_amount: u64,
+) -> Result<(u64, u64), String> {
+    let reserve_coin = coin_vault_amount
+        .checked_sub(state.need_take_pnl_coin)
+        .ok_or("coin vault holds less than the PnL owed")?;
+    let reserve_pc = pc_vault_amount
+        .checked_sub(state.need_take_pnl_pc)
+        .ok_or("pc vault holds less than the PnL owed")?;
+    Ok((reserve_coin, reserve_pc))
+}
+
+/// Quote an exact-in swap against the reserves with the constant-product formula, as the
+/// program's `swap_base_in` does: the swap fee is taken from the input, rounded up, and the
+/// output is rounded down
+fn quote_exact_in(
+    snapshot: &AmmSnapshot,
+    amount_in: u64,
+    coin_for_pc: bool,
+) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    let (reserve_in, reserve_out) = if coin_for_pc {
+        (snapshot.reserve_coin, snapshot.reserve_pc)
+    } else {
+        (snapshot.reserve_pc, snapshot.reserve_coin)
+    };
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(format!("Raydium AMM pool {} has an empty reserve", snapshot.pool).into());
+    }
+    let fee = (amount_in as u128 * snapshot.state.swap_fee_numerator as u128)
+        .div_ceil(snapshot.state.swap_fee_denominator as u128) as u64;
+    let amount_in_after_fee = amount_in.saturating_sub(fee) as u128;
+    let amount_out =
+        reserve_out as u128 * amount_in_after_fee / (reserve_in as u128 + amount_in_after_fee);
+    Ok(SwapQuote::without_transfer_fees(
+        amount_in,
+        amount_out as u64,
+        fee,
+    ))
+}
+
+/// Quote tokens per base token implied by the reserves, before fees and price impact
+fn spot_price(snapshot: &AmmSnapshot, base_is_coin: bool) -> Option<Decimal> {
+    let (base_reserve, quote_reserve, base_decimals, quote_decimals) = if base_is_coin {
+        (
+            snapshot.reserve_coin,
+            snapshot.reserve_pc,
+            snapshot.state.coin_decimals,
+            snapshot.state.pc_decimals,
+        )
+    } else {
+        (
+            snapshot.reserve_pc,
+            snapshot.reserve_coin,
+            snapshot.state.pc_decimals,
+            snapshot.state.coin_decimals,
+        )
+    };
+    let price = normalized_price(base_reserve, quote_reserve, base_decimals, quote_decimals);
+    (price > Decimal::ZERO).then_some(price)
+}
+
+fn build_price_quote(
+    symbol: &str,
+    snapshot: &AmmSnapshot,
+    base_is_coin: bool,
+    sell: SwapQuote,
+    buy: SwapQuote,
+) -> PriceQuote {
+    let (base_decimals, quote_decimals, base_reserve, quote_reserve) = if base_is_coin {
+        (
+            snapshot.state.coin_decimals,
+            snapshot.state.pc_decimals,
+            snapshot.reserve_coin,
+            snapshot.reserve_pc,
+        )
+    } else {
+        (
+            snapshot.state.pc_decimals,
+            snapshot.state.coin_decimals,
+            snapshot.reserve_pc,
+            snapshot.reserve_coin,
+        )
+    };
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, base_decimals, quote_decimals);
+    let spot_price = spot_price(snapshot, base_is_coin);
+
+    PriceQuote {
+        symbol: symbol.to_string(),
+        pool: snapshot.pool,
+        route: None,
+        hops: Vec::new(),
+        slot: snapshot.clock.slot,
+        block_time: snapshot.block_time(),
+        fetched_at: snapshot.fetched_at,
+        fetch_latency: snapshot.fetch_latency,
+        commitment: snapshot.commitment,
+        stale: snapshot.stale,
+        missing_bin_arrays: 0,
+        base_decimals,
+        quote_decimals,
+        bid_impact_bps: spot_price.and_then(|spot| price_impact_bps(bid_price, spot)),
+        ask_impact_bps: spot_price.and_then(|spot| price_impact_bps(ask_price, spot)),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: None,
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price,
+        liquidity: PoolLiquidity::new(
+            base_reserve as u128,
+            quote_reserve as u128,
+            base_decimals,
+            quote_decimals,
+            spot_price,
+        ),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+#[cfg(test)]
+#[path = "raydium_amm_tests.rs"]
+mod raydium_amm_tests;
@@ -0,0 +1,197 @@
+use super::*;
+
+/// 500k coin (TRUMP) against 5M pc (USDC), both with 6 decimals, and the standard 0.25% fee
+fn fixture_snapshot() -> AmmSnapshot {
+    AmmSnapshot {
+        pool: Pubkey::new_unique(),
+        state: AmmPoolState {
+            status: STATUS_SWAP_ONLY,
+            coin_decimals: 6,
+            pc_decimals: 6,
+            swap_fee_numerator: 25,
+            swap_fee_denominator: 10_000,
+            need_take_pnl_coin: 1_000_000_000,
+            need_take_pnl_pc: 20_000_000_000,
+            pool_open_time: 0,
+            coin_vault: Pubkey::new_unique(),
+            pc_vault: Pubkey::new_unique(),
+            coin_mint: Pubkey::new_unique(),
+            pc_mint: Pubkey::new_unique(),
+        },
+        reserve_coin: 500_000_000_000,
+        reserve_pc: 5_000_000_000_000,
+        clock: Clock {
+            slot: 42,
+            unix_timestamp: 1_700_000_000,
+            ..Clock::default()
+        },
+        commitment: CommitmentLevel::Confirmed,
+        stale: false,
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(40),
+    }
+}
+
+fn pool_account_data(state: &AmmPoolState) -> Vec<u8> {
+    let mut data = vec![0u8; AMM_INFO_LEN];
+    for (offset, value) in [
+        (STATUS_OFFSET, state.status),
+        (COIN_DECIMALS_OFFSET, state.coin_decimals as u64),
+        (PC_DECIMALS_OFFSET, state.pc_decimals as u64),
+        (SWAP_FEE_NUMERATOR_OFFSET, state.swap_fee_numerator),
+        (SWAP_FEE_DENOMINATOR_OFFSET, state.swap_fee_denominator),
+        (NEED_TAKE_PNL_COIN_OFFSET, state.need_take_pnl_coin),
+        (NEED_TAKE_PNL_PC_OFFSET, state.need_take_pnl_pc),
+        (POOL_OPEN_TIME_OFFSET, state.pool_open_time),
+    ] {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    for (offset, pubkey) in [
+        (COIN_VAULT_OFFSET, state.coin_vault),
+        (PC_VAULT_OFFSET, state.pc_vault),
+        (COIN_MINT_OFFSET, state.coin_mint),
+        (PC_MINT_OFFSET, state.pc_mint),
+    ] {
+        data[offset..offset + 32].copy_from_slice(pubkey.as_ref());
+    }
+    data
+}
+
+#[test]
+fn decode_pool_reads_fees_pnl_and_vaults() {
+    let state = fixture_snapshot().state;
+
+    assert_eq!(decode_pool(&pool_account_data(&state)).unwrap(), state);
+}
+
+#[test]
+fn decode_pool_rejects_other_accounts() {
+    let state = fixture_snapshot().state;
+    let data = pool_account_data(&state);
+    let mut longer = data.clone();
+    longer.push(0);
+    let no_fee_denominator = pool_account_data(&AmmPoolState {
+        swap_fee_denominator: 0,
+        ..state.clone()
+    });
+    let bad_decimals = pool_account_data(&AmmPoolState {
+        coin_decimals: 200,
+        ..state
+    });
+
+    assert!(decode_pool(&data[..AMM_INFO_LEN - 1]).is_err());
+    assert!(decode_pool(&longer).is_err());
+    assert!(decode_pool(&no_fee_denominator).is_err());
+    assert!(decode_pool(&bad_decimals).is_err());
+}
+
+#[test]
+fn swaps_are_enabled_by_status_and_open_time() {
+    let clock = fixture_snapshot().clock;
+    let state = |status, pool_open_time| AmmPoolState {
+        status,
+        pool_open_time,
+        ..fixture_snapshot().state
+    };
+
+    assert!(state(STATUS_INITIALIZED, 0).swap_enabled(&clock));
+    assert!(state(STATUS_SWAP_ONLY, 0).swap_enabled(&clock));
+    assert!(state(STATUS_WAITING_TRADE, 1_700_000_000).swap_enabled(&clock));
+    assert!(!state(STATUS_WAITING_TRADE, 1_700_000_001).swap_enabled(&clock));
+    // Disabled and withdraw-only
+    assert!(!state(2, 0).swap_enabled(&clock));
+    assert!(!state(3, 0).swap_enabled(&clock));
+}
+
+#[test]
+fn swappable_reserves_exclude_the_pnl_owed() {
+    let state = fixture_snapshot().state;
+
+    assert_eq!(
+        swappable_reserves(&state, 501_000_000_000, 5_020_000_000_000).unwrap(),
+        (500_000_000_000, 5_000_000_000_000)
+    );
+    assert!(swappable_reserves(&state, 999_999_999, 5_020_000_000_000).is_err());
+    assert!(swappable_reserves(&state, 501_000_000_000, 0).is_err());
+}
+
+#[test]
+fn quote_exact_in_applies_fee_and_constant_product() {
+    let snapshot = fixture_snapshot();
+
+    // 1,000 coin: fee 2.5 coin, 5e12 * 997.5e6 / (5e11 + 997.5e6) pc out
+    let sell = quote_exact_in(&snapshot, 1_000_000_000, true).unwrap();
+    assert_eq!(sell.fee, 2_500_000);
+    assert_eq!(sell.amount_out, 9_955_139_496);
+
+    // 10,000 pc: fee 25 pc, 5e11 * 9.975e9 / (5e12 + 9.975e9) coin out
+    let buy = quote_exact_in(&snapshot, 10_000_000_000, false).unwrap();
+    assert_eq!(buy.fee, 25_000_000);
+    assert_eq!(buy.amount_out, 995_513_949);
+}
+
+#[test]
+fn quote_exact_in_rounds_the_fee_up_and_the_output_down() {
+    let snapshot = fixture_snapshot();
+
+    let quote = quote_exact_in(&snapshot, 7, true).unwrap();
+    assert_eq!(quote.fee, 1);
+    assert_eq!(quote.amount_out, 59);
+
+    // The fee swallows a single unit
+    let quote = quote_exact_in(&snapshot, 1, true).unwrap();
+    assert_eq!(quote.fee, 1);
+    assert_eq!(quote.amount_out, 0);
+}
+
+#[test]
+fn quote_exact_in_rejects_empty_vaults() {
+    let mut empty_pc = fixture_snapshot();
+    empty_pc.reserve_pc = 0;
+    let mut empty_coin = fixture_snapshot();
+    empty_coin.reserve_coin = 0;
+
+    assert!(quote_exact_in(&empty_pc, 1_000, true).is_err());
+    assert!(quote_exact_in(&empty_pc, 1_000, false).is_err());
+    assert!(quote_exact_in(&empty_coin, 1_000, true).is_err());
+    assert!(quote_exact_in(&empty_coin, 1_000, false).is_err());
+}
+
+#[test]
+fn spot_price_is_none_for_empty_vaults() {
+    let mut snapshot = fixture_snapshot();
+    snapshot.reserve_pc = 0;
+
+    assert_eq!(spot_price(&snapshot, true), None);
+    assert_eq!(spot_price(&snapshot, false), None);
+}
+
+#[test]
+fn build_price_quote_brackets_the_reserve_price() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 1_000_000_000, true).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, false).unwrap();
+
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, true, sell, buy);
+
+    assert_eq!(quote.spot_price, Some(Decimal::TEN));
+    assert!(quote.bid_price < Decimal::TEN);
+    assert!(quote.ask_price > Decimal::TEN);
+    assert_eq!(quote.sell_fee_pct, "0.25".parse::<Decimal>().unwrap());
+    assert_eq!(quote.slot, 42);
+    assert_eq!(quote.pool, snapshot.pool);
+    // 500k base at 10 plus 5M quote
+    assert_eq!(quote.liquidity.notional, Some(Decimal::from(10_000_000)));
+}
+
+#[test]
+fn build_price_quote_inverts_when_base_is_pc() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 1_000_000_000, false).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, true).unwrap();
+
+    let quote = build_price_quote("USDCTRUMP", &snapshot, false, sell, buy);
+
+    assert_eq!(quote.spot_price, Some("0.1".parse::<Decimal>().unwrap()));
+    assert!(quote.bid_price < quote.ask_price);
+}
@@ -0,0 +1,1155 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::screeners::dex_runner::{DexQuoter, DexScreenerRunner, get_best_price};
+use crate::screeners::dex_writer::DexMarketWriter;
+use crate::screeners::meteora::{
+    BestPriceQuote, MeteoraConfig, PoolConfig, PoolLiquidity, PriceQuote, SwapQuote, TradeConfig,
+    clock_drift_secs, derive_bid_ask, fee_pct, is_clock_stale, max_clock_drift_from_env,
+    max_concurrent_pairs_from_env, max_quote_age_from_env, pairs_refresh_interval_from_env,
+    poll_interval_from_env, price_impact_bps, select_best_price, successful_pool_results,
+};
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::solana::utils::token_account_amount;
+use crate::store::db::DatabasePool;
+
+/// Venue name of Raydium CLMM pairs in the trade_pairs table, also used as the DEX market exchange
+const VENUE: &str = "raydium_clmm";
+/// Raydium concentrated liquidity program, owner of the pools and tick arrays
+pub const CLMM_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+/// Anchor discriminators (`sha256("account:<Name>")[..8]`) of the CLMM accounts we decode
+const POOL_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = [218, 244, 33, 104, 203, 203, 43, 111];
+const TICK_ARRAY_DISCRIMINATOR: [u8; 8] = [192, 155, 85, 205, 49, 249, 129, 42];
+/// Offsets of the packed `PoolState` fields needed to quote a pool
+const AMM_CONFIG_OFFSET: usize = 9;
+const TOKEN_MINT_0_OFFSET: usize = 73;
+const TOKEN_MINT_1_OFFSET: usize = 105;
+const TOKEN_VAULT_0_OFFSET: usize = 137;
+const TOKEN_VAULT_1_OFFSET: usize = 169;
+const MINT_DECIMALS_0_OFFSET: usize = 233;
+const MINT_DECIMALS_1_OFFSET: usize = 234;
+const TICK_SPACING_OFFSET: usize = 235;
+const LIQUIDITY_OFFSET: usize = 237;
+const SQRT_PRICE_OFFSET: usize = 253;
+const TICK_CURRENT_OFFSET: usize = 269;
+const STATUS_OFFSET: usize = 389;
+/// Offset of the 1024-bit bitmap of initialized tick arrays, after the three reward infos
+const TICK_ARRAY_BITMAP_OFFSET: usize = 904;
+const TICK_ARRAY_BITMAP_WORDS: usize = 16;
+const POOL_MIN_LEN: usize = TICK_ARRAY_BITMAP_OFFSET + TICK_ARRAY_BITMAP_WORDS * 8;
+/// Bit of the pool status that disables swaps
+const SWAP_DISABLED_STATUS_BIT: u8 = 1 << 4;
+/// Offset of `trade_fee_rate` in the `AmmConfig` account
+const TRADE_FEE_RATE_OFFSET: usize = 47;
+const AMM_CONFIG_MIN_LEN: usize = TRADE_FEE_RATE_OFFSET + 4;
+/// Layout of a `TickArrayState`: pool, start tick index, then 60 ticks of 168 bytes holding
+/// the tick, `liquidity_net` and `liquidity_gross` first
+const TICK_ARRAY_POOL_OFFSET: usize = 8;
+const TICK_ARRAY_START_OFFSET: usize = 40;
+const TICKS_OFFSET: usize = 44;
+const TICK_STATE_LEN: usize = 168;
+const TICK_ARRAY_SIZE: i32 = 60;
+const TICK_ARRAY_MIN_LEN: usize = TICKS_OFFSET + TICK_ARRAY_SIZE as usize * TICK_STATE_LEN;
+/// Seed of the tick array PDAs, followed by the pool and the big-endian start tick index
+const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+/// Tick arrays the pool bitmap covers on each side of tick 0; arrays further out are
+/// tracked by the bitmap extension account, which is not read
+const BITMAP_HALF_RANGE: i32 = 512;
+/// Upper bound for the tick array count when retrying a quote that ran out of tick arrays
+const MAX_TICK_ARRAY_COUNT: u8 = 16;
+/// Denominator of CLMM fee rates
+const FEE_RATE_DENOMINATOR: u32 = 1_000_000;
+/// Tick and Q64.64 square root price bounds of the program
+const MIN_TICK: i32 = -443_636;
+const MAX_TICK: i32 = 443_636;
+const MIN_SQRT_PRICE_X64: u128 = 4_295_048_016;
+const MAX_SQRT_PRICE_X64: u128 = 79_226_673_521_066_979_257_578_248_091;
+const Q64: u128 = 1 << 64;
+/// `sqrt(1.0001)^-(2^i)` in Q64.64, the factors the program builds tick prices from
+const SQRT_PRICE_FACTORS: [u128; 19] = [
+    0xfffcb933bd6fb800,
+    0xfff97272373d4000,
+    0xfff2e50f5f657000,
+    0xffe5caca7e10f000,
+    0xffcb9843d60f7000,
+    0xff973b41fa98e800,
+    0xff2ea16466c9b000,
+    0xfe5dee046a9a3800,
+    0xfcbe86c7900bb000,
+    0xf987a7253ac65800,
+    0xf3392b0822bb6000,
+    0xe7159475a2caf000,
+    0xd097f3bdfd2f2000,
+    0xa9f746462d9f8000,
+    0x70d869a156f31c00,
+    0x31be135f97ed3200,
+    0x9aa508b5b85a500,
+    0x5d6af8dedc582c,
+    0x2216e584f5fa,
+];
+/// Error of a swap that walked past the fetched tick arrays, retried with more of them
+const TICK_ARRAYS_EXHAUSTED: &str = "swap walked past the fetched tick arrays";
+
+mod wide {
+    #![allow(clippy::all)]
+    uint::construct_uint! {
+        /// Unsigned integer wide enough for the program's 256-bit products of Q64.64 values
+        pub(super) struct U512(8);
+    }
+}
+use wide::U512;
+
+/// Fields of a CLMM pool account needed to quote it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClmmPoolState {
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    /// Liquidity in range at the current tick
+    pub liquidity: u128,
+    /// Square root of the token 1 per token 0 price, in Q64.64
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub status: u8,
+    /// Initialized tick arrays within `BITMAP_HALF_RANGE` arrays of tick 0
+    pub tick_array_bitmap: [u64; TICK_ARRAY_BITMAP_WORDS],
+}
+
+impl ClmmPoolState {
+    fn swap_disabled(&self) -> bool {
+        self.status & SWAP_DISABLED_STATUS_BIT != 0
+    }
+
+    fn ticks_per_array(&self) -> i32 {
+        i32::from(self.tick_spacing) * TICK_ARRAY_SIZE
+    }
+
+    /// Start tick index of the tick array holding `tick`
+    fn tick_array_start(&self, tick: i32) -> i32 {
+        tick.div_euclid(self.ticks_per_array()) * self.ticks_per_array()
+    }
+
+    /// Bit of the tick array starting at `start` in the pool bitmap, `None` beyond its range
+    fn bitmap_position(&self, start: i32) -> Option<i32> {
+        let position = start / self.ticks_per_array() + BITMAP_HALF_RANGE;
+        (0..2 * BITMAP_HALF_RANGE)
+            .contains(&position)
+            .then_some(position)
+    }
+
+    fn is_bit_set(&self, position: i32) -> bool {
+        let position = position as usize;
+        self.tick_array_bitmap[position / 64] & (1 << (position % 64)) != 0
+    }
+
+    fn is_tick_array_initialized(&self, start: i32) -> bool {
+        self.bitmap_position(start)
+            .is_some_and(|position| self.is_bit_set(position))
+    }
+
+    /// Next initialized tick array after the one starting at `start`, below it when the swap
+    /// sells token 0 (price going down) and above it otherwise
+    fn next_initialized_tick_array(&self, start: i32, zero_for_one: bool) -> Option<i32> {
+        let position = self.bitmap_position(start)?;
+        let found = if zero_for_one {
+            (0..position)
+                .rev()
+                .find(|&position| self.is_bit_set(position))
+        } else {
+            (position + 1..2 * BITMAP_HALF_RANGE).find(|&position| self.is_bit_set(position))
+        };
+        found.map(|position| (position - BITMAP_HALF_RANGE) * self.ticks_per_array())
+    }
+
+    /// Start indexes of the first `count` initialized tick arrays a swap in one direction walks
+    /// through, beginning with the array of the current tick when it is initialized. Swaps
+    /// that need more arrays fail with `TICK_ARRAYS_EXHAUSTED`, like bin arrays on Meteora.
+    fn tick_array_starts_for_swap(
+        &self,
+        zero_for_one: bool,
+        count: u8,
+    ) -> Result<Vec<i32>, String> {
+        let current = self.tick_array_start(self.tick_current);
+        if self.bitmap_position(current).is_none() {
+            return Err(format!(
+                "current tick {} is beyond the pool's tick array bitmap, whose extension is not supported",
+                self.tick_current
+            ));
+        }
+        let mut starts = Vec::with_capacity(count as usize);
+        let mut start = if self.is_tick_array_initialized(current) {
+            Some(current)
+        } else {
+            self.next_initialized_tick_array(current, zero_for_one)
+        };
+        while let Some(next) = start
+            && starts.len() < count as usize
+        {
+            starts.push(next);
+            start = self.next_initialized_tick_array(next, zero_for_one);
+        }
+        Ok(starts)
+    }
+}
+
+/// Initialized state of one tick
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickState {
+    pub tick: i32,
+    /// Liquidity added when the price crosses the tick upwards
+    pub liquidity_net: i128,
+    /// Liquidity referencing the tick, zero when it is not initialized
+    pub liquidity_gross: u128,
+}
+
+impl TickState {
+    fn is_initialized(&self) -> bool {
+        self.liquidity_gross != 0
+    }
+}
+
+/// Ticks of one tick array account
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickArray {
+    pub start_tick_index: i32,
+    /// `TICK_ARRAY_SIZE` ticks, `tick_spacing` apart
+    pub ticks: Vec<TickState>,
+}
+
+impl TickArray {
+    /// Next initialized tick of this array from `tick`, at or below it when the swap sells
+    /// token 0 and strictly above it otherwise; `None` when `tick` lies in another array
+    fn next_initialized_tick(
+        &self,
+        tick: i32,
+        tick_spacing: u16,
+        zero_for_one: bool,
+    ) -> Option<&TickState> {
+        let ticks_per_array = i32::from(tick_spacing) * TICK_ARRAY_SIZE;
+        if tick.div_euclid(ticks_per_array) * ticks_per_array != self.start_tick_index {
+            return None;
+        }
+        let offset = ((tick - self.start_tick_index) / i32::from(tick_spacing)) as usize;
+        if zero_for_one {
+            self.ticks[..=offset]
+                .iter()
+                .rev()
+                .find(|tick| tick.is_initialized())
+        } else {
+            self.ticks[offset + 1..]
+                .iter()
+                .find(|tick| tick.is_initialized())
+        }
+    }
+
+    /// First initialized tick a swap entering this array meets
+    fn first_initialized_tick(&self, zero_for_one: bool) -> Option<&TickState> {
+        if zero_for_one {
+            self.ticks.iter().rev().find(|tick| tick.is_initialized())
+        } else {
+            self.ticks.iter().find(|tick| tick.is_initialized())
+        }
+    }
+}
+
+/// Pool state, fee rate, vault reserves and the tick arrays of both swap directions
+#[derive(Debug, Clone)]
+pub struct ClmmSnapshot {
+    pub pool: Pubkey,
+    pub state: ClmmPoolState,
+    /// Trade fee rate of the pool's AMM config over `FEE_RATE_DENOMINATOR`
+    pub trade_fee_rate: u32,
+    /// Fetched tick arrays keyed by start tick index
+    pub tick_arrays: HashMap<i32, TickArray>,
+    pub reserve_0: u64,
+    pub reserve_1: u64,
+    pub clock: Clock,
+    /// Commitment the accounts were read at
+    pub commitment: CommitmentLevel,
+    /// Whether the Clock drifted from wall time by more than the allowed drift
+    pub stale: bool,
+    /// Wall time the account fetch started at
+    pub fetched_at: DateTime<Utc>,
+    /// Wall-clock duration of both account round trips
+    pub fetch_latency: Duration,
+}
+
+impl ClmmSnapshot {
+    fn block_time(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.clock.unix_timestamp, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+/// Polls Raydium CLMM (concentrated liquidity) pools and persists their best bid and ask
+pub struct RaydiumClmmScreener {
+    pub db_pool: DatabasePool,
+    pub rpc_client: FailoverRpcClient,
+    /// Commitment of pool, config, vault, tick array and Clock reads
+    pub commitment: CommitmentConfig,
+    /// Cancelled by `stop()` to end the polling loop
+    pub shutdown: CancellationToken,
+    /// Delay between two polling ticks
+    pub poll_interval: Duration,
+    /// Delay between two reloads of the trade pairs table
+    pub pairs_refresh_interval: Duration,
+    /// Number of pairs quoted concurrently within a tick
+    pub max_concurrent_pairs: usize,
+    /// Drift between the Clock sysvar and wall time above which a quote is tagged stale
+    pub max_clock_drift: Duration,
+    /// Age above which a best quote is rejected instead of being returned or persisted
+    pub max_quote_age: Duration,
+    /// Trade configs loaded from the database, keyed by symbol; `base_is_x` means base is
+    /// token 0 and `bin_array_count` is the number of tick arrays fetched per direction
+    trade_pairs: Arc<RwLock<HashMap<String, TradeConfig>>>,
+}
+
+impl RaydiumClmmScreener {
+    pub fn new(db_pool: DatabasePool) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_config(db_pool, MeteoraConfig::from_env()?))
+    }
+
+    /// Build the screener on already resolved RPC settings, shared with the Meteora screeners.
+    /// Every account read uses `config.commitment`.
+    pub fn with_config(db_pool: DatabasePool, config: MeteoraConfig) -> Self {
+        info!(
+            "Raydium CLMM RPC endpoints: {}",
+            config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Self {
+            db_pool,
+            rpc_client: FailoverRpcClient::from_urls(config.rpc_endpoints, config.commitment),
+            commitment: config.commitment,
+            shutdown: CancellationToken::new(),
+            poll_interval: poll_interval_from_env(),
+            pairs_refresh_interval: pairs_refresh_interval_from_env(),
+            max_concurrent_pairs: max_concurrent_pairs_from_env(),
+            max_clock_drift: max_clock_drift_from_env(),
+            max_quote_age: max_quote_age_from_env(),
+            trade_pairs: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Poll quotes for every configured pair until the screener is stopped
+    pub async fn start(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Starting Raydium CLMM screener (poll interval {:?})...",
+            self.poll_interval
+        );
+
+        DexScreenerRunner {
+            quoter: self.clone(),
+            db_pool: self.db_pool.clone(),
+            dex_writer: Arc::new(DexMarketWriter::for_database(self.db_pool.clone(), VENUE)),
+            shutdown: self.shutdown.clone(),
+            poll_interval: self.poll_interval,
+            pairs_refresh_interval: self.pairs_refresh_interval,
+            max_concurrent_pairs: self.max_concurrent_pairs,
+            max_quote_age: self.max_quote_age,
+            trade_pairs: self.trade_pairs.clone(),
+            // Raydium pairs are never auto-discovered
+            discovered_pools: Arc::new(RwLock::new(HashMap::new())),
+        }
+        .run()
+        .await
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Quote both swap directions on every pool of a pair and keep the best bid and best ask.
+    /// The buy side spends the quote token received for `amount_in` base token.
+    pub async fn get_price(
+        &self,
+        symbol: &str,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        get_best_price(
+            self,
+            &self.trade_pairs,
+            symbol,
+            amount_in,
+            self.max_quote_age,
+        )
+        .await
+    }
+
+    /// Quote both swap directions of a single pool against the same fetched pool state
+    async fn get_pool_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        retry_with_more_tick_arrays(symbol, pool.bin_array_count, |count| {
+            self.quote_price(symbol, pool, amount_in, count)
+        })
+        .await
+    }
+
+    async fn quote_price(
+        &self,
+        symbol: &str,
+        pool: &PoolConfig,
+        amount_in: u64,
+        tick_array_count: u8,
+    ) -> Result<PriceQuote, Box<dyn std::error::Error>> {
+        let snapshot = self
+            .fetch_snapshot(pool.pool_pubkey, tick_array_count)
+            .await?;
+        // Selling base means swapping token 0 for token 1 when base is token 0
+        let sell_zero_for_one = pool.base_is_x;
+        let sell = quote_exact_in(&snapshot, amount_in, sell_zero_for_one)?;
+        if sell.amount_out == 0 {
+            return Err(format!("Pool returned no output when selling {}", symbol).into());
+        }
+        let buy = quote_exact_in(&snapshot, sell.amount_out, !sell_zero_for_one)?;
+        Ok(build_price_quote(
+            symbol,
+            &snapshot,
+            pool.base_is_x,
+            sell,
+            buy,
+        ))
+    }
+
+    /// Fetch the pool account and Clock, then the AMM config, both vaults and the tick arrays
+    /// of both swap directions in a second round trip
+    async fn fetch_snapshot(
+        &self,
+        pool: Pubkey,
+        tick_array_count: u8,
+    ) -> Result<ClmmSnapshot, Box<dyn std::error::Error>> {
+        let fetched_at = Utc::now();
+        let fetch_started = Instant::now();
+        let mut accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[pool, solana_sdk::sysvar::clock::ID])
+            .await?
+            .into_iter();
+        let pool_account = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| format!("Raydium CLMM pool {} not found", pool))?;
+        let clock_account = accounts.next().flatten().ok_or("Failed to fetch clock")?;
+        let state = decode_pool(&pool_account.data)
+            .map_err(|e| format!("Invalid Raydium CLMM pool {}: {}", pool, e))?;
+        if state.swap_disabled() {
+            return Err(format!("Raydium CLMM pool {} has swaps disabled", pool).into());
+        }
+        let clock: Clock = bincode::deserialize(&clock_account.data)?;
+        let now = Utc::now();
+        let stale = is_clock_stale(&clock, now, self.max_clock_drift);
+        if stale {
+            warn!(
+                "Clock sysvar at slot {} drifted {}s from wall time, tagging Raydium CLMM pool {} quote as stale",
+                clock.slot,
+                clock_drift_secs(&clock, now),
+                pool
+            );
+        }
+
+        // Both directions walk away from the current tick on opposite sides, so the sets differ
+        let mut starts = state.tick_array_starts_for_swap(true, tick_array_count)?;
+        for start in state.tick_array_starts_for_swap(false, tick_array_count)? {
+            if !starts.contains(&start) {
+                starts.push(start);
+            }
+        }
+        let mut keys = vec![state.amm_config, state.token_vault_0, state.token_vault_1];
+        keys.extend(starts.iter().map(|&start| tick_array_address(&pool, start)));
+        let accounts = self.rpc_client.get_multiple_accounts(&keys).await?;
+        let [amm_config, vault_0, vault_1, tick_array_accounts @ ..] = accounts.as_slice() else {
+            return Err("Unexpected number of Raydium CLMM accounts".into());
+        };
+        let required = |account: &Option<Account>, name: &str| {
+            account
+                .clone()
+                .ok_or_else(|| format!("Raydium CLMM pool {} {} not found", pool, name))
+        };
+        let trade_fee_rate = decode_amm_config(&required(amm_config, "AMM config")?.data)
+            .map_err(|e| format!("Invalid AMM config {}: {}", state.amm_config, e))?;
+        let reserve_0 = token_account_amount(&required(vault_0, "token 0 vault")?)?;
+        let reserve_1 = token_account_amount(&required(vault_1, "token 1 vault")?)?;
+        // The bitmap marks these arrays initialized, so a missing one would silently drop
+        // liquidity from the quote
+        let mut tick_arrays = HashMap::with_capacity(starts.len());
+        for (&start, account) in starts.iter().zip(tick_array_accounts) {
+            let account = required(account, &format!("tick array {}", start))?;
+            let tick_array = decode_tick_array(&account.data, &pool, start)
+                .map_err(|e| format!("Invalid tick array {} of pool {}: {}", start, pool, e))?;
+            tick_arrays.insert(start, tick_array);
+        }
+
+        Ok(ClmmSnapshot {
+            pool,
+            state,
+            trade_fee_rate,
+            tick_arrays,
+            reserve_0,
+            reserve_1,
+            clock,
+            commitment: self.commitment.commitment,
+            stale,
+            fetched_at,
+            fetch_latency: fetch_started.elapsed(),
+        })
+    }
+}
+
+#[async_trait]
+impl DexQuoter for RaydiumClmmScreener {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    async fn quote_exact_in(
+        &self,
+        symbol: &str,
+        trade_config: &TradeConfig,
+        amount_in: u64,
+    ) -> Result<BestPriceQuote, Box<dyn std::error::Error>> {
+        let results = join_all(trade_config.pools.iter().map(|pool| async move {
+            self.get_pool_price(symbol, pool, amount_in)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+        .await;
+        let quotes = successful_pool_results(symbol, &trade_config.pools, results);
+
+        select_best_price(symbol, quotes)
+            .ok_or_else(|| format!("No Raydium CLMM pool could quote {}", symbol).into())
+    }
+}
+
+/// Address of the tick array of `pool` starting at `start_tick_index`
+pub fn tick_array_address(pool: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED,
+            pool.as_ref(),
+            &start_tick_index.to_be_bytes(),
+        ],
+        &CLMM_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Decode the CLMM pool fields needed for quoting, checking the discriminator and length
+fn decode_pool(data: &[u8]) -> Result<ClmmPoolState, String> {
+    if data.len() < POOL_MIN_LEN {
+        return Err(format!(
+            "account is {} bytes, expected at least {}",
+            data.len(),
+            POOL_MIN_LEN
+        ));
+    }
+    if data[..8] != POOL_DISCRIMINATOR {
+        return Err("account discriminator does not match PoolState".to_string());
+    }
+    let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).unwrap();
+    let u128_at =
+        |offset: usize| u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap());
+    let mut tick_array_bitmap = [0u64; TICK_ARRAY_BITMAP_WORDS];
+    for (index, word) in tick_array_bitmap.iter_mut().enumerate() {
+        let offset = TICK_ARRAY_BITMAP_OFFSET + index * 8;
+        *word = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    }
+    Ok(ClmmPoolState {
+        amm_config: pubkey_at(AMM_CONFIG_OFFSET),
+        token_mint_0: pubkey_at(TOKEN_MINT_0_OFFSET),
+        token_mint_1: pubkey_at(TOKEN_MINT_1_OFFSET),
+        token_vault_0: pubkey_at(TOKEN_VAULT_0_OFFSET),
+        token_vault_1: pubkey_at(TOKEN_VAULT_1_OFFSET),
+        mint_decimals_0: data[MINT_DECIMALS_0_OFFSET],
+        mint_decimals_1: data[MINT_DECIMALS_1_OFFSET],
+        tick_spacing: u16::from_le_bytes(
+            data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2]
+                .try_into()
+                .unwrap(),
+        ),
+        liquidity: u128_at(LIQUIDITY_OFFSET),
+        sqrt_price_x64: u128_at(SQRT_PRICE_OFFSET),
+        tick_current: i32::from_le_bytes(
+            data[TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4]
+                .try_into()
+                .unwrap(),
+        ),
+        status: data[STATUS_OFFSET],
+        tick_array_bitmap,
+    })
+}
+
+/// Trade fee rate of an `AmmConfig` account
+fn decode_amm_config(data: &[u8]) -> Result<u32, String> {
+    if data.len() < AMM_CONFIG_MIN_LEN {
+        return Err(format!(
+            "account is {} bytes, expected at least {}",
+            data.len(),
+            AMM_CONFIG_MIN_LEN
+        ));
+    }
+    if data[..8] != AMM_CONFIG_DISCRIMINATOR {
+        return Err("account discriminator does not match AmmConfig".to_string());
+    }
+    let fee_rate = u32::from_le_bytes(
+        data[TRADE_FEE_RATE_OFFSET..TRADE_FEE_RATE_OFFSET + 4]
+            .try_into()
+            .unwrap(),
+    );
+    if fee_rate >= FEE_RATE_DENOMINATOR {
+        return Err(format!("trade fee rate {} is not below 100%", fee_rate));
+    }
+    Ok(fee_rate)
+}
+
+/// Decode a tick array of `pool` expected to start at `start_tick_index`
+fn decode_tick_array(
+    data: &[u8],
+    pool: &Pubkey,
+    start_tick_index: i32,
+) -> Result<TickArray, String> {
+    if data.len() < TICK_ARRAY_MIN_LEN {
+        return Err(format!(
+            "account is {} bytes, expected at least {}",
+            data.len(),
+            TICK_ARRAY_MIN_LEN
+        ));
+    }
+    if data[..8] != TICK_ARRAY_DISCRIMINATOR {
+        return Err("account discriminator does not match TickArrayState".to_string());
+    }
+    if &data[TICK_ARRAY_POOL_OFFSET..TICK_ARRAY_POOL_OFFSET + 32] != pool.as_ref() {
+        return Err("tick array belongs to another pool".to_string());
+    }
+    let start = i32::from_le_bytes(
+        data[TICK_ARRAY_START_OFFSET..TICK_ARRAY_START_OFFSET + 4]
+            .try_into()
+            .unwrap(),
+    );
+    if start != start_tick_index {
+        return Err(format!(
+            "tick array starts at {}, expected {}",
+            start, start_tick_index
+        ));
+    }
+    let ticks = (0..TICK_ARRAY_SIZE as usize)
+        .map(|index| {
+            let offset = TICKS_OFFSET + index * TICK_STATE_LEN;
+            TickState {
+                tick: i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()),
+                liquidity_net: i128::from_le_bytes(
+                    data[offset + 4..offset + 20].try_into().unwrap(),
+                ),
+                liquidity_gross: u128::from_le_bytes(
+                    data[offset + 20..offset + 36].try_into().unwrap(),
+                ),
+            }
+        })
+        .collect();
+    Ok(TickArray {
+        start_tick_index,
+        ticks,
+    })
+}
+
+/// Square root price of a tick in Q64.64, bit for bit as the program computes it
+fn sqrt_price_at_tick(tick: i32) -> Result<u128, String> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return Err(format!("tick {} is out of range", tick));
+    }
+    let mut ratio = if abs_tick & 1 != 0 {
+        SQRT_PRICE_FACTORS[0]
+    } else {
+        Q64
+    };
+    for (bit, factor) in SQRT_PRICE_FACTORS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    Ok(ratio)
+}
+
+fn mul_div_floor(a: U512, b: U512, denominator: U512) -> U512 {
+    a * b / denominator
+}
+
+fn mul_div_ceil(a: U512, b: U512, denominator: U512) -> U512 {
+    div_ceil(a * b, denominator)
+}
+
+fn div_ceil(numerator: U512, denominator: U512) -> U512 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + U512::one()
+    }
+}
+
+/// Token 0 between two square root prices at `liquidity`, `None` beyond u64
+fn delta_amount_0(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u64> {
+    let (lower, upper) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    let numerator_1 = U512::from(liquidity) * U512::from(Q64);
+    let numerator_2 = U512::from(upper - lower);
+    let amount = if round_up {
+        div_ceil(
+            mul_div_ceil(numerator_1, numerator_2, U512::from(upper)),
+            U512::from(lower),
+        )
+    } else {
+        mul_div_floor(numerator_1, numerator_2, U512::from(upper)) / U512::from(lower)
+    };
+    u64::try_from(amount).ok()
+}
+
+/// Token 1 between two square root prices at `liquidity`, `None` beyond u64
+fn delta_amount_1(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u64> {
+    let (lower, upper) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    let (liquidity, delta, q64) = (
+        U512::from(liquidity),
+        U512::from(upper - lower),
+        U512::from(Q64),
+    );
+    let amount = if round_up {
+        mul_div_ceil(liquidity, delta, q64)
+    } else {
+        mul_div_floor(liquidity, delta, q64)
+    };
+    u64::try_from(amount).ok()
+}
+
+/// Square root price reached by adding `amount_in` to the pool, rounded against the trader
+fn next_sqrt_price_from_input(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Result<u128, String> {
+    if amount_in == 0 {
+        return Ok(sqrt_price_x64);
+    }
+    if zero_for_one {
+        let numerator = U512::from(liquidity) * U512::from(Q64);
+        let denominator = numerator + U512::from(amount_in) * U512::from(sqrt_price_x64);
+        u128::try_from(mul_div_ceil(
+            numerator,
+            U512::from(sqrt_price_x64),
+            denominator,
+        ))
+        .map_err(|_| "square root price overflow".to_string())
+    } else {
+        let quotient = (u128::from(amount_in) << 64) / liquidity;
+        sqrt_price_x64
+            .checked_add(quotient)
+            .ok_or_else(|| "square root price overflow".to_string())
+    }
+}
+
+/// Outcome of a swap within one liquidity range
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SwapStep {
+    sqrt_price_next_x64: u128,
+    amount_in: u64,
+    amount_out: u64,
+    fee_amount: u64,
+}
+
+/// Exact-in swap step from `sqrt_price_current_x64` towards `sqrt_price_target_x64`, mirroring
+/// the program's `compute_swap_step`: the fee comes off the input, and when the target is not
+/// reached the whole remaining input is consumed, its unswapped dust counted as fee
+fn compute_swap_step(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_rate: u32,
+    zero_for_one: bool,
+) -> Result<SwapStep, String> {
+    let amount_remaining_less_fee = (u128::from(amount_remaining)
+        * u128::from(FEE_RATE_DENOMINATOR - fee_rate)
+        / u128::from(FEE_RATE_DENOMINATOR)) as u64;
+    let amount_in_range = if zero_for_one {
+        delta_amount_0(
+            sqrt_price_target_x64,
+            sqrt_price_current_x64,
+            liquidity,
+            true,
+        )
+    } else {
+        delta_amount_1(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            true,
+        )
+    };
+    let sqrt_price_next_x64 = match amount_in_range {
+        Some(amount_in) if amount_remaining_less_fee >= amount_in => sqrt_price_target_x64,
+        _ => next_sqrt_price_from_input(
+            sqrt_price_current_x64,
+            liquidity,
+            amount_remaining_less_fee,
+            zero_for_one,
+        )?,
+    };
+
+    let reached_target = sqrt_price_next_x64 == sqrt_price_target_x64;
+    let overflow = || "swap step amount overflows u64".to_string();
+    let (amount_in, amount_out) = if zero_for_one {
+        let amount_in = if reached_target {
+            amount_in_range.unwrap_or_default()
+        } else {
+            delta_amount_0(sqrt_price_next_x64, sqrt_price_current_x64, liquidity, true)
+                .ok_or_else(overflow)?
+        };
+        let amount_out = delta_amount_1(
+            sqrt_price_next_x64,
+            sqrt_price_current_x64,
+            liquidity,
+            false,
+        )
+        .ok_or_else(overflow)?;
+        (amount_in, amount_out)
+    } else {
+        let amount_in = if reached_target {
+            amount_in_range.unwrap_or_default()
+        } else {
+            delta_amount_1(sqrt_price_current_x64, sqrt_price_next_x64, liquidity, true)
+                .ok_or_else(overflow)?
+        };
+        let amount_out = delta_amount_0(
+            sqrt_price_current_x64,
+            sqrt_price_next_x64,
+            liquidity,
+            false,
+        )
+        .ok_or_else(overflow)?;
+        (amount_in, amount_out)
+    };
+    let fee_amount = if reached_target {
+        u128::from(amount_in)
+            .checked_mul(u128::from(fee_rate))
+            .map(|fee| fee.div_ceil(u128::from(FEE_RATE_DENOMINATOR - fee_rate)))
+            .and_then(|fee| u64::try_from(fee).ok())
+            .ok_or_else(overflow)?
+    } else {
+        amount_remaining
+            .checked_sub(amount_in)
+            .ok_or_else(|| "swap step consumed more than the remaining input".to_string())?
+    };
+    Ok(SwapStep {
+        sqrt_price_next_x64,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Apply a signed liquidity change
+fn add_liquidity_delta(liquidity: u128, delta: i128) -> Result<u128, String> {
+    if delta < 0 {
+        liquidity.checked_sub(delta.unsigned_abs())
+    } else {
+        liquidity.checked_add(delta as u128)
+    }
+    .ok_or_else(|| format!("liquidity {} cannot change by {}", liquidity, delta))
+}
+
+/// Simulate an exact-in swap of `amount_in` the way the program's `swap_internal` walks the
+/// pool: range by range up to the next initialized tick, moving to the next initialized
+/// tick array of the bitmap when the current one has no tick left, and crossing ticks by
+/// their `liquidity_net`. Returns the output and the total fee, both in raw units.
+fn swap_exact_in(
+    state: &ClmmPoolState,
+    tick_arrays: &HashMap<i32, TickArray>,
+    fee_rate: u32,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Result<(u64, u64), String> {
+    let sqrt_price_limit_x64 = if zero_for_one {
+        MIN_SQRT_PRICE_X64 + 1
+    } else {
+        MAX_SQRT_PRICE_X64 - 1
+    };
+    let fetched = |start: i32| {
+        tick_arrays
+            .get(&start)
+            .ok_or_else(|| format!("{} (needs tick array {})", TICK_ARRAYS_EXHAUSTED, start))
+    };
+    let no_liquidity = || "pool ran out of liquidity".to_string();
+
+    let current_start = state.tick_array_start(state.tick_current);
+    // Whether the current tick's array was entered; otherwise the first array reached is
+    // entered at its first initialized tick
+    let (mut entered_current, mut array_start) = if state.is_tick_array_initialized(current_start) {
+        (true, current_start)
+    } else {
+        (
+            false,
+            state
+                .next_initialized_tick_array(current_start, zero_for_one)
+                .ok_or_else(no_liquidity)?,
+        )
+    };
+    let mut tick_array = fetched(array_start)?;
+
+    let mut amount_remaining = amount_in;
+    let mut amount_out: u64 = 0;
+    let mut fee: u64 = 0;
+    let mut sqrt_price_x64 = state.sqrt_price_x64;
+    let mut tick = state.tick_current;
+    let mut liquidity = state.liquidity;
+    while amount_remaining != 0
+        && sqrt_price_x64 != sqrt_price_limit_x64
+        && tick < MAX_TICK
+        && tick > MIN_TICK
+    {
+        let mut next_tick =
+            tick_array.next_initialized_tick(tick, state.tick_spacing, zero_for_one);
+        if next_tick.is_none() && !entered_current {
+            entered_current = true;
+            next_tick = tick_array.first_initialized_tick(zero_for_one);
+        }
+        let next_tick = match next_tick {
+            Some(next_tick) => *next_tick,
+            None => {
+                array_start = state
+                    .next_initialized_tick_array(array_start, zero_for_one)
+                    .ok_or_else(no_liquidity)?;
+                tick_array = fetched(array_start)?;
+                *tick_array
+                    .first_initialized_tick(zero_for_one)
+                    .ok_or_else(|| format!("tick array {} has no initialized tick", array_start))?
+            }
+        };
+
+        let tick_next = next_tick.tick.clamp(MIN_TICK, MAX_TICK);
+        let sqrt_price_next_x64 = sqrt_price_at_tick(tick_next)?;
+        let sqrt_price_target_x64 = if zero_for_one {
+            sqrt_price_next_x64.max(sqrt_price_limit_x64)
+        } else {
+            sqrt_price_next_x64.min(sqrt_price_limit_x64)
+        };
+        let step = compute_swap_step(
+            sqrt_price_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            amount_remaining,
+            fee_rate,
+            zero_for_one,
+        )?;
+        sqrt_price_x64 = step.sqrt_price_next_x64;
+        amount_remaining = amount_remaining
+            .checked_sub(step.amount_in + step.fee_amount)
+            .ok_or("swap consumed more than its input")?;
+        amount_out = amount_out
+            .checked_add(step.amount_out)
+            .ok_or("swap output overflows u64")?;
+        fee += step.fee_amount;
+
+        if sqrt_price_x64 == sqrt_price_next_x64 {
+            let liquidity_net = if zero_for_one {
+                -next_tick.liquidity_net
+            } else {
+                next_tick.liquidity_net
+            };
+            liquidity = add_liquidity_delta(liquidity, liquidity_net)?;
+            tick = if zero_for_one {
+                tick_next - 1
+            } else {
+                tick_next
+            };
+        }
+        // A step stopping short of its tick consumed the whole remaining input, so the loop
+        // ends without needing the tick of the new price
+    }
+    if amount_remaining != 0 {
+        return Err(format!(
+            "swap reached the price limit with {} input left",
+            amount_remaining
+        ));
+    }
+    Ok((amount_out, fee))
+}
+
+/// Quote an exact-in swap against the snapshot with the program's integer math.
+/// Only the trade fee applies; Token-2022 transfer fees are not deducted.
+fn quote_exact_in(
+    snapshot: &ClmmSnapshot,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Result<SwapQuote, Box<dyn std::error::Error>> {
+    let (amount_out, fee) = swap_exact_in(
+        &snapshot.state,
+        &snapshot.tick_arrays,
+        snapshot.trade_fee_rate,
+        amount_in,
+        zero_for_one,
+    )
+    .map_err(|e| format!("Raydium CLMM pool {}: {}", snapshot.pool, e))?;
+    Ok(SwapQuote::without_transfer_fees(amount_in, amount_out, fee))
+}
+
+/// Run `quote` with the configured tick array count and, when the swap walked past the
+/// fetched tick arrays, retry once with twice as many (capped at `MAX_TICK_ARRAY_COUNT`)
+async fn retry_with_more_tick_arrays<F, Fut>(
+    symbol: &str,
+    tick_array_count: u8,
+    mut quote: F,
+) -> Result<PriceQuote, Box<dyn std::error::Error>>
+where
+    F: FnMut(u8) -> Fut,
+    Fut: Future<Output = Result<PriceQuote, Box<dyn std::error::Error>>>,
+{
+    // Decide on the retry before awaiting again so the error is not held across the await
+    let retry_count = match quote(tick_array_count).await {
+        Err(e)
+            if e.to_string().contains(TICK_ARRAYS_EXHAUSTED)
+                && tick_array_count < MAX_TICK_ARRAY_COUNT =>
+        {
+            let retry_count = tick_array_count.saturating_mul(2).min(MAX_TICK_ARRAY_COUNT);
+            warn!(
+                "Raydium CLMM quote for {} failed with {} tick arrays ({}), retrying with {}; the configured bin_array_count is too small",
+                symbol, tick_array_count, e, retry_count
+            );
+            retry_count
+        }
+        result => return result,
+    };
+    quote(retry_count).await
+}
+
+/// Quote tokens per base token at the pool's square root price, before fees and price impact
+fn spot_price(state: &ClmmPoolState, base_is_0: bool) -> Option<Decimal> {
+    let sqrt_price =
+        Decimal::from_u128(state.sqrt_price_x64)?.checked_div(Decimal::from_u128(Q64)?)?;
+    // Token 1 per token 0, in UI units
+    let price = sqrt_price
+        .checked_mul(sqrt_price)?
+        .checked_mul(Decimal::from(
+            10u64.checked_pow(state.mint_decimals_0.into())?,
+        ))?
+        .checked_div(Decimal::from(
+            10u64.checked_pow(state.mint_decimals_1.into())?,
+        ))?;
+    let price = if base_is_0 {
+        price
+    } else {
+        Decimal::ONE.checked_div(price)?
+    };
+    (price > Decimal::ZERO).then_some(price)
+}
+
+fn build_price_quote(
+    symbol: &str,
+    snapshot: &ClmmSnapshot,
+    base_is_0: bool,
+    sell: SwapQuote,
+    buy: SwapQuote,
+) -> PriceQuote {
+    let state = &snapshot.state;
+    let (base_decimals, quote_decimals, base_reserve, quote_reserve) = if base_is_0 {
+        (
+            u32::from(state.mint_decimals_0),
+            u32::from(state.mint_decimals_1),
+            snapshot.reserve_0,
+            snapshot.reserve_1,
+        )
+    } else {
+        (
+            u32::from(state.mint_decimals_1),
+            u32::from(state.mint_decimals_0),
+            snapshot.reserve_1,
+            snapshot.reserve_0,
+        )
+    };
+    let (bid_price, ask_price) = derive_bid_ask(&sell, &buy, base_decimals, quote_decimals);
+    let spot_price = spot_price(state, base_is_0);
+
+    PriceQuote {
+        symbol: symbol.to_string(),
+        pool: snapshot.pool,
+        route: None,
+        hops: Vec::new(),
+        slot: snapshot.clock.slot,
+        block_time: snapshot.block_time(),
+        fetched_at: snapshot.fetched_at,
+        fetch_latency: snapshot.fetch_latency,
+        commitment: snapshot.commitment,
+        stale: snapshot.stale,
+        missing_bin_arrays: 0,
+        base_decimals,
+        quote_decimals,
+        bid_impact_bps: spot_price.and_then(|spot| price_impact_bps(bid_price, spot)),
+        ask_impact_bps: spot_price.and_then(|spot| price_impact_bps(ask_price, spot)),
+        sell_fee_pct: fee_pct(&sell),
+        buy_fee_pct: fee_pct(&buy),
+        fee_rate: None,
+        sell,
+        buy,
+        bid_price,
+        ask_price,
+        spot_price,
+        liquidity: PoolLiquidity::new(
+            base_reserve as u128,
+            quote_reserve as u128,
+            base_decimals,
+            quote_decimals,
+            spot_price,
+        ),
+        landing_cost: 0,
+        net_amount_out: None,
+    }
+}
+
+#[cfg(test)]
+#[path = "raydium_clmm_tests.rs"]
+mod raydium_clmm_tests;
@@ -0,0 +1,419 @@
+use super::*;
+
+const TRUMP: u64 = 1_000_000;
+const USDC: u64 = 1_000_000;
+
+/// TRUMP (token 0) / USDC (token 1) pool at ~10 USDC, both with 6 decimals, a 0.25% fee and
+/// a tick spacing of 10, so tick arrays span 600 ticks. The price sits inside tick 23027,
+/// between the initialized ticks 22980 and 23100 of the current array. Expected amounts
+/// below are reproduced with an exact integer replay of the program's `swap_internal`.
+fn fixture_state() -> ClmmPoolState {
+    let mut tick_array_bitmap = [0u64; TICK_ARRAY_BITMAP_WORDS];
+    // Arrays 22200, 22800 and 23400 are bits 549, 550 and 551
+    tick_array_bitmap[8] = 0b111 << 37;
+    ClmmPoolState {
+        amm_config: Pubkey::new_unique(),
+        token_mint_0: Pubkey::new_unique(),
+        token_mint_1: Pubkey::new_unique(),
+        token_vault_0: Pubkey::new_unique(),
+        token_vault_1: Pubkey::new_unique(),
+        mint_decimals_0: 6,
+        mint_decimals_1: 6,
+        tick_spacing: 10,
+        liquidity: 25_000_000_000_000,
+        sqrt_price_x64: 58_333_732_606_780_105_402,
+        tick_current: 23_027,
+        status: 0,
+        tick_array_bitmap,
+    }
+}
+
+fn tick_array(start_tick_index: i32, liquidity_nets: &[(i32, i128)]) -> TickArray {
+    let mut ticks: Vec<TickState> = (0..TICK_ARRAY_SIZE)
+        .map(|index| TickState {
+            tick: start_tick_index + index * 10,
+            ..TickState::default()
+        })
+        .collect();
+    for &(tick, liquidity_net) in liquidity_nets {
+        let tick_state = &mut ticks[((tick - start_tick_index) / 10) as usize];
+        tick_state.liquidity_net = liquidity_net;
+        tick_state.liquidity_gross = liquidity_net.unsigned_abs();
+    }
+    TickArray {
+        start_tick_index,
+        ticks,
+    }
+}
+
+fn fixture_tick_arrays() -> HashMap<i32, TickArray> {
+    [
+        tick_array(22_200, &[(22_200, 5_000_000_000_000)]),
+        tick_array(
+            22_800,
+            &[
+                (22_800, 10_000_000_000_000),
+                (22_980, 10_000_000_000_000),
+                (23_100, -10_000_000_000_000),
+            ],
+        ),
+        tick_array(
+            23_400,
+            &[(23_400, -10_000_000_000_000), (23_700, -5_000_000_000_000)],
+        ),
+    ]
+    .into_iter()
+    .map(|tick_array| (tick_array.start_tick_index, tick_array))
+    .collect()
+}
+
+fn fixture_snapshot() -> ClmmSnapshot {
+    ClmmSnapshot {
+        pool: Pubkey::new_unique(),
+        state: fixture_state(),
+        trade_fee_rate: 2_500,
+        tick_arrays: fixture_tick_arrays(),
+        reserve_0: 2_000_000 * TRUMP,
+        reserve_1: 20_000_000 * USDC,
+        clock: Clock {
+            slot: 42,
+            unix_timestamp: 1_700_000_000,
+            ..Clock::default()
+        },
+        commitment: CommitmentLevel::Confirmed,
+        stale: false,
+        fetched_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        fetch_latency: Duration::from_millis(40),
+    }
+}
+
+fn swap(amount_in: u64, zero_for_one: bool) -> Result<(u64, u64), String> {
+    swap_exact_in(
+        &fixture_state(),
+        &fixture_tick_arrays(),
+        2_500,
+        amount_in,
+        zero_for_one,
+    )
+}
+
+fn pool_account_data(state: &ClmmPoolState) -> Vec<u8> {
+    let mut data = vec![0u8; 1544];
+    data[..8].copy_from_slice(&POOL_DISCRIMINATOR);
+    for (offset, pubkey) in [
+        (AMM_CONFIG_OFFSET, state.amm_config),
+        (TOKEN_MINT_0_OFFSET, state.token_mint_0),
+        (TOKEN_MINT_1_OFFSET, state.token_mint_1),
+        (TOKEN_VAULT_0_OFFSET, state.token_vault_0),
+        (TOKEN_VAULT_1_OFFSET, state.token_vault_1),
+    ] {
+        data[offset..offset + 32].copy_from_slice(pubkey.as_ref());
+    }
+    data[MINT_DECIMALS_0_OFFSET] = state.mint_decimals_0;
+    data[MINT_DECIMALS_1_OFFSET] = state.mint_decimals_1;
+    data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2]
+        .copy_from_slice(&state.tick_spacing.to_le_bytes());
+    data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&state.liquidity.to_le_bytes());
+    data[SQRT_PRICE_OFFSET..SQRT_PRICE_OFFSET + 16]
+        .copy_from_slice(&state.sqrt_price_x64.to_le_bytes());
+    data[TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4]
+        .copy_from_slice(&state.tick_current.to_le_bytes());
+    data[STATUS_OFFSET] = state.status;
+    for (index, word) in state.tick_array_bitmap.iter().enumerate() {
+        let offset = TICK_ARRAY_BITMAP_OFFSET + index * 8;
+        data[offset..offset + 8].copy_from_slice(&word.to_le_bytes());
+    }
+    data
+}
+
+fn tick_array_account_data(pool: &Pubkey, tick_array: &TickArray) -> Vec<u8> {
+    let mut data = vec![0u8; 10_240];
+    data[..8].copy_from_slice(&TICK_ARRAY_DISCRIMINATOR);
+    data[TICK_ARRAY_POOL_OFFSET..TICK_ARRAY_POOL_OFFSET + 32].copy_from_slice(pool.as_ref());
+    data[TICK_ARRAY_START_OFFSET..TICK_ARRAY_START_OFFSET + 4]
+        .copy_from_slice(&tick_array.start_tick_index.to_le_bytes());
+    for (index, tick) in tick_array.ticks.iter().enumerate() {
+        let offset = TICKS_OFFSET + index * TICK_STATE_LEN;
+        data[offset..offset + 4].copy_from_slice(&tick.tick.to_le_bytes());
+        data[offset + 4..offset + 20].copy_from_slice(&tick.liquidity_net.to_le_bytes());
+        data[offset + 20..offset + 36].copy_from_slice(&tick.liquidity_gross.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn sqrt_price_at_tick_matches_the_program() {
+    assert_eq!(sqrt_price_at_tick(0).unwrap(), Q64);
+    assert_eq!(sqrt_price_at_tick(MIN_TICK).unwrap(), MIN_SQRT_PRICE_X64);
+    assert_eq!(sqrt_price_at_tick(MAX_TICK).unwrap(), MAX_SQRT_PRICE_X64);
+    assert_eq!(
+        sqrt_price_at_tick(23_027).unwrap(),
+        58_333_720_261_101_204_168
+    );
+    assert_eq!(
+        sqrt_price_at_tick(-23_027).unwrap(),
+        5_833_373_311_316_982_142
+    );
+    assert!(sqrt_price_at_tick(MAX_TICK + 1).is_err());
+    assert!(sqrt_price_at_tick(MIN_TICK - 1).is_err());
+}
+
+#[test]
+fn tick_array_start_rounds_down_below_zero() {
+    let state = fixture_state();
+
+    assert_eq!(state.tick_array_start(23_027), 22_800);
+    assert_eq!(state.tick_array_start(599), 0);
+    assert_eq!(state.tick_array_start(-1), -600);
+    assert_eq!(state.tick_array_start(-600), -600);
+    assert_eq!(state.tick_array_start(-601), -1_200);
+}
+
+#[test]
+fn tick_array_starts_follow_the_bitmap_away_from_the_current_tick() {
+    let state = fixture_state();
+
+    assert_eq!(
+        state.tick_array_starts_for_swap(true, 4).unwrap(),
+        [22_800, 22_200]
+    );
+    assert_eq!(
+        state.tick_array_starts_for_swap(false, 4).unwrap(),
+        [22_800, 23_400]
+    );
+    assert_eq!(state.tick_array_starts_for_swap(true, 1).unwrap(), [22_800]);
+}
+
+#[test]
+fn tick_array_starts_skip_an_uninitialized_current_array() {
+    let mut state = fixture_state();
+    // Clear array 22800
+    state.tick_array_bitmap[8] &= !(1 << 38);
+
+    assert_eq!(state.tick_array_starts_for_swap(true, 4).unwrap(), [22_200]);
+    assert_eq!(
+        state.tick_array_starts_for_swap(false, 4).unwrap(),
+        [23_400]
+    );
+}
+
+#[test]
+fn tick_array_starts_reject_ticks_beyond_the_bitmap() {
+    let state = ClmmPoolState {
+        tick_spacing: 1,
+        tick_current: 400_000,
+        ..fixture_state()
+    };
+
+    assert!(state.tick_array_starts_for_swap(true, 4).is_err());
+}
+
+#[test]
+fn decode_pool_reads_quoting_fields() {
+    let state = fixture_state();
+
+    assert_eq!(decode_pool(&pool_account_data(&state)).unwrap(), state);
+}
+
+#[test]
+fn decode_pool_rejects_other_accounts() {
+    let data = pool_account_data(&fixture_state());
+    let mut wrong_discriminator = data.clone();
+    wrong_discriminator[0] ^= 1;
+
+    assert!(decode_pool(&data[..POOL_MIN_LEN - 1]).is_err());
+    assert!(decode_pool(&wrong_discriminator).is_err());
+}
+
+#[test]
+fn decode_pool_reads_the_swap_disabled_status() {
+    let state = ClmmPoolState {
+        status: SWAP_DISABLED_STATUS_BIT,
+        ..fixture_state()
+    };
+
+    assert!(
+        decode_pool(&pool_account_data(&state))
+            .unwrap()
+            .swap_disabled()
+    );
+    assert!(!fixture_state().swap_disabled());
+}
+
+#[test]
+fn decode_amm_config_reads_the_trade_fee_rate() {
+    let mut data = vec![0u8; 117];
+    data[..8].copy_from_slice(&AMM_CONFIG_DISCRIMINATOR);
+    data[TRADE_FEE_RATE_OFFSET..TRADE_FEE_RATE_OFFSET + 4].copy_from_slice(&2_500u32.to_le_bytes());
+
+    assert_eq!(decode_amm_config(&data).unwrap(), 2_500);
+    assert!(decode_amm_config(&data[..AMM_CONFIG_MIN_LEN - 1]).is_err());
+    data[0] ^= 1;
+    assert!(decode_amm_config(&data).is_err());
+}
+
+#[test]
+fn decode_tick_array_reads_ticks_of_its_pool() {
+    let pool = Pubkey::new_unique();
+    let tick_array = fixture_tick_arrays().remove(&22_800).unwrap();
+    let data = tick_array_account_data(&pool, &tick_array);
+
+    assert_eq!(decode_tick_array(&data, &pool, 22_800).unwrap(), tick_array);
+    assert!(decode_tick_array(&data, &Pubkey::new_unique(), 22_800).is_err());
+    assert!(decode_tick_array(&data, &pool, 23_400).is_err());
+    assert!(decode_tick_array(&data[..TICK_ARRAY_MIN_LEN - 1], &pool, 22_800).is_err());
+}
+
+#[test]
+fn tick_array_address_is_derived_from_pool_and_big_endian_start() {
+    let pool = Pubkey::new_unique();
+    let expected = Pubkey::find_program_address(
+        &[b"tick_array", pool.as_ref(), &[0xff, 0xff, 0xfd, 0xa8]],
+        &CLMM_PROGRAM_ID,
+    )
+    .0;
+
+    assert_eq!(tick_array_address(&pool, -600), expected);
+    assert_ne!(tick_array_address(&pool, 600), expected);
+}
+
+#[test]
+fn swap_within_the_current_range() {
+    // 1,000 TRUMP for USDC without crossing a tick
+    assert_eq!(
+        swap(1_000 * TRUMP, true).unwrap(),
+        (9_973_743_588, 2_500_000)
+    );
+}
+
+#[test]
+fn swap_crossing_ticks_of_the_current_array() {
+    // Crosses tick 22980, leaving 15M of liquidity
+    assert_eq!(
+        swap(50_000 * TRUMP, true).unwrap(),
+        (494_812_907_930, 125_000_001)
+    );
+    // Crosses tick 23100 upwards
+    assert_eq!(
+        swap(1_000_000 * USDC, false).unwrap(),
+        (98_098_229_671, 2_500_000_001)
+    );
+}
+
+#[test]
+fn swap_walks_into_the_next_initialized_tick_array() {
+    // Crosses 22980 and 22800, ending in array 22200
+    assert_eq!(
+        swap(100_000 * TRUMP, true).unwrap(),
+        (973_952_204_636, 250_000_001)
+    );
+    // Crosses 23100 and 23400, ending in array 23400
+    assert_eq!(
+        swap(1_200_000 * USDC, false).unwrap(),
+        (117_108_075_234, 3_000_000_001)
+    );
+}
+
+#[test]
+fn swap_counts_dust_left_after_rounding_as_fee() {
+    assert_eq!(swap(7, true).unwrap(), (60, 1));
+    assert_eq!(swap(7, false).unwrap(), (0, 1));
+}
+
+#[test]
+fn swap_fails_when_it_needs_an_unfetched_tick_array() {
+    let mut tick_arrays = fixture_tick_arrays();
+    tick_arrays.retain(|&start, _| start == 22_800);
+    let swap = |amount_in, zero_for_one| {
+        swap_exact_in(
+            &fixture_state(),
+            &tick_arrays,
+            2_500,
+            amount_in,
+            zero_for_one,
+        )
+    };
+
+    let error = swap(100_000 * TRUMP, true).unwrap_err();
+    assert!(error.contains(TICK_ARRAYS_EXHAUSTED), "{}", error);
+    let error = swap(1_200_000 * USDC, false).unwrap_err();
+    assert!(error.contains(TICK_ARRAYS_EXHAUSTED), "{}", error);
+    // Swaps staying within the fetched array still quote
+    assert_eq!(
+        swap(50_000 * TRUMP, true).unwrap(),
+        (494_812_907_930, 125_000_001)
+    );
+}
+
+#[test]
+fn swap_fails_when_the_pool_runs_out_of_liquidity() {
+    assert!(swap(u64::MAX / 4, true).is_err());
+    assert!(swap(u64::MAX / 4, false).is_err());
+}
+
+#[test]
+fn build_price_quote_brackets_the_pool_price() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 1_000 * TRUMP, true).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, false).unwrap();
+
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, true, sell, buy);
+
+    let spot = quote.spot_price.unwrap();
+    assert_eq!(spot.round_dp(6), Decimal::new(10_000_002, 6));
+    assert!(quote.bid_price < spot && spot < quote.ask_price);
+    assert_eq!(quote.base_decimals, 6);
+    assert_eq!(quote.sell.amount_out, 9_973_743_588);
+}
+
+#[test]
+fn build_price_quote_inverts_when_base_is_token_1() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 10_000 * USDC, false).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, true).unwrap();
+
+    let quote = build_price_quote("USDCTRUMP", &snapshot, false, sell, buy);
+
+    let spot = quote.spot_price.unwrap();
+    assert_eq!(spot.round_dp(4), Decimal::new(1_000, 4));
+    assert!(quote.bid_price < spot && spot < quote.ask_price);
+}
+
+#[tokio::test]
+async fn a_tick_array_shortage_is_retried_with_twice_as_many() {
+    let snapshot = fixture_snapshot();
+    let sell = quote_exact_in(&snapshot, 1_000 * TRUMP, true).unwrap();
+    let buy = quote_exact_in(&snapshot, sell.amount_out, false).unwrap();
+    let quote = build_price_quote("TRUMPUSDC", &snapshot, true, sell, buy);
+    let counts = std::sync::Mutex::new(Vec::new());
+
+    let result = retry_with_more_tick_arrays("TRUMPUSDC", 4, |count| {
+        counts.lock().unwrap().push(count);
+        let quote = quote.clone();
+        async move {
+            if count < 8 {
+                Err(TICK_ARRAYS_EXHAUSTED.into())
+            } else {
+                Ok(quote)
+            }
+        }
+    })
+    .await;
+
+    assert!(result.is_ok());
+    assert_eq!(*counts.lock().unwrap(), [4, 8]);
+}
+
+#[tokio::test]
+async fn other_quote_errors_are_not_retried() {
+    let counts = std::sync::Mutex::new(Vec::new());
+
+    let result = retry_with_more_tick_arrays("TRUMPUSDC", 4, |count| {
+        counts.lock().unwrap().push(count);
+        async { Err("pool ran out of liquidity".into()) }
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(*counts.lock().unwrap(), [4]);
+}
@@ -0,0 +1,171 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use super::backpack::BackpackScreener;
+use super::binance::BinanceScreener;
+use super::bitget::BitgetScreener;
+use super::bybit::BybitScreener;
+use super::coinbase::CoinbaseScreener;
+use super::gate::GateScreener;
+use super::htx::HtxScreener;
+use super::hyperliquid::HyperliquidScreener;
+use super::jupiter::JupiterScreener;
+use super::kraken::KrakenScreener;
+use super::kucoin::KuCoinScreener;
+use super::meteora::MeteoraScreener;
+use super::meteora_damm::DammScreener;
+use super::mexc::MexcScreener;
+use super::okx::OKXScreener;
+use super::phoenix::PhoenixScreener;
+use super::pumpfun::PumpFunScreener;
+use super::raydium_amm::RaydiumAmmScreener;
+use super::raydium_clmm::RaydiumClmmScreener;
+
+/// Why a screener failed to run or stop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenerError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ScreenerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ScreenerError {}
+
+impl From<Box<dyn std::error::Error>> for ScreenerError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        Self {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A market data feed run as its own task: `start` streams until `stop` is called. `start`
+/// takes the `Arc` so a screener can hand itself to the tasks it spawns, as Meteora does.
+#[async_trait]
+pub trait Screener: Send + Sync {
+    async fn start(self: Arc<Self>) -> Result<(), ScreenerError>;
+    async fn stop(&self) -> Result<(), ScreenerError>;
+    /// Name used in logs, e.g. `Bybit`
+    fn name(&self) -> &str;
+}
+
+/// Implement `Screener` on the inherent `start`/`stop` of a screener type; `shared` marks a
+/// `start` taking `self: Arc<Self>`
+macro_rules! impl_screener {
+    (@impl $screener:ty, $name:literal, $this:ident => $start:expr) => {
+        #[async_trait]
+        impl Screener for $screener {
+            async fn start(self: Arc<Self>) -> Result<(), ScreenerError> {
+                let $this = self;
+                Ok($start.await?)
+            }
+
+            async fn stop(&self) -> Result<(), ScreenerError> {
+                Ok(<$screener>::stop(self).await?)
+            }
+
+            fn name(&self) -> &str {
+                $name
+            }
+        }
+    };
+    ($screener:ty, $name:literal) => {
+        impl_screener!(@impl $screener, $name, screener => <$screener>::start(&screener));
+    };
+    ($screener:ty, $name:literal, shared) => {
+        impl_screener!(@impl $screener, $name, screener => <$screener>::start(screener));
+    };
+}
+
+impl_screener!(MeteoraScreener, "Meteora", shared);
+impl_screener!(DammScreener, "Meteora DAMM", shared);
+impl_screener!(RaydiumClmmScreener, "Raydium CLMM", shared);
+impl_screener!(RaydiumAmmScreener, "Raydium AMM", shared);
+impl_screener!(PumpFunScreener, "Pump.fun", shared);
+impl_screener!(PhoenixScreener, "Phoenix", shared);
+impl_screener!(JupiterScreener, "Jupiter", shared);
+impl_screener!(BybitScreener, "Bybit");
+impl_screener!(BinanceScreener, "Binance");
+impl_screener!(OKXScreener, "OKX");
+impl_screener!(CoinbaseScreener, "Coinbase");
+impl_screener!(KrakenScreener, "Kraken");
+impl_screener!(GateScreener, "Gate");
+impl_screener!(KuCoinScreener, "KuCoin");
+impl_screener!(MexcScreener, "MEXC");
+impl_screener!(BitgetScreener, "Bitget");
+impl_screener!(HtxScreener, "HTX");
+impl_screener!(HyperliquidScreener, "Hyperliquid");
+impl_screener!(BackpackScreener, "Backpack");
+
+/// Failure of one screener, with its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenerFailure {
+    pub name: String,
+    pub error: ScreenerError,
+}
+
+/// Screeners running on their own tasks, in start order
+pub struct ScreenerTasks {
+    running: Vec<(Arc<dyn Screener>, JoinHandle<Result<(), ScreenerError>>)>,
+}
+
+impl ScreenerTasks {
+    /// Spawn the `start` of every screener; a screener that fails is logged with its name
+    /// while the others keep running
+    pub fn spawn(screeners: Vec<Arc<dyn Screener>>) -> Self {
+        let running = screeners
+            .into_iter()
+            .map(|screener| {
+                info!("Starting {} screener...", screener.name());
+                let task_screener = screener.clone();
+                let handle = tokio::spawn(async move {
+                    let name = task_screener.name().to_string();
+                    let result = task_screener.start().await;
+                    if let Err(e) = &result {
+                        error!("{} screener failed: {}", name, e);
+                    }
+                    result
+                });
+                (screener, handle)
+            })
+            .collect();
+        Self { running }
+    }
+
+    /// Stop every screener in start order and wait for its task. Every screener is stopped
+    /// even when others fail; the failures of `start`, `stop` or a panicked task are returned
+    /// with the screener's name.
+    pub async fn stop_all(self) -> Vec<ScreenerFailure> {
+        let mut failures = Vec::new();
+        for (screener, handle) in self.running {
+            let name = screener.name().to_string();
+            if let Err(error) = screener.stop().await {
+                error!("{} screener failed to stop: {}", name, error);
+                failures.push(ScreenerFailure {
+                    name: name.clone(),
+                    error,
+                });
+            }
+            let result = match handle.await {
+                Ok(result) => result,
+                Err(e) => Err(ScreenerError {
+                    message: format!("task failed: {}", e),
+                }),
+            };
+            if let Err(error) = result {
+                failures.push(ScreenerFailure { name, error });
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+#[path = "screener_tests.rs"]
+mod screener_tests;
@@ -0,0 +1,157 @@
+use super::*;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Screener running until stopped, or failing right away with `start_error`
+struct DummyScreener {
+    name: &'static str,
+    start_error: Option<&'static str>,
+    stop_error: Option<&'static str>,
+    shutdown: CancellationToken,
+    /// Calls made on every dummy of a test, in order
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl DummyScreener {
+    fn new(name: &'static str, calls: &Arc<Mutex<Vec<String>>>) -> Self {
+        Self {
+            name,
+            start_error: None,
+            stop_error: None,
+            shutdown: CancellationToken::new(),
+            calls: calls.clone(),
+        }
+    }
+
+    fn record(&self, call: &str) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", call, self.name));
+    }
+}
+
+#[async_trait]
+impl Screener for DummyScreener {
+    async fn start(self: Arc<Self>) -> Result<(), ScreenerError> {
+        self.record("start");
+        if let Some(message) = self.start_error {
+            return Err(ScreenerError {
+                message: message.to_string(),
+            });
+        }
+        self.shutdown.cancelled().await;
+        self.record("stopped");
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<(), ScreenerError> {
+        self.record("stop");
+        self.shutdown.cancel();
+        match self.stop_error {
+            Some(message) => Err(ScreenerError {
+                message: message.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+#[tokio::test]
+async fn every_screener_is_started_then_stopped_in_order() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let screeners: Vec<Arc<dyn Screener>> = vec![
+        Arc::new(DummyScreener::new("Meteora", &calls)),
+        Arc::new(DummyScreener::new("Bybit", &calls)),
+    ];
+
+    let tasks = ScreenerTasks::spawn(screeners);
+    tokio::task::yield_now().await;
+    assert_eq!(*calls.lock().unwrap(), ["start Meteora", "start Bybit"]);
+
+    assert!(tasks.stop_all().await.is_empty());
+    assert_eq!(
+        calls.lock().unwrap()[2..],
+        [
+            "stop Meteora",
+            "stopped Meteora",
+            "stop Bybit",
+            "stopped Bybit"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn failures_are_reported_with_the_screener_name() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let failing_start = DummyScreener {
+        start_error: Some("no trade pairs table"),
+        ..DummyScreener::new("Meteora", &calls)
+    };
+    let failing_stop = DummyScreener {
+        stop_error: Some("already stopped"),
+        ..DummyScreener::new("OKX", &calls)
+    };
+    let screeners: Vec<Arc<dyn Screener>> = vec![
+        Arc::new(failing_start),
+        Arc::new(failing_stop),
+        Arc::new(DummyScreener::new("Bybit", &calls)),
+    ];
+
+    let failures = ScreenerTasks::spawn(screeners).stop_all().await;
+
+    assert_eq!(
+        failures,
+        [
+            ScreenerFailure {
+                name: "Meteora".to_string(),
+                error: ScreenerError {
+                    message: "no trade pairs table".to_string()
+                },
+            },
+            ScreenerFailure {
+                name: "OKX".to_string(),
+                error: ScreenerError {
+                    message: "already stopped".to_string()
+                },
+            },
+        ]
+    );
+    // A failed screener does not keep the others from being stopped
+    assert!(calls.lock().unwrap().contains(&"stopped Bybit".to_string()));
+}
+
+#[tokio::test]
+async fn a_panicked_screener_is_reported_as_failed() {
+    struct PanickingScreener;
+
+    #[async_trait]
+    impl Screener for PanickingScreener {
+        async fn start(self: Arc<Self>) -> Result<(), ScreenerError> {
+            panic!("order book invariant broken");
+        }
+
+        async fn stop(&self) -> Result<(), ScreenerError> {
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "Kraken"
+        }
+    }
+
+    let screeners: Vec<Arc<dyn Screener>> = vec![Arc::new(PanickingScreener)];
+    let failures = ScreenerTasks::spawn(screeners).stop_all().await;
+
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].name, "Kraken");
+    assert!(
+        failures[0].error.message.contains("panicked"),
+        "{}",
+        failures[0].error
+    );
+}
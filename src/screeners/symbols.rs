@@ -0,0 +1,256 @@
+use std::sync::OnceLock;
+
+/// Quote assets an internal symbol such as `TRUMPUSDC` can end with, longest first so
+/// `FDUSD` wins over `USD`
+const QUOTE_ASSETS: [&str; 7] = ["FDUSD", "USDC", "USDT", "EUR", "USD", "BTC", "ETH"];
+
+/// Whether `symbol` looks like an internal spot symbol, as Bybit and Binance spell them:
+/// uppercase letters and digits
+pub(crate) fn is_valid_symbol(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && symbol
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Split an internal symbol into its base and quote assets, `TRUMPUSDC` → (`TRUMP`, `USDC`)
+pub fn split_symbol(symbol: &str) -> Option<(&str, &str)> {
+    if !is_valid_symbol(symbol) {
+        return None;
+    }
+    QUOTE_ASSETS.iter().find_map(|quote| {
+        symbol
+            .strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| (base, *quote))
+    })
+}
+
+/// Canonical spot pair. Its internal symbol, `BASEQUOTE`, is what every screener persists
+/// as `trade_pair`, whatever the venue calls it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TradingPair {
+    pub base: String,
+    pub quote: String,
+}
+
+impl TradingPair {
+    /// Pair of an internal symbol, `TRUMPUSDC` → `TRUMP`/`USDC`
+    pub fn parse(symbol: &str) -> Option<Self> {
+        let (base, quote) = split_symbol(symbol)?;
+        Some(Self {
+            base: base.to_string(),
+            quote: quote.to_string(),
+        })
+    }
+
+    /// Internal symbol of the pair, `TRUMPUSDC`
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.base, self.quote)
+    }
+}
+
+/// How a venue spells a pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolFormat {
+    /// `TRUMPUSDC`, like internal symbols
+    Concatenated,
+    /// `TRUMP-USDC` with `-`, `TRUMP/USD` with `/`, ...
+    Separated(char),
+    /// `trumpusdc`
+    Lowercase,
+}
+
+impl SymbolFormat {
+    pub fn format(self, pair: &TradingPair) -> String {
+        match self {
+            Self::Concatenated => pair.symbol(),
+            Self::Separated(separator) => format!("{}{}{}", pair.base, separator, pair.quote),
+            Self::Lowercase => pair.symbol().to_lowercase(),
+        }
+    }
+
+    /// Pair of a venue symbol, or `None` when the venue would not spell a spot pair this way
+    pub fn parse(self, venue_symbol: &str) -> Option<TradingPair> {
+        match self {
+            Self::Concatenated => TradingPair::parse(venue_symbol),
+            Self::Separated(separator) => {
+                let (base, quote) = venue_symbol.split_once(separator)?;
+                // The quote asset is not checked, so pairs listed on a single venue still map
+                (is_valid_symbol(base) && is_valid_symbol(quote)).then(|| TradingPair {
+                    base: base.to_string(),
+                    quote: quote.to_string(),
+                })
+            }
+            Self::Lowercase => {
+                let symbol = venue_symbol.to_uppercase();
+                if venue_symbol != symbol.to_lowercase() {
+                    return None;
+                }
+                TradingPair::parse(&symbol)
+            }
+        }
+    }
+}
+
+/// Spelling of every venue mapped through this module, by exchange name as persisted in
+/// `cex_markets`. Bybit subscribes with internal symbols, MEXC lists its symbols explicitly
+/// in `MEXC_SYMBOLS` and Hyperliquid streams coins rather than pairs, so they are not here.
+pub const VENUE_FORMATS: [(&str, SymbolFormat); 9] = [
+    ("binance", SymbolFormat::Concatenated),
+    ("bitget", SymbolFormat::Concatenated),
+    ("okx", SymbolFormat::Separated('-')),
+    ("coinbase", SymbolFormat::Separated('-')),
+    ("kucoin", SymbolFormat::Separated('-')),
+    ("kraken", SymbolFormat::Separated('/')),
+    ("gate", SymbolFormat::Separated('_')),
+    ("backpack", SymbolFormat::Separated('_')),
+    ("htx", SymbolFormat::Lowercase),
+];
+
+pub fn venue_format(exchange: &str) -> Option<SymbolFormat> {
+    VENUE_FORMATS
+        .iter()
+        .find(|(name, _)| *name == exchange)
+        .map(|(_, format)| *format)
+}
+
+/// Explicit venue spelling of an internal symbol, for pairs the venue's format cannot
+/// derive, e.g. a venue quoting in USDT a pair tracked as USDC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolOverride {
+    pub exchange: String,
+    /// Internal symbol persisted as `trade_pair`
+    pub symbol: String,
+    /// Symbol subscribed to on the venue
+    pub venue_symbol: String,
+}
+
+/// Symbol overrides of every venue, checked before the venue's format
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolOverrides {
+    entries: Vec<SymbolOverride>,
+}
+
+impl SymbolOverrides {
+    /// Read `SYMBOL_OVERRIDES`; no override when it is unset
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        match std::env::var("SYMBOL_OVERRIDES") {
+            Ok(value) => Ok(Self::parse(&value)?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Parse comma-separated `exchange:SYMBOL:VENUE_SYMBOL` entries such as
+    /// `okx:TRUMPUSDC:TRUMP-USDT`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut overrides = Self::default();
+        for entry in value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let fields: Vec<&str> = entry.split(':').map(str::trim).collect();
+            let [exchange, symbol, venue_symbol] = fields[..] else {
+                return Err(format!(
+                    "SYMBOL_OVERRIDES entry `{}` is not exchange:SYMBOL:VENUE_SYMBOL",
+                    entry
+                ));
+            };
+            if venue_format(exchange).is_none() {
+                return Err(format!(
+                    "SYMBOL_OVERRIDES entry `{}` names an exchange without symbol mapping",
+                    entry
+                ));
+            }
+            if TradingPair::parse(symbol).is_none() || venue_symbol.is_empty() {
+                return Err(format!(
+                    "SYMBOL_OVERRIDES entry `{}` has an invalid symbol",
+                    entry
+                ));
+            }
+            if overrides.override_of_symbol(exchange, symbol).is_some()
+                || overrides
+                    .override_of_venue_symbol(exchange, venue_symbol)
+                    .is_some()
+            {
+                return Err(format!(
+                    "SYMBOL_OVERRIDES maps {} or {} on {} more than once",
+                    symbol, venue_symbol, exchange
+                ));
+            }
+            overrides.entries.push(SymbolOverride {
+                exchange: exchange.to_string(),
+                symbol: symbol.to_string(),
+                venue_symbol: venue_symbol.to_string(),
+            });
+        }
+        Ok(overrides)
+    }
+
+    pub fn entries(&self) -> &[SymbolOverride] {
+        &self.entries
+    }
+
+    fn override_of_symbol(&self, exchange: &str, symbol: &str) -> Option<&SymbolOverride> {
+        self.entries
+            .iter()
+            .find(|entry| entry.exchange == exchange && entry.symbol == symbol)
+    }
+
+    fn override_of_venue_symbol(
+        &self,
+        exchange: &str,
+        venue_symbol: &str,
+    ) -> Option<&SymbolOverride> {
+        self.entries
+            .iter()
+            .find(|entry| entry.exchange == exchange && entry.venue_symbol == venue_symbol)
+    }
+
+    /// Venue symbol of an internal symbol on `exchange`: its override, else the venue's
+    /// spelling of the pair
+    pub fn venue_symbol(&self, exchange: &str, symbol: &str) -> Option<String> {
+        if let Some(entry) = self.override_of_symbol(exchange, symbol) {
+            return Some(entry.venue_symbol.clone());
+        }
+        Some(venue_format(exchange)?.format(&TradingPair::parse(symbol)?))
+    }
+
+    /// Internal symbol of a venue symbol on `exchange`, the inverse of `venue_symbol`
+    pub fn internal_symbol(&self, exchange: &str, venue_symbol: &str) -> Option<String> {
+        if let Some(entry) = self.override_of_venue_symbol(exchange, venue_symbol) {
+            return Some(entry.symbol.clone());
+        }
+        Some(venue_format(exchange)?.parse(venue_symbol)?.symbol())
+    }
+}
+
+/// Overrides of this process, see `install_overrides`
+static OVERRIDES: OnceLock<SymbolOverrides> = OnceLock::new();
+
+/// Install the overrides every screener of this process maps symbols with. Must run before
+/// any symbol is mapped, since the first mapping freezes the overrides (none by default).
+pub fn install_overrides(overrides: SymbolOverrides) -> Result<(), String> {
+    OVERRIDES
+        .set(overrides)
+        .map_err(|_| "symbol overrides are already in use".to_string())
+}
+
+fn overrides() -> &'static SymbolOverrides {
+    OVERRIDES.get_or_init(SymbolOverrides::default)
+}
+
+/// Venue symbol of an internal symbol on `exchange`, `okx`: `TRUMPUSDC` → `TRUMP-USDC`
+pub fn to_venue_symbol(exchange: &str, symbol: &str) -> Option<String> {
+    overrides().venue_symbol(exchange, symbol)
+}
+
+/// Internal symbol of a venue symbol on `exchange`, `okx`: `TRUMP-USDC` → `TRUMPUSDC`
+pub fn from_venue_symbol(exchange: &str, venue_symbol: &str) -> Option<String> {
+    overrides().internal_symbol(exchange, venue_symbol)
+}
+
+#[cfg(test)]
+#[path = "symbols_tests.rs"]
+mod symbols_tests;
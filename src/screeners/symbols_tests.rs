@@ -0,0 +1,191 @@
+use super::*;
+
+/// Venue symbol of `symbol` without overrides
+fn venue(exchange: &str, symbol: &str) -> Option<String> {
+    SymbolOverrides::default().venue_symbol(exchange, symbol)
+}
+
+/// Internal symbol of `venue_symbol` without overrides
+fn internal(exchange: &str, venue_symbol: &str) -> Option<String> {
+    SymbolOverrides::default().internal_symbol(exchange, venue_symbol)
+}
+
+#[test]
+fn internal_symbols_split_on_their_quote_asset() {
+    assert_eq!(split_symbol("TRUMPUSDC"), Some(("TRUMP", "USDC")));
+    assert_eq!(split_symbol("BTCFDUSD"), Some(("BTC", "FDUSD")));
+    assert_eq!(split_symbol("ETHBTC"), Some(("ETH", "BTC")));
+    assert_eq!(split_symbol("USDC"), None);
+    assert_eq!(split_symbol("TRUMPXYZ"), None);
+    assert_eq!(split_symbol("trumpusdc"), None);
+}
+
+#[test]
+fn trading_pairs_parse_from_internal_symbols() {
+    let pair = TradingPair::parse("1INCHUSDT").unwrap();
+    assert_eq!(
+        pair,
+        TradingPair {
+            base: "1INCH".to_string(),
+            quote: "USDT".to_string()
+        }
+    );
+    assert_eq!(pair.symbol(), "1INCHUSDT");
+    assert_eq!(TradingPair::parse("TRUMP"), None);
+}
+
+#[test]
+fn every_venue_round_trips_internal_symbols() {
+    for (exchange, format) in VENUE_FORMATS {
+        for symbol in ["TRUMPUSDC", "TRUMPUSDT", "BTCFDUSD", "1INCHUSD", "ETHBTC"] {
+            let venue_symbol = venue(exchange, symbol).unwrap();
+            assert_eq!(
+                venue_symbol,
+                format.format(&TradingPair::parse(symbol).unwrap())
+            );
+            assert_eq!(
+                internal(exchange, &venue_symbol).as_deref(),
+                Some(symbol),
+                "{} {}",
+                exchange,
+                venue_symbol
+            );
+        }
+        assert_eq!(venue(exchange, "TRUMP"), None, "{}", exchange);
+    }
+}
+
+#[test]
+fn unregistered_venues_map_nothing() {
+    assert_eq!(venue_format("bybit"), None);
+    assert_eq!(venue("mexc", "TRUMPUSDT"), None);
+    assert_eq!(internal("hyperliquid", "TRUMP"), None);
+}
+
+#[test]
+fn okx_inst_ids_map_both_ways() {
+    assert_eq!(venue("okx", "TRUMPUSDC").as_deref(), Some("TRUMP-USDC"));
+    assert_eq!(venue("okx", "1INCHUSDT").as_deref(), Some("1INCH-USDT"));
+    assert_eq!(internal("okx", "TRUMP-USDC").as_deref(), Some("TRUMPUSDC"));
+    assert_eq!(internal("okx", "TRUMPUSDC"), None);
+    assert_eq!(internal("okx", "BTC-USD-SWAP"), None);
+    assert_eq!(internal("okx", "-USDC"), None);
+}
+
+#[test]
+fn coinbase_product_ids_map_both_ways() {
+    assert_eq!(venue("coinbase", "TRUMPUSD").as_deref(), Some("TRUMP-USD"));
+    assert_eq!(
+        venue("coinbase", "TRUMPUSDC").as_deref(),
+        Some("TRUMP-USDC")
+    );
+    assert_eq!(internal("coinbase", "ETH-EUR").as_deref(), Some("ETHEUR"));
+    assert_eq!(internal("coinbase", "eth-eur"), None);
+}
+
+#[test]
+fn kraken_symbols_map_both_ways() {
+    assert_eq!(venue("kraken", "TRUMPUSD").as_deref(), Some("TRUMP/USD"));
+    assert_eq!(internal("kraken", "TRUMP/USD").as_deref(), Some("TRUMPUSD"));
+    assert_eq!(internal("kraken", "TRUMP-USD"), None);
+}
+
+#[test]
+fn gate_pairs_map_both_ways() {
+    assert_eq!(venue("gate", "TRUMPUSDT").as_deref(), Some("TRUMP_USDT"));
+    assert_eq!(internal("gate", "TRUMP_USDT").as_deref(), Some("TRUMPUSDT"));
+    assert_eq!(internal("gate", "TRUMP_3L_USDT"), None);
+}
+
+#[test]
+fn kucoin_symbols_map_both_ways() {
+    assert_eq!(venue("kucoin", "TRUMPUSDT").as_deref(), Some("TRUMP-USDT"));
+    assert_eq!(
+        internal("kucoin", "TRUMP-USDT").as_deref(),
+        Some("TRUMPUSDT")
+    );
+    assert_eq!(internal("kucoin", "TRUMP_USDT"), None);
+}
+
+#[test]
+fn backpack_symbols_map_both_ways() {
+    assert_eq!(
+        venue("backpack", "TRUMPUSDC").as_deref(),
+        Some("TRUMP_USDC")
+    );
+    assert_eq!(
+        internal("backpack", "TRUMP_USDC").as_deref(),
+        Some("TRUMPUSDC")
+    );
+    assert_eq!(internal("backpack", "TRUMP_USDC_PERP"), None);
+    assert_eq!(internal("backpack", "TRUMP-USDC"), None);
+}
+
+#[test]
+fn htx_symbols_map_both_ways() {
+    assert_eq!(venue("htx", "TRUMPUSDT").as_deref(), Some("trumpusdt"));
+    assert_eq!(venue("htx", "TRUMP"), None);
+    assert_eq!(internal("htx", "trumpusdt").as_deref(), Some("TRUMPUSDT"));
+    assert_eq!(internal("htx", "TRUMPUSDT"), None);
+    assert_eq!(internal("htx", "trump_usdt"), None);
+}
+
+#[test]
+fn binance_and_bitget_spell_internal_symbols() {
+    for exchange in ["binance", "bitget"] {
+        assert_eq!(venue(exchange, "TRUMPUSDC").as_deref(), Some("TRUMPUSDC"));
+        assert_eq!(
+            internal(exchange, "TRUMPUSDC").as_deref(),
+            Some("TRUMPUSDC")
+        );
+        assert_eq!(internal(exchange, "trumpusdc"), None);
+    }
+}
+
+#[test]
+fn overrides_win_over_the_venue_format_both_ways() {
+    let overrides =
+        SymbolOverrides::parse(" okx:TRUMPUSDC:TRUMP-USDT , kraken:BTCUSD:XBT/USD").unwrap();
+    assert_eq!(overrides.entries().len(), 2);
+
+    assert_eq!(
+        overrides.venue_symbol("okx", "TRUMPUSDC").as_deref(),
+        Some("TRUMP-USDT")
+    );
+    assert_eq!(
+        overrides.internal_symbol("okx", "TRUMP-USDT").as_deref(),
+        Some("TRUMPUSDC")
+    );
+    assert_eq!(
+        overrides.internal_symbol("kraken", "XBT/USD").as_deref(),
+        Some("BTCUSD")
+    );
+    // Other symbols and venues keep the venue format
+    assert_eq!(
+        overrides.venue_symbol("okx", "ETHUSDC").as_deref(),
+        Some("ETH-USDC")
+    );
+    assert_eq!(
+        overrides.venue_symbol("kucoin", "TRUMPUSDC").as_deref(),
+        Some("TRUMP-USDC")
+    );
+}
+
+#[test]
+fn invalid_overrides_are_rejected() {
+    assert_eq!(
+        SymbolOverrides::parse("").unwrap(),
+        SymbolOverrides::default()
+    );
+    for value in [
+        "okx:TRUMPUSDC",
+        "okx:TRUMPUSDC:TRUMP-USDT:SPOT",
+        "bybit:TRUMPUSDC:TRUMPUSDT",
+        "okx:TRUMP:TRUMP-USDT",
+        "okx:TRUMPUSDC:",
+        "okx:TRUMPUSDC:TRUMP-USDT,okx:TRUMPUSDC:TRUMP-EUR",
+        "okx:TRUMPUSDC:TRUMP-USDT,okx:TRUMPUSD:TRUMP-USDT",
+    ] {
+        assert!(SymbolOverrides::parse(value).is_err(), "{}", value);
+    }
+}
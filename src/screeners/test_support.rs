@@ -0,0 +1,55 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::market;
+
+use super::cex_writer::{CexMarketSink, CexMarketWriter, CexWriterConfig};
+
+/// CEX market sink keeping every written state, in write order
+#[derive(Clone, Default)]
+pub(crate) struct RecordingSink {
+    pub(crate) states: Arc<Mutex<Vec<market::CEXState>>>,
+}
+
+impl RecordingSink {
+    /// Trade id of every written state
+    pub(crate) fn trade_ids(&self) -> Vec<String> {
+        self.states
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|state| state.trade_id.clone())
+            .collect()
+    }
+}
+
+impl CexMarketSink for RecordingSink {
+    async fn write_states(
+        &self,
+        states: &[market::CEXState],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.states.lock().unwrap().extend_from_slice(states);
+        Ok(())
+    }
+}
+
+/// Screener the tests of its module build around a given CEX market writer
+pub(crate) trait TestScreener: Sized {
+    /// Screener on the test configuration of its module, ready to handle messages and
+    /// persisting through `cex_writer`
+    fn with_cex_writer(cex_writer: CexMarketWriter) -> Self;
+}
+
+/// Screener writing to a new [`RecordingSink`]. States reach the sink when the writer is
+/// flushed.
+pub(crate) fn build_screener_with_sink<T: TestScreener>() -> (T, RecordingSink) {
+    let sink = RecordingSink::default();
+    let cex_writer = CexMarketWriter::spawn(
+        sink.clone(),
+        CexWriterConfig {
+            flush_interval: Duration::from_secs(60),
+            queue_capacity: 64,
+        },
+    );
+    (T::with_cex_writer(cex_writer), sink)
+}
@@ -0,0 +1,41 @@
+use flate2::read::GzDecoder;
+use serde_json::{Value, json};
+use std::io::Read;
+
+/// Text of a gzip-compressed binary frame. HTX compresses every message it sends this way,
+/// as do the venues built on Huobi's gateway.
+pub(super) fn gunzip_text(data: &[u8]) -> Result<String, String> {
+    let mut text = String::new();
+    GzDecoder::new(data)
+        .read_to_string(&mut text)
+        .map_err(|e| format!("malformed gzip frame: {}", e))?;
+    Ok(text)
+}
+
+/// Reply to a heartbeat carried in a JSON payload instead of a websocket ping frame, or
+/// `None` when `text` is not one. The reply echoes the timestamp of the ping:
+/// - `{"ping": ts}` → `{"pong": ts}` (HTX spot market data)
+/// - `{"op": "ping", "ts": ts}` → `{"op": "pong", "ts": ts}` (HTX derivatives)
+/// - `{"action": "ping", "data": {"ts": ts}}` → `{"action": "pong", "data": {"ts": ts}}`
+///   (HTX v2 account streams)
+pub(super) fn embedded_pong(text: &str) -> Option<String> {
+    // Cheap filter, since every data message goes through here
+    if !text.contains("ping") {
+        return None;
+    }
+    let payload: Value = serde_json::from_str(text).ok()?;
+    let reply = if !payload["ping"].is_null() {
+        json!({"pong": payload["ping"]})
+    } else if payload["op"] == "ping" {
+        json!({"op": "pong", "ts": payload["ts"]})
+    } else if payload["action"] == "ping" {
+        json!({"action": "pong", "data": {"ts": payload["data"]["ts"]}})
+    } else {
+        return None;
+    };
+    Some(reply.to_string())
+}
+
+#[cfg(test)]
+#[path = "ws_codec_tests.rs"]
+mod ws_codec_tests;
@@ -0,0 +1,51 @@
+use super::*;
+
+/// HTX heartbeat `{"ping":1716863719000}` as received, gzip-compressed
+const PING_FRAME: &str =
+    "1f8b0800000000000203ab562ac8cc4b57b232343734b330333637b4343030a8050037e33f5916000000";
+
+#[test]
+fn gzip_frames_are_decompressed_to_text() {
+    let data = hex::decode(PING_FRAME).unwrap();
+    assert_eq!(
+        gunzip_text(&data).as_deref(),
+        Ok(r#"{"ping":1716863719000}"#)
+    );
+    assert!(gunzip_text(&data[..20]).is_err());
+    assert!(gunzip_text(br#"{"ping":1716863719000}"#).is_err());
+}
+
+#[test]
+fn embedded_pings_are_answered_with_their_timestamp() {
+    let ping = gunzip_text(&hex::decode(PING_FRAME).unwrap()).unwrap();
+    let reply: Value = serde_json::from_str(&embedded_pong(&ping).unwrap()).unwrap();
+    assert_eq!(reply, json!({"pong": 1716863719000u64}));
+
+    let reply: Value =
+        serde_json::from_str(&embedded_pong(r#"{"op":"ping","ts":"1716863719000"}"#).unwrap())
+            .unwrap();
+    assert_eq!(reply, json!({"op": "pong", "ts": "1716863719000"}));
+
+    let reply: Value = serde_json::from_str(
+        &embedded_pong(r#"{"action":"ping","data":{"ts":1716863719000}}"#).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        reply,
+        json!({"action": "pong", "data": {"ts": 1716863719000u64}})
+    );
+}
+
+#[test]
+fn other_payloads_are_not_heartbeats() {
+    assert_eq!(embedded_pong(r#"{"pong":1716863719000}"#), None);
+    assert_eq!(
+        embedded_pong(r#"{"id":"1","status":"ok","subbed":"market.pingusdt.mbp.150"}"#),
+        None
+    );
+    assert_eq!(embedded_pong("ping"), None);
+    assert_eq!(
+        embedded_pong(r#"{"ch":"market.trumpusdt.mbp.150","tick":{}}"#),
+        None
+    );
+}
@@ -0,0 +1,132 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::solana::retry::RetryPolicy;
+
+/// Backoff between two sessions of a websocket screener: 500ms doubling up to 30s, jittered,
+/// retried until the screener stops
+pub(super) fn reconnect_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: u32::MAX,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(30),
+    }
+}
+
+/// One websocket connection of a screener, from connecting to the end of the stream
+pub(super) trait WsSession {
+    /// Reset the local state, connect and stream until the connection drops, goes silent or
+    /// the screener stops. Frames and delivered messages are reported to `feed`.
+    fn run_session(
+        &self,
+        feed: &mut SessionFeed,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+/// Liveness of the current session, reported by the session as frames arrive
+pub(super) struct SessionFeed {
+    exchange: &'static str,
+    stale_timeout: Duration,
+    last_frame: Instant,
+    delivered: usize,
+}
+
+impl SessionFeed {
+    fn new(exchange: &'static str, stale_timeout: Duration) -> Self {
+        Self {
+            exchange,
+            stale_timeout,
+            last_frame: Instant::now(),
+            delivered: 0,
+        }
+    }
+
+    /// Record a frame proving the connection alive
+    pub(super) fn frame(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    /// Record a market data message; a session that delivered any restarts the backoff
+    pub(super) fn delivered(&mut self) {
+        self.delivered += 1;
+    }
+
+    /// Replace the stale timeout, for venues announcing their heartbeat when connecting
+    pub(super) fn set_stale_timeout(&mut self, stale_timeout: Duration) {
+        self.stale_timeout = stale_timeout;
+    }
+
+    /// Resolve with the reason to end the session once no frame arrived for the stale
+    /// timeout, counted in `{exchange}_stale_feeds_total`
+    pub(super) async fn stale(&self) -> String {
+        tokio::time::sleep_until(self.last_frame + self.stale_timeout).await;
+        metrics::counter!(format!("{}_stale_feeds_total", self.exchange)).increment(1);
+        format!("no frame for {:?}", self.last_frame.elapsed())
+    }
+}
+
+/// Reconnect loop of a websocket screener: runs sessions until the screener stops, waiting
+/// a [`reconnect_policy`] backoff after each one. The backoff restarts after a session that
+/// delivered a message.
+pub(super) struct WsSupervisor {
+    /// Venue name of the logs, e.g. `Binance`
+    venue: &'static str,
+    /// Exchange name prefixing the metrics, e.g. `binance`
+    exchange: &'static str,
+    /// Silence after which a session is considered dead
+    stale_timeout: Duration,
+    reconnect_policy: RetryPolicy,
+}
+
+impl WsSupervisor {
+    pub(super) fn new(
+        venue: &'static str,
+        exchange: &'static str,
+        stale_timeout: Duration,
+    ) -> Self {
+        Self {
+            venue,
+            exchange,
+            stale_timeout,
+            reconnect_policy: reconnect_policy(),
+        }
+    }
+
+    /// Run sessions of `session` until `shutdown` is cancelled; every reconnect is logged
+    /// with its reason and counted in `{exchange}_websocket_reconnects_total`
+    pub(super) async fn run(&self, shutdown: &CancellationToken, session: &impl WsSession) {
+        let mut reconnects = 0;
+        loop {
+            let mut feed = SessionFeed::new(self.exchange, self.stale_timeout);
+            let result = session.run_session(&mut feed).await;
+            if shutdown.is_cancelled() {
+                break;
+            }
+
+            if feed.delivered > 0 {
+                reconnects = 0;
+            }
+            reconnects += 1;
+            let delay = self.reconnect_policy.backoff(reconnects);
+            let reason = result
+                .err()
+                .unwrap_or_else(|| "closed by server".to_string());
+            warn!(
+                "{} websocket disconnected ({}), reconnect attempt {} in {:?}",
+                self.venue, reason, reconnects, delay
+            );
+            metrics::counter!(format!("{}_websocket_reconnects_total", self.exchange)).increment(1);
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "ws_supervisor_tests.rs"]
+mod ws_supervisor_tests;
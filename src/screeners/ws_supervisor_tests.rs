@@ -0,0 +1,58 @@
+use super::*;
+use futures::FutureExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Session dropped right after connecting, stopping the screener on its `stop_after`-th run
+struct DroppedSession {
+    runs: AtomicUsize,
+    stop_after: usize,
+    shutdown: CancellationToken,
+}
+
+impl WsSession for DroppedSession {
+    async fn run_session(&self, feed: &mut SessionFeed) -> Result<(), String> {
+        feed.frame();
+        if self.runs.fetch_add(1, Ordering::SeqCst) + 1 == self.stop_after {
+            self.shutdown.cancel();
+        }
+        Err("connection reset".to_string())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn silent_feed_goes_stale_after_the_timeout() {
+    let mut feed = SessionFeed::new("test", Duration::from_secs(30));
+
+    tokio::time::advance(Duration::from_secs(29)).await;
+    assert!(feed.stale().now_or_never().is_none());
+    feed.frame();
+    tokio::time::advance(Duration::from_secs(29)).await;
+    assert!(feed.stale().now_or_never().is_none());
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    let reason = feed.stale().now_or_never().unwrap();
+    assert_eq!(reason, "no frame for 30s");
+}
+
+#[tokio::test(start_paused = true)]
+async fn dropped_sessions_reconnect_until_shutdown() {
+    let supervisor = WsSupervisor::new("Test", "test", Duration::from_secs(30));
+    let session = DroppedSession {
+        runs: AtomicUsize::new(0),
+        stop_after: 3,
+        shutdown: CancellationToken::new(),
+    };
+
+    supervisor.run(&session.shutdown, &session).await;
+
+    assert_eq!(session.runs.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn reconnects_back_off_up_to_30_seconds() {
+    let policy = reconnect_policy();
+
+    assert_eq!(policy.max_attempts, u32::MAX);
+    assert!((Duration::from_millis(250)..=Duration::from_millis(500)).contains(&policy.backoff(1)));
+    assert!((Duration::from_secs(15)..=Duration::from_secs(30)).contains(&policy.backoff(20)));
+}
@@ -0,0 +1,174 @@
+use solana_client::client_error::Result as ClientResult;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::rpc::{FailoverRpcClient, SolanaRpc};
+
+/// Source of account data for the cache
+pub trait AccountFetcher: Send + Sync {
+    fn fetch_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> impl Future<Output = ClientResult<Vec<Option<Account>>>> + Send;
+}
+
+impl<C: SolanaRpc> AccountFetcher for FailoverRpcClient<C> {
+    async fn fetch_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.get_multiple_accounts(pubkeys).await
+    }
+}
+
+/// How long a cached account may be served without refetching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Never served from cache (e.g. the Clock sysvar)
+    Always,
+    /// Refetched once the cached slot is older than the cache's max slot age,
+    /// unless the account is kept up to date by a stream
+    Slots,
+    /// Refetched once older than the cache's TTL (e.g. mints, which basically never change)
+    Ttl,
+    /// Served from cache only while kept up to date by a stream, refetched otherwise
+    /// (e.g. spot price reads that must not lag between two quotes)
+    Streamed,
+}
+
+struct CachedAccount {
+    account: Account,
+    slot: u64,
+    fetched_at: Instant,
+    /// Pushed by an account stream that is still connected
+    streamed: bool,
+}
+
+/// In-memory account cache keyed by pubkey with slot- and time-based invalidation
+pub struct AccountCache {
+    entries: Mutex<HashMap<Pubkey, CachedAccount>>,
+    /// Max number of slots a `Freshness::Slots` account may lag behind the current slot
+    max_slot_age: u64,
+    /// Max age of a `Freshness::Ttl` account
+    ttl: Duration,
+}
+
+impl AccountCache {
+    pub fn new(max_slot_age: u64, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_slot_age,
+            ttl,
+        }
+    }
+
+    /// Return the requested accounts in order, serving fresh entries from the cache
+    /// and fetching all misses with a single call. Fetched accounts are stamped with
+    /// `current_slot`, the latest slot known to the caller.
+    pub async fn get_accounts<F: AccountFetcher>(
+        &self,
+        fetcher: &F,
+        requests: &[(Pubkey, Freshness)],
+        current_slot: u64,
+    ) -> ClientResult<Vec<Option<Account>>> {
+        let mut accounts: Vec<Option<Account>> = vec![None; requests.len()];
+        let mut misses = Vec::new();
+        {
+            let entries = self.entries.lock().unwrap();
+            for (index, (pubkey, freshness)) in requests.iter().enumerate() {
+                match entries.get(pubkey) {
+                    Some(entry) if self.is_fresh(entry, *freshness, current_slot) => {
+                        accounts[index] = Some(entry.account.clone());
+                    }
+                    _ => misses.push(index),
+                }
+            }
+        }
+
+        if misses.is_empty() {
+            return Ok(accounts);
+        }
+
+        let keys: Vec<Pubkey> = misses.iter().map(|&index| requests[index].0).collect();
+        let fetched = fetcher.fetch_accounts(&keys).await?;
+
+        let mut entries = self.entries.lock().unwrap();
+        for (&index, account) in misses.iter().zip(fetched) {
+            let (pubkey, freshness) = requests[index];
+            match &account {
+                Some(account) if freshness != Freshness::Always => {
+                    entries.insert(
+                        pubkey,
+                        CachedAccount {
+                            account: account.clone(),
+                            slot: current_slot,
+                            fetched_at: Instant::now(),
+                            streamed: false,
+                        },
+                    );
+                }
+                _ => {
+                    entries.remove(&pubkey);
+                }
+            }
+            accounts[index] = account;
+        }
+
+        Ok(accounts)
+    }
+
+    /// Store an account pushed by a stream at `slot`. Streamed accounts stay fresh
+    /// until `release_streamed` is called, since the stream pushes every change.
+    /// Updates older than the cached slot are ignored.
+    pub fn insert_streamed(&self, pubkey: Pubkey, account: Account, slot: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.get(&pubkey).is_some_and(|entry| entry.slot > slot) {
+            return;
+        }
+        entries.insert(
+            pubkey,
+            CachedAccount {
+                account,
+                slot,
+                fetched_at: Instant::now(),
+                streamed: true,
+            },
+        );
+    }
+
+    /// Fall back to slot-based freshness for streamed accounts, e.g. after the stream disconnected
+    pub fn release_streamed(&self) {
+        for entry in self.entries.lock().unwrap().values_mut() {
+            entry.streamed = false;
+        }
+    }
+
+    /// Drop a cached account so the next lookup refetches it
+    pub fn invalidate(&self, pubkey: &Pubkey) {
+        self.entries.lock().unwrap().remove(pubkey);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_fresh(&self, entry: &CachedAccount, freshness: Freshness, current_slot: u64) -> bool {
+        match freshness {
+            Freshness::Always => false,
+            Freshness::Slots => {
+                entry.streamed || current_slot.saturating_sub(entry.slot) <= self.max_slot_age
+            }
+            Freshness::Ttl => entry.fetched_at.elapsed() < self.ttl,
+            Freshness::Streamed => entry.streamed,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "account_cache_tests.rs"]
+mod account_cache_tests;
@@ -0,0 +1,176 @@
+use super::*;
+
+/// Fake fetcher recording every batch of requested pubkeys
+#[derive(Default)]
+struct MockFetcher {
+    batches: Mutex<Vec<Vec<Pubkey>>>,
+}
+
+impl MockFetcher {
+    fn batches(&self) -> Vec<Vec<Pubkey>> {
+        self.batches.lock().unwrap().clone()
+    }
+}
+
+impl AccountFetcher for MockFetcher {
+    async fn fetch_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.batches.lock().unwrap().push(pubkeys.to_vec());
+        Ok(pubkeys
+            .iter()
+            .map(|pubkey| {
+                Some(Account {
+                    data: pubkey.to_bytes().to_vec(),
+                    ..Account::default()
+                })
+            })
+            .collect())
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fresh_entries_skip_the_fetch() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let lb_pair = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let requests = [(lb_pair, Freshness::Slots), (mint, Freshness::Ttl)];
+
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+    let accounts = cache.get_accounts(&fetcher, &requests, 101).await.unwrap();
+
+    assert_eq!(fetcher.batches().len(), 1);
+    assert_eq!(accounts[0].as_ref().unwrap().data, lb_pair.to_bytes());
+    assert_eq!(accounts[1].as_ref().unwrap().data, mint.to_bytes());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn stale_slot_entries_are_refetched_in_one_batch() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let lb_pair = Pubkey::new_unique();
+    let bin_array = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let requests = [
+        (lb_pair, Freshness::Slots),
+        (mint, Freshness::Ttl),
+        (bin_array, Freshness::Slots),
+    ];
+
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+    cache.get_accounts(&fetcher, &requests, 103).await.unwrap();
+
+    let batches = fetcher.batches();
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[1], vec![lb_pair, bin_array]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn always_fresh_accounts_are_never_cached() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let clock = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let requests = [(clock, Freshness::Always), (mint, Freshness::Ttl)];
+
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+
+    let batches = fetcher.batches();
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[1], vec![clock]);
+    assert_eq!(cache.len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn expired_ttl_entries_are_refetched() {
+    let cache = AccountCache::new(2, Duration::ZERO);
+    let fetcher = MockFetcher::default();
+    let mint = Pubkey::new_unique();
+    let requests = [(mint, Freshness::Ttl)];
+
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+
+    assert_eq!(fetcher.batches().len(), 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn invalidate_forces_a_refetch() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let lb_pair = Pubkey::new_unique();
+    let requests = [(lb_pair, Freshness::Slots)];
+
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+    cache.invalidate(&lb_pair);
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+
+    assert_eq!(fetcher.batches().len(), 2);
+}
+
+fn account_with_lamports(lamports: u64) -> Account {
+    Account {
+        lamports,
+        ..Account::default()
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn streamed_entries_stay_fresh_until_released() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let lb_pair = Pubkey::new_unique();
+    let requests = [(lb_pair, Freshness::Slots)];
+
+    cache.insert_streamed(lb_pair, account_with_lamports(7), 100);
+    let accounts = cache.get_accounts(&fetcher, &requests, 150).await.unwrap();
+
+    assert!(fetcher.batches().is_empty());
+    assert_eq!(accounts[0].as_ref().unwrap().lamports, 7);
+
+    cache.release_streamed();
+    cache.get_accounts(&fetcher, &requests, 150).await.unwrap();
+
+    assert_eq!(fetcher.batches(), vec![vec![lb_pair]]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn streamed_updates_older_than_the_cached_slot_are_ignored() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let lb_pair = Pubkey::new_unique();
+
+    cache.insert_streamed(lb_pair, account_with_lamports(2), 101);
+    cache.insert_streamed(lb_pair, account_with_lamports(1), 100);
+    let accounts = cache
+        .get_accounts(&fetcher, &[(lb_pair, Freshness::Slots)], 101)
+        .await
+        .unwrap();
+
+    assert_eq!(accounts[0].as_ref().unwrap().lamports, 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn streamed_freshness_refetches_polled_entries() {
+    let cache = AccountCache::new(2, Duration::from_secs(60));
+    let fetcher = MockFetcher::default();
+    let (polled, streamed) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let requests = [
+        (polled, Freshness::Streamed),
+        (streamed, Freshness::Streamed),
+    ];
+
+    cache.insert_streamed(streamed, account_with_lamports(7), 100);
+    cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+    let accounts = cache.get_accounts(&fetcher, &requests, 100).await.unwrap();
+
+    assert_eq!(fetcher.batches(), vec![vec![polled], vec![polled]]);
+    assert_eq!(accounts[1].as_ref().unwrap().lamports, 7);
+
+    // Polled entries are still cached for slot-based readers
+    cache
+        .get_accounts(&fetcher, &[(polled, Freshness::Slots)], 100)
+        .await
+        .unwrap();
+    assert_eq!(fetcher.batches().len(), 2);
+}
@@ -0,0 +1,252 @@
+use futures::{SinkExt, StreamExt};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::prelude::{
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestPing,
+    SubscribeUpdateAccountInfo, subscribe_update::UpdateOneof,
+};
+
+use super::account_cache::AccountCache;
+use super::retry::RetryPolicy;
+
+/// How often the watched account set is compared against the active subscription
+const RESUBSCRIBE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How often the stalest streamed account is logged
+const STALENESS_LOG_INTERVAL: Duration = Duration::from_secs(60);
+/// Name of the account filter in subscribe requests
+const ACCOUNTS_FILTER: &str = "meteora";
+
+type GeyserResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Yellowstone gRPC endpoint settings
+#[derive(Debug, Clone)]
+pub struct GeyserConfig {
+    pub endpoint: String,
+    pub x_token: Option<String>,
+}
+
+impl GeyserConfig {
+    /// Read `GEYSER_ENDPOINT` and `GEYSER_X_TOKEN`; `None` when no endpoint is configured
+    pub fn from_env() -> Option<Self> {
+        let endpoint = std::env::var("GEYSER_ENDPOINT")
+            .ok()
+            .filter(|endpoint| !endpoint.trim().is_empty())?;
+        Some(Self {
+            endpoint,
+            x_token: std::env::var("GEYSER_X_TOKEN").ok(),
+        })
+    }
+}
+
+/// Streams account updates from a Geyser plugin into an `AccountCache`,
+/// reconnecting and resubscribing whenever the stream drops
+pub struct GeyserSource {
+    config: GeyserConfig,
+    reconnect_policy: RetryPolicy,
+    /// Time of the last update of every subscribed account, or of its subscription
+    last_updates: Mutex<HashMap<Pubkey, Instant>>,
+}
+
+impl GeyserSource {
+    pub fn new(config: GeyserConfig) -> Self {
+        Self {
+            config,
+            reconnect_policy: RetryPolicy {
+                max_attempts: u32::MAX,
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(30),
+            },
+            last_updates: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Milliseconds since the last streamed update of every subscribed account
+    pub fn staleness_ms(&self) -> HashMap<Pubkey, u64> {
+        self.last_updates
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(pubkey, updated_at)| (*pubkey, updated_at.elapsed().as_millis() as u64))
+            .collect()
+    }
+
+    /// Stream updates of the accounts returned by `accounts` into `cache` until `shutdown`
+    /// is cancelled. The account set is re-read periodically and resubscribed when it changes.
+    pub async fn run<A>(&self, cache: &AccountCache, accounts: A, shutdown: &CancellationToken)
+    where
+        A: Fn() -> Vec<Pubkey>,
+    {
+        info!(
+            "🚀 Starting Geyser account stream ({})...",
+            self.config.endpoint
+        );
+        let mut reconnects = 0;
+        while !shutdown.is_cancelled() {
+            let error = match self.stream_updates(cache, &accounts, shutdown).await {
+                Ok(received) => {
+                    if received {
+                        reconnects = 0;
+                    }
+                    None
+                }
+                Err(e) => Some(e.to_string()),
+            };
+            // Streamed entries are no longer kept up to date until we resubscribe
+            cache.release_streamed();
+            if shutdown.is_cancelled() {
+                break;
+            }
+
+            reconnects += 1;
+            let delay = self.reconnect_policy.backoff(reconnects);
+            warn!(
+                "Geyser stream ended ({}), reconnecting in {:?}",
+                error.as_deref().unwrap_or("closed by server"),
+                delay
+            );
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        info!("Geyser account stream stopped");
+    }
+
+    /// Run one subscription until the stream ends or `shutdown` is cancelled.
+    /// Returns whether any update was received.
+    async fn stream_updates<A>(
+        &self,
+        cache: &AccountCache,
+        accounts: &A,
+        shutdown: &CancellationToken,
+    ) -> GeyserResult<bool>
+    where
+        A: Fn() -> Vec<Pubkey>,
+    {
+        let mut client = GeyserGrpcClient::build_from_shared(self.config.endpoint.clone())?
+            .x_token(self.config.x_token.clone())?
+            .tls_config(ClientTlsConfig::new().with_native_roots())?
+            .connect_timeout(Duration::from_secs(10))
+            .connect()
+            .await?;
+
+        let mut subscribed = sorted(accounts());
+        self.track_subscription(&subscribed);
+        let (mut sink, mut stream) = client
+            .subscribe_with_request(Some(subscribe_request(&subscribed)))
+            .await?;
+        info!("Geyser subscribed to {} accounts", subscribed.len());
+
+        let mut resubscribe_check = tokio::time::interval(RESUBSCRIBE_CHECK_INTERVAL);
+        let mut staleness_log = tokio::time::interval(STALENESS_LOG_INTERVAL);
+        let mut received = false;
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(received),
+                _ = resubscribe_check.tick() => {
+                    let watched = sorted(accounts());
+                    if watched != subscribed {
+                        subscribed = watched;
+                        self.track_subscription(&subscribed);
+                        sink.send(subscribe_request(&subscribed)).await?;
+                        info!("Geyser resubscribed to {} accounts", subscribed.len());
+                    }
+                }
+                _ = staleness_log.tick() => self.log_staleness(),
+                message = stream.next() => {
+                    let Some(message) = message else {
+                        return Ok(received);
+                    };
+                    received = true;
+                    match message?.update_oneof {
+                        Some(UpdateOneof::Account(update)) => {
+                            if let Some((pubkey, account)) = update.account.and_then(account_from_update) {
+                                cache.insert_streamed(pubkey, account, update.slot);
+                                self.last_updates.lock().unwrap().insert(pubkey, Instant::now());
+                            }
+                        }
+                        Some(UpdateOneof::Ping(_)) => {
+                            // Answer server pings so load balancers keep the stream open
+                            sink.send(SubscribeRequest {
+                                ping: Some(SubscribeRequestPing { id: 1 }),
+                                ..SubscribeRequest::default()
+                            })
+                            .await?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start staleness tracking of newly subscribed accounts and forget dropped ones
+    fn track_subscription(&self, accounts: &[Pubkey]) {
+        let mut last_updates = self.last_updates.lock().unwrap();
+        last_updates.retain(|pubkey, _| accounts.contains(pubkey));
+        let now = Instant::now();
+        for pubkey in accounts {
+            last_updates.entry(*pubkey).or_insert(now);
+        }
+    }
+
+    fn log_staleness(&self) {
+        let staleness = self.staleness_ms();
+        if let Some((pubkey, ms)) = staleness.iter().max_by_key(|(_, ms)| **ms) {
+            info!(
+                "[geyser] {} accounts streamed, stalest {} last updated {}ms ago",
+                staleness.len(),
+                pubkey,
+                ms
+            );
+        }
+    }
+}
+
+fn sorted(mut accounts: Vec<Pubkey>) -> Vec<Pubkey> {
+    accounts.sort();
+    accounts.dedup();
+    accounts
+}
+
+/// Subscribe request for `accounts` at processed commitment
+fn subscribe_request(accounts: &[Pubkey]) -> SubscribeRequest {
+    SubscribeRequest {
+        accounts: HashMap::from([(
+            ACCOUNTS_FILTER.to_string(),
+            SubscribeRequestFilterAccounts {
+                account: accounts.iter().map(Pubkey::to_string).collect(),
+                ..SubscribeRequestFilterAccounts::default()
+            },
+        )]),
+        commitment: Some(CommitmentLevel::Processed as i32),
+        ..SubscribeRequest::default()
+    }
+}
+
+/// Convert a streamed account into an RPC account, `None` if its keys are malformed
+fn account_from_update(info: SubscribeUpdateAccountInfo) -> Option<(Pubkey, Account)> {
+    let pubkey = Pubkey::try_from(info.pubkey.as_slice()).ok()?;
+    let owner = Pubkey::try_from(info.owner.as_slice()).ok()?;
+    Some((
+        pubkey,
+        Account {
+            lamports: info.lamports,
+            data: info.data,
+            owner,
+            executable: info.executable,
+            rent_epoch: info.rent_epoch,
+        },
+    ))
+}
+
+#[cfg(test)]
+#[path = "geyser_tests.rs"]
+mod geyser_tests;
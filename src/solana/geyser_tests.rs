@@ -0,0 +1,70 @@
+use super::*;
+
+#[test]
+fn subscribe_request_filters_accounts_at_processed() {
+    let accounts = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let request = subscribe_request(&accounts);
+
+    let filter = &request.accounts[ACCOUNTS_FILTER];
+    assert_eq!(
+        filter.account,
+        vec![accounts[0].to_string(), accounts[1].to_string()]
+    );
+    assert!(filter.owner.is_empty());
+    assert_eq!(request.commitment, Some(CommitmentLevel::Processed as i32));
+}
+
+#[test]
+fn account_from_update_converts_streamed_account() {
+    let pubkey = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let info = SubscribeUpdateAccountInfo {
+        pubkey: pubkey.to_bytes().to_vec(),
+        lamports: 42,
+        owner: owner.to_bytes().to_vec(),
+        data: vec![1, 2, 3],
+        ..SubscribeUpdateAccountInfo::default()
+    };
+
+    let (key, account) = account_from_update(info).unwrap();
+
+    assert_eq!(key, pubkey);
+    assert_eq!(account.owner, owner);
+    assert_eq!(account.lamports, 42);
+    assert_eq!(account.data, vec![1, 2, 3]);
+}
+
+#[test]
+fn account_from_update_rejects_malformed_pubkeys() {
+    let info = SubscribeUpdateAccountInfo {
+        pubkey: vec![1, 2, 3],
+        owner: Pubkey::new_unique().to_bytes().to_vec(),
+        ..SubscribeUpdateAccountInfo::default()
+    };
+
+    assert!(account_from_update(info).is_none());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn track_subscription_measures_staleness_per_account() {
+    let source = GeyserSource::new(GeyserConfig {
+        endpoint: "http://localhost:10000".to_string(),
+        x_token: None,
+    });
+    let kept = Pubkey::new_unique();
+    let dropped = Pubkey::new_unique();
+
+    source.track_subscription(&[kept, dropped]);
+    tokio::time::advance(Duration::from_millis(250)).await;
+    source.track_subscription(&[kept]);
+
+    assert_eq!(source.staleness_ms(), HashMap::from([(kept, 250)]));
+}
+
+#[test]
+fn sorted_dedups_watched_accounts() {
+    let pubkey = Pubkey::new_unique();
+
+    assert_eq!(sorted(vec![pubkey, pubkey]), vec![pubkey]);
+}
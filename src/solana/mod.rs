@@ -0,0 +1,7 @@
+pub mod account_cache;
+#[cfg(feature = "geyser")]
+pub mod geyser;
+pub mod rate_limit;
+pub mod retry;
+pub mod rpc;
+pub mod utils;
@@ -0,0 +1,97 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::info;
+
+/// Default number of RPC requests per second
+const DEFAULT_MAX_RPS: u32 = 10;
+/// Interval between two reports of throttled calls
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    /// Available tokens; negative when callers are already waiting for future tokens
+    tokens: f64,
+    last_refill: Instant,
+    last_report: Instant,
+}
+
+/// Token-bucket rate limiter shared by every RPC call of a client.
+/// Callers beyond the rate wait for their token instead of failing.
+pub struct RateLimiter {
+    name: String,
+    /// Tokens added per second
+    rate: f64,
+    /// Maximum number of tokens, i.e. the allowed burst
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+    throttled: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Limiter allowing `max_rps` calls per second with bursts of up to `max_rps` calls
+    pub fn new(name: &str, max_rps: u32) -> Self {
+        let max_rps = max_rps.max(1) as f64;
+        let now = Instant::now();
+        Self {
+            name: name.to_string(),
+            rate: max_rps,
+            capacity: max_rps,
+            bucket: Mutex::new(Bucket {
+                tokens: max_rps,
+                last_refill: now,
+                last_report: now,
+            }),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    /// Build a limiter from `RPC_MAX_RPS`, falling back to the default
+    pub fn from_env(name: &str) -> Self {
+        let max_rps = std::env::var("RPC_MAX_RPS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_RPS);
+        Self::new(name, max_rps)
+    }
+
+    /// Wait until a call is allowed. Each caller reserves its token up front, so
+    /// concurrent callers are released one token interval apart.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+            bucket.last_refill = now;
+            bucket.tokens -= 1.0;
+
+            if now.duration_since(bucket.last_report) >= REPORT_INTERVAL {
+                bucket.last_report = now;
+                let throttled = self.throttled.swap(0, Ordering::Relaxed);
+                if throttled > 0 {
+                    info!(
+                        "[{}] {} RPC calls throttled in the last minute",
+                        self.name, throttled
+                    );
+                }
+            }
+
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / self.rate))
+        };
+
+        if let Some(wait) = wait {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Calls that had to wait since the last report
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+#[path = "rate_limit_tests.rs"]
+mod rate_limit_tests;
@@ -0,0 +1,64 @@
+use super::*;
+use std::sync::Arc;
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn burst_within_capacity_is_not_throttled() {
+    let limiter = RateLimiter::new("test", 10);
+    let start = Instant::now();
+
+    for _ in 0..10 {
+        limiter.acquire().await;
+    }
+
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert_eq!(limiter.throttled(), 0);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn concurrent_burst_is_spread_at_the_configured_rate() {
+    let limiter = Arc::new(RateLimiter::new("test", 10));
+    let start = Instant::now();
+
+    let callers: Vec<_> = (0..30)
+        .map(|_| {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                limiter.acquire().await;
+                start.elapsed()
+            })
+        })
+        .collect();
+    let mut released = Vec::new();
+    for caller in callers {
+        released.push(caller.await.unwrap());
+    }
+    released.sort();
+
+    // 10 calls fit the burst, the remaining 20 are released 100ms apart
+    assert_eq!(limiter.throttled(), 20);
+    assert_eq!(released[9], Duration::ZERO);
+    let last = released[29];
+    assert!(last >= Duration::from_millis(1_990) && last <= Duration::from_millis(2_010));
+    let in_first_second = released
+        .iter()
+        .filter(|elapsed| **elapsed <= Duration::from_secs(1))
+        .count();
+    assert!(in_first_second <= 20);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn tokens_refill_after_idle_period() {
+    let limiter = RateLimiter::new("test", 5);
+    for _ in 0..5 {
+        limiter.acquire().await;
+    }
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    let start = Instant::now();
+    for _ in 0..5 {
+        limiter.acquire().await;
+    }
+
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert_eq!(limiter.throttled(), 0);
+}
@@ -0,0 +1,149 @@
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_request::RpcError;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::time::Duration;
+use tracing::warn;
+
+/// Default number of attempts per RPC call, including the first one
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Default backoff before the first retry
+const DEFAULT_BASE_DELAY_MS: u64 = 200;
+/// Upper bound for a single backoff
+const DEFAULT_MAX_DELAY_MS: u64 = 5_000;
+
+/// Class of an RPC error, deciding whether the call is worth retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    RateLimited,
+    Timeout,
+    Connection,
+    /// Request or deserialization errors that fail the same way on every attempt
+    Fatal,
+}
+
+impl ErrorClass {
+    pub fn is_retryable(self) -> bool {
+        self != ErrorClass::Fatal
+    }
+}
+
+/// Classify an RPC error into a retry class
+pub fn classify(error: &ClientError) -> ErrorClass {
+    match error.kind() {
+        ClientErrorKind::Io(e) => match e.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => ErrorClass::Timeout,
+            ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof => ErrorClass::Connection,
+            _ => ErrorClass::Fatal,
+        },
+        ClientErrorKind::Reqwest(e) => {
+            if e.status().is_some_and(|status| status.as_u16() == 429) {
+                ErrorClass::RateLimited
+            } else if e.is_timeout() {
+                ErrorClass::Timeout
+            } else if e.is_connect()
+                || e.is_request()
+                || e.status().is_some_and(|status| status.is_server_error())
+            {
+                ErrorClass::Connection
+            } else {
+                ErrorClass::Fatal
+            }
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+            if *code == 429 || *code == -32429 || message.contains("Too many requests") {
+                ErrorClass::RateLimited
+            } else {
+                ErrorClass::Fatal
+            }
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => ErrorClass::Connection,
+        _ => ErrorClass::Fatal,
+    }
+}
+
+/// Exponential backoff policy for RPC calls
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: Duration::from_millis(DEFAULT_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Load the policy from `RPC_MAX_ATTEMPTS` and `RPC_RETRY_BASE_DELAY_MS`, falling back to defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_attempts: std::env::var("RPC_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|attempts| *attempts > 0)
+                .unwrap_or(default.max_attempts),
+            base_delay: std::env::var("RPC_RETRY_BASE_DELAY_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.base_delay),
+            max_delay: default.max_delay,
+        }
+    }
+
+    /// Backoff before retry number `retry` (1-based): the capped exponential delay
+    /// with jitter picked uniformly from its upper half
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        let millis = delay.as_millis() as u64;
+        Duration::from_millis(rand::random_range(millis / 2..=millis))
+    }
+}
+
+/// Run `op` until it succeeds, fails with a non-retryable error, or runs out of attempts
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, method: &str, mut op: F) -> ClientResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ClientResult<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let class = classify(&e);
+                if !class.is_retryable() || attempt >= policy.max_attempts {
+                    return Err(e);
+                }
+                let delay = policy.backoff(attempt);
+                warn!(
+                    "RPC {} failed ({:?}) on attempt {}/{}: {}, retrying in {:?}",
+                    method, class, attempt, policy.max_attempts, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "retry_tests.rs"]
+mod retry_tests;
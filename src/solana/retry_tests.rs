@@ -0,0 +1,132 @@
+use super::*;
+use solana_client::rpc_request::RpcResponseErrorData;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+fn io_error(kind: ErrorKind) -> ClientError {
+    ClientErrorKind::Io(std::io::Error::new(kind, "synthetic")).into()
+}
+
+fn response_error(code: i64, message: &str) -> ClientError {
+    ClientErrorKind::RpcError(RpcError::RpcResponseError {
+        code,
+        message: message.to_string(),
+        data: RpcResponseErrorData::Empty,
+    })
+    .into()
+}
+
+fn serde_error() -> ClientError {
+    let error = serde_json::from_str::<u64>("not json").unwrap_err();
+    ClientErrorKind::SerdeJson(error).into()
+}
+
+fn fast_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(2),
+    }
+}
+
+#[test]
+fn classify_connection_errors_as_retryable() {
+    assert_eq!(
+        classify(&io_error(ErrorKind::ConnectionReset)),
+        ErrorClass::Connection
+    );
+    assert_eq!(
+        classify(&io_error(ErrorKind::BrokenPipe)),
+        ErrorClass::Connection
+    );
+    assert_eq!(
+        classify(&io_error(ErrorKind::TimedOut)),
+        ErrorClass::Timeout
+    );
+}
+
+#[test]
+fn classify_rate_limit_responses() {
+    assert_eq!(
+        classify(&response_error(429, "Too many requests")),
+        ErrorClass::RateLimited
+    );
+    assert_eq!(
+        classify(&response_error(-32429, "rate limited")),
+        ErrorClass::RateLimited
+    );
+}
+
+#[test]
+fn classify_request_and_deserialization_errors_as_fatal() {
+    assert_eq!(
+        classify(&response_error(-32602, "Invalid params")),
+        ErrorClass::Fatal
+    );
+    assert_eq!(classify(&serde_error()), ErrorClass::Fatal);
+    assert_eq!(
+        classify(&ClientErrorKind::Custom("bad data".to_string()).into()),
+        ErrorClass::Fatal
+    );
+    assert!(!ErrorClass::Fatal.is_retryable());
+}
+
+#[test]
+fn backoff_grows_exponentially_and_is_capped() {
+    let policy = RetryPolicy {
+        max_attempts: 10,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(1_000),
+    };
+
+    let first = policy.backoff(1);
+    assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+    let third = policy.backoff(3);
+    assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+    let capped = policy.backoff(10);
+    assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_millis(1_000));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_succeeds_after_transient_errors() {
+    let calls = AtomicU32::new(0);
+
+    let result = retry(&fast_policy(3), "getAccountInfo", || async {
+        if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+            Err(io_error(ErrorKind::ConnectionReset))
+        } else {
+            Ok(7)
+        }
+    })
+    .await;
+
+    assert_eq!(result.unwrap(), 7);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_fails_fast_on_fatal_error() {
+    let calls = AtomicU32::new(0);
+
+    let result: ClientResult<()> = retry(&fast_policy(3), "getAccountInfo", || async {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Err(serde_error())
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_gives_up_after_max_attempts() {
+    let calls = AtomicU32::new(0);
+
+    let result: ClientResult<()> = retry(&fast_policy(3), "getAccountInfo", || async {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Err(io_error(ErrorKind::TimedOut))
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,472 @@
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_response::{Response, RpcPrioritizationFee, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+use super::rate_limit::RateLimiter;
+use super::retry::{RetryPolicy, retry};
+use super::utils::{MAX_ACCOUNTS_PER_REQUEST, fetch_in_chunks};
+use crate::telemetry::LatencyMetrics;
+
+/// Minimal Solana RPC surface used by the screeners
+pub trait SolanaRpc: Send + Sync {
+    fn url(&self) -> String;
+
+    fn get_account(&self, pubkey: &Pubkey) -> impl Future<Output = ClientResult<Account>> + Send;
+
+    /// Fetch an account at `commitment` instead of the client default; `None` if it does not exist
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> impl Future<Output = ClientResult<Option<Account>>> + Send;
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> impl Future<Output = ClientResult<Vec<Option<Account>>>> + Send;
+
+    fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> impl Future<Output = ClientResult<RpcSimulateTransactionResult>> + Send;
+
+    /// Prioritization fees paid in recent slots by transactions locking any of `addresses`
+    fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> impl Future<Output = ClientResult<Vec<RpcPrioritizationFee>>> + Send;
+
+    /// Lamports held by `pubkey`, with the slot they were read at
+    fn get_balance(
+        &self,
+        pubkey: &Pubkey,
+    ) -> impl Future<Output = ClientResult<Response<u64>>> + Send;
+
+    /// Raw `getTokenAccountsByOwner` result (`context` and `value`) of the token accounts of
+    /// `owner` under `program_id`, with `jsonParsed` account data
+    fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> impl Future<Output = ClientResult<serde_json::Value>> + Send;
+}
+
+impl SolanaRpc for RpcClient {
+    fn url(&self) -> String {
+        RpcClient::url(self)
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        RpcClient::get_account(self, pubkey).await
+    }
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<Option<Account>> {
+        Ok(
+            RpcClient::get_account_with_commitment(self, pubkey, commitment)
+                .await?
+                .value,
+        )
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<Vec<Option<Account>>> {
+        RpcClient::get_multiple_accounts(self, pubkeys).await
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> ClientResult<RpcSimulateTransactionResult> {
+        Ok(
+            RpcClient::simulate_transaction_with_config(self, transaction, config)
+                .await?
+                .value,
+        )
+    }
+
+    async fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        RpcClient::get_recent_prioritization_fees(self, addresses).await
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<Response<u64>> {
+        RpcClient::get_balance_with_commitment(self, pubkey, self.commitment()).await
+    }
+
+    async fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> ClientResult<serde_json::Value> {
+        self.send(
+            RpcRequest::GetTokenAccountsByOwner,
+            serde_json::json!([
+                owner.to_string(),
+                { "programId": program_id.to_string() },
+                { "encoding": "jsonParsed", "commitment": self.commitment().commitment },
+            ]),
+        )
+        .await
+    }
+}
+
+/// RPC endpoint with its consecutive error count
+struct Endpoint<C> {
+    client: C,
+    errors: AtomicU64,
+}
+
+/// RPC client that fails over to the next endpoint on transport or server errors.
+/// Endpoints with fewer recent errors are tried first, and a call that failed on
+/// every endpoint is retried with backoff when the error is transient.
+pub struct FailoverRpcClient<C = RpcClient> {
+    endpoints: Vec<Endpoint<C>>,
+    retry_policy: RetryPolicy,
+    /// Larger `get_multiple_accounts` calls are split into concurrent requests of this size
+    max_accounts_per_request: usize,
+    /// Shared limiter every request waits on before being sent
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Latency and outcome of every call, including its failovers and retries
+    metrics: Option<Arc<LatencyMetrics>>,
+}
+
+impl FailoverRpcClient<RpcClient> {
+    /// Build a client for every endpoint url using the given commitment
+    pub fn from_urls(urls: Vec<String>, commitment: CommitmentConfig) -> Self {
+        Self::new(
+            urls.into_iter()
+                .map(|url| RpcClient::new_with_commitment(url, commitment))
+                .collect(),
+        )
+        .with_retry_policy(RetryPolicy::from_env())
+        .with_max_accounts_per_request(max_accounts_per_request_from_env())
+        .with_rate_limiter(Arc::new(RateLimiter::from_env("rpc")))
+    }
+}
+
+impl<C: SolanaRpc> FailoverRpcClient<C> {
+    pub fn new(clients: Vec<C>) -> Self {
+        Self {
+            endpoints: clients
+                .into_iter()
+                .map(|client| Endpoint {
+                    client,
+                    errors: AtomicU64::new(0),
+                })
+                .collect(),
+            retry_policy: RetryPolicy::default(),
+            max_accounts_per_request: MAX_ACCOUNTS_PER_REQUEST,
+            rate_limiter: None,
+            metrics: None,
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Throttle every request, including failover attempts and retries, through `rate_limiter`
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<LatencyMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_max_accounts_per_request(mut self, max_accounts_per_request: usize) -> Self {
+        self.max_accounts_per_request = max_accounts_per_request.max(1);
+        self
+    }
+
+    /// Error counts per endpoint, in configuration order
+    pub fn error_counts(&self) -> Vec<u64> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.errors.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Count a bad response of the preferred endpoint, e.g. lagging data, as an error so
+    /// later calls prefer another endpoint. Returns whether another endpoint is now preferred.
+    pub fn demote_preferred_endpoint(&self) -> bool {
+        let Some(&preferred) = self.endpoint_order().first() else {
+            return false;
+        };
+        let errors = self.endpoints[preferred]
+            .errors
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        let next = self.endpoint_order()[0];
+        if next == preferred {
+            return false;
+        }
+        warn!(
+            "RPC endpoint {} demoted ({} errors), preferring {}",
+            redact_url(&self.endpoints[preferred].client.url()),
+            errors,
+            redact_url(&self.endpoints[next].client.url())
+        );
+        true
+    }
+
+    pub async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.call("getAccountInfo", |client| client.get_account(pubkey))
+            .await
+    }
+
+    pub async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<Option<Account>> {
+        self.call("getAccountInfo", |client| {
+            client.get_account_with_commitment(pubkey, commitment)
+        })
+        .await
+    }
+
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<Vec<Option<Account>>> {
+        fetch_in_chunks(pubkeys, self.max_accounts_per_request, |chunk| {
+            self.call("getMultipleAccounts", move |client| {
+                client.get_multiple_accounts(chunk)
+            })
+        })
+        .await
+    }
+
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> ClientResult<RpcSimulateTransactionResult> {
+        self.call("simulateTransaction", |client| {
+            client.simulate_transaction(transaction, config.clone())
+        })
+        .await
+    }
+
+    pub async fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        self.call("getRecentPrioritizationFees", |client| {
+            client.get_recent_prioritization_fees(addresses)
+        })
+        .await
+    }
+
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<Response<u64>> {
+        self.call("getBalance", |client| client.get_balance(pubkey))
+            .await
+    }
+
+    pub async fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> ClientResult<serde_json::Value> {
+        self.call("getTokenAccountsByOwner", |client| {
+            client.get_token_accounts_by_owner(owner, program_id)
+        })
+        .await
+    }
+
+    /// Run `op` with failover, retrying the whole sequence on transient errors.
+    /// The whole sequence is timed as one call of `method` when metrics are enabled.
+    async fn call<'a, T, F, Fut>(&'a self, method: &str, op: F) -> ClientResult<T>
+    where
+        F: Fn(&'a C) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let call = retry(&self.retry_policy, method, || self.call_with_failover(&op));
+        match &self.metrics {
+            Some(metrics) => metrics.time(method, call).await,
+            None => call.await,
+        }
+    }
+
+    /// Run `op` against the healthiest endpoint, failing over on transport/5xx errors
+    async fn call_with_failover<'a, T, F, Fut>(&'a self, op: &F) -> ClientResult<T>
+    where
+        F: Fn(&'a C) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let order = self.endpoint_order();
+        let mut last_error = None;
+
+        for (attempt, &index) in order.iter().enumerate() {
+            let endpoint = &self.endpoints[index];
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+            match op(&endpoint.client).await {
+                Ok(value) => {
+                    endpoint.errors.store(0, Ordering::Relaxed);
+                    return Ok(value);
+                }
+                Err(e) if is_failover_error(&e) => {
+                    let errors = endpoint.errors.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Some(&next) = order.get(attempt + 1) {
+                        warn!(
+                            "RPC endpoint {} failed ({} errors): {}, failing over to {}",
+                            redact_url(&endpoint.client.url()),
+                            errors,
+                            e,
+                            redact_url(&self.endpoints[next].client.url())
+                        );
+                    }
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            ClientErrorKind::Custom("No RPC endpoints configured".to_string()).into()
+        }))
+    }
+
+    /// Endpoint indexes sorted by error count, keeping configuration order on ties
+    fn endpoint_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        order.sort_by_key(|&index| self.endpoints[index].errors.load(Ordering::Relaxed));
+        order
+    }
+}
+
+/// Whether an error is caused by the endpoint itself (transport, timeout, 5xx, rate limit)
+/// rather than by the request, so another endpoint may succeed
+pub fn is_failover_error(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_connect()
+                || e.is_timeout()
+                || e.status()
+                    .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        _ => false,
+    }
+}
+
+/// Parse a comma-separated list of RPC endpoint urls
+pub fn parse_endpoints(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Resolve RPC endpoints from `RPC_ENDPOINTS`, falling back to Helius when only `HELIUS_API_KEY` is set
+pub fn rpc_endpoints_from_env() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    resolve_rpc_endpoints(
+        std::env::var("RPC_ENDPOINTS").ok(),
+        std::env::var("HELIUS_API_KEY").ok(),
+    )
+}
+
+fn resolve_rpc_endpoints(
+    rpc_endpoints: Option<String>,
+    helius_api_key: Option<String>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let endpoints = rpc_endpoints
+        .map(|value| parse_endpoints(&value))
+        .unwrap_or_default();
+    if !endpoints.is_empty() {
+        return Ok(endpoints);
+    }
+
+    let helius_api_key = helius_api_key
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .ok_or("Missing Solana RPC configuration: set RPC_ENDPOINTS or HELIUS_API_KEY")?;
+    Ok(vec![helius_url(&helius_api_key)])
+}
+
+/// Parse a commitment level name: `processed`, `confirmed` or `finalized`
+pub fn parse_commitment(value: &str) -> Option<CommitmentConfig> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "processed" => Some(CommitmentConfig::processed()),
+        "confirmed" => Some(CommitmentConfig::confirmed()),
+        "finalized" => Some(CommitmentConfig::finalized()),
+        _ => None,
+    }
+}
+
+/// Read a commitment level from the env var `name`, falling back to `default` when unset.
+/// Unknown values are rejected so a typo does not silently change finality.
+pub fn commitment_from_env(
+    name: &str,
+    default: CommitmentConfig,
+) -> Result<CommitmentConfig, Box<dyn std::error::Error>> {
+    resolve_commitment(name, std::env::var(name).ok(), default)
+}
+
+fn resolve_commitment(
+    name: &str,
+    value: Option<String>,
+    default: CommitmentConfig,
+) -> Result<CommitmentConfig, Box<dyn std::error::Error>> {
+    match value {
+        Some(value) => parse_commitment(&value).ok_or_else(|| {
+            format!(
+                "Invalid {} '{}': expected processed, confirmed or finalized",
+                name, value
+            )
+            .into()
+        }),
+        None => Ok(default),
+    }
+}
+
+/// Read the `getMultipleAccounts` chunk size from `RPC_MAX_ACCOUNTS_PER_REQUEST`, falling back to the RPC limit
+fn max_accounts_per_request_from_env() -> usize {
+    std::env::var("RPC_MAX_ACCOUNTS_PER_REQUEST")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(MAX_ACCOUNTS_PER_REQUEST)
+}
+
+/// Helius mainnet RPC url for an API key
+pub fn helius_url(api_key: &str) -> String {
+    format!("https://mainnet.helius-rpc.com/?api-key={}", api_key)
+}
+
+/// Strip the query string so API keys never end up in logs
+pub fn redact_url(url: &str) -> &str {
+    url.split('?').next().unwrap_or(url)
+}
+
+#[cfg(test)]
+#[path = "rpc_tests.rs"]
+mod rpc_tests;
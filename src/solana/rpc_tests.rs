@@ -0,0 +1,345 @@
+use super::*;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+
+/// Fake endpoint returning queued results and counting calls
+struct MockRpc {
+    url: String,
+    results: Mutex<Vec<ClientResult<Account>>>,
+    calls: AtomicUsize,
+}
+
+impl MockRpc {
+    fn new(url: &str, results: Vec<ClientResult<Account>>) -> Self {
+        Self {
+            url: url.to_string(),
+            results: Mutex::new(results),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl SolanaRpc for MockRpc {
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn get_account(&self, _pubkey: &Pubkey) -> ClientResult<Account> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut results = self.results.lock().unwrap();
+        if results.is_empty() {
+            Ok(Account::default())
+        } else {
+            results.remove(0)
+        }
+    }
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        _commitment: CommitmentConfig,
+    ) -> ClientResult<Option<Account>> {
+        self.get_account(pubkey).await.map(Some)
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<Vec<Option<Account>>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![None; pubkeys.len()])
+    }
+
+    async fn simulate_transaction(
+        &self,
+        _transaction: &Transaction,
+        _config: RpcSimulateTransactionConfig,
+    ) -> ClientResult<RpcSimulateTransactionResult> {
+        Err(ClientErrorKind::Custom("simulation is not mocked".to_string()).into())
+    }
+
+    async fn get_recent_prioritization_fees(
+        &self,
+        _addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_balance(&self, _pubkey: &Pubkey) -> ClientResult<Response<u64>> {
+        Err(ClientErrorKind::Custom("balances are not mocked".to_string()).into())
+    }
+
+    async fn get_token_accounts_by_owner(
+        &self,
+        _owner: &Pubkey,
+        _program_id: &Pubkey,
+    ) -> ClientResult<serde_json::Value> {
+        Err(ClientErrorKind::Custom("token accounts are not mocked".to_string()).into())
+    }
+}
+
+fn transport_error() -> ClientError {
+    ClientErrorKind::Io(std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        "connection reset",
+    ))
+    .into()
+}
+
+fn request_error() -> ClientError {
+    ClientErrorKind::RpcError(RpcError::ForUser("AccountNotFound".to_string())).into()
+}
+
+/// Failover client without retries so each test sees a single pass over the endpoints
+fn failover(clients: Vec<MockRpc>) -> FailoverRpcClient<MockRpc> {
+    FailoverRpcClient::new(clients).with_retry_policy(RetryPolicy {
+        max_attempts: 1,
+        ..RetryPolicy::default()
+    })
+}
+
+fn account_with_lamports(lamports: u64) -> Account {
+    Account {
+        lamports,
+        ..Account::default()
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn failover_uses_next_endpoint_on_transport_error() {
+    let client = failover(vec![
+        MockRpc::new("http://primary", vec![Err(transport_error())]),
+        MockRpc::new("http://secondary", vec![Ok(account_with_lamports(42))]),
+    ]);
+
+    let account = client.get_account(&Pubkey::new_unique()).await.unwrap();
+
+    assert_eq!(account.lamports, 42);
+    assert_eq!(client.error_counts(), vec![1, 0]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn failover_prefers_endpoint_with_fewer_errors() {
+    let client = failover(vec![
+        MockRpc::new("http://primary", vec![Err(transport_error())]),
+        MockRpc::new("http://secondary", vec![]),
+    ]);
+
+    client.get_account(&Pubkey::new_unique()).await.unwrap();
+    client.get_account(&Pubkey::new_unique()).await.unwrap();
+
+    assert_eq!(client.endpoints[0].client.calls(), 1);
+    assert_eq!(client.endpoints[1].client.calls(), 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn failover_does_not_retry_request_errors() {
+    let client = failover(vec![
+        MockRpc::new("http://primary", vec![Err(request_error())]),
+        MockRpc::new("http://secondary", vec![]),
+    ]);
+
+    let result = client.get_account(&Pubkey::new_unique()).await;
+
+    assert!(result.is_err());
+    assert_eq!(client.endpoints[1].client.calls(), 0);
+    assert_eq!(client.error_counts(), vec![0, 0]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn failover_returns_last_error_when_all_endpoints_fail() {
+    let client = failover(vec![
+        MockRpc::new("http://primary", vec![Err(transport_error())]),
+        MockRpc::new("http://secondary", vec![Err(transport_error())]),
+    ]);
+
+    let result = client.get_account(&Pubkey::new_unique()).await;
+
+    assert!(result.is_err());
+    assert_eq!(client.error_counts(), vec![1, 1]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn successful_call_resets_endpoint_error_count() {
+    let client = failover(vec![
+        MockRpc::new(
+            "http://primary",
+            vec![Err(transport_error()), Ok(Account::default())],
+        ),
+        MockRpc::new(
+            "http://secondary",
+            vec![Ok(Account::default()), Err(transport_error())],
+        ),
+    ]);
+
+    client.get_account(&Pubkey::new_unique()).await.unwrap();
+    assert_eq!(client.error_counts(), vec![1, 0]);
+    client.get_account(&Pubkey::new_unique()).await.unwrap();
+
+    assert_eq!(client.error_counts(), vec![0, 1]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn demoted_endpoint_is_tried_after_the_others() {
+    let client = failover(vec![
+        MockRpc::new("http://primary", vec![]),
+        MockRpc::new("http://secondary", vec![]),
+    ]);
+
+    assert!(client.demote_preferred_endpoint());
+    client.get_account(&Pubkey::new_unique()).await.unwrap();
+
+    assert_eq!(client.error_counts(), vec![1, 0]);
+    assert_eq!(client.endpoints[0].client.calls(), 0);
+    assert_eq!(client.endpoints[1].client.calls(), 1);
+}
+
+#[test]
+fn demoting_the_only_endpoint_has_no_fallback() {
+    let client = failover(vec![MockRpc::new("http://primary", vec![])]);
+
+    assert!(!client.demote_preferred_endpoint());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retries_after_every_endpoint_failed() {
+    let client = FailoverRpcClient::new(vec![
+        MockRpc::new("http://primary", vec![Err(transport_error())]),
+        MockRpc::new("http://secondary", vec![Err(transport_error())]),
+    ])
+    .with_retry_policy(RetryPolicy {
+        max_attempts: 2,
+        base_delay: std::time::Duration::from_millis(1),
+        max_delay: std::time::Duration::from_millis(1),
+    });
+
+    let result = client.get_account(&Pubkey::new_unique()).await;
+
+    assert!(result.is_ok());
+    assert_eq!(client.endpoints[0].client.calls(), 2);
+}
+
+#[test]
+fn parse_endpoints_splits_and_trims() {
+    let endpoints = parse_endpoints(" https://a.example , ,https://b.example,");
+
+    assert_eq!(endpoints, vec!["https://a.example", "https://b.example"]);
+}
+
+#[test]
+fn resolve_rpc_endpoints_prefers_rpc_endpoints_over_helius() {
+    assert_eq!(
+        resolve_rpc_endpoints(
+            Some("http://a, http://b".to_string()),
+            Some("key".to_string())
+        )
+        .unwrap(),
+        vec!["http://a".to_string(), "http://b".to_string()]
+    );
+    assert_eq!(
+        resolve_rpc_endpoints(Some(" , ".to_string()), Some("key".to_string())).unwrap(),
+        vec![helius_url("key")]
+    );
+}
+
+#[test]
+fn resolve_rpc_endpoints_names_both_variables_when_unset() {
+    let error = resolve_rpc_endpoints(None, None).unwrap_err().to_string();
+
+    assert!(error.contains("RPC_ENDPOINTS") && error.contains("HELIUS_API_KEY"));
+    assert!(resolve_rpc_endpoints(None, Some(" ".to_string())).is_err());
+}
+
+#[test]
+fn redact_url_strips_api_key() {
+    assert_eq!(
+        redact_url("https://mainnet.helius-rpc.com/?api-key=secret"),
+        "https://mainnet.helius-rpc.com/"
+    );
+    assert_eq!(redact_url("https://rpc.example"), "https://rpc.example");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn get_multiple_accounts_is_split_into_chunks() {
+    let client =
+        failover(vec![MockRpc::new("http://primary", vec![])]).with_max_accounts_per_request(2);
+    let keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+
+    let accounts = client.get_multiple_accounts(&keys).await.unwrap();
+
+    assert_eq!(accounts.len(), 5);
+    assert_eq!(client.endpoints[0].client.calls(), 3);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn requests_wait_on_the_rate_limiter() {
+    let client = failover(vec![MockRpc::new("http://primary", vec![])])
+        .with_rate_limiter(Arc::new(RateLimiter::new("test", 2)));
+    let start = tokio::time::Instant::now();
+
+    for _ in 0..4 {
+        client.get_account(&Pubkey::new_unique()).await.unwrap();
+    }
+
+    assert_eq!(start.elapsed(), std::time::Duration::from_secs(1));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn calls_are_timed_once_per_method_including_failovers() {
+    let metrics = Arc::new(LatencyMetrics::new("rpc"));
+    let client = failover(vec![
+        MockRpc::new("http://primary", vec![Err(transport_error())]),
+        MockRpc::new("http://backup", vec![Err(request_error())]),
+    ])
+    .with_metrics(metrics.clone());
+
+    client.get_account(&Pubkey::new_unique()).await.unwrap_err();
+    client.get_account(&Pubkey::new_unique()).await.unwrap();
+
+    let summaries = metrics.take_summary();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].method, "getAccountInfo");
+    assert_eq!((summaries[0].calls, summaries[0].errors), (2, 1));
+}
+
+#[test]
+fn parse_commitment_accepts_known_levels() {
+    assert_eq!(
+        parse_commitment("processed"),
+        Some(CommitmentConfig::processed())
+    );
+    assert_eq!(
+        parse_commitment(" Confirmed "),
+        Some(CommitmentConfig::confirmed())
+    );
+    assert_eq!(
+        parse_commitment("finalized"),
+        Some(CommitmentConfig::finalized())
+    );
+    assert_eq!(parse_commitment("finalised"), None);
+}
+
+#[test]
+fn resolve_commitment_rejects_typos() {
+    let result = resolve_commitment(
+        "SOLANA_COMMITMENT",
+        Some("confrimed".to_string()),
+        CommitmentConfig::confirmed(),
+    );
+
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("SOLANA_COMMITMENT"));
+    assert!(message.contains("confrimed"));
+}
+
+#[test]
+fn resolve_commitment_defaults_when_unset() {
+    assert_eq!(
+        resolve_commitment("SOLANA_COMMITMENT", None, CommitmentConfig::processed()).unwrap(),
+        CommitmentConfig::processed()
+    );
+}
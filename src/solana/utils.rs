@@ -0,0 +1,95 @@
+use futures::future::try_join_all;
+use solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::check_spl_token_program_account;
+use std::future::Future;
+
+/// Maximum number of accounts a `getMultipleAccounts` request may ask for
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Fetch `pubkeys` in chunks of at most `chunk_size` accounts, issuing the chunk
+/// requests concurrently and returning the accounts in the order of `pubkeys`.
+pub async fn fetch_in_chunks<'a, F, Fut>(
+    pubkeys: &'a [Pubkey],
+    chunk_size: usize,
+    fetch: F,
+) -> ClientResult<Vec<Option<Account>>>
+where
+    F: Fn(&'a [Pubkey]) -> Fut,
+    Fut: Future<Output = ClientResult<Vec<Option<Account>>>>,
+{
+    let chunks: Vec<&[Pubkey]> = pubkeys.chunks(chunk_size.max(1)).collect();
+    let results = try_join_all(chunks.iter().map(|&chunk| fetch(chunk))).await?;
+
+    let mut accounts = Vec::with_capacity(pubkeys.len());
+    for (chunk, result) in chunks.iter().zip(results) {
+        if result.len() != chunk.len() {
+            return Err(ClientErrorKind::Custom(format!(
+                "getMultipleAccounts returned {} accounts for {} keys",
+                result.len(),
+                chunk.len()
+            ))
+            .into());
+        }
+        accounts.extend(result);
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+#[path = "utils_tests.rs"]
+mod utils_tests;
+
+/// Length of the Anchor account discriminator prefixing account data
+pub const ANCHOR_DISCRIMINATOR_LEN: usize = 8;
+
+/// Deserialize an Anchor zero-copy account, checking its discriminator and data length
+/// so truncated or unexpected accounts return an error instead of panicking.
+pub fn read_anchor_account<T: bytemuck::AnyBitPattern>(
+    name: &str,
+    data: &[u8],
+    discriminator: &[u8; ANCHOR_DISCRIMINATOR_LEN],
+) -> Result<T, Box<dyn std::error::Error>> {
+    let expected_len = ANCHOR_DISCRIMINATOR_LEN + std::mem::size_of::<T>();
+    if data.len() < expected_len {
+        return Err(format!(
+            "{} account data too short: {} bytes, expected at least {}",
+            name,
+            data.len(),
+            expected_len
+        )
+        .into());
+    }
+    if data[..ANCHOR_DISCRIMINATOR_LEN] != discriminator[..] {
+        return Err(format!(
+            "{} account has discriminator {:?}, expected {:?}",
+            name,
+            &data[..ANCHOR_DISCRIMINATOR_LEN],
+            discriminator
+        )
+        .into());
+    }
+    Ok(bytemuck::pod_read_unaligned(
+        &data[ANCHOR_DISCRIMINATOR_LEN..expected_len],
+    ))
+}
+
+/// Offset of the amount in SPL Token / Token-2022 token accounts
+pub const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+/// Length of the base SPL token account layout
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Token amount held by an SPL Token / Token-2022 token account
+pub fn token_account_amount(account: &Account) -> Result<u64, Box<dyn std::error::Error>> {
+    check_spl_token_program_account(&account.owner)
+        .map_err(|_| format!("Account is owned by {}, not a token program", account.owner))?;
+    if account.data.len() < TOKEN_ACCOUNT_LEN {
+        return Err(format!("Token account is only {} bytes", account.data.len()).into());
+    }
+    Ok(u64::from_le_bytes(
+        account.data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+            .try_into()
+            .unwrap(),
+    ))
+}
@@ -0,0 +1,125 @@
+use super::*;
+use std::sync::Mutex;
+
+/// Account whose lamports encode the position of its pubkey in `keys`
+fn indexed_account(keys: &[Pubkey], pubkey: &Pubkey) -> Option<Account> {
+    let index = keys.iter().position(|key| key == pubkey)?;
+    Some(Account {
+        lamports: index as u64,
+        ..Account::default()
+    })
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fetch_in_chunks_splits_and_reassembles_in_order() {
+    let keys: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+    let chunk_sizes = Mutex::new(Vec::new());
+
+    let accounts = fetch_in_chunks(&keys, MAX_ACCOUNTS_PER_REQUEST, |chunk| {
+        chunk_sizes.lock().unwrap().push(chunk.len());
+        let result = chunk
+            .iter()
+            .map(|key| indexed_account(&keys, key))
+            .collect();
+        async move { Ok(result) }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(*chunk_sizes.lock().unwrap(), vec![100, 100, 50]);
+    assert_eq!(accounts.len(), 250);
+    for (index, account) in accounts.iter().enumerate() {
+        assert_eq!(account.as_ref().unwrap().lamports, index as u64);
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fetch_in_chunks_keeps_missing_accounts_in_place() {
+    let keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let missing = keys[3];
+
+    let accounts = fetch_in_chunks(&keys, 2, |chunk| {
+        let result = chunk
+            .iter()
+            .map(|key| {
+                (*key != missing)
+                    .then(|| indexed_account(&keys, key))
+                    .flatten()
+            })
+            .collect();
+        async move { Ok(result) }
+    })
+    .await
+    .unwrap();
+
+    assert!(accounts[3].is_none());
+    assert_eq!(accounts[4].as_ref().unwrap().lamports, 4);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fetch_in_chunks_fails_when_any_chunk_fails() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+
+    let result = fetch_in_chunks(&keys, 2, |chunk| {
+        let fail = chunk[0] == keys[2];
+        let len = chunk.len();
+        async move {
+            if fail {
+                Err(ClientErrorKind::Custom("boom".to_string()).into())
+            } else {
+                Ok(vec![None; len])
+            }
+        }
+    })
+    .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fetch_in_chunks_rejects_short_responses() {
+    let keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+
+    let result = fetch_in_chunks(&keys, 10, |_| async { Ok(vec![None]) }).await;
+
+    assert!(result.is_err());
+}
+
+const DISCRIMINATOR: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+fn anchor_account_data(value: u64) -> Vec<u8> {
+    let mut data = DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&value.to_le_bytes());
+    data
+}
+
+#[test]
+fn read_anchor_account_reads_value_after_discriminator() {
+    let mut data = anchor_account_data(42);
+    // Trailing padding is allowed, as accounts may be allocated larger than the struct
+    data.extend_from_slice(&[0u8; 16]);
+
+    let value: u64 = read_anchor_account("Test", &data, &DISCRIMINATOR).unwrap();
+
+    assert_eq!(value, 42);
+}
+
+#[test]
+fn read_anchor_account_rejects_too_short_data() {
+    let data = anchor_account_data(42);
+
+    let result = read_anchor_account::<u64>("Test", &data[..12], &DISCRIMINATOR);
+
+    assert!(result.unwrap_err().to_string().contains("too short"));
+    assert!(read_anchor_account::<u64>("Test", &[], &DISCRIMINATOR).is_err());
+}
+
+#[test]
+fn read_anchor_account_rejects_wrong_discriminator() {
+    let mut data = anchor_account_data(42);
+    data[0] = 0xff;
+
+    let result = read_anchor_account::<u64>("Test", &data, &DISCRIMINATOR);
+
+    assert!(result.unwrap_err().to_string().contains("discriminator"));
+}
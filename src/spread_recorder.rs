@@ -0,0 +1,215 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::models::market::{CEXState, DEXState};
+use crate::models::spread::Spread;
+use crate::store::db::DatabasePool;
+use crate::store::markets::{get_latest_cex_states, get_latest_dex_states};
+use crate::store::spreads::insert_spreads;
+
+/// Delay between two recorded ticks when `SPREAD_RECORD_INTERVAL_MS` is unset
+const DEFAULT_RECORD_INTERVAL_MS: u64 = 1000;
+/// Oldest state a spread is computed from when `SPREAD_MAX_STATE_AGE_MS` is unset
+const DEFAULT_MAX_STATE_AGE_MS: u64 = 5000;
+
+/// Tick interval and staleness limit of a `SpreadRecorder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpreadRecorderConfig {
+    pub record_interval: Duration,
+    /// Pairs whose CEX state or either DEX quote is older than this are skipped
+    pub max_state_age: Duration,
+}
+
+impl SpreadRecorderConfig {
+    /// Read `SPREAD_RECORD_INTERVAL_MS` and `SPREAD_MAX_STATE_AGE_MS`, falling back to the
+    /// defaults
+    pub fn from_env() -> Self {
+        let millis = |name: &str, default: u64| {
+            let millis = std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|millis| *millis > 0)
+                .unwrap_or(default);
+            Duration::from_millis(millis)
+        };
+        Self {
+            record_interval: millis("SPREAD_RECORD_INTERVAL_MS", DEFAULT_RECORD_INTERVAL_MS),
+            max_state_age: millis("SPREAD_MAX_STATE_AGE_MS", DEFAULT_MAX_STATE_AGE_MS),
+        }
+    }
+}
+
+/// Spread in bps of `buy_price` earned by buying at it and selling at `sell_price`, rounded to
+/// the 4 decimals stored. `None` for a non-positive buy price.
+pub fn spread_bps(buy_price: Decimal, sell_price: Decimal) -> Option<Decimal> {
+    if buy_price <= Decimal::ZERO {
+        return None;
+    }
+    let spread = (sell_price - buy_price)
+        .checked_mul(Decimal::from(10_000))?
+        .checked_div(buy_price)?;
+    Some(spread.round_dp(4))
+}
+
+/// Milliseconds `fetch_time` is behind `now`, zero for a state fetched after it
+fn age_ms(fetch_time: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    (now - fetch_time).num_milliseconds().max(0) as u64
+}
+
+/// Spreads of one tick, and the pairs left out because a side was stale
+#[derive(Debug, Default, PartialEq)]
+pub struct SpreadTick {
+    pub spreads: Vec<Spread>,
+    /// Trade pair of every (CEX, DEX) pairing skipped for a stale state
+    pub stale_pairs: Vec<String>,
+}
+
+/// Pair every CEX state with the `sell` and `buy` quotes of each DEX for the same pair and
+/// compute both directional spreads. DEXs missing one of the quotes are left out, as are
+/// pairings with a state older than `max_state_age` at `computed_at`, which are reported in
+/// `stale_pairs`. The newest quote wins when a DEX has several for a direction.
+pub fn compute_spreads(
+    cex_states: &[CEXState],
+    dex_states: &[DEXState],
+    computed_at: DateTime<Utc>,
+    max_state_age: Duration,
+) -> SpreadTick {
+    // Sell and buy quote of every DEX, per pair
+    let mut quotes: HashMap<&str, BTreeMap<&str, [Option<&DEXState>; 2]>> = HashMap::new();
+    for dex_state in dex_states {
+        let side = match dex_state.direction.as_str() {
+            "sell" => 0,
+            "buy" => 1,
+            _ => continue,
+        };
+        let slot = &mut quotes
+            .entry(dex_state.trade_pair.as_str())
+            .or_default()
+            .entry(dex_state.exchange.as_str())
+            .or_default()[side];
+        if slot.is_none_or(|kept| kept.fetch_time < dex_state.fetch_time) {
+            *slot = Some(dex_state);
+        }
+    }
+
+    let max_age_ms = max_state_age.as_millis() as u64;
+    let mut tick = SpreadTick::default();
+    for cex_state in cex_states {
+        let Some(dexes) = quotes.get(cex_state.trade_pair.as_str()) else {
+            continue;
+        };
+        for (dex_exchange, sides) in dexes {
+            let [Some(dex_bid), Some(dex_ask)] = *sides else {
+                continue;
+            };
+            let cex_age_ms = age_ms(cex_state.fetch_time, computed_at);
+            let dex_age_ms = age_ms(dex_bid.fetch_time, computed_at)
+                .max(age_ms(dex_ask.fetch_time, computed_at));
+            if cex_age_ms > max_age_ms || dex_age_ms > max_age_ms {
+                tick.stale_pairs.push(cex_state.trade_pair.clone());
+                continue;
+            }
+            let (Some(buy_dex_sell_cex), Some(buy_cex_sell_dex)) = (
+                spread_bps(dex_ask.price, cex_state.bid_price),
+                spread_bps(cex_state.ask_price, dex_bid.price),
+            ) else {
+                continue;
+            };
+            tick.spreads.push(Spread {
+                trade_pair: cex_state.trade_pair.clone(),
+                cex_exchange: cex_state.exchange.clone(),
+                dex_exchange: dex_exchange.to_string(),
+                cex_bid: cex_state.bid_price,
+                cex_ask: cex_state.ask_price,
+                dex_bid: dex_bid.price,
+                dex_ask: dex_ask.price,
+                spread_buy_dex_sell_cex_bps: buy_dex_sell_cex,
+                spread_buy_cex_sell_dex_bps: buy_cex_sell_dex,
+                cex_age_ms,
+                dex_age_ms,
+                computed_at,
+            });
+        }
+    }
+    tick
+}
+
+/// Records the spreads between the latest stored CEX and DEX states of every pair into the
+/// `spreads` table each `record_interval`
+pub struct SpreadRecorder {
+    pub db_pool: DatabasePool,
+    pub config: SpreadRecorderConfig,
+    /// Cancelled by `stop()` to end the record loop
+    pub shutdown: CancellationToken,
+    stale_skipped: AtomicU64,
+}
+
+impl SpreadRecorder {
+    pub fn with_config(db_pool: DatabasePool, config: SpreadRecorderConfig) -> Self {
+        Self {
+            db_pool,
+            config,
+            shutdown: CancellationToken::new(),
+            stale_skipped: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a tick each `record_interval` until the recorder is stopped
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Recording CEX/DEX spreads every {:?}, skipping states older than {:?}...",
+            self.config.record_interval, self.config.max_state_age
+        );
+        loop {
+            if let Err(e) = self.record().await {
+                error!("Failed to record spreads: {}", e);
+            }
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                _ = tokio::time::sleep(self.config.record_interval) => {}
+            }
+        }
+        info!("Spread recorder stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Pairings skipped so far because a side was stale
+    pub fn stale_skipped(&self) -> u64 {
+        self.stale_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Compute the spreads of the latest state of every pair and insert them. Stale pairs
+    /// are read too, so that skipping them is counted rather than silent.
+    async fn record(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let cex_states = get_latest_cex_states(&self.db_pool, None).await?;
+        let dex_states = get_latest_dex_states(&self.db_pool, None).await?;
+        let tick = compute_spreads(
+            &cex_states,
+            &dex_states,
+            Utc::now(),
+            self.config.max_state_age,
+        );
+
+        for trade_pair in tick.stale_pairs {
+            self.stale_skipped.fetch_add(1, Ordering::Relaxed);
+            metrics::counter!("spread_stale_pairs_skipped_total", "trade_pair" => trade_pair)
+                .increment(1);
+        }
+        insert_spreads(&self.db_pool, &tick.spreads).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "spread_recorder_tests.rs"]
+mod spread_recorder_tests;
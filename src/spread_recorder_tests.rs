@@ -0,0 +1,187 @@
+use super::*;
+use chrono::TimeZone;
+
+const MAX_AGE: Duration = Duration::from_secs(5);
+
+fn at_ms(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(1_700_000_000_000 + millis)
+        .unwrap()
+}
+
+fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+fn cex_state(exchange: &str, trade_pair: &str, bid: &str, ask: &str, fetched_ms: i64) -> CEXState {
+    CEXState {
+        trade_id: format!("{}:{}", trade_pair, fetched_ms),
+        exchange: exchange.to_string(),
+        trade_pair: trade_pair.to_string(),
+        bid_price: dec(bid),
+        bid_volume: Decimal::ONE,
+        ask_price: dec(ask),
+        ask_volume: Decimal::ONE,
+        trade_time: at_ms(fetched_ms),
+        fetch_time: at_ms(fetched_ms),
+        feed_latency_ms: None,
+        depth: None,
+    }
+}
+
+fn dex_state(exchange: &str, direction: &str, price: &str, fetched_ms: i64) -> DEXState {
+    DEXState {
+        trade_id: format!("TRUMPUSDC:{}:{}", fetched_ms, direction),
+        exchange: exchange.to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        direction: direction.to_string(),
+        price: dec(price),
+        volume: Decimal::TEN,
+        trade_time: at_ms(fetched_ms),
+        fetch_time: at_ms(fetched_ms),
+        block_number: 321_000_000,
+        price_impact_bps: None,
+        pool_address: None,
+        stale: false,
+        fetch_latency_ms: None,
+        route: None,
+        route_plan: None,
+    }
+}
+
+/// Sell and buy quote of `exchange` for TRUMPUSDC
+fn dex_quotes(exchange: &str, bid: &str, ask: &str, fetched_ms: i64) -> Vec<DEXState> {
+    vec![
+        dex_state(exchange, "sell", bid, fetched_ms),
+        dex_state(exchange, "buy", ask, fetched_ms),
+    ]
+}
+
+#[test]
+fn spread_bps_is_in_bps_of_the_buy_price() {
+    assert_eq!(spread_bps(dec("10"), dec("10.05")), Some(dec("50")));
+    assert_eq!(spread_bps(dec("10.05"), dec("10")), Some(dec("-49.7512")));
+    assert_eq!(spread_bps(dec("3"), dec("3")), Some(Decimal::ZERO));
+}
+
+#[test]
+fn spread_bps_rejects_a_non_positive_buy_price() {
+    assert_eq!(spread_bps(Decimal::ZERO, dec("10")), None);
+    assert_eq!(spread_bps(dec("-1"), dec("10")), None);
+}
+
+#[test]
+fn compute_spreads_pairs_the_cex_book_with_the_dex_quotes_in_both_directions() {
+    let cex_states = [cex_state("bybit", "TRUMPUSDC", "10.10", "10.12", 0)];
+    let dex_states = dex_quotes("meteora", "10.00", "10.02", -1500);
+
+    let tick = compute_spreads(&cex_states, &dex_states, at_ms(500), MAX_AGE);
+
+    assert_eq!(
+        tick,
+        SpreadTick {
+            spreads: vec![Spread {
+                trade_pair: "TRUMPUSDC".to_string(),
+                cex_exchange: "bybit".to_string(),
+                dex_exchange: "meteora".to_string(),
+                cex_bid: dec("10.10"),
+                cex_ask: dec("10.12"),
+                dex_bid: dec("10.00"),
+                dex_ask: dec("10.02"),
+                // (10.10 - 10.02) / 10.02 and (10.00 - 10.12) / 10.12
+                spread_buy_dex_sell_cex_bps: dec("79.8403"),
+                spread_buy_cex_sell_dex_bps: dec("-118.5771"),
+                cex_age_ms: 500,
+                dex_age_ms: 2000,
+                computed_at: at_ms(500),
+            }],
+            stale_pairs: vec![],
+        }
+    );
+}
+
+#[test]
+fn compute_spreads_records_every_dex_of_a_pair_and_skips_unmatched_pairs() {
+    let cex_states = [
+        cex_state("bybit", "TRUMPUSDC", "10", "10", 0),
+        cex_state("bybit", "SOLUSDC", "150", "150", 0),
+    ];
+    let mut dex_states = dex_quotes("raydium_clmm", "10", "10", 0);
+    dex_states.extend(dex_quotes("meteora", "10", "10", 0));
+
+    let tick = compute_spreads(&cex_states, &dex_states, at_ms(0), MAX_AGE);
+
+    let pairings: Vec<(&str, &str)> = tick
+        .spreads
+        .iter()
+        .map(|spread| (spread.trade_pair.as_str(), spread.dex_exchange.as_str()))
+        .collect();
+    assert_eq!(
+        pairings,
+        [("TRUMPUSDC", "meteora"), ("TRUMPUSDC", "raydium_clmm")]
+    );
+    assert!(tick.stale_pairs.is_empty());
+}
+
+#[test]
+fn compute_spreads_needs_both_dex_quotes() {
+    let cex_states = [cex_state("bybit", "TRUMPUSDC", "10", "10", 0)];
+    let dex_states = [
+        dex_state("meteora", "sell", "10", 0),
+        // A spot price is not a quote of either side
+        dex_state("meteora", "spot", "10", 0),
+    ];
+
+    let tick = compute_spreads(&cex_states, &dex_states, at_ms(0), MAX_AGE);
+
+    assert_eq!(tick, SpreadTick::default());
+}
+
+#[test]
+fn compute_spreads_uses_the_newest_quote_of_a_direction() {
+    let cex_states = [cex_state("bybit", "TRUMPUSDC", "10", "10", 0)];
+    let mut dex_states = dex_quotes("meteora", "9", "11", 0);
+    dex_states.insert(0, dex_state("meteora", "sell", "9.5", 100));
+    dex_states.push(dex_state("meteora", "buy", "12", -100));
+
+    let tick = compute_spreads(&cex_states, &dex_states, at_ms(100), MAX_AGE);
+
+    assert_eq!(tick.spreads.len(), 1);
+    assert_eq!(
+        (tick.spreads[0].dex_bid, tick.spreads[0].dex_ask),
+        (dec("9.5"), dec("11"))
+    );
+}
+
+#[test]
+fn compute_spreads_skips_pairs_with_a_stale_side() {
+    let cex_states = [
+        // Stale CEX book
+        cex_state("bybit", "TRUMPUSDC", "10", "10", -5001),
+        cex_state("okx", "TRUMPUSDC", "10", "10", -5000),
+    ];
+    let mut dex_states = dex_quotes("meteora", "10", "10", 0);
+    // Fresh bid, stale ask
+    dex_states.push(dex_state("raydium_clmm", "sell", "10", 0));
+    dex_states.push(dex_state("raydium_clmm", "buy", "10", -6000));
+
+    let tick = compute_spreads(&cex_states, &dex_states, at_ms(0), MAX_AGE);
+
+    let recorded: Vec<(&str, &str)> = tick
+        .spreads
+        .iter()
+        .map(|spread| (spread.cex_exchange.as_str(), spread.dex_exchange.as_str()))
+        .collect();
+    // Exactly the maximum age is still fresh
+    assert_eq!(recorded, [("okx", "meteora")]);
+    assert_eq!(tick.stale_pairs, ["TRUMPUSDC"; 3]);
+}
+
+#[test]
+fn compute_spreads_counts_states_fetched_after_the_tick_as_fresh() {
+    let cex_states = [cex_state("bybit", "TRUMPUSDC", "10", "10", 20)];
+    let dex_states = dex_quotes("meteora", "10", "10", 0);
+
+    let tick = compute_spreads(&cex_states, &dex_states, at_ms(0), MAX_AGE);
+
+    assert_eq!(tick.spreads[0].cex_age_ms, 0);
+}
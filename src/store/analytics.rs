@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::FromRow;
+
+use crate::models::candle::{Candle, CandleInterval};
+use crate::store::db::{DatabasePool, Unsigned, sql};
+
+/// Bucket of the query of [`ohlc_query`]
+#[derive(Debug, FromRow)]
+struct CandleRow {
+    open_time: DateTime<Utc>,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    #[sqlx(try_from = "Unsigned")]
+    row_count: u64,
+}
+
+impl From<CandleRow> for Candle {
+    fn from(row: CandleRow) -> Self {
+        Self {
+            open_time: row.open_time,
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+            row_count: row.row_count,
+        }
+    }
+}
+
+/// Get the candles of the mid price of a CEX pair fetched from `from` (inclusive) to `to`
+/// (exclusive), oldest first. Buckets without any stored state are omitted rather than
+/// zero-filled, so consecutive candles may be more than one `interval` apart; the first and
+/// last buckets only cover the states within the range.
+pub async fn get_ohlc(
+    pool: &DatabasePool,
+    exchange: &str,
+    trade_pair: &str,
+    interval: CandleInterval,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Candle>, Box<dyn std::error::Error>> {
+    let query = ohlc_query(interval);
+
+    let rows = sqlx::query_as::<_, CandleRow>(&sql(&query))
+        .bind(exchange)
+        .bind(trade_pair)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(Candle::from).collect())
+}
+
+/// Aggregation of the `cex_markets` rows of a pair and fetch time range into `interval`
+/// buckets. Rows are ranked from both ends of their bucket, by fetch time then id, to pick the
+/// open and close without an aggregate that depends on row order.
+fn ohlc_query(interval: CandleInterval) -> String {
+    let bucket = bucket_start("fetch_timestamp", interval.seconds());
+    format!(
+        r#"
+        SELECT {} AS open_time,
+            MAX(CASE WHEN first_rank = 1 THEN mid END) AS open,
+            MAX(mid) AS high,
+            MIN(mid) AS low,
+            MAX(CASE WHEN last_rank = 1 THEN mid END) AS close,
+            COUNT(*) AS row_count
+        FROM (
+            SELECT {} AS bucket,
+                (bid_price + ask_price) / 2 AS mid,
+                ROW_NUMBER() OVER (PARTITION BY {} ORDER BY fetch_timestamp, id) AS first_rank,
+                ROW_NUMBER() OVER (PARTITION BY {} ORDER BY fetch_timestamp DESC, id DESC) AS last_rank
+            FROM cex_markets
+            WHERE exchange = ? AND trade_pair = ? AND fetch_timestamp >= ? AND fetch_timestamp < ?
+        ) ranked
+        GROUP BY bucket
+        ORDER BY bucket
+    "#,
+        from_unix_seconds("bucket"),
+        bucket,
+        bucket,
+        bucket
+    )
+}
+
+/// Unix time, in whole seconds, of the start of the `seconds`-long bucket holding `column`
+fn bucket_start(column: &str, seconds: i64) -> String {
+    #[cfg(not(feature = "postgres"))]
+    {
+        // The session time zone is UTC, so UNIX_TIMESTAMP reads DATETIME values as UTC
+        format!(
+            "CAST(FLOOR(UNIX_TIMESTAMP({}) / {}) AS SIGNED) * {}",
+            column, seconds, seconds
+        )
+    }
+    #[cfg(feature = "postgres")]
+    {
+        format!(
+            "CAST(FLOOR(EXTRACT(EPOCH FROM {}) / {}) AS BIGINT) * {}",
+            column, seconds, seconds
+        )
+    }
+}
+
+/// Timestamp of the Unix time `seconds`
+fn from_unix_seconds(seconds: &str) -> String {
+    #[cfg(not(feature = "postgres"))]
+    {
+        format!("FROM_UNIXTIME({})", seconds)
+    }
+    #[cfg(feature = "postgres")]
+    {
+        format!("TO_TIMESTAMP({})", seconds)
+    }
+}
+
+#[cfg(test)]
+#[path = "analytics_tests.rs"]
+mod analytics_tests;
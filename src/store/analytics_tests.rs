@@ -0,0 +1,160 @@
+use super::*;
+use chrono::TimeZone;
+use rust_decimal::Decimal;
+
+use crate::models::market::CEXState;
+use crate::store::markets::insert_cex_markets_batch;
+use crate::store::test_db::{test_pool, unique_exchange, with_question_marks};
+
+/// `secs` after the start of an hour, which the buckets of every interval are aligned on
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_699_999_200 + secs, 0).unwrap()
+}
+
+fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+fn cex_state(exchange: &str, secs: i64, bid: &str, ask: &str) -> CEXState {
+    CEXState {
+        trade_id: format!("TRUMPUSDC:{}", secs),
+        exchange: exchange.to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        bid_price: dec(bid),
+        bid_volume: Decimal::ONE,
+        ask_price: dec(ask),
+        ask_volume: Decimal::ONE,
+        trade_time: at(secs),
+        fetch_time: at(secs),
+        feed_latency_ms: None,
+        depth: None,
+    }
+}
+
+fn candle(secs: i64, [open, high, low, close]: [&str; 4], row_count: u64) -> Candle {
+    Candle {
+        open_time: at(secs),
+        open: dec(open),
+        high: dec(high),
+        low: dec(low),
+        close: dec(close),
+        row_count,
+    }
+}
+
+/// Rows of the first minute, then one in the third minute and one in the second hour
+async fn seed(pool: &DatabasePool, exchange: &str) {
+    let states = [
+        cex_state(exchange, 0, "10", "11"),
+        cex_state(exchange, 20, "12", "12"),
+        cex_state(exchange, 40, "9", "10"),
+        cex_state(exchange, 59, "10", "10.5"),
+        cex_state(exchange, 125, "11", "11"),
+        cex_state(exchange, 3700, "20", "20"),
+        // Another pair of the exchange, left out
+        CEXState {
+            trade_id: "SOLUSDC:30".to_string(),
+            trade_pair: "SOLUSDC".to_string(),
+            ..cex_state(exchange, 30, "150", "151")
+        },
+    ];
+    insert_cex_markets_batch(pool, &states).await.unwrap();
+}
+
+#[test]
+fn candle_interval_round_trips_through_its_name() {
+    for interval in [
+        CandleInterval::OneMinute,
+        CandleInterval::FiveMinutes,
+        CandleInterval::OneHour,
+    ] {
+        assert_eq!(CandleInterval::parse(interval.as_str()), Ok(interval));
+    }
+    assert!(CandleInterval::parse("1d").is_err());
+}
+
+#[test]
+fn ohlc_query_buckets_by_the_interval_and_binds_the_pair_and_range() {
+    let query = with_question_marks(&sql(&ohlc_query(CandleInterval::FiveMinutes)));
+
+    assert!(query.contains("/ 300)"));
+    assert!(query.contains(
+        "WHERE exchange = ? AND trade_pair = ? AND fetch_timestamp >= ? AND fetch_timestamp < ?"
+    ));
+    assert!(query.contains("GROUP BY bucket"));
+}
+
+#[tokio::test]
+async fn get_ohlc_aggregates_minute_candles_and_omits_empty_buckets() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("ohlc");
+    seed(&pool, &exchange).await;
+
+    let candles = get_ohlc(
+        &pool,
+        &exchange,
+        "TRUMPUSDC",
+        CandleInterval::OneMinute,
+        at(0),
+        at(7200),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        candles,
+        vec![
+            candle(0, ["10.5", "12", "9.5", "10.25"], 4),
+            candle(120, ["11", "11", "11", "11"], 1),
+            candle(3660, ["20", "20", "20", "20"], 1),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_ohlc_aggregates_five_minute_and_hour_candles() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("ohlc");
+    seed(&pool, &exchange).await;
+
+    for interval in [CandleInterval::FiveMinutes, CandleInterval::OneHour] {
+        let candles = get_ohlc(&pool, &exchange, "TRUMPUSDC", interval, at(0), at(7200))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            candles,
+            vec![
+                candle(0, ["10.5", "12", "9.5", "11"], 5),
+                candle(3600, ["20", "20", "20", "20"], 1),
+            ]
+        );
+    }
+}
+
+#[tokio::test]
+async fn get_ohlc_only_reads_the_requested_range() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("ohlc");
+    seed(&pool, &exchange).await;
+
+    // The first hour without its rows fetched before the 30th second
+    let candles = get_ohlc(
+        &pool,
+        &exchange,
+        "TRUMPUSDC",
+        CandleInterval::OneHour,
+        at(30),
+        at(3600),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(candles, vec![candle(0, ["9.5", "11", "9.5", "11"], 3)]);
+}
@@ -0,0 +1,58 @@
+use crate::models::balance::{CEXBalance, OnchainBalance};
+use crate::store::db::{DatabasePool, insert_returning_id, returning_id, sql};
+
+/// Insert a CEX balance record
+pub async fn insert_cex_balance(
+    pool: &DatabasePool,
+    balance: &CEXBalance,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+            INSERT INTO cex_balances (exchange, coin, free, locked, update_timestamp, fetch_timestamp)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&balance.exchange)
+        .bind(&balance.coin)
+        .bind(balance.free)
+        .bind(balance.locked)
+        .bind(balance.update_time)
+        .bind(balance.fetch_time);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
+
+/// Insert a snapshot of on-chain balances in one multi-row statement
+pub async fn insert_onchain_balances(
+    pool: &DatabasePool,
+    balances: &[OnchainBalance],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    if balances.is_empty() {
+        return Ok(0);
+    }
+    let rows = vec!["(?, ?, ?, ?, ?, ?)"; balances.len()].join(", ");
+    let query = format!(
+        r#"
+        INSERT INTO onchain_balances (wallet, mint, symbol, amount, slot, fetch_timestamp)
+        VALUES {}
+    "#,
+        rows
+    );
+    let query = sql(&query);
+
+    let mut insert = sqlx::query(&query);
+    for balance in balances {
+        insert = insert
+            .bind(&balance.wallet)
+            .bind(&balance.mint)
+            .bind(&balance.symbol)
+            .bind(balance.amount)
+            .bind(balance.slot as i64) // Convert u64 to i64 for BIGINT
+            .bind(balance.fetch_time);
+    }
+    let result = insert.execute(pool).await?;
+
+    Ok(result.rows_affected())
+}
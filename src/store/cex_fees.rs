@@ -0,0 +1,24 @@
+use crate::models::cex_fee::CEXFee;
+use crate::store::db::{DatabasePool, insert_returning_id, returning_id};
+
+/// Insert a CEX fee rate snapshot
+pub async fn insert_cex_fee(
+    pool: &DatabasePool,
+    fee: &CEXFee,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+            INSERT INTO cex_fees (exchange, trade_pair, maker_fee_bps, taker_fee_bps, fetch_timestamp)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&fee.exchange)
+        .bind(&fee.trade_pair)
+        .bind(fee.maker_fee_bps)
+        .bind(fee.taker_fee_bps)
+        .bind(fee.fetch_time);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
@@ -1,7 +1,42 @@
-use sqlx::{MySql, MySqlPool, Pool};
+//! Database connection and schema management.
+//!
+//! The store runs on MySQL, or on PostgreSQL when built with the `postgres` feature. Queries
+//! are written once in MySQL syntax and go through [`sql`], which rewrites their placeholders
+//! and quoted identifiers for Postgres; upserts are built by [`on_conflict_update`]. The few
+//! statements whose semantics differ between the two are written for each backend.
+
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::pool::PoolOptions;
+use sqlx::{Database, Executor, Pool, Row, ValueRef};
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::env;
+use std::time::Duration;
 use tracing::{error, info, warn};
 
+/// Database backend the store is built for
+#[cfg(not(feature = "postgres"))]
+pub type Db = sqlx::MySql;
+/// Database backend the store is built for
+#[cfg(feature = "postgres")]
+pub type Db = sqlx::Postgres;
+
+#[cfg(not(feature = "postgres"))]
+type DbConnectOptions = sqlx::mysql::MySqlConnectOptions;
+#[cfg(feature = "postgres")]
+type DbConnectOptions = sqlx::postgres::PgConnectOptions;
+
+/// Row read from the backend
+pub type DbRow = <Db as Database>::Row;
+/// Connection of the backend, which a transaction derefs to
+pub type DbConnection = <Db as Database>::Connection;
+
+/// URL scheme, default port and default user of the backend
+#[cfg(not(feature = "postgres"))]
+const BACKEND: (&str, u16, &str) = ("mysql", 3306, "root");
+#[cfg(feature = "postgres")]
+const BACKEND: (&str, u16, &str) = ("postgres", 5432, "postgres");
+
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
     pub host: String,
@@ -9,42 +44,293 @@ pub struct DatabaseConfig {
     pub username: String,
     pub password: String,
     pub database: String,
+    /// Connections the pool opens at most; writers wait for one beyond that
+    pub max_connections: u32,
+    /// Connections the pool keeps open even when idle
+    pub min_connections: u32,
+    /// How long a query waits for a free connection before failing
+    pub acquire_timeout_secs: u64,
+    /// Idle time after which a connection above `min_connections` is closed, 0 to keep it
+    pub idle_timeout_secs: u64,
+    /// Age after which a connection is closed and replaced, 0 to keep it
+    pub max_lifetime_secs: u64,
 }
 
 impl DatabaseConfig {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            host: env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string()),
-            port: env::var("DB_PORT")
-                .unwrap_or_else(|_| "3306".to_string())
-                .parse()
-                .map_err(|_| "Invalid DB_PORT")?,
-            username: env::var("DB_USER").unwrap_or_else(|_| "root".to_string()),
-            password: env::var("DB_PASSWORD").unwrap_or_else(|_| "".to_string()),
-            database: env::var("DB_NAME").unwrap_or_else(|_| "zero".to_string()),
-        })
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Load configuration from the variables `var` returns, falling back to the defaults of
+    /// the unset ones
+    pub fn from_vars(
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = Self {
+            host: var("DB_HOST").unwrap_or_else(|| "localhost".to_string()),
+            port: parse_var(&var, "DB_PORT", BACKEND.1)?,
+            username: var("DB_USER").unwrap_or_else(|| BACKEND.2.to_string()),
+            password: var("DB_PASSWORD").unwrap_or_default(),
+            database: var("DB_NAME").unwrap_or_else(|| "zero".to_string()),
+            max_connections: parse_var(&var, "DB_MAX_CONNECTIONS", 20)?,
+            min_connections: parse_var(&var, "DB_MIN_CONNECTIONS", 0)?,
+            acquire_timeout_secs: parse_var(&var, "DB_ACQUIRE_TIMEOUT_SECS", 30)?,
+            idle_timeout_secs: parse_var(&var, "DB_IDLE_TIMEOUT_SECS", 600)?,
+            max_lifetime_secs: parse_var(&var, "DB_MAX_LIFETIME_SECS", 1800)?,
+        };
+        if config.max_connections == 0 {
+            return Err("DB_MAX_CONNECTIONS must be positive".into());
+        }
+        if config.min_connections > config.max_connections {
+            return Err(format!(
+                "DB_MIN_CONNECTIONS ({}) exceeds DB_MAX_CONNECTIONS ({})",
+                config.min_connections, config.max_connections
+            )
+            .into());
+        }
+        if config.acquire_timeout_secs == 0 {
+            return Err("DB_ACQUIRE_TIMEOUT_SECS must be positive".into());
+        }
+        Ok(config)
+    }
+
+    /// Pool options carrying the connection limits and timeouts of this configuration
+    pub fn pool_options(&self) -> PoolOptions<Db> {
+        let seconds = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        PoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+            .idle_timeout(seconds(self.idle_timeout_secs))
+            .max_lifetime(seconds(self.max_lifetime_secs))
     }
 
     /// Build database URL for sqlx
     pub fn database_url(&self) -> String {
         format!(
-            "mysql://{}:{}@{}:{}/{}",
-            self.username, self.password, self.host, self.port, self.database
+            "{}://{}:{}@{}:{}/{}",
+            BACKEND.0, self.username, self.password, self.host, self.port, self.database
         )
     }
 
-    /// Build database URL without specific database (for database creation)
+    /// Build database URL without specific database (for database creation). Postgres
+    /// always connects to a database, its `postgres` maintenance one there.
     pub fn server_url(&self) -> String {
+        let url = format!(
+            "{}://{}:{}@{}:{}",
+            BACKEND.0, self.username, self.password, self.host, self.port
+        );
+        if cfg!(feature = "postgres") {
+            format!("{}/postgres", url)
+        } else {
+            url
+        }
+    }
+}
+
+/// Value of the variable `name`, or `default` when unset
+fn parse_var<T: std::str::FromStr>(
+    var: impl Fn(&str) -> Option<String>,
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    match var(name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid {}: {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+/// Database connection pool type alias
+pub type DatabasePool = Pool<Db>;
+
+/// Schema migrations embedded from `migrations/mysql/`, applied in version order
+#[cfg(not(feature = "postgres"))]
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/mysql");
+/// Schema migrations embedded from `migrations/postgres/`, applied in version order
+#[cfg(feature = "postgres")]
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Pool that only connects when first used, for code paths that never write. Failing fast
+/// when used anyway, so that flushing a write to it does not stall.
+pub fn lazy_pool() -> DatabasePool {
+    PoolOptions::<Db>::new()
+        .acquire_timeout(Duration::from_millis(50))
+        .connect_lazy_with(DbConnectOptions::new())
+}
+
+/// `query`, written with MySQL `?` placeholders and backquoted identifiers, in the syntax of
+/// the backend
+pub fn sql(query: &str) -> Cow<'_, str> {
+    #[cfg(feature = "postgres")]
+    {
+        Cow::Owned(to_postgres(query))
+    }
+    #[cfg(not(feature = "postgres"))]
+    {
+        Cow::Borrowed(query)
+    }
+}
+
+/// Number the `?` placeholders of `query` as `$1`, `$2`... and turn its backquoted
+/// identifiers into double-quoted ones, leaving string literals untouched
+#[cfg(any(feature = "postgres", test))]
+fn to_postgres(query: &str) -> String {
+    let mut converted = String::with_capacity(query.len() + 16);
+    let mut placeholders = 0;
+    let mut in_literal = false;
+    for c in query.chars() {
+        match c {
+            '\'' => {
+                in_literal = !in_literal;
+                converted.push(c);
+            }
+            '?' if !in_literal => {
+                placeholders += 1;
+                converted.push_str(&format!("${}", placeholders));
+            }
+            '`' if !in_literal => converted.push('"'),
+            _ => converted.push(c),
+        }
+    }
+    converted
+}
+
+/// Clause making an `INSERT` update the row conflicting on the unique key `conflict`, setting
+/// `columns` to the inserted values. A row updated this way counts as 2 affected rows on
+/// MySQL and 1 on Postgres.
+pub fn on_conflict_update(conflict: &[&str], columns: &[&str]) -> String {
+    #[cfg(not(feature = "postgres"))]
+    {
+        let _ = conflict;
+        let assignments: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{0} = VALUES({0})", column))
+            .collect();
+        format!(" ON DUPLICATE KEY UPDATE {}", assignments.join(", "))
+    }
+    #[cfg(feature = "postgres")]
+    {
+        let assignments: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{0} = EXCLUDED.{0}", column))
+            .collect();
         format!(
-            "mysql://{}:{}@{}:{}",
-            self.username, self.password, self.host, self.port
+            " ON CONFLICT ({}) DO UPDATE SET {}",
+            conflict.join(", "),
+            assignments.join(", ")
         )
     }
 }
 
-/// Database connection pool type alias
-pub type DatabasePool = Pool<MySql>;
+/// `INSERT` of one row, written like [`sql`] queries, made to report the id of the row it
+/// writes to [`insert_returning_id`]
+pub fn returning_id(query: &str) -> String {
+    let query = sql(query.trim_end());
+    if cfg!(feature = "postgres") {
+        format!("{} RETURNING id", query)
+    } else {
+        query.into_owned()
+    }
+}
+
+/// Execute a [`returning_id`] query on a pool or a connection and return the id of the row
+/// it inserted
+pub async fn insert_returning_id<'q, 'e, E>(
+    executor: E,
+    query: sqlx::query::Query<'q, Db, <Db as Database>::Arguments<'q>>,
+) -> Result<u64, sqlx::Error>
+where
+    'q: 'e,
+    E: 'e + Executor<'e, Database = Db>,
+{
+    #[cfg(not(feature = "postgres"))]
+    {
+        Ok(query.execute(executor).await?.last_insert_id())
+    }
+    #[cfg(feature = "postgres")]
+    {
+        Ok(query.fetch_one(executor).await?.get::<i64, _>("id") as u64)
+    }
+}
+
+/// Unsigned integer of `column`: MySQL decodes unsigned columns only as unsigned integers,
+/// Postgres, without unsigned types, stores them as BIGINT
+pub fn get_unsigned(row: &DbRow, column: &str) -> u64 {
+    #[cfg(not(feature = "postgres"))]
+    {
+        row.get::<u64, _>(column)
+    }
+    #[cfg(feature = "postgres")]
+    {
+        row.get::<i64, _>(column) as u64
+    }
+}
+
+/// [`get_unsigned`] of a nullable column
+pub fn get_optional_unsigned(row: &DbRow, column: &str) -> Option<u64> {
+    #[cfg(not(feature = "postgres"))]
+    {
+        row.get::<Option<u64>, _>(column)
+    }
+    #[cfg(feature = "postgres")]
+    {
+        row.get::<Option<i64>, _>(column).map(|value| value as u64)
+    }
+}
+
+/// Unsigned integer column read into a `u64` or `Option<u64>` field of a store row with
+/// `#[sqlx(try_from = "Unsigned")]`, the [`get_unsigned`] of a derived `FromRow`. Decodes
+/// signed and unsigned MySQL columns alike, NULL as `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unsigned(Option<u64>);
+
+impl sqlx::Type<Db> for Unsigned {
+    fn type_info() -> <Db as Database>::TypeInfo {
+        <i64 as sqlx::Type<Db>>::type_info()
+    }
+
+    fn compatible(ty: &<Db as Database>::TypeInfo) -> bool {
+        #[cfg(not(feature = "postgres"))]
+        if <u64 as sqlx::Type<Db>>::compatible(ty) {
+            return true;
+        }
+        <i64 as sqlx::Type<Db>>::compatible(ty)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, Db> for Unsigned {
+    fn decode(value: <Db as Database>::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        if value.is_null() {
+            return Ok(Self(None));
+        }
+        #[cfg(not(feature = "postgres"))]
+        if <u64 as sqlx::Type<Db>>::compatible(&value.type_info()) {
+            return Ok(Self(Some(<u64 as sqlx::Decode<Db>>::decode(value)?)));
+        }
+        let value = <i64 as sqlx::Decode<Db>>::decode(value)?;
+        Ok(Self(Some(u64::try_from(value)?)))
+    }
+}
+
+impl TryFrom<Unsigned> for u64 {
+    type Error = String;
+
+    fn try_from(value: Unsigned) -> Result<Self, Self::Error> {
+        value
+            .0
+            .ok_or_else(|| "Unexpected NULL in a non-nullable column".to_string())
+    }
+}
+
+impl From<Unsigned> for Option<u64> {
+    fn from(value: Unsigned) -> Self {
+        value.0
+    }
+}
 
 /// Initialize database connection and setup
 pub async fn init_database() -> Result<DatabasePool, Box<dyn std::error::Error>> {
@@ -58,10 +344,10 @@ pub async fn init_database() -> Result<DatabasePool, Box<dyn std::error::Error>>
         config.username, config.host, config.port, config.database
     );
 
-    let server_pool = MySqlPool::connect(&config.server_url())
+    let server_pool = DatabasePool::connect(&config.server_url())
         .await
         .map_err(|e| {
-            error!("Failed to connect to MySQL server: {}", e);
+            error!("Failed to connect to {} server: {}", BACKEND.0, e);
             e
         })?;
 
@@ -78,13 +364,23 @@ pub async fn init_database() -> Result<DatabasePool, Box<dyn std::error::Error>>
     }
     server_pool.close().await;
 
-    let pool = MySqlPool::connect(&config.database_url())
+    info!(
+        "Database pool: {}-{} connections, acquire timeout {}s, idle timeout {}s, max lifetime {}s",
+        config.min_connections,
+        config.max_connections,
+        config.acquire_timeout_secs,
+        config.idle_timeout_secs,
+        config.max_lifetime_secs
+    );
+    let pool = config
+        .pool_options()
+        .connect(&config.database_url())
         .await
         .map_err(|e| {
             error!("Failed to connect to database '{}': {}", config.database, e);
             e
         })?;
-    run_init_script(&pool).await?;
+    run_migrations(&pool).await?;
 
     // Perform final health check
     match health_check(&pool).await {
@@ -108,7 +404,10 @@ async fn database_exists(
     pool: &DatabasePool,
     database_name: &str,
 ) -> Result<bool, Box<dyn std::error::Error>> {
+    #[cfg(not(feature = "postgres"))]
     let query = "SELECT SCHEMA_NAME FROM INFORMATION_SCHEMA.SCHEMATA WHERE SCHEMA_NAME = ?";
+    #[cfg(feature = "postgres")]
+    let query = "SELECT datname FROM pg_database WHERE datname = $1";
     let exists: Option<String> = sqlx::query_scalar(query)
         .bind(database_name)
         .fetch_optional(pool)
@@ -122,44 +421,63 @@ async fn create_database(
     pool: &DatabasePool,
     database_name: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Postgres has no IF NOT EXISTS here, `database_exists` was checked first
+    #[cfg(not(feature = "postgres"))]
     let query = format!(
         "CREATE DATABASE IF NOT EXISTS `{}` CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
         database_name
     );
+    #[cfg(feature = "postgres")]
+    let query = format!("CREATE DATABASE \"{}\" ENCODING 'UTF8'", database_name);
     sqlx::query(&query).execute(pool).await?;
 
     Ok(())
 }
 
-/// Run the initialization SQL script
-async fn run_init_script(pool: &DatabasePool) -> Result<(), Box<dyn std::error::Error>> {
-    let init_sql = include_str!("../store/init.sql");
-
-    // Split the SQL script by semicolons and execute each statement
-    let statements: Vec<&str> = init_sql
-        .split(';')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    for statement in statements {
-        if !statement.is_empty() {
-            sqlx::query(statement).execute(pool).await.map_err(|e| {
-                error!("Failed to execute SQL statement: {}", e);
-                error!("Statement: {}", statement);
-                e
-            })?;
+/// Apply the migrations not yet recorded in `_sqlx_migrations` and return their versions.
+/// Running it again is a no-op; a migration that failed midway is left dirty and must be
+/// fixed by hand on MySQL, which cannot roll back schema changes. Postgres runs each one in
+/// a transaction.
+pub async fn run_migrations(pool: &DatabasePool) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    let applied: HashSet<i64> = {
+        let mut conn = pool.acquire().await?;
+        conn.ensure_migrations_table().await?;
+        conn.list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect()
+    };
+
+    MIGRATOR.run(pool).await.map_err(|e| {
+        error!("Failed to run database migrations: {}", e);
+        e
+    })?;
+
+    let mut newly_applied = Vec::new();
+    for migration in MIGRATOR.iter() {
+        if migration.migration_type.is_down_migration() || applied.contains(&migration.version) {
+            continue;
         }
+        info!(
+            "Applied migration {} ({})",
+            migration.version, migration.description
+        );
+        newly_applied.push(migration.version);
     }
-
-    info!("Initialization SQL script executed successfully");
-    Ok(())
+    if newly_applied.is_empty() {
+        info!(
+            "Database schema up to date, {} migrations applied",
+            applied.len()
+        );
+    }
+    Ok(newly_applied)
 }
 
 /// Get a database connection from the pool
 pub async fn get_connection(
     pool: &DatabasePool,
-) -> Result<sqlx::pool::PoolConnection<MySql>, sqlx::Error> {
+) -> Result<sqlx::pool::PoolConnection<Db>, sqlx::Error> {
     pool.acquire().await
 }
 
@@ -175,3 +493,7 @@ pub async fn health_check(pool: &DatabasePool) -> Result<bool, sqlx::Error> {
         }
     }
 }
+
+#[cfg(test)]
+#[path = "db_tests.rs"]
+mod db_tests;
@@ -0,0 +1,274 @@
+use super::*;
+use crate::models::market::CEXState;
+use crate::store::markets::{get_all_cex_markets, insert_cex_market};
+use crate::store::test_db::{test_pool, unique_exchange};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Variables of `pairs`, as `DatabaseConfig::from_vars` reads them
+fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn database_config_defaults_unset_variables() {
+    let config = DatabaseConfig::from_vars(vars(&[])).unwrap();
+
+    #[cfg(not(feature = "postgres"))]
+    assert_eq!(config.database_url(), "mysql://root:@localhost:3306/zero");
+    #[cfg(feature = "postgres")]
+    assert_eq!(
+        config.database_url(),
+        "postgres://postgres:@localhost:5432/zero"
+    );
+    assert_eq!(config.max_connections, 20);
+    assert_eq!(config.min_connections, 0);
+    assert_eq!(config.acquire_timeout_secs, 30);
+    assert_eq!(config.idle_timeout_secs, 600);
+    assert_eq!(config.max_lifetime_secs, 1800);
+}
+
+#[test]
+fn database_config_reads_pool_settings() {
+    let config = DatabaseConfig::from_vars(vars(&[
+        ("DB_HOST", "db.internal"),
+        ("DB_PORT", "3307"),
+        ("DB_MAX_CONNECTIONS", "50"),
+        ("DB_MIN_CONNECTIONS", " 5 "),
+        ("DB_ACQUIRE_TIMEOUT_SECS", "3"),
+        ("DB_IDLE_TIMEOUT_SECS", "0"),
+        ("DB_MAX_LIFETIME_SECS", "3600"),
+    ]))
+    .unwrap();
+
+    #[cfg(not(feature = "postgres"))]
+    assert_eq!(config.server_url(), "mysql://root:@db.internal:3307");
+    #[cfg(feature = "postgres")]
+    assert_eq!(
+        config.server_url(),
+        "postgres://postgres:@db.internal:3307/postgres"
+    );
+    assert_eq!(config.max_connections, 50);
+    assert_eq!(config.min_connections, 5);
+    assert_eq!(config.acquire_timeout_secs, 3);
+    assert_eq!(config.idle_timeout_secs, 0);
+    assert_eq!(config.max_lifetime_secs, 3600);
+}
+
+#[test]
+fn database_config_rejects_invalid_pool_settings() {
+    let error = |pairs: &[(&str, &str)]| {
+        DatabaseConfig::from_vars(vars(pairs))
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert_eq!(
+        error(&[("DB_MAX_CONNECTIONS", "many")]),
+        "Invalid DB_MAX_CONNECTIONS: many"
+    );
+    assert_eq!(
+        error(&[("DB_ACQUIRE_TIMEOUT_SECS", "-1")]),
+        "Invalid DB_ACQUIRE_TIMEOUT_SECS: -1"
+    );
+    assert_eq!(error(&[("DB_PORT", "")]), "Invalid DB_PORT: ");
+    assert_eq!(
+        error(&[("DB_MAX_CONNECTIONS", "0")]),
+        "DB_MAX_CONNECTIONS must be positive"
+    );
+    assert_eq!(
+        error(&[("DB_MAX_CONNECTIONS", "4"), ("DB_MIN_CONNECTIONS", "5")]),
+        "DB_MIN_CONNECTIONS (5) exceeds DB_MAX_CONNECTIONS (4)"
+    );
+    assert_eq!(
+        error(&[("DB_ACQUIRE_TIMEOUT_SECS", "0")]),
+        "DB_ACQUIRE_TIMEOUT_SECS must be positive"
+    );
+}
+
+#[test]
+fn pool_options_apply_the_settings() {
+    let config = DatabaseConfig::from_vars(vars(&[
+        ("DB_MAX_CONNECTIONS", "50"),
+        ("DB_MIN_CONNECTIONS", "5"),
+        ("DB_ACQUIRE_TIMEOUT_SECS", "3"),
+        ("DB_IDLE_TIMEOUT_SECS", "0"),
+    ]))
+    .unwrap();
+
+    let options = config.pool_options();
+
+    assert_eq!(options.get_max_connections(), 50);
+    assert_eq!(options.get_min_connections(), 5);
+    assert_eq!(options.get_acquire_timeout(), Duration::from_secs(3));
+    assert_eq!(options.get_idle_timeout(), None);
+    assert_eq!(options.get_max_lifetime(), Some(Duration::from_secs(1800)));
+}
+
+#[test]
+fn migrations_start_with_the_idempotent_initial_schema() {
+    let initial = MIGRATOR.iter().next().unwrap();
+
+    assert_eq!(initial.version, 20261016000000);
+    assert_eq!(initial.description, "initial schema");
+    // Databases created by the first init.sql apply it as their baseline, so it holds exactly
+    // its two tables and everything added since is in later migrations
+    let sql = initial.sql.to_uppercase();
+    assert_eq!(sql.matches("CREATE TABLE IF NOT EXISTS ").count(), 2);
+    assert_eq!(sql.matches("CREATE TABLE ").count(), 2);
+    assert!(!sql.contains("INSERT"));
+}
+
+#[test]
+fn every_migration_has_a_postgres_counterpart() {
+    let versions = |backend: &str| {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("migrations")
+            .join(backend);
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        files
+    };
+
+    assert_eq!(versions("mysql"), versions("postgres"));
+}
+
+#[test]
+fn to_postgres_numbers_placeholders_and_quotes_identifiers() {
+    assert_eq!(
+        to_postgres("SELECT `precision` FROM trade_pairs WHERE venue = ? AND symbol = ?"),
+        r#"SELECT "precision" FROM trade_pairs WHERE venue = $1 AND symbol = $2"#
+    );
+    // Literals are left as written
+    assert_eq!(
+        to_postgres("SELECT id FROM orders WHERE status IN ('?', '`') AND id = ?"),
+        "SELECT id FROM orders WHERE status IN ('?', '`') AND id = $1"
+    );
+}
+
+#[test]
+fn on_conflict_update_sets_the_inserted_values() {
+    let clause = on_conflict_update(&["trade_id", "exchange"], &["price", "volume"]);
+
+    #[cfg(not(feature = "postgres"))]
+    assert_eq!(
+        clause,
+        " ON DUPLICATE KEY UPDATE price = VALUES(price), volume = VALUES(volume)"
+    );
+    #[cfg(feature = "postgres")]
+    assert_eq!(
+        clause,
+        " ON CONFLICT (trade_id, exchange) DO UPDATE SET price = EXCLUDED.price, volume = EXCLUDED.volume"
+    );
+}
+
+#[test]
+fn returning_id_reports_the_inserted_row_on_postgres() {
+    let query = returning_id("INSERT INTO cex_fees (exchange) VALUES (?)\n    ");
+
+    #[cfg(not(feature = "postgres"))]
+    assert_eq!(query, "INSERT INTO cex_fees (exchange) VALUES (?)");
+    #[cfg(feature = "postgres")]
+    assert_eq!(
+        query,
+        "INSERT INTO cex_fees (exchange) VALUES ($1) RETURNING id"
+    );
+}
+
+#[test]
+fn migration_versions_are_unique_and_ordered() {
+    let versions: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+
+    assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[tokio::test]
+async fn run_migrations_twice_applies_nothing_the_second_time() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+
+    assert_eq!(run_migrations(&pool).await.unwrap(), Vec::<i64>::new());
+    assert_eq!(run_migrations(&pool).await.unwrap(), Vec::<i64>::new());
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    let expected: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+    assert_eq!(applied, expected);
+}
+
+#[tokio::test]
+async fn run_migrations_upgrades_a_database_created_by_the_first_init_sql() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    // A database of its own holding the tables of the first init.sql, which the initial
+    // migration repeats statement for statement, with a row written before migrations existed
+    let name = unique_exchange("zero_baseline");
+    create_database(&pool, &name).await.unwrap();
+    let options: DbConnectOptions = std::env::var("TEST_DATABASE_URL").unwrap().parse().unwrap();
+    let baseline = DatabasePool::connect_with(options.database(&name))
+        .await
+        .unwrap();
+    let initial = MIGRATOR.iter().next().unwrap();
+    sqlx::raw_sql(&*initial.sql)
+        .execute(&baseline)
+        .await
+        .unwrap();
+    sqlx::query(&sql(
+        "INSERT INTO cex_markets (trade_id, exchange, trade_pair, bid_price, bid_volume, ask_price, ask_volume, trade_timestamp, fetch_timestamp) VALUES ('before', 'bybit', 'TRUMPUSDC', 10, 1, 11, 1, ?, ?)",
+    ))
+    .bind(Utc::now())
+    .bind(Utc::now())
+    .execute(&baseline)
+    .await
+    .unwrap();
+
+    let expected: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+    assert_eq!(run_migrations(&baseline).await.unwrap(), expected);
+    assert_eq!(run_migrations(&baseline).await.unwrap(), Vec::<i64>::new());
+
+    // The columns added since are there, NULL on the row written before
+    insert_cex_market(
+        &baseline,
+        &CEXState {
+            trade_id: "after".to_string(),
+            exchange: "bybit".to_string(),
+            trade_pair: "TRUMPUSDC".to_string(),
+            bid_price: Decimal::TEN,
+            bid_volume: Decimal::ONE,
+            ask_price: Decimal::from(11),
+            ask_volume: Decimal::ONE,
+            trade_time: Utc::now(),
+            fetch_time: Utc::now(),
+            feed_latency_ms: Some(12),
+            depth: None,
+        },
+    )
+    .await
+    .unwrap();
+    let mut rows = get_all_cex_markets(&baseline).await.unwrap();
+    rows.sort_by(|a, b| a.trade_id.cmp(&b.trade_id));
+    let latencies: Vec<(&str, Option<u64>)> = rows
+        .iter()
+        .map(|row| (row.trade_id.as_str(), row.feed_latency_ms))
+        .collect();
+    assert_eq!(latencies, [("after", Some(12)), ("before", None)]);
+
+    baseline.close().await;
+    #[cfg(not(feature = "postgres"))]
+    let drop = format!("DROP DATABASE `{}`", name);
+    #[cfg(feature = "postgres")]
+    let drop = format!("DROP DATABASE \"{}\"", name);
+    sqlx::query(&drop).execute(&pool).await.unwrap();
+}
@@ -1,25 +1,66 @@
-use sqlx::{MySql, Pool, Row};
+use sqlx::{FromRow, QueryBuilder, Row};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use tracing::warn;
 
-use crate::models::market::{CEXState, DEXState};
+use rust_decimal::Decimal;
+
+use chrono::{DateTime, Utc};
+
+use crate::models::market::{
+    CEXDepth, CEXKline, CEXOrderBookSnapshot, CEXState, CEXTicker, DEXState,
+};
+use crate::store::db::{
+    DatabasePool, Db, DbConnection, DbRow, Unsigned, insert_returning_id, on_conflict_update,
+    returning_id, sql,
+};
+use crate::store::pagination::{MarketCursor, MarketPage, SortOrder, into_page, push_page};
+use crate::store::write_metrics::timed_write;
+
+/// Unique key of `cex_markets` and `dex_markets`, which upserts update the rows of
+const MARKET_KEY: &[&str] = &["trade_id", "exchange"];
+
+/// Columns of `cex_markets` an upsert of an existing state updates
+const CEX_MARKET_UPDATED_COLUMNS: &[&str] = &[
+    "bid_price",
+    "bid_volume",
+    "ask_price",
+    "ask_volume",
+    "fetch_timestamp",
+    "feed_latency_ms",
+    "bid_depth_5bps",
+    "ask_depth_5bps",
+    "bid_depth_10bps",
+    "ask_depth_10bps",
+    "bid_depth_25bps",
+    "ask_depth_25bps",
+];
 
 /// Insert a new CEX market record
 pub async fn insert_cex_market(
-    pool: &Pool<MySql>,
+    pool: &DatabasePool,
     cex_state: &CEXState,
 ) -> Result<u64, Box<dyn std::error::Error>> {
-    let query = r#"
-        INSERT INTO cex_markets (trade_id, exchange, trade_pair, bid_price, bid_volume, ask_price, ask_volume, trade_timestamp, fetch_timestamp)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-        ON DUPLICATE KEY UPDATE
-            bid_price = VALUES(bid_price),
-            bid_volume = VALUES(bid_volume),
-            ask_price = VALUES(ask_price),
-            ask_volume = VALUES(ask_volume),
-            fetch_timestamp = VALUES(fetch_timestamp)
-    "#;
+    let mut conn = pool.acquire().await?;
+    insert_cex_market_tx(&mut conn, cex_state).await
+}
 
-    let result = sqlx::query(query)
+/// Insert a new CEX market record on `conn`, which may be an open transaction
+pub async fn insert_cex_market_tx(
+    conn: &mut DbConnection,
+    cex_state: &CEXState,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(&format!(
+        r#"
+        INSERT INTO cex_markets (trade_id, exchange, trade_pair, bid_price, bid_volume, ask_price, ask_volume, trade_timestamp, fetch_timestamp, feed_latency_ms, bid_depth_5bps, ask_depth_5bps, bid_depth_10bps, ask_depth_10bps, bid_depth_25bps, ask_depth_25bps)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        {}
+    "#,
+        on_conflict_update(MARKET_KEY, CEX_MARKET_UPDATED_COLUMNS)
+    ));
+
+    let insert = sqlx::query(&query)
         .bind(&cex_state.trade_id)
         .bind(&cex_state.exchange)
         .bind(&cex_state.trade_pair)
@@ -29,41 +70,534 @@ pub async fn insert_cex_market(
         .bind(&cex_state.ask_volume)
         .bind(cex_state.trade_time)
         .bind(cex_state.fetch_time)
+        .bind(cex_state.feed_latency_ms.map(|ms| ms as i64)) // Convert u64 to i64 for BIGINT
+        .bind(cex_state.depth.as_ref().map(|depth| depth.bid_5bps))
+        .bind(cex_state.depth.as_ref().map(|depth| depth.ask_5bps))
+        .bind(cex_state.depth.as_ref().map(|depth| depth.bid_10bps))
+        .bind(cex_state.depth.as_ref().map(|depth| depth.ask_10bps))
+        .bind(cex_state.depth.as_ref().map(|depth| depth.bid_25bps))
+        .bind(cex_state.depth.as_ref().map(|depth| depth.ask_25bps));
+
+    Ok(timed_write("cex_markets", insert_returning_id(conn, insert)).await?)
+}
+
+/// Rows per multi-row insert. A few hundred bytes each keeps a statement far below the 4MB
+/// `max_allowed_packet` of older MySQL servers and its bind parameters below the 65535 limit.
+const MAX_ROWS_PER_INSERT: usize = 1000;
+
+/// Insert or update several CEX market records, one multi-row statement per
+/// [`MAX_ROWS_PER_INSERT`] rows. A state sharing the trade id and exchange of an existing row
+/// updates it, and only the last of the states of a batch sharing them is written. Returns the
+/// affected rows as the backend counts them: 1 per inserted row, per updated one 2 on MySQL
+/// and 1 on Postgres, and 0 on MySQL when an update changed nothing.
+pub async fn insert_cex_markets_batch(
+    pool: &DatabasePool,
+    cex_states: &[CEXState],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let cex_states = last_per_key(cex_states, |cex_state| {
+        (cex_state.trade_id.as_str(), cex_state.exchange.as_str())
+    });
+    let mut rows_affected = 0;
+    for chunk in cex_states.chunks(MAX_ROWS_PER_INSERT) {
+        // The query builder has to outlive the insert, so both stay in one statement
+        let result = timed_write(
+            "cex_markets",
+            cex_markets_insert_query(chunk).build().execute(pool),
+        )
+        .await?;
+        rows_affected += result.rows_affected();
+    }
+
+    Ok(rows_affected)
+}
+
+/// The last state of `states` of every key, in the order of their first occurrence. Postgres
+/// rejects a statement updating the same row twice, which MySQL applies in order.
+fn last_per_key<'a, T: Clone, K: Eq + Hash>(
+    states: &'a [T],
+    key: impl Fn(&'a T) -> K,
+) -> Cow<'a, [T]> {
+    let mut last = HashMap::new();
+    for (index, state) in states.iter().enumerate() {
+        last.insert(key(state), index);
+    }
+    if last.len() == states.len() {
+        return Cow::Borrowed(states);
+    }
+    let mut written = HashSet::new();
+    let unique = states
+        .iter()
+        .filter_map(|state| {
+            let index = last[&key(state)];
+            written.insert(index).then(|| states[index].clone())
+        })
+        .collect();
+    Cow::Owned(unique)
+}
+
+/// Multi-row upsert of `cex_states` into `cex_markets`
+fn cex_markets_insert_query(cex_states: &[CEXState]) -> QueryBuilder<'_, Db> {
+    let mut query = QueryBuilder::new(
+        "INSERT INTO cex_markets (trade_id, exchange, trade_pair, bid_price, bid_volume, ask_price, ask_volume, trade_timestamp, fetch_timestamp, feed_latency_ms, bid_depth_5bps, ask_depth_5bps, bid_depth_10bps, ask_depth_10bps, bid_depth_25bps, ask_depth_25bps) ",
+    );
+    query.push_values(cex_states, |mut row, cex_state| {
+        let depth = cex_state.depth.as_ref();
+        row.push_bind(&cex_state.trade_id)
+            .push_bind(&cex_state.exchange)
+            .push_bind(&cex_state.trade_pair)
+            .push_bind(cex_state.bid_price)
+            .push_bind(cex_state.bid_volume)
+            .push_bind(cex_state.ask_price)
+            .push_bind(cex_state.ask_volume)
+            .push_bind(cex_state.trade_time)
+            .push_bind(cex_state.fetch_time)
+            .push_bind(cex_state.feed_latency_ms.map(|ms| ms as i64))
+            .push_bind(depth.map(|depth| depth.bid_5bps))
+            .push_bind(depth.map(|depth| depth.ask_5bps))
+            .push_bind(depth.map(|depth| depth.bid_10bps))
+            .push_bind(depth.map(|depth| depth.ask_10bps))
+            .push_bind(depth.map(|depth| depth.bid_25bps))
+            .push_bind(depth.map(|depth| depth.ask_25bps));
+    });
+    query.push(on_conflict_update(MARKET_KEY, CEX_MARKET_UPDATED_COLUMNS));
+    query
+}
+
+/// Insert a CEX ticker snapshot
+pub async fn insert_cex_ticker(
+    pool: &DatabasePool,
+    ticker: &CEXTicker,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+        INSERT INTO cex_tickers (exchange, trade_pair, last_price, high_price_24h, low_price_24h, prev_price_24h, volume_24h, turnover_24h, price_change_24h, usd_index_price, trade_timestamp, fetch_timestamp)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&ticker.exchange)
+        .bind(&ticker.trade_pair)
+        .bind(ticker.last_price)
+        .bind(ticker.high_price_24h)
+        .bind(ticker.low_price_24h)
+        .bind(ticker.prev_price_24h)
+        .bind(ticker.volume_24h)
+        .bind(ticker.turnover_24h)
+        .bind(ticker.price_change_24h)
+        .bind(ticker.usd_index_price)
+        .bind(ticker.trade_time)
+        .bind(ticker.fetch_time);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
+
+/// Insert a candle, or update it in place while it is still forming.
+/// A confirmed candle is final: later updates of the same candle are ignored.
+pub async fn upsert_cex_kline(
+    pool: &DatabasePool,
+    kline: &CEXKline,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    // Assignments apply left to right, so `confirmed` is updated last
+    #[cfg(not(feature = "postgres"))]
+    let query = r#"
+        INSERT INTO cex_klines (exchange, trade_pair, `interval`, open_time, `open`, high, low, `close`, volume, confirmed, fetch_timestamp)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE
+            `open` = IF(confirmed, `open`, VALUES(`open`)),
+            high = IF(confirmed, high, VALUES(high)),
+            low = IF(confirmed, low, VALUES(low)),
+            `close` = IF(confirmed, `close`, VALUES(`close`)),
+            volume = IF(confirmed, volume, VALUES(volume)),
+            fetch_timestamp = IF(confirmed, fetch_timestamp, VALUES(fetch_timestamp)),
+            confirmed = confirmed OR VALUES(confirmed)
+    "#;
+    #[cfg(feature = "postgres")]
+    let query = r#"
+        INSERT INTO cex_klines (exchange, trade_pair, "interval", open_time, "open", high, low, "close", volume, confirmed, fetch_timestamp)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (exchange, trade_pair, "interval", open_time) DO UPDATE SET
+            "open" = EXCLUDED."open",
+            high = EXCLUDED.high,
+            low = EXCLUDED.low,
+            "close" = EXCLUDED."close",
+            volume = EXCLUDED.volume,
+            fetch_timestamp = EXCLUDED.fetch_timestamp,
+            confirmed = EXCLUDED.confirmed
+        WHERE NOT cex_klines.confirmed
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(&kline.exchange)
+        .bind(&kline.trade_pair)
+        .bind(&kline.interval)
+        .bind(kline.open_time)
+        .bind(kline.open)
+        .bind(kline.high)
+        .bind(kline.low)
+        .bind(kline.close)
+        .bind(kline.volume)
+        .bind(kline.confirmed)
+        .bind(kline.fetch_time)
         .execute(pool)
         .await?;
 
-    Ok(result.last_insert_id())
+    Ok(result.rows_affected())
 }
 
-/// Get all CEX market records
-pub async fn get_all_cex_markets(
-    pool: &Pool<MySql>,
-) -> Result<Vec<CEXState>, Box<dyn std::error::Error>> {
-    let query = "SELECT id, trade_id, exchange, trade_pair, bid_price, bid_volume, ask_price, ask_volume, trade_timestamp, fetch_timestamp FROM cex_markets ORDER BY fetch_timestamp DESC";
+/// Get the last `limit` candles of a pair, oldest first
+pub async fn get_last_cex_klines(
+    pool: &DatabasePool,
+    exchange: &str,
+    trade_pair: &str,
+    interval: &str,
+    limit: u32,
+) -> Result<Vec<CEXKline>, Box<dyn std::error::Error>> {
+    let query = r#"
+        SELECT exchange, trade_pair, `interval`, open_time, `open`, high, low, `close`, volume, confirmed, fetch_timestamp
+        FROM cex_klines
+        WHERE exchange = ? AND trade_pair = ? AND `interval` = ?
+        ORDER BY open_time DESC
+        LIMIT ?
+    "#;
 
-    let rows = sqlx::query(query).fetch_all(pool).await?;
+    let rows = sqlx::query(&sql(query))
+        .bind(exchange)
+        .bind(trade_pair)
+        .bind(interval)
+        .bind(i64::from(limit))
+        .fetch_all(pool)
+        .await?;
 
-    let mut cex_states = Vec::new();
-    for row in rows {
-        cex_states.push(CEXState {
-            trade_id: row.get("trade_id"),
+    let mut klines = Vec::new();
+    for row in rows.iter().rev() {
+        klines.push(CEXKline {
             exchange: row.get("exchange"),
             trade_pair: row.get("trade_pair"),
-            bid_price: row.get("bid_price"),
-            bid_volume: row.get("bid_volume"),
-            ask_price: row.get("ask_price"),
-            ask_volume: row.get("ask_volume"),
-            trade_time: row.get("trade_timestamp"),
+            interval: row.get("interval"),
+            open_time: row.get("open_time"),
+            open: row.get("open"),
+            high: row.get("high"),
+            low: row.get("low"),
+            close: row.get("close"),
+            volume: row.get("volume"),
+            confirmed: row.get("confirmed"),
             fetch_time: row.get("fetch_timestamp"),
         });
     }
 
+    Ok(klines)
+}
+
+/// Insert a full order book snapshot, its levels serialized as JSON arrays
+pub async fn insert_orderbook_snapshot(
+    pool: &DatabasePool,
+    snapshot: &CEXOrderBookSnapshot,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+        INSERT INTO cex_orderbook_snapshots (exchange, trade_pair, snapshot_time, bids_json, asks_json)
+        VALUES (?, ?, ?, ?, ?)
+    "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&snapshot.exchange)
+        .bind(&snapshot.trade_pair)
+        .bind(snapshot.snapshot_time)
+        .bind(serde_json::to_string(&snapshot.bids)?)
+        .bind(serde_json::to_string(&snapshot.asks)?);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
+
+/// Get the order book snapshot of a pair taken closest to `at`, before or after it
+pub async fn get_nearest_orderbook_snapshot(
+    pool: &DatabasePool,
+    exchange: &str,
+    trade_pair: &str,
+    at: DateTime<Utc>,
+) -> Result<Option<CEXOrderBookSnapshot>, Box<dyn std::error::Error>> {
+    // One indexed lookup per side of `at` instead of ordering every row by distance
+    let before = r#"
+        SELECT exchange, trade_pair, snapshot_time, bids_json, asks_json
+        FROM cex_orderbook_snapshots
+        WHERE exchange = ? AND trade_pair = ? AND snapshot_time <= ?
+        ORDER BY snapshot_time DESC
+        LIMIT 1
+    "#;
+    let after = r#"
+        SELECT exchange, trade_pair, snapshot_time, bids_json, asks_json
+        FROM cex_orderbook_snapshots
+        WHERE exchange = ? AND trade_pair = ? AND snapshot_time >= ?
+        ORDER BY snapshot_time ASC
+        LIMIT 1
+    "#;
+
+    let mut nearest = Vec::new();
+    for query in [before, after] {
+        let row = sqlx::query(&sql(query))
+            .bind(exchange)
+            .bind(trade_pair)
+            .bind(at)
+            .fetch_optional(pool)
+            .await?;
+        if let Some(row) = row {
+            nearest.push(CEXOrderBookSnapshot {
+                exchange: row.get("exchange"),
+                trade_pair: row.get("trade_pair"),
+                snapshot_time: row.get("snapshot_time"),
+                bids: serde_json::from_str(row.get("bids_json"))?,
+                asks: serde_json::from_str(row.get("asks_json"))?,
+            });
+        }
+    }
+
+    Ok(nearest_snapshot(nearest, at))
+}
+
+/// Snapshot taken closest to `at`, the earlier one on a tie
+fn nearest_snapshot(
+    snapshots: Vec<CEXOrderBookSnapshot>,
+    at: DateTime<Utc>,
+) -> Option<CEXOrderBookSnapshot> {
+    snapshots
+        .into_iter()
+        .min_by_key(|snapshot| ((snapshot.snapshot_time - at).abs(), snapshot.snapshot_time))
+}
+
+/// Columns of `cex_markets` read back into a [`CEXState`]
+const CEX_MARKET_COLUMNS: &str = "id, trade_id, exchange, trade_pair, bid_price, bid_volume, ask_price, ask_volume, trade_timestamp, fetch_timestamp, feed_latency_ms, bid_depth_5bps, ask_depth_5bps, bid_depth_10bps, ask_depth_10bps, bid_depth_25bps, ask_depth_25bps";
+
+/// `cex_markets` row read with [`CEX_MARKET_COLUMNS`]
+#[derive(Debug, FromRow)]
+struct CexMarketRow {
+    trade_id: String,
+    exchange: String,
+    trade_pair: String,
+    bid_price: Decimal,
+    bid_volume: Decimal,
+    ask_price: Decimal,
+    ask_volume: Decimal,
+    trade_timestamp: DateTime<Utc>,
+    fetch_timestamp: DateTime<Utc>,
+    #[sqlx(try_from = "Unsigned")]
+    feed_latency_ms: Option<u64>,
+    #[sqlx(flatten)]
+    depth: CexDepthColumns,
+}
+
+/// Depth columns of a `cex_markets` row, NULL in the rows written without a book
+#[derive(Debug, FromRow)]
+struct CexDepthColumns {
+    bid_depth_5bps: Option<Decimal>,
+    ask_depth_5bps: Option<Decimal>,
+    bid_depth_10bps: Option<Decimal>,
+    ask_depth_10bps: Option<Decimal>,
+    bid_depth_25bps: Option<Decimal>,
+    ask_depth_25bps: Option<Decimal>,
+}
+
+impl From<CexMarketRow> for CEXState {
+    fn from(row: CexMarketRow) -> Self {
+        Self {
+            trade_id: row.trade_id,
+            exchange: row.exchange,
+            trade_pair: row.trade_pair,
+            bid_price: row.bid_price,
+            bid_volume: row.bid_volume,
+            ask_price: row.ask_price,
+            ask_volume: row.ask_volume,
+            trade_time: row.trade_timestamp,
+            fetch_time: row.fetch_timestamp,
+            feed_latency_ms: row.feed_latency_ms,
+            depth: row.depth.into(),
+        }
+    }
+}
+
+impl From<CexDepthColumns> for Option<CEXDepth> {
+    /// Depth of the row, `None` unless every column was recorded
+    fn from(columns: CexDepthColumns) -> Self {
+        Some(CEXDepth {
+            bid_5bps: columns.bid_depth_5bps?,
+            ask_5bps: columns.ask_depth_5bps?,
+            bid_10bps: columns.bid_depth_10bps?,
+            ask_10bps: columns.ask_depth_10bps?,
+            bid_25bps: columns.bid_depth_25bps?,
+            ask_25bps: columns.ask_depth_25bps?,
+        })
+    }
+}
+
+/// Restricts the `cex_markets` rows read by [`get_cex_markets`] and
+/// [`get_cex_markets_page`]; unset fields match every row
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CexMarketFilter {
+    pub exchange: Option<String>,
+    pub trade_pair: Option<String>,
+    /// Earliest fetch time, inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// Latest fetch time, exclusive
+    pub to: Option<DateTime<Utc>>,
+    /// Maximum number of rows, the most recent first. Pages are bounded by their page size.
+    pub limit: Option<u32>,
+}
+
+/// Get all CEX market records
+pub async fn get_all_cex_markets(
+    pool: &DatabasePool,
+) -> Result<Vec<CEXState>, Box<dyn std::error::Error>> {
+    get_cex_markets(pool, &CexMarketFilter::default()).await
+}
+
+/// Get the CEX market records matching `filter`, the most recently fetched first
+pub async fn get_cex_markets(
+    pool: &DatabasePool,
+    filter: &CexMarketFilter,
+) -> Result<Vec<CEXState>, Box<dyn std::error::Error>> {
+    let rows = cex_markets_query(filter)
+        .build_query_as::<CexMarketRow>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(CEXState::from).collect())
+}
+
+/// Get one page of the CEX market records matching `filter`, in `order` of fetch time from
+/// `after` (the first page when `None`). Rows fetched at the same instant are ordered by id,
+/// so following the returned cursor reads every row exactly once.
+pub async fn get_cex_markets_page(
+    pool: &DatabasePool,
+    filter: &CexMarketFilter,
+    after: Option<MarketCursor>,
+    order: SortOrder,
+    page_size: u32,
+) -> Result<MarketPage<CEXState>, Box<dyn std::error::Error>> {
+    if page_size == 0 {
+        return Err("Page size must be positive".into());
+    }
+    let mut query = cex_markets_select(filter);
+    push_page(&mut query, after, order, page_size);
+    let rows = query.build().fetch_all(pool).await?;
+
+    Ok(into_page(
+        with_cursors::<CexMarketRow, _>(&rows)?,
+        page_size,
+    ))
+}
+
+/// Get the most recently fetched CEX market record of a pair
+pub async fn get_latest_cex_market(
+    pool: &DatabasePool,
+    exchange: &str,
+    trade_pair: &str,
+) -> Result<Option<CEXState>, Box<dyn std::error::Error>> {
+    let filter = CexMarketFilter {
+        exchange: Some(exchange.to_string()),
+        trade_pair: Some(trade_pair.to_string()),
+        limit: Some(1),
+        ..CexMarketFilter::default()
+    };
+    let row = cex_markets_query(&filter)
+        .build_query_as::<CexMarketRow>()
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(CEXState::from))
+}
+
+/// Get the most recently fetched CEX market record of every (exchange, pair) in one query,
+/// leaving out pairs not fetched since `fetched_since`
+pub async fn get_latest_cex_states(
+    pool: &DatabasePool,
+    fetched_since: Option<DateTime<Utc>>,
+) -> Result<Vec<CEXState>, Box<dyn std::error::Error>> {
+    let rows = latest_cex_states_query(fetched_since)
+        .build_query_as::<CexMarketRow>()
+        .fetch_all(pool)
+        .await?;
+
+    let mut cex_states: Vec<CEXState> = rows.into_iter().map(CEXState::from).collect();
+    // Rows of a pair fetched at the same instant all match its MAX, the newest id comes first
+    cex_states.dedup_by(|next, kept| {
+        next.exchange == kept.exchange && next.trade_pair == kept.trade_pair
+    });
     Ok(cex_states)
 }
 
+/// `SELECT` of the rows fetched last of every pair, joined on the `MAX(fetch_timestamp)` the
+/// `idx_cex_markets_pair_fetch_ts` index answers without scanning the pairs' rows
+fn latest_cex_states_query<'a>(fetched_since: Option<DateTime<Utc>>) -> QueryBuilder<'a, Db> {
+    let mut query = QueryBuilder::new(format!(
+        "SELECT {} FROM cex_markets m JOIN (SELECT exchange, trade_pair, MAX(fetch_timestamp) AS fetch_timestamp FROM cex_markets",
+        qualified_columns(CEX_MARKET_COLUMNS, "m")
+    ));
+    if let Some(fetched_since) = fetched_since {
+        query
+            .push(" WHERE fetch_timestamp >= ")
+            .push_bind(fetched_since);
+    }
+    query.push(
+        " GROUP BY exchange, trade_pair) latest ON m.exchange = latest.exchange AND m.trade_pair = latest.trade_pair AND m.fetch_timestamp = latest.fetch_timestamp ORDER BY m.exchange, m.trade_pair, m.id DESC",
+    );
+    query
+}
+
+/// `SELECT` of the `cex_markets` rows matching `filter`, most recent first
+fn cex_markets_query(filter: &CexMarketFilter) -> QueryBuilder<'_, Db> {
+    let mut query = cex_markets_select(filter);
+    query.push(" ORDER BY fetch_timestamp DESC");
+    if let Some(limit) = filter.limit {
+        query.push(" LIMIT ").push_bind(i64::from(limit));
+    }
+    query
+}
+
+/// `SELECT` of the `cex_markets` rows matching `filter`, every value bound as a parameter
+fn cex_markets_select(filter: &CexMarketFilter) -> QueryBuilder<'_, Db> {
+    let mut query = QueryBuilder::new(format!(
+        "SELECT {} FROM cex_markets WHERE 1 = 1",
+        CEX_MARKET_COLUMNS
+    ));
+    if let Some(exchange) = &filter.exchange {
+        query.push(" AND exchange = ").push_bind(exchange);
+    }
+    if let Some(trade_pair) = &filter.trade_pair {
+        query.push(" AND trade_pair = ").push_bind(trade_pair);
+    }
+    if let Some(from) = filter.from {
+        query.push(" AND fetch_timestamp >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(" AND fetch_timestamp < ").push_bind(to);
+    }
+    query
+}
+
+/// Market states of `rows` read as `R` rows, each paired with the cursor of its row
+fn with_cursors<'r, R, T>(rows: &'r [DbRow]) -> Result<Vec<(T, MarketCursor)>, sqlx::Error>
+where
+    R: FromRow<'r, DbRow>,
+    T: From<R>,
+{
+    rows.iter()
+        .map(|row| Ok((R::from_row(row)?.into(), MarketCursor::from_row(row)?)))
+        .collect()
+}
+
+/// `columns` prefixed with the table `alias`
+fn qualified_columns(columns: &str, alias: &str) -> String {
+    columns
+        .split(", ")
+        .map(|column| format!("{}.{}", alias, column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Update existing CEX market record
 pub async fn update_cex_market(
-    pool: &Pool<MySql>,
+    pool: &DatabasePool,
     cex_state: &CEXState,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let query = r#"
@@ -72,7 +606,7 @@ pub async fn update_cex_market(
         WHERE trade_id = ? AND exchange = ?
     "#;
 
-    let result = sqlx::query(query)
+    let result = sqlx::query(&sql(query))
         .bind(cex_state.bid_price)
         .bind(cex_state.bid_volume)
         .bind(cex_state.ask_price)
@@ -93,89 +627,315 @@ pub async fn update_cex_market(
     Ok(())
 }
 
+/// Columns of `dex_markets` an upsert of an existing state updates
+const DEX_MARKET_UPDATED_COLUMNS: &[&str] = &[
+    "direction",
+    "volume",
+    "price",
+    "trade_timestamp",
+    "fetch_timestamp",
+    "block_number",
+    "price_impact_bps",
+    "pool_address",
+    "stale",
+    "fetch_latency_ms",
+    "route",
+    "route_plan",
+];
+
 /// Insert a new DEX market record
 pub async fn insert_dex_market(
-    pool: &Pool<MySql>,
+    pool: &DatabasePool,
     dex_state: &DEXState,
 ) -> Result<u64, Box<dyn std::error::Error>> {
-    let query = r#"
-        INSERT INTO dex_markets (trade_id, exchange, trade_pair, direction, volume, price, trade_timestamp, fetch_timestamp, block_number)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-        ON DUPLICATE KEY UPDATE
-            direction = VALUES(direction),
-            volume = VALUES(volume),
-            price = VALUES(price),
-            trade_timestamp = VALUES(trade_timestamp),
-            fetch_timestamp = VALUES(fetch_timestamp),
-            block_number = VALUES(block_number)
-    "#;
+    let mut conn = pool.acquire().await?;
+    insert_dex_market_tx(&mut conn, dex_state).await
+}
 
-    let result = sqlx::query(query)
+/// Insert a new DEX market record on `conn`, which may be an open transaction
+pub async fn insert_dex_market_tx(
+    conn: &mut DbConnection,
+    dex_state: &DEXState,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(&format!(
+        r#"
+        INSERT INTO dex_markets (trade_id, exchange, trade_pair, direction, volume, price, trade_timestamp, fetch_timestamp, block_number, price_impact_bps, pool_address, stale, fetch_latency_ms, route, route_plan)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        {}
+    "#,
+        on_conflict_update(MARKET_KEY, DEX_MARKET_UPDATED_COLUMNS)
+    ));
+
+    let insert = sqlx::query(&query)
         .bind(&dex_state.trade_id)
         .bind(&dex_state.exchange)
         .bind(&dex_state.trade_pair)
         .bind(&dex_state.direction)
-        .bind(dex_state.volume) // Convert u64 to i64 for BIGINT
+        .bind(dex_state.volume)
         .bind(dex_state.price)
         .bind(dex_state.trade_time)
         .bind(dex_state.fetch_time)
         .bind(dex_state.block_number as i64) // Convert u64 to i64 for BIGINT
-        .execute(pool)
+        .bind(dex_state.price_impact_bps)
+        .bind(&dex_state.pool_address)
+        .bind(dex_state.stale)
+        .bind(dex_state.fetch_latency_ms.map(|ms| ms as i64)) // Convert u64 to i64 for BIGINT
+        .bind(&dex_state.route)
+        .bind(&dex_state.route_plan);
+
+    Ok(timed_write("dex_markets", insert_returning_id(conn, insert)).await?)
+}
+
+/// Insert or update several DEX market records like [`insert_cex_markets_batch`]
+pub async fn insert_dex_markets_batch(
+    pool: &DatabasePool,
+    dex_states: &[DEXState],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let dex_states = last_per_key(dex_states, |dex_state| {
+        (dex_state.trade_id.as_str(), dex_state.exchange.as_str())
+    });
+    let mut rows_affected = 0;
+    for chunk in dex_states.chunks(MAX_ROWS_PER_INSERT) {
+        let result = timed_write(
+            "dex_markets",
+            dex_markets_insert_query(chunk).build().execute(pool),
+        )
         .await?;
+        rows_affected += result.rows_affected();
+    }
+
+    Ok(rows_affected)
+}
+
+/// Multi-row upsert of `dex_states` into `dex_markets`
+fn dex_markets_insert_query(dex_states: &[DEXState]) -> QueryBuilder<'_, Db> {
+    let mut query = QueryBuilder::new(
+        "INSERT INTO dex_markets (trade_id, exchange, trade_pair, direction, volume, price, trade_timestamp, fetch_timestamp, block_number, price_impact_bps, pool_address, stale, fetch_latency_ms, route, route_plan) ",
+    );
+    query.push_values(dex_states, |mut row, dex_state| {
+        row.push_bind(&dex_state.trade_id)
+            .push_bind(&dex_state.exchange)
+            .push_bind(&dex_state.trade_pair)
+            .push_bind(&dex_state.direction)
+            .push_bind(dex_state.volume)
+            .push_bind(dex_state.price)
+            .push_bind(dex_state.trade_time)
+            .push_bind(dex_state.fetch_time)
+            .push_bind(dex_state.block_number as i64)
+            .push_bind(dex_state.price_impact_bps)
+            .push_bind(&dex_state.pool_address)
+            .push_bind(dex_state.stale)
+            .push_bind(dex_state.fetch_latency_ms.map(|ms| ms as i64))
+            .push_bind(&dex_state.route)
+            .push_bind(&dex_state.route_plan);
+    });
+    query.push(on_conflict_update(MARKET_KEY, DEX_MARKET_UPDATED_COLUMNS));
+    query
+}
+
+/// Columns of `dex_markets` read back into a [`DEXState`]
+const DEX_MARKET_COLUMNS: &str = "id, trade_id, exchange, trade_pair, direction, volume, price, trade_timestamp, fetch_timestamp, block_number, price_impact_bps, pool_address, stale, fetch_latency_ms, route, route_plan";
+
+/// `dex_markets` row read with [`DEX_MARKET_COLUMNS`]
+#[derive(Debug, FromRow)]
+struct DexMarketRow {
+    trade_id: String,
+    exchange: String,
+    trade_pair: String,
+    direction: String,
+    price: Decimal,
+    volume: Decimal,
+    trade_timestamp: DateTime<Utc>,
+    fetch_timestamp: DateTime<Utc>,
+    #[sqlx(try_from = "Unsigned")]
+    block_number: u64,
+    price_impact_bps: Option<Decimal>,
+    pool_address: Option<String>,
+    stale: bool,
+    #[sqlx(try_from = "Unsigned")]
+    fetch_latency_ms: Option<u64>,
+    route: Option<String>,
+    route_plan: Option<String>,
+}
+
+impl From<DexMarketRow> for DEXState {
+    fn from(row: DexMarketRow) -> Self {
+        Self {
+            trade_id: row.trade_id,
+            exchange: row.exchange,
+            trade_pair: row.trade_pair,
+            direction: row.direction,
+            price: row.price,
+            volume: row.volume,
+            trade_time: row.trade_timestamp,
+            fetch_time: row.fetch_timestamp,
+            block_number: row.block_number,
+            price_impact_bps: row.price_impact_bps,
+            pool_address: row.pool_address,
+            stale: row.stale,
+            fetch_latency_ms: row.fetch_latency_ms,
+            route: row.route,
+            route_plan: row.route_plan,
+        }
+    }
+}
 
-    Ok(result.last_insert_id())
+/// Restricts the `dex_markets` rows read by [`get_dex_markets`] and
+/// [`get_dex_markets_page`]; unset fields match every row
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DexMarketFilter {
+    pub exchange: Option<String>,
+    pub trade_pair: Option<String>,
+    /// `sell` or `buy`
+    pub direction: Option<String>,
+    /// Earliest fetch time, inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// Latest fetch time, exclusive
+    pub to: Option<DateTime<Utc>>,
+    /// Maximum number of rows, the most recent first. Pages are bounded by their page size.
+    pub limit: Option<u32>,
 }
 
 /// Get all DEX market records
 pub async fn get_all_dex_markets(
-    pool: &Pool<MySql>,
+    pool: &DatabasePool,
 ) -> Result<Vec<DEXState>, Box<dyn std::error::Error>> {
-    let query = "SELECT id, trade_id, exchange, trade_pair, direction, volume, price, trade_timestamp, fetch_timestamp, block_number FROM dex_markets ORDER BY fetch_timestamp DESC";
+    get_dex_markets(pool, &DexMarketFilter::default()).await
+}
 
-    let rows = sqlx::query(query).fetch_all(pool).await?;
+/// Get the DEX market records matching `filter`, the most recently fetched first
+pub async fn get_dex_markets(
+    pool: &DatabasePool,
+    filter: &DexMarketFilter,
+) -> Result<Vec<DEXState>, Box<dyn std::error::Error>> {
+    let mut query = dex_markets_select(filter);
+    query.push(" ORDER BY fetch_timestamp DESC");
+    if let Some(limit) = filter.limit {
+        query.push(" LIMIT ").push_bind(i64::from(limit));
+    }
+    let rows = query
+        .build_query_as::<DexMarketRow>()
+        .fetch_all(pool)
+        .await?;
 
-    let mut dex_states = Vec::new();
-    for row in rows {
-        dex_states.push(DEXState {
-            trade_id: row.get("trade_id"),
-            exchange: row.get("exchange"),
-            trade_pair: row.get("trade_pair"),
-            direction: row.get("direction"),
-            volume: row.get("volume"),
-            price: row.get("price"),
-            trade_time: row.get("trade_timestamp"),
-            fetch_time: row.get("fetch_timestamp"),
-            block_number: row.get::<i64, _>("block_number") as u64, // Convert i64 to u64
-        });
+    Ok(rows.into_iter().map(DEXState::from).collect())
+}
+
+/// Get one page of the DEX market records matching `filter`, like [`get_cex_markets_page`]
+pub async fn get_dex_markets_page(
+    pool: &DatabasePool,
+    filter: &DexMarketFilter,
+    after: Option<MarketCursor>,
+    order: SortOrder,
+    page_size: u32,
+) -> Result<MarketPage<DEXState>, Box<dyn std::error::Error>> {
+    if page_size == 0 {
+        return Err("Page size must be positive".into());
     }
+    let mut query = dex_markets_select(filter);
+    push_page(&mut query, after, order, page_size);
+    let rows = query.build().fetch_all(pool).await?;
+
+    Ok(into_page(
+        with_cursors::<DexMarketRow, _>(&rows)?,
+        page_size,
+    ))
+}
 
+/// `SELECT` of the `dex_markets` rows matching `filter`, every value bound as a parameter
+fn dex_markets_select(filter: &DexMarketFilter) -> QueryBuilder<'_, Db> {
+    let mut query = QueryBuilder::new(format!(
+        "SELECT {} FROM dex_markets WHERE 1 = 1",
+        DEX_MARKET_COLUMNS
+    ));
+    if let Some(exchange) = &filter.exchange {
+        query.push(" AND exchange = ").push_bind(exchange);
+    }
+    if let Some(trade_pair) = &filter.trade_pair {
+        query.push(" AND trade_pair = ").push_bind(trade_pair);
+    }
+    if let Some(direction) = &filter.direction {
+        query.push(" AND direction = ").push_bind(direction);
+    }
+    if let Some(from) = filter.from {
+        query.push(" AND fetch_timestamp >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(" AND fetch_timestamp < ").push_bind(to);
+    }
+    query
+}
+
+/// Get the most recently fetched DEX market record of every (exchange, pair, direction) in
+/// one query, leaving out those not fetched since `fetched_since`. Sells and buys are
+/// separate quotes of a pair, so both of its latest ones are returned.
+pub async fn get_latest_dex_states(
+    pool: &DatabasePool,
+    fetched_since: Option<DateTime<Utc>>,
+) -> Result<Vec<DEXState>, Box<dyn std::error::Error>> {
+    let rows = latest_dex_states_query(fetched_since)
+        .build_query_as::<DexMarketRow>()
+        .fetch_all(pool)
+        .await?;
+
+    let mut dex_states: Vec<DEXState> = rows.into_iter().map(DEXState::from).collect();
+    // Rows fetched at the same instant all match the MAX, the newest id comes first
+    dex_states.dedup_by(|next, kept| {
+        next.exchange == kept.exchange
+            && next.trade_pair == kept.trade_pair
+            && next.direction == kept.direction
+    });
     Ok(dex_states)
 }
 
+/// `SELECT` of the rows fetched last of every pair and direction, joined on the
+/// `MAX(fetch_timestamp)` the `idx_dex_markets_pair_fetch_ts` index answers
+fn latest_dex_states_query<'a>(fetched_since: Option<DateTime<Utc>>) -> QueryBuilder<'a, Db> {
+    let mut query = QueryBuilder::new(format!(
+        "SELECT {} FROM dex_markets m JOIN (SELECT exchange, trade_pair, direction, MAX(fetch_timestamp) AS fetch_timestamp FROM dex_markets",
+        qualified_columns(DEX_MARKET_COLUMNS, "m")
+    ));
+    if let Some(fetched_since) = fetched_since {
+        query
+            .push(" WHERE fetch_timestamp >= ")
+            .push_bind(fetched_since);
+    }
+    query.push(
+        " GROUP BY exchange, trade_pair, direction) latest ON m.exchange = latest.exchange AND m.trade_pair = latest.trade_pair AND m.direction = latest.direction AND m.fetch_timestamp = latest.fetch_timestamp ORDER BY m.exchange, m.trade_pair, m.direction, m.id DESC",
+    );
+    query
+}
+
 /// Update existing DEX market record
 pub async fn update_dex_market(
-    pool: &Pool<MySql>,
+    pool: &DatabasePool,
     dex_state: &DEXState,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let query = r#"
         UPDATE dex_markets
-        SET direction = ?, volume = ?, price = ?, trade_timestamp = ?, fetch_timestamp = ?, block_number = ?
+        SET direction = ?, volume = ?, price = ?, trade_timestamp = ?, fetch_timestamp = ?, block_number = ?, price_impact_bps = ?, pool_address = ?, stale = ?, fetch_latency_ms = ?, route = ?, route_plan = ?
         WHERE trade_id = ? AND exchange = ?
     "#;
 
-    let result = sqlx::query(query)
+    let result = sqlx::query(&sql(query))
         .bind(&dex_state.direction)
-        .bind(&dex_state.volume)
-        .bind(&dex_state.price)
+        .bind(dex_state.volume)
+        .bind(dex_state.price)
         .bind(dex_state.trade_time)
         .bind(dex_state.fetch_time)
-        .bind(&dex_state.block_number)
+        .bind(dex_state.block_number as i64) // Convert u64 to i64 for BIGINT
+        .bind(dex_state.price_impact_bps)
+        .bind(&dex_state.pool_address)
+        .bind(dex_state.stale)
+        .bind(dex_state.fetch_latency_ms.map(|ms| ms as i64)) // Convert u64 to i64 for BIGINT
+        .bind(&dex_state.route)
+        .bind(&dex_state.route_plan)
         .bind(&dex_state.trade_id)
         .bind(&dex_state.exchange)
         .execute(pool)
         .await?;
 
-    if result.rows_affected() > 0 {
+    if result.rows_affected() == 0 {
         warn!(
             "No DEX market record found to update: trade_id={}, exchange={}",
             dex_state.trade_id, dex_state.exchange
@@ -184,3 +944,7 @@ pub async fn update_dex_market(
 
     Ok(())
 }
+
+#[cfg(test)]
+#[path = "markets_tests.rs"]
+mod markets_tests;
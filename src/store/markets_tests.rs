@@ -0,0 +1,692 @@
+use super::*;
+use chrono::TimeZone;
+use rust_decimal::Decimal;
+
+use crate::models::market::{CEXDepth, OrderBookItem};
+use crate::store::db::lazy_pool;
+use crate::store::pagination::{MarketCursor, SortOrder};
+use crate::store::test_db::{CapturedLogs, test_pool, unique_exchange, with_question_marks};
+
+fn snapshot(secs: i64) -> CEXOrderBookSnapshot {
+    CEXOrderBookSnapshot {
+        exchange: "bybit".to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        snapshot_time: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+        bids: vec![OrderBookItem::new("100.5", "1")],
+        asks: vec![OrderBookItem::new("101", "2")],
+    }
+}
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+}
+
+#[test]
+fn nearest_snapshot_picks_the_closest_side() {
+    assert_eq!(
+        nearest_snapshot(vec![snapshot(0), snapshot(10)], at(7)),
+        Some(snapshot(10))
+    );
+    assert_eq!(
+        nearest_snapshot(vec![snapshot(0), snapshot(10)], at(3)),
+        Some(snapshot(0))
+    );
+    // Both lookups return the same row when a snapshot was taken exactly at `at`
+    assert_eq!(
+        nearest_snapshot(vec![snapshot(10), snapshot(10)], at(10)),
+        Some(snapshot(10))
+    );
+}
+
+#[test]
+fn nearest_snapshot_prefers_the_earlier_one_on_a_tie() {
+    assert_eq!(
+        nearest_snapshot(vec![snapshot(10), snapshot(0)], at(5)),
+        Some(snapshot(0))
+    );
+}
+
+#[test]
+fn nearest_snapshot_handles_a_single_or_no_side() {
+    assert_eq!(
+        nearest_snapshot(vec![snapshot(-30)], at(0)),
+        Some(snapshot(-30))
+    );
+    assert_eq!(
+        nearest_snapshot(vec![snapshot(30)], at(0)),
+        Some(snapshot(30))
+    );
+    assert_eq!(nearest_snapshot(Vec::new(), at(0)), None);
+}
+
+#[test]
+fn snapshot_levels_serialize_as_price_volume_objects() {
+    let json = serde_json::to_string(&snapshot(0).bids).unwrap();
+
+    assert_eq!(json, r#"[{"price":"100.5","volume":"1"}]"#);
+    let levels: Vec<OrderBookItem> = serde_json::from_str(&json).unwrap();
+    assert_eq!(levels, snapshot(0).bids);
+}
+
+fn cex_state(exchange: &str, trade_pair: &str, secs: i64, bid: &str) -> CEXState {
+    CEXState {
+        trade_id: format!("{}:{}", trade_pair, secs),
+        exchange: exchange.to_string(),
+        trade_pair: trade_pair.to_string(),
+        bid_price: bid.parse().unwrap(),
+        bid_volume: Decimal::ONE,
+        ask_price: bid.parse::<Decimal>().unwrap() + Decimal::ONE,
+        ask_volume: Decimal::TWO,
+        trade_time: at(secs),
+        fetch_time: at(secs),
+        feed_latency_ms: Some(12),
+        depth: None,
+    }
+}
+
+#[test]
+fn cex_markets_query_without_filter_reads_every_row() {
+    let filter = CexMarketFilter::default();
+
+    let query = cex_markets_query(&filter);
+
+    assert_eq!(
+        with_question_marks(query.sql()),
+        format!(
+            "SELECT {} FROM cex_markets WHERE 1 = 1 ORDER BY fetch_timestamp DESC",
+            CEX_MARKET_COLUMNS
+        )
+    );
+}
+
+#[test]
+fn cex_markets_query_binds_every_filter() {
+    let filter = CexMarketFilter {
+        exchange: Some("bybit'; DROP TABLE cex_markets; --".to_string()),
+        trade_pair: Some("TRUMPUSDC".to_string()),
+        from: Some(at(0)),
+        to: Some(at(60)),
+        limit: Some(100),
+    };
+
+    let query = cex_markets_query(&filter);
+
+    assert_eq!(
+        with_question_marks(query.sql()),
+        format!(
+            "SELECT {} FROM cex_markets WHERE 1 = 1 AND exchange = ? AND trade_pair = ? AND fetch_timestamp >= ? AND fetch_timestamp < ? ORDER BY fetch_timestamp DESC LIMIT ?",
+            CEX_MARKET_COLUMNS
+        )
+    );
+}
+
+#[test]
+fn cex_markets_query_skips_unset_filters() {
+    let filter = CexMarketFilter {
+        trade_pair: Some("TRUMPUSDC".to_string()),
+        to: Some(at(60)),
+        ..CexMarketFilter::default()
+    };
+
+    let query = cex_markets_query(&filter);
+
+    assert!(with_question_marks(query.sql()).ends_with(
+        "WHERE 1 = 1 AND trade_pair = ? AND fetch_timestamp < ? ORDER BY fetch_timestamp DESC"
+    ));
+}
+
+#[tokio::test]
+async fn get_cex_markets_filters_a_seeded_database() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("filter");
+    let other = unique_exchange("filter");
+    let states = [
+        cex_state(&exchange, "TRUMPUSDC", 0, "10"),
+        cex_state(&exchange, "TRUMPUSDC", 30, "11"),
+        cex_state(&exchange, "TRUMPUSDC", 60, "12"),
+        cex_state(&exchange, "SOLUSDC", 30, "150"),
+        cex_state(&other, "TRUMPUSDC", 30, "13"),
+    ];
+    insert_cex_markets_batch(&pool, &states).await.unwrap();
+
+    let pair = |from, to, limit| CexMarketFilter {
+        exchange: Some(exchange.clone()),
+        trade_pair: Some("TRUMPUSDC".to_string()),
+        from,
+        to,
+        limit,
+    };
+    let bids = |states: Vec<CEXState>| -> Vec<Decimal> {
+        states.iter().map(|state| state.bid_price).collect()
+    };
+
+    let all = get_cex_markets(&pool, &pair(None, None, None))
+        .await
+        .unwrap();
+    assert_eq!(
+        bids(all),
+        vec![Decimal::from(12), Decimal::from(11), Decimal::TEN]
+    );
+    let window = get_cex_markets(&pool, &pair(Some(at(30)), Some(at(60)), None))
+        .await
+        .unwrap();
+    assert_eq!(bids(window), vec![Decimal::from(11)]);
+    let limited = get_cex_markets(&pool, &pair(None, None, Some(2)))
+        .await
+        .unwrap();
+    assert_eq!(bids(limited), vec![Decimal::from(12), Decimal::from(11)]);
+    let exchange_only = CexMarketFilter {
+        exchange: Some(exchange.clone()),
+        ..CexMarketFilter::default()
+    };
+    assert_eq!(
+        get_cex_markets(&pool, &exchange_only).await.unwrap().len(),
+        4
+    );
+
+    let latest = get_latest_cex_market(&pool, &exchange, "TRUMPUSDC")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(latest.bid_price, Decimal::from(12));
+    assert_eq!(latest.fetch_time, at(60));
+    assert_eq!(latest.feed_latency_ms, Some(12));
+    assert!(
+        get_latest_cex_market(&pool, &exchange, "BTCUSDC")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+fn dex_state(exchange: &str, direction: &str, secs: i64, price: &str) -> DEXState {
+    DEXState {
+        trade_id: format!("TRUMPUSDC:{}:{}", secs, direction),
+        exchange: exchange.to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        direction: direction.to_string(),
+        price: price.parse().unwrap(),
+        volume: Decimal::TEN,
+        trade_time: at(secs),
+        fetch_time: at(secs),
+        block_number: 321_000_000 + secs as u64,
+        price_impact_bps: None,
+        pool_address: None,
+        stale: false,
+        fetch_latency_ms: None,
+        route: None,
+        route_plan: None,
+    }
+}
+
+#[test]
+fn qualified_columns_prefix_every_column() {
+    assert_eq!(
+        qualified_columns("id, trade_id, exchange", "m"),
+        "m.id, m.trade_id, m.exchange"
+    );
+}
+
+#[test]
+fn latest_cex_states_query_binds_the_staleness_cutoff() {
+    let unbounded = latest_cex_states_query(None);
+    let bounded = latest_cex_states_query(Some(at(0)));
+
+    assert!(!unbounded.sql().contains("WHERE"));
+    assert!(with_question_marks(bounded.sql()).contains(
+        "MAX(fetch_timestamp) AS fetch_timestamp FROM cex_markets WHERE fetch_timestamp >= ? GROUP BY exchange, trade_pair) latest"
+    ));
+    assert!(
+        bounded
+            .sql()
+            .ends_with("ORDER BY m.exchange, m.trade_pair, m.id DESC")
+    );
+}
+
+#[test]
+fn latest_dex_states_query_groups_by_direction() {
+    let query = latest_dex_states_query(Some(at(0)));
+
+    assert!(with_question_marks(query.sql()).contains(
+        "FROM dex_markets WHERE fetch_timestamp >= ? GROUP BY exchange, trade_pair, direction) latest"
+    ));
+    assert!(query.sql().contains("AND m.direction = latest.direction"));
+}
+
+#[tokio::test]
+async fn get_latest_cex_states_returns_the_newest_row_of_each_pair() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("latest");
+    let states = [
+        cex_state(&exchange, "TRUMPUSDC", 0, "10"),
+        cex_state(&exchange, "TRUMPUSDC", 60, "12"),
+        cex_state(&exchange, "TRUMPUSDC", 30, "11"),
+        cex_state(&exchange, "SOLUSDC", 10, "150"),
+        cex_state(&exchange, "SOLUSDC", 20, "151"),
+        cex_state(&exchange, "BTCUSDC", -3600, "90000"),
+    ];
+    insert_cex_markets_batch(&pool, &states).await.unwrap();
+
+    let latest = |states: Vec<CEXState>| -> Vec<(String, Decimal)> {
+        states
+            .into_iter()
+            .filter(|state| state.exchange == exchange)
+            .map(|state| (state.trade_pair, state.bid_price))
+            .collect()
+    };
+
+    assert_eq!(
+        latest(get_latest_cex_states(&pool, None).await.unwrap()),
+        vec![
+            ("BTCUSDC".to_string(), Decimal::from(90_000)),
+            ("SOLUSDC".to_string(), Decimal::from(151)),
+            ("TRUMPUSDC".to_string(), Decimal::from(12)),
+        ]
+    );
+    assert_eq!(
+        latest(get_latest_cex_states(&pool, Some(at(0))).await.unwrap()),
+        vec![
+            ("SOLUSDC".to_string(), Decimal::from(151)),
+            ("TRUMPUSDC".to_string(), Decimal::from(12)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_latest_dex_states_returns_the_newest_row_of_each_direction() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("latest_dex");
+    for dex_state in [
+        dex_state(&exchange, "sell", 0, "9.9"),
+        dex_state(&exchange, "sell", 30, "9.8"),
+        dex_state(&exchange, "buy", 0, "10.1"),
+        dex_state(&exchange, "buy", 20, "10.2"),
+        dex_state(&exchange, "buy", 10, "10.3"),
+    ] {
+        insert_dex_market(&pool, &dex_state).await.unwrap();
+    }
+
+    let latest: Vec<(String, Decimal, u64)> = get_latest_dex_states(&pool, Some(at(0)))
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|state| state.exchange == exchange)
+        .map(|state| (state.direction, state.price, state.block_number))
+        .collect();
+
+    assert_eq!(
+        latest,
+        vec![
+            ("buy".to_string(), "10.2".parse().unwrap(), 321_000_020),
+            ("sell".to_string(), "9.8".parse().unwrap(), 321_000_030),
+        ]
+    );
+}
+
+/// Trade ids of every page of the pair read from the first, following the cursors
+async fn page_through(
+    pool: &DatabasePool,
+    filter: &CexMarketFilter,
+    order: SortOrder,
+    page_size: u32,
+) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut after: Option<MarketCursor> = None;
+    loop {
+        let page = get_cex_markets_page(pool, filter, after, order, page_size)
+            .await
+            .unwrap();
+        pages.push(page.rows.into_iter().map(|state| state.trade_id).collect());
+        match page.next {
+            // Through the opaque form, as API clients hand it back
+            Some(next) => after = Some(MarketCursor::decode(&next.encode()).unwrap()),
+            None => return pages,
+        }
+    }
+}
+
+#[tokio::test]
+async fn get_cex_markets_page_iterates_across_duplicate_fetch_times() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("pages");
+    // Inserted in id order: three rows share the second timestamp, two the last
+    let states: Vec<CEXState> = [
+        ("a", 0),
+        ("b", 10),
+        ("c", 10),
+        ("d", 10),
+        ("e", 20),
+        ("f", 20),
+    ]
+    .into_iter()
+    .map(|(trade_id, secs)| CEXState {
+        trade_id: trade_id.to_string(),
+        ..cex_state(&exchange, "TRUMPUSDC", secs, "10")
+    })
+    .collect();
+    for state in &states {
+        insert_cex_market(&pool, state).await.unwrap();
+    }
+    let filter = CexMarketFilter {
+        exchange: Some(exchange.clone()),
+        ..CexMarketFilter::default()
+    };
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+        page_through(&pool, &filter, SortOrder::Ascending, 2).await,
+        vec![ids(&["a", "b"]), ids(&["c", "d"]), ids(&["e", "f"])]
+    );
+    assert_eq!(
+        page_through(&pool, &filter, SortOrder::Descending, 4).await,
+        vec![ids(&["f", "e", "d", "c"]), ids(&["b", "a"])]
+    );
+    assert_eq!(
+        page_through(&pool, &filter, SortOrder::Descending, 6).await,
+        vec![ids(&["f", "e", "d", "c", "b", "a"])]
+    );
+    assert!(
+        get_cex_markets_page(&pool, &filter, None, SortOrder::Ascending, 0)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn get_dex_markets_page_reads_one_direction() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("dex_pages");
+    for dex_state in [
+        dex_state(&exchange, "sell", 0, "9.9"),
+        dex_state(&exchange, "buy", 0, "10.1"),
+        dex_state(&exchange, "sell", 10, "9.8"),
+        dex_state(&exchange, "sell", 20, "9.7"),
+    ] {
+        insert_dex_market(&pool, &dex_state).await.unwrap();
+    }
+    let filter = DexMarketFilter {
+        exchange: Some(exchange.clone()),
+        direction: Some("sell".to_string()),
+        ..DexMarketFilter::default()
+    };
+
+    let first = get_dex_markets_page(&pool, &filter, None, SortOrder::Descending, 2)
+        .await
+        .unwrap();
+    let second = get_dex_markets_page(&pool, &filter, first.next, SortOrder::Descending, 2)
+        .await
+        .unwrap();
+
+    let prices =
+        |states: &[DEXState]| -> Vec<Decimal> { states.iter().map(|state| state.price).collect() };
+    assert_eq!(
+        prices(&first.rows),
+        vec!["9.7".parse().unwrap(), "9.8".parse().unwrap()]
+    );
+    assert_eq!(
+        prices(&second.rows),
+        vec!["9.9".parse::<Decimal>().unwrap()]
+    );
+    assert_eq!(second.next, None);
+    assert_eq!(get_dex_markets(&pool, &filter).await.unwrap().len(), 3);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn batch_inserts_of_nothing_do_not_touch_the_database() {
+    let pool = lazy_pool();
+
+    assert_eq!(insert_cex_markets_batch(&pool, &[]).await.unwrap(), 0);
+    assert_eq!(insert_dex_markets_batch(&pool, &[]).await.unwrap(), 0);
+}
+
+#[test]
+fn cex_markets_insert_query_binds_one_row_per_state() {
+    let states = [
+        cex_state("bybit", "TRUMPUSDC", 0, "10"),
+        cex_state("bybit", "TRUMPUSDC", 1, "11"),
+    ];
+
+    let query = cex_markets_insert_query(&states);
+
+    let row = format!("({})", vec!["?"; 16].join(", "));
+    assert!(with_question_marks(query.sql()).ends_with(&format!(
+        "ask_depth_25bps) VALUES {}, {}{}",
+        row,
+        row,
+        on_conflict_update(MARKET_KEY, CEX_MARKET_UPDATED_COLUMNS)
+    )));
+}
+
+#[test]
+fn dex_markets_insert_query_binds_one_row_per_state() {
+    let states = [dex_state("meteora", "sell", 0, "9.9")];
+
+    let query = dex_markets_insert_query(&states);
+
+    let row = format!("({})", vec!["?"; 15].join(", "));
+    assert!(with_question_marks(query.sql()).ends_with(&format!(
+        "route_plan) VALUES {}{}",
+        row,
+        on_conflict_update(MARKET_KEY, DEX_MARKET_UPDATED_COLUMNS)
+    )));
+}
+
+#[test]
+fn last_per_key_keeps_the_last_state_of_every_key_in_first_seen_order() {
+    let unique = [
+        cex_state("bybit", "TRUMPUSDC", 0, "10"),
+        cex_state("bybit", "TRUMPUSDC", 1, "11"),
+    ];
+    let duplicated = [
+        cex_state("bybit", "TRUMPUSDC", 0, "10"),
+        cex_state("bybit", "TRUMPUSDC", 1, "11"),
+        cex_state("bybit", "TRUMPUSDC", 0, "20"),
+    ];
+    let key = |state: &CEXState| state.trade_id.clone();
+
+    assert!(matches!(last_per_key(&unique, key), Cow::Borrowed(_)));
+    let bids: Vec<Decimal> = last_per_key(&duplicated, key)
+        .iter()
+        .map(|state| state.bid_price)
+        .collect();
+    assert_eq!(bids, vec![Decimal::from(20), Decimal::from(11)]);
+}
+
+/// Affected rows an upsert counts for an updated row
+const UPDATED: u64 = if cfg!(feature = "postgres") { 1 } else { 2 };
+
+#[tokio::test]
+async fn insert_cex_markets_batch_upserts_duplicates() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("batch");
+    let first = [
+        cex_state(&exchange, "TRUMPUSDC", 0, "10"),
+        cex_state(&exchange, "TRUMPUSDC", 1, "11"),
+    ];
+    // Updates the first row, and writes the same trade twice within the batch: the last one
+    // wins and only it is written
+    let second = [
+        cex_state(&exchange, "TRUMPUSDC", 0, "20"),
+        cex_state(&exchange, "TRUMPUSDC", 2, "12"),
+        cex_state(&exchange, "TRUMPUSDC", 2, "13"),
+    ];
+
+    assert_eq!(insert_cex_markets_batch(&pool, &first).await.unwrap(), 2);
+    assert_eq!(
+        insert_cex_markets_batch(&pool, &second).await.unwrap(),
+        UPDATED + 1
+    );
+
+    let filter = CexMarketFilter {
+        exchange: Some(exchange.clone()),
+        ..CexMarketFilter::default()
+    };
+    let mut bids: Vec<(String, Decimal)> = get_cex_markets(&pool, &filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|state| (state.trade_id, state.bid_price))
+        .collect();
+    bids.sort();
+    assert_eq!(
+        bids,
+        vec![
+            ("TRUMPUSDC:0".to_string(), Decimal::from(20)),
+            ("TRUMPUSDC:1".to_string(), Decimal::from(11)),
+            ("TRUMPUSDC:2".to_string(), Decimal::from(13)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn insert_dex_markets_batch_upserts_duplicates() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("dex_batch");
+    let states = [
+        dex_state(&exchange, "sell", 0, "9.9"),
+        dex_state(&exchange, "buy", 0, "10.1"),
+        dex_state(&exchange, "sell", 0, "9.8"),
+    ];
+
+    assert_eq!(
+        insert_dex_markets_batch(&pool, &states).await.unwrap(),
+        1 + 1
+    );
+
+    let filter = DexMarketFilter {
+        exchange: Some(exchange.clone()),
+        direction: Some("sell".to_string()),
+        ..DexMarketFilter::default()
+    };
+    let sells = get_dex_markets(&pool, &filter).await.unwrap();
+    assert_eq!(sells.len(), 1);
+    assert_eq!(sells[0].price, "9.8".parse().unwrap());
+    assert_eq!(sells[0].block_number, 321_000_000);
+}
+
+#[tokio::test]
+async fn update_dex_market_overwrites_an_existing_row_without_warning() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("dex_update");
+    insert_dex_market(&pool, &dex_state(&exchange, "sell", 0, "9.9"))
+        .await
+        .unwrap();
+    let updated = DEXState {
+        volume: Decimal::from(25),
+        // Above u32::MAX, like mainnet slots
+        block_number: 5_000_000_000,
+        price_impact_bps: Some("1.5".parse().unwrap()),
+        ..dex_state(&exchange, "sell", 0, "10.2")
+    };
+
+    let logs = CapturedLogs::start();
+    update_dex_market(&pool, &updated).await.unwrap();
+
+    assert!(!logs.contents().contains("No DEX market record found"));
+    let filter = DexMarketFilter {
+        exchange: Some(exchange),
+        ..DexMarketFilter::default()
+    };
+    let rows = get_dex_markets(&pool, &filter).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].price, updated.price);
+    assert_eq!(rows[0].volume, updated.volume);
+    assert_eq!(rows[0].block_number, updated.block_number);
+    assert_eq!(rows[0].price_impact_bps, updated.price_impact_bps);
+}
+
+#[tokio::test]
+async fn update_dex_market_warns_when_the_row_is_missing() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("dex_update");
+
+    let logs = CapturedLogs::start();
+    update_dex_market(&pool, &dex_state(&exchange, "sell", 0, "9.9"))
+        .await
+        .unwrap();
+
+    assert!(logs.contents().contains(&format!(
+        "No DEX market record found to update: trade_id=TRUMPUSDC:0:sell, exchange={}",
+        exchange
+    )));
+}
+
+#[tokio::test]
+async fn cex_state_round_trips_through_insert_and_select() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("cex_round_trip");
+    let with_depth = CEXState {
+        depth: Some(CEXDepth {
+            bid_5bps: "1.5".parse().unwrap(),
+            ask_5bps: Decimal::TWO,
+            bid_10bps: Decimal::from(3),
+            ask_10bps: "4.25".parse().unwrap(),
+            bid_25bps: Decimal::from(8),
+            ask_25bps: Decimal::from(9),
+        }),
+        ..cex_state(&exchange, "TRUMPUSDC", 0, "10.5")
+    };
+    let without_depth = CEXState {
+        feed_latency_ms: None,
+        ..cex_state(&exchange, "SOLUSDC", 1, "150")
+    };
+
+    for stored in [with_depth, without_depth] {
+        insert_cex_market(&pool, &stored).await.unwrap();
+
+        let read = get_latest_cex_market(&pool, &exchange, &stored.trade_pair)
+            .await
+            .unwrap();
+        assert_eq!(read, Some(stored));
+    }
+}
+
+#[tokio::test]
+async fn dex_state_round_trips_through_insert_and_select() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("dex_round_trip");
+    let filled = DEXState {
+        block_number: 5_000_000_000,
+        price_impact_bps: Some("1.5".parse().unwrap()),
+        pool_address: Some("5rCf1DM8LjKTw4YqhnoLcngyZYeNnQqztScTogYHAS6".to_string()),
+        stale: true,
+        fetch_latency_ms: Some(180),
+        route: Some("TRUMP-SOL-USDC".to_string()),
+        route_plan: Some("Meteora DLMM 100%".to_string()),
+        ..dex_state(&exchange, "sell", 0, "9.9")
+    };
+    let bare = dex_state(&exchange, "buy", 1, "10.1");
+
+    for stored in [filled, bare] {
+        insert_dex_market(&pool, &stored).await.unwrap();
+
+        let filter = DexMarketFilter {
+            exchange: Some(exchange.clone()),
+            direction: Some(stored.direction.clone()),
+            ..DexMarketFilter::default()
+        };
+        assert_eq!(get_dex_markets(&pool, &filter).await.unwrap(), vec![stored]);
+    }
+}
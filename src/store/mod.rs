@@ -1,2 +1,18 @@
+pub mod analytics;
+pub mod balances;
+pub mod cex_fees;
 pub mod db;
 pub mod markets;
+pub mod opportunities;
+pub mod orders;
+pub mod pagination;
+pub mod pool_fees;
+pub mod pool_stats;
+pub mod quote_checks;
+pub mod spreads;
+#[cfg(test)]
+pub(crate) mod test_db;
+pub mod trade_pairs;
+pub mod tx;
+pub mod write_buffer;
+pub mod write_metrics;
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{FromRow, Row};
+
+use crate::models::opportunity::{Opportunity, OpportunityStatus};
+use crate::store::db::{
+    DatabasePool, DbConnection, DbRow, Unsigned, insert_returning_id, returning_id, sql,
+};
+
+const OPPORTUNITY_COLUMNS: &str = "id, trade_pair, buy_venue, sell_venue, buy_price, sell_price, max_size, gross_spread_bps, net_spread_bps, cex_state_id, dex_state_id, detected_at, status";
+
+/// `opportunities` row read with [`OPPORTUNITY_COLUMNS`]
+#[derive(Debug, FromRow)]
+struct OpportunityRow {
+    trade_pair: String,
+    buy_venue: String,
+    sell_venue: String,
+    buy_price: Decimal,
+    sell_price: Decimal,
+    max_size: Decimal,
+    gross_spread_bps: Decimal,
+    net_spread_bps: Decimal,
+    #[sqlx(try_from = "Unsigned")]
+    cex_state_id: Option<u64>,
+    #[sqlx(try_from = "Unsigned")]
+    dex_state_id: Option<u64>,
+    detected_at: DateTime<Utc>,
+    #[sqlx(try_from = "String")]
+    status: OpportunityStatus,
+}
+
+impl From<OpportunityRow> for Opportunity {
+    fn from(row: OpportunityRow) -> Self {
+        Self {
+            trade_pair: row.trade_pair,
+            buy_venue: row.buy_venue,
+            sell_venue: row.sell_venue,
+            buy_price: row.buy_price,
+            sell_price: row.sell_price,
+            max_size: row.max_size,
+            gross_spread_bps: row.gross_spread_bps,
+            net_spread_bps: row.net_spread_bps,
+            cex_state_id: row.cex_state_id,
+            dex_state_id: row.dex_state_id,
+            detected_at: row.detected_at,
+            status: row.status,
+        }
+    }
+}
+
+/// Insert a detected opportunity and return its id
+pub async fn insert_opportunity(
+    pool: &DatabasePool,
+    opportunity: &Opportunity,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut conn = pool.acquire().await?;
+    insert_opportunity_tx(&mut conn, opportunity).await
+}
+
+/// Insert an opportunity on `conn`, which may be the transaction that stored the market states
+/// it references
+pub async fn insert_opportunity_tx(
+    conn: &mut DbConnection,
+    opportunity: &Opportunity,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+            INSERT INTO opportunities (trade_pair, buy_venue, sell_venue, buy_price, sell_price, max_size, gross_spread_bps, net_spread_bps, cex_state_id, dex_state_id, detected_at, status)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&opportunity.trade_pair)
+        .bind(&opportunity.buy_venue)
+        .bind(&opportunity.sell_venue)
+        .bind(opportunity.buy_price)
+        .bind(opportunity.sell_price)
+        .bind(opportunity.max_size)
+        .bind(opportunity.gross_spread_bps)
+        .bind(opportunity.net_spread_bps)
+        .bind(opportunity.cex_state_id.map(|id| id as i64))
+        .bind(opportunity.dex_state_id.map(|id| id as i64))
+        .bind(opportunity.detected_at)
+        .bind(opportunity.status.as_str());
+
+    Ok(insert_returning_id(conn, insert).await?)
+}
+
+/// Move a detected opportunity to `status`. Expired and acted opportunities keep their
+/// status; returns whether the opportunity was still detected and got updated.
+pub async fn update_opportunity_status(
+    pool: &DatabasePool,
+    id: u64,
+    status: OpportunityStatus,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let query = "UPDATE opportunities SET status = ? WHERE id = ? AND status = 'detected'";
+
+    let result = sqlx::query(&sql(query))
+        .bind(status.as_str())
+        .bind(id as i64)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the opportunities not expired or acted on yet, with their ids, oldest first
+pub async fn get_open_opportunities(
+    pool: &DatabasePool,
+) -> Result<Vec<(u64, Opportunity)>, Box<dyn std::error::Error>> {
+    let query = format!(
+        "SELECT {} FROM opportunities WHERE status = 'detected' ORDER BY detected_at, id",
+        OPPORTUNITY_COLUMNS
+    );
+
+    let rows = sqlx::query(&sql(&query)).fetch_all(pool).await?;
+
+    Ok(with_ids(&rows)?)
+}
+
+/// Get the opportunities detected from `from` (inclusive) to `to` (exclusive), whatever their
+/// status, with their ids, oldest first
+pub async fn get_opportunities(
+    pool: &DatabasePool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<(u64, Opportunity)>, Box<dyn std::error::Error>> {
+    let query = format!(
+        "SELECT {} FROM opportunities WHERE detected_at >= ? AND detected_at < ? ORDER BY detected_at, id",
+        OPPORTUNITY_COLUMNS
+    );
+
+    let rows = sqlx::query(&sql(&query))
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(with_ids(&rows)?)
+}
+
+/// Opportunities of `rows`, each paired with its id
+fn with_ids(rows: &[DbRow]) -> Result<Vec<(u64, Opportunity)>, sqlx::Error> {
+    rows.iter()
+        .map(|row| {
+            Ok((
+                row.try_get::<i64, _>("id")? as u64,
+                OpportunityRow::from_row(row)?.into(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "opportunities_tests.rs"]
+mod opportunities_tests;
@@ -0,0 +1,172 @@
+use super::*;
+use chrono::TimeZone;
+use rust_decimal::Decimal;
+
+use crate::models::market::CEXState;
+use crate::store::markets::insert_cex_market;
+use crate::store::test_db::{test_pool, unique_exchange};
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+}
+
+fn opportunity(trade_pair: &str, secs: i64) -> Opportunity {
+    Opportunity {
+        trade_pair: trade_pair.to_string(),
+        buy_venue: "meteora".to_string(),
+        sell_venue: "bybit".to_string(),
+        buy_price: "10".parse().unwrap(),
+        sell_price: "10.05".parse().unwrap(),
+        max_size: Decimal::from(250),
+        gross_spread_bps: Decimal::from(50),
+        net_spread_bps: "12.5".parse().unwrap(),
+        cex_state_id: None,
+        dex_state_id: None,
+        detected_at: at(secs),
+        status: OpportunityStatus::Detected,
+    }
+}
+
+/// Opportunities of `trade_pair` among `rows`, as (detected second, status) pairs
+fn of_pair(rows: Vec<(u64, Opportunity)>, trade_pair: &str) -> Vec<(i64, OpportunityStatus)> {
+    rows.into_iter()
+        .filter(|(_, opportunity)| opportunity.trade_pair == trade_pair)
+        .map(|(_, opportunity)| {
+            (
+                opportunity.detected_at.timestamp() - 1_700_000_000,
+                opportunity.status,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn opportunity_status_round_trips_through_its_column_value() {
+    for status in [
+        OpportunityStatus::Detected,
+        OpportunityStatus::Expired,
+        OpportunityStatus::Acted,
+    ] {
+        assert_eq!(OpportunityStatus::parse(status.as_str()), Ok(status));
+    }
+    assert!(OpportunityStatus::parse("open").is_err());
+}
+
+#[tokio::test]
+async fn inserted_opportunities_read_back_with_their_market_states() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let trade_pair = unique_exchange("OPP");
+    let cex_state_id = insert_cex_market(
+        &pool,
+        &CEXState {
+            trade_id: format!("{}:0", trade_pair),
+            exchange: "bybit".to_string(),
+            trade_pair: trade_pair.clone(),
+            bid_price: "10.05".parse().unwrap(),
+            bid_volume: Decimal::from(300),
+            ask_price: "10.06".parse().unwrap(),
+            ask_volume: Decimal::from(300),
+            trade_time: at(0),
+            fetch_time: at(0),
+            feed_latency_ms: None,
+            depth: None,
+        },
+    )
+    .await
+    .unwrap();
+    let stored = Opportunity {
+        cex_state_id: Some(cex_state_id),
+        ..opportunity(&trade_pair, 0)
+    };
+
+    let id = insert_opportunity(&pool, &stored).await.unwrap();
+
+    let open = get_open_opportunities(&pool).await.unwrap();
+    assert!(open.contains(&(id, stored)));
+}
+
+#[tokio::test]
+async fn insert_opportunity_rejects_unknown_market_states() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let trade_pair = unique_exchange("OPP");
+    let dangling = Opportunity {
+        dex_state_id: Some(i64::MAX as u64),
+        ..opportunity(&trade_pair, 0)
+    };
+
+    assert!(insert_opportunity(&pool, &dangling).await.is_err());
+}
+
+#[tokio::test]
+async fn update_opportunity_status_only_moves_detected_opportunities() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let trade_pair = unique_exchange("OPP");
+    let expired = insert_opportunity(&pool, &opportunity(&trade_pair, 0))
+        .await
+        .unwrap();
+    let acted = insert_opportunity(&pool, &opportunity(&trade_pair, 1))
+        .await
+        .unwrap();
+    insert_opportunity(&pool, &opportunity(&trade_pair, 2))
+        .await
+        .unwrap();
+
+    assert!(
+        update_opportunity_status(&pool, expired, OpportunityStatus::Expired)
+            .await
+            .unwrap()
+    );
+    assert!(
+        update_opportunity_status(&pool, acted, OpportunityStatus::Acted)
+            .await
+            .unwrap()
+    );
+    // Final statuses are kept
+    assert!(
+        !update_opportunity_status(&pool, expired, OpportunityStatus::Acted)
+            .await
+            .unwrap()
+    );
+
+    assert_eq!(
+        of_pair(get_open_opportunities(&pool).await.unwrap(), &trade_pair),
+        vec![(2, OpportunityStatus::Detected)]
+    );
+}
+
+#[tokio::test]
+async fn get_opportunities_reads_a_detection_time_range_of_any_status() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let trade_pair = unique_exchange("OPP");
+    let mut ids = Vec::new();
+    for secs in [30, -10, 0, 60, 10] {
+        ids.push(
+            insert_opportunity(&pool, &opportunity(&trade_pair, secs))
+                .await
+                .unwrap(),
+        );
+    }
+    update_opportunity_status(&pool, ids[0], OpportunityStatus::Acted)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        of_pair(
+            get_opportunities(&pool, at(0), at(60)).await.unwrap(),
+            &trade_pair
+        ),
+        vec![
+            (0, OpportunityStatus::Detected),
+            (10, OpportunityStatus::Detected),
+            (30, OpportunityStatus::Acted),
+        ]
+    );
+}
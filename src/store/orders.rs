@@ -0,0 +1,56 @@
+use crate::models::order::Order;
+use crate::store::db::DatabasePool;
+
+/// Insert an order, or move an existing one to its new state.
+/// Orders in a final state keep it, so a late or replayed update cannot reopen them.
+pub async fn upsert_order(
+    pool: &DatabasePool,
+    order: &Order,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    // Assignments apply left to right, so `status` is updated last
+    #[cfg(not(feature = "postgres"))]
+    let query = r#"
+        INSERT INTO orders (exchange, order_link_id, order_id, trade_pair, side, order_type, price, qty, status, cum_exec_qty, avg_price, reject_reason, create_timestamp, update_timestamp)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE
+            order_id = COALESCE(VALUES(order_id), order_id),
+            cum_exec_qty = IF(status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated'), cum_exec_qty, VALUES(cum_exec_qty)),
+            avg_price = IF(status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated'), avg_price, VALUES(avg_price)),
+            reject_reason = IF(status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated'), reject_reason, VALUES(reject_reason)),
+            update_timestamp = IF(status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated'), update_timestamp, VALUES(update_timestamp)),
+            status = IF(status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated'), status, VALUES(status))
+    "#;
+    // Every assignment reads the row as it was before the update
+    #[cfg(feature = "postgres")]
+    let query = r#"
+        INSERT INTO orders (exchange, order_link_id, order_id, trade_pair, side, order_type, price, qty, status, cum_exec_qty, avg_price, reject_reason, create_timestamp, update_timestamp)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        ON CONFLICT (exchange, order_link_id) DO UPDATE SET
+            order_id = COALESCE(EXCLUDED.order_id, orders.order_id),
+            cum_exec_qty = CASE WHEN orders.status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated') THEN orders.cum_exec_qty ELSE EXCLUDED.cum_exec_qty END,
+            avg_price = CASE WHEN orders.status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated') THEN orders.avg_price ELSE EXCLUDED.avg_price END,
+            reject_reason = CASE WHEN orders.status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated') THEN orders.reject_reason ELSE EXCLUDED.reject_reason END,
+            update_timestamp = CASE WHEN orders.status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated') THEN orders.update_timestamp ELSE EXCLUDED.update_timestamp END,
+            status = CASE WHEN orders.status IN ('Filled', 'Cancelled', 'PartiallyFilledCanceled', 'Rejected', 'Deactivated') THEN orders.status ELSE EXCLUDED.status END
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(&order.exchange)
+        .bind(&order.order_link_id)
+        .bind(&order.order_id)
+        .bind(&order.trade_pair)
+        .bind(&order.side)
+        .bind(&order.order_type)
+        .bind(order.price)
+        .bind(order.qty)
+        .bind(&order.status)
+        .bind(order.cum_exec_qty)
+        .bind(order.avg_price)
+        .bind(&order.reject_reason)
+        .bind(order.create_time)
+        .bind(order.update_time)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, QueryBuilder};
+
+use crate::store::db::{Db, Unsigned};
+
+/// Direction market history pages are read in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Oldest fetch first
+    Ascending,
+    /// Most recent fetch first
+    #[default]
+    Descending,
+}
+
+impl SortOrder {
+    fn sql(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "ASC",
+            SortOrder::Descending => "DESC",
+        }
+    }
+
+    /// Comparison selecting the rows after the cursor in this order
+    fn after(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => ">",
+            SortOrder::Descending => "<",
+        }
+    }
+}
+
+/// Position after the last row of a page: its fetch time, then its id to order the rows
+/// fetched at the same instant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRow)]
+pub struct MarketCursor {
+    #[sqlx(rename = "fetch_timestamp")]
+    pub fetch_time: DateTime<Utc>,
+    #[sqlx(try_from = "Unsigned")]
+    pub id: u64,
+}
+
+impl MarketCursor {
+    /// Opaque string form handed to API clients: the fetch time in microseconds and the id,
+    /// big-endian, hex encoded
+    pub fn encode(&self) -> String {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.fetch_time.timestamp_micros().to_be_bytes());
+        bytes[8..].copy_from_slice(&self.id.to_be_bytes());
+        hex::encode(bytes)
+    }
+
+    /// Parse a cursor produced by [`MarketCursor::encode`]
+    pub fn decode(value: &str) -> Result<Self, String> {
+        let bytes: [u8; 16] = hex::decode(value)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("Invalid page cursor {}", value))?;
+        let micros = i64::from_be_bytes(bytes[..8].try_into().unwrap());
+        let fetch_time = DateTime::from_timestamp_micros(micros)
+            .ok_or_else(|| format!("Invalid page cursor {}", value))?;
+        Ok(Self {
+            fetch_time,
+            id: u64::from_be_bytes(bytes[8..].try_into().unwrap()),
+        })
+    }
+}
+
+/// One page of market history and the cursor of the next one, `None` on the last page
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketPage<T> {
+    pub rows: Vec<T>,
+    pub next: Option<MarketCursor>,
+}
+
+/// Append the keyset condition, ordering and limit of a page to a query whose `WHERE` clause
+/// is already open. One row more than `page_size` is read to tell whether another page follows.
+pub(super) fn push_page(
+    query: &mut QueryBuilder<'_, Db>,
+    after: Option<MarketCursor>,
+    order: SortOrder,
+    page_size: u32,
+) {
+    if let Some(after) = after {
+        // Expanded instead of a row comparison, which MySQL cannot serve from an index range
+        query
+            .push(format!(" AND (fetch_timestamp {} ", order.after()))
+            .push_bind(after.fetch_time)
+            .push(" OR (fetch_timestamp = ")
+            .push_bind(after.fetch_time)
+            .push(format!(" AND id {} ", order.after()))
+            .push_bind(after.id as i64)
+            .push("))");
+    }
+    query
+        .push(format!(
+            " ORDER BY fetch_timestamp {0}, id {0} LIMIT ",
+            order.sql()
+        ))
+        .push_bind(i64::from(page_size) + 1);
+}
+
+/// Page of the rows read by a [`push_page`] query, each with its own cursor
+pub(super) fn into_page<T>(mut rows: Vec<(T, MarketCursor)>, page_size: u32) -> MarketPage<T> {
+    let has_more = rows.len() > page_size as usize;
+    rows.truncate(page_size as usize);
+    let next = if has_more {
+        rows.last().map(|(_, cursor)| *cursor)
+    } else {
+        None
+    };
+    MarketPage {
+        rows: rows.into_iter().map(|(row, _)| row).collect(),
+        next,
+    }
+}
+
+#[cfg(test)]
+#[path = "pagination_tests.rs"]
+mod pagination_tests;
@@ -0,0 +1,101 @@
+use super::*;
+use crate::store::test_db::with_question_marks;
+
+fn cursor(micros: i64, id: u64) -> MarketCursor {
+    MarketCursor {
+        fetch_time: DateTime::from_timestamp_micros(micros).unwrap(),
+        id,
+    }
+}
+
+#[test]
+fn cursor_round_trips_through_its_opaque_form() {
+    let original = cursor(1_700_000_000_123_456, 42);
+
+    let encoded = original.encode();
+
+    assert_eq!(encoded, "00060a2418202240000000000000002a");
+    assert_eq!(MarketCursor::decode(&encoded), Ok(original));
+    assert_eq!(
+        MarketCursor::decode(&cursor(0, u64::MAX).encode()),
+        Ok(cursor(0, u64::MAX))
+    );
+}
+
+#[test]
+fn cursor_decode_rejects_foreign_strings() {
+    assert!(MarketCursor::decode("").is_err());
+    assert!(MarketCursor::decode("not-a-cursor").is_err());
+    // Valid hex of the wrong length
+    assert!(MarketCursor::decode("00060a2418202240").is_err());
+    assert!(MarketCursor::decode("00060a2418202240000000000000002a00").is_err());
+    // Microseconds beyond the range of a DateTime
+    assert!(MarketCursor::decode("7fffffffffffffff000000000000002a").is_err());
+}
+
+#[test]
+fn into_page_keeps_the_page_and_points_after_its_last_row() {
+    let rows = vec![
+        ("a", cursor(1, 1)),
+        ("b", cursor(1, 2)),
+        ("c", cursor(2, 3)),
+    ];
+
+    let page = into_page(rows, 2);
+
+    assert_eq!(
+        page,
+        MarketPage {
+            rows: vec!["a", "b"],
+            next: Some(cursor(1, 2)),
+        }
+    );
+}
+
+#[test]
+fn into_page_ends_when_no_extra_row_was_read() {
+    let full = into_page(vec![("a", cursor(1, 1)), ("b", cursor(1, 2))], 2);
+    let empty = into_page(Vec::<(&str, MarketCursor)>::new(), 2);
+
+    assert_eq!(full.rows, vec!["a", "b"]);
+    assert_eq!(full.next, None);
+    assert_eq!(
+        empty,
+        MarketPage {
+            rows: vec![],
+            next: None
+        }
+    );
+}
+
+#[test]
+fn push_page_orders_by_fetch_time_then_id() {
+    let mut first = QueryBuilder::<Db>::new("SELECT id FROM cex_markets WHERE 1 = 1");
+    push_page(&mut first, None, SortOrder::Ascending, 50);
+
+    assert_eq!(
+        with_question_marks(first.sql()),
+        "SELECT id FROM cex_markets WHERE 1 = 1 ORDER BY fetch_timestamp ASC, id ASC LIMIT ?"
+    );
+}
+
+#[test]
+fn push_page_continues_after_the_cursor_in_either_order() {
+    let mut descending = QueryBuilder::<Db>::new("SELECT id FROM cex_markets WHERE 1 = 1");
+    push_page(
+        &mut descending,
+        Some(cursor(1, 7)),
+        SortOrder::Descending,
+        50,
+    );
+    let mut ascending = QueryBuilder::<Db>::new("SELECT id FROM cex_markets WHERE 1 = 1");
+    push_page(&mut ascending, Some(cursor(1, 7)), SortOrder::Ascending, 50);
+
+    assert_eq!(
+        with_question_marks(descending.sql()),
+        "SELECT id FROM cex_markets WHERE 1 = 1 AND (fetch_timestamp < ? OR (fetch_timestamp = ? AND id < ?)) ORDER BY fetch_timestamp DESC, id DESC LIMIT ?"
+    );
+    assert!(with_question_marks(ascending.sql()).contains(
+        "AND (fetch_timestamp > ? OR (fetch_timestamp = ? AND id > ?)) ORDER BY fetch_timestamp ASC, id ASC"
+    ));
+}
@@ -0,0 +1,27 @@
+use crate::models::pool_fee::PoolFee;
+use crate::store::db::{DatabasePool, insert_returning_id, returning_id};
+
+/// Insert a pool fee rate record
+pub async fn insert_pool_fee(
+    pool: &DatabasePool,
+    fee: &PoolFee,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+            INSERT INTO dex_pool_fees (exchange, trade_pair, pool_address, block_number, base_fee_bps, variable_fee_bps, total_fee_bps, fetch_timestamp)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&fee.exchange)
+        .bind(&fee.trade_pair)
+        .bind(&fee.pool_address)
+        .bind(fee.block_number as i64) // Convert u64 to i64 for BIGINT
+        .bind(fee.base_fee_bps)
+        .bind(fee.variable_fee_bps)
+        .bind(fee.total_fee_bps)
+        .bind(fee.fetch_time);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
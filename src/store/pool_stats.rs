@@ -0,0 +1,28 @@
+use crate::models::pool_stats::PoolStats;
+use crate::store::db::{DatabasePool, insert_returning_id, returning_id};
+
+/// Insert a pool liquidity record
+pub async fn insert_pool_stats(
+    pool: &DatabasePool,
+    stats: &PoolStats,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+            INSERT INTO dex_pool_stats (exchange, trade_pair, pool_address, block_number, base_amount, quote_amount, liquidity, degraded, fetch_timestamp)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&stats.exchange)
+        .bind(&stats.trade_pair)
+        .bind(&stats.pool_address)
+        .bind(stats.block_number as i64) // Convert u64 to i64 for BIGINT
+        .bind(stats.base_amount)
+        .bind(stats.quote_amount)
+        .bind(stats.liquidity)
+        .bind(stats.degraded)
+        .bind(stats.fetch_time);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
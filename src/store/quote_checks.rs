@@ -0,0 +1,29 @@
+use crate::models::quote_check::QuoteCheck;
+use crate::store::db::{DatabasePool, insert_returning_id, returning_id};
+
+/// Insert a quote cross-check record
+pub async fn insert_quote_check(
+    pool: &DatabasePool,
+    check: &QuoteCheck,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let query = returning_id(
+        r#"
+            INSERT INTO dex_quote_checks (exchange, trade_pair, pool_address, block_number, amount_in, local_amount_out, simulated_amount_out, deviation_bps, check_timestamp)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    );
+
+    let insert = sqlx::query(&query)
+        .bind(&check.exchange)
+        .bind(&check.trade_pair)
+        .bind(&check.pool_address)
+        // Convert u64 to i64 for BIGINT, which Postgres has no unsigned form of
+        .bind(check.block_number as i64)
+        .bind(check.amount_in as i64)
+        .bind(check.local_amount_out as i64)
+        .bind(check.simulated_amount_out as i64)
+        .bind(check.deviation_bps)
+        .bind(check.check_time);
+
+    Ok(insert_returning_id(pool, insert).await?)
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{FromRow, QueryBuilder};
+
+use crate::models::spread::Spread;
+use crate::store::db::{DatabasePool, Db, Unsigned, sql};
+
+/// Columns of `spreads` read back into a [`Spread`]
+const SPREAD_COLUMNS: &str = "trade_pair, cex_exchange, dex_exchange, cex_bid, cex_ask, dex_bid, dex_ask, spread_buy_dex_sell_cex_bps, spread_buy_cex_sell_dex_bps, cex_age_ms, dex_age_ms, computed_at";
+
+/// `spreads` row read with [`SPREAD_COLUMNS`]
+#[derive(Debug, FromRow)]
+struct SpreadRow {
+    trade_pair: String,
+    cex_exchange: String,
+    dex_exchange: String,
+    cex_bid: Decimal,
+    cex_ask: Decimal,
+    dex_bid: Decimal,
+    dex_ask: Decimal,
+    spread_buy_dex_sell_cex_bps: Decimal,
+    spread_buy_cex_sell_dex_bps: Decimal,
+    #[sqlx(try_from = "Unsigned")]
+    cex_age_ms: u64,
+    #[sqlx(try_from = "Unsigned")]
+    dex_age_ms: u64,
+    computed_at: DateTime<Utc>,
+}
+
+impl From<SpreadRow> for Spread {
+    fn from(row: SpreadRow) -> Self {
+        Self {
+            trade_pair: row.trade_pair,
+            cex_exchange: row.cex_exchange,
+            dex_exchange: row.dex_exchange,
+            cex_bid: row.cex_bid,
+            cex_ask: row.cex_ask,
+            dex_bid: row.dex_bid,
+            dex_ask: row.dex_ask,
+            spread_buy_dex_sell_cex_bps: row.spread_buy_dex_sell_cex_bps,
+            spread_buy_cex_sell_dex_bps: row.spread_buy_cex_sell_dex_bps,
+            cex_age_ms: row.cex_age_ms,
+            dex_age_ms: row.dex_age_ms,
+            computed_at: row.computed_at,
+        }
+    }
+}
+
+/// Insert the spreads of one tick in one multi-row statement
+pub async fn insert_spreads(
+    pool: &DatabasePool,
+    spreads: &[Spread],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    if spreads.is_empty() {
+        return Ok(0);
+    }
+    let result = spreads_insert_query(spreads).build().execute(pool).await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Multi-row insert of `spreads` into `spreads`
+fn spreads_insert_query(spreads: &[Spread]) -> QueryBuilder<'_, Db> {
+    let mut query = QueryBuilder::new(format!("INSERT INTO spreads ({}) ", SPREAD_COLUMNS));
+    query.push_values(spreads, |mut row, spread| {
+        row.push_bind(&spread.trade_pair)
+            .push_bind(&spread.cex_exchange)
+            .push_bind(&spread.dex_exchange)
+            .push_bind(spread.cex_bid)
+            .push_bind(spread.cex_ask)
+            .push_bind(spread.dex_bid)
+            .push_bind(spread.dex_ask)
+            .push_bind(spread.spread_buy_dex_sell_cex_bps)
+            .push_bind(spread.spread_buy_cex_sell_dex_bps)
+            .push_bind(spread.cex_age_ms as i64) // Convert u64 to i64 for BIGINT
+            .push_bind(spread.dex_age_ms as i64)
+            .push_bind(spread.computed_at);
+    });
+    query
+}
+
+/// Get the spreads of a pair computed from `from` (inclusive) to `to` (exclusive), oldest first
+pub async fn get_spreads(
+    pool: &DatabasePool,
+    trade_pair: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Spread>, Box<dyn std::error::Error>> {
+    let query = format!(
+        "SELECT {} FROM spreads WHERE trade_pair = ? AND computed_at >= ? AND computed_at < ? ORDER BY computed_at, id",
+        SPREAD_COLUMNS
+    );
+
+    let rows = sqlx::query_as::<_, SpreadRow>(&sql(&query))
+        .bind(trade_pair)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(Spread::from).collect())
+}
+
+#[cfg(test)]
+#[path = "spreads_tests.rs"]
+mod spreads_tests;
@@ -0,0 +1,55 @@
+use super::*;
+use chrono::TimeZone;
+use rust_decimal::Decimal;
+
+use crate::store::test_db::{test_pool, unique_exchange};
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+}
+
+fn spread(trade_pair: &str, secs: i64) -> Spread {
+    Spread {
+        trade_pair: trade_pair.to_string(),
+        cex_exchange: "bybit".to_string(),
+        dex_exchange: "meteora".to_string(),
+        cex_bid: "10.10".parse().unwrap(),
+        cex_ask: "10.12".parse().unwrap(),
+        dex_bid: Decimal::TEN,
+        dex_ask: "10.02".parse().unwrap(),
+        spread_buy_dex_sell_cex_bps: "79.8403".parse().unwrap(),
+        spread_buy_cex_sell_dex_bps: "-118.5771".parse().unwrap(),
+        cex_age_ms: 500,
+        dex_age_ms: 2000,
+        computed_at: at(secs),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn inserting_no_spreads_does_not_touch_the_database() {
+    let pool = crate::store::db::lazy_pool();
+
+    assert_eq!(insert_spreads(&pool, &[]).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn spreads_round_trip_through_insert_and_select() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let trade_pair = unique_exchange("SPREAD");
+    let spreads = [
+        spread(&trade_pair, 10),
+        spread(&trade_pair, 0),
+        spread(&trade_pair, 60),
+    ];
+
+    assert_eq!(insert_spreads(&pool, &spreads).await.unwrap(), 3);
+
+    assert_eq!(
+        get_spreads(&pool, &trade_pair, at(0), at(60))
+            .await
+            .unwrap(),
+        vec![spreads[1].clone(), spreads[0].clone()]
+    );
+}
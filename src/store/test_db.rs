@@ -0,0 +1,84 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing::subscriber::DefaultGuard;
+
+use crate::store::db::{DatabasePool, run_migrations};
+
+/// Pool on the `TEST_DATABASE_URL` database, MySQL or Postgres like the build, with every
+/// migration applied, or `None` when the variable is unset so that tests needing a database
+/// are skipped
+pub(crate) async fn test_pool() -> Option<DatabasePool> {
+    let url = std::env::var("TEST_DATABASE_URL")
+        .ok()
+        .filter(|url| !url.is_empty())?;
+    let pool = DatabasePool::connect(&url)
+        .await
+        .expect("Failed to connect to TEST_DATABASE_URL");
+    run_migrations(&pool)
+        .await
+        .expect("Failed to migrate the test database");
+    Some(pool)
+}
+
+/// Exchange name no other test run writes, so tests sharing the database only see their rows
+pub(crate) fn unique_exchange(prefix: &str) -> String {
+    format!("{}_{:08x}", prefix, rand::random::<u32>())
+}
+
+/// `query` built for the backend with its placeholders written `?` like on MySQL, so that
+/// assertions on generated SQL hold on both backends
+pub(crate) fn with_question_marks(query: &str) -> String {
+    let mut converted = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' && chars.peek().is_some_and(char::is_ascii_digit) {
+            while chars.peek().is_some_and(char::is_ascii_digit) {
+                chars.next();
+            }
+            converted.push('?');
+        } else {
+            converted.push(c);
+        }
+    }
+    converted
+}
+
+/// Warnings and errors logged on the current thread while it is alive. `#[tokio::test]` runs
+/// on a single thread, so this sees the logs of the test's futures.
+pub(crate) struct CapturedLogs {
+    buffer: LogBuffer,
+    _guard: DefaultGuard,
+}
+
+impl CapturedLogs {
+    pub(crate) fn start() -> Self {
+        let buffer = LogBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::WARN)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        Self {
+            buffer,
+            _guard: tracing::subscriber::set_default(subscriber),
+        }
+    }
+
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.0.lock().unwrap()).into_owned()
+    }
+}
+
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
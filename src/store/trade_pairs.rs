@@ -0,0 +1,37 @@
+use sqlx::Row;
+
+use crate::models::trade_pair::TradePair;
+use crate::store::db::{DatabasePool, get_unsigned, sql};
+
+/// Get all enabled trade pairs for a venue
+pub async fn get_enabled_pairs(
+    pool: &DatabasePool,
+    venue: &str,
+) -> Result<Vec<TradePair>, Box<dyn std::error::Error>> {
+    let query = "SELECT id, symbol, venue, pool_pubkey, `precision`, base_is_x, bin_array_count, base_mint, quote_mint, auto_discover, route, route_pool_pubkey, route_base_is_x, enabled, created_at FROM trade_pairs WHERE venue = ? AND enabled = TRUE ORDER BY symbol, id";
+
+    let rows = sqlx::query(&sql(query)).bind(venue).fetch_all(pool).await?;
+
+    let mut trade_pairs = Vec::new();
+    for row in rows {
+        trade_pairs.push(TradePair {
+            id: row.get("id"),
+            symbol: row.get("symbol"),
+            venue: row.get("venue"),
+            pool_pubkey: row.get("pool_pubkey"),
+            precision: get_unsigned(&row, "precision") as u32,
+            base_is_x: row.get("base_is_x"),
+            bin_array_count: get_unsigned(&row, "bin_array_count") as u8,
+            base_mint: row.get("base_mint"),
+            quote_mint: row.get("quote_mint"),
+            auto_discover: row.get("auto_discover"),
+            route: row.get("route"),
+            route_pool_pubkey: row.get("route_pool_pubkey"),
+            route_base_is_x: row.get("route_base_is_x"),
+            enabled: row.get("enabled"),
+            created_at: row.get("created_at"),
+        });
+    }
+
+    Ok(trade_pairs)
+}
@@ -0,0 +1,57 @@
+//! Transactions spanning several store writes.
+//!
+//! [`with_transaction`] runs a closure on an open transaction and commits it when the closure
+//! succeeds, so that related rows are either all saved or none is. The `_tx` variants of the
+//! insert functions take the `&mut DbConnection` a transaction derefs to.
+
+use sqlx::Transaction;
+use tracing::warn;
+
+use crate::store::db::{DatabasePool, Db};
+
+tokio::task_local! {
+    /// Set while a `with_transaction` closure runs on the current task
+    static IN_TRANSACTION: ();
+}
+
+/// Error returned when `with_transaction` is called from within another transaction's closure
+pub const NESTED_TRANSACTION_ERROR: &str =
+    "with_transaction called inside another transaction: pass its `tx` down instead";
+
+/// Begin a transaction, run `f` on it, then commit when `f` returns `Ok` and roll back when it
+/// returns `Err`, which is returned as is. Writes made through `tx` are only visible to other
+/// connections once committed.
+///
+/// Transactions do not nest: calling it again from within `f` fails with
+/// [`NESTED_TRANSACTION_ERROR`] without touching the database, since the inner transaction
+/// would run on another connection and commit independently of the outer one.
+pub async fn with_transaction<T, F>(
+    pool: &DatabasePool,
+    f: F,
+) -> Result<T, Box<dyn std::error::Error>>
+where
+    F: AsyncFnOnce(&mut Transaction<'static, Db>) -> Result<T, Box<dyn std::error::Error>>,
+{
+    if IN_TRANSACTION.try_with(|_| ()).is_ok() {
+        return Err(NESTED_TRANSACTION_ERROR.into());
+    }
+
+    let mut tx = pool.begin().await?;
+    match IN_TRANSACTION.scope((), f(&mut tx)).await {
+        Ok(value) => {
+            tx.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback) = tx.rollback().await {
+                // The connection is closed instead of returned to the pool, discarding the writes
+                warn!("Failed to roll back transaction after {}: {}", e, rollback);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tx_tests.rs"]
+mod tx_tests;
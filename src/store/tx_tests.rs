@@ -0,0 +1,128 @@
+use super::*;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::models::market::{CEXState, DEXState};
+use crate::store::markets::{
+    CexMarketFilter, DexMarketFilter, get_cex_markets, get_dex_markets, insert_cex_market_tx,
+    insert_dex_market_tx,
+};
+use crate::store::test_db::{test_pool, unique_exchange};
+
+fn at(secs: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(1_760_000_000 + secs, 0).unwrap()
+}
+
+fn cex_state(exchange: &str) -> CEXState {
+    CEXState {
+        trade_id: "TRUMPUSDC:0".to_string(),
+        exchange: exchange.to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        bid_price: Decimal::TEN,
+        bid_volume: Decimal::ONE,
+        ask_price: Decimal::from(11),
+        ask_volume: Decimal::TWO,
+        trade_time: at(0),
+        fetch_time: at(0),
+        feed_latency_ms: None,
+        depth: None,
+    }
+}
+
+fn dex_state(exchange: &str) -> DEXState {
+    DEXState {
+        trade_id: "TRUMPUSDC:0:sell".to_string(),
+        exchange: exchange.to_string(),
+        trade_pair: "TRUMPUSDC".to_string(),
+        direction: "sell".to_string(),
+        price: Decimal::from(12),
+        volume: Decimal::TEN,
+        trade_time: at(0),
+        fetch_time: at(0),
+        block_number: 321_000_000,
+        price_impact_bps: None,
+        pool_address: None,
+        stale: false,
+        fetch_latency_ms: None,
+        route: None,
+        route_plan: None,
+    }
+}
+
+/// Number of CEX and DEX market rows of `exchange`
+async fn market_rows(pool: &DatabasePool, exchange: &str) -> (usize, usize) {
+    let cex = get_cex_markets(
+        pool,
+        &CexMarketFilter {
+            exchange: Some(exchange.to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let dex = get_dex_markets(
+        pool,
+        &DexMarketFilter {
+            exchange: Some(exchange.to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    (cex.len(), dex.len())
+}
+
+#[tokio::test]
+async fn with_transaction_commits_every_write_on_success() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("tx_commit");
+
+    let ids = with_transaction(&pool, async |tx| {
+        let cex_id = insert_cex_market_tx(tx, &cex_state(&exchange)).await?;
+        let dex_id = insert_dex_market_tx(tx, &dex_state(&exchange)).await?;
+        Ok((cex_id, dex_id))
+    })
+    .await
+    .unwrap();
+
+    assert!(ids.0 > 0 && ids.1 > 0);
+    assert_eq!(market_rows(&pool, &exchange).await, (1, 1));
+}
+
+#[tokio::test]
+async fn with_transaction_rolls_back_partial_writes_on_error() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("tx_rollback");
+
+    let result: Result<(), _> = with_transaction(&pool, async |tx| {
+        insert_cex_market_tx(tx, &cex_state(&exchange)).await?;
+        insert_dex_market_tx(tx, &dex_state(&exchange)).await?;
+        Err("opportunity rejected".into())
+    })
+    .await;
+
+    assert_eq!(result.unwrap_err().to_string(), "opportunity rejected");
+    assert_eq!(market_rows(&pool, &exchange).await, (0, 0));
+}
+
+#[tokio::test]
+async fn with_transaction_rejects_nested_transactions() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let exchange = unique_exchange("tx_nested");
+
+    let result: Result<(), _> = with_transaction(&pool, async |tx| {
+        insert_cex_market_tx(tx, &cex_state(&exchange)).await?;
+        with_transaction(&pool, async |_| Ok(())).await
+    })
+    .await;
+
+    assert_eq!(result.unwrap_err().to_string(), NESTED_TRANSACTION_ERROR);
+    // The outer transaction fails with the inner error, so its write is rolled back too
+    assert_eq!(market_rows(&pool, &exchange).await, (0, 0));
+}
@@ -0,0 +1,604 @@
+//! Write-behind buffer for market states the database failed to store.
+//!
+//! A batch whose insert fails waits in a bounded in-memory queue, continued in a JSON lines
+//! spill file when one is configured, and a background task retries the oldest states with
+//! exponential backoff. Once the database is back the buffer is drained oldest first through
+//! the batch-insert API. New states queue behind the buffered ones until it is empty, so rows
+//! are written in the order they were submitted.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::sync::Notify;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::{error, info, warn};
+
+use crate::models::market::{CEXState, DEXState};
+use crate::store::db::DatabasePool;
+use crate::store::markets::{insert_cex_markets_batch, insert_dex_markets_batch};
+
+/// States buffered in memory when `WRITE_BUFFER_CAPACITY` is unset
+const DEFAULT_CAPACITY: usize = 100_000;
+/// First retry delay when `WRITE_BUFFER_RETRY_MIN_MS` is unset
+const DEFAULT_RETRY_MIN_MS: u64 = 500;
+/// Longest retry delay when `WRITE_BUFFER_RETRY_MAX_MS` is unset
+const DEFAULT_RETRY_MAX_MS: u64 = 30_000;
+/// States written per retry, the row count of one multi-row insert
+const DRAIN_BATCH_SIZE: usize = 1000;
+
+/// Destination of the buffered states
+pub trait StateSink<T>: Send + Sync + 'static {
+    fn write_batch(
+        &self,
+        states: &[T],
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send;
+}
+
+impl StateSink<CEXState> for DatabasePool {
+    async fn write_batch(&self, states: &[CEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        insert_cex_markets_batch(self, states).await.map(|_| ())
+    }
+}
+
+impl StateSink<DEXState> for DatabasePool {
+    async fn write_batch(&self, states: &[DEXState]) -> Result<(), Box<dyn std::error::Error>> {
+        insert_dex_markets_batch(self, states).await.map(|_| ())
+    }
+}
+
+/// State given up when a state arrives at a full buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Make room by dropping the oldest buffered state
+    #[default]
+    Oldest,
+    /// Keep the buffer as is and drop the arriving state
+    Newest,
+}
+
+impl DropPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "oldest" => Some(Self::Oldest),
+            "newest" => Some(Self::Newest),
+            _ => None,
+        }
+    }
+}
+
+/// Capacity, spill file, drop policy and retry backoff of a `WriteBuffer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteBufferConfig {
+    /// Name of the buffer in logs and metrics
+    pub name: String,
+    /// States kept in memory
+    pub capacity: usize,
+    /// JSON lines file taking the states that do not fit in memory, `None` to drop them.
+    /// States left in it are written when a buffer on the same file starts.
+    pub spill_path: Option<PathBuf>,
+    /// What to drop when the buffer is full and cannot spill
+    pub drop_policy: DropPolicy,
+    /// Delay before the first retry, doubled after each failed one
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl WriteBufferConfig {
+    /// Read `WRITE_BUFFER_CAPACITY`, `WRITE_BUFFER_DROP_POLICY` (`oldest` or `newest`),
+    /// `WRITE_BUFFER_RETRY_MIN_MS`, `WRITE_BUFFER_RETRY_MAX_MS` and `WRITE_BUFFER_SPILL_DIR`,
+    /// where the buffer spills to `<name>.jsonl`, falling back to the defaults
+    pub fn from_env(name: &str) -> Self {
+        let env_u64 = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default)
+        };
+        let min_backoff_ms = env_u64("WRITE_BUFFER_RETRY_MIN_MS", DEFAULT_RETRY_MIN_MS);
+        let max_backoff_ms = env_u64("WRITE_BUFFER_RETRY_MAX_MS", DEFAULT_RETRY_MAX_MS);
+        Self {
+            name: name.to_string(),
+            capacity: env_u64("WRITE_BUFFER_CAPACITY", DEFAULT_CAPACITY as u64) as usize,
+            spill_path: std::env::var("WRITE_BUFFER_SPILL_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| PathBuf::from(dir).join(format!("{}.jsonl", name))),
+            drop_policy: std::env::var("WRITE_BUFFER_DROP_POLICY")
+                .ok()
+                .and_then(|value| DropPolicy::parse(&value.to_lowercase()))
+                .unwrap_or_default(),
+            min_backoff: Duration::from_millis(min_backoff_ms),
+            max_backoff: Duration::from_millis(max_backoff_ms.max(min_backoff_ms)),
+        }
+    }
+}
+
+/// Spilled states not read back yet: the lines of the file after `offset`
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    pending: u64,
+    offset: u64,
+}
+
+impl SpillFile {
+    /// Spill file at `path`, with the lines a previous buffer left in it pending
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let pending = match File::open(&path) {
+            Ok(file) => BufReader::new(file).lines().count() as u64,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            pending,
+            offset: 0,
+        })
+    }
+
+    fn append<T: Serialize>(&mut self, state: &T) -> Result<(), Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_string(state)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Read back up to `max` states, oldest first, along with the number of lines that could
+    /// not be parsed. The file is emptied once every line was read.
+    fn read<T: DeserializeOwned>(&mut self, max: usize) -> std::io::Result<(Vec<T>, u64)> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file);
+        let mut states = Vec::new();
+        let mut unreadable = 0;
+        let mut line = String::new();
+        while states.len() < max && self.pending > 0 {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                // Fewer lines than counted, e.g. the file was edited by hand
+                self.pending = 0;
+                break;
+            }
+            self.offset += read as u64;
+            self.pending -= 1;
+            match serde_json::from_str(line.trim_end()) {
+                Ok(state) => states.push(state),
+                Err(_) => unreadable += 1,
+            }
+        }
+        if self.pending == 0 {
+            File::create(&self.path)?;
+            self.offset = 0;
+        }
+        Ok((states, unreadable))
+    }
+
+    /// Put `states`, older than every spilled one, at the start of the file
+    fn prepend<T: Serialize>(
+        &mut self,
+        states: &VecDeque<T>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut contents = String::new();
+        for state in states {
+            contents.push_str(&serde_json::to_string(state)?);
+            contents.push('\n');
+        }
+        if self.pending > 0 {
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(self.offset))?;
+            file.read_to_string(&mut contents)?;
+        }
+        let staging = self.path.with_extension("jsonl.tmp");
+        fs::write(&staging, contents)?;
+        fs::rename(&staging, &self.path)?;
+        self.pending += states.len() as u64;
+        self.offset = 0;
+        Ok(())
+    }
+}
+
+/// Buffered states, oldest first
+#[derive(Debug)]
+struct Queue<T> {
+    states: VecDeque<T>,
+    /// States taken by the drain task and being written, counted against the capacity
+    in_flight: usize,
+    spill: Option<SpillFile>,
+    /// States buffered and dropped since the buffer was last empty
+    outage_buffered: u64,
+    outage_dropped: u64,
+}
+
+impl<T> Queue<T> {
+    fn len(&self) -> usize {
+        let spilled = self
+            .spill
+            .as_ref()
+            .map_or(0, |spill| spill.pending as usize);
+        self.states.len() + self.in_flight + spilled
+    }
+}
+
+/// Queue shared with the drain task, and counts over the buffer's lifetime
+struct Shared<T> {
+    name: String,
+    capacity: usize,
+    drop_policy: DropPolicy,
+    queue: Mutex<Queue<T>>,
+    wake: Notify,
+    buffered: AtomicU64,
+    dropped: AtomicU64,
+    drained: AtomicU64,
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Shared<T> {
+    /// Queue `states` behind the buffered ones, spilling or dropping what does not fit
+    fn push(&self, states: &[T]) {
+        let mut queue = self.queue.lock().unwrap();
+        let mut buffered = 0;
+        let mut dropped = 0;
+        for state in states {
+            if self.spill(&mut queue, state) {
+                buffered += 1;
+            } else if queue.states.len() + queue.in_flight < self.capacity {
+                queue.states.push_back(state.clone());
+                buffered += 1;
+            } else {
+                // Without states in memory, the batch being retried fills the buffer
+                if self.drop_policy == DropPolicy::Oldest && queue.states.pop_front().is_some() {
+                    queue.states.push_back(state.clone());
+                    buffered += 1;
+                }
+                dropped += 1;
+            }
+        }
+        queue.outage_buffered += buffered;
+        self.buffered.fetch_add(buffered, Ordering::Relaxed);
+        metrics::counter!("write_buffer_states_buffered_total", "buffer" => self.name.clone())
+            .increment(buffered);
+        if dropped > 0 {
+            if queue.outage_dropped == 0 {
+                warn!(
+                    "Write buffer {} is full ({} states), dropping the {} states",
+                    self.name,
+                    self.capacity,
+                    match self.drop_policy {
+                        DropPolicy::Oldest => "oldest",
+                        DropPolicy::Newest => "newest",
+                    }
+                );
+            }
+            self.count_drops(&mut queue, dropped);
+        }
+    }
+
+    /// Append `state` to the spill file when the spilled states are not all read back yet
+    /// or the memory is full. Returns whether it was spilled.
+    fn spill(&self, queue: &mut Queue<T>, state: &T) -> bool {
+        let full = queue.states.len() + queue.in_flight >= self.capacity;
+        let Some(spill) = queue.spill.as_mut() else {
+            return false;
+        };
+        if spill.pending == 0 && !full {
+            return false;
+        }
+        match spill.append(state) {
+            Ok(()) => true,
+            Err(e) => {
+                error!(
+                    "Write buffer {} failed to spill to {}: {}",
+                    self.name,
+                    spill.path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    /// Take the oldest states to retry, reading spilled ones back once the memory is empty
+    fn take_batch(&self) -> Option<Vec<T>> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.states.is_empty() {
+            self.read_spill(&mut queue);
+        }
+        if queue.states.is_empty() {
+            return None;
+        }
+        let count = queue.states.len().min(DRAIN_BATCH_SIZE);
+        queue.in_flight = count;
+        Some(queue.states.drain(..count).collect())
+    }
+
+    fn read_spill(&self, queue: &mut Queue<T>) {
+        let Some(spill) = queue.spill.as_mut() else {
+            return;
+        };
+        if spill.pending == 0 {
+            return;
+        }
+        match spill.read(self.capacity) {
+            Ok((states, unreadable)) => {
+                queue.states.extend(states);
+                if unreadable > 0 {
+                    warn!(
+                        "Write buffer {} dropped {} unreadable lines of {}",
+                        self.name,
+                        unreadable,
+                        queue.spill.as_ref().unwrap().path.display()
+                    );
+                    self.count_drops(queue, unreadable);
+                }
+            }
+            Err(e) => error!(
+                "Write buffer {} failed to read {}: {}",
+                self.name,
+                spill.path.display(),
+                e
+            ),
+        }
+    }
+
+    /// Put back a batch that failed to write, ahead of the states queued meanwhile
+    fn restore(&self, batch: Vec<T>) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.in_flight = 0;
+        for state in batch.into_iter().rev() {
+            queue.states.push_front(state);
+        }
+    }
+
+    /// Count a written batch, logging the outage totals once the buffer is empty
+    fn finish_batch(&self, written: usize) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.in_flight = 0;
+        self.drained.fetch_add(written as u64, Ordering::Relaxed);
+        metrics::counter!("write_buffer_states_drained_total", "buffer" => self.name.clone())
+            .increment(written as u64);
+        if queue.len() == 0 {
+            info!(
+                "Write buffer {} drained: {} states buffered, {} dropped",
+                self.name, queue.outage_buffered, queue.outage_dropped
+            );
+            queue.outage_buffered = 0;
+            queue.outage_dropped = 0;
+        }
+    }
+
+    fn count_drops(&self, queue: &mut Queue<T>, dropped: u64) {
+        queue.outage_dropped += dropped;
+        self.dropped.fetch_add(dropped, Ordering::Relaxed);
+        metrics::counter!("write_buffer_states_dropped_total", "buffer" => self.name.clone())
+            .increment(dropped);
+    }
+
+    /// Keep the states not written yet in the spill file for the next start, or report
+    /// them lost
+    fn shut_down(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.states.is_empty() {
+            return;
+        }
+        let states = std::mem::take(&mut queue.states);
+        let kept = match queue.spill.as_mut() {
+            Some(spill) => match spill.prepend(&states) {
+                Ok(()) => true,
+                Err(e) => {
+                    error!(
+                        "Write buffer {} failed to spill to {}: {}",
+                        self.name,
+                        spill.path.display(),
+                        e
+                    );
+                    false
+                }
+            },
+            None => false,
+        };
+        if kept {
+            info!(
+                "Write buffer {} stopped, {} states left in its spill file",
+                self.name,
+                queue.len()
+            );
+        } else {
+            warn!(
+                "Write buffer {} stopped, {} buffered states not written",
+                self.name,
+                states.len()
+            );
+            self.count_drops(&mut queue, states.len() as u64);
+        }
+    }
+}
+
+/// Sink wrapper that keeps the states it failed to write and retries them in the background
+/// until they are written, see the module documentation
+pub struct WriteBuffer<T, S> {
+    sink: Arc<S>,
+    shared: Arc<Shared<T>>,
+    /// Stops the drain task when the buffer is dropped
+    _stop: DropGuard,
+}
+
+impl<T, S> WriteBuffer<T, S>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    S: StateSink<T>,
+{
+    /// Buffer writes to `sink` and spawn the drain task, which starts with the states left in
+    /// the spill file. When the buffer is dropped, the states still buffered in memory move to
+    /// the spill file.
+    pub fn spawn(sink: S, config: WriteBufferConfig) -> Self {
+        let spill = config
+            .spill_path
+            .and_then(|path| match SpillFile::open(path.clone()) {
+                Ok(spill) => Some(spill),
+                Err(e) => {
+                    error!(
+                        "Write buffer {} cannot use spill file {}: {}",
+                        config.name,
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            });
+        if let Some(spill) = spill.as_ref().filter(|spill| spill.pending > 0) {
+            info!(
+                "Write buffer {} found {} states in {}",
+                config.name,
+                spill.pending,
+                spill.path.display()
+            );
+        }
+        let shared = Arc::new(Shared {
+            name: config.name,
+            capacity: config.capacity,
+            drop_policy: config.drop_policy,
+            queue: Mutex::new(Queue {
+                states: VecDeque::new(),
+                in_flight: 0,
+                spill,
+                outage_buffered: 0,
+                outage_dropped: 0,
+            }),
+            wake: Notify::new(),
+            buffered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            drained: AtomicU64::new(0),
+        });
+        let sink = Arc::new(sink);
+        let stop = CancellationToken::new();
+        tokio::spawn(run_drain(
+            sink.clone(),
+            shared.clone(),
+            config.min_backoff,
+            config.max_backoff,
+            stop.clone(),
+        ));
+
+        Self {
+            sink,
+            shared,
+            _stop: stop.drop_guard(),
+        }
+    }
+
+    /// Write `states` to the sink, or buffer them when it fails or older states are still
+    /// buffered. Only a full buffer loses states, as counted by [`WriteBuffer::dropped`].
+    pub async fn write(&self, states: &[T]) {
+        if states.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            let written = self
+                .sink
+                .write_batch(states)
+                .await
+                .map_err(|e| e.to_string());
+            match written {
+                Ok(()) => return,
+                Err(e) => warn!(
+                    "Write buffer {}: failed to write {} states, buffering them: {}",
+                    self.shared.name,
+                    states.len(),
+                    e
+                ),
+            }
+        }
+        self.shared.push(states);
+        self.shared.wake.notify_one();
+    }
+
+    /// States waiting to be written, in memory and in the spill file
+    pub fn len(&self) -> usize {
+        self.shared.queue.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// States buffered after a failed write since the buffer started
+    pub fn buffered(&self) -> u64 {
+        self.shared.buffered.load(Ordering::Relaxed)
+    }
+
+    /// States given up because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Buffered states written by the drain task
+    pub fn drained(&self) -> u64 {
+        self.shared.drained.load(Ordering::Relaxed)
+    }
+}
+
+/// Retry the buffered states oldest first, backing off while the sink fails, until `stop`
+async fn run_drain<T, S>(
+    sink: Arc<S>,
+    shared: Arc<Shared<T>>,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    stop: CancellationToken,
+) where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    S: StateSink<T>,
+{
+    let mut backoff = min_backoff;
+    loop {
+        let Some(batch) = shared.take_batch() else {
+            // States queued by the last writes are tried once more before stopping
+            tokio::select! {
+                biased;
+                _ = shared.wake.notified() => {}
+                _ = stop.cancelled() => break,
+            }
+            continue;
+        };
+        let written = sink.write_batch(&batch).await.map_err(|e| e.to_string());
+        match written {
+            Ok(()) => {
+                shared.finish_batch(batch.len());
+                backoff = min_backoff;
+            }
+            Err(e) => {
+                shared.restore(batch);
+                warn!(
+                    "Write buffer {}: retry failed, {} states buffered, next retry in {:?}: {}",
+                    shared.name,
+                    shared.queue.lock().unwrap().len(),
+                    backoff,
+                    e
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = stop.cancelled() => break,
+                }
+                backoff = (backoff * 2).min(max_backoff);
+            }
+        }
+    }
+    shared.shut_down();
+}
+
+#[cfg(test)]
+#[path = "write_buffer_tests.rs"]
+mod write_buffer_tests;
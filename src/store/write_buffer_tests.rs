@@ -0,0 +1,196 @@
+use super::*;
+use serde::Deserialize;
+use std::sync::atomic::AtomicBool;
+
+/// Buffered record numbered in submission order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Row(u32);
+
+/// Fake sink recording every written batch, failing the writes while `failing` is set
+#[derive(Clone, Default)]
+struct MockSink {
+    batches: Arc<Mutex<Vec<Vec<Row>>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl MockSink {
+    fn failing() -> Self {
+        let sink = Self::default();
+        sink.failing.store(true, Ordering::SeqCst);
+        sink
+    }
+
+    fn recover(&self) {
+        self.failing.store(false, Ordering::SeqCst);
+    }
+
+    /// Every written row, in write order
+    fn written(&self) -> Vec<u32> {
+        self.batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(|row| row.0)
+            .collect()
+    }
+}
+
+impl StateSink<Row> for MockSink {
+    async fn write_batch(&self, states: &[Row]) -> Result<(), Box<dyn std::error::Error>> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("database unavailable".into());
+        }
+        self.batches.lock().unwrap().push(states.to_vec());
+        Ok(())
+    }
+}
+
+fn config(capacity: usize, drop_policy: DropPolicy) -> WriteBufferConfig {
+    WriteBufferConfig {
+        name: "test".to_string(),
+        capacity,
+        spill_path: None,
+        drop_policy,
+        min_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(1),
+    }
+}
+
+fn rows(range: std::ops::Range<u32>) -> Vec<Row> {
+    range.map(Row).collect()
+}
+
+/// Spill file no other test uses
+fn spill_path() -> PathBuf {
+    std::env::temp_dir().join(format!("write_buffer_{:08x}.jsonl", rand::random::<u32>()))
+}
+
+/// Let the drain task retry until the buffer is empty, with the clock paused
+async fn wait_drained(buffer: &WriteBuffer<Row, MockSink>) {
+    for _ in 0..100 {
+        if buffer.is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} states still buffered", buffer.len());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn writes_go_straight_to_a_healthy_sink() {
+    let sink = MockSink::default();
+    let buffer = WriteBuffer::spawn(sink.clone(), config(10, DropPolicy::Oldest));
+
+    buffer.write(&rows(0..3)).await;
+
+    assert_eq!(sink.written(), [0, 1, 2]);
+    assert_eq!(buffer.buffered(), 0);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn failed_writes_are_drained_in_order_once_the_sink_recovers() {
+    let sink = MockSink::failing();
+    let buffer = WriteBuffer::spawn(sink.clone(), config(10, DropPolicy::Oldest));
+
+    buffer.write(&rows(0..3)).await;
+    buffer.write(&rows(3..7)).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(buffer.len(), 7);
+    sink.recover();
+    // Written behind the buffered states even though the sink is back
+    buffer.write(&rows(7..10)).await;
+    wait_drained(&buffer).await;
+
+    assert_eq!(sink.written(), (0..10).collect::<Vec<_>>());
+    assert_eq!(
+        (buffer.buffered(), buffer.dropped(), buffer.drained()),
+        (10, 0, 10)
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn full_buffer_drops_the_oldest_states() {
+    let sink = MockSink::failing();
+    let buffer = WriteBuffer::spawn(sink.clone(), config(4, DropPolicy::Oldest));
+
+    buffer.write(&rows(0..6)).await;
+    sink.recover();
+    wait_drained(&buffer).await;
+
+    assert_eq!(sink.written(), [2, 3, 4, 5]);
+    assert_eq!(buffer.dropped(), 2);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn full_buffer_drops_the_newest_states() {
+    let sink = MockSink::failing();
+    let buffer = WriteBuffer::spawn(sink.clone(), config(4, DropPolicy::Newest));
+
+    buffer.write(&rows(0..6)).await;
+    sink.recover();
+    wait_drained(&buffer).await;
+
+    assert_eq!(sink.written(), [0, 1, 2, 3]);
+    assert_eq!(buffer.dropped(), 2);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn states_beyond_the_capacity_spill_to_disk_and_are_drained_after_memory() {
+    let path = spill_path();
+    let sink = MockSink::failing();
+    let buffer = WriteBuffer::spawn(
+        sink.clone(),
+        WriteBufferConfig {
+            spill_path: Some(path.clone()),
+            ..config(3, DropPolicy::Newest)
+        },
+    );
+
+    buffer.write(&rows(0..5)).await;
+    buffer.write(&rows(5..8)).await;
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 5);
+    sink.recover();
+    wait_drained(&buffer).await;
+
+    assert_eq!(sink.written(), (0..8).collect::<Vec<_>>());
+    assert_eq!(buffer.dropped(), 0);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    fs::remove_file(path).unwrap();
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn states_left_in_memory_are_spilled_on_drop_and_written_on_the_next_start() {
+    let path = spill_path();
+    let spilling = |capacity| WriteBufferConfig {
+        spill_path: Some(path.clone()),
+        ..config(capacity, DropPolicy::Newest)
+    };
+    let sink = MockSink::failing();
+    let buffer = WriteBuffer::spawn(sink.clone(), spilling(2));
+    buffer.write(&rows(0..4)).await;
+    drop(buffer);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Memory first, then the states that had spilled
+    let lines: Vec<Row> = fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines, rows(0..4));
+
+    sink.recover();
+    let buffer = WriteBuffer::spawn(sink.clone(), spilling(10));
+    wait_drained(&buffer).await;
+
+    assert_eq!(sink.written(), [0, 1, 2, 3]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn drop_policy_parses_its_env_values() {
+    assert_eq!(DropPolicy::parse("oldest"), Some(DropPolicy::Oldest));
+    assert_eq!(DropPolicy::parse("newest"), Some(DropPolicy::Newest));
+    assert_eq!(DropPolicy::parse("random"), None);
+}
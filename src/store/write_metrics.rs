@@ -0,0 +1,92 @@
+//! Latency and outcome of store writes, per table.
+//!
+//! Wrapping a write in [`timed_write`] exports its duration and outcome through
+//! [`LatencyMetrics`] as `db_write_request_duration_seconds` and `db_write_requests_total`,
+//! with the table in the `method` label, and logs it when it is slower than
+//! `DB_SLOW_WRITE_MS`. `report_every` on [`write_metrics`] logs the p95 latency and error
+//! count of every table.
+
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::telemetry::{LatencyMetrics, MethodSummary};
+
+/// Writes slower than this are logged when `DB_SLOW_WRITE_MS` is unset
+const DEFAULT_SLOW_WRITE_MS: u64 = 500;
+
+/// Duration and outcome of the writes of every table, and the duration above which a write
+/// is logged as slow
+pub struct WriteMetrics {
+    latency: LatencyMetrics,
+    slow_write_threshold: Duration,
+}
+
+impl WriteMetrics {
+    pub fn new(slow_write_threshold: Duration) -> Self {
+        Self {
+            latency: LatencyMetrics::new("db_write"),
+            slow_write_threshold,
+        }
+    }
+
+    /// Read `DB_SLOW_WRITE_MS`, falling back to the default threshold
+    pub fn from_env() -> Self {
+        let millis = std::env::var("DB_SLOW_WRITE_MS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|millis| *millis > 0)
+            .unwrap_or(DEFAULT_SLOW_WRITE_MS);
+        Self::new(Duration::from_millis(millis))
+    }
+
+    /// Await `write` and record its duration and outcome under `table`
+    pub async fn time<T, E, Fut>(&self, table: &str, write: Fut) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let result = write.await;
+        self.record(table, started.elapsed(), result.is_ok());
+        result
+    }
+
+    /// Record one write to `table`, warning when it took longer than the threshold
+    pub fn record(&self, table: &str, duration: Duration, success: bool) {
+        if duration > self.slow_write_threshold {
+            warn!("Slow write to {}: took {:?}", table, duration);
+        }
+        self.latency.record(table, duration, success);
+    }
+
+    /// Summarize the writes recorded since the previous call, per table
+    pub fn take_summary(&self) -> Vec<MethodSummary> {
+        self.latency.take_summary()
+    }
+
+    /// Log the latency and errors of every table each `interval` until `shutdown` is cancelled
+    pub async fn report_every(&self, interval: Duration, shutdown: &CancellationToken) {
+        self.latency.report_every(interval, shutdown).await
+    }
+}
+
+static WRITE_METRICS: OnceLock<WriteMetrics> = OnceLock::new();
+
+/// Metrics of every store write of the process, configured from the environment on first use
+pub fn write_metrics() -> &'static WriteMetrics {
+    WRITE_METRICS.get_or_init(WriteMetrics::from_env)
+}
+
+/// Await `write` to `table`, recording it in [`write_metrics`]
+pub async fn timed_write<T, E, Fut>(table: &str, write: Fut) -> Result<T, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    write_metrics().time(table, write).await
+}
+
+#[cfg(test)]
+#[path = "write_metrics_tests.rs"]
+mod write_metrics_tests;
@@ -0,0 +1,65 @@
+use super::*;
+use crate::store::test_db::CapturedLogs;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn summary_aggregates_the_writes_of_every_table() {
+    let metrics = WriteMetrics::new(ms(10_000));
+    for millis in 1..=20 {
+        metrics.record("cex_markets", ms(millis), millis % 10 != 0);
+    }
+    metrics.record("dex_markets", ms(7), true);
+
+    assert_eq!(
+        metrics.take_summary(),
+        vec![
+            MethodSummary {
+                method: "cex_markets".to_string(),
+                calls: 20,
+                errors: 2,
+                p50: ms(10),
+                p95: ms(19),
+            },
+            MethodSummary {
+                method: "dex_markets".to_string(),
+                calls: 1,
+                errors: 0,
+                p50: ms(7),
+                p95: ms(7),
+            },
+        ]
+    );
+    // The next window starts empty
+    assert!(metrics.take_summary().is_empty());
+}
+
+#[tokio::test]
+async fn time_records_a_failed_write_as_an_error() {
+    let metrics = WriteMetrics::new(ms(10_000));
+
+    let ok: Result<u64, String> = metrics.time("spreads", async { Ok(3) }).await;
+    let failed: Result<u64, String> = metrics
+        .time("spreads", async { Err("connection reset".to_string()) })
+        .await;
+
+    assert_eq!(ok, Ok(3));
+    assert_eq!(failed, Err("connection reset".to_string()));
+    let summary = metrics.take_summary();
+    assert_eq!((summary[0].calls, summary[0].errors), (2, 1));
+}
+
+#[test]
+fn only_writes_slower_than_the_threshold_are_logged() {
+    let metrics = WriteMetrics::new(ms(500));
+    let logs = CapturedLogs::start();
+
+    metrics.record("cex_markets", ms(500), true);
+    metrics.record("dex_markets", ms(750), false);
+
+    let contents = logs.contents();
+    assert!(!contents.contains("cex_markets"));
+    assert!(contents.contains("Slow write to dex_markets: took 750ms"));
+}
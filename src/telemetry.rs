@@ -0,0 +1,198 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Default delay between two latency summaries
+pub const DEFAULT_SUMMARY_INTERVAL_SECS: u64 = 60;
+
+/// Latency and error samples of one method within a summary window
+#[derive(Debug, Default)]
+struct MethodSamples {
+    durations: Vec<Duration>,
+    errors: u64,
+}
+
+/// Aggregated latency of one method over a summary window
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSummary {
+    pub method: String,
+    pub calls: usize,
+    pub errors: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+/// Records the duration and outcome of calls to an external API, per method.
+/// Every sample is exported through the `metrics` facade as `{scope}_request_duration_seconds`
+/// and `{scope}_requests_total`, and kept until the next summary.
+pub struct LatencyMetrics {
+    /// Name of the API, e.g. `rpc` or `bybit_rest`
+    scope: &'static str,
+    samples: Mutex<HashMap<String, MethodSamples>>,
+}
+
+impl LatencyMetrics {
+    pub fn new(scope: &'static str) -> Self {
+        Self {
+            scope,
+            samples: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Await `call` and record its duration and outcome under `method`
+    pub async fn time<T, E, Fut>(&self, method: &str, call: Fut) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let result = call.await;
+        self.record(method, started.elapsed(), result.is_ok());
+        result
+    }
+
+    /// Record one call of `method`
+    pub fn record(&self, method: &str, duration: Duration, success: bool) {
+        let status = if success { "ok" } else { "error" };
+        metrics::histogram!(
+            format!("{}_request_duration_seconds", self.scope),
+            "method" => method.to_string()
+        )
+        .record(duration.as_secs_f64());
+        metrics::counter!(
+            format!("{}_requests_total", self.scope),
+            "method" => method.to_string(),
+            "status" => status
+        )
+        .increment(1);
+
+        let mut samples = self.samples.lock().unwrap();
+        let method_samples = samples.entry(method.to_string()).or_default();
+        method_samples.durations.push(duration);
+        if !success {
+            method_samples.errors += 1;
+        }
+    }
+
+    /// Summarize the samples recorded since the previous call and start a new window
+    pub fn take_summary(&self) -> Vec<MethodSummary> {
+        let samples = std::mem::take(&mut *self.samples.lock().unwrap());
+        let mut summaries: Vec<MethodSummary> = samples
+            .into_iter()
+            .map(|(method, samples)| summarize(method, samples))
+            .collect();
+        summaries.sort_by(|a, b| a.method.cmp(&b.method));
+        summaries
+    }
+
+    /// Log a one-line summary every `interval` until `shutdown` is cancelled.
+    /// Windows without any call are not logged.
+    pub async fn report_every(&self, interval: Duration, shutdown: &CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+            let summaries = self.take_summary();
+            if !summaries.is_empty() {
+                info!(
+                    "[{}] latency over {:?}: {}",
+                    self.scope,
+                    interval,
+                    format_summaries(&summaries)
+                );
+            }
+        }
+    }
+}
+
+fn summarize(method: String, mut samples: MethodSamples) -> MethodSummary {
+    samples.durations.sort();
+    MethodSummary {
+        method,
+        calls: samples.durations.len(),
+        errors: samples.errors,
+        p50: percentile(&samples.durations, 50),
+        p95: percentile(&samples.durations, 95),
+    }
+}
+
+/// Nearest-rank percentile of sorted durations, zero when there are none
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    sorted[nearest_rank(sorted.len(), percent) - 1]
+}
+
+/// 1-based nearest rank of `percent` among `len` sorted values
+fn nearest_rank(len: usize, percent: usize) -> usize {
+    (len * percent).div_ceil(100).clamp(1, len)
+}
+
+/// Percentiles over the last `capacity` recorded values; older values are evicted first
+#[derive(Debug, Clone)]
+pub struct RollingPercentiles {
+    values: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl RollingPercentiles {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn record(&mut self, value: u64) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// Number of values in the window
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Nearest-rank percentiles of the window, e.g. `&[50, 95]`, or `None` while it is empty
+    pub fn percentiles(&self, percents: &[usize]) -> Option<Vec<u64>> {
+        if self.values.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<u64> = self.values.iter().copied().collect();
+        sorted.sort_unstable();
+        Some(
+            percents
+                .iter()
+                .map(|&percent| sorted[nearest_rank(sorted.len(), percent) - 1])
+                .collect(),
+        )
+    }
+}
+
+/// `method calls=.. errors=.. p50=.. p95=..` for every method, separated by ` | `
+fn format_summaries(summaries: &[MethodSummary]) -> String {
+    summaries
+        .iter()
+        .map(|summary| {
+            format!(
+                "{} calls={} errors={} p50={:?} p95={:?}",
+                summary.method, summary.calls, summary.errors, summary.p50, summary.p95
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+#[path = "telemetry_tests.rs"]
+mod telemetry_tests;
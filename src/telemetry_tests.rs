@@ -0,0 +1,110 @@
+use super::*;
+
+fn millis(values: &[u64]) -> Vec<Duration> {
+    values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+}
+
+#[test]
+fn percentile_uses_nearest_rank() {
+    let sorted = millis(&(1..=100).collect::<Vec<_>>());
+
+    assert_eq!(percentile(&sorted, 50), Duration::from_millis(50));
+    assert_eq!(percentile(&sorted, 95), Duration::from_millis(95));
+    assert_eq!(percentile(&millis(&[7]), 95), Duration::from_millis(7));
+    assert_eq!(percentile(&[], 50), Duration::ZERO);
+}
+
+#[test]
+fn take_summary_aggregates_per_method_and_resets_the_window() {
+    let metrics = LatencyMetrics::new("rpc");
+    for ms in [40, 10, 30, 20, 500] {
+        metrics.record("getMultipleAccounts", Duration::from_millis(ms), true);
+    }
+    metrics.record("getAccountInfo", Duration::from_millis(80), false);
+    metrics.record("getAccountInfo", Duration::from_millis(60), true);
+
+    let summaries = metrics.take_summary();
+
+    assert_eq!(
+        summaries,
+        vec![
+            MethodSummary {
+                method: "getAccountInfo".to_string(),
+                calls: 2,
+                errors: 1,
+                p50: Duration::from_millis(60),
+                p95: Duration::from_millis(80),
+            },
+            MethodSummary {
+                method: "getMultipleAccounts".to_string(),
+                calls: 5,
+                errors: 0,
+                p50: Duration::from_millis(30),
+                p95: Duration::from_millis(500),
+            },
+        ]
+    );
+    assert!(metrics.take_summary().is_empty());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn time_records_the_outcome_of_the_call() {
+    let metrics = LatencyMetrics::new("rpc");
+
+    let ok: Result<u32, String> = metrics.time("getSlot", async { Ok(1) }).await;
+    let err: Result<u32, String> = metrics
+        .time("getSlot", async { Err("timeout".to_string()) })
+        .await;
+
+    assert_eq!(ok, Ok(1));
+    assert!(err.is_err());
+    let summary = &metrics.take_summary()[0];
+    assert_eq!((summary.calls, summary.errors), (2, 1));
+}
+
+#[test]
+fn format_summaries_joins_methods_on_one_line() {
+    let line = format_summaries(&[
+        MethodSummary {
+            method: "getAccountInfo".to_string(),
+            calls: 2,
+            errors: 1,
+            p50: Duration::from_millis(60),
+            p95: Duration::from_millis(80),
+        },
+        MethodSummary {
+            method: "getMultipleAccounts".to_string(),
+            calls: 5,
+            errors: 0,
+            p50: Duration::from_millis(30),
+            p95: Duration::from_millis(500),
+        },
+    ]);
+
+    assert_eq!(
+        line,
+        "getAccountInfo calls=2 errors=1 p50=60ms p95=80ms | getMultipleAccounts calls=5 errors=0 p50=30ms p95=500ms"
+    );
+}
+
+#[test]
+fn rolling_percentiles_cover_the_last_values_only() {
+    let mut window = RollingPercentiles::new(4);
+    assert!(window.is_empty());
+    assert_eq!(window.percentiles(&[50, 95]), None);
+
+    for value in [900, 800, 10, 40, 20, 30] {
+        window.record(value);
+    }
+
+    assert_eq!(window.len(), 4);
+    assert_eq!(window.percentiles(&[50, 95]), Some(vec![20, 40]));
+}
+
+#[test]
+fn rolling_percentiles_of_a_single_value() {
+    let mut window = RollingPercentiles::new(100);
+    window.record(7);
+
+    assert_eq!(window.percentiles(&[1, 50, 100]), Some(vec![7, 7, 7]));
+}
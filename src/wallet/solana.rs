@@ -0,0 +1,376 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::execution::meteora::TOKEN_PROGRAM_ID;
+use crate::models::balance::OnchainBalance;
+use crate::screeners::meteora::MeteoraConfig;
+use crate::solana::rpc::{FailoverRpcClient, redact_url};
+use crate::store::balances::insert_onchain_balances;
+use crate::store::db::DatabasePool;
+
+/// Key native SOL balances are cached and stored under, apart from wrapped SOL token accounts
+pub const NATIVE_SOL_MINT: Pubkey = solana_sdk::system_program::ID;
+/// Decimals of native SOL (lamports per SOL)
+const SOL_DECIMALS: u32 = 9;
+/// Symbols of the mints we usually hold, overridable with `WALLET_MINT_SYMBOLS`
+const DEFAULT_MINT_SYMBOLS: &str = "SOL:So11111111111111111111111111111111111111112,USDC:EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v,USDT:Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB,TRUMP:6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN";
+const DEFAULT_REFRESH_INTERVAL_SECS: u64 = 30;
+
+/// Wallets whose balances are tracked, from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletConfig {
+    /// Wallets of `WALLET_PUBKEYS`, in configuration order
+    pub wallets: Vec<Pubkey>,
+    /// Symbol of every known mint, persisted along its balances
+    pub mint_symbols: HashMap<Pubkey, String>,
+    /// Delay between two balance snapshots
+    pub refresh_interval: Duration,
+}
+
+impl WalletConfig {
+    /// Read `WALLET_PUBKEYS` (comma separated), `WALLET_MINT_SYMBOLS` (`SYMBOL:MINT` entries)
+    /// and `WALLET_REFRESH_INTERVAL_SECS`. Returns `None` when no wallet is configured.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let wallets = parse_wallets(&std::env::var("WALLET_PUBKEYS").unwrap_or_default())?;
+        if wallets.is_empty() {
+            return Ok(None);
+        }
+        let mint_symbols = parse_mint_symbols(
+            &std::env::var("WALLET_MINT_SYMBOLS")
+                .unwrap_or_else(|_| DEFAULT_MINT_SYMBOLS.to_string()),
+        )?;
+        let refresh_secs = std::env::var("WALLET_REFRESH_INTERVAL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL_SECS);
+        Ok(Some(Self {
+            wallets,
+            mint_symbols,
+            refresh_interval: Duration::from_secs(refresh_secs),
+        }))
+    }
+}
+
+/// Parse comma separated wallet pubkeys, rejecting duplicates
+pub fn parse_wallets(value: &str) -> Result<Vec<Pubkey>, String> {
+    let mut wallets = Vec::new();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let wallet = Pubkey::from_str(entry)
+            .map_err(|e| format!("Invalid wallet pubkey {}: {}", entry, e))?;
+        if wallets.contains(&wallet) {
+            return Err(format!("Wallet {} is configured twice", wallet));
+        }
+        wallets.push(wallet);
+    }
+    Ok(wallets)
+}
+
+/// Parse `SYMBOL:MINT` entries into the symbol of every mint
+pub fn parse_mint_symbols(value: &str) -> Result<HashMap<Pubkey, String>, String> {
+    let mut symbols = HashMap::new();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let (symbol, mint) = entry
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid mint symbol {}, expected SYMBOL:MINT", entry))?;
+        let mint = Pubkey::from_str(mint.trim())
+            .map_err(|e| format!("Invalid mint {} of {}: {}", mint, symbol, e))?;
+        let symbol = symbol.trim().to_uppercase();
+        if symbol.is_empty() {
+            return Err(format!("Missing symbol for mint {}", mint));
+        }
+        if symbols.insert(mint, symbol).is_some() {
+            return Err(format!("Mint {} is configured twice", mint));
+        }
+    }
+    Ok(symbols)
+}
+
+/// `getTokenAccountsByOwner` result with `jsonParsed` account data
+#[derive(Debug, Deserialize)]
+struct RpcTokenAccounts {
+    context: RpcContext,
+    value: Vec<RpcKeyedTokenAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcContext {
+    slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcKeyedTokenAccount {
+    pubkey: String,
+    account: RpcTokenAccount,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcTokenAccount {
+    data: RpcParsedData,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcParsedData {
+    parsed: RpcParsedAccount,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcParsedAccount {
+    info: RpcTokenInfo,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcTokenInfo {
+    mint: String,
+    token_amount: RpcTokenAmount,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcTokenAmount {
+    /// Raw amount, as a string since it may exceed what JSON numbers hold exactly
+    amount: String,
+    decimals: u32,
+}
+
+/// Token account owned by a wallet, SPL Token or Token-2022
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccountBalance {
+    pub token_account: Pubkey,
+    pub mint: Pubkey,
+    /// Amount in the smallest unit of the mint
+    pub raw_amount: u64,
+    pub decimals: u32,
+}
+
+/// Parse a `getTokenAccountsByOwner` result into its slot and token accounts
+pub fn parse_token_accounts(
+    result: serde_json::Value,
+) -> Result<(u64, Vec<TokenAccountBalance>), String> {
+    let accounts: RpcTokenAccounts = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected getTokenAccountsByOwner result: {}", e))?;
+    let balances = accounts
+        .value
+        .into_iter()
+        .map(|keyed| {
+            let info = keyed.account.data.parsed.info;
+            Ok(TokenAccountBalance {
+                token_account: Pubkey::from_str(&keyed.pubkey)
+                    .map_err(|e| format!("Invalid token account {}: {}", keyed.pubkey, e))?,
+                mint: Pubkey::from_str(&info.mint)
+                    .map_err(|e| format!("Invalid mint {}: {}", info.mint, e))?,
+                raw_amount: info.token_amount.amount.parse().map_err(|e| {
+                    format!(
+                        "Invalid amount {} of token account {}: {}",
+                        info.token_amount.amount, keyed.pubkey, e
+                    )
+                })?,
+                decimals: info.token_amount.decimals,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((accounts.context.slot, balances))
+}
+
+/// `raw_amount` in whole tokens of a mint with `decimals` decimals, `None` beyond what a
+/// `Decimal` can scale
+pub fn normalize_amount(raw_amount: u128, decimals: u32) -> Option<Decimal> {
+    Decimal::try_from_i128_with_scale(raw_amount as i128, decimals).ok()
+}
+
+/// One balance per mint of `wallet`, summing the token accounts holding the same mint.
+/// Mints whose amount cannot be normalized are skipped with a warning.
+pub fn wallet_balances(
+    wallet: &Pubkey,
+    slot: u64,
+    accounts: &[TokenAccountBalance],
+    mint_symbols: &HashMap<Pubkey, String>,
+    fetch_time: DateTime<Utc>,
+) -> Vec<OnchainBalance> {
+    let mut per_mint: BTreeMap<Pubkey, (u128, u32)> = BTreeMap::new();
+    for account in accounts {
+        // Every account of a mint reports the decimals of that mint
+        let (amount, _) = per_mint
+            .entry(account.mint)
+            .or_insert((0, account.decimals));
+        *amount += account.raw_amount as u128;
+    }
+    per_mint
+        .into_iter()
+        .filter_map(|(mint, (raw_amount, decimals))| {
+            let Some(amount) = normalize_amount(raw_amount, decimals) else {
+                warn!(
+                    "Cannot normalize {} raw units of mint {} at {} decimals held by {}",
+                    raw_amount, mint, decimals, wallet
+                );
+                return None;
+            };
+            Some(OnchainBalance {
+                wallet: wallet.to_string(),
+                mint: mint.to_string(),
+                symbol: mint_symbols.get(&mint).cloned(),
+                amount,
+                slot,
+                fetch_time,
+            })
+        })
+        .collect()
+}
+
+/// Periodically snapshots the SOL and token balances of our wallets, keeping the latest in
+/// memory for sizing and persisting every snapshot to `onchain_balances`
+pub struct SolanaBalanceTracker {
+    pub db_pool: DatabasePool,
+    pub rpc_client: FailoverRpcClient,
+    pub config: WalletConfig,
+    /// Cancelled by `stop()` to end the refresh loop
+    pub shutdown: CancellationToken,
+    /// Latest balance of every (wallet, mint) pair
+    balances: RwLock<HashMap<(Pubkey, Pubkey), OnchainBalance>>,
+}
+
+impl SolanaBalanceTracker {
+    /// Build the tracker on the shared Solana RPC settings
+    pub fn with_config(
+        db_pool: DatabasePool,
+        rpc_config: MeteoraConfig,
+        config: WalletConfig,
+    ) -> Self {
+        info!(
+            "Wallet balance RPC endpoints: {}",
+            rpc_config
+                .rpc_endpoints
+                .iter()
+                .map(|url| redact_url(url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Self {
+            db_pool,
+            rpc_client: FailoverRpcClient::from_urls(
+                rpc_config.rpc_endpoints,
+                rpc_config.commitment,
+            ),
+            config,
+            shutdown: CancellationToken::new(),
+            balances: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Snapshot every wallet each `refresh_interval` until the tracker is stopped
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "🚀 Tracking balances of {} Solana wallets every {:?}...",
+            self.config.wallets.len(),
+            self.config.refresh_interval
+        );
+        loop {
+            for wallet in &self.config.wallets {
+                if let Err(e) = self.refresh_wallet(wallet).await {
+                    warn!(
+                        "Failed to refresh balances of wallet {}, keeping the last known: {}",
+                        wallet, e
+                    );
+                }
+            }
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                _ = tokio::time::sleep(self.config.refresh_interval) => {}
+            }
+        }
+        info!("Solana balance tracker stopped");
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+
+    /// Latest known balance of `mint` in `wallet`; native SOL is under [`NATIVE_SOL_MINT`].
+    /// `None` until the wallet was snapshot or when it holds no account of the mint.
+    pub fn get_balance(&self, wallet: &Pubkey, mint: &Pubkey) -> Option<OnchainBalance> {
+        self.balances
+            .read()
+            .unwrap()
+            .get(&(*wallet, *mint))
+            .cloned()
+    }
+
+    /// Fetch the SOL balance and the SPL Token and Token-2022 accounts of `wallet`, replace its
+    /// cached balances and persist them
+    async fn refresh_wallet(&self, wallet: &Pubkey) -> Result<(), Box<dyn std::error::Error>> {
+        let fetch_time = Utc::now();
+        let (sol, legacy, token_2022) = tokio::try_join!(
+            self.rpc_client.get_balance(wallet),
+            self.rpc_client
+                .get_token_accounts_by_owner(wallet, &TOKEN_PROGRAM_ID),
+            self.rpc_client
+                .get_token_accounts_by_owner(wallet, &spl_token_2022::ID),
+        )?;
+
+        let mut balances = Vec::new();
+        for result in [legacy, token_2022] {
+            let (slot, accounts) = parse_token_accounts(result)?;
+            balances.extend(wallet_balances(
+                wallet,
+                slot,
+                &accounts,
+                &self.config.mint_symbols,
+                fetch_time,
+            ));
+        }
+        balances.push(OnchainBalance {
+            wallet: wallet.to_string(),
+            mint: NATIVE_SOL_MINT.to_string(),
+            symbol: Some("SOL".to_string()),
+            amount: normalize_amount(sol.value as u128, SOL_DECIMALS).unwrap_or_default(),
+            slot: sol.context.slot,
+            fetch_time,
+        });
+
+        {
+            let mut cached = self.balances.write().unwrap();
+            // Token accounts closed since the last snapshot no longer hold anything
+            cached.retain(|(cached_wallet, _), _| cached_wallet != wallet);
+            for balance in &balances {
+                let mint = Pubkey::from_str(&balance.mint)?;
+                cached.insert((*wallet, mint), balance.clone());
+            }
+        }
+        info!(
+            "[wallet] {} holds {} mints, {} SOL",
+            wallet,
+            balances.len() - 1,
+            balances.last().map(|sol| sol.amount).unwrap_or_default()
+        );
+
+        let db_pool = self.db_pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = insert_onchain_balances(&db_pool, &balances).await {
+                error!("Failed to insert on-chain balances: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "solana_tests.rs"]
+mod solana_tests;
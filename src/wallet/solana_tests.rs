@@ -0,0 +1,278 @@
+use super::*;
+
+const WALLET: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+const TRUMP: &str = "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN";
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const PYUSD: &str = "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo";
+
+/// Two USDC accounts and one TRUMP account, as `getTokenAccountsByOwner` returns them for the
+/// SPL Token program with `jsonParsed` encoding
+const LEGACY_ACCOUNTS: &str = r#"{
+    "context": {"apiVersion": "2.1.21", "slot": 321000123},
+    "value": [
+        {
+            "pubkey": "3emsAVdmGKERbHjmGfQ6oZ1e35dkf5iYcS6U4CPKFVaa",
+            "account": {
+                "data": {
+                    "parsed": {
+                        "info": {
+                            "isNative": false,
+                            "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                            "owner": "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU",
+                            "state": "initialized",
+                            "tokenAmount": {
+                                "amount": "1250500000",
+                                "decimals": 6,
+                                "uiAmount": 1250.5,
+                                "uiAmountString": "1250.5"
+                            }
+                        },
+                        "type": "account"
+                    },
+                    "program": "spl-token",
+                    "space": 165
+                },
+                "executable": false,
+                "lamports": 2039280,
+                "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "rentEpoch": 18446744073709551615,
+                "space": 165
+            }
+        },
+        {
+            "pubkey": "9wFFyRfZBsuAha4YcuxcXLKwMxJR43S7fPfQLusDBzvT",
+            "account": {
+                "data": {
+                    "parsed": {
+                        "info": {
+                            "isNative": false,
+                            "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                            "owner": "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU",
+                            "state": "initialized",
+                            "tokenAmount": {
+                                "amount": "500000",
+                                "decimals": 6,
+                                "uiAmount": 0.5,
+                                "uiAmountString": "0.5"
+                            }
+                        },
+                        "type": "account"
+                    },
+                    "program": "spl-token",
+                    "space": 165
+                },
+                "executable": false,
+                "lamports": 2039280,
+                "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "rentEpoch": 18446744073709551615,
+                "space": 165
+            }
+        },
+        {
+            "pubkey": "HZ1JovNiVvGrGNiiYvEozEVgZ58xaU3RKwX8eACQBCt3",
+            "account": {
+                "data": {
+                    "parsed": {
+                        "info": {
+                            "isNative": false,
+                            "mint": "6p6xgHyF7AeE6TZkSmFsko444wqoP15icUSqi2jfGiPN",
+                            "owner": "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU",
+                            "state": "initialized",
+                            "tokenAmount": {
+                                "amount": "42000000",
+                                "decimals": 6,
+                                "uiAmount": 42.0,
+                                "uiAmountString": "42"
+                            }
+                        },
+                        "type": "account"
+                    },
+                    "program": "spl-token",
+                    "space": 165
+                },
+                "executable": false,
+                "lamports": 2039280,
+                "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "rentEpoch": 18446744073709551615,
+                "space": 165
+            }
+        }
+    ]
+}"#;
+
+/// One PYUSD account of the Token-2022 program, whose parsed info carries extensions
+const TOKEN_2022_ACCOUNTS: &str = r#"{
+    "context": {"apiVersion": "2.1.21", "slot": 321000124},
+    "value": [
+        {
+            "pubkey": "5KfTn1oBBnUZb7MWwTSpPKe4Wbu5mjgoQ2AGyHxpXRrC",
+            "account": {
+                "data": {
+                    "parsed": {
+                        "info": {
+                            "extensions": [{"extension": "immutableOwner"}],
+                            "isNative": false,
+                            "mint": "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo",
+                            "owner": "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU",
+                            "state": "initialized",
+                            "tokenAmount": {
+                                "amount": "75000001",
+                                "decimals": 6,
+                                "uiAmount": 75.000001,
+                                "uiAmountString": "75.000001"
+                            }
+                        },
+                        "type": "account"
+                    },
+                    "program": "spl-token-2022",
+                    "space": 170
+                },
+                "executable": false,
+                "lamports": 2074080,
+                "owner": "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+                "rentEpoch": 18446744073709551615,
+                "space": 170
+            }
+        }
+    ]
+}"#;
+
+fn pubkey(value: &str) -> Pubkey {
+    Pubkey::from_str(value).unwrap()
+}
+
+fn json(value: &str) -> serde_json::Value {
+    serde_json::from_str(value).unwrap()
+}
+
+fn fetch_time() -> DateTime<Utc> {
+    DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+}
+
+#[test]
+fn parse_token_accounts_reads_slot_mint_and_raw_amounts() {
+    let (slot, accounts) = parse_token_accounts(json(LEGACY_ACCOUNTS)).unwrap();
+
+    assert_eq!(slot, 321_000_123);
+    assert_eq!(accounts.len(), 3);
+    assert_eq!(
+        accounts[0],
+        TokenAccountBalance {
+            token_account: pubkey("3emsAVdmGKERbHjmGfQ6oZ1e35dkf5iYcS6U4CPKFVaa"),
+            mint: pubkey(USDC),
+            raw_amount: 1_250_500_000,
+            decimals: 6,
+        }
+    );
+    assert_eq!(accounts[2].mint, pubkey(TRUMP));
+}
+
+#[test]
+fn parse_token_accounts_reads_token_2022_accounts_with_extensions() {
+    let (slot, accounts) = parse_token_accounts(json(TOKEN_2022_ACCOUNTS)).unwrap();
+
+    assert_eq!(slot, 321_000_124);
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].mint, pubkey(PYUSD));
+    assert_eq!(accounts[0].raw_amount, 75_000_001);
+}
+
+#[test]
+fn parse_token_accounts_accepts_wallets_without_token_accounts() {
+    let (slot, accounts) =
+        parse_token_accounts(json(r#"{"context":{"slot":7},"value":[]}"#)).unwrap();
+
+    assert_eq!(slot, 7);
+    assert!(accounts.is_empty());
+}
+
+#[test]
+fn parse_token_accounts_rejects_malformed_results() {
+    let bad_amount = LEGACY_ACCOUNTS.replace(r#""amount": "500000""#, r#""amount": "-5""#);
+    let bad_mint =
+        LEGACY_ACCOUNTS.replacen(&format!(r#""mint": "{}""#, TRUMP), r#""mint": "x""#, 1);
+    // base64 encoded accounts are not parsed
+    let not_parsed = r#"{"context":{"slot":7},"value":[{"pubkey":"3emsAVdmGKERbHjmGfQ6oZ1e35dkf5iYcS6U4CPKFVaa","account":{"data":["AAAA","base64"]}}]}"#;
+
+    assert!(parse_token_accounts(json(&bad_amount)).is_err());
+    assert!(parse_token_accounts(json(&bad_mint)).is_err());
+    assert!(parse_token_accounts(json(not_parsed)).is_err());
+    assert!(parse_token_accounts(serde_json::Value::Null).is_err());
+}
+
+#[test]
+fn normalize_amount_scales_by_mint_decimals() {
+    assert_eq!(
+        normalize_amount(1_250_500_000, 6),
+        Some("1250.5".parse().unwrap())
+    );
+    assert_eq!(normalize_amount(1, 9), Some("0.000000001".parse().unwrap()));
+    assert_eq!(normalize_amount(42, 0), Some(Decimal::from(42)));
+    assert_eq!(
+        normalize_amount(u64::MAX as u128 * 3, 6),
+        Some("55340232221128.654845".parse().unwrap())
+    );
+    assert_eq!(normalize_amount(1, 29), None);
+}
+
+#[test]
+fn wallet_balances_sum_the_accounts_of_each_mint() {
+    let wallet = pubkey(WALLET);
+    let (slot, accounts) = parse_token_accounts(json(LEGACY_ACCOUNTS)).unwrap();
+    let symbols = parse_mint_symbols(DEFAULT_MINT_SYMBOLS).unwrap();
+
+    let balances = wallet_balances(&wallet, slot, &accounts, &symbols, fetch_time());
+
+    assert_eq!(balances.len(), 2);
+    let usdc = balances.iter().find(|b| b.mint == USDC).unwrap();
+    assert_eq!(
+        usdc,
+        &OnchainBalance {
+            wallet: WALLET.to_string(),
+            mint: USDC.to_string(),
+            symbol: Some("USDC".to_string()),
+            amount: "1251".parse().unwrap(),
+            slot: 321_000_123,
+            fetch_time: fetch_time(),
+        }
+    );
+    let trump = balances.iter().find(|b| b.mint == TRUMP).unwrap();
+    assert_eq!(trump.amount, Decimal::from(42));
+    assert_eq!(trump.symbol.as_deref(), Some("TRUMP"));
+}
+
+#[test]
+fn wallet_balances_leave_unknown_mints_without_symbol() {
+    let wallet = pubkey(WALLET);
+    let (slot, accounts) = parse_token_accounts(json(TOKEN_2022_ACCOUNTS)).unwrap();
+    let symbols = parse_mint_symbols(DEFAULT_MINT_SYMBOLS).unwrap();
+
+    let balances = wallet_balances(&wallet, slot, &accounts, &symbols, fetch_time());
+
+    assert_eq!(balances.len(), 1);
+    assert_eq!(balances[0].symbol, None);
+    assert_eq!(balances[0].amount, "75.000001".parse().unwrap());
+}
+
+#[test]
+fn parse_wallets_reads_comma_separated_pubkeys() {
+    assert_eq!(
+        parse_wallets(&format!(" {}, ", WALLET)).unwrap(),
+        vec![pubkey(WALLET)]
+    );
+    assert!(parse_wallets("").unwrap().is_empty());
+    assert!(parse_wallets("not-a-wallet").is_err());
+    assert!(parse_wallets(&format!("{},{}", WALLET, WALLET)).is_err());
+}
+
+#[test]
+fn parse_mint_symbols_reads_symbol_mint_entries() {
+    let symbols = parse_mint_symbols(&format!("usdc:{}, TRUMP:{}", USDC, TRUMP)).unwrap();
+
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[&pubkey(USDC)], "USDC");
+    assert!(parse_mint_symbols(USDC).is_err());
+    assert!(parse_mint_symbols(&format!(":{}", USDC)).is_err());
+    assert!(parse_mint_symbols("USDC:not-a-mint").is_err());
+    assert!(parse_mint_symbols(&format!("USDC:{},USD:{}", USDC, USDC)).is_err());
+}